@@ -0,0 +1,59 @@
+//! Throughput of the chunked copier by buffer size
+//!
+//! Copies a file of `PLM_BENCH_SIZE_MIB` MiB (default 256) into the
+//! directory given by `PLM_BENCH_DIR`, such as a mounted USB device, or
+//! into a temporary directory, with each buffer size in turn:
+//!
+//! ```text
+//! PLM_BENCH_DIR=/mnt/usb cargo bench --bench copy_buffer
+//! ```
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use playlist_manager::file_utils::copy_file_buffered;
+use tempfile::TempDir;
+
+const BUFFER_SIZES: [usize; 6] = [
+    64 * 1024,
+    256 * 1024,
+    1024 * 1024,
+    2 * 1024 * 1024,
+    4 * 1024 * 1024,
+    8 * 1024 * 1024,
+];
+
+fn main() -> anyhow::Result<()> {
+    let size_mib: usize = match env::var("PLM_BENCH_SIZE_MIB") {
+        Ok(size) => size.parse()?,
+        Err(_) => 256,
+    };
+    let src_dir = TempDir::new()?;
+    let dest_dir = match env::var_os("PLM_BENCH_DIR") {
+        Some(dir) => TempDir::new_in(PathBuf::from(dir))?,
+        None => TempDir::new()?,
+    };
+
+    let src_path = src_dir.path().join("source.bin");
+    let content: Vec<u8> = (0..size_mib * 1024 * 1024).map(|i| i as u8).collect();
+    fs::write(&src_path, &content)?;
+    let dest_path = dest_dir.path().join("dest.bin");
+
+    println!("{:>10} {:>10}", "buffer", "MiB/s");
+    for buffer_size in BUFFER_SIZES {
+        let started = Instant::now();
+        copy_file_buffered(&src_path, &dest_path, buffer_size)?;
+        fs::File::open(&dest_path)?.sync_all()?;
+        let seconds = started.elapsed().as_secs_f64();
+        println!(
+            "{:>9}K {:>10.1}",
+            buffer_size / 1024,
+            size_mib as f64 / seconds
+        );
+        fs::remove_file(&dest_path)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,147 @@
+//! Generation of playlists listing the tracks of an album directory
+//!
+//! Some players only offer playlist navigation, so a playlist per album
+//! directory is the only way to play an album in order on them.  Tracks
+//! are ordered by the track number at the start of their file names,
+//! such as `01 Title.flac` or `1-01 Title.flac` for multi-disc albums.
+
+use std::cmp::Ordering;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::media_extensions::is_media_file;
+
+/// Extension of the generated playlists
+const PLAYLIST_EXTENSION: &str = "m3u8";
+
+/// Track number at the start of a file name
+///
+/// Numbers separated by `-` or `.` give the disc number first, so
+/// `1-02 Title.flac` yields `[1, 2]`.  Returns an empty list if the
+/// file name does not start with a number.
+pub fn track_number(file_name: &str) -> Vec<u32> {
+    let mut numbers = Vec::new();
+    let mut rest = file_name;
+
+    loop {
+        let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+        let Ok(number) = rest[..digits].parse() else {
+            break;
+        };
+        numbers.push(number);
+        rest = &rest[digits..];
+
+        match rest.strip_prefix(['-', '.']) {
+            Some(next) if next.starts_with(|c: char| c.is_ascii_digit()) => rest = next,
+            _ => break,
+        }
+    }
+
+    numbers
+}
+
+/// Order of tracks in an album: numbered tracks by their numbers, then
+/// the others by name
+pub fn compare_tracks(a: &str, b: &str) -> Ordering {
+    let (number_a, number_b) = (track_number(a), track_number(b));
+    number_a
+        .is_empty()
+        .cmp(&number_b.is_empty())
+        .then_with(|| number_a.cmp(&number_b))
+        .then_with(|| a.cmp(b))
+}
+
+/// File names of the media files in an album directory, in track order
+pub fn album_tracks(dir: &Path) -> io::Result<Vec<String>> {
+    let mut tracks = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_file() && is_media_file(&path) {
+            tracks.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+
+    tracks.sort_by(|a, b| compare_tracks(a, b));
+    Ok(tracks)
+}
+
+/// Path of the playlist of an album directory, named after the directory
+pub fn album_playlist_path(dir: &Path) -> Option<PathBuf> {
+    let mut name = dir.file_name()?.to_os_string();
+    name.push(".");
+    name.push(PLAYLIST_EXTENSION);
+    Some(dir.join(name))
+}
+
+/// Write the playlist of the media files in an album directory
+///
+/// Returns the path of the written playlist, or `None` if the directory
+/// contains no media file.
+pub fn write_album_playlist(dir: &Path) -> io::Result<Option<PathBuf>> {
+    let tracks = album_tracks(dir)?;
+    if tracks.is_empty() {
+        return Ok(None);
+    }
+
+    let playlist = album_playlist_path(dir).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Not an album directory: {}", dir.display()),
+        )
+    })?;
+
+    let mut content = tracks.join("\n");
+    content.push('\n');
+    fs::write(&playlist, content)?;
+
+    Ok(Some(playlist))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_track_number() {
+        assert_eq!(track_number("01 Title.flac"), vec![1]);
+        assert_eq!(track_number("1-02 Title.flac"), vec![1, 2]);
+        assert_eq!(track_number("03. Title.flac"), vec![3]);
+        assert_eq!(track_number("12-Title.flac"), vec![12]);
+        assert_eq!(track_number("Title 01.flac"), Vec::<u32>::new());
+    }
+
+    #[test]
+    fn test_write_album_playlist() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album_dir = temp_dir.path().join("album1 vol.2");
+        fs::create_dir(&album_dir)?;
+        for file in [
+            "10 Ten.flac",
+            "2 Two.FLAC",
+            "Bonus.mp3",
+            "01 One.flac",
+            "01 One.lrc",
+            "cover.jpg",
+        ] {
+            fs::write(album_dir.join(file), "test content")?;
+        }
+
+        let playlist = write_album_playlist(&album_dir)?;
+
+        assert_eq!(playlist, Some(album_dir.join("album1 vol.2.m3u8")));
+        assert_eq!(
+            fs::read_to_string(album_dir.join("album1 vol.2.m3u8"))?,
+            "01 One.flac\n2 Two.FLAC\n10 Ten.flac\nBonus.mp3\n"
+        );
+
+        let empty_dir = temp_dir.path().join("empty");
+        fs::create_dir(&empty_dir)?;
+        assert_eq!(write_album_playlist(&empty_dir)?, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,660 @@
+//! Writing of tar and zip archives
+//!
+//! Files are stored without compression, as media files are compressed
+//! already, and are streamed into the archive so that it can be written
+//! to a pipe.  Tar archives use the ustar format, with GNU long name
+//! entries for paths that do not fit its header.  Zip archives switch to
+//! zip64 records for files and archives of 4GiB or more.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Size of the blocks of tar archives
+const TAR_BLOCK_SIZE: u64 = 512;
+
+/// Largest size of tar entries written as an octal number (8GiB - 1)
+const TAR_MAX_OCTAL_SIZE: u64 = 0o77_777_777_777;
+
+/// Value of the 32-bit sizes and offsets of zip archives standing for
+/// their zip64 values
+const ZIP64_U32: u32 = u32::MAX;
+
+/// Value of the 16-bit entry counts of zip archives standing for their
+/// zip64 values
+const ZIP64_U16: u16 = u16::MAX;
+
+/// General purpose flags of zip entries: sizes and CRC-32 follow the
+/// data, and names are UTF-8
+const ZIP_FLAGS: u16 = 0x0008 | 0x0800;
+
+/// Zip versions needed to extract entries without and with zip64
+/// records, and the host system (Unix) in the version made by
+const ZIP_VERSION: u16 = 20;
+const ZIP64_VERSION: u16 = 45;
+const ZIP_HOST_UNIX: u16 = 3 << 8;
+
+/// Permissions of archived files
+const FILE_MODE: u32 = 0o644;
+
+/// Size of the buffer archived files are copied through
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Format of an archive
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    /// Uncompressed tar archive
+    #[default]
+    Tar,
+    /// Zip archive with stored entries
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Format of an archive by the extension of its path: zip for `.zip`
+    /// and tar otherwise
+    pub fn of_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("zip") => Self::Zip,
+            _ => Self::Tar,
+        }
+    }
+}
+
+impl FromStr for ArchiveFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tar" => Ok(Self::Tar),
+            "zip" => Ok(Self::Zip),
+            _ => Err(format!(
+                "Unknown archive format: {} (expected tar or zip)",
+                s
+            )),
+        }
+    }
+}
+
+/// A file written into an archive
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchivedFile {
+    pub name: String, // Path in the archive
+    pub size: u64,
+    pub crc32: u32,
+}
+
+/// Entry of the central directory of a zip archive
+#[derive(Debug)]
+struct ZipEntry {
+    name: String,
+    size: u64,
+    crc32: u32,
+    offset: u64, // Offset of the local header
+    dos_time: (u16, u16),
+}
+
+/// Writer of an archive into `W`
+///
+/// Files are added with [`ArchiveWriter::add_file`] and
+/// [`ArchiveWriter::add_data`], and the archive is completed by
+/// [`ArchiveWriter::finish`].
+#[derive(Debug)]
+pub struct ArchiveWriter<W: Write> {
+    out: W,
+    format: ArchiveFormat,
+    offset: u64,                // Number of bytes written
+    zip_entries: Vec<ZipEntry>, // Central directory of a zip archive
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    /// Create a writer of an archive in `format` into `out`
+    pub fn new(out: W, format: ArchiveFormat) -> Self {
+        Self {
+            out,
+            format,
+            offset: 0,
+            zip_entries: Vec::new(),
+        }
+    }
+
+    /// Add the file at `path` to the archive as `name`, with its
+    /// modification time
+    pub fn add_file(&mut self, name: &str, path: &Path) -> io::Result<ArchivedFile> {
+        let file = File::open(path)?;
+        let metadata = file.metadata()?;
+        let modified = metadata.modified().unwrap_or(UNIX_EPOCH);
+        self.add_entry(name, metadata.len(), modified, file)
+    }
+
+    /// Add `data` to the archive as a file named `name`
+    pub fn add_data(
+        &mut self,
+        name: &str,
+        data: &[u8],
+        modified: SystemTime,
+    ) -> io::Result<ArchivedFile> {
+        self.add_entry(name, data.len() as u64, modified, data)
+    }
+
+    /// Complete the archive, returning the writer it was written into
+    pub fn finish(mut self) -> io::Result<W> {
+        match self.format {
+            ArchiveFormat::Tar => self.write(&[0; 2 * TAR_BLOCK_SIZE as usize])?,
+            ArchiveFormat::Zip => self.write_zip_central_directory()?,
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    /// Add `size` bytes read from `contents` as a file named `name`
+    fn add_entry(
+        &mut self,
+        name: &str,
+        size: u64,
+        modified: SystemTime,
+        contents: impl Read,
+    ) -> io::Result<ArchivedFile> {
+        let crc32 = match self.format {
+            ArchiveFormat::Tar => self.add_tar_entry(name, size, modified, contents)?,
+            ArchiveFormat::Zip => self.add_zip_entry(name, size, modified, contents)?,
+        };
+        Ok(ArchivedFile {
+            name: name.to_string(),
+            size,
+            crc32,
+        })
+    }
+
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        self.out.write_all(data)?;
+        self.offset += data.len() as u64;
+        Ok(())
+    }
+
+    /// Copy exactly `size` bytes of `contents` into the archive,
+    /// returning their CRC-32 checksum
+    fn copy_contents(&mut self, contents: impl Read, size: u64) -> io::Result<u32> {
+        let mut hasher = crc32fast::Hasher::new();
+        let mut buf = vec![0; COPY_BUFFER_SIZE];
+        let mut contents = contents.take(size);
+        let mut copied = 0;
+
+        loop {
+            let n = match contents.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            hasher.update(&buf[..n]);
+            self.write(&buf[..n])?;
+            copied += n as u64;
+        }
+
+        // The size is written ahead of the contents in tar archives
+        if copied != size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "File shrank while being archived",
+            ));
+        }
+        Ok(hasher.finalize())
+    }
+
+    fn add_tar_entry(
+        &mut self,
+        name: &str,
+        size: u64,
+        modified: SystemTime,
+        contents: impl Read,
+    ) -> io::Result<u32> {
+        let (prefix, short_name) = match split_tar_name(name) {
+            Some((prefix, short_name)) => (prefix.as_bytes(), short_name.as_bytes()),
+            None => {
+                // The full name goes into a GNU long name entry, read by
+                // GNU tar, bsdtar and most other readers
+                let mut long_name = name.as_bytes().to_vec();
+                long_name.push(0);
+                let size = long_name.len() as u64;
+                self.write(&tar_header(b"././@LongLink", b"", size, 0, b'L'))?;
+                self.write(&long_name)?;
+                self.pad_tar_block(size)?;
+                (&b""[..], &name.as_bytes()[..100])
+            }
+        };
+
+        let mtime = unix_time(modified);
+        self.write(&tar_header(short_name, prefix, size, mtime, b'0'))?;
+        let crc32 = self.copy_contents(contents, size)?;
+        self.pad_tar_block(size)?;
+        Ok(crc32)
+    }
+
+    /// Pad the contents of a tar entry of `size` bytes to a whole block
+    fn pad_tar_block(&mut self, size: u64) -> io::Result<()> {
+        let padding = (TAR_BLOCK_SIZE - size % TAR_BLOCK_SIZE) % TAR_BLOCK_SIZE;
+        self.write(&vec![0; padding as usize])
+    }
+
+    fn add_zip_entry(
+        &mut self,
+        name: &str,
+        size: u64,
+        modified: SystemTime,
+        contents: impl Read,
+    ) -> io::Result<u32> {
+        let name_len = u16::try_from(name.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Name too long: {}", name),
+            )
+        })?;
+        let zip64 = size >= u64::from(ZIP64_U32);
+        let offset = self.offset;
+        let dos_time = dos_date_time(modified);
+
+        // The CRC-32 and the sizes follow the contents in a data
+        // descriptor, so that the contents are read only once
+        let mut header = Vec::with_capacity(50 + name.len());
+        put_u32(&mut header, 0x0403_4b50);
+        put_u16(&mut header, if zip64 { ZIP64_VERSION } else { ZIP_VERSION });
+        put_u16(&mut header, ZIP_FLAGS);
+        put_u16(&mut header, 0); // Stored
+        put_u16(&mut header, dos_time.0);
+        put_u16(&mut header, dos_time.1);
+        put_u32(&mut header, 0);
+        put_u32(&mut header, if zip64 { ZIP64_U32 } else { 0 });
+        put_u32(&mut header, if zip64 { ZIP64_U32 } else { 0 });
+        put_u16(&mut header, name_len);
+        put_u16(&mut header, if zip64 { 20 } else { 0 });
+        header.extend_from_slice(name.as_bytes());
+        if zip64 {
+            put_u16(&mut header, 0x0001);
+            put_u16(&mut header, 16);
+            put_u64(&mut header, 0);
+            put_u64(&mut header, 0);
+        }
+        self.write(&header)?;
+
+        let crc32 = self.copy_contents(contents, size)?;
+
+        let mut descriptor = Vec::with_capacity(24);
+        put_u32(&mut descriptor, 0x0807_4b50);
+        put_u32(&mut descriptor, crc32);
+        for _ in 0..2 {
+            match zip64 {
+                true => put_u64(&mut descriptor, size),
+                false => put_u32(&mut descriptor, size as u32),
+            }
+        }
+        self.write(&descriptor)?;
+
+        self.zip_entries.push(ZipEntry {
+            name: name.to_string(),
+            size,
+            crc32,
+            offset,
+            dos_time,
+        });
+        Ok(crc32)
+    }
+
+    fn write_zip_central_directory(&mut self) -> io::Result<()> {
+        let cd_offset = self.offset;
+        let entries = std::mem::take(&mut self.zip_entries);
+
+        for entry in &entries {
+            let size = u32::try_from(entry.size).unwrap_or(ZIP64_U32);
+            let offset = u32::try_from(entry.offset).unwrap_or(ZIP64_U32);
+            let mut zip64_extra = Vec::new();
+            if size == ZIP64_U32 {
+                put_u64(&mut zip64_extra, entry.size);
+                put_u64(&mut zip64_extra, entry.size);
+            }
+            if offset == ZIP64_U32 {
+                put_u64(&mut zip64_extra, entry.offset);
+            }
+            let version = match zip64_extra.is_empty() {
+                true => ZIP_VERSION,
+                false => ZIP64_VERSION,
+            };
+
+            let mut header = Vec::with_capacity(74 + entry.name.len());
+            put_u32(&mut header, 0x0201_4b50);
+            put_u16(&mut header, ZIP_HOST_UNIX | version);
+            put_u16(&mut header, version);
+            put_u16(&mut header, ZIP_FLAGS);
+            put_u16(&mut header, 0); // Stored
+            put_u16(&mut header, entry.dos_time.0);
+            put_u16(&mut header, entry.dos_time.1);
+            put_u32(&mut header, entry.crc32);
+            put_u32(&mut header, size);
+            put_u32(&mut header, size);
+            put_u16(&mut header, entry.name.len() as u16);
+            let extra_len = match zip64_extra.is_empty() {
+                true => 0,
+                false => 4 + zip64_extra.len() as u16,
+            };
+            put_u16(&mut header, extra_len);
+            put_u16(&mut header, 0); // Comment
+            put_u16(&mut header, 0); // Disk
+            put_u16(&mut header, 0); // Internal attributes
+            put_u32(&mut header, (0o100_000 | FILE_MODE) << 16);
+            put_u32(&mut header, offset);
+            header.extend_from_slice(entry.name.as_bytes());
+            if !zip64_extra.is_empty() {
+                put_u16(&mut header, 0x0001);
+                put_u16(&mut header, zip64_extra.len() as u16);
+                header.extend_from_slice(&zip64_extra);
+            }
+            self.write(&header)?;
+        }
+
+        let cd_size = self.offset - cd_offset;
+        let n_entries = entries.len() as u64;
+        let n_entries16 = u16::try_from(n_entries)
+            .ok()
+            .filter(|&n| n != ZIP64_U16)
+            .unwrap_or(ZIP64_U16);
+        let cd_size32 = u32::try_from(cd_size).unwrap_or(ZIP64_U32);
+        let cd_offset32 = u32::try_from(cd_offset).unwrap_or(ZIP64_U32);
+
+        let mut end = Vec::with_capacity(98);
+        if n_entries16 == ZIP64_U16 || cd_size32 == ZIP64_U32 || cd_offset32 == ZIP64_U32 {
+            let zip64_end_offset = self.offset;
+            put_u32(&mut end, 0x0606_4b50);
+            put_u64(&mut end, 44);
+            put_u16(&mut end, ZIP_HOST_UNIX | ZIP64_VERSION);
+            put_u16(&mut end, ZIP64_VERSION);
+            put_u32(&mut end, 0);
+            put_u32(&mut end, 0);
+            put_u64(&mut end, n_entries);
+            put_u64(&mut end, n_entries);
+            put_u64(&mut end, cd_size);
+            put_u64(&mut end, cd_offset);
+
+            put_u32(&mut end, 0x0706_4b50);
+            put_u32(&mut end, 0);
+            put_u64(&mut end, zip64_end_offset);
+            put_u32(&mut end, 1);
+        }
+        put_u32(&mut end, 0x0605_4b50);
+        put_u16(&mut end, 0);
+        put_u16(&mut end, 0);
+        put_u16(&mut end, n_entries16);
+        put_u16(&mut end, n_entries16);
+        put_u32(&mut end, cd_size32);
+        put_u32(&mut end, cd_offset32);
+        put_u16(&mut end, 0); // Comment
+        self.write(&end)
+    }
+}
+
+/// Split a name into the prefix and the name fields of a ustar header,
+/// at a `/`
+///
+/// Returns `None` if the name does not fit the fields.
+fn split_tar_name(name: &str) -> Option<(&str, &str)> {
+    if name.len() <= 100 {
+        return Some(("", name));
+    }
+    name.match_indices('/')
+        .map(|(i, _)| (&name[..i], &name[i + 1..]))
+        .find(|(prefix, rest)| prefix.len() <= 155 && rest.len() <= 100 && !rest.is_empty())
+}
+
+/// Header block of a tar entry
+fn tar_header(name: &[u8], prefix: &[u8], size: u64, mtime: u64, typeflag: u8) -> [u8; 512] {
+    let mut header = [0; TAR_BLOCK_SIZE as usize];
+    header[..name.len()].copy_from_slice(name);
+    write_octal(&mut header[100..108], u64::from(FILE_MODE));
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    match size <= TAR_MAX_OCTAL_SIZE {
+        true => write_octal(&mut header[124..136], size),
+        // GNU base-256 encoding of larger sizes
+        false => {
+            header[124] = 0x80;
+            header[128..136].copy_from_slice(&size.to_be_bytes());
+        }
+    }
+    write_octal(&mut header[136..148], mtime.min(TAR_MAX_OCTAL_SIZE));
+    header[156] = typeflag;
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix);
+
+    // The checksum is computed with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u64 = header.iter().map(|&b| u64::from(b)).sum();
+    write_octal(&mut header[148..155], checksum);
+    header
+}
+
+/// Write `value` into a tar header field as a NUL-terminated octal
+/// number filling the field
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}", value, width = field.len() - 1);
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+    field[digits.len()] = 0;
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(buf: &mut Vec<u8>, value: u64) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Seconds since the Unix epoch, or 0 before it
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+/// MS-DOS time and date of a time in UTC, as in zip archives, clamped
+/// to the years 1980 to 2107 they can represent
+fn dos_date_time(time: SystemTime) -> (u16, u16) {
+    let secs = unix_time(time);
+    let (year, month, day) = civil_from_days(secs / 86_400);
+    let secs_of_day = secs % 86_400;
+    match year {
+        ..=1979 => (0, (1 << 5) | 1),
+        2108.. => ((23 << 11) | (59 << 5) | 29, (127 << 9) | (12 << 5) | 31),
+        _ => {
+            let hour = secs_of_day / 3600;
+            let minute = secs_of_day % 3600 / 60;
+            let second = secs_of_day % 60;
+            let time = (hour << 11) | (minute << 5) | (second / 2);
+            let date = ((year - 1980) << 9) | (month << 5) | day;
+            (time as u16, date as u16)
+        }
+    }
+}
+
+/// Year, month and day of the proleptic Gregorian calendar `days` days
+/// after 1970-01-01
+pub(crate) fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153; // From March
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Days after 1970-01-01 of a date of the proleptic Gregorian calendar,
+/// or `None` for an invalid date or one before 1970
+pub(crate) fn days_from_civil(year: u64, month: u64, day: u64) -> Option<u64> {
+    if !(1..=12).contains(&month) || day == 0 || year < 1970 {
+        return None;
+    }
+    let year = year - u64::from(month <= 2);
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let shifted_month = (month + 9) % 12; // From March
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146_097 + day_of_era).checked_sub(719_468)?;
+    // Days past the end of the month fall into the next one
+    (civil_from_days(days) == (year + u64::from(month <= 2), month, day)).then_some(days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Read the entries of a tar archive as pairs of names and contents
+    fn read_tar(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut entries = Vec::new();
+        let mut long_name = None;
+        let mut blocks = archive.chunks(512);
+        while let Some(header) = blocks.next() {
+            if header.iter().all(|&b| b == 0) {
+                break;
+            }
+            let field = |range: std::ops::Range<usize>| {
+                let field = &header[range];
+                let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+                String::from_utf8(field[..end].to_vec()).unwrap()
+            };
+            let size = u64::from_str_radix(&field(124..136), 8).unwrap();
+            let checksum = u64::from_str_radix(&field(148..155), 8).unwrap();
+            let sum: u64 = header
+                .iter()
+                .enumerate()
+                .map(|(i, &b)| {
+                    if (148..156).contains(&i) {
+                        32
+                    } else {
+                        u64::from(b)
+                    }
+                })
+                .sum();
+            assert_eq!(checksum, sum);
+
+            let mut contents = Vec::new();
+            while (contents.len() as u64) < size {
+                contents.extend_from_slice(blocks.next().unwrap());
+            }
+            contents.truncate(size as usize);
+            if header[156] == b'L' {
+                contents.pop();
+                long_name = Some(String::from_utf8(contents).unwrap());
+                continue;
+            }
+            let name = match field(345..500) {
+                prefix if prefix.is_empty() => field(0..100),
+                prefix => format!("{}/{}", prefix, field(0..100)),
+            };
+            entries.push((long_name.take().unwrap_or(name), contents));
+        }
+        entries
+    }
+
+    #[test]
+    fn test_archive_format() {
+        assert_eq!(
+            ArchiveFormat::of_path(Path::new("backup.ZIP")),
+            ArchiveFormat::Zip
+        );
+        assert_eq!(
+            ArchiveFormat::of_path(Path::new("backup.tar")),
+            ArchiveFormat::Tar
+        );
+        assert_eq!("zip".parse(), Ok(ArchiveFormat::Zip));
+        assert!("7z".parse::<ArchiveFormat>().is_err());
+    }
+
+    #[test]
+    fn test_tar_archive() -> io::Result<()> {
+        let long_dir = "d".repeat(120);
+        let long_name = "n".repeat(120);
+        let mut writer = ArchiveWriter::new(Vec::new(), ArchiveFormat::Tar);
+        let archived = writer.add_data("a/title1.flac", b"content 1", UNIX_EPOCH)?;
+        assert_eq!(archived.size, 9);
+        assert_eq!(archived.crc32, crc32fast::hash(b"content 1"));
+        writer.add_data(
+            &format!("{}/title2.flac", long_dir),
+            b"content 2",
+            UNIX_EPOCH,
+        )?;
+        writer.add_data(&format!("a/{}.flac", long_name), b"", UNIX_EPOCH)?;
+        let archive = writer.finish()?;
+
+        assert_eq!(archive.len() % 512, 0);
+        assert!(archive.ends_with(&[0; 1024]));
+        assert_eq!(
+            read_tar(&archive),
+            vec![
+                ("a/title1.flac".to_string(), b"content 1".to_vec()),
+                (format!("{}/title2.flac", long_dir), b"content 2".to_vec()),
+                (format!("a/{}.flac", long_name), Vec::new()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_zip_archive() -> io::Result<()> {
+        let mut writer = ArchiveWriter::new(Vec::new(), ArchiveFormat::Zip);
+        writer.add_data("a/title1.flac", b"content 1", UNIX_EPOCH)?;
+        writer.add_data("b/title2.flac", b"content 2", UNIX_EPOCH)?;
+        let archive = writer.finish()?;
+
+        assert!(archive.starts_with(&0x0403_4b50u32.to_le_bytes()));
+        let end = &archive[archive.len() - 22..];
+        assert_eq!(end[..4], 0x0605_4b50u32.to_le_bytes());
+        assert_eq!(u16::from_le_bytes([end[10], end[11]]), 2);
+        let cd_size = u32::from_le_bytes(end[12..16].try_into().unwrap()) as usize;
+        let cd_offset = u32::from_le_bytes(end[16..20].try_into().unwrap()) as usize;
+        assert_eq!(cd_offset + cd_size, archive.len() - 22);
+
+        // The second local header follows the first entry and its data
+        // descriptor
+        let cd = &archive[cd_offset..cd_offset + cd_size];
+        let second = cd.len() / 2;
+        assert_eq!(cd[second..second + 4], 0x0201_4b50u32.to_le_bytes());
+        let crc32 = u32::from_le_bytes(cd[second + 16..second + 20].try_into().unwrap());
+        assert_eq!(crc32, crc32fast::hash(b"content 2"));
+        let offset = u32::from_le_bytes(cd[second + 42..second + 46].try_into().unwrap());
+        assert_eq!(offset as usize, 30 + 13 + 9 + 16);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dos_date_time() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000); // 2023-11-14 22:13:20
+        assert_eq!(
+            dos_date_time(time),
+            (
+                (22 << 11) | (13 << 5) | 10,
+                ((2023 - 1980) << 9) | (11 << 5) | 14
+            )
+        );
+        assert_eq!(dos_date_time(UNIX_EPOCH), (0, (1 << 5) | 1));
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(days_from_civil(1970, 1, 1), Some(0));
+        assert_eq!(days_from_civil(2024, 2, 29), Some(19_782));
+        assert_eq!(days_from_civil(2023, 2, 29), None);
+        assert_eq!(days_from_civil(2024, 13, 1), None);
+    }
+}
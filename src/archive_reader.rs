@@ -0,0 +1,559 @@
+//! Extraction of tar and zip archives
+//!
+//! Reads the archives written by the `archive` module as well as those
+//! of common archivers, as long as their entries are stored without
+//! compression.  Tar archives are read as a stream, so that they can be
+//! read from a pipe, while zip archives are read through their central
+//! directory.  Entries with absolute paths or `..` components are
+//! rejected, so that nothing is written outside the destination.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::archive::ArchiveFormat;
+
+/// Size of the blocks of tar archives
+const TAR_BLOCK_SIZE: usize = 512;
+
+/// Size of the end of central directory record of zip archives without
+/// its comment, and the largest size of the comment
+const ZIP_END_SIZE: usize = 22;
+const ZIP_MAX_COMMENT_SIZE: usize = 65_535;
+
+/// Value of the 32-bit sizes and offsets of zip archives standing for
+/// their zip64 values
+const ZIP64_U32: u32 = u32::MAX;
+
+/// Size of the buffer extracted files are copied through
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A file extracted from an archive
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtractedFile {
+    pub name: String, // Path in the archive
+    pub path: PathBuf,
+    pub size: u64,
+    pub crc32: u32,
+}
+
+/// Entry of the central directory of a zip archive
+#[derive(Debug)]
+struct ZipEntry {
+    name: String,
+    size: u64,
+    crc32: u32,
+    offset: u64, // Offset of the local header
+    modified: SystemTime,
+}
+
+/// Format of an archive by its first bytes: zip for the signature of a
+/// local header or of an empty archive, and tar otherwise
+pub fn detect_format(magic: &[u8]) -> ArchiveFormat {
+    match magic {
+        [b'P', b'K', 3, 4, ..] | [b'P', b'K', 5, 6, ..] => ArchiveFormat::Zip,
+        _ => ArchiveFormat::Tar,
+    }
+}
+
+/// Extract the archive at `path` into the directory `dest`, detecting
+/// its format by its contents
+pub fn extract_archive(path: &Path, dest: &Path) -> io::Result<Vec<ExtractedFile>> {
+    let mut file = File::open(path)?;
+    let mut magic = Vec::with_capacity(4);
+    (&mut file).take(4).read_to_end(&mut magic)?;
+    file.seek(SeekFrom::Start(0))?;
+    match detect_format(&magic) {
+        ArchiveFormat::Tar => extract_tar(io::BufReader::new(file), dest),
+        ArchiveFormat::Zip => extract_zip(file, dest),
+    }
+}
+
+/// Extract the regular files of a tar archive read from `input` into
+/// the directory `dest`
+///
+/// GNU long names and the paths of pax extended headers are honoured.
+/// Other entries, such as symbolic links, are skipped.
+pub fn extract_tar<R: Read>(mut input: R, dest: &Path) -> io::Result<Vec<ExtractedFile>> {
+    let mut extracted = Vec::new();
+    let mut long_name: Option<String> = None;
+    let mut header = [0; TAR_BLOCK_SIZE];
+
+    loop {
+        input.read_exact(&mut header)?;
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        check_tar_checksum(&header)?;
+        let size = tar_size(&header)?;
+
+        match header[156] {
+            b'0' | 0 => {
+                let name = long_name.take().unwrap_or_else(|| tar_name(&header));
+                let mtime = parse_octal(&header[136..148]).unwrap_or(0);
+                let modified = UNIX_EPOCH + Duration::from_secs(mtime);
+                if let Some(path) = entry_path(dest, &name)? {
+                    let crc32 = extract_file(&mut input, size, &path, modified)?;
+                    extracted.push(ExtractedFile {
+                        name,
+                        path,
+                        size,
+                        crc32,
+                    });
+                } else {
+                    io::copy(&mut (&mut input).take(size), &mut io::sink())?;
+                }
+            }
+            b'5' => {
+                let name = long_name.take().unwrap_or_else(|| tar_name(&header));
+                if let Some(path) = entry_path(dest, &name)? {
+                    fs::create_dir_all(path)?;
+                }
+                io::copy(&mut (&mut input).take(size), &mut io::sink())?;
+            }
+            b'L' => {
+                let data = read_tar_data(&mut input, size)?;
+                let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+                long_name = Some(String::from_utf8_lossy(&data[..end]).into_owned());
+            }
+            b'x' => {
+                let data = read_tar_data(&mut input, size)?;
+                if let Some(path) = pax_path(&data) {
+                    long_name = Some(path);
+                }
+            }
+            _ => {
+                long_name = None;
+                io::copy(&mut (&mut input).take(size), &mut io::sink())?;
+            }
+        }
+
+        let padding =
+            (TAR_BLOCK_SIZE as u64 - size % TAR_BLOCK_SIZE as u64) % TAR_BLOCK_SIZE as u64;
+        io::copy(&mut (&mut input).take(padding), &mut io::sink())?;
+    }
+
+    Ok(extracted)
+}
+
+/// Extract the files of a zip archive read from `input` into the
+/// directory `dest`
+///
+/// Fails on compressed or encrypted entries, and on entries whose
+/// contents do not match their CRC-32 checksum.
+pub fn extract_zip<R: Read + Seek>(mut input: R, dest: &Path) -> io::Result<Vec<ExtractedFile>> {
+    let mut extracted = Vec::new();
+
+    for entry in read_zip_central_directory(&mut input)? {
+        let Some(path) = entry_path(dest, &entry.name)? else {
+            continue;
+        };
+        if entry.name.ends_with('/') {
+            fs::create_dir_all(path)?;
+            continue;
+        }
+
+        let mut header = [0; 30];
+        input.seek(SeekFrom::Start(entry.offset))?;
+        input.read_exact(&mut header)?;
+        if get_u32(&header, 0) != 0x0403_4b50 {
+            return Err(invalid_data(format!(
+                "Invalid local header of {} in zip archive",
+                entry.name
+            )));
+        }
+        let skip = u64::from(get_u16(&header, 26)) + u64::from(get_u16(&header, 28));
+        input.seek(SeekFrom::Current(skip as i64))?;
+
+        let crc32 = extract_file(&mut input, entry.size, &path, entry.modified)?;
+        if crc32 != entry.crc32 {
+            return Err(invalid_data(format!(
+                "CRC-32 mismatch of {} in zip archive",
+                entry.name
+            )));
+        }
+        extracted.push(ExtractedFile {
+            name: entry.name,
+            path,
+            size: entry.size,
+            crc32,
+        });
+    }
+
+    Ok(extracted)
+}
+
+/// Path in `dest` of an entry named `name`, or `None` for a name
+/// without any component, such as `./`
+///
+/// Fails on absolute names and on names with `..` components.
+fn entry_path(dest: &Path, name: &str) -> io::Result<Option<PathBuf>> {
+    let mut path = dest.to_path_buf();
+    let mut has_components = false;
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(component) => {
+                path.push(component);
+                has_components = true;
+            }
+            Component::CurDir => {}
+            _ => return Err(invalid_data(format!("Unsafe path in archive: {}", name))),
+        }
+    }
+    Ok(has_components.then_some(path))
+}
+
+/// Copy exactly `size` bytes of `input` into a new file at `path` with
+/// the modification time `modified`, returning their CRC-32 checksum
+fn extract_file(
+    input: &mut impl Read,
+    size: u64,
+    path: &Path,
+    modified: SystemTime,
+) -> io::Result<u32> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = File::create(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0; COPY_BUFFER_SIZE];
+    let mut remaining = size;
+
+    while remaining > 0 {
+        let len = remaining.min(buf.len() as u64) as usize;
+        input.read_exact(&mut buf[..len])?;
+        hasher.update(&buf[..len]);
+        file.write_all(&buf[..len])?;
+        remaining -= len as u64;
+    }
+
+    file.set_modified(modified)?;
+    Ok(hasher.finalize())
+}
+
+/// Check the checksum of a tar header, computed with its own field
+/// filled with spaces
+fn check_tar_checksum(header: &[u8; TAR_BLOCK_SIZE]) -> io::Result<()> {
+    let sum: u64 = header
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| match i {
+            148..156 => u64::from(b' '),
+            _ => u64::from(b),
+        })
+        .sum();
+    match parse_octal(&header[148..156]) {
+        Some(checksum) if checksum == sum => Ok(()),
+        _ => Err(invalid_data("Invalid tar header checksum".to_string())),
+    }
+}
+
+/// Size of a tar entry, either an octal number or a GNU base-256 number
+fn tar_size(header: &[u8; TAR_BLOCK_SIZE]) -> io::Result<u64> {
+    let field = &header[124..136];
+    if field[0] & 0x80 != 0 {
+        return Ok(u64::from_be_bytes(field[4..].try_into().unwrap()));
+    }
+    parse_octal(field).ok_or_else(|| invalid_data("Invalid tar entry size".to_string()))
+}
+
+/// Name of a tar entry, with the prefix of a ustar header
+fn tar_name(header: &[u8; TAR_BLOCK_SIZE]) -> String {
+    let name = c_string(&header[..100]);
+    if &header[257..262] != b"ustar" {
+        return name;
+    }
+    match c_string(&header[345..500]) {
+        prefix if prefix.is_empty() => name,
+        prefix => format!("{}/{}", prefix, name),
+    }
+}
+
+/// Read the `size` bytes of data of a tar entry
+fn read_tar_data(input: &mut impl Read, size: u64) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    input.take(size).read_to_end(&mut data)?;
+    if (data.len() as u64) < size {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(data)
+}
+
+/// Path of a pax extended header, made of records `LENGTH KEY=VALUE\n`
+fn pax_path(data: &[u8]) -> Option<String> {
+    let mut rest = data;
+    while !rest.is_empty() {
+        let space = rest.iter().position(|&b| b == b' ')?;
+        let len: usize = std::str::from_utf8(&rest[..space]).ok()?.parse().ok()?;
+        let record = rest.get(space + 1..len)?.strip_suffix(b"\n")?;
+        if let Some(path) = record.strip_prefix(b"path=") {
+            return Some(String::from_utf8_lossy(path).into_owned());
+        }
+        rest = &rest[len..];
+    }
+    None
+}
+
+/// Value of an octal number field of a tar header, terminated by a NUL
+/// or a space
+fn parse_octal(field: &[u8]) -> Option<u64> {
+    let digits = c_string(field);
+    u64::from_str_radix(digits.trim_matches(' '), 8).ok()
+}
+
+/// String of a NUL-terminated field
+fn c_string(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+/// Read the entries of the central directory of a zip archive, with
+/// their zip64 sizes and offsets
+fn read_zip_central_directory<R: Read + Seek>(input: &mut R) -> io::Result<Vec<ZipEntry>> {
+    let len = input.seek(SeekFrom::End(0))?;
+    let tail_len = len.min((ZIP_END_SIZE + ZIP_MAX_COMMENT_SIZE) as u64);
+    let mut tail = vec![0; tail_len as usize];
+    input.seek(SeekFrom::Start(len - tail_len))?;
+    input.read_exact(&mut tail)?;
+
+    let end = (0..tail.len().saturating_sub(ZIP_END_SIZE - 1))
+        .rev()
+        .find(|&i| get_u32(&tail, i) == 0x0605_4b50)
+        .ok_or_else(|| invalid_data("No end of central directory in zip archive".to_string()))?;
+    let mut n_entries = u64::from(get_u16(&tail, end + 10));
+    let mut cd_size = u64::from(get_u32(&tail, end + 12));
+    let mut cd_offset = u64::from(get_u32(&tail, end + 16));
+
+    // A zip64 end of central directory locator precedes the record
+    if end >= 20 && get_u32(&tail, end - 20) == 0x0706_4b50 {
+        let mut zip64_end = [0; 56];
+        input.seek(SeekFrom::Start(get_u64(&tail, end - 12)))?;
+        input.read_exact(&mut zip64_end)?;
+        if get_u32(&zip64_end, 0) != 0x0606_4b50 {
+            return Err(invalid_data(
+                "Invalid zip64 end of central directory".to_string(),
+            ));
+        }
+        n_entries = get_u64(&zip64_end, 32);
+        cd_size = get_u64(&zip64_end, 40);
+        cd_offset = get_u64(&zip64_end, 48);
+    }
+
+    let mut cd = Vec::new();
+    input.seek(SeekFrom::Start(cd_offset))?;
+    input.take(cd_size).read_to_end(&mut cd)?;
+
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    for _ in 0..n_entries {
+        if pos + 46 > cd.len() || get_u32(&cd, pos) != 0x0201_4b50 {
+            return Err(invalid_data(
+                "Invalid central directory in zip archive".to_string(),
+            ));
+        }
+        let flags = get_u16(&cd, pos + 8);
+        let method = get_u16(&cd, pos + 10);
+        let name_len = usize::from(get_u16(&cd, pos + 28));
+        let extra_len = usize::from(get_u16(&cd, pos + 30));
+        let comment_len = usize::from(get_u16(&cd, pos + 32));
+        let name_end = pos + 46 + name_len;
+        let extra_end = name_end + extra_len;
+        if extra_end > cd.len() {
+            return Err(invalid_data(
+                "Invalid central directory in zip archive".to_string(),
+            ));
+        }
+        let name = String::from_utf8_lossy(&cd[pos + 46..name_end]).into_owned();
+
+        if flags & 0x0001 != 0 {
+            return Err(invalid_data(format!(
+                "Encrypted entry in zip archive: {}",
+                name
+            )));
+        }
+        if method != 0 {
+            return Err(invalid_data(format!(
+                "Compressed entry in zip archive: {}",
+                name
+            )));
+        }
+
+        let mut size = u64::from(get_u32(&cd, pos + 24));
+        let mut offset = u64::from(get_u32(&cd, pos + 42));
+        let mut extra = &cd[name_end..extra_end];
+        while extra.len() >= 4 {
+            let id = get_u16(extra, 0);
+            let len = usize::from(get_u16(extra, 2)).min(extra.len() - 4);
+            if id == 0x0001 {
+                // Only the fields standing for zip64 values are present,
+                // in this order
+                let mut values = extra[4..4 + len].chunks_exact(8).map(|v| get_u64(v, 0));
+                if size == u64::from(ZIP64_U32) {
+                    size = values.next().unwrap_or(size);
+                }
+                if get_u32(&cd, pos + 20) == ZIP64_U32 {
+                    values.next();
+                }
+                if offset == u64::from(ZIP64_U32) {
+                    offset = values.next().unwrap_or(offset);
+                }
+            }
+            extra = &extra[4 + len..];
+        }
+
+        entries.push(ZipEntry {
+            name,
+            size,
+            crc32: get_u32(&cd, pos + 16),
+            offset,
+            modified: dos_to_system_time(get_u16(&cd, pos + 12), get_u16(&cd, pos + 14)),
+        });
+        pos = extra_end + comment_len;
+    }
+
+    Ok(entries)
+}
+
+/// Time of an MS-DOS time and date, taken as UTC
+fn dos_to_system_time(time: u16, date: u16) -> SystemTime {
+    let year = 1980 + u64::from(date >> 9);
+    let month = u64::from((date >> 5) & 0x0f).clamp(1, 12);
+    let day = u64::from(date & 0x1f).max(1);
+    let secs = u64::from(time >> 11) * 3600
+        + u64::from((time >> 5) & 0x3f) * 60
+        + u64::from(time & 0x1f) * 2;
+    UNIX_EPOCH + Duration::from_secs(days_from_civil(year, month, day) * 86_400 + secs)
+}
+
+/// Days after 1970-01-01 of a date of the proleptic Gregorian calendar
+/// from 1970
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year % 400;
+    let shifted_month = (month + 9) % 12; // From March
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn get_u16(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes(buf[at..at + 2].try_into().unwrap())
+}
+
+fn get_u32(buf: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(buf[at..at + 4].try_into().unwrap())
+}
+
+fn get_u64(buf: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(buf[at..at + 8].try_into().unwrap())
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive::ArchiveWriter;
+    use std::io::Cursor;
+
+    /// Write an archive of two files, one with a long name
+    fn write_archive(format: ArchiveFormat, long_name: &str) -> io::Result<Vec<u8>> {
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let mut writer = ArchiveWriter::new(Vec::new(), format);
+        writer.add_data("playlist.m3u8", b"a/title1.flac\n", modified)?;
+        writer.add_data(long_name, b"content 1", modified)?;
+        writer.finish()
+    }
+
+    #[test]
+    fn test_extract_tar() -> io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let long_name = format!("{}/title1.flac", "d".repeat(200));
+        let archive = write_archive(ArchiveFormat::Tar, &long_name)?;
+        assert_eq!(detect_format(&archive), ArchiveFormat::Tar);
+
+        let extracted = extract_tar(Cursor::new(archive), temp_dir.path())?;
+        assert_eq!(extracted.len(), 2);
+        assert_eq!(extracted[1].name, long_name);
+        assert_eq!(extracted[1].crc32, crc32fast::hash(b"content 1"));
+        let path = temp_dir.path().join(&long_name);
+        assert_eq!(fs::read(&path)?, b"content 1");
+        assert_eq!(
+            fs::metadata(&path)?.modified()?,
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_zip() -> io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let archive = write_archive(ArchiveFormat::Zip, "a/title1.flac")?;
+        assert_eq!(detect_format(&archive), ArchiveFormat::Zip);
+
+        let extracted = extract_zip(Cursor::new(archive), temp_dir.path())?;
+        assert_eq!(
+            extracted
+                .iter()
+                .map(|file| (file.name.as_str(), file.size))
+                .collect::<Vec<_>>(),
+            vec![("playlist.m3u8", 14), ("a/title1.flac", 9)]
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("playlist.m3u8"))?,
+            "a/title1.flac\n"
+        );
+        // DOS times have a resolution of two seconds
+        assert_eq!(
+            fs::metadata(temp_dir.path().join("a/title1.flac"))?.modified()?,
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_unsafe_path() -> io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let dest = temp_dir.path().join("dest");
+        for name in ["../title1.flac", "/tmp/title1.flac"] {
+            let mut writer = ArchiveWriter::new(Vec::new(), ArchiveFormat::Tar);
+            writer.add_data(name, b"content 1", UNIX_EPOCH)?;
+            let archive = writer.finish()?;
+            let err = extract_tar(Cursor::new(archive), &dest).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+        assert!(!temp_dir.path().join("title1.flac").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_corrupt_zip() -> io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let mut archive = write_archive(ArchiveFormat::Zip, "a/title1.flac")?;
+        let pos = archive
+            .windows(9)
+            .position(|window| window == b"content 1")
+            .unwrap();
+        archive[pos] = b'C';
+        let err = extract_zip(Cursor::new(archive), temp_dir.path()).unwrap_err();
+        assert!(err.to_string().contains("CRC-32 mismatch"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_days_from_civil() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2024, 2, 29), 19_782);
+        assert_eq!(
+            dos_to_system_time(
+                (22 << 11) | (13 << 5) | 10,
+                ((2023 - 1980) << 9) | (11 << 5) | 14
+            ),
+            UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use std::io;
+use std::path::Path;
+use std::process;
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Parser};
+use playlist_manager::logger::{Logger, StderrLogger};
+use playlist_manager::playlist_file::PlaylistFile;
+use playlist_manager::track_matcher::{playlist_dir, relative_entry, TrackMatcher};
+use thiserror::Error;
+
+#[derive(Parser)]
+#[command(name = "plm-add-track")]
+#[command(about = "Append or insert tracks into a playlist file")]
+#[command(version)]
+struct Cli {
+    /// Print verbose messages
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Insert the tracks so that the first becomes the N-th entry (1-based)
+    #[arg(short = 'n', long = "position", value_name = "N", conflicts_with_all = ["before", "after"])]
+    position: Option<usize>,
+
+    /// Insert the tracks before the first entry matching ENTRY
+    #[arg(
+        short = 'b',
+        long = "before",
+        value_name = "ENTRY",
+        conflicts_with = "after"
+    )]
+    before: Option<String>,
+
+    /// Insert the tracks after the first entry matching ENTRY
+    #[arg(short = 'a', long = "after", value_name = "ENTRY")]
+    after: Option<String>,
+
+    /// Playlist file to add the tracks to
+    playlist: String,
+
+    /// Media file(s) to add
+    #[arg(required = true)]
+    tracks: Vec<String>,
+}
+
+#[derive(Error, Debug)]
+enum AppError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    #[error("Track is not under the directory of the playlist: {0}")]
+    OutsidePlaylistDir(String),
+    #[error("Position {0} is out of range (1-{1})")]
+    PositionOutOfRange(usize, usize),
+    #[error("Entry not found in playlist: {0}")]
+    EntryNotFound(String),
+}
+
+/// Turn the tracks given on the command line into playlist entries
+fn resolve_entries(tracks: &[String], dir: &Path) -> Result<Vec<String>, AppError> {
+    tracks
+        .iter()
+        .map(|track| {
+            relative_entry(Path::new(track), dir)?
+                .ok_or_else(|| AppError::OutsidePlaylistDir(track.clone()))
+        })
+        .collect()
+}
+
+/// Find the index of the entry the tracks are inserted at
+fn insert_index(cli: &Cli, playlist_file: &PlaylistFile, dir: &Path) -> Result<usize, AppError> {
+    let n_entries = playlist_file.entry_count();
+
+    if let Some(position) = cli.position {
+        if position == 0 || position > n_entries + 1 {
+            return Err(AppError::PositionOutOfRange(position, n_entries + 1));
+        }
+        return Ok(position - 1);
+    }
+
+    let (entry, offset) = match (&cli.before, &cli.after) {
+        (Some(entry), _) => (entry, 0),
+        (_, Some(entry)) => (entry, 1),
+        _ => return Ok(n_entries),
+    };
+
+    let matcher = TrackMatcher::path(entry)?;
+    playlist_file
+        .find_entry(|candidate| matcher.matches(dir, candidate))
+        .map(|index| index + offset)
+        .ok_or_else(|| AppError::EntryNotFound(entry.clone()))
+}
+
+/// Build the `#EXTINF` line of a new entry, titled after its file name
+/// with an unknown duration
+fn extinf_line(entry: &str) -> String {
+    let title = Path::new(entry)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("#EXTINF:-1,{}", title)
+}
+
+/// Add the tracks to the playlist file
+/// Returns the number of added tracks
+fn add_tracks(cli: &Cli, logger: &dyn Logger) -> Result<usize> {
+    let playlist_path = Path::new(&cli.playlist);
+    let dir = playlist_dir(playlist_path);
+
+    let mut playlist_file = PlaylistFile::read(playlist_path)
+        .with_context(|| format!("Failed to read playlist: {}", cli.playlist))?;
+
+    let entries = resolve_entries(&cli.tracks, &dir)?;
+    let index = insert_index(cli, &playlist_file, &dir)?;
+
+    let extended = playlist_file.is_extended();
+    let backslashes = playlist_file.uses_backslashes();
+
+    for (i, entry) in entries.iter().enumerate() {
+        if !dir.join(entry).exists() {
+            eprintln!(
+                "Warning: Media file not found: {}",
+                dir.join(entry).display()
+            );
+        }
+
+        logger.log_formatted(
+            "Adding track \"{}\" to playlist \"{}\"",
+            &[entry, &cli.playlist],
+        );
+
+        let extinf = extended.then(|| extinf_line(entry));
+        let written = if backslashes {
+            entry.replace('/', "\\")
+        } else {
+            entry.clone()
+        };
+        playlist_file.insert_entry(index + i, extinf.as_deref(), &written);
+    }
+
+    playlist_file
+        .write(playlist_path)
+        .with_context(|| format!("Failed to write playlist: {}", cli.playlist))?;
+
+    Ok(entries.len())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let logger = StderrLogger::new(cli.verbose);
+
+    match add_tracks(&cli, &logger) {
+        Ok(n_tracks) => {
+            if cli.verbose {
+                println!("Number of added tracks: {}", n_tracks);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            match e.downcast_ref::<AppError>() {
+                Some(AppError::OutsidePlaylistDir(_)) => process::exit(255),
+                _ => process::exit(1),
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,355 @@
+use std::collections::HashMap;
+#[cfg(feature = "s3")]
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Parser};
+use playlist_manager::archive::{ArchiveFormat, ArchiveWriter, ArchivedFile};
+use playlist_manager::error_file::escape_path;
+#[cfg(feature = "s3")]
+use playlist_manager::object_store::{self, S3Config, S3Location};
+use playlist_manager::playlist_file::PlaylistFile;
+use playlist_manager::track_matcher::{absolute_path, playlist_dir, relative_entry};
+
+/// Name of the manifest written into the archive with `--manifest`
+const MANIFEST_NAME: &str = "plm-manifest.tsv";
+
+/// Header line of the manifest, giving the version of its format
+const MANIFEST_HEADER: &str = "# plm-manifest 1";
+
+/// Extension of lyrics files
+const LYRICS_EXTENSION: &str = "lrc";
+
+#[derive(Parser)]
+#[command(name = "plm-backup")]
+#[command(about = "Archive playlist files and associated media files into a tar or zip file")]
+#[command(version)]
+struct Cli {
+    /// Print verbose messages
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Archive lyrics files (.lrc) along with media files
+    #[arg(short = 'l', long = "lyrics", action = ArgAction::SetTrue)]
+    lyrics: bool,
+
+    /// Skip playlists and media files that cannot be archived instead of failing
+    #[arg(short = 'k', long = "keep-going", action = ArgAction::SetTrue)]
+    keep_going: bool,
+
+    /// Format of the archive (tar or zip, default: by the extension of ARCHIVE)
+    #[arg(long = "format", value_name = "FORMAT")]
+    format: Option<ArchiveFormat>,
+
+    /// Add a manifest of the archived files with their sizes and CRC-32 checksums
+    #[arg(long = "manifest", action = ArgAction::SetTrue)]
+    manifest: bool,
+
+    /// Rewrite the entries of the archived playlists to the paths of the media files in the archive
+    #[arg(long = "rewrite-paths", action = ArgAction::SetTrue)]
+    rewrite_paths: bool,
+
+    /// Archive file to write, s3://BUCKET/KEY for object storage, or - for the standard output
+    archive: String,
+
+    /// Playlist files to archive
+    #[arg(required = true)]
+    playlists: Vec<String>,
+}
+
+/// A playlist resolved to the paths of its files in the archive
+struct ResolvedPlaylist {
+    name: String,                   // Path of the playlist in the archive
+    contents: Vec<u8>,              // Contents archived for the playlist
+    tracks: Vec<(String, PathBuf)>, // Paths in the archive and source paths
+}
+
+/// Numbers of the files archived
+#[derive(Debug, Default)]
+struct BackupCounts {
+    playlists: usize,
+    media_files: usize,
+    lyrics_files: usize,
+}
+
+/// Archive being written, with the files added so far
+struct Backup<W: Write> {
+    writer: ArchiveWriter<W>,
+    sources: HashMap<String, PathBuf>, // Source of each path in the archive
+    archived: Vec<ArchivedFile>,
+    counts: BackupCounts,
+    verbose: bool,
+}
+
+impl<W: Write> Backup<W> {
+    fn new(writer: ArchiveWriter<W>, verbose: bool) -> Self {
+        Self {
+            writer,
+            sources: HashMap::new(),
+            archived: Vec::new(),
+            counts: BackupCounts::default(),
+            verbose,
+        }
+    }
+
+    /// Whether the file at `path` is to be added as `name`, `false` if
+    /// it was added already
+    ///
+    /// Fails if the file does not exist or if `name` is taken by another
+    /// file, before anything is written into the archive.
+    fn is_new(&self, name: &str, path: &Path) -> Result<bool> {
+        if !path.is_file() {
+            anyhow::bail!("File not found: {}", path.display());
+        }
+        match self.sources.get(name) {
+            Some(source) if *source == absolute_path(path)? => Ok(false),
+            Some(source) => anyhow::bail!(
+                "Archive path {} of {} is taken by {}",
+                name,
+                path.display(),
+                source.display()
+            ),
+            None => Ok(true),
+        }
+    }
+
+    /// Whether the file at `path` is to be added as `name` as by
+    /// [`Backup::is_new`], printing why not with `--keep-going`
+    fn is_to_add(&self, name: &str, path: &Path, keep_going: bool) -> Result<bool> {
+        match self.is_new(name, path) {
+            Err(e) if keep_going => {
+                eprintln!("Error: {}", e);
+                Ok(false)
+            }
+            result => result,
+        }
+    }
+
+    /// Add the file at `path` as `name`, or `contents` for it if given
+    fn add(&mut self, name: &str, path: &Path, contents: Option<&[u8]>) -> Result<()> {
+        if self.verbose {
+            eprintln!("Add \"{}\" as \"{}\"", path.display(), name);
+        }
+        let archived = match contents {
+            Some(contents) => self.writer.add_data(name, contents, modified_time(path)),
+            None => self.writer.add_file(name, path),
+        }
+        .map_err(|e| anyhow::anyhow!("Failed to archive {}: {}", path.display(), e))?;
+        self.sources.insert(name.to_string(), absolute_path(path)?);
+        self.archived.push(archived);
+        Ok(())
+    }
+
+    /// Add a resolved playlist and its media files, and their lyrics
+    /// files with `--lyrics`
+    fn add_playlist(
+        &mut self,
+        playlist: &Path,
+        resolved: &ResolvedPlaylist,
+        cli: &Cli,
+    ) -> Result<()> {
+        self.add(&resolved.name, playlist, Some(&resolved.contents))?;
+        self.counts.playlists += 1;
+
+        for (name, track) in &resolved.tracks {
+            if !self.is_to_add(name, track, cli.keep_going)? {
+                continue;
+            }
+            self.add(name, track, None)?;
+            self.counts.media_files += 1;
+
+            let lyrics = track.with_extension(LYRICS_EXTENSION);
+            let lyrics_name = Path::new(name).with_extension(LYRICS_EXTENSION);
+            let lyrics_name = lyrics_name.to_string_lossy();
+            if cli.lyrics
+                && lyrics.is_file()
+                && self.is_to_add(&lyrics_name, &lyrics, cli.keep_going)?
+            {
+                self.add(&lyrics_name, &lyrics, None)?;
+                self.counts.lyrics_files += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Add the manifest of the files added so far
+    fn add_manifest(&mut self) -> Result<()> {
+        let mut manifest = format!("{}\n", MANIFEST_HEADER);
+        for archived in &self.archived {
+            manifest.push_str(&format!(
+                "{}\t{}\t{:08x}\n",
+                escape_path(&archived.name),
+                archived.size,
+                archived.crc32
+            ));
+        }
+        self.writer
+            .add_data(MANIFEST_NAME, manifest.as_bytes(), SystemTime::now())
+            .map_err(|e| anyhow::anyhow!("Failed to archive the manifest: {}", e))?;
+        Ok(())
+    }
+
+    /// Complete the archive, returning the numbers of the files archived
+    fn finish(self) -> Result<BackupCounts> {
+        self.writer
+            .finish()
+            .map_err(|e| anyhow::anyhow!("Failed to write archive: {}", e))?;
+        Ok(self.counts)
+    }
+}
+
+/// Modification time of a file, or now if unknown
+fn modified_time(path: &Path) -> SystemTime {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or_else(|_| SystemTime::now())
+}
+
+/// Resolve the entries of a playlist to the paths of its media files in
+/// the archive, relative to the directory of the playlist placed at the
+/// root of the archive
+///
+/// Fails if an entry is outside the directory of the playlist, or if the
+/// name of the playlist is taken by another playlist.
+fn resolve_playlist<W: Write>(
+    playlist: &Path,
+    backup: &Backup<W>,
+    cli: &Cli,
+) -> Result<ResolvedPlaylist> {
+    let mut playlist_file = PlaylistFile::read(playlist)
+        .with_context(|| format!("Failed to read playlist: {}", playlist.display()))?;
+    let dir = playlist_dir(playlist);
+
+    let mut names = HashMap::new();
+    let mut tracks = Vec::new();
+    for entry in playlist_file.entries() {
+        let track = dir.join(&entry);
+        let name = relative_entry(&track, &dir)?
+            .ok_or_else(|| anyhow::anyhow!("{} is not under {}", entry, dir.display()))?;
+        tracks.push((name.clone(), track));
+        names.insert(entry, name);
+    }
+
+    let name = playlist
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow::anyhow!("Not a playlist file: {}", playlist.display()))?;
+    if !backup.is_new(&name, playlist)? {
+        anyhow::bail!("Playlist {} is given twice", playlist.display());
+    }
+
+    let contents = match cli.rewrite_paths {
+        true => {
+            playlist_file.rewrite_entries(|entry| names[entry].clone());
+            playlist_file.contents().into_bytes()
+        }
+        false => fs::read(playlist)?,
+    };
+    Ok(ResolvedPlaylist {
+        name,
+        contents,
+        tracks,
+    })
+}
+
+/// Archive the playlists and their media files into `out`
+fn backup<W: Write>(cli: &Cli, out: W, format: ArchiveFormat) -> Result<BackupCounts> {
+    let mut backup = Backup::new(ArchiveWriter::new(out, format), cli.verbose);
+
+    for playlist in &cli.playlists {
+        let playlist = Path::new(playlist);
+        let resolved = match resolve_playlist(playlist, &backup, cli) {
+            Ok(resolved) => resolved,
+            Err(e) if cli.keep_going => {
+                eprintln!("Error: {}", e);
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        backup.add_playlist(playlist, &resolved, cli)?;
+    }
+
+    if cli.manifest {
+        backup.add_manifest()?;
+    }
+    backup.finish()
+}
+
+/// Write the archive into the file at `path`, deleting it if left
+/// incomplete by an error
+fn write_archive_file(cli: &Cli, path: &Path, format: ArchiveFormat) -> Result<BackupCounts> {
+    let file = File::create(path)
+        .with_context(|| format!("Failed to create archive: {}", path.display()))?;
+    backup(cli, BufWriter::new(file), format).inspect_err(|_| {
+        let _ = fs::remove_file(path);
+    })
+}
+
+/// Write the archive into a temporary file and upload it to object
+/// storage
+#[cfg(feature = "s3")]
+fn upload_archive(cli: &Cli, format: ArchiveFormat) -> Result<BackupCounts> {
+    // Settings are checked ahead of writing a large archive in vain
+    let location = S3Location::parse(&cli.archive)?;
+    let config = S3Config::from_env()?;
+    let path = env::temp_dir().join(format!("plm-backup-{}", process::id()));
+
+    let counts = write_archive_file(cli, &path, format)?;
+    if cli.verbose {
+        eprintln!("Upload archive to {}", cli.archive);
+    }
+    let result = object_store::upload(&path, &location, &config)
+        .map_err(|e| anyhow::anyhow!("Failed to upload archive to {}: {}", cli.archive, e));
+    let _ = fs::remove_file(&path);
+    result.map(|_| counts)
+}
+
+#[cfg(not(feature = "s3"))]
+fn upload_archive(cli: &Cli, _format: ArchiveFormat) -> Result<BackupCounts> {
+    anyhow::bail!(
+        "Archives in object storage require the s3 feature: {}",
+        cli.archive
+    )
+}
+
+/// Write the archive to the standard output, to object storage or to
+/// its file
+fn write_archive(cli: &Cli) -> Result<BackupCounts> {
+    let format = cli
+        .format
+        .unwrap_or_else(|| ArchiveFormat::of_path(Path::new(&cli.archive)));
+    if cli.archive == "-" {
+        return backup(cli, BufWriter::new(io::stdout().lock()), format);
+    }
+    if cli.archive.starts_with("s3://") {
+        return upload_archive(cli, format);
+    }
+    write_archive_file(cli, Path::new(&cli.archive), format)
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    match write_archive(&cli) {
+        Ok(counts) => {
+            if cli.verbose {
+                eprintln!(
+                    "Archived {} playlists, {} media files and {} lyrics files",
+                    counts.playlists, counts.media_files, counts.lyrics_files
+                );
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+
+    Ok(())
+}
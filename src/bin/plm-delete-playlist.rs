@@ -1,12 +1,19 @@
-use std::collections::HashSet;
-use std::fs::{self, File};
-use std::io::{self, BufRead, BufReader};
-use std::path::Path;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::process;
 
 use anyhow::{Context, Result};
 use clap::{ArgAction, Parser};
-use thiserror::Error;
+use playlist_manager::empty_dirs::{prune_empty_dirs, PruneOptions};
+use playlist_manager::logger::StderrLogger;
+use playlist_manager::media_extensions::{is_media_file, parse_extension};
+use playlist_manager::media_set::{read_playlist_media, MediaSet};
+use playlist_manager::path_interner::PathInterner;
+use playlist_manager::playlist_file::is_playlist_file;
+use playlist_manager::post_sync::PostSyncActions;
+use regex::Regex;
 
 #[derive(Parser)]
 #[command(name = "plm-delete-playlist")]
@@ -21,15 +28,37 @@ struct Cli {
     #[arg(short = 'm', long = "media", action = ArgAction::SetTrue)]
     media: bool,
 
-    /// Playlist file(s) to delete
-    #[arg(required = true)]
-    playlists: Vec<String>,
-}
+    /// Delete the lyrics files with the comma-separated extensions, such as lrc,txt, instead of .lrc (may be repeated)
+    #[arg(long = "lyrics-ext", value_name = "EXTS", value_delimiter = ',', value_parser = parse_extension, default_value = "lrc", requires = "media")]
+    lyrics_ext: Vec<String>,
+
+    /// Also delete the files sharing the stem of each media file with the comma-separated extensions, such as cue,jpg,pdf (may be repeated)
+    #[arg(long = "sidecar", value_name = "EXTS", value_delimiter = ',', value_parser = parse_extension, requires = "media")]
+    sidecar: Vec<String>,
+
+    /// Expand playlist entries naming directories into the media files in them
+    #[arg(long = "expand-dirs", action = ArgAction::SetTrue)]
+    expand_dirs: bool,
+
+    /// Directory the entries of the playlists are relative to (default: directory of each playlist)
+    #[arg(long = "relative-to", value_name = "DIR", requires = "media")]
+    relative_to: Option<String>,
+
+    /// Read playlist files to delete from FILE, one per line ("-" for standard input)
+    #[arg(long = "playlists-from", value_name = "FILE")]
+    playlists_from: Option<String>,
 
-#[derive(Error, Debug)]
-enum AppError {
-    #[error("IO error: {0}")]
-    Io(#[from] io::Error),
+    /// Delete only playlist files whose names match REGEX, searching directories given as PLAYLIST
+    #[arg(long = "match-regex", value_name = "REGEX", value_parser = Regex::new)]
+    match_regex: Option<Regex>,
+
+    /// Run the actions of the device profile FILE in the directory of the playlists after deleting them
+    #[arg(long = "post-sync", value_name = "FILE")]
+    post_sync: Option<String>,
+
+    /// Playlist file(s) to delete, or wildcard patterns ("-" reads them from standard input)
+    #[arg(required_unless_present = "playlists_from")]
+    playlists: Vec<String>,
 }
 
 /// Print a message if verbose mode is enabled
@@ -42,52 +71,106 @@ fn print_message(verbose: bool, fmt: &str, args: &[&str]) {
     }
 }
 
-/// Extract media files from a playlist
-fn extract_media_files(playlist: &str) -> Result<(String, Vec<String>)> {
-    let playlist_path = Path::new(playlist);
-    let base_dir = playlist_path
-        .parent()
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_else(|| ".".to_string());
-
-    let file =
-        File::open(playlist).with_context(|| format!("Failed to open playlist: {}", playlist))?;
-    let reader = BufReader::new(file);
+/// Read the playlist files listed one per line in the file at `source`,
+/// or in the standard input if `source` is `-`
+///
+/// Blank lines and lines starting with `#` are ignored, and surrounding
+/// white space is removed.
+fn read_playlist_list(source: &str) -> Result<Vec<String>> {
+    let content = match source {
+        "-" => io::read_to_string(io::stdin()),
+        path => fs::read_to_string(path),
+    }
+    .map_err(|e| anyhow::anyhow!("Failed to read playlist list {}: {}", source, e))?;
 
-    let media_files = reader
+    Ok(content
         .lines()
-        .filter_map(Result::ok)
-        .map(|line| {
-            // Remove BOM if present
-            let line = if line.starts_with('\u{feff}') {
-                line[3..].to_string()
-            } else {
-                line
-            };
-
-            // Remove carriage return if present
-            let line = if line.ends_with('\r') {
-                line[..line.len() - 1].to_string()
-            } else {
-                line
-            };
-
-            line
-        })
-        .filter(|line| {
-            // Skip comments and empty lines
-            if line.starts_with('#') || line.is_empty() {
-                return false;
-            }
-            true
-        })
-        .map(|line| {
-            // Replace backslashes with forward slashes
-            line.replace('\\', "/")
-        })
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Expand a playlist argument containing wildcards into the files
+/// matching it, sorted by name
+///
+/// Arguments without wildcards and paths of existing files are kept as
+/// they are, so that names containing `[` can still be given.  A pattern
+/// matching nothing is an error rather than a playlist name.
+fn expand_pattern(playlist: &str) -> Result<Vec<String>> {
+    if !playlist.contains(['*', '?', '[']) || Path::new(playlist).exists() {
+        return Ok(vec![playlist.to_string()]);
+    }
+
+    let paths = glob::glob(playlist)
+        .map_err(|e| anyhow::anyhow!("Invalid pattern {}: {}", playlist, e))?;
+    let mut playlists: Vec<String> = paths
+        .filter_map(|path| path.ok())
+        .map(|path| path.to_string_lossy().to_string())
         .collect();
+    if playlists.is_empty() {
+        return Err(anyhow::anyhow!("No playlist matches {}", playlist));
+    }
+
+    playlists.sort();
+    Ok(playlists)
+}
 
-    Ok((base_dir, media_files))
+/// Keep the playlist files whose names match `regex`, replacing each
+/// directory by the playlist files directly in it, sorted by name
+fn match_playlists(playlists: Vec<String>, regex: &Regex) -> Result<Vec<String>> {
+    let mut matched = Vec::new();
+
+    for playlist in playlists {
+        let path = PathBuf::from(&playlist);
+        let candidates = match path.is_dir() {
+            true => {
+                let mut files: Vec<PathBuf> = fs::read_dir(&path)
+                    .map_err(|e| anyhow::anyhow!("Failed to read directory {}: {}", playlist, e))?
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|file| file.is_file() && is_playlist_file(file))
+                    .collect();
+                files.sort();
+                files
+            }
+            false => vec![path],
+        };
+
+        matched.extend(
+            candidates
+                .into_iter()
+                .filter(|file| {
+                    file.file_name()
+                        .is_some_and(|name| regex.is_match(&name.to_string_lossy()))
+                })
+                .map(|file| file.to_string_lossy().to_string()),
+        );
+    }
+
+    Ok(matched)
+}
+
+/// Playlist files given as arguments, with `-` replaced by those read
+/// from the standard input and wildcard patterns by the files matching
+/// them, followed by those read by `--playlists-from`, narrowed down by
+/// `--match-regex`
+fn collect_playlists(cli: &Cli) -> Result<Vec<String>> {
+    let mut playlists = Vec::new();
+
+    for playlist in &cli.playlists {
+        match playlist.as_str() {
+            "-" => playlists.extend(read_playlist_list("-")?),
+            _ => playlists.extend(expand_pattern(playlist)?),
+        }
+    }
+    if let Some(source) = &cli.playlists_from {
+        playlists.extend(read_playlist_list(source)?);
+    }
+
+    match &cli.match_regex {
+        Some(regex) => match_playlists(playlists, regex),
+        None => Ok(playlists),
+    }
 }
 
 /// Delete a playlist file
@@ -104,18 +187,87 @@ fn delete_playlist_file(playlist: &str, verbose: bool) -> Result<()> {
     Ok(())
 }
 
-/// Delete media files referenced in a playlist
+/// Whether the extension of the path is one of `extensions`
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    path.extension().is_some_and(|ext| {
+        extensions
+            .iter()
+            .any(|extension| ext.to_string_lossy().eq_ignore_ascii_case(extension))
+    })
+}
+
+/// Delete a lyrics file or other sidecar file of a media file if it
+/// exists, naming it as a file of `file_type` in messages
+///
+/// Returns the number of files deleted
+fn delete_sidecar_file(sidecar_path: &Path, file_type: &str, verbose: bool) -> Result<usize> {
+    if !sidecar_path.is_file() {
+        return Ok(0);
+    }
+    print_message(
+        verbose,
+        "Deleting {} file \"{}\"",
+        &[file_type, &sidecar_path.to_string_lossy()],
+    );
+    fs::remove_file(sidecar_path).with_context(|| {
+        format!("Failed to delete {} file: {}", file_type, sidecar_path.display())
+    })?;
+    Ok(1)
+}
+
+/// Delete the files with the sidecar extensions left in directories of
+/// deleted media files once no media file is left in them, such as the
+/// cover image or the booklet of an album, so that the directories can
+/// be pruned
+///
+/// Returns the number of files deleted
+fn delete_leftover_sidecars(
+    base_dir: &str,
+    dirs: &BTreeSet<PathBuf>,
+    extensions: &[String],
+    verbose: bool,
+) -> Result<usize> {
+    let mut n_files = 0;
+
+    for dir in dirs {
+        let dir = Path::new(base_dir).join(dir);
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        let paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect();
+        if paths.iter().any(|path| is_media_file(path)) {
+            continue;
+        }
+        for path in paths.iter().filter(|path| has_extension(path, extensions)) {
+            n_files += delete_sidecar_file(path, "sidecar", verbose)?;
+        }
+    }
+
+    Ok(n_files)
+}
+
+/// Delete media files referenced in a playlist, along with their lyrics
+/// files with the extensions `lyrics_extensions` and the sidecar files
+/// with the extensions `sidecars`
 fn delete_media_files(
     base_dir: &str,
     files: impl Iterator<Item = String>,
+    lyrics_extensions: &[String],
+    sidecars: &[String],
     verbose: bool,
 ) -> Result<usize> {
     let mut n_files = 0;
+    let mut dirs = BTreeSet::new();
 
     for file in files {
         let file_path = Path::new(&file);
         let dir_part = file_path.parent().unwrap_or(Path::new(""));
         let file_stem = file_path.file_stem().unwrap_or_default();
+        dirs.insert(dir_part.to_path_buf());
 
         let media_file = Path::new(base_dir).join(&file);
 
@@ -134,68 +286,78 @@ fn delete_media_files(
             eprintln!("Media file not found: {}", media_file.display());
         }
 
-        // Check for lyrics file with .lrc extension
-        let lyrics_filename = format!("{}.lrc", file_stem.to_string_lossy());
-        let lyrics_path = Path::new(base_dir).join(dir_part).join(&lyrics_filename);
-
-        if lyrics_path.exists() {
-            print_message(
-                verbose,
-                "Deleting lyrics file \"{}\"",
-                &[&lyrics_path.to_string_lossy()],
-            );
-
-            fs::remove_file(&lyrics_path)
-                .with_context(|| format!("Failed to delete lyrics file: {}", lyrics_path.display()))?;
-
-            n_files += 1;
+        // Delete the lyrics files and then the other sidecar files
+        // sharing the stem of the media file, each extension once
+        let lyrics = lyrics_extensions.iter().map(|ext| (ext, "lyrics"));
+        let others = sidecars
+            .iter()
+            .filter(|ext| {
+                !lyrics_extensions
+                    .iter()
+                    .any(|lyrics| lyrics.eq_ignore_ascii_case(ext))
+            })
+            .map(|ext| (ext, "sidecar"));
+        for (extension, file_type) in lyrics.chain(others) {
+            let sidecar_filename = format!("{}.{}", file_stem.to_string_lossy(), extension);
+            let sidecar_path = Path::new(base_dir).join(dir_part).join(&sidecar_filename);
+            n_files += delete_sidecar_file(&sidecar_path, file_type, verbose)?;
         }
     }
 
-    Ok(n_files)
-}
-
-/// Delete empty directories recursively
-fn delete_empty_dirs(dir: &Path, verbose: bool) -> Result<()> {
-    if !dir.exists() || !dir.is_dir() {
-        return Ok(());
+    if !sidecars.is_empty() {
+        n_files += delete_leftover_sidecars(base_dir, &dirs, sidecars, verbose)?;
     }
 
-    // First, recursively delete empty subdirectories
-    for entry in fs::read_dir(dir)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_dir() {
-            delete_empty_dirs(&path, verbose)?;
-        }
-    }
+    Ok(n_files)
+}
 
-    // Check if directory is now empty
-    let is_empty = fs::read_dir(dir)?.next().is_none();
+/// Read the post-sync actions of the device profile `file`
+fn read_device_profile(file: &str) -> Result<PostSyncActions> {
+    PostSyncActions::from_file(Path::new(file))
+        .map_err(|e| anyhow::anyhow!("Failed to read device profile {}: {}", file, e))
+}
 
-    if is_empty {
-        print_message(
-            verbose,
-            "Deleting empty directory \"{}\"",
-            &[&dir.to_string_lossy()],
-        );
+/// Run the post-sync actions in each directory the playlists were
+/// deleted from, the root of the device they were put into
+fn run_post_sync(actions: &PostSyncActions, playlists: &[String], verbose: bool) -> Result<()> {
+    let dirs: BTreeSet<&Path> = playlists
+        .iter()
+        .map(|playlist| match Path::new(playlist).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        })
+        .collect();
 
-        fs::remove_dir(dir)
-            .with_context(|| format!("Failed to delete directory: {}", dir.display()))?;
+    let logger = StderrLogger::new(verbose);
+    for dir in dirs {
+        actions.run(dir, &logger)?;
     }
-
     Ok(())
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let post_sync = match cli.post_sync.as_deref().map(read_device_profile).transpose() {
+        Ok(post_sync) => post_sync,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+    let playlists = match collect_playlists(&cli) {
+        Ok(playlists) => playlists,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
 
-    let mut media_files_map: Vec<(String, HashSet<String>)> = Vec::new();
+    let mut interner = PathInterner::new();
+    let mut media_set = MediaSet::new();
     let mut n_playlists = 0;
 
     // First, process all playlists and collect media files
-    for playlist in &cli.playlists {
+    for playlist in &playlists {
         print_message(
             cli.verbose,
             "Processing playlist \"{}\"",
@@ -203,23 +365,23 @@ fn main() -> Result<()> {
         );
 
         // Extract media files before deleting the playlist
-        match extract_media_files(playlist) {
-            Ok((base_dir, files)) => {
-                // Add to the media files map
-                let entry = media_files_map.iter_mut().find(|(base, _)| *base == base_dir);
-                if let Some((_, files_set)) = entry {
-                    // Add files to existing set
-                    for file in files {
-                        files_set.insert(file);
-                    }
-                } else {
-                    // Create new entry
-                    let mut files_set = HashSet::new();
-                    for file in files {
-                        files_set.insert(file);
-                    }
-                    media_files_map.push((base_dir, files_set));
+        match read_playlist_media(Path::new(playlist), &mut interner)
+            .with_context(|| format!("Failed to open playlist: {}", playlist))
+            .and_then(|mut media| {
+                if let Some(dir) = &cli.relative_to {
+                    media.src_root = interner.intern(dir);
                 }
+                if cli.expand_dirs {
+                    media.expand_directories(&mut interner).map_err(|e| {
+                        anyhow::anyhow!("Failed to expand directories: {}", e)
+                    })?;
+                }
+                Ok(media)
+            })
+        {
+            Ok(media) => {
+                // Add to the unique media set
+                media_set.add_playlist(&media);
 
                 // Delete the playlist file
                 match delete_playlist_file(playlist, cli.verbose) {
@@ -246,11 +408,16 @@ fn main() -> Result<()> {
         print_message(
             cli.verbose,
             "Deleting {} unique media files",
-            &[&media_files_map.iter().map(|(_, files)| files.len()).sum::<usize>().to_string()],
+            &[&media_set.len().to_string()],
         );
 
-        for (base_dir, files) in media_files_map {
-            match delete_media_files(&base_dir, files.into_iter(), cli.verbose) {
+        for base_dir in media_set.roots() {
+            let files = media_set
+                .iter()
+                .filter(|(src_root, _)| src_root == base_dir)
+                .map(|(_, file)| file.to_string());
+
+            match delete_media_files(base_dir, files, &cli.lyrics_ext, &cli.sidecar, cli.verbose) {
                 Ok(files_deleted) => {
                     n_files += files_deleted;
                 }
@@ -261,8 +428,9 @@ fn main() -> Result<()> {
             }
 
             // Delete empty directories
-            let base_dir_path = Path::new(&base_dir);
-            if let Err(e) = delete_empty_dirs(base_dir_path, cli.verbose) {
+            let base_dir_path = Path::new(&**base_dir);
+            let logger = StderrLogger::new(cli.verbose);
+            if let Err(e) = prune_empty_dirs(base_dir_path, &PruneOptions::new(), &logger) {
                 eprintln!("Error deleting empty directories: {}", e);
                 // Continue execution even if directory deletion fails
             }
@@ -273,5 +441,12 @@ fn main() -> Result<()> {
         println!("Number of deleted files: {}", n_files);
     }
 
+    if let Some(actions) = &post_sync {
+        if let Err(e) = run_post_sync(actions, &playlists, cli.verbose) {
+            eprintln!("Error running post-sync actions: {}", e);
+            process::exit(1);
+        }
+    }
+
     Ok(())
 }
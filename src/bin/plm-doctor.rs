@@ -0,0 +1,132 @@
+use std::env;
+use std::path::Path;
+use std::process;
+
+use anyhow::Result;
+use clap::{ArgAction, Parser};
+use playlist_manager::diagnostics::{check_commands, check_destination, Finding, Severity};
+use playlist_manager::sync_state::SyncState;
+
+/// Commands run by `plm`, expected next to this one
+const COMMANDS: &[&str] = &[
+    "plm-put-playlist",
+    "plm-delete-playlist",
+    "plm-remove-track",
+    "plm-add-track",
+    "plm-reorder",
+    "plm-prune",
+    "plm-tracks",
+    "plm-which",
+    "plm-backup",
+    "plm-restore",
+    "plm-get-playlist",
+    "plm-sync",
+    "plm-gc",
+    "plm-list-playlist",
+    "plm-fsck",
+    "plm-mv",
+    "plm-new-playlist",
+    "plm-verify-playlist",
+    "plm-generate",
+];
+
+#[derive(Parser)]
+#[command(name = "plm-doctor")]
+#[command(about = "Check the installation and a destination for problems syncing playlists")]
+#[command(version)]
+struct Cli {
+    /// Print the checks passed as well as the problems found
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Destination directory to check, such as the mount point of a device
+    dest: Option<String>,
+}
+
+/// Finding on the sync state kept on the destination, if any
+fn check_sync_state(dest: &Path) -> Option<Finding> {
+    if !SyncState::path(dest).exists() {
+        return None;
+    }
+
+    let finding = match SyncState::load(dest) {
+        Ok(state) => Finding::new(
+            "sync state",
+            Severity::Ok,
+            format!("Sync state of {} files", state.files().count()),
+        ),
+        Err(e) => Finding::new(
+            "sync state",
+            Severity::Error,
+            format!(
+                "Invalid sync state {}: {}",
+                SyncState::path(dest).display(),
+                e
+            ),
+        )
+        .with_advice("Delete the file to sync every media file again with --sync-state"),
+    };
+    Some(finding)
+}
+
+/// Run the checks of the installation and of the destination if given
+fn diagnose(cli: &Cli) -> Result<Vec<Finding>> {
+    let exe =
+        env::current_exe().map_err(|e| anyhow::anyhow!("Failed to locate plm-doctor: {}", e))?;
+    let exe_dir = exe.parent().unwrap_or(Path::new("."));
+    let mut findings = check_commands(exe_dir, COMMANDS);
+
+    if let Some(dest) = &cli.dest {
+        let dest = Path::new(dest);
+        findings.extend(check_destination(dest));
+        findings.extend(check_sync_state(dest));
+    }
+
+    Ok(findings)
+}
+
+/// Print a finding with its advice indented below it
+fn print_finding(finding: &Finding) {
+    println!(
+        "[{}] {}: {}",
+        finding.severity, finding.check, finding.message
+    );
+    if let Some(advice) = &finding.advice {
+        println!("    {}", advice);
+    }
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let findings = match diagnose(&cli) {
+        Ok(findings) => findings,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    for finding in &findings {
+        if cli.verbose || finding.severity != Severity::Ok {
+            print_finding(finding);
+        }
+    }
+
+    let n_problems = findings
+        .iter()
+        .filter(|finding| finding.severity >= Severity::Warning)
+        .count();
+    match n_problems {
+        0 => println!("No problems found"),
+        n => println!("Number of problems found: {}", n),
+    }
+    if findings
+        .iter()
+        .any(|finding| finding.severity == Severity::Error)
+    {
+        process::exit(1);
+    }
+
+    Ok(())
+}
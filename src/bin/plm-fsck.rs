@@ -0,0 +1,331 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Parser};
+use playlist_manager::fat_order::{sort_directory, SORT_DIR_NAME};
+use playlist_manager::playlist_encoding::PlaylistEncoding;
+use playlist_manager::playlist_file::is_playlist_file;
+use playlist_manager::playlist_scanner::parse_playlist;
+use playlist_manager::sync_state::{SyncState, STATE_DIR};
+use playlist_manager::track_path::TrackPath;
+use playlist_manager::xspf::{is_xspf_file, XspfPlaylist};
+use thiserror::Error;
+
+/// Suffix of the backups kept by `plm-put-playlist --backup` beside the
+/// files they replace
+const BACKUP_SUFFIX: &str = ".plm-bak";
+
+/// Byte order mark starting the UTF-16LE playlists of `--dest-encoding`
+const UTF16LE_BOM: [u8; 2] = [0xff, 0xfe];
+
+#[derive(Parser)]
+#[command(name = "plm-fsck")]
+#[command(about = "Check the playlists and the sync state of a device")]
+#[command(version)]
+struct Cli {
+    /// Print the playlists checked
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Repair the problems left by interrupted runs
+    #[arg(long = "fix", action = ArgAction::SetTrue)]
+    fix: bool,
+
+    /// With --fix, rename orphaned backups back to the files they replace instead of deleting them
+    #[arg(long = "restore-backups", action = ArgAction::SetTrue, requires = "fix")]
+    restore_backups: bool,
+
+    /// Encoding of the playlists written with --dest-encoding (utf8 or cp932; UTF-16LE is detected)
+    #[arg(long = "encoding", value_name = "ENCODING", default_value = "utf8")]
+    encoding: PlaylistEncoding,
+
+    /// Destination to check
+    dest: String,
+}
+
+#[derive(Error, Debug)]
+enum AppError {
+    #[error("Not a directory: {0}")]
+    NotADirectory(String),
+}
+
+/// Inconsistency found on the destination
+#[derive(Debug, PartialEq, Eq)]
+enum Problem {
+    /// Playlist that cannot be read or decoded
+    UnreadablePlaylist { playlist: PathBuf, error: String },
+    /// Playlist entry naming no file on the destination
+    MissingEntry { playlist: PathBuf, entry: String },
+    /// State file that cannot be read
+    InvalidState { error: String },
+    /// File recorded in the state file but gone from the destination
+    StaleState { dest_file: String },
+    /// State file left half written by an interrupted run
+    PartialState { path: PathBuf },
+    /// Directory whose files were being reordered by `--fat-order`
+    InterruptedSort { dir: PathBuf },
+    /// Backup of a file that is gone, as when copying over it failed
+    OrphanedBackup { backup: PathBuf, original: PathBuf },
+}
+
+impl Problem {
+    /// Line printed for the problem
+    fn describe(&self) -> String {
+        match self {
+            Self::UnreadablePlaylist { playlist, error } => {
+                format!("unreadable playlist: {}: {}", playlist.display(), error)
+            }
+            Self::MissingEntry { playlist, entry } => {
+                format!("missing entry: {}: {}", playlist.display(), entry)
+            }
+            Self::InvalidState { error } => format!("invalid state file: {}", error),
+            Self::StaleState { dest_file } => format!("stale state entry: {}", dest_file),
+            Self::PartialState { path } => format!("partial state file: {}", path.display()),
+            Self::InterruptedSort { dir } => format!("interrupted sort: {}", dir.display()),
+            Self::OrphanedBackup { backup, .. } => {
+                format!("orphaned backup: {}", backup.display())
+            }
+        }
+    }
+
+    /// Repair the problem, returning `false` if it cannot be repaired
+    ///
+    /// Orphaned backups are deleted, or renamed back to the files they
+    /// replace with `restore_backups`.  Stale state entries are repaired
+    /// together by [`forget_stale`].
+    fn fix(&self, restore_backups: bool) -> Result<bool> {
+        match self {
+            Self::PartialState { path } => fs::remove_file(path)
+                .with_context(|| format!("Failed to remove {}", path.display()))?,
+            Self::InterruptedSort { dir } => {
+                let parent = dir.parent().unwrap_or(dir);
+                sort_directory(parent)
+                    .with_context(|| format!("Failed to finish sorting {}", parent.display()))?;
+            }
+            Self::OrphanedBackup { backup, original } if restore_backups => {
+                fs::rename(backup, original)
+                    .with_context(|| format!("Failed to restore {}", backup.display()))?
+            }
+            Self::OrphanedBackup { backup, .. } => fs::remove_file(backup)
+                .with_context(|| format!("Failed to remove {}", backup.display()))?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+/// Whether the file at `path` exists, or would once the interrupted sort
+/// of its directory is finished or its orphaned backup restored
+fn exists_or_recoverable(path: &Path) -> bool {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return path.exists();
+    };
+    let mut backup = name.to_os_string();
+    backup.push(BACKUP_SUFFIX);
+    path.exists() || dir.join(SORT_DIR_NAME).join(name).exists() || dir.join(backup).is_file()
+}
+
+/// Contents of a playlist on the destination, decoded from `encoding`
+/// or from UTF-16LE if it starts with its byte order mark
+fn read_playlist(path: &Path, encoding: PlaylistEncoding) -> Result<String> {
+    let bytes = fs::read(path)?;
+    if bytes.starts_with(&UTF16LE_BOM) {
+        return Ok(PlaylistEncoding::Utf16Le.decode(&bytes));
+    }
+    match encoding {
+        PlaylistEncoding::Utf8 => Ok(String::from_utf8(bytes)
+            .map_err(|_| anyhow::anyhow!("Not valid UTF-8 (try --encoding)"))?),
+        encoding => Ok(encoding.decode(&bytes)),
+    }
+}
+
+/// Check that a playlist can be read and that each of its entries names
+/// a file or directory on the destination
+///
+/// Files of an interrupted sort or with an orphaned backup are reported
+/// as those problems rather than as missing.
+fn check_playlist(playlist: &Path, cli: &Cli, problems: &mut Vec<Problem>) {
+    if cli.verbose {
+        eprintln!("Checking playlist \"{}\"", playlist.display());
+    }
+    let unreadable = |error: String| Problem::UnreadablePlaylist {
+        playlist: playlist.to_path_buf(),
+        error,
+    };
+    let entries: Vec<String> = if is_xspf_file(playlist) {
+        match XspfPlaylist::read(playlist) {
+            Ok(xspf) => xspf
+                .entries()
+                .map(|entry| TrackPath::new(entry).into_string())
+                .collect(),
+            Err(e) => return problems.push(unreadable(e.to_string())),
+        }
+    } else {
+        match read_playlist(playlist, cli.encoding) {
+            Ok(content) => parse_playlist(&content).collect(),
+            Err(e) => return problems.push(unreadable(e.to_string())),
+        }
+    };
+
+    let playlist_dir = playlist.parent().unwrap_or(Path::new("."));
+    for entry in entries {
+        if !exists_or_recoverable(&playlist_dir.join(&entry)) {
+            problems.push(Problem::MissingEntry {
+                playlist: playlist.to_path_buf(),
+                entry,
+            });
+        }
+    }
+}
+
+/// Check the playlists under `dir` and look for the files left by
+/// interrupted runs, in the order of their names
+///
+/// The state directory, the trash and other directories whose names
+/// start with a dot are left alone.
+fn scan_dir(dir: &Path, cli: &Cli, problems: &mut Vec<Problem>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_dir() {
+            if name == SORT_DIR_NAME {
+                problems.push(Problem::InterruptedSort { dir: path });
+            } else if !name.starts_with('.') {
+                scan_dir(&path, cli, problems)?;
+            }
+        } else if let Some(original) = name.strip_suffix(BACKUP_SUFFIX) {
+            let original = path.with_file_name(original);
+            if !original.exists() {
+                problems.push(Problem::OrphanedBackup {
+                    backup: path,
+                    original,
+                });
+            }
+        } else if is_playlist_file(&path) || is_xspf_file(&path) {
+            check_playlist(&path, cli, problems);
+        }
+    }
+    Ok(())
+}
+
+/// Check the state file of the destination, whose entries must name
+/// files on the destination
+fn check_state(dest: &Path, problems: &mut Vec<Problem>) {
+    let partial = SyncState::path(dest).with_extension("json.partial");
+    if partial.exists() {
+        problems.push(Problem::PartialState { path: partial });
+    }
+
+    match SyncState::load(dest) {
+        Ok(state) => problems.extend(
+            state
+                .files()
+                .filter(|(dest_file, _)| !exists_or_recoverable(&dest.join(dest_file)))
+                .map(|(dest_file, _)| Problem::StaleState {
+                    dest_file: dest_file.to_string(),
+                }),
+        ),
+        Err(e) => problems.push(Problem::InvalidState {
+            error: format!("{}: {}", SyncState::path(dest).display(), e),
+        }),
+    }
+}
+
+/// Forget the stale entries of the state file, returning their number
+fn forget_stale(dest: &Path, problems: &[Problem]) -> Result<usize> {
+    let stale: Vec<&str> = problems
+        .iter()
+        .filter_map(|problem| match problem {
+            Problem::StaleState { dest_file } => Some(dest_file.as_str()),
+            _ => None,
+        })
+        .collect();
+    if stale.is_empty() {
+        return Ok(0);
+    }
+
+    let mut state = SyncState::load(dest)?;
+    for dest_file in &stale {
+        state.forget(dest_file);
+    }
+    state
+        .save(dest)
+        .with_context(|| format!("Failed to write {}", SyncState::path(dest).display()))?;
+    Ok(stale.len())
+}
+
+/// Check the destination, repairing the problems found with `--fix`,
+/// and return the number of problems left
+fn fsck(cli: &Cli) -> Result<usize> {
+    let dest = Path::new(&cli.dest);
+    if !dest.is_dir() {
+        return Err(AppError::NotADirectory(cli.dest.clone()).into());
+    }
+
+    let mut problems = Vec::new();
+    scan_dir(dest, cli, &mut problems)?;
+    if dest.join(STATE_DIR).is_dir() {
+        check_state(dest, &mut problems);
+    }
+
+    let mut fixed = vec![false; problems.len()];
+    if cli.fix {
+        for (problem, fixed) in problems.iter().zip(fixed.iter_mut()) {
+            *fixed = problem.fix(cli.restore_backups).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                false
+            });
+        }
+        // Saving the state replaces any partial state file, so the stale
+        // entries are forgotten last
+        let stale_fixed = forget_stale(dest, &problems).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            0
+        });
+        for (problem, fixed) in problems.iter().zip(fixed.iter_mut()) {
+            if let Problem::StaleState { .. } = problem {
+                *fixed = stale_fixed > 0;
+            }
+        }
+    }
+
+    for (problem, fixed) in problems.iter().zip(&fixed) {
+        match fixed {
+            true => println!("{} (fixed)", problem.describe()),
+            false => println!("{}", problem.describe()),
+        }
+    }
+    let n_fixed = fixed.iter().filter(|fixed| **fixed).count();
+
+    println!("({}) problems found", problems.len());
+    if cli.fix {
+        println!("({}) problems fixed", n_fixed);
+    }
+    Ok(problems.len() - n_fixed)
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    match fsck(&cli) {
+        Ok(0) => {}
+        Ok(_) => process::exit(1),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            match e.downcast_ref::<AppError>() {
+                Some(_) => process::exit(255),
+                None => process::exit(2),
+            }
+        }
+    }
+
+    Ok(())
+}
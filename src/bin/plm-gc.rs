@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Parser};
+use playlist_manager::trash::{expired_trash_folders, parse_age, today};
+use thiserror::Error;
+
+#[derive(Parser)]
+#[command(name = "plm-gc")]
+#[command(about = "Delete files kept on device for a while for good")]
+#[command(version)]
+struct Cli {
+    /// Print the trash folders deleted
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Print the trash folders to delete without deleting them
+    #[arg(short = 'n', long = "dry-run", action = ArgAction::SetTrue)]
+    dry_run: bool,
+
+    /// Delete the trash folders of the files moved into the trash by plm-sync --delete
+    #[arg(long = "empty-trash", action = ArgAction::SetTrue)]
+    empty_trash: bool,
+
+    /// Delete only the trash folders more than AGE old, such as 30d or 2w
+    #[arg(long = "older-than", value_name = "AGE", value_parser = parse_age)]
+    older_than: Option<u64>,
+
+    /// Destination to delete the files from
+    dest: String,
+}
+
+#[derive(Error, Debug)]
+enum AppError {
+    #[error("Not a directory: {0}")]
+    NotADirectory(String),
+    #[error("Nothing to delete: give --empty-trash")]
+    NothingToDelete,
+}
+
+/// Delete the trash folders of the destination more than `--older-than`
+/// old, returning the number of folders deleted
+fn empty_trash(cli: &Cli) -> Result<usize> {
+    let dest = Path::new(&cli.dest);
+    let folders = expired_trash_folders(dest, cli.older_than, today())
+        .with_context(|| format!("Failed to read trash of {}", cli.dest))?;
+
+    for folder in &folders {
+        if cli.dry_run {
+            println!("{}", folder.display());
+            continue;
+        }
+        if cli.verbose {
+            eprintln!("Deleting trash folder \"{}\"", folder.display());
+        }
+        fs::remove_dir_all(folder)
+            .with_context(|| format!("Failed to delete trash folder: {}", folder.display()))?;
+    }
+
+    Ok(folders.len())
+}
+
+/// Delete the files kept on the destination as requested
+fn gc(cli: &Cli) -> Result<()> {
+    if !cli.empty_trash {
+        return Err(AppError::NothingToDelete.into());
+    }
+    if !Path::new(&cli.dest).is_dir() {
+        return Err(AppError::NotADirectory(cli.dest.clone()).into());
+    }
+
+    let n_folders = empty_trash(cli)?;
+    if !cli.dry_run {
+        println!("({}) trash folders deleted", n_folders);
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    if let Err(e) = gc(&cli) {
+        eprintln!("Error: {}", e);
+        match e.downcast_ref::<AppError>() {
+            Some(_) => process::exit(255),
+            None => process::exit(1),
+        }
+    }
+
+    Ok(())
+}
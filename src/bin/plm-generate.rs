@@ -0,0 +1,106 @@
+use std::path::Path;
+use std::process;
+
+use anyhow::Result;
+use clap::{ArgAction, Parser};
+use playlist_manager::library_playlists::{
+    days_ago, group_library, recently_added, GroupBy, GroupSource,
+};
+use playlist_manager::trash::parse_age;
+use thiserror::Error;
+
+#[derive(Parser)]
+#[command(name = "plm-generate")]
+#[command(
+    about = "Generate a playlist per artist or genre, or of the recently added tracks, of a library"
+)]
+#[command(version)]
+struct Cli {
+    /// Print verbose messages
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Group the tracks by GROUP: artist or genre
+    #[arg(
+        short = 'g',
+        long = "group-by",
+        value_name = "GROUP",
+        required_unless_present = "added_since",
+        conflicts_with = "added_since"
+    )]
+    group_by: Option<GroupBy>,
+
+    /// Write a single playlist of the tracks modified within AGE, such as 30d or 2w, newest first
+    #[arg(long = "added-since", value_name = "AGE", value_parser = parse_age)]
+    added_since: Option<u64>,
+
+    /// Read the group of each track from SOURCE: tags, or dirs for the top-level directories
+    #[arg(long = "from", value_name = "SOURCE", default_value = "tags")]
+    from: GroupSource,
+
+    /// Directory to write the playlists into
+    #[arg(short = 'o', long = "output", value_name = "OUTDIR")]
+    output: String,
+
+    /// Library directory to scan for media files
+    library_dir: String,
+}
+
+#[derive(Error, Debug)]
+enum AppError {
+    #[error("Not a directory: {0}")]
+    NotADirectory(String),
+}
+
+/// Write a playlist per group of the tracks of the library
+/// Returns the number of written playlists
+fn generate(cli: &Cli) -> Result<usize> {
+    let library_dir = Path::new(&cli.library_dir);
+    if !library_dir.is_dir() {
+        return Err(AppError::NotADirectory(cli.library_dir.clone()).into());
+    }
+
+    let playlists = match (cli.added_since, cli.group_by) {
+        (Some(days), _) => recently_added(library_dir, days_ago(days)),
+        (None, Some(group_by)) => group_library(library_dir, group_by, cli.from),
+        (None, None) => unreachable!("--group-by or --added-since is required"),
+    }
+    .map_err(|e| anyhow::anyhow!("Failed to scan library {}: {}", cli.library_dir, e))?;
+    if cli.verbose {
+        for track in &playlists.ungrouped {
+            eprintln!("No group for track \"{}\"", track);
+        }
+    }
+
+    let written = playlists
+        .write(library_dir, Path::new(&cli.output))
+        .map_err(|e| anyhow::anyhow!("Failed to write playlists into {}: {}", cli.output, e))?;
+    if cli.verbose {
+        for playlist in &written {
+            eprintln!("Generated playlist \"{}\"", playlist.display());
+        }
+    }
+
+    Ok(written.len())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    match generate(&cli) {
+        Ok(n_playlists) => {
+            if cli.verbose {
+                println!("Number of generated playlists: {}", n_playlists);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            match e.downcast_ref::<AppError>() {
+                Some(_) => process::exit(255),
+                None => process::exit(1),
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,332 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Parser};
+use playlist_manager::error_file::{ErrorEntry, ErrorFileWriter, RunTotals};
+use playlist_manager::file_utils::copy_file;
+use playlist_manager::playlist_file::PlaylistFile;
+use playlist_manager::run_id::new_run_id;
+use playlist_manager::track_matcher::{absolute_path, playlist_dir, relative_entry};
+
+/// Extension of lyrics files
+const LYRICS_EXTENSION: &str = "lrc";
+
+#[derive(Parser)]
+#[command(name = "plm-get-playlist")]
+#[command(about = "Copy playlist files and associated media files from device back to library")]
+#[command(version)]
+struct Cli {
+    /// Print verbose messages
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Copy lyrics files (.lrc) along with media files
+    #[arg(short = 'l', long = "lyrics", action = ArgAction::SetTrue)]
+    lyrics: bool,
+
+    /// Skip playlists and media files that cannot be copied instead of failing
+    #[arg(short = 'k', long = "keep-going", action = ArgAction::SetTrue)]
+    keep_going: bool,
+
+    /// Write list of failed files to FILE (requires --keep-going)
+    #[arg(short = 'e', long = "error-files", value_name = "FILE")]
+    error_files: Option<String>,
+
+    /// Library directory to copy playlists and media files into
+    dest: String,
+
+    /// Playlist files on the device to copy
+    #[arg(required = true)]
+    playlists: Vec<String>,
+}
+
+/// A playlist on the device resolved to its media files
+struct ResolvedPlaylist {
+    name: String,                   // File name of the playlist in the library
+    tracks: Vec<(String, PathBuf)>, // Paths in the library and on the device
+}
+
+/// Numbers of the files copied out of those to copy
+#[derive(Debug, Default)]
+struct GetCounts {
+    successful_playlists: usize,
+    total_playlists: usize,
+    successful_media_files: usize,
+    total_media_files: usize,
+    successful_lyrics_files: usize,
+    total_lyrics_files: usize,
+}
+
+/// Copy in progress into the library
+struct Get<'a> {
+    cli: &'a Cli,
+    dest: PathBuf,
+    errors: Option<ErrorFileWriter<File>>,
+    sources: HashMap<String, PathBuf>, // Source of each media file taken so far
+    counts: GetCounts,
+}
+
+impl<'a> Get<'a> {
+    /// Report a failure, recording it in the error file with
+    /// `--keep-going` and failing without it
+    fn fail(&mut self, entry: ErrorEntry, e: anyhow::Error) -> Result<()> {
+        if !self.cli.keep_going {
+            return Err(e);
+        }
+        eprintln!("Error: {}", e);
+        if let Some(errors) = &mut self.errors {
+            errors
+                .write_entry(&entry)
+                .context("Failed to write error log file")?;
+        }
+        Ok(())
+    }
+
+    /// Copy the file at `src` to `name` in the library unless it is up
+    /// to date there
+    fn copy(&self, src: &Path, name: &str) -> Result<()> {
+        let dest_path = self.dest.join(name);
+        if is_up_to_date(src, &dest_path) {
+            if self.cli.verbose {
+                eprintln!("Skip up-to-date \"{}\"", dest_path.display());
+            }
+            return Ok(());
+        }
+        if self.cli.verbose {
+            eprintln!("Copy \"{}\" to \"{}\"", src.display(), dest_path.display());
+        }
+        copy_file(src, &dest_path).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to copy {} to {}: {}",
+                src.display(),
+                dest_path.display(),
+                e
+            )
+        })
+    }
+
+    /// Copy a resolved playlist and its media files, and their lyrics
+    /// files with `--lyrics`
+    fn get_playlist(&mut self, playlist: &Path, resolved: &ResolvedPlaylist) -> Result<()> {
+        match self.copy(playlist, &resolved.name) {
+            Ok(()) => self.counts.successful_playlists += 1,
+            Err(e) => self.fail(ErrorEntry::Playlist(playlist.to_path_buf()), e)?,
+        }
+
+        for (name, track) in &resolved.tracks {
+            let source = absolute_path(track)?;
+            match self.sources.get(name) {
+                Some(other) if *other == source => continue,
+                Some(other) => {
+                    let e = anyhow::anyhow!(
+                        "Library path {} of {} is taken by {}",
+                        name,
+                        track.display(),
+                        other.display()
+                    );
+                    self.counts.total_media_files += 1;
+                    self.fail(ErrorEntry::Media(track.clone()), e)?;
+                    continue;
+                }
+                None => self.sources.insert(name.clone(), source),
+            };
+            self.counts.total_media_files += 1;
+            if let Err(e) = self.copy(track, name) {
+                self.fail(ErrorEntry::Media(track.clone()), e)?;
+                continue;
+            }
+            self.counts.successful_media_files += 1;
+
+            let lyrics = track.with_extension(LYRICS_EXTENSION);
+            if !self.cli.lyrics || !lyrics.is_file() {
+                continue;
+            }
+            self.counts.total_lyrics_files += 1;
+            let lyrics_name = Path::new(name).with_extension(LYRICS_EXTENSION);
+            match self.copy(&lyrics, &lyrics_name.to_string_lossy()) {
+                Ok(()) => self.counts.successful_lyrics_files += 1,
+                Err(e) => self.fail(ErrorEntry::Lyrics(lyrics), e)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the file at `dest_path` has the size of the file at `src`
+/// and is not older than it
+fn is_up_to_date(src: &Path, dest_path: &Path) -> bool {
+    let (Ok(src), Ok(dest)) = (fs::metadata(src), fs::metadata(dest_path)) else {
+        return false;
+    };
+    match (src.modified(), dest.modified()) {
+        (Ok(src_time), Ok(dest_time)) => src.len() == dest.len() && dest_time >= src_time,
+        _ => false,
+    }
+}
+
+/// Resolve the entries of a playlist to the paths of its media files in
+/// the library, relative to the directory of the playlist placed at the
+/// root of the library
+///
+/// Fails if an entry is outside the directory of the playlist, or if the
+/// name of the playlist is taken by another playlist.
+fn resolve_playlist(
+    playlist: &Path,
+    names: &mut HashMap<String, PathBuf>,
+) -> Result<ResolvedPlaylist> {
+    let playlist_file = PlaylistFile::read(playlist)
+        .with_context(|| format!("Failed to read playlist: {}", playlist.display()))?;
+    let dir = playlist_dir(playlist);
+
+    let mut tracks = Vec::new();
+    for entry in playlist_file.entries() {
+        let track = dir.join(&entry);
+        let name = relative_entry(&track, &dir)?
+            .ok_or_else(|| anyhow::anyhow!("{} is not under {}", entry, dir.display()))?;
+        tracks.push((name, track));
+    }
+
+    let name = playlist
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow::anyhow!("Not a playlist file: {}", playlist.display()))?;
+    let source = absolute_path(playlist)?;
+    match names.get(&name) {
+        Some(other) if *other != source => anyhow::bail!(
+            "Library path {} of {} is taken by {}",
+            name,
+            playlist.display(),
+            other.display()
+        ),
+        _ => names.insert(name.clone(), source),
+    };
+
+    Ok(ResolvedPlaylist { name, tracks })
+}
+
+/// Copy the playlists and their media files into the library
+fn get_playlists(cli: &Cli, errors: Option<ErrorFileWriter<File>>) -> Result<GetCounts> {
+    let mut names = HashMap::new();
+    let resolved: Vec<(&Path, Result<ResolvedPlaylist>)> = cli
+        .playlists
+        .iter()
+        .map(|playlist| {
+            let playlist = Path::new(playlist);
+            (playlist, resolve_playlist(playlist, &mut names))
+        })
+        .collect();
+
+    let mut get = Get {
+        cli,
+        dest: PathBuf::from(&cli.dest),
+        errors,
+        sources: HashMap::new(),
+        counts: GetCounts {
+            total_playlists: resolved.len(),
+            ..GetCounts::default()
+        },
+    };
+    if let Some(errors) = &mut get.errors {
+        let media_files: HashSet<PathBuf> = resolved
+            .iter()
+            .filter_map(|(_, resolved)| resolved.as_ref().ok())
+            .flat_map(|resolved| &resolved.tracks)
+            .filter_map(|(_, track)| absolute_path(track).ok())
+            .collect();
+        errors
+            .set_totals(RunTotals {
+                playlists: resolved.len(),
+                media_files: media_files.len(),
+            })
+            .context("Failed to write error log file")?;
+    }
+
+    for (playlist, resolved) in resolved {
+        match resolved {
+            Ok(resolved) => get.get_playlist(playlist, &resolved)?,
+            Err(e) => get.fail(ErrorEntry::Playlist(playlist.to_path_buf()), e)?,
+        }
+    }
+
+    if let Some(errors) = get.errors {
+        errors
+            .into_inner()
+            .sync_all()
+            .context("Failed to write error log file")?;
+    }
+    Ok(get.counts)
+}
+
+/// Print the numbers of the files copied
+fn print_summary(counts: &GetCounts) {
+    println!(
+        "({}/{}) playlist copied",
+        counts.successful_playlists, counts.total_playlists
+    );
+    println!(
+        "({}/{}) media files copied",
+        counts.successful_media_files, counts.total_media_files
+    );
+    if counts.total_lyrics_files > 0 {
+        println!(
+            "({}/{}) lyrics files copied",
+            counts.successful_lyrics_files, counts.total_lyrics_files
+        );
+    }
+}
+
+/// Check the command line
+fn handle_arguments(cli: &Cli) -> Result<()> {
+    if cli.error_files.is_some() && !cli.keep_going {
+        anyhow::bail!("--error-files can only be used with --keep-going");
+    }
+    if !Path::new(&cli.dest).is_dir() {
+        anyhow::bail!("Destination directory not found: {}", cli.dest);
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    if let Err(e) = handle_arguments(&cli) {
+        eprintln!("Error: {}", e);
+        process::exit(255); // Argument/validation error
+    }
+
+    let run_id = new_run_id();
+    let errors = match &cli.error_files {
+        Some(path) => match ErrorFileWriter::create(Path::new(path)).and_then(|mut errors| {
+            errors.set_run_id(&run_id)?;
+            Ok(errors)
+        }) {
+            Ok(errors) => Some(errors),
+            Err(e) => {
+                eprintln!("Error: Failed to create error log file {}: {}", path, e);
+                process::exit(2);
+            }
+        },
+        None => None,
+    };
+
+    match get_playlists(&cli, errors) {
+        Ok(counts) => {
+            print_summary(&counts);
+            println!("Run ID: {}", run_id);
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            // Exit code 2 for error file issues, 1 for failed copies
+            if e.to_string().contains("Failed to write error log file") {
+                process::exit(2);
+            }
+            process::exit(1);
+        }
+    }
+
+    Ok(())
+}
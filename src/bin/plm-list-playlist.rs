@@ -0,0 +1,207 @@
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::Result;
+use clap::{ArgAction, Parser};
+use playlist_manager::media_set::read_playlist_media;
+use playlist_manager::path_interner::PathInterner;
+use playlist_manager::track_matcher::absolute_path;
+use serde_json::{json, Value};
+
+/// Extension of the lyrics file of a media file
+const LYRICS_EXTENSION: &str = "lrc";
+
+#[derive(Parser)]
+#[command(name = "plm-list-playlist")]
+#[command(about = "Print the tracks of playlist files with whether their files exist")]
+#[command(version)]
+struct Cli {
+    /// Print verbose messages
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Print the playlists as JSON
+    #[arg(long = "json", action = ArgAction::SetTrue)]
+    json: bool,
+
+    /// Expand playlist entries naming directories into the media files in them
+    #[arg(long = "expand-dirs", action = ArgAction::SetTrue)]
+    expand_dirs: bool,
+
+    /// Playlist file(s) to list
+    #[arg(required = true)]
+    playlists: Vec<String>,
+}
+
+/// Track of a playlist, with whether its media and lyrics files exist
+struct Track {
+    path: PathBuf,
+    exists: bool,
+    lyrics: bool,
+}
+
+/// Numbers of tracks, of those found and of those with lyrics
+#[derive(Default)]
+struct Totals {
+    playlists: usize,
+    tracks: usize,
+    found: usize,
+    lyrics: usize,
+}
+
+impl Totals {
+    /// Count the tracks of a playlist
+    fn add(&mut self, tracks: &[Track]) {
+        self.playlists += 1;
+        self.tracks += tracks.len();
+        self.found += tracks.iter().filter(|track| track.exists).count();
+        self.lyrics += tracks.iter().filter(|track| track.lyrics).count();
+    }
+
+    /// The totals as a line of text
+    fn describe(&self) -> String {
+        format!(
+            "{} tracks, {} found, {} missing, {} with lyrics",
+            self.tracks,
+            self.found,
+            self.tracks - self.found,
+            self.lyrics
+        )
+    }
+
+    /// The totals as a JSON object
+    fn to_json(&self) -> Value {
+        json!({
+            "tracks": self.tracks,
+            "found": self.found,
+            "missing": self.tracks - self.found,
+            "lyrics": self.lyrics,
+        })
+    }
+}
+
+/// Tracks of a playlist in playlist order, with their absolute paths
+fn playlist_tracks(playlist: &str, expand_dirs: bool) -> Result<Vec<Track>> {
+    let mut interner = PathInterner::new();
+    let mut media = read_playlist_media(Path::new(playlist), &mut interner)
+        .map_err(|e| anyhow::anyhow!("Failed to open playlist {}: {}", playlist, e))?;
+    if expand_dirs {
+        media
+            .expand_directories(&mut interner)
+            .map_err(|e| anyhow::anyhow!("Failed to expand directories: {}", e))?;
+    }
+
+    let src_root = Path::new(&*media.src_root);
+    media
+        .files
+        .iter()
+        .map(|file| {
+            let path = absolute_path(&src_root.join(&**file))
+                .map_err(|e| anyhow::anyhow!("Failed to resolve track {}: {}", file, e))?;
+            Ok(Track {
+                exists: path.is_file(),
+                lyrics: path.with_extension(LYRICS_EXTENSION).is_file(),
+                path,
+            })
+        })
+        .collect()
+}
+
+/// Print a playlist and its tracks as text to `out`
+fn print_playlist(playlist: &Path, tracks: &[Track], out: &mut impl Write) -> Result<()> {
+    writeln!(out, "{}", playlist.display())?;
+    for track in tracks {
+        writeln!(
+            out,
+            "  {:<7}  {:<3}  {}",
+            if track.exists { "found" } else { "missing" },
+            if track.lyrics { "lrc" } else { "-" },
+            track.path.display()
+        )?;
+    }
+
+    let mut totals = Totals::default();
+    totals.add(tracks);
+    writeln!(out, "  {}", totals.describe())?;
+    Ok(())
+}
+
+/// Print the playlists with their tracks and totals to `out`
+fn list_playlists(cli: &Cli, out: &mut impl Write) -> Result<Totals> {
+    let mut totals = Totals::default();
+    let mut playlists = Vec::new();
+
+    for playlist in &cli.playlists {
+        if cli.verbose {
+            eprintln!("Reading playlist \"{}\"", playlist);
+        }
+        let tracks = playlist_tracks(playlist, cli.expand_dirs)?;
+        let path = absolute_path(Path::new(playlist))?;
+        totals.add(&tracks);
+
+        if !cli.json {
+            print_playlist(&path, &tracks, out)?;
+            continue;
+        }
+        let mut playlist_totals = Totals::default();
+        playlist_totals.add(&tracks);
+        let tracks: Vec<Value> = tracks
+            .iter()
+            .map(|track| {
+                json!({
+                    "path": track.path.to_string_lossy(),
+                    "exists": track.exists,
+                    "lyrics": track.lyrics,
+                })
+            })
+            .collect();
+        playlists.push(json!({
+            "playlist": path.to_string_lossy(),
+            "tracks": tracks,
+            "totals": playlist_totals.to_json(),
+        }));
+    }
+
+    if cli.json {
+        let mut all_totals = totals.to_json();
+        all_totals["playlists"] = json!(totals.playlists);
+        let document = json!({ "playlists": playlists, "totals": all_totals });
+        writeln!(out, "{}", serde_json::to_string_pretty(&document)?)?;
+    } else if totals.playlists > 1 {
+        writeln!(
+            out,
+            "Total: {} playlists, {}",
+            totals.playlists,
+            totals.describe()
+        )?;
+    }
+
+    out.flush()?;
+    Ok(totals)
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut out = BufWriter::new(io::stdout().lock());
+
+    match list_playlists(&cli, &mut out) {
+        Ok(totals) => {
+            if cli.verbose {
+                eprintln!("Number of tracks: {}", totals.tracks);
+            }
+        }
+        Err(e) => {
+            // The reader has gone away, as with `plm list-playlist ... | head`
+            if e.downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+            {
+                return Ok(());
+            }
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,218 @@
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::process;
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Parser};
+use playlist_manager::logger::{Logger, StderrLogger};
+use playlist_manager::playlist_file::{is_playlist_file, PlaylistFile};
+use playlist_manager::track_matcher::{absolute_path, climbing_entry, playlist_dir};
+use thiserror::Error;
+
+/// Extension of lyrics files
+const LYRICS_EXTENSION: &str = "lrc";
+
+#[derive(Parser)]
+#[command(name = "plm-mv")]
+#[command(about = "Move a media file and rewrite the playlists referencing it")]
+#[command(version)]
+struct Cli {
+    /// Print verbose messages
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Rewrite the playlist files under DIR, searched recursively (may be repeated)
+    #[arg(long = "playlists", value_name = "DIR", required = true)]
+    playlists: Vec<String>,
+
+    /// Media file to move
+    old_path: String,
+
+    /// New path of the media file, or the directory to move it into
+    new_path: String,
+}
+
+#[derive(Error, Debug)]
+enum AppError {
+    #[error("Media file not found: {0}")]
+    NotFound(String),
+    #[error("File already exists: {0}")]
+    AlreadyExists(String),
+    #[error("Not a directory: {0}")]
+    NotADirectory(String),
+}
+
+/// Moves of the media file and its lyrics file, as pairs of the old and
+/// the new path
+fn planned_moves(cli: &Cli) -> Result<Vec<(PathBuf, PathBuf)>, AppError> {
+    let old_path = Path::new(&cli.old_path);
+    if !old_path.is_file() {
+        return Err(AppError::NotFound(cli.old_path.clone()));
+    }
+    let new_path = match (Path::new(&cli.new_path), old_path.file_name()) {
+        (dir, Some(name)) if dir.is_dir() => dir.join(name),
+        (path, _) => path.to_path_buf(),
+    };
+
+    let mut moves = vec![(old_path.to_path_buf(), new_path.clone())];
+    let lyrics_path = old_path.with_extension(LYRICS_EXTENSION);
+    if lyrics_path.is_file() {
+        moves.push((lyrics_path, new_path.with_extension(LYRICS_EXTENSION)));
+    }
+
+    match moves.iter().find(|(_, new_path)| new_path.exists()) {
+        Some((_, new_path)) => Err(AppError::AlreadyExists(new_path.display().to_string())),
+        None => Ok(moves),
+    }
+}
+
+/// Move a file, copying it and deleting the original when it moves to
+/// another filesystem
+fn move_file(old_path: &Path, new_path: &Path) -> io::Result<()> {
+    if let Some(dir) = new_path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    match fs::rename(old_path, new_path) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            fs::copy(old_path, new_path)?;
+            fs::remove_file(old_path)
+        }
+        result => result,
+    }
+}
+
+/// List the playlist files under a directory recursively, in the order
+/// of their paths
+///
+/// Directories whose names start with a dot are left alone.
+fn find_playlists(dir: &Path, playlists: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            if !entry.file_name().to_string_lossy().starts_with('.') {
+                find_playlists(&path, playlists)?;
+            }
+        } else if is_playlist_file(&path) {
+            playlists.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Rewrite the entries of a playlist file naming the moved media files
+/// to their new paths, written the way the playlist writes its entries
+///
+/// Returns the number of rewritten entries.
+fn rewrite_playlist(
+    playlist: &Path,
+    moves: &[(PathBuf, PathBuf)],
+    logger: &dyn Logger,
+) -> Result<usize> {
+    let dir = playlist_dir(playlist);
+    let mut playlist_file = PlaylistFile::read(playlist)
+        .with_context(|| format!("Failed to read playlist: {}", playlist.display()))?;
+    let backslashes = playlist_file.uses_backslashes();
+
+    let mut error = None;
+    let n_rewritten = playlist_file.replace_entries(|entry| {
+        let full_path = absolute_path(&dir.join(entry)).ok()?;
+        let (_, new_path) = moves
+            .iter()
+            .find(|(old_path, _)| absolute_path(old_path).is_ok_and(|old| old == full_path))?;
+        let new_entry = match Path::new(entry).components().next() {
+            Some(Component::RootDir | Component::Prefix(_)) => {
+                absolute_path(new_path).map(|path| path.to_string_lossy().to_string())
+            }
+            _ => climbing_entry(new_path, &dir),
+        };
+        let new_entry = match new_entry {
+            Ok(new_entry) => new_entry,
+            Err(e) => {
+                error.get_or_insert(e);
+                return None;
+            }
+        };
+
+        logger.log_formatted(
+            "Rewriting track \"{}\" to \"{}\" in playlist \"{}\"",
+            &[entry, &new_entry, &playlist.to_string_lossy()],
+        );
+        match backslashes {
+            true => Some(new_entry.replace('/', "\\")),
+            false => Some(new_entry),
+        }
+    });
+    if let Some(e) = error {
+        return Err(e)
+            .with_context(|| format!("Failed to rewrite playlist: {}", playlist.display()));
+    }
+
+    if n_rewritten > 0 {
+        playlist_file
+            .write(playlist)
+            .with_context(|| format!("Failed to write playlist: {}", playlist.display()))?;
+    }
+    Ok(n_rewritten)
+}
+
+/// Move the media file and rewrite the playlists referencing it
+/// Returns the number of moved files and of rewritten playlists
+fn move_track(cli: &Cli, logger: &dyn Logger) -> Result<(usize, usize)> {
+    let moves = planned_moves(cli)?;
+    let mut playlists = Vec::new();
+    for dir in &cli.playlists {
+        if !Path::new(dir).is_dir() {
+            return Err(AppError::NotADirectory(dir.clone()).into());
+        }
+        find_playlists(Path::new(dir), &mut playlists)?;
+    }
+    playlists.sort();
+    playlists.dedup();
+
+    for (old_path, new_path) in &moves {
+        logger.log_formatted(
+            "Moving \"{}\" to \"{}\"",
+            &[&old_path.to_string_lossy(), &new_path.to_string_lossy()],
+        );
+        move_file(old_path, new_path)
+            .with_context(|| format!("Failed to move {}", old_path.display()))?;
+    }
+
+    let mut n_playlists = 0;
+    for playlist in &playlists {
+        if rewrite_playlist(playlist, &moves, logger)? > 0 {
+            n_playlists += 1;
+        }
+    }
+    Ok((moves.len(), n_playlists))
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let logger = StderrLogger::new(cli.verbose);
+
+    match move_track(&cli, &logger) {
+        Ok((n_files, n_playlists)) => {
+            if cli.verbose {
+                println!("Number of moved files: {}", n_files);
+                println!("Number of rewritten playlists: {}", n_playlists);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            match e.downcast_ref::<AppError>() {
+                Some(_) => process::exit(255),
+                None => process::exit(1),
+            }
+        }
+    }
+
+    Ok(())
+}
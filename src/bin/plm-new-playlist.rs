@@ -0,0 +1,232 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Parser};
+use playlist_manager::album_playlist::compare_tracks;
+use playlist_manager::logger::{Logger, StderrLogger};
+use playlist_manager::media_extensions::ExtensionAllowlist;
+use playlist_manager::media_tags::read_tags;
+use playlist_manager::track_matcher::{absolute_path, climbing_entry, playlist_dir};
+use thiserror::Error;
+
+/// Source reading the paths of the tracks from the standard input
+const STDIN_SOURCE: &str = "-";
+
+#[derive(Parser)]
+#[command(name = "plm-new-playlist")]
+#[command(about = "Create a playlist file from directories, glob patterns or a list of files")]
+#[command(version)]
+struct Cli {
+    /// Print verbose messages
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Write #EXTM3U and #EXTINF lines titled after the tags or names of the tracks
+    #[arg(long = "extinf", action = ArgAction::SetTrue)]
+    extinf: bool,
+
+    /// Collect the files with the comma-separated extensions EXTS from directories, such as flac,mp3
+    #[arg(long = "ext", value_name = "EXTS")]
+    ext: Option<ExtensionAllowlist>,
+
+    /// Replace the playlist file if it exists
+    #[arg(short = 'f', long = "force", action = ArgAction::SetTrue)]
+    force: bool,
+
+    /// Playlist file to create
+    playlist: String,
+
+    /// Directories to collect media files from recursively, media files, glob patterns, or - to read paths from the standard input (default)
+    sources: Vec<String>,
+}
+
+#[derive(Error, Debug)]
+enum AppError {
+    #[error("Playlist file already exists: {0} (use --force to replace it)")]
+    AlreadyExists(String),
+    #[error("Source not found: {0}")]
+    NotFound(String),
+    #[error("Invalid glob pattern {0}: {1}")]
+    InvalidPattern(String, glob::PatternError),
+}
+
+/// Whether a source is a glob pattern rather than a path
+fn is_pattern(source: &str) -> bool {
+    source.contains(['*', '?', '['])
+}
+
+/// Append the files with the extensions of `extensions` found
+/// recursively in `dir` to `tracks`, in track order within each
+/// directory and followed by its subdirectories in the order of their
+/// names
+///
+/// Directories whose names start with a dot are left alone, and
+/// symbolic links to directories are not followed.
+fn collect_dir(
+    dir: &Path,
+    extensions: &ExtensionAllowlist,
+    tracks: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let mut files = Vec::new();
+    let mut subdirs = Vec::new();
+    let entries = fs::read_dir(dir)
+        .and_then(|entries| entries.collect::<io::Result<Vec<_>>>())
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            if !name.starts_with('.') {
+                subdirs.push(path);
+            }
+        } else if extensions.allows(&name) && path.is_file() {
+            files.push(name);
+        }
+    }
+
+    files.sort_by(|a, b| compare_tracks(a, b));
+    tracks.extend(files.iter().map(|name| dir.join(name)));
+    subdirs.sort();
+    for subdir in subdirs {
+        collect_dir(&subdir, extensions, tracks)?;
+    }
+    Ok(())
+}
+
+/// Paths of the tracks listed on the standard input, one per line
+///
+/// Blank lines and lines starting with `#` are ignored.
+fn read_stdin() -> Result<Vec<PathBuf>> {
+    let mut tracks = Vec::new();
+    for line in io::stdin().lock().lines() {
+        let line = line.context("Failed to read the standard input")?;
+        let line = line.trim_start_matches('\u{feff}').trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            tracks.push(PathBuf::from(line));
+        }
+    }
+    Ok(tracks)
+}
+
+/// Paths of the tracks given by the sources, in the order given
+fn collect_tracks(cli: &Cli) -> Result<Vec<PathBuf>> {
+    let extensions = cli.ext.clone().unwrap_or_default();
+    let sources = match cli.sources.is_empty() {
+        true => vec![STDIN_SOURCE.to_string()],
+        false => cli.sources.clone(),
+    };
+
+    let mut tracks = Vec::new();
+    for source in &sources {
+        let path = Path::new(source);
+        if source == STDIN_SOURCE {
+            tracks.extend(read_stdin()?);
+        } else if path.is_dir() {
+            collect_dir(path, &extensions, &mut tracks)?;
+        } else if path.is_file() {
+            tracks.push(path.to_path_buf());
+        } else if is_pattern(source) {
+            let matches = glob::glob(source)
+                .map_err(|e| AppError::InvalidPattern(source.clone(), e))?
+                .filter_map(|entry| entry.ok())
+                .filter(|path| path.is_file())
+                .collect::<Vec<_>>();
+            if matches.is_empty() {
+                eprintln!("Warning: No file matches {}", source);
+            }
+            tracks.extend(matches);
+        } else {
+            return Err(AppError::NotFound(source.clone()).into());
+        }
+    }
+    Ok(tracks)
+}
+
+/// Build the `#EXTINF` line of a track, titled `Artist - Title` after
+/// its tags, or after its file name if they cannot be read, with an
+/// unknown duration
+fn extinf_line(track: &Path) -> String {
+    let tags = read_tags(track).ok();
+    let tagged = tags.as_ref().and_then(|tags| {
+        let title = tags.title.as_deref()?;
+        Some(match tags.artist.as_deref() {
+            Some(artist) => format!("{} - {}", artist, title),
+            None => title.to_string(),
+        })
+    });
+    let title = tagged.unwrap_or_else(|| {
+        track
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default()
+    });
+    format!("#EXTINF:-1,{}", title)
+}
+
+/// Write the playlist of the tracks given by the sources
+/// Returns the number of tracks written
+fn new_playlist(cli: &Cli, logger: &dyn Logger) -> Result<usize> {
+    let playlist = Path::new(&cli.playlist);
+    if playlist.exists() && !cli.force {
+        return Err(AppError::AlreadyExists(cli.playlist.clone()).into());
+    }
+    let dir = playlist_dir(playlist);
+
+    let mut seen = HashSet::new();
+    let mut content = String::new();
+    if cli.extinf {
+        content.push_str("#EXTM3U\n");
+    }
+    for track in collect_tracks(cli)? {
+        // List a track given by several sources once
+        if !seen.insert(absolute_path(&track)?) {
+            continue;
+        }
+        if !track.exists() {
+            eprintln!("Warning: Media file not found: {}", track.display());
+        }
+        let entry = climbing_entry(&track, &dir)?;
+        logger.log_formatted(
+            "Adding track \"{}\" to playlist \"{}\"",
+            &[&entry, &cli.playlist],
+        );
+        if cli.extinf {
+            content.push_str(&extinf_line(&track));
+            content.push('\n');
+        }
+        content.push_str(&entry);
+        content.push('\n');
+    }
+
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create directory: {}", dir.display()))?;
+    fs::write(playlist, content)
+        .with_context(|| format!("Failed to write playlist: {}", cli.playlist))?;
+    Ok(seen.len())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let logger = StderrLogger::new(cli.verbose);
+
+    match new_playlist(&cli, &logger) {
+        Ok(n_tracks) => {
+            if cli.verbose {
+                println!("Number of added tracks: {}", n_tracks);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            match e.downcast_ref::<AppError>() {
+                Some(_) => process::exit(255),
+                None => process::exit(1),
+            }
+        }
+    }
+
+    Ok(())
+}
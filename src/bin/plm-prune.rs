@@ -0,0 +1,110 @@
+use std::path::Path;
+use std::process;
+
+use anyhow::Result;
+use clap::{ArgAction, Parser};
+use playlist_manager::empty_dirs::{prune_empty_dirs, PruneOptions};
+use playlist_manager::logger::StderrLogger;
+use thiserror::Error;
+
+#[derive(Parser)]
+#[command(name = "plm-prune")]
+#[command(about = "Delete empty directories from device")]
+#[command(version)]
+struct Cli {
+    /// Print verbose messages
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Print the empty directories without deleting them
+    #[arg(short = 'n', long = "dry-run", action = ArgAction::SetTrue)]
+    dry_run: bool,
+
+    /// Keep directories less than N levels below DIR (DIR itself is level 0)
+    #[arg(long = "min-depth", value_name = "N", default_value_t = 1)]
+    min_depth: usize,
+
+    /// Keep directories whose name or path relative to DIR matches PATTERN (may be repeated)
+    #[arg(short = 'x', long = "exclude", value_name = "PATTERN")]
+    exclude: Vec<String>,
+
+    /// Keep directories matching the patterns listed in FILE (may be repeated)
+    #[arg(long = "exclude-from", value_name = "FILE")]
+    exclude_from: Vec<String>,
+
+    /// Directory to delete empty directories from
+    dir: String,
+}
+
+#[derive(Error, Debug)]
+enum AppError {
+    #[error("Not a directory: {0}")]
+    NotADirectory(String),
+    #[error("Invalid exclusion: {0}")]
+    InvalidExclusion(String),
+}
+
+/// Build the pruning options from the command line
+fn prune_options(cli: &Cli) -> Result<PruneOptions, AppError> {
+    let mut options = PruneOptions::new();
+    options.dry_run = cli.dry_run;
+    options.min_depth = cli.min_depth;
+
+    for pattern in &cli.exclude {
+        options
+            .exclude(pattern)
+            .map_err(|e| AppError::InvalidExclusion(e.to_string()))?;
+    }
+    for exclusion_file in &cli.exclude_from {
+        options
+            .exclude_file(Path::new(exclusion_file))
+            .map_err(|e| AppError::InvalidExclusion(format!("{}: {}", exclusion_file, e)))?;
+    }
+
+    Ok(options)
+}
+
+/// Delete the empty directories under the directory
+/// Returns the number of deleted directories
+fn prune(cli: &Cli) -> Result<usize> {
+    let dir = Path::new(&cli.dir);
+    if !dir.is_dir() {
+        return Err(AppError::NotADirectory(cli.dir.clone()).into());
+    }
+
+    let options = prune_options(cli)?;
+    let logger = StderrLogger::new(cli.verbose);
+    let pruned = prune_empty_dirs(dir, &options, &logger)?;
+
+    if cli.dry_run {
+        for empty_dir in &pruned {
+            println!("{}", empty_dir.display());
+        }
+    }
+
+    Ok(pruned.len())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    match prune(&cli) {
+        Ok(n_dirs) => {
+            if cli.verbose {
+                match cli.dry_run {
+                    true => println!("Number of empty directories: {}", n_dirs),
+                    false => println!("Number of deleted directories: {}", n_dirs),
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            match e.downcast_ref::<AppError>() {
+                Some(_) => process::exit(255),
+                None => process::exit(1),
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -1,436 +1,323 @@
-use std::collections::HashSet;
-use std::fs::{self, File};
-use std::io::{self, Write};
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::process;
+use std::sync::Arc;
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use clap::{ArgAction, Parser};
-use playlist_manager::file_utils::copy_file;
-use playlist_manager::playlist_scanner;
-use thiserror::Error;
-
-// Import MediaFileInfo from the shared module
-use playlist_manager::media_file_info::MediaFileInfo;
-
-mod plm_put_playlist_retry;
-
-/// Struct to hold command line options
-#[derive(Debug)]
-struct CommandOptions {
-    copy_lyrics: bool,
-    keep_going: bool,
-}
+use glob::Pattern;
+use playlist_manager::diagnostics::parse_size;
+use playlist_manager::file_hash::HashAlgorithm;
+use playlist_manager::logger::{Logger, StderrLogger};
+use playlist_manager::media_extensions::{parse_extension, ExtensionAllowlist};
+use playlist_manager::playlist_encoding::PlaylistEncoding;
+use playlist_manager::transfer::artwork::{parse_artwork_pattern, ARTWORK_PATTERNS};
+use playlist_manager::transfer::backend::IoBackend;
+use playlist_manager::transfer::conflicts::ConflictPolicy;
+use playlist_manager::transfer::copy::parse_buffer_size;
+use playlist_manager::transfer::names::{
+    parse_extension_rewrite, CollisionStrategy, Layout, LetterCase,
+};
+use playlist_manager::transfer::report::CopyReport;
+use playlist_manager::transfer::validate::validate_playlists;
+use playlist_manager::transfer::{ErrorTracker, PlaylistTransfer, TransferOptions};
+
+mod plm_put_playlist_config;
+mod plm_put_playlist_support;
+
+use plm_put_playlist_config::effective_config;
+use plm_put_playlist_support::{
+    lock_destinations, perform_cleanup, prepare_environment, print_run_summary,
+    read_device_profile, AppError,
+};
 
 #[derive(Parser)]
 #[command(name = "plm-put-playlist")]
 #[command(about = "Copy playlist files and associated media files from PC to device")]
 #[command(version)]
 struct Cli {
-    /// Print verbose messages
-    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
-    verbose: bool,
+    /// Print verbose messages (specify twice for debug messages)
+    #[arg(short = 'v', long = "verbose", action = ArgAction::Count)]
+    verbose: u8,
 
-    /// Copy lyrics files (.lrc) along with media files
+    /// Copy lyrics files (.lrc unless --lyrics-ext is given) along with media files
     #[arg(short = 'l', long = "lyrics", action = ArgAction::SetTrue)]
     lyrics: bool,
 
+    /// Treat files with the comma-separated extensions, such as lrc,txt, as lyrics files (may be repeated)
+    #[arg(long = "lyrics-ext", value_name = "EXTS", value_delimiter = ',', value_parser = parse_extension, default_value = "lrc")]
+    lyrics_ext: Vec<String>,
+
+    /// Copy files sharing the stem of each media file with the comma-separated extensions, such as cue,pdf (may be repeated)
+    #[arg(long = "sidecar", value_name = "EXTS", value_delimiter = ',', value_parser = parse_extension)]
+    sidecar: Vec<String>,
+
+    /// Copy the cover images of each source album directory into the directory its media files are copied into
+    #[arg(long = "artwork", action = ArgAction::SetTrue)]
+    artwork: bool,
+
+    /// Copy the cover images with names matching the comma-separated wildcard PATTERNS with --artwork (may be repeated)
+    #[arg(long = "artwork-pattern", value_name = "PATTERNS", value_delimiter = ',', value_parser = parse_artwork_pattern, default_value = ARTWORK_PATTERNS, requires = "artwork")]
+    artwork_pattern: Vec<Pattern>,
+
     /// Continue operation despite errors
     #[arg(short = 'k', long = "keep-going", action = ArgAction::SetTrue)]
     keep_going: bool,
 
-    /// Write list of failed files to specified file (only with --keep-going)
-    #[arg(short = 'e', long = "error-files", value_name = "FILE")]
-    error_files: Option<String>,
+    /// Verify copied media files against checksums computed while copying
+    #[arg(long = "verify", action = ArgAction::SetTrue)]
+    verify: bool,
 
-    /// Retry failed operations from error file
-    #[arg(short = 'r', long = "retry", value_name = "FILE")]
-    retry_file: Option<String>,
+    /// Copy files in chunks of SIZE bytes (K or M suffix allowed) with read-ahead
+    #[arg(long = "buffer-size", value_name = "SIZE", value_parser = parse_buffer_size)]
+    buffer_size: Option<usize>,
 
-    /// Destination to put playlists and media files into
-    #[arg(required = true)]
-    dest: String,
+    /// Copy media files with the portable or the uring (io_uring, Linux only) I/O backend
+    #[arg(long = "io-backend", value_name = "BACKEND", default_value = "portable")]
+    io_backend: IoBackend,
 
-    /// Playlist file(s) to put
-    #[arg(required_unless_present = "retry_file")]
-    playlists: Vec<String>,
-}
+    /// Retry a copy failing with a transient error, such as EIO or a timeout, up to N (at most 10) times
+    #[arg(long = "io-retries", value_name = "N", default_value_t = 0, value_parser = clap::value_parser!(u32).range(..=10))]
+    io_retries: u32,
 
-#[derive(Error, Debug)]
-enum AppError {
-    #[error("IO error: {0}")]
-    Io(#[from] io::Error),
+    /// Copy the media files of each playlist with N (at most 64) threads of the portable I/O backend
+    #[arg(short = 'j', long = "jobs", value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..=64))]
+    jobs: u16,
 
-    #[error("Failed to get absolute path: {0}")]
-    AbsPath(String),
-}
+    /// Copy media files with identical contents only once, pointing playlist entries at the copy
+    #[arg(long = "dedup", action = ArgAction::SetTrue)]
+    dedup: bool,
 
-/// Enum to represent different types of failures
-#[derive(Debug)]
-enum FailureType {
-    Playlist(String),          // Failed playlist path
-    MediaFile(String, String), // (src_basedir, file) for failed media file
-}
+    /// Hash copies to verify and media files to deduplicate with crc32, blake3, sha256 or xxh3
+    #[arg(long = "hash", value_name = "ALGORITHM", default_value = "crc32")]
+    hash: HashAlgorithm,
 
-/// Struct to track failed files
-#[derive(Debug)]
-struct ErrorTracker {
-    failures: Vec<FailureType>, // Failures in operation order
-}
+    /// Move destination files about to be overwritten with different contents to NAME.plm-bak
+    #[arg(long = "backup", action = ArgAction::SetTrue)]
+    backup: bool,
 
-impl ErrorTracker {
-    fn new() -> Self {
-        Self {
-            failures: Vec::new(),
-        }
-    }
+    /// Move destination files about to be overwritten into DIR instead (implies --backup)
+    #[arg(long = "backup-dir", value_name = "DIR")]
+    backup_dir: Option<String>,
 
-    fn add_failed_playlist(&mut self, playlist: String) {
-        self.failures.push(FailureType::Playlist(playlist));
-    }
+    /// Copy the user extended attributes of media files and lyrics files where supported
+    #[arg(long = "xattrs", action = ArgAction::SetTrue)]
+    xattrs: bool,
 
-    fn add_failed_media_file(&mut self, src_basedir: String, file: String) {
-        self.failures
-            .push(FailureType::MediaFile(src_basedir, file));
-    }
+    /// Keep the state of synced files on the destination and skip media files unchanged since
+    #[arg(long = "sync-state", action = ArgAction::SetTrue)]
+    sync_state: bool,
 
-    fn write_to_file(&self, path: &str) -> Result<(), io::Error> {
-        let mut file = File::create(path)?;
-
-        // Write failures in operation order with appropriate prefixes
-        for failure in &self.failures {
-            match failure {
-                FailureType::Playlist(playlist) => {
-                    writeln!(file, "P {}", playlist)?;
-                }
-                FailureType::MediaFile(src_basedir, file_path) => {
-                    let full_path = Path::new(src_basedir).join(file_path);
-                    writeln!(file, "M {}", full_path.display())?;
-                }
-            }
-        }
+    /// Skip media files whose destination file has the same size and is not older
+    #[arg(short = 'u', long = "update", visible_alias = "skip-existing", action = ArgAction::SetTrue)]
+    update: bool,
 
-        Ok(())
-    }
-}
+    /// Create a playlist in each destination album directory, sorted by track number
+    #[arg(long = "album-playlists", action = ArgAction::SetTrue)]
+    album_playlists: bool,
 
-/// Get the absolute path of a directory
-fn abs_dir(path: &str) -> Result<String, AppError> {
-    let path = Path::new(path);
-    let abs_path = fs::canonicalize(path).map_err(|e| {
-        AppError::AbsPath(format!(
-            "Failed to get absolute path for {}: {}",
-            path.display(),
-            e
-        ))
-    })?;
-
-    if !abs_path.is_dir() {
-        return Err(AppError::AbsPath(format!(
-            "{} is not a directory",
-            abs_path.display()
-        )));
-    }
+    /// Reorder the directory entries of each destination album directory into track order
+    #[arg(long = "fat-order", action = ArgAction::SetTrue)]
+    fat_order: bool,
 
-    Ok(abs_path.to_string_lossy().to_string())
-}
+    /// Remove #EXTINF and other comment lines from the copied playlists
+    #[arg(long = "strip-extinf", action = ArgAction::SetTrue)]
+    strip_extinf: bool,
 
+    /// Encoding of the copied playlists (utf8, utf16le or cp932)
+    #[arg(long = "dest-encoding", value_name = "ENCODING", default_value = "utf8")]
+    dest_encoding: PlaylistEncoding,
 
-/// Copy a single media file from source to destination
-/// Returns a tuple of (number of files copied, whether the media file was successfully copied)
-fn copy_single_media_file(
-    media_file: &MediaFileInfo,
-    dest_basedir: &str,
-    options: &CommandOptions,
-    error_tracker: &mut Option<&mut ErrorTracker>,
-    _current_file_num: Option<usize>,
-    _total_files: Option<usize>,
-) -> Result<(usize, bool)> {
-    let mut n_files = 0;
-    let file_path = Path::new(&media_file.file);
-    let dir_part = file_path.parent().unwrap_or(Path::new(""));
-    let file_part = file_path.file_name().unwrap_or_default();
-
-    let src_file = Path::new(&media_file.src_basedir).join(&media_file.file);
-    let dest_file = Path::new(dest_basedir).join(dir_part).join(file_part);
-
-    // Copy the main media file
-    if let Err(err) = copy_file(&src_file, &dest_file) {
-        eprintln!("Error: {}", err);
-        if let Some(tracker) = error_tracker {
-            tracker.add_failed_media_file(
-                media_file.src_basedir.clone(),
-                media_file.file.clone(),
-            );
-        }
-        if options.keep_going {
-            return Ok((0, false));
-        } else {
-            return Err(err);
-        }
-    }
-    n_files += 1;
-
-    // If lyrics option is enabled, try to copy the corresponding .lrc file
-    if options.copy_lyrics {
-        if let Some(stem) = file_path.file_stem() {
-            let lyrics_filename = format!("{}.lrc", stem.to_string_lossy());
-            let lyrics_path = Path::new(&media_file.src_basedir)
-                .join(dir_part)
-                .join(&lyrics_filename);
-
-            if lyrics_path.exists() {
-                let dest_lyrics_file =
-                    Path::new(dest_basedir).join(dir_part).join(&lyrics_filename);
-
-                // Copy lyrics file (don't track lyrics files in error tracker)
-                if let Err(err) = copy_file(&lyrics_path, &dest_lyrics_file) {
-                    eprintln!("Error: {}", err);
-                    if !options.keep_going {
-                        return Err(err);
-                    }
-                } else {
-                    n_files += 1;
-                }
-            }
-        }
-    }
+    /// Transliterate non-ASCII characters in destination names and playlist entries to ASCII
+    #[arg(long = "transliterate", action = ArgAction::SetTrue)]
+    transliterate: bool,
 
-    Ok((n_files, true))
-}
+    /// Letter case of destination names and playlist entries (lower, upper or preserve)
+    #[arg(long = "case", value_name = "CASE", default_value = "preserve")]
+    case: LetterCase,
 
-/// Copy media files from source to destination
-/// Returns a tuple of (number of files copied, list of successfully copied media files)
-fn copy_media_files(
-    src_basedir: &str,
-    dest_basedir: &str,
-    files: impl Iterator<Item = String>,
-    options: &CommandOptions,
-    error_tracker: &mut Option<&mut ErrorTracker>,
-    total_files: Option<usize>,
-    current_success_count: &mut usize,
-) -> Result<(usize, Vec<String>)> {
-    let mut n_files = 0;
-    let mut successful_files = Vec::new();
-    let files_vec: Vec<String> = files.collect();
-
-    for file in files_vec.into_iter() {
-        // Create a MediaFileInfo for this file
-        let media_file = MediaFileInfo {
-            src_basedir: src_basedir.to_string(),
-            file: file.clone(),
-        };
+    /// Layout of destination paths (source, or tags for Artist/Album/NN Title.ext)
+    #[arg(long = "layout", value_name = "LAYOUT", default_value = "source")]
+    layout: Layout,
 
-        // We'll update current_file_num only if the copy is successful
-        match copy_single_media_file(
-            &media_file,
-            dest_basedir,
-            options,
-            error_tracker,
-            None, // We'll print the message after successful copy
-            total_files,
-        ) {
-            Ok((copied, success)) => {
-                n_files += copied;
-                if success {
-                    // Increment the global success counter only for successful files
-                    *current_success_count += 1;
-
-                    // Print message with updated counter after successful copy
-                    let src_file = Path::new(&media_file.src_basedir).join(&media_file.file);
-                    let file_path = Path::new(&media_file.file);
-                    let dir_part = file_path.parent().unwrap_or(Path::new(""));
-                    let file_part = file_path.file_name().unwrap_or_default();
-                    let dest_file = Path::new(dest_basedir).join(dir_part).join(file_part);
-
-                    playlist_manager::logger::get_logger().log_with_counters(
-                        "Copy track \"{}\" to \"{}\"",
-                        &[&src_file.to_string_lossy(), &dest_file.to_string_lossy()],
-                        Some(*current_success_count),
-                        total_files,
-                        Some("media"),
-                    );
-
-                    // If lyrics option is enabled, print message for lyrics file too
-                    if options.copy_lyrics {
-                        if let Some(stem) = file_path.file_stem() {
-                            let lyrics_filename = format!("{}.lrc", stem.to_string_lossy());
-                            let lyrics_path = Path::new(&media_file.src_basedir)
-                                .join(dir_part)
-                                .join(&lyrics_filename);
-
-                            if lyrics_path.exists() {
-                                let dest_lyrics_file = Path::new(dest_basedir)
-                                    .join(dir_part)
-                                    .join(&lyrics_filename);
-
-                                playlist_manager::logger::get_logger().log_with_counters(
-                                    "Copy lyrics \"{}\" to \"{}\"",
-                                    &[
-                                        &lyrics_path.to_string_lossy(),
-                                        &dest_lyrics_file.to_string_lossy(),
-                                    ],
-                                    None, // Don't increment counter for lyrics files
-                                    total_files,
-                                    Some("lyrics"),
-                                );
-                            }
-                        }
-                    }
-
-                    successful_files.push(file);
-                }
-                // Note: We don't increment the counter for failed files
-            }
-            Err(e) => return Err(e),
-        }
-    }
+    /// Rewrite the extensions of destination files and playlist entries, such as flac=opus,wav=mp3 (may be repeated)
+    #[arg(long = "rewrite-ext", value_name = "MAP", value_delimiter = ',', value_parser = parse_extension_rewrite)]
+    rewrite_ext: Vec<(String, String)>,
 
-    Ok((n_files, successful_files))
-}
+    /// Rename destination names by the rules of the name policy FILE, such as replace, truncate, transliterate and case
+    #[arg(long = "name-policy", value_name = "FILE")]
+    name_policy: Option<String>,
 
-/// Extract media files from a playlist
-fn extract_media_files(playlist: &str) -> Result<(String, Vec<String>)> {
-    let playlist_path = Path::new(playlist);
-    let src_basedir = playlist_path
-        .parent()
-        .map(|p| p.to_string_lossy().to_string())
-        .unwrap_or_else(|| ".".to_string());
+    /// Write XSPF playlists as M3U8 playlists on the destination
+    #[arg(long = "xspf-to-m3u8", action = ArgAction::SetTrue)]
+    xspf_to_m3u8: bool,
 
-    let file =
-        File::open(playlist).with_context(|| format!("Failed to open playlist: {}", playlist))?;
-    let media_files: Vec<String> = playlist_scanner::read_playlist(file).collect();
+    /// Collapse source directories deeper than N levels into one destination directory
+    #[arg(long = "max-depth", value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+    max_depth: Option<u16>,
 
-    Ok((src_basedir, media_files))
-}
+    /// Fail on destination names Windows cannot use, such as CON.flac, instead of renaming them
+    #[arg(long = "strict", action = ArgAction::SetTrue)]
+    strict: bool,
 
-/// Copy a playlist file to the destination
-fn copy_playlist_file(
-    playlist: &str,
-    dest_basedir: &str,
-    current_playlist_num: Option<usize>,
-    total_playlists: Option<usize>,
-) -> Result<()> {
-    let playlist_path = Path::new(playlist);
-    let dest_dir = PathBuf::from(dest_basedir);
+    /// Print an error for each missing media file instead of one line per source directory
+    #[arg(long = "list-missing", action = ArgAction::SetTrue)]
+    list_missing: bool,
 
-    if !dest_dir.exists() {
-        fs::create_dir_all(&dest_dir)
-            .with_context(|| format!("Failed to create directory: {}", dest_dir.display()))?;
-    }
+    /// Put the tracks not fitting into DEST into the comma-separated further destinations, in order (may be repeated)
+    #[arg(long = "spillover", value_name = "DESTS", value_delimiter = ',')]
+    spillover: Vec<String>,
 
-    let playlist_filename = playlist_path
-        .file_name()
-        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid playlist filename"))?;
-
-    let dest_playlist = dest_dir.join(playlist_filename);
-
-    // Check if the playlist contains backslashes
-    let playlist_content = fs::read_to_string(playlist)
-        .with_context(|| format!("Failed to read playlist: {}", playlist))?;
-
-    let has_backslashes = playlist_content
-        .lines()
-        .any(|line| !line.starts_with('#') && line.contains('\\'));
-
-    if has_backslashes {
-        // Replace backslashes with forward slashes
-        let modified_content = playlist_content
-            .lines()
-            .map(|line| {
-                if !line.starts_with('#') && line.contains('\\') {
-                    line.replace('\\', "/")
-                } else {
-                    line.to_string()
-                }
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        fs::write(&dest_playlist, modified_content)
-            .with_context(|| format!("Failed to write playlist: {}", dest_playlist.display()))?;
-    } else {
-        playlist_manager::logger::get_logger().log_with_counters(
-            "Copy playlist \"{}\" to \"{}\"",
-            &[playlist, &format!("{}/", dest_basedir)],
-            current_playlist_num,
-            total_playlists,
-            None,
-        );
+    /// Space to leave free on each destination with --spillover (K, M or G suffix allowed)
+    #[arg(long = "min-free", value_name = "SIZE", default_value = "64M", value_parser = parse_size)]
+    min_free: u64,
 
-        fs::copy(playlist, &dest_playlist).with_context(|| {
-            format!("Failed to copy {} to {}", playlist, dest_playlist.display())
-        })?;
-    }
+    /// Put at most SIZE bytes of files into each destination with --spillover
+    #[arg(long = "max-fill", value_name = "SIZE", value_parser = parse_size, requires = "spillover")]
+    max_fill: Option<u64>,
 
-    Ok(())
-}
+    /// Copy even if the destination and the directory of a playlist or a file list lie inside one another
+    #[arg(long = "allow-overlap", action = ArgAction::SetTrue)]
+    allow_overlap: bool,
 
-/// Process a playlist file and its associated media files
-fn process_playlist(
-    playlist: &str,
-    dest_basedir: &str,
-    media_files_map: &mut Vec<(String, HashSet<String>)>,
-    current_playlist_num: Option<usize>,
-    total_playlists: Option<usize>,
-) -> Result<(String, Vec<String>)> {
-    playlist_manager::logger::get_logger().log_formatted("Processing playlist \"{}\"", &[playlist]);
-
-    // Copy the playlist file
-    copy_playlist_file(
-        playlist,
-        dest_basedir,
-        current_playlist_num,
-        total_playlists,
-    )?;
-
-    // Extract media files
-    let (src_basedir, files) = extract_media_files(playlist)?;
-
-    // Add to the media files map
-    let entry = media_files_map
-        .iter_mut()
-        .find(|(base, _)| *base == src_basedir);
-
-    if let Some((_, files_set)) = entry {
-        // Add files to existing set
-        for file in &files {
-            files_set.insert(file.clone());
-        }
-    } else {
-        // Create new entry
-        let mut files_set = HashSet::new();
-        for file in &files {
-            files_set.insert(file.clone());
-        }
-        media_files_map.push((src_basedir.clone(), files_set));
-    }
+    /// Fail before writing anything if the copy would write or delete under the directories of the playlists, file lists or media files
+    #[arg(long = "source-read-only", action = ArgAction::SetTrue)]
+    source_read_only: bool,
 
-    Ok((src_basedir, files))
-}
+    /// Do not copy tracks matching PATTERN or at the path PATTERN (may be repeated)
+    #[arg(short = 'x', long = "exclude", value_name = "PATTERN")]
+    exclude: Vec<String>,
+
+    /// Do not copy tracks matching the patterns or paths listed in FILE (may be repeated)
+    #[arg(long = "exclude-from", value_name = "FILE")]
+    exclude_from: Vec<String>,
+
+    /// Skip playlist entries without an audio extension, warning about them
+    #[arg(long = "audio-only", action = ArgAction::SetTrue)]
+    audio_only: bool,
+
+    /// Comma-separated audio extensions allowed by --audio-only (implies --audio-only)
+    #[arg(long = "audio-extensions", value_name = "LIST")]
+    audio_extensions: Option<ExtensionAllowlist>,
+
+    /// Also drop entries without an audio extension from the copied playlists
+    #[arg(long = "drop-non-audio", action = ArgAction::SetTrue)]
+    drop_non_audio: bool,
+
+    /// Expand playlist entries naming directories into the media files in them
+    #[arg(long = "expand-dirs", action = ArgAction::SetTrue)]
+    expand_dirs: bool,
+
+    /// Check that every entry of the written playlists resolves to a file on the destination
+    #[arg(long = "validate", action = ArgAction::SetTrue)]
+    validate: bool,
+
+    /// Policy for media files mapping to the destination path of another (first-wins, error or rename)
+    #[arg(long = "on-conflict", value_name = "POLICY", default_value = "first-wins")]
+    on_conflict: ConflictPolicy,
+
+    /// Strategy for media files renamed to the destination path of another (number, hash, skip or fail)
+    #[arg(long = "on-collision", value_name = "STRATEGY", default_value = "number")]
+    on_collision: CollisionStrategy,
+
+    /// Write a CSV report of the processed media files to FILE (TSV if FILE ends with .tsv)
+    #[arg(long = "report", value_name = "FILE")]
+    report: Option<String>,
+
+    /// Print a summary line for each playlist after the totals
+    #[arg(long = "playlist-summary", action = ArgAction::SetTrue)]
+    playlist_summary: bool,
+
+    /// Print the summary and the run ID as JSON instead of text
+    #[arg(long = "json", action = ArgAction::SetTrue)]
+    json: bool,
+
+    /// Print a change indicator for each media file copied, like rsync -i
+    #[arg(short = 'i', long = "itemize", action = ArgAction::SetTrue)]
+    itemize: bool,
 
-/// Filter out files that have already been copied
-fn filter_already_copied_files(
-    src_basedir: &str,
-    files: &[String],
-    copied_files: &HashSet<(String, String)>,
-) -> Vec<String> {
-    files
-        .iter()
-        .filter(|file| !copied_files.contains(&(src_basedir.to_string(), file.to_string())))
-        .cloned()
-        .collect()
+    /// Show a progress bar of the media files copied, or a line per media file when standard error is not a terminal
+    #[arg(long = "progress", action = ArgAction::SetTrue, conflicts_with = "verbose")]
+    progress: bool,
+
+    /// Print the changes a copy would make without copying anything
+    #[arg(short = 'n', long = "dry-run", action = ArgAction::SetTrue)]
+    dry_run: bool,
+
+    /// Print the options in effect, with defaults and implied options applied, as JSON and exit
+    #[arg(long = "print-config", action = ArgAction::SetTrue)]
+    print_config: bool,
+
+    /// Run the actions of the device profile FILE, such as touching marker files, after a successful run
+    #[arg(long = "post-sync", value_name = "FILE")]
+    post_sync: Option<String>,
+
+    /// Copy the playlists the device profile FILE sets options for with those options
+    #[arg(long = "profile", value_name = "FILE")]
+    profile: Option<String>,
+
+    /// Write list of failed files to specified file (only with --keep-going)
+    #[arg(short = 'e', long = "error-files", value_name = "FILE")]
+    error_files: Option<String>,
+
+    /// Retry failed operations from error file
+    #[arg(short = 'r', long = "retry", value_name = "FILE")]
+    retry_file: Option<String>,
+
+    /// Destination to put playlists and media files into
+    #[arg(required = true)]
+    dest: String,
+
+    /// Copy the media files listed in FILE, one path per line, without a playlist (may be repeated)
+    #[arg(long = "from-list", value_name = "FILE")]
+    from_list: Vec<String>,
+
+    /// Directory the paths in --from-list files are relative to (default: directory of the list)
+    #[arg(long = "list-root", value_name = "DIR", requires = "from_list")]
+    list_root: Option<String>,
+
+    /// Directory the entries of the playlists are relative to (default: directory of each playlist)
+    #[arg(long = "relative-to", value_name = "DIR")]
+    relative_to: Option<String>,
+
+    /// Playlist file(s) to put
+    #[arg(required_unless_present_any = ["retry_file", "from_list"])]
+    playlists: Vec<String>,
 }
 
 /// Handle command line arguments and validate them
 fn handle_arguments() -> Result<Cli> {
     let cli = Cli::parse();
 
-    // Validate that --error-files is only used with --keep-going when not using --retry
-    if cli.error_files.is_some() && !cli.keep_going && cli.retry_file.is_none() {
+    // Validate that --error-files is only used with --keep-going when not
+    // using --retry or --dry-run
+    if cli.error_files.is_some() && !cli.keep_going && cli.retry_file.is_none() && !cli.dry_run {
         return Err(anyhow::anyhow!("--error-files can only be used with --keep-going"));
     }
 
+    if cli.dry_run && cli.retry_file.is_some() {
+        return Err(anyhow::anyhow!("--dry-run cannot be used with --retry"));
+    }
+
+    if !cli.from_list.is_empty() && cli.retry_file.is_some() {
+        return Err(anyhow::anyhow!("--from-list cannot be used with --retry"));
+    }
+
+    if cli.jobs > 1 && cli.io_backend == IoBackend::Uring {
+        return Err(anyhow::anyhow!("--jobs cannot be used with --io-backend uring"));
+    }
+
+    // Each destination is filled in a run of its own
+    let spillover_conflict = [
+        (cli.retry_file.is_some(), "--retry"),
+        (cli.dry_run, "--dry-run"),
+        (cli.error_files.is_some(), "--error-files"),
+    ]
+    .into_iter()
+    .find_map(|(given, option)| given.then_some(option));
+    if let (false, Some(option)) = (cli.spillover.is_empty(), spillover_conflict) {
+        return Err(anyhow::anyhow!("--spillover cannot be used with {}", option));
+    }
+
     // Validate that --retry and --error-files don't use the same file
     if let (Some(retry_file), Some(error_file)) = (&cli.retry_file, &cli.error_files) {
         if retry_file == error_file {
@@ -441,230 +328,74 @@ fn handle_arguments() -> Result<Cli> {
     Ok(cli)
 }
 
-/// Prepare the environment for operations
-fn prepare_environment(cli: &Cli) -> Result<(String, CommandOptions, Option<ErrorTracker>)> {
-    // Test if error file can be created (fail fast)
-    if let Some(error_file) = &cli.error_files {
-        File::create(error_file)
-            .with_context(|| format!("Failed to create error log file: {}", error_file))?;
-        // File can be created, we'll write to it at the end if needed
-        // The file will remain empty if no errors occur
-    }
-
-    // Get absolute path of destination directory
-    let dest_dir = abs_dir(&cli.dest)?;
-
-    // Create CommandOptions struct from CLI arguments
-    let options = CommandOptions {
-        copy_lyrics: cli.lyrics,
-        keep_going: cli.keep_going,
-    };
-
-    // Initialize error tracker if --error-files is specified
-    let error_tracker: Option<ErrorTracker> = cli.error_files.as_ref().map(|_| ErrorTracker::new());
+/// Allowlist of audio extensions to check playlist entries against, if
+/// any of the options checking them is given
+fn audio_extensions(cli: &Cli) -> Option<ExtensionAllowlist> {
+    (cli.audio_only || cli.audio_extensions.is_some() || cli.drop_non_audio)
+        .then(|| cli.audio_extensions.clone().unwrap_or_default())
+}
 
-    Ok((dest_dir, options, error_tracker))
+/// Names of the cover images to copy with `--artwork`, none without it
+fn artwork_patterns(cli: &Cli) -> Vec<Pattern> {
+    match cli.artwork {
+        true => cli.artwork_pattern.clone(),
+        false => Vec::new(),
+    }
 }
 
 /// Run the core logic (retry or normal operations)
 fn run_core_logic(
     cli: &Cli,
     dest_dir: &str,
-    options: &CommandOptions,
-    error_tracker_ref: &mut Option<&mut ErrorTracker>,
+    options: TransferOptions,
+    error_tracker: Option<&mut ErrorTracker>,
+    report: Option<CopyReport>,
 ) -> Result<()> {
-    let (successful_playlists, total_playlists, successful_media_files, total_media_files) =
-        if let Some(retry_file) = &cli.retry_file {
-            // Process retry operations
-            plm_put_playlist_retry::retry_operations(
-                retry_file,
-                dest_dir,
-                options,
-                error_tracker_ref,
-                cli.verbose,
-            )?
-        } else {
-            // Normal operation mode
-            process_normal_operations(&cli.playlists, dest_dir, options, error_tracker_ref, cli.verbose)?
-        };
-
-    // Print summary
-    println!(
-        "({}/{}) playlist copied",
-        successful_playlists, total_playlists
-    );
-    println!(
-        "({}/{}) media files copied",
-        successful_media_files, total_media_files
-    );
-
-    Ok(())
-}
-
-/// Perform cleanup operations (write error log if needed)
-fn perform_cleanup(cli: &Cli, error_tracker: Option<ErrorTracker>) -> Result<()> {
-    // Write error log if requested
-    if let Some(error_file) = &cli.error_files {
-        if let Some(tracker) = error_tracker {
-            tracker
-                .write_to_file(error_file)
-                .with_context(|| format!("Failed to write error log file: {}", error_file))?;
-        }
-    }
-
-    Ok(())
-}
-
-/// Collect all unique media files from the given playlists
-fn collect_all_media_files(playlists: &[String], options: &CommandOptions) -> Result<HashSet<(String, String)>> {
-    let mut all_media_files: HashSet<(String, String)> = HashSet::new();
-
-    for playlist in playlists.iter() {
-        match extract_media_files(playlist) {
-            Ok((src_basedir, files)) => {
-                for file in files {
-                    all_media_files.insert((src_basedir.clone(), file));
-                }
-            }
-            Err(e) => {
-                eprintln!(
-                    "Error extracting media files from playlist {}: {}",
-                    playlist, e
-                );
-                if !options.keep_going {
-                    return Err(e);
-                }
-            }
-        }
-    }
-
-    Ok(all_media_files)
-}
+    let logger: Arc<dyn Logger> = Arc::new(StderrLogger::with_verbosity(cli.verbose));
+    let run_id = options.run_id.clone();
+    let mut transfer = PlaylistTransfer::new(dest_dir)
+        .with_options(options)
+        .with_logger(logger);
+    if let Some(error_tracker) = error_tracker {
+        transfer = transfer.with_error_tracker(error_tracker);
+    }
+    if let Some(report) = report {
+        transfer = transfer.with_report(report);
+    }
+
+    if cli.dry_run {
+        // Walk the operation without touching the destination
+        let summary = transfer.dry_run(&cli.playlists)?;
+        print_run_summary(cli, &summary, &run_id)?;
+        return Ok(());
+    }
+
+    let summary = match &cli.retry_file {
+        // Process retry operations
+        Some(retry_file) => transfer.retry(retry_file)?,
+        // Normal operation mode, filling each destination in turn with
+        // --spillover
+        None => transfer.run(&cli.playlists)?,
+    };
 
-/// Process a single playlist and its associated media files
-fn process_single_playlist(
-    playlist: &str,
-    index: usize,
-    total_playlists: usize,
-    dest_dir: &str,
-    options: &CommandOptions,
-    media_files_map: &mut Vec<(String, HashSet<String>)>,
-    copied_files: &mut HashSet<(String, String)>,
-    error_tracker_ref: &mut Option<&mut ErrorTracker>,
-    total_media_files: usize,
-    successful_media_files: &mut usize,
-) -> Result<bool> {
-    playlist_manager::logger::get_logger().log_formatted(
-        "Put playlist \"{}\" into \"{}\"",
-        &[playlist, dest_dir],
-    );
-
-    match process_playlist(
-        playlist,
-        dest_dir,
-        media_files_map,
-        Some(index + 1),
-        Some(total_playlists),
-    ) {
-        Ok((src_basedir, files)) => {
-            // Filter out already copied files
-            let files_to_copy =
-                filter_already_copied_files(&src_basedir, &files, copied_files);
-
-            playlist_manager::logger::get_logger().log_formatted(
-                "Copying {} media files for playlist \"{}\"",
-                &[&files_to_copy.len().to_string(), playlist],
-            );
-
-            // Copy files for this playlist
-            match copy_media_files(
-                &src_basedir,
-                dest_dir,
-                files_to_copy.into_iter(),
-                options,
-                error_tracker_ref,
-                Some(total_media_files),
-                successful_media_files,
-            ) {
-                Ok((_copied, successful_files)) => {
-                    // Update copied_files set with only the successfully copied files
-                    for file in successful_files {
-                        copied_files.insert((src_basedir.clone(), file));
-                    }
-                    Ok(true) // Playlist processed successfully
-                }
-                Err(e) => {
-                    eprintln!("Error copying media files for playlist {}: {}", playlist, e);
-                    if !options.keep_going {
-                        process::exit(1);
-                    }
-                    Ok(false) // Playlist processing failed
-                }
-            }
-        }
-        Err(e) => {
-            eprintln!("Error processing playlist {}: {}", playlist, e);
-            if let Some(tracker) = error_tracker_ref {
-                tracker.add_failed_playlist(playlist.to_string());
-            }
-            if !options.keep_going {
-                process::exit(1);
-            }
-            Ok(false) // Playlist processing failed
-        }
+    print_run_summary(cli, &summary, &run_id)?;
+    if transfer.options().validate {
+        let (resolved_entries, total_entries) =
+            validate_playlists(&summary.written_playlists, transfer.options());
+        println!(
+            "({}/{}) playlist entries validated",
+            resolved_entries, total_entries
+        );
     }
-}
 
-/// Process normal operations (non-retry mode)
-fn process_normal_operations(
-    playlists: &[String],
-    dest_dir: &str,
-    options: &CommandOptions,
-    error_tracker_ref: &mut Option<&mut ErrorTracker>,
-    verbose: bool,
-) -> Result<(usize, usize, usize, usize)> {
-    // Initialize the static logger for this compilation unit
-    playlist_manager::logger::init_logger(verbose);
-
-    let total_playlists = playlists.len();
-    let mut successful_playlists = 0;
-    let mut successful_media_files = 0;
-    let mut media_files_map: Vec<(String, HashSet<String>)> = Vec::new();
-    let mut copied_files: HashSet<(String, String)> = HashSet::new();
-
-    // First, calculate the total number of unique media files across all playlists
-    let all_media_files = collect_all_media_files(playlists, options)?;
-    let total_media_files = all_media_files.len();
-
-    // Process each playlist and copy its media files one-by-one
-    for (i, playlist) in playlists.iter().enumerate() {
-        match process_single_playlist(
-            playlist,
-            i,
-            total_playlists,
-            dest_dir,
-            options,
-            &mut media_files_map,
-            &mut copied_files,
-            error_tracker_ref,
-            total_media_files,
-            &mut successful_media_files,
-        ) {
-            Ok(success) => {
-                if success {
-                    successful_playlists += 1;
-                }
-            }
-            Err(e) => return Err(e),
-        }
+    // Complete the report, whose rows have all been streamed by now
+    if let (Some(report_file), Some(report)) = (&cli.report, summary.report) {
+        report
+            .finish()
+            .map_err(|e| anyhow::anyhow!("Failed to write report file {}: {}", report_file, e))?;
     }
 
-    Ok((
-        successful_playlists,
-        total_playlists,
-        successful_media_files,
-        total_media_files,
-    ))
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -677,47 +408,85 @@ fn main() -> Result<()> {
         }
     };
 
+    // Print the options in effect before anything is read or written
+    if cli.print_config {
+        println!("{}", serde_json::to_string_pretty(&effective_config(&cli))?);
+        return Ok(());
+    }
+
+    // Read the device profiles before anything is copied: the playlist
+    // options of --profile, and the post-sync actions of --post-sync
+    let profiles = read_device_profile(cli.profile.as_deref())
+        .and_then(|profile| Ok((profile, read_device_profile(cli.post_sync.as_deref())?)));
+    let (profile, post_sync) = match profiles {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(255);
+        }
+    };
+
     // 2. Prepare Environment
-    let (dest_dir, options, mut error_tracker_owner) = match prepare_environment(&cli) {
+    let (dest_dir, mut options, mut error_tracker_owner, report) = match prepare_environment(&cli) {
         Ok(env_details) => env_details,
         Err(e) => {
             eprintln!("Error: {}", e);
-            // Exit code 2 for error file issues, 255 for dest_dir issues
+            // Exit code 2 for error file issues, 3 for a destination
+            // that cannot be written, 255 for dest_dir issues
             if e.to_string().contains("Failed to create error log file") {
                 process::exit(2);
+            } else if let Some(AppError::NotWritable { .. }) = e.downcast_ref() {
+                process::exit(3);
             } else {
                 process::exit(255);
             }
         }
     };
 
-    // 3. Create a mutable reference to the ErrorTracker for core logic
-    let mut error_tracker_ref: Option<&mut ErrorTracker> = error_tracker_owner.as_mut();
+    if let Some(profile) = &profile {
+        options.playlist_options = profile.playlist_options().to_vec();
+    }
+
+    // Wait for other runs into the same physical devices, holding the
+    // locks until the post-sync actions are done
+    let _device_locks = match cli.dry_run {
+        true => Vec::new(),
+        false => lock_destinations(&dest_dir, &options, cli.verbose > 0),
+    };
 
-    // 4. Run Core Logic
-    if let Err(e) = run_core_logic(&cli, &dest_dir, &options, &mut error_tracker_ref) {
+    // 3. Run Core Logic
+    if let Err(e) = run_core_logic(&cli, &dest_dir, options, error_tracker_owner.as_mut(), report) {
         eprintln!("Error during operations: {}", e);
         process::exit(1); // Operational error
     }
 
-    // 5. Perform Cleanup
+    // 4. Perform Cleanup
     if let Err(e) = perform_cleanup(&cli, error_tracker_owner) {
         eprintln!("Error during cleanup: {}", e);
         process::exit(2); // Error writing log file
     }
 
+    // 5. Run the post-sync actions of the device profile
+    if let (Some(profile), false) = (post_sync, cli.dry_run) {
+        let logger = StderrLogger::with_verbosity(cli.verbose);
+        if let Err(e) = profile.actions().run(Path::new(&dest_dir), &logger) {
+            eprintln!("Error during post-sync actions: {}", e);
+            process::exit(1);
+        }
+    }
+
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
-    use std::path::PathBuf;
-    use tempfile::TempDir;
+    use playlist_manager::exclusion_list::ExclusionList;
+    use playlist_manager::transfer::backup::Backup;
+    use playlist_manager::transfer::name_policy::NamePolicy;
 
     /// Helper function to create a test CLI struct
-    fn create_test_cli(
+    pub(crate) fn create_test_cli(
         dest: String,
         playlists: Vec<String>,
         verbose: bool,
@@ -727,12 +496,67 @@ mod tests {
         retry_file: Option<String>,
     ) -> Cli {
         Cli {
-            verbose,
+            verbose: u8::from(verbose),
             lyrics,
+            lyrics_ext: vec!["lrc".to_string()],
+            sidecar: Vec::new(),
+            artwork: false,
+            artwork_pattern: Vec::new(),
             keep_going,
+            verify: false,
+            buffer_size: None,
+            io_backend: IoBackend::Portable,
+            io_retries: 0,
+            jobs: 1,
+            dedup: false,
+            sync_state: false,
+            hash: HashAlgorithm::Crc32,
+            backup: false,
+            backup_dir: None,
+            xattrs: false,
+            strict: false,
+            list_missing: false,
+            spillover: Vec::new(),
+            min_free: 64 * 1024 * 1024,
+            max_fill: None,
+            rewrite_ext: Vec::new(),
+            name_policy: None,
+            xspf_to_m3u8: false,
+            progress: false,
+            allow_overlap: false,
+            source_read_only: false,
+            update: false,
+            album_playlists: false,
+            fat_order: false,
+            strip_extinf: false,
+            dest_encoding: PlaylistEncoding::Utf8,
+            transliterate: false,
+            max_depth: None,
+            exclude: Vec::new(),
+            exclude_from: Vec::new(),
+            audio_only: false,
+            audio_extensions: None,
+            drop_non_audio: false,
+            expand_dirs: false,
+            validate: false,
+            on_conflict: ConflictPolicy::FirstWins,
+            on_collision: CollisionStrategy::Number,
+            case: LetterCase::Preserve,
+            layout: Layout::Source,
+            itemize: false,
+            report: None,
+            playlist_summary: false,
+            json: false,
+            dry_run: false,
+            print_config: false,
+            post_sync: None,
+            profile: None,
             error_files,
             retry_file,
             dest,
+            from_list: Vec::new(),
+            list_root: None,
+            relative_to: None,
             playlists,
         }
     }
@@ -794,173 +618,6 @@ mod tests {
         }
     }
 
-    #[test]
-    fn test_prepare_environment_valid_dest() -> Result<()> {
-        let temp_dir = TempDir::new()?;
-        let dest_path = temp_dir.path().to_string_lossy().to_string();
-
-        let cli = create_test_cli(
-            dest_path.clone(),
-            vec!["playlist.m3u".to_string()],
-            true,
-            true,
-            true,
-            None,
-            None,
-        );
-
-        let result = prepare_environment(&cli)?;
-        let (dest_dir, options, error_tracker) = result;
-
-        // Check that dest_dir is absolute and exists
-        assert!(PathBuf::from(&dest_dir).is_absolute());
-        assert!(PathBuf::from(&dest_dir).exists());
-
-        // Check CommandOptions are set correctly
-        assert_eq!(options.copy_lyrics, true);
-        assert_eq!(options.keep_going, true);
-
-        // Check error_tracker is None when no error_files specified
-        assert!(error_tracker.is_none());
-
-        Ok(())
-    }
-
-    #[test]
-    fn test_prepare_environment_with_error_file() -> Result<()> {
-        let temp_dir = TempDir::new()?;
-        let dest_path = temp_dir.path().to_string_lossy().to_string();
-        let error_file_path = temp_dir.path().join("error.log");
-
-        let cli = create_test_cli(
-            dest_path,
-            vec!["playlist.m3u".to_string()],
-            false,
-            false,
-            true,
-            Some(error_file_path.to_string_lossy().to_string()),
-            None,
-        );
-
-        let result = prepare_environment(&cli)?;
-        let (_dest_dir, _options, error_tracker) = result;
-
-        // Check error_tracker is Some when error_files is specified
-        assert!(error_tracker.is_some());
-
-        // Check that error file was created (and is empty)
-        assert!(error_file_path.exists());
-        let content = fs::read_to_string(&error_file_path)?;
-        assert!(content.is_empty());
-
-        Ok(())
-    }
-
-    #[test]
-    fn test_prepare_environment_invalid_dest() {
-        let cli = create_test_cli(
-            "/nonexistent/path/that/should/not/exist".to_string(),
-            vec!["playlist.m3u".to_string()],
-            false,
-            false,
-            false,
-            None,
-            None,
-        );
-
-        let result = prepare_environment(&cli);
-        assert!(result.is_err());
-    }
-
-    #[test]
-    fn test_prepare_environment_error_file_creation_fails() {
-        let temp_dir = TempDir::new().unwrap();
-        let dest_path = temp_dir.path().to_string_lossy().to_string();
-
-        // Try to create error file in a non-existent directory
-        let cli = create_test_cli(
-            dest_path,
-            vec!["playlist.m3u".to_string()],
-            false,
-            false,
-            true,
-            Some("/nonexistent/dir/error.log".to_string()),
-            None,
-        );
-
-        let result = prepare_environment(&cli);
-        assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("Failed to create error log file"));
-    }
-
-    #[test]
-    fn test_perform_cleanup_no_error_file() -> Result<()> {
-        let cli = create_test_cli(
-            "/tmp".to_string(),
-            vec!["playlist.m3u".to_string()],
-            false,
-            false,
-            false,
-            None,
-            None,
-        );
-
-        let result = perform_cleanup(&cli, None);
-        assert!(result.is_ok());
-
-        Ok(())
-    }
-
-    #[test]
-    fn test_perform_cleanup_with_error_file() -> Result<()> {
-        let temp_dir = TempDir::new()?;
-        let error_file_path = temp_dir.path().join("error.log");
-
-        let cli = create_test_cli(
-            "/tmp".to_string(),
-            vec!["playlist.m3u".to_string()],
-            false,
-            false,
-            true,
-            Some(error_file_path.to_string_lossy().to_string()),
-            None,
-        );
-
-        let mut error_tracker = ErrorTracker::new();
-        error_tracker.add_failed_playlist("test_playlist.m3u".to_string());
-        error_tracker.add_failed_media_file("/music".to_string(), "song.mp3".to_string());
-
-        let result = perform_cleanup(&cli, Some(error_tracker));
-        assert!(result.is_ok());
-
-        // Check that error file was written with correct content
-        assert!(error_file_path.exists());
-        let content = fs::read_to_string(&error_file_path)?;
-        assert!(content.contains("P test_playlist.m3u"));
-        assert!(content.contains("M /music/song.mp3"));
-
-        Ok(())
-    }
-
-    #[test]
-    fn test_perform_cleanup_error_file_write_fails() {
-        // Try to write to a directory that doesn't exist
-        let cli = create_test_cli(
-            "/tmp".to_string(),
-            vec!["playlist.m3u".to_string()],
-            false,
-            false,
-            true,
-            Some("/nonexistent/dir/error.log".to_string()),
-            None,
-        );
-
-        let error_tracker = ErrorTracker::new();
-        let result = perform_cleanup(&cli, Some(error_tracker));
-        assert!(result.is_err());
-        assert!(result.unwrap_err().to_string().contains("Failed to write error log file"));
-    }
-
     #[test]
     fn test_command_options_creation() {
         let cli = create_test_cli(
@@ -973,86 +630,56 @@ mod tests {
             None,
         );
 
-        let options = CommandOptions {
+        let options = TransferOptions {
             copy_lyrics: cli.lyrics,
-            keep_going: cli.keep_going,
-        };
-
-        assert_eq!(options.copy_lyrics, false);
-        assert_eq!(options.keep_going, true);
-    }
-
-    #[test]
-    fn test_collect_all_media_files_empty_playlists() -> Result<()> {
-        let options = CommandOptions {
-            copy_lyrics: false,
-            keep_going: false,
-        };
-
-        let result = collect_all_media_files(&[], &options)?;
-        assert!(result.is_empty());
-
-        Ok(())
-    }
-
-    #[test]
-    fn test_collect_all_media_files_with_keep_going() -> Result<()> {
-        let options = CommandOptions {
-            copy_lyrics: false,
-            keep_going: true,
+            lyrics_extensions: cli.lyrics_ext.clone(),
+            sidecars: cli.sidecar.clone(),
+            artwork: artwork_patterns(&cli),
+            keep_going: cli.keep_going || cli.dry_run,
+            verify: cli.verify,
+            dedup: cli.dedup,
+            album_playlists: cli.album_playlists,
+            fat_order: cli.fat_order,
+            strip_extinf: cli.strip_extinf,
+            dest_encoding: cli.dest_encoding,
+            transliterate: cli.transliterate,
+            max_depth: cli.max_depth.map(usize::from),
+            max_file_size: None,
+            exclusions: ExclusionList::new(),
+            audio_extensions: audio_extensions(&cli),
+            drop_non_audio: cli.drop_non_audio,
+            expand_dirs: cli.expand_dirs,
+            validate: cli.validate,
+            on_conflict: cli.on_conflict,
+            on_collision: cli.on_collision,
+            case: cli.case,
+            layout: cli.layout,
+            itemize: cli.itemize || cli.dry_run,
+            lists: cli.from_list.clone(),
+            list_root: cli.list_root.clone(),
+            relative_to: cli.relative_to.clone(),
+            buffer_size: cli.buffer_size,
+            io_backend: cli.io_backend,
+            io_retries: cli.io_retries,
+            jobs: usize::from(cli.jobs),
+            sync_state: cli.sync_state,
+            hash: cli.hash,
+            backup: Backup::from_args(cli.backup, cli.backup_dir.as_deref()),
+            xattrs: cli.xattrs,
+            strict: cli.strict,
+            list_missing: cli.list_missing,
+            spillover: None,
+            rewrite_ext: Vec::new(),
+            name_policy: NamePolicy::default(),
+            xspf_to_m3u8: false,
+            progress: false,
+            source_read_only: false,
+            playlist_options: Vec::new(),
+            update: false,
+            run_id: String::new(),
         };
 
-        // Test with non-existent playlist files - should not fail with keep_going
-        let playlists = vec!["nonexistent1.m3u".to_string(), "nonexistent2.m3u".to_string()];
-        let result = collect_all_media_files(&playlists, &options)?;
-        assert!(result.is_empty());
-
-        Ok(())
-    }
-
-    #[test]
-    fn test_collect_all_media_files_without_keep_going() {
-        let options = CommandOptions {
-            copy_lyrics: false,
-            keep_going: false,
-        };
-
-        // Test with non-existent playlist files - should fail without keep_going
-        let playlists = vec!["nonexistent.m3u".to_string()];
-        let result = collect_all_media_files(&playlists, &options);
-        assert!(result.is_err());
-    }
-
-    #[test]
-    fn test_collect_all_media_files_deduplication() -> Result<()> {
-        let temp_dir = TempDir::new()?;
-        let playlist1_path = temp_dir.path().join("playlist1.m3u");
-        let playlist2_path = temp_dir.path().join("playlist2.m3u");
-
-        // Create two playlists with overlapping media files
-        fs::write(&playlist1_path, "song1.mp3\nsong2.mp3\n")?;
-        fs::write(&playlist2_path, "song2.mp3\nsong3.mp3\n")?;
-
-        let options = CommandOptions {
-            copy_lyrics: false,
-            keep_going: false,
-        };
-
-        let playlists = vec![
-            playlist1_path.to_string_lossy().to_string(),
-            playlist2_path.to_string_lossy().to_string(),
-        ];
-
-        let result = collect_all_media_files(&playlists, &options)?;
-
-        // Should have 3 unique files (song1.mp3, song2.mp3, song3.mp3)
-        assert_eq!(result.len(), 3);
-
-        let temp_dir_str = temp_dir.path().to_string_lossy().to_string();
-        assert!(result.contains(&(temp_dir_str.clone(), "song1.mp3".to_string())));
-        assert!(result.contains(&(temp_dir_str.clone(), "song2.mp3".to_string())));
-        assert!(result.contains(&(temp_dir_str, "song3.mp3".to_string())));
-
-        Ok(())
+        assert!(!options.copy_lyrics);
+        assert!(options.keep_going);
     }
 }
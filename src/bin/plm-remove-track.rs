@@ -0,0 +1,255 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Parser};
+use playlist_manager::logger::{Logger, StderrLogger};
+use playlist_manager::media_set::unique_media;
+use playlist_manager::path_interner::PathInterner;
+use playlist_manager::playlist_file::{is_playlist_file, PlaylistFile};
+use playlist_manager::track_matcher::{playlist_dir, TrackMatcher};
+use thiserror::Error;
+
+#[derive(Parser)]
+#[command(name = "plm-remove-track")]
+#[command(
+    about = "Remove tracks from playlist files and optionally delete unreferenced media files"
+)]
+#[command(version)]
+struct Cli {
+    /// Print verbose messages
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Treat TRACK as a wildcard pattern matched against playlist entries
+    #[arg(short = 'p', long = "pattern", action = ArgAction::SetTrue)]
+    pattern: bool,
+
+    /// Delete media files (and lyrics files with .lrc extension) no longer referenced by any playlist
+    #[arg(short = 'm', long = "media", action = ArgAction::SetTrue)]
+    media: bool,
+
+    /// Track to remove, given as a playlist entry or a path to the media file
+    track: String,
+
+    /// Playlist file(s) to remove the track from
+    #[arg(required = true)]
+    playlists: Vec<String>,
+}
+
+#[derive(Error, Debug)]
+enum AppError {
+    #[error("Invalid pattern: {0}")]
+    Pattern(#[from] glob::PatternError),
+}
+
+/// Remove the matching tracks from a playlist file
+/// Returns the removed entries
+fn remove_track_from_playlist(
+    playlist: &str,
+    matcher: &TrackMatcher,
+    logger: &dyn Logger,
+) -> Result<Vec<String>> {
+    let playlist_path = Path::new(playlist);
+    let dir = playlist_dir(playlist_path);
+
+    let mut playlist_file = PlaylistFile::read(playlist_path)
+        .with_context(|| format!("Failed to read playlist: {}", playlist))?;
+    let removed = playlist_file.remove_entries(|entry| matcher.matches(&dir, entry));
+
+    for entry in &removed {
+        logger.log_formatted(
+            "Removing track \"{}\" from playlist \"{}\"",
+            &[entry, playlist],
+        );
+    }
+
+    if !removed.is_empty() {
+        playlist_file
+            .write(playlist_path)
+            .with_context(|| format!("Failed to write playlist: {}", playlist))?;
+    }
+
+    Ok(removed)
+}
+
+/// List the playlist files in a directory
+fn list_playlists(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut playlists = Vec::new();
+
+    for entry in
+        fs::read_dir(dir).with_context(|| format!("Failed to read directory: {}", dir.display()))?
+    {
+        let path = entry?.path();
+        if path.is_file() && is_playlist_file(&path) {
+            playlists.push(path);
+        }
+    }
+
+    playlists.sort();
+    Ok(playlists)
+}
+
+/// Delete the removed tracks of the playlists in `dir` that no playlist
+/// in the directory refers to any more
+/// Returns the number of deleted files
+fn delete_unreferenced_tracks(dir: &Path, entries: &[&str], logger: &dyn Logger) -> Result<usize> {
+    let mut interner = PathInterner::new();
+    let media_set = unique_media(&list_playlists(dir)?, &mut interner)
+        .with_context(|| format!("Failed to read playlists in: {}", dir.display()))?;
+    let src_root = interner.intern(&dir.to_string_lossy());
+
+    let mut n_files = 0;
+
+    for entry in entries {
+        let media_file = dir.join(entry);
+
+        if media_set.contains(&src_root, &interner.intern(entry)) {
+            logger.log_formatted(
+                "Keeping media file \"{}\" referenced by another playlist",
+                &[&media_file.to_string_lossy()],
+            );
+            continue;
+        }
+
+        n_files += delete_track_files(&media_file, logger)?;
+        remove_empty_parents(&media_file, dir, logger)?;
+    }
+
+    Ok(n_files)
+}
+
+/// Delete a media file and its lyrics file (with .lrc extension)
+/// Returns the number of deleted files
+fn delete_track_files(media_file: &Path, logger: &dyn Logger) -> Result<usize> {
+    let mut n_files = 0;
+
+    if media_file.exists() {
+        logger.log_formatted(
+            "Deleting media file \"{}\"",
+            &[&media_file.to_string_lossy()],
+        );
+        fs::remove_file(media_file)
+            .with_context(|| format!("Failed to delete media file: {}", media_file.display()))?;
+        n_files += 1;
+    } else {
+        logger.log_formatted("Media file not found: {}", &[&media_file.to_string_lossy()]);
+    }
+
+    let lyrics_file = media_file.with_extension("lrc");
+    if lyrics_file.exists() {
+        logger.log_formatted(
+            "Deleting lyrics file \"{}\"",
+            &[&lyrics_file.to_string_lossy()],
+        );
+        fs::remove_file(&lyrics_file)
+            .with_context(|| format!("Failed to delete lyrics file: {}", lyrics_file.display()))?;
+        n_files += 1;
+    }
+
+    Ok(n_files)
+}
+
+/// Delete the empty ancestor directories of a file up to, but not
+/// including, `base_dir`
+fn remove_empty_parents(file: &Path, base_dir: &Path, logger: &dyn Logger) -> Result<()> {
+    let mut dir = file.parent();
+
+    while let Some(current) = dir {
+        if current == base_dir || !current.starts_with(base_dir) || !current.is_dir() {
+            break;
+        }
+        if fs::read_dir(current)?.next().is_some() {
+            break;
+        }
+
+        logger.log_formatted(
+            "Deleting empty directory \"{}\"",
+            &[&current.to_string_lossy()],
+        );
+        fs::remove_dir(current)
+            .with_context(|| format!("Failed to delete directory: {}", current.display()))?;
+
+        dir = current.parent();
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let logger = StderrLogger::new(cli.verbose);
+
+    let matcher = if cli.pattern {
+        TrackMatcher::pattern(&cli.track).map_err(|e| AppError::from(e).to_string())
+    } else {
+        TrackMatcher::path(&cli.track)
+            .map_err(|e| format!("Failed to resolve track {}: {}", cli.track, e))
+    };
+    let matcher = match matcher {
+        Ok(matcher) => matcher,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(255);
+        }
+    };
+
+    // Remove the track from each playlist, remembering where it was removed
+    let mut removed_tracks: BTreeSet<(PathBuf, String)> = BTreeSet::new();
+    let mut n_tracks = 0;
+
+    for playlist in &cli.playlists {
+        logger.log_formatted("Processing playlist \"{}\"", &[playlist]);
+
+        match remove_track_from_playlist(playlist, &matcher, &logger) {
+            Ok(removed) => {
+                n_tracks += removed.len();
+                let dir = playlist_dir(Path::new(playlist));
+                removed_tracks.extend(removed.into_iter().map(|entry| (dir.clone(), entry)));
+            }
+            Err(e) => {
+                eprintln!("Error processing playlist {}: {}", playlist, e);
+                process::exit(1);
+            }
+        }
+    }
+
+    if n_tracks == 0 {
+        eprintln!("Error: Track not found in any playlist: {}", cli.track);
+        process::exit(1);
+    }
+
+    // Delete the removed tracks no playlist refers to any more
+    let mut n_files = 0;
+
+    if cli.media {
+        let dirs: BTreeSet<&PathBuf> = removed_tracks.iter().map(|(dir, _)| dir).collect();
+
+        for dir in dirs {
+            let entries: Vec<&str> = removed_tracks
+                .iter()
+                .filter(|(track_dir, _)| track_dir == dir)
+                .map(|(_, entry)| entry.as_str())
+                .collect();
+
+            match delete_unreferenced_tracks(dir, &entries, &logger) {
+                Ok(deleted) => n_files += deleted,
+                Err(e) => {
+                    eprintln!("Error deleting media files: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+
+    if cli.verbose {
+        println!("Number of removed tracks: {}", n_tracks);
+        if cli.media {
+            println!("Number of deleted files: {}", n_files);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,194 @@
+use std::path::Path;
+use std::process;
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, ArgGroup, Parser};
+use playlist_manager::logger::{Logger, StderrLogger};
+use playlist_manager::playlist_file::PlaylistFile;
+use thiserror::Error;
+
+#[derive(Parser)]
+#[command(name = "plm-reorder")]
+#[command(about = "Move entries within a playlist file")]
+#[command(version)]
+#[command(group(
+    ArgGroup::new("operation")
+        .required(true)
+        .args(["move_range", "reverse", "interleave"])
+))]
+struct Cli {
+    /// Print verbose messages
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Move the entry N, or the entries N to M, given as N or N-M (1-based)
+    #[arg(short = 'm', long = "move", value_name = "RANGE", requires = "to")]
+    move_range: Option<String>,
+
+    /// Position the first moved entry ends up at (1-based)
+    #[arg(
+        short = 't',
+        long = "to",
+        value_name = "POSITION",
+        requires = "move_range"
+    )]
+    to: Option<usize>,
+
+    /// Reverse the order of the entries
+    #[arg(short = 'r', long = "reverse", action = ArgAction::SetTrue)]
+    reverse: bool,
+
+    /// Interleave the entries so that consecutive entries are by different artists
+    #[arg(short = 'i', long = "interleave", action = ArgAction::SetTrue)]
+    interleave: bool,
+
+    /// Playlist file to reorder
+    playlist: String,
+}
+
+#[derive(Error, Debug)]
+enum AppError {
+    #[error("Invalid range: {0}")]
+    InvalidMove(String),
+    #[error("Range {0}-{1} is out of range (1-{2})")]
+    RangeOutOfRange(usize, usize, usize),
+    #[error("Position {0} is out of range (1-{1})")]
+    PositionOutOfRange(usize, usize),
+}
+
+/// Parse a range of entries given as `N` or `N-M`
+/// Returns the 1-based first and last entries of the range
+fn parse_range(range: &str) -> Result<(usize, usize), AppError> {
+    let invalid = || AppError::InvalidMove(range.to_string());
+    let parse = |n: &str| n.trim().parse::<usize>().map_err(|_| invalid());
+
+    let (first, last) = match range.split_once('-') {
+        Some((first, last)) => (parse(first)?, parse(last)?),
+        None => (parse(range)?, parse(range)?),
+    };
+
+    if first == 0 || first > last {
+        return Err(invalid());
+    }
+    Ok((first, last))
+}
+
+/// Order of the entries after moving the entries `first` to `last` so
+/// that `first` becomes the entry at `to` (all 1-based)
+fn move_order(
+    n_entries: usize,
+    (first, last): (usize, usize),
+    to: usize,
+) -> Result<Vec<usize>, AppError> {
+    if last > n_entries {
+        return Err(AppError::RangeOutOfRange(first, last, n_entries));
+    }
+
+    let len = last - first + 1;
+    let max_position = n_entries - len + 1;
+    if to == 0 || to > max_position {
+        return Err(AppError::PositionOutOfRange(to, max_position));
+    }
+
+    let mut order: Vec<usize> = (0..n_entries)
+        .filter(|index| !(first - 1..last).contains(index))
+        .collect();
+    order.splice(to - 1..to - 1, first - 1..last);
+    Ok(order)
+}
+
+/// Artist of an entry, taken as the directory two levels above the media
+/// file as in `artist/album/title.flac`, or the directory of the media
+/// file if it has only one level
+fn artist_of(entry: &str) -> &str {
+    let mut dirs = entry.rsplit('/').skip(1);
+    let album = dirs.next();
+    dirs.next().or(album).unwrap_or("")
+}
+
+/// Order of the entries after interleaving them by artist
+///
+/// Artists take turns in the order they first appear in the playlist,
+/// and the entries of each artist keep their relative order.
+fn interleave_order(entries: &[String]) -> Vec<usize> {
+    let mut artists: Vec<(&str, Vec<usize>)> = Vec::new();
+
+    for (index, entry) in entries.iter().enumerate() {
+        let artist = artist_of(entry);
+        match artists.iter_mut().find(|(name, _)| *name == artist) {
+            Some((_, indices)) => indices.push(index),
+            None => artists.push((artist, vec![index])),
+        }
+    }
+
+    let longest = artists.iter().map(|(_, indices)| indices.len()).max();
+    (0..longest.unwrap_or(0))
+        .flat_map(|round| {
+            artists
+                .iter()
+                .filter_map(move |(_, indices)| indices.get(round).copied())
+        })
+        .collect()
+}
+
+/// Reorder the entries of the playlist file
+/// Returns the number of entries whose position changed
+fn reorder_playlist(cli: &Cli, logger: &dyn Logger) -> Result<usize> {
+    let playlist_path = Path::new(&cli.playlist);
+
+    let mut playlist_file = PlaylistFile::read(playlist_path)
+        .with_context(|| format!("Failed to read playlist: {}", cli.playlist))?;
+    let entries: Vec<String> = playlist_file.entries().collect();
+
+    let order = match (&cli.move_range, cli.to) {
+        (Some(range), Some(to)) => move_order(entries.len(), parse_range(range)?, to)?,
+        _ if cli.reverse => (0..entries.len()).rev().collect(),
+        _ => interleave_order(&entries),
+    };
+
+    let mut n_moved = 0;
+    for (position, &index) in order.iter().enumerate() {
+        if position != index {
+            logger.log_formatted(
+                "Moving entry \"{}\" from {} to {}",
+                &[
+                    &entries[index],
+                    &(index + 1).to_string(),
+                    &(position + 1).to_string(),
+                ],
+            );
+            n_moved += 1;
+        }
+    }
+
+    if n_moved > 0 {
+        playlist_file.reorder_entries(&order);
+        playlist_file
+            .write(playlist_path)
+            .with_context(|| format!("Failed to write playlist: {}", cli.playlist))?;
+    }
+
+    Ok(n_moved)
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let logger = StderrLogger::new(cli.verbose);
+
+    match reorder_playlist(&cli, &logger) {
+        Ok(n_moved) => {
+            if cli.verbose {
+                println!("Number of moved entries: {}", n_moved);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            match e.downcast_ref::<AppError>() {
+                Some(AppError::InvalidMove(_)) => process::exit(255),
+                _ => process::exit(1),
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Parser};
+use playlist_manager::archive::ArchiveFormat;
+use playlist_manager::archive_reader::{
+    detect_format, extract_archive, extract_tar, ExtractedFile,
+};
+use playlist_manager::error_file::unescape_path;
+#[cfg(feature = "s3")]
+use playlist_manager::object_store::{self, S3Config, S3Location};
+use playlist_manager::playlist_file::is_playlist_file;
+
+/// Name of the manifest written into archives by `plm-backup --manifest`
+const MANIFEST_NAME: &str = "plm-manifest.tsv";
+
+/// Command putting the extracted playlists into the destination
+const PUT_COMMAND: &str = "plm-put-playlist";
+
+#[derive(Parser)]
+#[command(name = "plm-restore")]
+#[command(
+    about = "Restore playlist files and associated media files from a tar or zip file to device"
+)]
+#[command(version)]
+struct Cli {
+    /// Print verbose messages
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Extract the archive into DIR and keep it, instead of a temporary directory
+    #[arg(long = "staging-dir", value_name = "DIR")]
+    staging_dir: Option<String>,
+
+    /// Archive file to restore, s3://BUCKET/KEY for object storage, or - for a tar archive on the standard input
+    archive: String,
+
+    /// Destination to put playlists and media files into
+    dest: String,
+
+    /// Options of plm-put-playlist to put the playlists with, after --
+    #[arg(last = true, value_name = "PUT_OPTIONS")]
+    put_options: Vec<String>,
+}
+
+/// Download the archive from object storage into a temporary file and
+/// extract it into `staging_dir`
+#[cfg(feature = "s3")]
+fn download_archive(cli: &Cli, staging_dir: &Path) -> Result<Vec<ExtractedFile>> {
+    let location = S3Location::parse(&cli.archive)?;
+    let config = S3Config::from_env()?;
+    let path = env::temp_dir().join(format!("plm-restore-{}.archive", process::id()));
+
+    if cli.verbose {
+        eprintln!("Download archive from {}", cli.archive);
+    }
+    object_store::download(&location, &path, &config)
+        .map_err(|e| anyhow::anyhow!("Failed to download archive from {}: {}", cli.archive, e))?;
+    let result = extract_archive(&path, staging_dir)
+        .map_err(|e| anyhow::anyhow!("Failed to extract {}: {}", cli.archive, e));
+    let _ = fs::remove_file(&path);
+    result
+}
+
+#[cfg(not(feature = "s3"))]
+fn download_archive(cli: &Cli, _staging_dir: &Path) -> Result<Vec<ExtractedFile>> {
+    anyhow::bail!(
+        "Archives in object storage require the s3 feature: {}",
+        cli.archive
+    )
+}
+
+/// Extract the archive into `staging_dir`
+fn extract(cli: &Cli, staging_dir: &Path) -> Result<Vec<ExtractedFile>> {
+    if cli.archive.starts_with("s3://") {
+        return download_archive(cli, staging_dir);
+    }
+    if cli.archive != "-" {
+        return extract_archive(Path::new(&cli.archive), staging_dir)
+            .map_err(|e| anyhow::anyhow!("Failed to extract {}: {}", cli.archive, e));
+    }
+
+    let mut stdin = io::stdin().lock();
+    let magic = stdin.fill_buf()?;
+    if detect_format(magic) == ArchiveFormat::Zip {
+        anyhow::bail!("Zip archives cannot be read from the standard input");
+    }
+    extract_tar(stdin, staging_dir)
+        .map_err(|e| anyhow::anyhow!("Failed to extract the standard input: {}", e))
+}
+
+/// Check the extracted files against the manifest of the archive, if
+/// any
+fn check_manifest(extracted: &[ExtractedFile]) -> Result<()> {
+    let Some(manifest) = extracted.iter().find(|file| file.name == MANIFEST_NAME) else {
+        return Ok(());
+    };
+    let files: HashMap<&str, &ExtractedFile> = extracted
+        .iter()
+        .map(|file| (file.name.as_str(), file))
+        .collect();
+
+    let contents = fs::read_to_string(&manifest.path)
+        .with_context(|| format!("Failed to read the manifest {}", MANIFEST_NAME))?;
+    for line in contents.lines().filter(|line| !line.starts_with('#')) {
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [name, size, crc32] = fields[..] else {
+            anyhow::bail!("Invalid line in the manifest: {}", line);
+        };
+        let name = unescape_path(name);
+        let file = files
+            .get(name.as_str())
+            .ok_or_else(|| anyhow::anyhow!("File in the manifest not archived: {}", name))?;
+        if size != file.size.to_string() || crc32 != format!("{:08x}", file.crc32) {
+            anyhow::bail!("File does not match the manifest: {}", name);
+        }
+    }
+    Ok(())
+}
+
+/// Playlists at the root of the archive, in order of their names
+fn root_playlists(extracted: &[ExtractedFile]) -> Vec<&PathBuf> {
+    let mut playlists: Vec<&ExtractedFile> = extracted
+        .iter()
+        .filter(|file| !file.name.contains('/') && is_playlist_file(&file.path))
+        .collect();
+    playlists.sort_by(|a, b| a.name.cmp(&b.name));
+    playlists.into_iter().map(|file| &file.path).collect()
+}
+
+/// Put the extracted playlists into the destination with
+/// `plm-put-playlist`, returning its exit status
+fn put_playlists(cli: &Cli, playlists: &[&PathBuf]) -> Result<i32> {
+    let exe =
+        env::current_exe().map_err(|e| anyhow::anyhow!("Failed to locate plm-restore: {}", e))?;
+    let put = exe.with_file_name(format!("{}{}", PUT_COMMAND, env::consts::EXE_SUFFIX));
+    let status = Command::new(&put)
+        .args(&cli.put_options)
+        .arg(&cli.dest)
+        .args(playlists)
+        .status()
+        .map_err(|e| anyhow::anyhow!("Failed to run {}: {}", put.display(), e))?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Extract the archive into `staging_dir` and put its playlists into
+/// the destination, returning the exit status of `plm-put-playlist`
+fn restore(cli: &Cli, staging_dir: &Path) -> Result<i32> {
+    // Checked ahead of extracting a large archive in vain
+    if !Path::new(&cli.dest).is_dir() {
+        anyhow::bail!("Destination directory not found: {}", cli.dest);
+    }
+    let extracted = extract(cli, staging_dir)?;
+    if cli.verbose {
+        eprintln!(
+            "Extracted {} files into {}",
+            extracted.len(),
+            staging_dir.display()
+        );
+    }
+    check_manifest(&extracted)?;
+
+    let playlists = root_playlists(&extracted);
+    if playlists.is_empty() {
+        anyhow::bail!("No playlists at the root of the archive: {}", cli.archive);
+    }
+    put_playlists(cli, &playlists)
+}
+
+/// Create the directory to extract the archive into, returning it and
+/// whether it is temporary
+fn create_staging_dir(cli: &Cli) -> Result<(PathBuf, bool)> {
+    match &cli.staging_dir {
+        Some(dir) => {
+            fs::create_dir_all(dir)
+                .map_err(|e| anyhow::anyhow!("Failed to create directory {}: {}", dir, e))?;
+            Ok((PathBuf::from(dir), false))
+        }
+        None => {
+            let dir = env::temp_dir().join(format!("plm-restore-{}", process::id()));
+            fs::create_dir(&dir).map_err(|e| {
+                anyhow::anyhow!("Failed to create directory {}: {}", dir.display(), e)
+            })?;
+            Ok((dir, true))
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    let result = create_staging_dir(&cli).and_then(|(staging_dir, temporary)| {
+        let result = restore(&cli, &staging_dir);
+        if temporary {
+            let _ = fs::remove_dir_all(&staging_dir);
+        }
+        result
+    });
+
+    match result {
+        Ok(status) => process::exit(status),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,635 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Parser};
+use playlist_manager::device_lock::{lock_devices, lock_dir, DeviceLock};
+use playlist_manager::device_profile::{read_synced, write_synced, DeviceProfile};
+use playlist_manager::diagnostics::{check_writable, write_error_advice};
+use playlist_manager::exclusion_list::ExclusionList;
+use playlist_manager::file_hash::HashAlgorithm;
+use playlist_manager::file_utils::paths_overlap;
+use playlist_manager::library_playlists::{days_ago, recently_added};
+use playlist_manager::logger::{Logger, StderrLogger};
+use playlist_manager::media_extensions::{is_media_file, parse_extension};
+use playlist_manager::media_set::read_playlist_media;
+use playlist_manager::path_interner::PathInterner;
+use playlist_manager::playlist_encoding::PlaylistEncoding;
+use playlist_manager::playlist_options::PlaylistMatch;
+use playlist_manager::run_id::new_run_id;
+use playlist_manager::source_guard::SourceGuard;
+use playlist_manager::track_matcher::{absolute_path, playlist_dir};
+use playlist_manager::transfer::backup::Backup;
+use playlist_manager::transfer::names::{Layout, LetterCase};
+use playlist_manager::transfer::read_only::playlist_guard;
+use playlist_manager::transfer::summary::{print_summary, CopySummary};
+use playlist_manager::transfer::{PlaylistTransfer, TransferOptions};
+use playlist_manager::trash::{move_to_trash, parse_age, today, trash_folder_name, TRASH_DIR};
+
+/// Extension of lyrics files without `--lyrics-ext`
+const LYRICS_EXTENSION: &str = "lrc";
+
+#[derive(Parser)]
+#[command(name = "plm-sync")]
+#[command(
+    about = "Mirror playlist files and associated media files to device, copying only new or changed files"
+)]
+#[command(version)]
+struct Cli {
+    /// Print the files moved to the trash, and the device waited for while another run copies into it
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Move media files and their lyrics files on the destination no given playlist references into the trash
+    #[arg(long = "delete", action = ArgAction::SetTrue)]
+    delete: bool,
+
+    /// Print the changes the sync would make, and the files --delete would move into the trash, without changing anything
+    #[arg(
+        short = 'n',
+        long = "dry-run",
+        action = ArgAction::SetTrue,
+        conflicts_with = "recently_added"
+    )]
+    dry_run: bool,
+
+    /// Run the actions of the device profile FILE, such as touching marker files, after a successful sync
+    #[arg(long = "post-sync", value_name = "FILE")]
+    post_sync: Option<String>,
+
+    /// Put the playlists the device profile FILE sets options for with those options
+    #[arg(long = "profile", value_name = "FILE")]
+    profile: Option<String>,
+
+    /// Sync the playlists the device profile NAME subscribes to when no playlists are given, with its playlist options, and run its actions
+    #[arg(long = "device", value_name = "NAME", conflicts_with_all = ["post_sync", "profile"])]
+    device: Option<String>,
+
+    /// Regenerate the playlist of the tracks recently added to the library DIR into DIR and mirror it too
+    #[arg(long = "recently-added", value_name = "DIR")]
+    recently_added: Option<String>,
+
+    /// Count the tracks modified within AGE, such as 30d or 2w, as recently added
+    #[arg(
+        long = "added-since",
+        value_name = "AGE",
+        value_parser = parse_age,
+        default_value = "30d",
+        requires = "recently_added"
+    )]
+    added_since: u64,
+
+    /// Fail before writing anything if the sync would write or delete under the directories of the playlists or their media files
+    #[arg(
+        long = "source-read-only",
+        action = ArgAction::SetTrue,
+        conflicts_with = "recently_added"
+    )]
+    source_read_only: bool,
+
+    /// Destination to mirror playlists and media files into
+    dest: String,
+
+    /// Playlist files to mirror
+    #[arg(required_unless_present_any = ["recently_added", "device"])]
+    playlists: Vec<String>,
+
+    /// Options of plm-put-playlist to put the playlists with, after --
+    #[arg(last = true, value_name = "PUT_OPTIONS")]
+    put_options: Vec<String>,
+}
+
+/// Options of plm-put-playlist the playlists are put with, given after
+/// --
+#[derive(Parser)]
+#[command(name = "plm-sync [OPTIONS] DEST [PLAYLIST ...] --")]
+#[command(no_binary_name = true, disable_version_flag = true)]
+struct PutOptions {
+    /// Print verbose messages (specify twice for debug messages)
+    #[arg(short = 'v', long = "verbose", action = ArgAction::Count)]
+    verbose: u8,
+
+    /// Copy lyrics files (.lrc unless --lyrics-ext is given) along with media files
+    #[arg(short = 'l', long = "lyrics", action = ArgAction::SetTrue)]
+    lyrics: bool,
+
+    /// Treat files with the comma-separated extensions, such as lrc,txt, as lyrics files (may be repeated)
+    #[arg(long = "lyrics-ext", value_name = "EXTS", value_delimiter = ',', value_parser = parse_extension, default_value = LYRICS_EXTENSION)]
+    lyrics_ext: Vec<String>,
+
+    /// Copy files sharing the stem of each media file with the comma-separated extensions, such as cue,pdf (may be repeated)
+    #[arg(long = "sidecar", value_name = "EXTS", value_delimiter = ',', value_parser = parse_extension)]
+    sidecar: Vec<String>,
+
+    /// Continue operation despite errors
+    #[arg(short = 'k', long = "keep-going", action = ArgAction::SetTrue)]
+    keep_going: bool,
+
+    /// Verify copied media files against checksums computed while copying
+    #[arg(long = "verify", action = ArgAction::SetTrue)]
+    verify: bool,
+
+    /// Hash copies to verify with crc32, blake3, sha256 or xxh3
+    #[arg(long = "hash", value_name = "ALGORITHM", default_value = "crc32")]
+    hash: HashAlgorithm,
+
+    /// Retry a copy failing with a transient error, such as EIO or a timeout, up to N (at most 10) times
+    #[arg(long = "io-retries", value_name = "N", default_value_t = 0, value_parser = clap::value_parser!(u32).range(..=10))]
+    io_retries: u32,
+
+    /// Copy the media files of each playlist with N (at most 64) threads
+    #[arg(short = 'j', long = "jobs", value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..=64))]
+    jobs: u16,
+
+    /// Move destination files about to be overwritten with different contents to NAME.plm-bak
+    #[arg(long = "backup", action = ArgAction::SetTrue)]
+    backup: bool,
+
+    /// Move destination files about to be overwritten into DIR instead (implies --backup)
+    #[arg(long = "backup-dir", value_name = "DIR")]
+    backup_dir: Option<String>,
+
+    /// Copy the user extended attributes of media files and lyrics files where supported
+    #[arg(long = "xattrs", action = ArgAction::SetTrue)]
+    xattrs: bool,
+
+    /// Keep the state of synced files on the destination and skip media files unchanged since
+    #[arg(long = "sync-state", action = ArgAction::SetTrue)]
+    sync_state: bool,
+
+    /// Remove #EXTINF and other comment lines from the copied playlists
+    #[arg(long = "strip-extinf", action = ArgAction::SetTrue)]
+    strip_extinf: bool,
+
+    /// Encoding of the copied playlists (utf8, utf16le or cp932)
+    #[arg(
+        long = "dest-encoding",
+        value_name = "ENCODING",
+        default_value = "utf8"
+    )]
+    dest_encoding: PlaylistEncoding,
+
+    /// Transliterate non-ASCII characters in destination names and playlist entries to ASCII
+    #[arg(long = "transliterate", action = ArgAction::SetTrue)]
+    transliterate: bool,
+
+    /// Letter case of destination names and playlist entries (lower, upper or preserve)
+    #[arg(long = "case", value_name = "CASE", default_value = "preserve")]
+    case: LetterCase,
+
+    /// Layout of destination paths (source, or tags for Artist/Album/NN Title.ext)
+    #[arg(long = "layout", value_name = "LAYOUT", default_value = "source")]
+    layout: Layout,
+
+    /// Do not copy tracks matching PATTERN or at the path PATTERN (may be repeated)
+    #[arg(short = 'x', long = "exclude", value_name = "PATTERN")]
+    exclude: Vec<String>,
+
+    /// Copy even if the destination and the directory of a playlist lie inside one another
+    #[arg(long = "allow-overlap", action = ArgAction::SetTrue)]
+    allow_overlap: bool,
+}
+
+impl PutOptions {
+    /// Options of the transfer putting the playlists, skipping the media
+    /// files whose destination files are up to date
+    ///
+    /// A dry run goes on past failures and itemizes the changes, as
+    /// `plm-put-playlist --dry-run` does.
+    fn transfer_options(&self, cli: &Cli) -> Result<TransferOptions> {
+        let mut exclusions = ExclusionList::new();
+        for exclusion in &self.exclude {
+            exclusions
+                .add(exclusion)
+                .map_err(|e| anyhow::anyhow!("Invalid exclusion: {}", e))?;
+        }
+        Ok(TransferOptions {
+            copy_lyrics: self.lyrics,
+            lyrics_extensions: self.lyrics_ext.clone(),
+            sidecars: self.sidecar.clone(),
+            keep_going: self.keep_going || cli.dry_run,
+            verify: self.verify,
+            hash: self.hash,
+            io_retries: self.io_retries,
+            jobs: usize::from(self.jobs),
+            backup: Backup::from_args(self.backup, self.backup_dir.as_deref()),
+            xattrs: self.xattrs,
+            sync_state: self.sync_state,
+            strip_extinf: self.strip_extinf,
+            dest_encoding: self.dest_encoding,
+            transliterate: self.transliterate,
+            case: self.case,
+            layout: self.layout,
+            exclusions,
+            itemize: cli.dry_run,
+            update: true,
+            run_id: new_run_id(),
+            source_read_only: cli.source_read_only,
+            ..TransferOptions::default()
+        })
+    }
+}
+
+/// Fail if the destination `dest_dir` and the directory of a playlist
+/// lie inside one another, as the sync would then write into its own
+/// sources
+fn check_overlap(dest_dir: &Path, playlists: &[String]) -> Result<()> {
+    for playlist in playlists {
+        let Ok(src_dir) = fs::canonicalize(playlist_dir(Path::new(playlist))) else {
+            continue;
+        };
+        if paths_overlap(dest_dir, &src_dir) {
+            anyhow::bail!(
+                "Destination {} overlaps the directory {} of {} (use --allow-overlap to sync anyway)",
+                dest_dir.display(),
+                src_dir.display(),
+                playlist
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Put the playlists into the destination `dest_dir`, copying only the
+/// media files whose destination files are not up to date, or only
+/// print the changes with `--dry-run`, and print the summary
+///
+/// The playlists the device profile sets options for are put with those
+/// options in place of the options of `plm-put-playlist` given.
+///
+/// The destination is test-written first unless it lies under the
+/// directory of a playlist with `--source-read-only`, as the copy is
+/// then refused once the playlists are planned.
+fn put_playlists(
+    cli: &Cli,
+    put: &PutOptions,
+    dest_dir: &Path,
+    playlists: &[String],
+    playlist_options: &[PlaylistMatch],
+) -> Result<CopySummary> {
+    if !put.allow_overlap {
+        check_overlap(dest_dir, playlists)?;
+    }
+    let guard = match cli.source_read_only {
+        true => playlist_guard(playlists, &[])?,
+        false => SourceGuard::new(),
+    };
+    if !cli.dry_run && guard.check(dest_dir).is_ok() {
+        check_writable(dest_dir).map_err(|e| {
+            anyhow::anyhow!(
+                "Destination {} is not writable: {}.  {}",
+                dest_dir.display(),
+                e,
+                write_error_advice(&e)
+            )
+        })?;
+    }
+
+    let logger: Arc<dyn Logger> = Arc::new(StderrLogger::with_verbosity(put.verbose));
+    let options = TransferOptions {
+        playlist_options: playlist_options.to_vec(),
+        ..put.transfer_options(cli)?
+    };
+    let mut transfer = PlaylistTransfer::new(dest_dir.to_string_lossy())
+        .with_options(options)
+        .with_logger(logger);
+    let summary = match cli.dry_run {
+        true => transfer.dry_run(playlists)?,
+        false => transfer.run(playlists)?,
+    };
+    print_summary(&summary, false);
+    Ok(summary)
+}
+
+/// Lock the physical device of the destination `dest_dir`, so that runs
+/// into partitions of the same device copy in turn
+///
+/// The device waited for is printed if `verbose`.  A destination whose
+/// device cannot be locked is synced without its lock, with a warning.
+fn lock_device(dest_dir: &Path, verbose: bool) -> Vec<DeviceLock> {
+    let waiting = |device: &str| {
+        if verbose {
+            eprintln!("Waiting for another run into device {} to finish", device);
+        }
+    };
+    lock_devices(&[dest_dir], &lock_dir(), waiting).unwrap_or_else(|e| {
+        eprintln!(
+            "Warning: Failed to lock the device of {}: {}",
+            dest_dir.display(),
+            e
+        );
+        Vec::new()
+    })
+}
+
+/// Guard protecting the directories of the source playlists and of
+/// their media files with `--source-read-only`
+fn source_guard(playlists: &[String]) -> Result<SourceGuard> {
+    let mut interner = PathInterner::new();
+    let mut guard = SourceGuard::new();
+    for playlist in playlists {
+        let media = read_playlist_media(Path::new(playlist), &mut interner)
+            .with_context(|| format!("Failed to read playlist: {}", playlist))?;
+        guard.protect(Path::new(&*media.src_root))?;
+        for file in &media.files {
+            guard.protect_file_dir(&Path::new(&*media.src_root).join(&**file))?;
+        }
+    }
+    Ok(guard)
+}
+
+/// Move the file at `path` into the trash folder `folder` of the
+/// destination `dest_dir`
+fn trash_file(cli: &Cli, dest_dir: &Path, path: &Path, kind: &str, folder: &str) -> Result<()> {
+    if cli.verbose {
+        eprintln!("Moving {} file \"{}\" to trash", kind, path.display());
+    }
+    move_to_trash(dest_dir, path, folder).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to move {} file {} to {}: {}",
+            kind,
+            path.display(),
+            TRASH_DIR,
+            e
+        )
+    })?;
+    Ok(())
+}
+
+/// Append the media files found recursively in `dir` to `files`
+///
+/// Directories whose names start with a dot, such as the sync state
+/// directory and the trash itself, are left alone, and so are symbolic
+/// links to directories, which may lead outside the destination.
+fn collect_dest_media_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut children: Vec<_> = fs::read_dir(dir)?.collect::<io::Result<_>>()?;
+    children.sort_by_key(|child| child.file_name());
+
+    for child in children {
+        let path = child.path();
+        if child.file_type()?.is_dir() {
+            if !child.file_name().to_string_lossy().starts_with('.') {
+                collect_dest_media_files(&path, files)?;
+            }
+        } else if !path.is_dir() && is_media_file(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Media files on the destination `dest_dir` the put playlists do not
+/// reference, along with their lyrics files, with the kind of each file
+///
+/// The referenced media files are the destination files of the media
+/// files put, as renamed by the options of `plm-put-playlist`, and the
+/// lyrics files are those with the extensions `lyrics_extensions`.  A
+/// lyrics file shared with a referenced media file of another extension
+/// is kept.  With `--source-read-only`, nothing is returned if a file
+/// lies under a directory of the source playlists or their media files.
+fn unreferenced_files(
+    cli: &Cli,
+    dest_dir: &Path,
+    summary: &CopySummary,
+    playlists: &[String],
+    lyrics_extensions: &[String],
+) -> Result<Vec<(PathBuf, &'static str)>> {
+    let referenced = summary
+        .dest_media_files
+        .iter()
+        .map(|path| absolute_path(path))
+        .collect::<io::Result<HashSet<_>>>()?;
+    let lyrics: HashSet<PathBuf> = referenced
+        .iter()
+        .flat_map(|path| lyrics_extensions.iter().map(|ext| path.with_extension(ext)))
+        .collect();
+    let mut media_files = Vec::new();
+    collect_dest_media_files(dest_dir, &mut media_files)
+        .with_context(|| format!("Failed to scan destination: {}", dest_dir.display()))?;
+
+    let mut unreferenced = Vec::new();
+    for path in media_files {
+        if referenced.contains(&absolute_path(&path)?) {
+            continue;
+        }
+        let lyrics_paths: Vec<PathBuf> = lyrics_extensions
+            .iter()
+            .map(|ext| path.with_extension(ext))
+            .collect();
+        unreferenced.push((path, "media"));
+        for lyrics_path in lyrics_paths {
+            if !lyrics.contains(&absolute_path(&lyrics_path)?) {
+                unreferenced.push((lyrics_path, "lyrics"));
+            }
+        }
+    }
+    unreferenced.retain(|(path, _)| path.is_file());
+
+    if cli.source_read_only {
+        let guard = source_guard(playlists)?;
+        for (path, _) in &unreferenced {
+            guard.check(path)?;
+        }
+    }
+    Ok(unreferenced)
+}
+
+/// Whether the files no playlist references may be moved into the
+/// trash, warning if not
+///
+/// The media files of a playlist failing with `--keep-going` are not
+/// known, so nothing is moved once any playlist has failed.
+fn may_delete(summary: &CopySummary) -> bool {
+    let failed = summary
+        .total_playlists
+        .saturating_sub(summary.successful_playlists);
+    if failed > 0 {
+        eprintln!(
+            "Warning: Not moving unreferenced files to trash, as {} playlists failed",
+            failed
+        );
+    }
+    failed == 0
+}
+
+/// Write the playlist of the tracks recently added to the library
+/// `library_dir` into it, returning its path
+fn regenerate_recently_added(cli: &Cli, library_dir: &str) -> Result<String> {
+    let library_dir = Path::new(library_dir);
+    if !library_dir.is_dir() {
+        anyhow::bail!("Library directory not found: {}", library_dir.display());
+    }
+    let written = recently_added(library_dir, days_ago(cli.added_since))
+        .and_then(|playlists| playlists.write(library_dir, library_dir))
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to generate recently added playlist in {}: {}",
+                library_dir.display(),
+                e
+            )
+        })?;
+    let playlist = written
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("No recently added playlist generated"))?;
+    if cli.verbose {
+        eprintln!("Generated playlist \"{}\"", playlist.display());
+    }
+    Ok(playlist.to_string_lossy().to_string())
+}
+
+/// Read the device profile `file` given by an option, if any
+fn read_profile(file: Option<&str>) -> Result<Option<DeviceProfile>> {
+    file.map(|file| {
+        DeviceProfile::from_file(Path::new(file))
+            .map_err(|e| anyhow::anyhow!("Failed to read device profile {}: {}", file, e))
+    })
+    .transpose()
+}
+
+/// Playlists the device profile subscribes to, warning of the
+/// subscriptions matching none and of the playlists synced before that
+/// no longer exist
+///
+/// The playlists matched for the first time are printed, once the
+/// subscribed playlists have been synced before.
+fn subscribed_playlists(cli: &Cli, profile: &DeviceProfile) -> Result<Vec<PathBuf>> {
+    let subscribed = profile
+        .resolve()
+        .map_err(|e| anyhow::anyhow!("Failed to resolve subscriptions: {}", e))?;
+    let synced = read_synced(Path::new(&cli.dest)).map_err(|e| {
+        anyhow::anyhow!("Failed to read subscribed playlists of {}: {}", cli.dest, e)
+    })?;
+
+    for subscription in &subscribed.unmatched {
+        eprintln!(
+            "Warning: Subscription matches no playlist: {}",
+            subscription
+        );
+    }
+    for playlist in synced.iter().filter(|playlist| !playlist.is_file()) {
+        eprintln!(
+            "Warning: Subscribed playlist no longer exists: {}",
+            playlist.display()
+        );
+    }
+    if !synced.is_empty() {
+        for playlist in subscribed
+            .playlists
+            .iter()
+            .filter(|playlist| !synced.contains(playlist))
+        {
+            println!("New subscribed playlist: {}", playlist.display());
+        }
+    }
+    Ok(subscribed.playlists)
+}
+
+/// Put the playlists into the destination, then move the files no
+/// playlist references into the trash with `--delete` and run the
+/// actions of the device profile
+///
+/// With `--recently-added`, the playlist of the recently added tracks is
+/// regenerated first and put along with the given playlists.  With
+/// `--device` and no playlists given, the playlists the profile
+/// subscribes to are put, and recorded on the destination once put.
+/// With `--dry-run`, the changes and the files that would be moved are
+/// only printed.
+fn sync(cli: &Cli) -> Result<()> {
+    if !Path::new(&cli.dest).is_dir() {
+        anyhow::bail!("Destination directory not found: {}", cli.dest);
+    }
+    let put = PutOptions::try_parse_from(&cli.put_options).unwrap_or_else(|e| e.exit());
+    let dest_dir = fs::canonicalize(&cli.dest)
+        .with_context(|| format!("Failed to get absolute path for {}", cli.dest))?;
+    let device = cli
+        .device
+        .as_ref()
+        .map(|name| DeviceProfile::find(name).map_err(|e| (name, e)))
+        .transpose()
+        .map_err(|(name, e)| anyhow::anyhow!("Failed to read device profile {}: {}", name, e))?;
+    // The profile of --device sets the playlist options and the actions,
+    // which are otherwise set by those of --profile and --post-sync
+    let profile = read_profile(cli.profile.as_deref())?;
+    let post_sync = read_profile(cli.post_sync.as_deref())?;
+    let mut playlists = cli.playlists.clone();
+    let subscribed = match &device {
+        Some(device) if playlists.is_empty() => Some(subscribed_playlists(cli, device)?),
+        _ => None,
+    };
+    if let Some(subscribed) = &subscribed {
+        playlists.extend(
+            subscribed
+                .iter()
+                .map(|playlist| playlist.to_string_lossy().to_string()),
+        );
+    }
+    if let Some(library_dir) = &cli.recently_added {
+        playlists.push(regenerate_recently_added(cli, library_dir)?);
+    }
+    if playlists.is_empty() {
+        anyhow::bail!("No playlists subscribed to by the device profile");
+    }
+
+    // Wait for other runs into the same physical device, holding the
+    // lock until the post-sync actions are done
+    let _device_locks = match cli.dry_run {
+        true => Vec::new(),
+        false => lock_device(&dest_dir, cli.verbose || put.verbose > 0),
+    };
+    let playlist_options = device
+        .as_ref()
+        .or(profile.as_ref())
+        .map_or(&[][..], |profile| profile.playlist_options());
+    let summary = put_playlists(cli, &put, &dest_dir, &playlists, playlist_options)?;
+    if cli.dry_run {
+        if cli.delete && may_delete(&summary) {
+            let unreferenced =
+                unreferenced_files(cli, &dest_dir, &summary, &playlists, &put.lyrics_ext)?;
+            for (path, kind) in &unreferenced {
+                println!("Would move {} file \"{}\" to trash", kind, path.display());
+            }
+            println!("({}) files would be moved to trash", unreferenced.len());
+        }
+        return Ok(());
+    }
+    if let Some(subscribed) = &subscribed {
+        write_synced(&dest_dir, subscribed).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to record subscribed playlists of {}: {}",
+                cli.dest,
+                e
+            )
+        })?;
+    }
+
+    if cli.delete && may_delete(&summary) {
+        let unreferenced =
+            unreferenced_files(cli, &dest_dir, &summary, &playlists, &put.lyrics_ext)?;
+        let folder = trash_folder_name(today());
+        for (path, kind) in &unreferenced {
+            trash_file(cli, &dest_dir, path, kind, &folder)?;
+        }
+        println!("({}) files moved to trash", unreferenced.len());
+    }
+    if let Some(profile) = device.as_ref().or(post_sync.as_ref()) {
+        profile
+            .actions()
+            .run(&dest_dir, &StderrLogger::new(cli.verbose))
+            .map_err(|e| anyhow::anyhow!("Failed to run post-sync actions: {}", e))?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    if let Err(e) = sync(&cli) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::Result;
+use clap::{ArgAction, Parser};
+use playlist_manager::media_set::read_playlist_media;
+use playlist_manager::path_interner::PathInterner;
+use playlist_manager::track_matcher::absolute_path;
+
+#[derive(Parser)]
+#[command(name = "plm-tracks")]
+#[command(about = "Print the absolute paths of the tracks of playlist files")]
+#[command(version)]
+struct Cli {
+    /// Print verbose messages
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Terminate each path with a NUL character instead of a newline
+    #[arg(short = '0', long = "null", action = ArgAction::SetTrue)]
+    null: bool,
+
+    /// Print each track only once across the playlists
+    #[arg(short = 'u', long = "unique", action = ArgAction::SetTrue)]
+    unique: bool,
+
+    /// Expand playlist entries naming directories into the media files in them
+    #[arg(long = "expand-dirs", action = ArgAction::SetTrue)]
+    expand_dirs: bool,
+
+    /// Playlist file(s) to print the tracks of
+    #[arg(required = true)]
+    playlists: Vec<String>,
+}
+
+/// Absolute paths of the tracks of a playlist, in playlist order
+fn playlist_tracks(playlist: &str, expand_dirs: bool) -> Result<Vec<PathBuf>> {
+    let mut interner = PathInterner::new();
+    let mut media = read_playlist_media(Path::new(playlist), &mut interner)
+        .map_err(|e| anyhow::anyhow!("Failed to open playlist {}: {}", playlist, e))?;
+    if expand_dirs {
+        media
+            .expand_directories(&mut interner)
+            .map_err(|e| anyhow::anyhow!("Failed to expand directories: {}", e))?;
+    }
+
+    let src_root = Path::new(&*media.src_root);
+    media
+        .files
+        .iter()
+        .map(|file| {
+            absolute_path(&src_root.join(&**file))
+                .map_err(|e| anyhow::anyhow!("Failed to resolve track {}: {}", file, e))
+        })
+        .collect()
+}
+
+/// Print the tracks of the playlists to `out`
+/// Returns the number of printed tracks
+fn print_tracks(cli: &Cli, out: &mut impl Write) -> Result<usize> {
+    let terminator = if cli.null { b'\0' } else { b'\n' };
+    let mut printed = HashSet::new();
+    let mut n_tracks = 0;
+
+    for playlist in &cli.playlists {
+        if cli.verbose {
+            eprintln!("Reading playlist \"{}\"", playlist);
+        }
+
+        for track in playlist_tracks(playlist, cli.expand_dirs)? {
+            if cli.unique && !printed.insert(track.clone()) {
+                continue;
+            }
+            if cli.verbose && !track.exists() {
+                eprintln!("Media file not found: {}", track.display());
+            }
+            out.write_all(track.to_string_lossy().as_bytes())?;
+            out.write_all(&[terminator])?;
+            n_tracks += 1;
+        }
+    }
+
+    out.flush()?;
+    Ok(n_tracks)
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut out = BufWriter::new(io::stdout().lock());
+
+    match print_tracks(&cli, &mut out) {
+        Ok(n_tracks) => {
+            if cli.verbose {
+                eprintln!("Number of tracks: {}", n_tracks);
+            }
+        }
+        Err(e) => {
+            // The reader has gone away, as with `plm tracks ... | head`
+            if e.downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+            {
+                return Ok(());
+            }
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+
+    Ok(())
+}
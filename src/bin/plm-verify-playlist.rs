@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+
+use anyhow::Result;
+use clap::{ArgAction, Parser};
+use playlist_manager::diagnostics::Severity;
+use playlist_manager::media_extensions::ExtensionAllowlist;
+use playlist_manager::track_matcher::{absolute_path, playlist_dir};
+use playlist_manager::track_path::TrackPath;
+use playlist_manager::xspf::{is_xspf_file, XspfPlaylist};
+use thiserror::Error;
+
+/// Byte order marks of UTF-16 playlists, little and big endian
+const UTF16_BOMS: [[u8; 2]; 2] = [[0xff, 0xfe], [0xfe, 0xff]];
+
+/// Extension of M3U playlists some players read as Latin-1
+const LATIN1_EXTENSION: &str = "m3u";
+
+#[derive(Parser)]
+#[command(name = "plm-verify-playlist")]
+#[command(
+    about = "Check the entries of playlist files for missing, duplicate and suspicious tracks"
+)]
+#[command(version)]
+struct Cli {
+    /// Print the playlists without issues as well
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Fail on warnings as well as on errors
+    #[arg(long = "strict", action = ArgAction::SetTrue)]
+    strict: bool,
+
+    /// Comma-separated checks to skip: missing, duplicate, non-audio, mixed-paths or encoding
+    #[arg(long = "ignore", value_name = "CHECKS", value_delimiter = ',')]
+    ignore: Vec<Check>,
+
+    /// Comma-separated audio extensions of the non-audio check, such as flac,mp3
+    #[arg(long = "audio-extensions", value_name = "LIST")]
+    audio_extensions: Option<ExtensionAllowlist>,
+
+    /// Playlist file(s) to verify
+    #[arg(required = true)]
+    playlists: Vec<String>,
+}
+
+#[derive(Error, Debug)]
+enum AppError {
+    #[error("Failed to read playlist {0}: {1}")]
+    Read(String, std::io::Error),
+}
+
+/// Check of the entries of a playlist
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Check {
+    /// Entries naming no file
+    Missing,
+    /// Entries naming the file of an earlier entry
+    Duplicate,
+    /// Entries without an audio extension
+    NonAudio,
+    /// Absolute entries among relative ones, or the other way round
+    MixedPaths,
+    /// Playlists that are not valid UTF-8
+    Encoding,
+}
+
+impl FromStr for Check {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "missing" => Ok(Self::Missing),
+            "duplicate" => Ok(Self::Duplicate),
+            "non-audio" => Ok(Self::NonAudio),
+            "mixed-paths" => Ok(Self::MixedPaths),
+            "encoding" => Ok(Self::Encoding),
+            _ => Err(format!(
+                "Unknown check: {} (expected missing, duplicate, non-audio, mixed-paths or encoding)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Missing => "missing",
+            Self::Duplicate => "duplicate",
+            Self::NonAudio => "non-audio",
+            Self::MixedPaths => "mixed-paths",
+            Self::Encoding => "encoding",
+        })
+    }
+}
+
+/// Issue found at a line of an M3U playlist, or a track of an XSPF
+/// playlist, counted from 1
+struct Issue {
+    position: usize,
+    check: Check,
+    severity: Severity,
+    message: String,
+}
+
+impl Issue {
+    fn new(position: usize, check: Check, severity: Severity, message: String) -> Self {
+        Self {
+            position,
+            check,
+            severity,
+            message,
+        }
+    }
+}
+
+/// Entries of a playlist with their positions, and the issues found
+/// decoding it
+struct Entries {
+    entries: Vec<(usize, String)>,
+    issues: Vec<Issue>,
+}
+
+/// Entries of an M3U playlist with their line numbers, checking that
+/// each line is valid UTF-8
+///
+/// Non-ASCII characters in an `.m3u` playlist are reported once, as
+/// some players read such playlists as Latin-1.
+fn m3u_entries(playlist: &Path, bytes: &[u8]) -> Entries {
+    let latin1 = playlist
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(LATIN1_EXTENSION));
+    let mut entries = Vec::new();
+    let mut issues = Vec::new();
+    let mut non_ascii_reported = false;
+
+    for (i, line) in bytes.split(|&b| b == b'\n').enumerate() {
+        let (line, problem) = match std::str::from_utf8(line) {
+            Err(_) => (
+                String::from_utf8_lossy(line).to_string(),
+                Some((Severity::Error, "Line is not valid UTF-8")),
+            ),
+            Ok(line) if line.contains('\u{fffd}') => (
+                line.to_string(),
+                Some((
+                    Severity::Warning,
+                    "Replacement character U+FFFD left by a lossy conversion",
+                )),
+            ),
+            Ok(line)
+                if latin1
+                    && !non_ascii_reported
+                    && !line.trim_start_matches('\u{feff}').is_ascii() =>
+            {
+                non_ascii_reported = true;
+                let message =
+                    "Non-ASCII characters in an .m3u playlist, which some players read as Latin-1";
+                (line.to_string(), Some((Severity::Warning, message)))
+            }
+            Ok(line) => (line.to_string(), None),
+        };
+        if let Some((severity, message)) = problem {
+            issues.push(Issue::new(
+                i + 1,
+                Check::Encoding,
+                severity,
+                message.to_string(),
+            ));
+        }
+        if let Some(entry) = TrackPath::from_line(&line) {
+            entries.push((i + 1, entry.into_string()));
+        }
+    }
+
+    Entries { entries, issues }
+}
+
+/// Entries of an XSPF playlist with their track numbers
+fn xspf_entries(bytes: &[u8]) -> Entries {
+    let mut issues = Vec::new();
+    let content = match std::str::from_utf8(bytes) {
+        Ok(content) => content.to_string(),
+        Err(e) => {
+            let message = format!("Playlist is not valid UTF-8: {}", e);
+            issues.push(Issue::new(1, Check::Encoding, Severity::Error, message));
+            String::from_utf8_lossy(bytes).to_string()
+        }
+    };
+    let entries = XspfPlaylist::parse(&content)
+        .entries()
+        .enumerate()
+        .map(|(i, location)| (i + 1, TrackPath::new(location).into_string()))
+        .collect();
+    Entries { entries, issues }
+}
+
+/// Whether an entry is an absolute path, including a Windows path with
+/// a drive letter such as `C:/Music/title.flac`
+fn is_absolute_entry(entry: &str) -> bool {
+    let bytes = entry.as_bytes();
+    entry.starts_with('/')
+        || (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+}
+
+/// Check the entries of a playlist, returning the issues found in the
+/// order of their positions
+fn verify_playlist(cli: &Cli, playlist: &str) -> Result<Vec<Issue>> {
+    let path = Path::new(playlist);
+    let bytes = fs::read(path).map_err(|e| AppError::Read(playlist.to_string(), e))?;
+    if UTF16_BOMS.iter().any(|bom| bytes.starts_with(bom)) {
+        let message =
+            "Playlist is encoded in UTF-16, while playlists are read as UTF-8".to_string();
+        return Ok(vec![Issue::new(
+            1,
+            Check::Encoding,
+            Severity::Error,
+            message,
+        )]);
+    }
+
+    let Entries {
+        entries,
+        mut issues,
+    } = match is_xspf_file(path) {
+        true => xspf_entries(&bytes),
+        false => m3u_entries(path, &bytes),
+    };
+    let audio_extensions = cli.audio_extensions.clone().unwrap_or_default();
+    let dir = playlist_dir(path);
+    let mut seen: HashMap<PathBuf, usize> = HashMap::new();
+
+    for (position, entry) in &entries {
+        let track = absolute_path(&dir.join(entry))?;
+        if !track.is_file() {
+            let message = format!("Media file not found: {}", entry);
+            issues.push(Issue::new(
+                *position,
+                Check::Missing,
+                Severity::Error,
+                message,
+            ));
+        }
+        if let Some(first) = seen.get(&track) {
+            let message = format!("Duplicate of the entry at {}: {}", first, entry);
+            issues.push(Issue::new(
+                *position,
+                Check::Duplicate,
+                Severity::Warning,
+                message,
+            ));
+        } else {
+            seen.insert(track, *position);
+        }
+        if !audio_extensions.allows(entry) {
+            let message = format!("Entry without an audio extension: {}", entry);
+            issues.push(Issue::new(
+                *position,
+                Check::NonAudio,
+                Severity::Warning,
+                message,
+            ));
+        }
+    }
+
+    // Report the entries of the less common kind
+    let n_absolute = entries
+        .iter()
+        .filter(|(_, entry)| is_absolute_entry(entry))
+        .count();
+    if n_absolute > 0 && n_absolute < entries.len() {
+        let absolute = n_absolute * 2 <= entries.len();
+        for (position, entry) in &entries {
+            if is_absolute_entry(entry) == absolute {
+                let kind = if absolute { "Absolute" } else { "Relative" };
+                let others = if absolute { "relative" } else { "absolute" };
+                let message = format!("{} entry among {} entries: {}", kind, others, entry);
+                issues.push(Issue::new(
+                    *position,
+                    Check::MixedPaths,
+                    Severity::Warning,
+                    message,
+                ));
+            }
+        }
+    }
+
+    issues.retain(|issue| !cli.ignore.contains(&issue.check));
+    issues.sort_by_key(|issue| issue.position);
+    Ok(issues)
+}
+
+/// Verify the playlists, printing their issues, and return whether any
+/// issue fails the verification
+fn verify_playlists(cli: &Cli) -> Result<bool> {
+    let (mut n_errors, mut n_warnings) = (0, 0);
+
+    for playlist in &cli.playlists {
+        let issues = verify_playlist(cli, playlist)?;
+        if issues.is_empty() && cli.verbose {
+            println!("{}: ok", playlist);
+        }
+        for issue in &issues {
+            println!(
+                "{}:{}: {}: {}: {}",
+                playlist, issue.position, issue.severity, issue.check, issue.message
+            );
+        }
+        n_errors += issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .count();
+        n_warnings += issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Warning)
+            .count();
+    }
+
+    match n_errors + n_warnings {
+        0 => println!("No problems found"),
+        _ => println!(
+            "Number of problems found: {} errors, {} warnings",
+            n_errors, n_warnings
+        ),
+    }
+    Ok(n_errors > 0 || (cli.strict && n_warnings > 0))
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    match verify_playlists(&cli) {
+        Ok(true) => process::exit(1),
+        Ok(false) => {}
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            match e.downcast_ref::<AppError>() {
+                Some(_) => process::exit(255),
+                None => process::exit(2),
+            }
+        }
+    }
+
+    Ok(())
+}
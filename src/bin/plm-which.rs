@@ -0,0 +1,154 @@
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use anyhow::{Context, Result};
+use clap::{ArgAction, Parser};
+use playlist_manager::media_set::read_playlist_media;
+use playlist_manager::path_interner::PathInterner;
+use playlist_manager::playlist_file::is_playlist_file;
+use playlist_manager::track_matcher::absolute_path;
+use playlist_manager::track_path::TrackPath;
+
+#[derive(Parser)]
+#[command(name = "plm-which")]
+#[command(about = "Print the playlist files referencing a track")]
+#[command(version)]
+struct Cli {
+    /// Print verbose messages
+    #[arg(short = 'v', long = "verbose", action = ArgAction::SetTrue)]
+    verbose: bool,
+
+    /// Terminate each path with a NUL character instead of a newline
+    #[arg(short = '0', long = "null", action = ArgAction::SetTrue)]
+    null: bool,
+
+    /// Expand playlist entries naming directories into the media files in them
+    #[arg(long = "expand-dirs", action = ArgAction::SetTrue)]
+    expand_dirs: bool,
+
+    /// Media file to look up
+    track: String,
+
+    /// Directory to search for playlist files recursively, or a playlist file (default: current directory)
+    #[arg(default_value = ".")]
+    playlist_dir: String,
+}
+
+/// Playlist files in a directory and its subdirectories, sorted by path,
+/// without following symbolic links
+fn find_playlists(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut playlists = Vec::new();
+    let mut dirs = vec![dir.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read directory: {}", dir.display()))?
+        {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(path);
+            } else if path.is_file() && is_playlist_file(&path) {
+                playlists.push(path);
+            }
+        }
+    }
+
+    playlists.sort();
+    Ok(playlists)
+}
+
+/// Positions, counted from 1, of the entries of a playlist resolving to
+/// the absolute path `track`
+fn track_positions(playlist: &Path, track: &Path, expand_dirs: bool) -> Result<Vec<usize>> {
+    let mut interner = PathInterner::new();
+    let mut media = read_playlist_media(playlist, &mut interner)
+        .with_context(|| format!("Failed to open playlist: {}", playlist.display()))?;
+    if expand_dirs {
+        media
+            .expand_directories(&mut interner)
+            .map_err(|e| anyhow::anyhow!("Failed to expand directories: {}", e))?;
+    }
+
+    let src_root = Path::new(&*media.src_root);
+    Ok(media
+        .files
+        .iter()
+        .enumerate()
+        .filter(|(_, file)| absolute_path(&src_root.join(&***file)).is_ok_and(|path| path == track))
+        .map(|(i, _)| i + 1)
+        .collect())
+}
+
+/// Print the playlists referencing the track to `out`
+/// Returns the number of printed playlists
+fn print_playlists(cli: &Cli, out: &mut impl Write) -> Result<usize> {
+    let terminator = if cli.null { b'\0' } else { b'\n' };
+    let track = absolute_path(TrackPath::new(&cli.track).as_ref())
+        .with_context(|| format!("Failed to resolve track: {}", cli.track))?;
+    let playlist_dir = Path::new(&cli.playlist_dir);
+    let playlists = match playlist_dir.is_file() {
+        true => vec![playlist_dir.to_path_buf()],
+        false => find_playlists(playlist_dir)?,
+    };
+    let mut n_playlists = 0;
+
+    for playlist in &playlists {
+        if cli.verbose {
+            eprintln!("Reading playlist \"{}\"", playlist.display());
+        }
+
+        let positions = match track_positions(playlist, &track, cli.expand_dirs) {
+            Ok(positions) => positions,
+            Err(e) => {
+                eprintln!("Warning: {}", e);
+                continue;
+            }
+        };
+        if positions.is_empty() {
+            continue;
+        }
+        if cli.verbose {
+            let positions: Vec<String> = positions.iter().map(usize::to_string).collect();
+            eprintln!(
+                "Found track at entry {} of \"{}\"",
+                positions.join(", "),
+                playlist.display()
+            );
+        }
+        out.write_all(playlist.to_string_lossy().as_bytes())?;
+        out.write_all(&[terminator])?;
+        n_playlists += 1;
+    }
+
+    out.flush()?;
+    Ok(n_playlists)
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let mut out = BufWriter::new(io::stdout().lock());
+
+    match print_playlists(&cli, &mut out) {
+        Ok(n_playlists) => {
+            if cli.verbose {
+                eprintln!("Number of playlists: {}", n_playlists);
+            }
+        }
+        Err(e) => {
+            // The reader has gone away, as with `plm which ... | head`
+            if e.downcast_ref::<io::Error>()
+                .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+            {
+                return Ok(());
+            }
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use playlist_manager::transfer::backup::Backup;
+use serde_json::{json, Value};
+
+use super::{audio_extensions, Cli};
+
+/// Options of a run in effect, keyed by their long names
+///
+/// The defaults of the options not given are filled in, and the options
+/// implied by others are turned on, such as `--keep-going` and
+/// `--itemize` by `--dry-run`, `--backup` by `--backup-dir` and
+/// `--audio-only` by `--audio-extensions`.  Paths are given as they are
+/// on the command line.
+pub fn effective_config(cli: &Cli) -> Value {
+    let audio_extensions = audio_extensions(cli);
+    let backup = Backup::from_args(cli.backup, cli.backup_dir.as_deref());
+    let rewrite_ext: Vec<String> = cli
+        .rewrite_ext
+        .iter()
+        .map(|(from, to)| format!("{}={}", from, to))
+        .collect();
+
+    let options = [
+        ("dest", json!(cli.dest)),
+        ("playlists", json!(cli.playlists)),
+        ("from-list", json!(cli.from_list)),
+        ("list-root", json!(cli.list_root)),
+        ("relative-to", json!(cli.relative_to)),
+        ("verbose", json!(cli.verbose)),
+        ("lyrics", json!(cli.lyrics)),
+        ("lyrics-ext", json!(cli.lyrics_ext)),
+        ("sidecar", json!(cli.sidecar)),
+        ("artwork", json!(cli.artwork)),
+        (
+            "artwork-pattern",
+            json!(cli
+                .artwork_pattern
+                .iter()
+                .map(|pattern| pattern.as_str())
+                .collect::<Vec<_>>()),
+        ),
+        ("keep-going", json!(cli.keep_going || cli.dry_run)),
+        ("verify", json!(cli.verify)),
+        ("buffer-size", json!(cli.buffer_size)),
+        ("io-backend", json!(cli.io_backend.to_string())),
+        ("io-retries", json!(cli.io_retries)),
+        ("jobs", json!(cli.jobs)),
+        ("dedup", json!(cli.dedup)),
+        ("hash", json!(cli.hash.to_string())),
+        ("backup", json!(backup != Backup::Off)),
+        ("backup-dir", json!(cli.backup_dir)),
+        ("xattrs", json!(cli.xattrs)),
+        ("sync-state", json!(cli.sync_state)),
+        ("update", json!(cli.update)),
+        ("album-playlists", json!(cli.album_playlists)),
+        ("fat-order", json!(cli.fat_order)),
+        ("strip-extinf", json!(cli.strip_extinf)),
+        ("dest-encoding", json!(cli.dest_encoding.to_string())),
+        ("transliterate", json!(cli.transliterate)),
+        ("case", json!(cli.case.to_string())),
+        ("layout", json!(cli.layout.to_string())),
+        ("rewrite-ext", json!(rewrite_ext)),
+        ("name-policy", json!(cli.name_policy)),
+        ("xspf-to-m3u8", json!(cli.xspf_to_m3u8)),
+        ("max-depth", json!(cli.max_depth)),
+        ("strict", json!(cli.strict)),
+        ("list-missing", json!(cli.list_missing)),
+        ("spillover", json!(cli.spillover)),
+        ("min-free", json!(cli.min_free)),
+        ("max-fill", json!(cli.max_fill)),
+        ("allow-overlap", json!(cli.allow_overlap)),
+        ("source-read-only", json!(cli.source_read_only)),
+        ("exclude", json!(cli.exclude)),
+        ("exclude-from", json!(cli.exclude_from)),
+        ("audio-only", json!(audio_extensions.is_some())),
+        (
+            "audio-extensions",
+            json!(audio_extensions
+                .as_ref()
+                .map(|allowlist| allowlist.extensions())),
+        ),
+        ("drop-non-audio", json!(cli.drop_non_audio)),
+        ("expand-dirs", json!(cli.expand_dirs)),
+        ("validate", json!(cli.validate)),
+        ("on-conflict", json!(cli.on_conflict.to_string())),
+        ("on-collision", json!(cli.on_collision.to_string())),
+        ("report", json!(cli.report)),
+        ("playlist-summary", json!(cli.playlist_summary)),
+        ("json", json!(cli.json)),
+        ("itemize", json!(cli.itemize || cli.dry_run)),
+        ("progress", json!(cli.progress)),
+        ("dry-run", json!(cli.dry_run)),
+        ("error-files", json!(cli.error_files)),
+        ("retry", json!(cli.retry_file)),
+        ("post-sync", json!(cli.post_sync)),
+        ("profile", json!(cli.profile)),
+    ];
+    Value::Object(
+        options
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect(),
+    )
+}
@@ -0,0 +1,506 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use playlist_manager::device_lock::{lock_devices, lock_dir, DeviceLock};
+use playlist_manager::device_profile::DeviceProfile;
+use playlist_manager::diagnostics::{check_writable, write_error_advice};
+use playlist_manager::exclusion_list::ExclusionList;
+use playlist_manager::file_utils::paths_overlap;
+use playlist_manager::run_id::new_run_id;
+use playlist_manager::source_guard::SourceGuard;
+use playlist_manager::track_matcher::playlist_dir;
+use playlist_manager::transfer::backup::Backup;
+use playlist_manager::transfer::name_policy::NamePolicy;
+use playlist_manager::transfer::read_only::playlist_guard;
+use playlist_manager::transfer::report::CopyReport;
+use playlist_manager::transfer::spillover::Spillover;
+use playlist_manager::transfer::summary::{json_summary, print_summary, CopySummary};
+use playlist_manager::transfer::{ErrorTracker, TransferOptions};
+use thiserror::Error;
+
+use super::{artwork_patterns, audio_extensions, Cli};
+
+#[derive(Error, Debug)]
+pub enum AppError {
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Failed to get absolute path: {0}")]
+    AbsPath(String),
+
+    #[error("Destination {dest} is not writable: {source}.  {advice}")]
+    NotWritable {
+        dest: String,
+        source: io::Error,
+        advice: &'static str,
+    },
+}
+
+/// Get the absolute path of a directory
+fn abs_dir(path: &str) -> Result<String, AppError> {
+    let path = Path::new(path);
+    let abs_path = fs::canonicalize(path).map_err(|e| {
+        AppError::AbsPath(format!(
+            "Failed to get absolute path for {}: {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    if !abs_path.is_dir() {
+        return Err(AppError::AbsPath(format!(
+            "{} is not a directory",
+            abs_path.display()
+        )));
+    }
+
+    Ok(abs_path.to_string_lossy().to_string())
+}
+
+/// Fail if the destination `dest_dir` and the source directory of a
+/// playlist or a file list lie inside one another, as the copy would
+/// then write into its own sources
+///
+/// Playlists that cannot be resolved fail when processed instead.
+fn check_overlap(cli: &Cli, dest_dir: &str) -> Result<()> {
+    let dest = Path::new(dest_dir);
+    let lists = cli.from_list.iter().map(|list| match &cli.list_root {
+        Some(list_root) => (list, PathBuf::from(list_root)),
+        None => (list, playlist_dir(Path::new(list))),
+    });
+    let sources = cli
+        .playlists
+        .iter()
+        .map(|playlist| match &cli.relative_to {
+            Some(dir) => (playlist, PathBuf::from(dir)),
+            None => (playlist, playlist_dir(Path::new(playlist))),
+        })
+        .chain(lists);
+    for (source, src_dir) in sources {
+        let Ok(src_dir) = fs::canonicalize(&src_dir) else {
+            continue;
+        };
+        if paths_overlap(dest, &src_dir) {
+            anyhow::bail!(
+                "Destination {} overlaps the directory {} of {} (use --allow-overlap to copy anyway)",
+                dest_dir,
+                src_dir.display(),
+                source
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Further destinations of `--spillover`, if any, each given once
+fn spillover(cli: &Cli, dest_dir: &str) -> Result<Option<Spillover>> {
+    if cli.spillover.is_empty() {
+        return Ok(None);
+    }
+    let mut dests: Vec<String> = Vec::with_capacity(cli.spillover.len());
+    for dest in &cli.spillover {
+        let dest = abs_dir(dest)?;
+        if dest == dest_dir || dests.contains(&dest) {
+            anyhow::bail!("Destination given more than once: {}", dest);
+        }
+        dests.push(dest);
+    }
+    Ok(Some(Spillover {
+        dests,
+        min_free: cli.min_free,
+        max_fill: cli.max_fill,
+    }))
+}
+
+/// Prepare the environment for operations
+pub fn prepare_environment(
+    cli: &Cli,
+) -> Result<(String, TransferOptions, Option<ErrorTracker>, Option<CopyReport>)> {
+    // Read the exclusions before creating any file
+    let mut exclusions = ExclusionList::new();
+    for exclusion in &cli.exclude {
+        exclusions
+            .add(exclusion)
+            .map_err(|e| anyhow::anyhow!("Invalid exclusion: {}", e))?;
+    }
+    for exclusion_file in &cli.exclude_from {
+        exclusions
+            .add_file(Path::new(exclusion_file))
+            .map_err(|e| {
+                anyhow::anyhow!("Failed to read exclusion file {}: {}", exclusion_file, e)
+            })?;
+    }
+
+    let name_policy = match &cli.name_policy {
+        Some(file) => NamePolicy::from_file(Path::new(file))
+            .map_err(|e| anyhow::anyhow!("Failed to read name policy {}: {}", file, e))?,
+        None => NamePolicy::default(),
+    };
+
+    // Create the error file up front (fail fast); failures are streamed
+    // into it as they happen and it remains empty if no errors occur
+    let run_id = new_run_id();
+    let error_tracker = match &cli.error_files {
+        Some(error_file) => {
+            let mut tracker = ErrorTracker::create(error_file)
+                .with_context(|| format!("Failed to create error log file: {}", error_file))?;
+            tracker.set_run_id(&run_id);
+            Some(tracker)
+        }
+        None => None,
+    };
+
+    // Get absolute path of destination directory
+    let dest_dir = abs_dir(&cli.dest)?;
+    let spillover = spillover(cli, &dest_dir)?;
+    if !cli.allow_overlap {
+        let dests = spillover.iter().flat_map(|spillover| &spillover.dests);
+        for dest in std::iter::once(&dest_dir).chain(dests) {
+            check_overlap(cli, dest)?;
+        }
+    }
+
+    // Test-write to the destinations before copying anything, so that a
+    // device mounted read-only fails once instead of for every file.  A
+    // destination under a read-only source is not test-written, as the
+    // copy is refused once the playlists are planned.
+    if !cli.dry_run {
+        let guard = match cli.source_read_only {
+            true => playlist_guard(&cli.playlists, &cli.from_list)?,
+            false => SourceGuard::new(),
+        };
+        let dests = spillover.iter().flat_map(|spillover| &spillover.dests);
+        for dest in std::iter::once(&dest_dir).chain(dests) {
+            if guard.check(Path::new(dest)).is_err() {
+                continue;
+            }
+            check_writable(Path::new(dest)).map_err(|e| AppError::NotWritable {
+                dest: dest.clone(),
+                advice: write_error_advice(&e),
+                source: e,
+            })?;
+        }
+    }
+
+    // Create the report up front as well; rows are streamed into it
+    let report = match &cli.report {
+        Some(report_file) => Some(CopyReport::create(report_file).map_err(|e| {
+            anyhow::anyhow!("Failed to create report file {}: {}", report_file, e)
+        })?),
+        None => None,
+    };
+
+    // Create the transfer options from CLI arguments
+    let options = TransferOptions {
+        copy_lyrics: cli.lyrics,
+        lyrics_extensions: cli.lyrics_ext.clone(),
+        sidecars: cli.sidecar.clone(),
+        artwork: artwork_patterns(cli),
+        keep_going: cli.keep_going || cli.dry_run,
+        verify: cli.verify,
+        dedup: cli.dedup,
+        album_playlists: cli.album_playlists,
+        fat_order: cli.fat_order,
+        strip_extinf: cli.strip_extinf,
+        dest_encoding: cli.dest_encoding,
+        transliterate: cli.transliterate,
+        max_depth: cli.max_depth.map(usize::from),
+        max_file_size: None,
+        exclusions,
+        audio_extensions: audio_extensions(cli),
+        drop_non_audio: cli.drop_non_audio,
+        expand_dirs: cli.expand_dirs,
+        validate: cli.validate,
+        on_conflict: cli.on_conflict,
+        on_collision: cli.on_collision,
+        case: cli.case,
+        layout: cli.layout,
+        itemize: cli.itemize || cli.dry_run,
+        lists: cli.from_list.clone(),
+        list_root: cli.list_root.clone(),
+        relative_to: cli.relative_to.clone(),
+        buffer_size: cli.buffer_size,
+        io_backend: cli.io_backend,
+        io_retries: cli.io_retries,
+        jobs: usize::from(cli.jobs),
+        sync_state: cli.sync_state,
+        update: cli.update,
+        run_id,
+        hash: cli.hash,
+        backup: Backup::from_args(cli.backup, cli.backup_dir.as_deref()),
+        xattrs: cli.xattrs,
+        strict: cli.strict,
+        list_missing: cli.list_missing,
+        spillover,
+        rewrite_ext: cli.rewrite_ext.clone(),
+        name_policy,
+        xspf_to_m3u8: cli.xspf_to_m3u8,
+        progress: cli.progress,
+        source_read_only: cli.source_read_only,
+        playlist_options: Vec::new(),
+    };
+
+    Ok((dest_dir, options, error_tracker, report))
+}
+
+/// Lock the physical devices of the destinations, so that runs into
+/// partitions of the same device copy in turn while runs into different
+/// devices copy in parallel
+///
+/// The devices waited for are printed if `verbose`.  Destinations whose
+/// devices cannot be locked are copied into without their locks, with a
+/// warning.
+pub fn lock_destinations(dest_dir: &str, options: &TransferOptions, verbose: bool) -> Vec<DeviceLock> {
+    let spillover = options.spillover.iter().flat_map(|spillover| &spillover.dests);
+    let dests: Vec<&str> = std::iter::once(dest_dir)
+        .chain(spillover.map(String::as_str))
+        .collect();
+    let waiting = |device: &str| {
+        if verbose {
+            eprintln!("Waiting for another run into device {} to finish", device);
+        }
+    };
+    lock_devices(&dests, &lock_dir(), waiting).unwrap_or_else(|e| {
+        eprintln!("Warning: Failed to lock the device of {}: {}", dest_dir, e);
+        Vec::new()
+    })
+}
+
+/// Print the summary of a run and its run ID, as text or as JSON
+pub fn print_run_summary(cli: &Cli, summary: &CopySummary, run_id: &str) -> Result<()> {
+    if cli.json {
+        let summary = json_summary(summary, run_id)?;
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+    } else {
+        print_summary(summary, cli.playlist_summary);
+        println!("Run ID: {}", run_id);
+    }
+    Ok(())
+}
+
+/// Perform cleanup operations (complete the error log if needed)
+pub fn perform_cleanup(cli: &Cli, error_tracker: Option<ErrorTracker>) -> Result<()> {
+    // Complete error log if requested
+    if let Some(error_file) = &cli.error_files {
+        if let Some(tracker) = error_tracker {
+            tracker
+                .finish()
+                .with_context(|| format!("Failed to write error log file: {}", error_file))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the device profile `file` given by an option, if any
+pub fn read_device_profile(file: Option<&str>) -> Result<Option<DeviceProfile>> {
+    file.map(|file| {
+        DeviceProfile::from_file(Path::new(file))
+            .map_err(|e| anyhow::anyhow!("Failed to read device profile {}: {}", file, e))
+    })
+    .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::create_test_cli;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_prepare_environment_valid_dest() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dest_path = temp_dir.path().to_string_lossy().to_string();
+
+        let cli = create_test_cli(
+            dest_path.clone(),
+            vec!["playlist.m3u".to_string()],
+            true,
+            true,
+            true,
+            None,
+            None,
+        );
+
+        let result = prepare_environment(&cli)?;
+        let (dest_dir, options, error_tracker, report) = result;
+
+        // Check that dest_dir is absolute and exists
+        assert!(PathBuf::from(&dest_dir).is_absolute());
+        assert!(PathBuf::from(&dest_dir).exists());
+
+        // Check TransferOptions are set correctly
+        assert!(options.copy_lyrics);
+        assert!(options.keep_going);
+
+        // Check error_tracker and report are None when not specified
+        assert!(error_tracker.is_none());
+        assert!(report.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepare_environment_with_error_file() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dest_path = temp_dir.path().to_string_lossy().to_string();
+        let error_file_path = temp_dir.path().join("error.log");
+
+        let cli = create_test_cli(
+            dest_path,
+            vec!["playlist.m3u".to_string()],
+            false,
+            false,
+            true,
+            Some(error_file_path.to_string_lossy().to_string()),
+            None,
+        );
+
+        let result = prepare_environment(&cli)?;
+        let (_dest_dir, _options, error_tracker, _report) = result;
+
+        // Check error_tracker is Some when error_files is specified
+        assert!(error_tracker.is_some());
+
+        // Check that error file was created (and is empty)
+        assert!(error_file_path.exists());
+        let content = fs::read_to_string(&error_file_path)?;
+        assert!(content.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prepare_environment_invalid_dest() {
+        let cli = create_test_cli(
+            "/nonexistent/path/that/should/not/exist".to_string(),
+            vec!["playlist.m3u".to_string()],
+            false,
+            false,
+            false,
+            None,
+            None,
+        );
+
+        let result = prepare_environment(&cli);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_prepare_environment_error_file_creation_fails() {
+        let temp_dir = TempDir::new().unwrap();
+        let dest_path = temp_dir.path().to_string_lossy().to_string();
+
+        // Try to create error file in a non-existent directory
+        let cli = create_test_cli(
+            dest_path,
+            vec!["playlist.m3u".to_string()],
+            false,
+            false,
+            true,
+            Some("/nonexistent/dir/error.log".to_string()),
+            None,
+        );
+
+        let result = prepare_environment(&cli);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("Failed to create error log file"));
+    }
+
+    #[test]
+    fn test_perform_cleanup_no_error_file() -> Result<()> {
+        let cli = create_test_cli(
+            "/tmp".to_string(),
+            vec!["playlist.m3u".to_string()],
+            false,
+            false,
+            false,
+            None,
+            None,
+        );
+
+        let result = perform_cleanup(&cli, None);
+        assert!(result.is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_perform_cleanup_with_error_file() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let error_file_path = temp_dir.path().join("error.log");
+        let error_file = error_file_path.to_string_lossy().to_string();
+
+        let cli = create_test_cli(
+            "/tmp".to_string(),
+            vec!["playlist.m3u".to_string()],
+            false,
+            false,
+            true,
+            Some(error_file.clone()),
+            None,
+        );
+
+        let mut error_tracker = ErrorTracker::create(&error_file)?;
+        error_tracker.add_failed_playlist("test_playlist.m3u".to_string());
+        error_tracker.add_failed_media_file("/music".to_string(), "song.mp3".to_string());
+
+        let result = perform_cleanup(&cli, Some(error_tracker));
+        assert!(result.is_ok());
+
+        // Check that error file was written with correct content
+        assert!(error_file_path.exists());
+        let content = fs::read_to_string(&error_file_path)?;
+        assert!(content.contains("P test_playlist.m3u"));
+        assert!(content.contains("M /music/song.mp3"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_tracker_streams_failures_immediately() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let error_file_path = temp_dir.path().join("error.log");
+
+        let mut error_tracker = ErrorTracker::create(&error_file_path.to_string_lossy())?;
+        error_tracker.add_failed_playlist("test_playlist.m3u".to_string());
+
+        // The entry must be on disk before the tracker is finished
+        let content = fs::read_to_string(&error_file_path)?;
+        assert_eq!(content, "# plm-error-file 2\nP test_playlist.m3u\n");
+
+        error_tracker.add_failed_media_file("/music".to_string(), "song.mp3".to_string());
+        let content = fs::read_to_string(&error_file_path)?;
+        assert_eq!(
+            content,
+            "# plm-error-file 2\nP test_playlist.m3u\nM /music/song.mp3\n"
+        );
+
+        error_tracker.finish()?;
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_perform_cleanup_error_file_write_fails() {
+        // Writes to /dev/full always fail with ENOSPC
+        let cli = create_test_cli(
+            "/tmp".to_string(),
+            vec!["playlist.m3u".to_string()],
+            false,
+            false,
+            true,
+            Some("/dev/full".to_string()),
+            None,
+        );
+
+        let mut error_tracker = ErrorTracker::create("/dev/full").unwrap();
+        error_tracker.add_failed_playlist("test_playlist.m3u".to_string());
+        let result = perform_cleanup(&cli, Some(error_tracker));
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("Failed to write error log file"));
+    }
+}
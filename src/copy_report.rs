@@ -0,0 +1,244 @@
+//! Reports of the media files processed by a copy
+//!
+//! A report holds one row per media file with the playlist it was
+//! processed for, its source and destination, what was done with it and
+//! how it went, for analysing a large sync in a spreadsheet.  Reports
+//! are written as CSV, or as TSV if the file name ends with `.tsv`.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Column names written as the first row of a report
+const COLUMNS: [&str; 8] = [
+    "playlist",
+    "source",
+    "destination",
+    "action",
+    "bytes",
+    "duration",
+    "result",
+    "reason",
+];
+
+/// Format of a report file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ReportFormat {
+    /// Comma-separated values, quoted as in RFC 4180
+    Csv,
+    /// Tab-separated values, with tabs and line breaks in fields
+    /// replaced by spaces
+    Tsv,
+}
+
+impl ReportFormat {
+    /// Format of the report file at `path`, chosen by its extension
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("tsv") => Self::Tsv,
+            _ => Self::Csv,
+        }
+    }
+
+    /// Escape a field for the format
+    fn field(self, value: &str) -> String {
+        match self {
+            Self::Csv if value.contains([',', '"', '\n', '\r']) => {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            }
+            Self::Csv => value.to_string(),
+            Self::Tsv => value.replace(['\t', '\n', '\r'], " "),
+        }
+    }
+
+    /// Separator between the fields of a row
+    fn separator(self) -> &'static str {
+        match self {
+            Self::Csv => ",",
+            Self::Tsv => "\t",
+        }
+    }
+}
+
+/// What was done with a media file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ReportAction {
+    /// The media file was copied, or copying it was attempted
+    Copy,
+    /// The media file was not copied, for the reason given in the row
+    Skip,
+}
+
+impl fmt::Display for ReportAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Copy => "copy",
+            Self::Skip => "skip",
+        })
+    }
+}
+
+/// A row of a report, describing one media file
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReportRow<'a> {
+    pub playlist: &'a str,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub source: &'a Path,
+    #[cfg_attr(feature = "serde", serde(borrow))]
+    pub destination: &'a Path,
+    pub action: ReportAction,
+    pub bytes: Option<u64>,         // Size of the source file, if known
+    pub duration: Option<Duration>, // Time spent copying, if copied
+    pub succeeded: bool,
+    pub reason: &'a str, // Why the file was skipped or failed, if so
+}
+
+/// Writer appending rows to a report
+///
+/// The column names are written first, and each row is flushed as soon
+/// as it is written, so the report survives a crash part way through.
+#[derive(Debug)]
+pub struct ReportWriter<W: Write> {
+    writer: W,
+    format: ReportFormat,
+}
+
+impl ReportWriter<File> {
+    /// Create (or truncate) the report at `path`, in the format chosen by
+    /// its extension
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Self::new(File::create(path)?, ReportFormat::from_path(path))
+    }
+}
+
+impl<W: Write> ReportWriter<W> {
+    /// Write a report into `writer`, starting with the column names
+    pub fn new(writer: W, format: ReportFormat) -> io::Result<Self> {
+        let mut report = Self { writer, format };
+        report.write_fields(COLUMNS.iter().map(|column| column.to_string()))?;
+        Ok(report)
+    }
+
+    /// Append a row
+    pub fn write_row(&mut self, row: &ReportRow) -> io::Result<()> {
+        self.write_fields(
+            [
+                row.playlist.to_string(),
+                row.source.to_string_lossy().to_string(),
+                row.destination.to_string_lossy().to_string(),
+                row.action.to_string(),
+                row.bytes.map(|bytes| bytes.to_string()).unwrap_or_default(),
+                row.duration
+                    .map(|duration| format!("{:.3}", duration.as_secs_f64()))
+                    .unwrap_or_default(),
+                match row.succeeded {
+                    true => "ok".to_string(),
+                    false => "failed".to_string(),
+                },
+                row.reason.to_string(),
+            ]
+            .into_iter(),
+        )
+    }
+
+    /// Write the fields of a row and flush it
+    fn write_fields(&mut self, fields: impl Iterator<Item = String>) -> io::Result<()> {
+        let line: Vec<String> = fields.map(|field| self.format.field(&field)).collect();
+        writeln!(self.writer, "{}", line.join(self.format.separator()))?;
+        self.writer.flush()
+    }
+
+    /// Unwrap the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row<'a>(playlist: &'a str, source: &'a Path, reason: &'a str) -> ReportRow<'a> {
+        ReportRow {
+            playlist,
+            source,
+            destination: Path::new("/dest/a.flac"),
+            action: ReportAction::Copy,
+            bytes: Some(1024),
+            duration: Some(Duration::from_millis(1500)),
+            succeeded: reason.is_empty(),
+            reason,
+        }
+    }
+
+    #[test]
+    fn test_write_csv() -> io::Result<()> {
+        let mut report = ReportWriter::new(Vec::new(), ReportFormat::Csv)?;
+        report.write_row(&row("list.m3u8", Path::new("/music/a.flac"), ""))?;
+        report.write_row(&row(
+            "a, b.m3u8",
+            Path::new("/music/\"b\".flac"),
+            "No space",
+        ))?;
+
+        let content = String::from_utf8(report.into_inner()).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "playlist,source,destination,action,bytes,duration,result,reason",
+                "list.m3u8,/music/a.flac,/dest/a.flac,copy,1024,1.500,ok,",
+                "\"a, b.m3u8\",\"/music/\"\"b\"\".flac\",/dest/a.flac,copy,1024,1.500,failed,No space",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_tsv() -> io::Result<()> {
+        assert_eq!(
+            ReportFormat::from_path(Path::new("report.TSV")),
+            ReportFormat::Tsv
+        );
+        assert_eq!(
+            ReportFormat::from_path(Path::new("report.csv")),
+            ReportFormat::Csv
+        );
+
+        let mut report = ReportWriter::new(Vec::new(), ReportFormat::Tsv)?;
+        report.write_row(&row(
+            "a,\tb.m3u8",
+            Path::new("/music/a.flac"),
+            "Line\nbreak",
+        ))?;
+
+        let content = String::from_utf8(report.into_inner()).unwrap();
+        assert_eq!(
+            content.lines().nth(1),
+            Some("a, b.m3u8\t/music/a.flac\t/dest/a.flac\tcopy\t1024\t1.500\tfailed\tLine break")
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let json =
+            serde_json::to_string(&row("list.m3u8", Path::new("/music/a.flac"), "")).unwrap();
+        assert!(json.contains(r#""action":"copy""#));
+
+        let row: ReportRow = serde_json::from_str(&json).unwrap();
+        assert_eq!(row.source, Path::new("/music/a.flac"));
+        assert_eq!(row.duration, Some(Duration::from_millis(1500)));
+        assert!(row.succeeded);
+    }
+}
@@ -0,0 +1,224 @@
+//! Index of the files and directories already present on a destination
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Size and modification time of a file found on the destination
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileEntry {
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Status of a destination file according to the index
+#[derive(Debug, PartialEq, Eq)]
+pub enum FileStatus<'a> {
+    /// The file exists on the destination
+    Present(&'a FileEntry),
+    /// The parent directory was scanned and the file is not there
+    Absent,
+    /// The parent directory was not scanned; the filesystem must be asked
+    Unknown,
+}
+
+/// One-time index of the destination directories a run writes into
+///
+/// Listing each relevant directory once up front replaces a stat call
+/// per file during the copy loop, which is slow on MTP and network
+/// filesystems.  Paths are stored joined to the destination root.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DestIndex {
+    root: PathBuf,
+    scanned: HashSet<PathBuf>,
+    dirs: HashSet<PathBuf>,
+    files: HashMap<PathBuf, FileEntry>,
+}
+
+impl DestIndex {
+    /// Create an empty index for the destination root
+    ///
+    /// Nothing is known about the destination, so every lookup reports
+    /// [`FileStatus::Unknown`] until directories are scanned.
+    pub fn new(root: &Path) -> Self {
+        let mut dirs = HashSet::new();
+        dirs.insert(root.to_path_buf());
+        Self {
+            root: root.to_path_buf(),
+            scanned: HashSet::new(),
+            dirs,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Build an index by listing each directory (relative to `root`) once
+    ///
+    /// Directories are read non-recursively; a directory that does not
+    /// exist is recorded as scanned so that files in it are known to be
+    /// absent.
+    pub fn scan<I, P>(root: &Path, rel_dirs: I) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let mut index = Self::new(root);
+        index.scan_dirs(rel_dirs)?;
+        Ok(index)
+    }
+
+    /// List more directories (relative to the root) into the index, such
+    /// as those of playlists parsed after the index was built
+    ///
+    /// Directories already scanned are not read again.
+    pub fn scan_dirs<I, P>(&mut self, rel_dirs: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        for rel_dir in rel_dirs {
+            self.scan_dir(&self.root.join(rel_dir))?;
+        }
+        Ok(())
+    }
+
+    /// List a single directory and record its files
+    fn scan_dir(&mut self, dir: &Path) -> io::Result<()> {
+        if !self.scanned.insert(dir.to_path_buf()) {
+            return Ok(());
+        }
+
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        self.add_dir(dir);
+        for entry in entries {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                self.dirs.insert(entry.path());
+            } else if file_type.is_file() {
+                let metadata = entry.metadata()?;
+                self.files.insert(
+                    entry.path(),
+                    FileEntry {
+                        len: metadata.len(),
+                        modified: metadata.modified().ok(),
+                    },
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Record a directory (and its ancestors below the root) as existing
+    ///
+    /// Callers creating destination directories record them here so that
+    /// each directory is created at most once per run.
+    pub fn add_dir(&mut self, dir: &Path) {
+        for ancestor in dir.ancestors() {
+            if !self.dirs.insert(ancestor.to_path_buf()) || ancestor == self.root {
+                break;
+            }
+        }
+    }
+
+    /// Destination root the index was built for
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Whether the directory is known to exist on the destination
+    pub fn contains_dir(&self, dir: &Path) -> bool {
+        self.dirs.contains(dir)
+    }
+
+    /// Look up a destination file
+    pub fn file_status(&self, path: &Path) -> FileStatus<'_> {
+        if let Some(entry) = self.files.get(path) {
+            return FileStatus::Present(entry);
+        }
+        match path.parent() {
+            Some(parent) if self.scanned.contains(parent) => FileStatus::Absent,
+            _ => FileStatus::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scan_records_files_and_dirs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("artist1/album1"))?;
+        fs::write(root.join("artist1/album1/title1.flac"), "content")?;
+
+        let index = DestIndex::scan(root, ["artist1/album1"])?;
+
+        assert!(index.contains_dir(&root.join("artist1/album1")));
+        assert!(index.contains_dir(&root.join("artist1")));
+        assert!(index.contains_dir(root));
+        match index.file_status(&root.join("artist1/album1/title1.flac")) {
+            FileStatus::Present(entry) => assert_eq!(entry.len, 7),
+            status => panic!("unexpected status: {:?}", status),
+        }
+        assert_eq!(
+            index.file_status(&root.join("artist1/album1/title2.flac")),
+            FileStatus::Absent
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scan_missing_dir_is_absent() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+
+        let index = DestIndex::scan(root, ["artist1/album1"])?;
+
+        assert!(!index.contains_dir(&root.join("artist1/album1")));
+        assert_eq!(
+            index.file_status(&root.join("artist1/album1/title1.flac")),
+            FileStatus::Absent
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_dir_records_ancestors() {
+        let root = Path::new("/dest");
+        let mut index = DestIndex::new(root);
+
+        index.add_dir(&root.join("artist1/album1"));
+
+        assert!(index.contains_dir(&root.join("artist1/album1")));
+        assert!(index.contains_dir(&root.join("artist1")));
+        assert!(!index.contains_dir(&root.join("artist2")));
+    }
+
+    #[test]
+    fn test_unscanned_dir_is_unknown() {
+        let root = Path::new("/dest");
+        let index = DestIndex::new(root);
+
+        assert!(index.contains_dir(root));
+        assert_eq!(
+            index.file_status(&root.join("artist1/title1.flac")),
+            FileStatus::Unknown
+        );
+    }
+}
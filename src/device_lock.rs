@@ -0,0 +1,197 @@
+//! Locks serialising syncs to the same physical device
+//!
+//! Copying into two partitions of one SD card at once makes its slow
+//! controller switch between them, which takes longer than filling each
+//! in turn.  A sync takes a lock named after the physical device of each
+//! destination, so that syncs to the same device wait for each other
+//! while syncs to different devices run in parallel.
+//!
+//! The locks are advisory locks on files in the directory given by the
+//! `PLM_LOCK_DIR` environment variable, or the temporary directory, and
+//! are released when the process exits, however it exits.
+
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Environment variable naming the directory of the lock files
+pub const LOCK_DIR_VAR: &str = "PLM_LOCK_DIR";
+
+/// Lock of a physical device, held until it is dropped
+#[derive(Debug)]
+pub struct DeviceLock {
+    device: String,
+    _file: File, // Locked file, unlocked when closed
+}
+
+impl DeviceLock {
+    /// Name of the locked device, such as `mmcblk0`
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+}
+
+/// Directory of the lock files, from `PLM_LOCK_DIR` or the temporary
+/// directory
+pub fn lock_dir() -> PathBuf {
+    std::env::var_os(LOCK_DIR_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// Name of the physical device holding `path`, or its nearest existing
+/// ancestor
+///
+/// On Linux, partitions are named after the disk holding them, so that
+/// two partitions of a card give the same name.  Elsewhere on Unix, the
+/// name is the device number of the filesystem, and on Windows the drive
+/// or share of the path.
+pub fn physical_device(path: &Path) -> io::Result<String> {
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(path);
+    device_name(existing)
+}
+
+#[cfg(unix)]
+fn device_name(path: &Path) -> io::Result<String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let dev = fs::metadata(path)?.dev() as libc::dev_t;
+    // Splitting a device number only does arithmetic on it
+    let (major, minor) = unsafe { (libc::major(dev), libc::minor(dev)) };
+    let number = format!("{}:{}", major, minor);
+    Ok(disk_name(&number).unwrap_or(number))
+}
+
+/// Name of the disk of the block device numbered `major:minor`, which is
+/// the parent of a partition in sysfs
+#[cfg(target_os = "linux")]
+fn disk_name(number: &str) -> Option<String> {
+    let device = fs::canonicalize(format!("/sys/dev/block/{}", number)).ok()?;
+    let disk = match device.join("partition").exists() {
+        true => device.parent()?,
+        false => &device,
+    };
+    Some(disk.file_name()?.to_string_lossy().into_owned())
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn disk_name(_number: &str) -> Option<String> {
+    None
+}
+
+#[cfg(not(unix))]
+fn device_name(path: &Path) -> io::Result<String> {
+    let path = fs::canonicalize(path)?;
+    Ok(path
+        .components()
+        .next()
+        .map(|root| root.as_os_str().to_string_lossy().into_owned())
+        .unwrap_or_default())
+}
+
+/// Name of the lock file of `device`, with the characters not allowed
+/// in file names replaced
+fn lock_file_name(device: &str) -> String {
+    let device: String = device
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("plm-device-{}.lock", device)
+}
+
+/// Lock the physical devices of `dests` with lock files in `lock_dir`,
+/// calling `waiting` with the name of a device before waiting for
+/// another process holding its lock
+///
+/// Each device is locked once, and devices are locked in the order of
+/// their names so that processes locking several devices cannot
+/// deadlock.
+pub fn lock_devices<P: AsRef<Path>>(
+    dests: &[P],
+    lock_dir: &Path,
+    mut waiting: impl FnMut(&str),
+) -> io::Result<Vec<DeviceLock>> {
+    let mut devices = dests
+        .iter()
+        .map(|dest| physical_device(dest.as_ref()))
+        .collect::<io::Result<Vec<_>>>()?;
+    devices.sort();
+    devices.dedup();
+
+    devices
+        .into_iter()
+        .map(|device| {
+            let file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(false)
+                .open(lock_dir.join(lock_file_name(&device)))?;
+            match file.try_lock() {
+                Ok(()) => {}
+                Err(fs::TryLockError::WouldBlock) => {
+                    waiting(&device);
+                    file.lock()?;
+                }
+                Err(fs::TryLockError::Error(e)) => return Err(e),
+            }
+            Ok(DeviceLock {
+                device,
+                _file: file,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lock_file_name() {
+        assert_eq!(lock_file_name("mmcblk0"), "plm-device-mmcblk0.lock");
+        assert_eq!(lock_file_name("8:17"), "plm-device-8_17.lock");
+        assert_eq!(
+            lock_file_name("\\\\server\\share"),
+            "plm-device-__server_share.lock"
+        );
+    }
+
+    #[test]
+    fn test_same_device_waits() {
+        let temp_dir = TempDir::new().unwrap();
+        let (dest1, dest2) = (temp_dir.path().join("a"), temp_dir.path().join("b/c"));
+        fs::create_dir(&dest1).unwrap();
+
+        // Both destinations are on the same device, which is locked once
+        let locks = lock_devices(&[&dest1, &dest2], temp_dir.path(), |_| {}).unwrap();
+        assert_eq!(locks.len(), 1);
+        assert_eq!(locks[0].device(), physical_device(&dest2).unwrap());
+
+        let (sender, receiver) = mpsc::channel();
+        let lock_dir = temp_dir.path().to_path_buf();
+        let waiter = thread::spawn(move || {
+            lock_devices(&[dest2], &lock_dir, |device| {
+                sender.send(device.to_string()).unwrap()
+            })
+            .unwrap()
+        });
+
+        // The second process waits until the first releases its lock
+        let device = receiver.recv().unwrap();
+        assert_eq!(device, locks[0].device());
+        drop(locks);
+        assert_eq!(waiter.join().unwrap().len(), 1);
+    }
+}
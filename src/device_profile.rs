@@ -0,0 +1,303 @@
+//! Device profiles subscribing a device to a set of playlists
+//!
+//! Besides the actions of the `post_sync` module, a device profile can
+//! list the playlists the device subscribes to, one glob pattern or path
+//! per `subscribe` line:
+//!
+//! ```text
+//! subscribe ~/Music/Playlists/*.m3u8
+//! subscribe ~/Music/Podcasts/latest.m3u8
+//! touch .rebuild_database
+//! ```
+//!
+//! `plm sync --device NAME` reads the profile `NAME` from the profile
+//! directory and syncs the playlists it subscribes to.  The playlists
+//! synced are recorded on the device, so that playlists matched for the
+//! first time and playlists gone from the computer can be told apart
+//! from the ones synced before.
+//!
+//! A profile can also set options for some of the playlists with
+//! `playlist` lines, as described in the `playlist_options` module.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::playlist_options::PlaylistMatch;
+use crate::post_sync::PostSyncActions;
+
+/// Environment variable naming the directory of device profiles
+pub const PROFILE_DIR_VAR: &str = "PLM_PROFILE_DIR";
+
+/// File of a destination recording the subscribed playlists synced into
+/// it, beside the sync state
+pub const SUBSCRIPTIONS_FILE: &str = ".plm/subscriptions";
+
+/// Device profile, with the playlists it subscribes to, the options it
+/// sets for playlists and its post-sync actions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceProfile {
+    base_dir: PathBuf,
+    subscriptions: Vec<String>,
+    playlist_options: Vec<PlaylistMatch>,
+    actions: PostSyncActions,
+}
+
+/// Playlists matched by the subscriptions of a profile
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Subscribed {
+    /// Playlist files, in the order of the subscriptions, each once
+    pub playlists: Vec<PathBuf>,
+    /// Subscriptions matching no playlist file
+    pub unmatched: Vec<String>,
+}
+
+impl DeviceProfile {
+    /// Parse a profile, with relative subscriptions relative to
+    /// `base_dir`
+    ///
+    /// Lines other than `subscribe` and `playlist` lines are post-sync
+    /// actions.
+    pub fn parse(content: &str, base_dir: &Path) -> Result<Self, String> {
+        let actions = PostSyncActions::parse(content)?;
+        let arguments = |keyword: &'static str| {
+            content
+                .lines()
+                .map(|line| line.trim_start_matches('\u{feff}').trim())
+                .enumerate()
+                .filter_map(move |(i, line)| Some((i, line.strip_prefix(keyword)?)))
+                .filter(|(_, argument)| argument.starts_with(char::is_whitespace))
+                .map(|(i, argument)| (i, argument.trim()))
+        };
+        let subscriptions = arguments("subscribe")
+            .map(|(_, argument)| argument.to_string())
+            .collect();
+        let playlist_options = arguments("playlist")
+            .map(|(i, argument)| {
+                PlaylistMatch::parse(argument).map_err(|e| format!("line {}: {}", i + 1, e))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            base_dir: base_dir.to_path_buf(),
+            subscriptions,
+            playlist_options,
+            actions,
+        })
+    }
+
+    /// Read the profile file at `path`
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        Self::parse(&content, base_dir).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Read the profile named `name` from the profile directory, or the
+    /// profile file at `name` if it is a path
+    pub fn find(name: &str) -> io::Result<Self> {
+        Self::from_file(&profile_path(name)?)
+    }
+
+    /// Subscriptions in the order they are listed
+    pub fn subscriptions(&self) -> &[String] {
+        &self.subscriptions
+    }
+
+    /// Options set for playlists, in the order they are listed
+    pub fn playlist_options(&self) -> &[PlaylistMatch] {
+        &self.playlist_options
+    }
+
+    /// Post-sync actions of the profile
+    pub fn actions(&self) -> &PostSyncActions {
+        &self.actions
+    }
+
+    /// Playlist files matched by the subscriptions
+    ///
+    /// A subscription with `*`, `?` or `[` is a glob pattern, matching
+    /// the playlists in the order of their paths, and any other is the
+    /// path of a playlist.  A leading `~/` stands for the home
+    /// directory.
+    pub fn resolve(&self) -> io::Result<Subscribed> {
+        let mut subscribed = Subscribed::default();
+
+        for subscription in &self.subscriptions {
+            let path = self.base_dir.join(expand_home(subscription));
+            let matches: Vec<PathBuf> = if subscription.contains(['*', '?', '[']) {
+                let pattern = path.to_string_lossy();
+                glob::glob(&pattern)
+                    .map_err(|e| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Invalid subscription {}: {}", subscription, e),
+                        )
+                    })?
+                    .filter_map(Result::ok)
+                    .filter(|path| path.is_file())
+                    .collect()
+            } else if path.is_file() {
+                vec![path]
+            } else {
+                Vec::new()
+            };
+
+            if matches.is_empty() {
+                subscribed.unmatched.push(subscription.clone());
+            }
+            for playlist in matches {
+                if !subscribed.playlists.contains(&playlist) {
+                    subscribed.playlists.push(playlist);
+                }
+            }
+        }
+
+        Ok(subscribed)
+    }
+}
+
+/// Directory of the device profiles, from `PLM_PROFILE_DIR`, or
+/// `playlist-manager/devices` of the configuration directory of the user
+pub fn profile_dir() -> io::Result<PathBuf> {
+    if let Some(dir) = std::env::var_os(PROFILE_DIR_VAR).filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| home_dir().map(|home| home.join(".config")))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No profile directory: set {}", PROFILE_DIR_VAR),
+            )
+        })?;
+    Ok(config_dir.join("playlist-manager").join("devices"))
+}
+
+/// Path of the profile `name`, in the profile directory unless `name`
+/// holds a directory separator
+pub fn profile_path(name: &str) -> io::Result<PathBuf> {
+    match name.contains(['/', std::path::MAIN_SEPARATOR]) {
+        true => Ok(PathBuf::from(name)),
+        false => Ok(profile_dir()?.join(name)),
+    }
+}
+
+/// Subscribed playlists recorded as synced into the destination
+/// `dest_root`, empty if none are
+pub fn read_synced(dest_root: &Path) -> io::Result<Vec<PathBuf>> {
+    match fs::read_to_string(dest_root.join(SUBSCRIPTIONS_FILE)) {
+        Ok(content) => Ok(content
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(PathBuf::from)
+            .collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Record the subscribed playlists synced into the destination
+/// `dest_root`
+pub fn write_synced(dest_root: &Path, playlists: &[PathBuf]) -> io::Result<()> {
+    let path = dest_root.join(SUBSCRIPTIONS_FILE);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let content: String = playlists
+        .iter()
+        .map(|playlist| format!("{}\n", playlist.display()))
+        .collect();
+    fs::write(path, content)
+}
+
+/// Home directory of the user, from `HOME` or `USERPROFILE`
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Path with a leading `~/` replaced by the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::post_sync::PostSyncAction;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse() {
+        let profile = DeviceProfile::parse(
+            "# Walkman\nsubscribe  lists/*.m3u8 \ntouch DB/refresh\nsubscribe podcast.m3u8\n",
+            Path::new("/profiles"),
+        )
+        .unwrap();
+        assert_eq!(profile.subscriptions(), ["lists/*.m3u8", "podcast.m3u8"]);
+        assert_eq!(
+            profile.actions().actions(),
+            [PostSyncAction::Touch(PathBuf::from("DB/refresh"))]
+        );
+
+        let e = DeviceProfile::parse("subscribe\n", Path::new("")).unwrap_err();
+        assert_eq!(e, "line 1: Missing argument of subscribe");
+    }
+
+    #[test]
+    fn test_parse_playlist_options() {
+        let profile = DeviceProfile::parse(
+            "subscribe *.m3u8\nplaylist \"Audiobooks*.m3u8\" lyrics=false dest-subdir=BOOKS\n",
+            Path::new("/profiles"),
+        )
+        .unwrap();
+        let options = profile.playlist_options();
+        assert_eq!(options.len(), 1);
+        assert!(options[0].matches(Path::new("Audiobooks 1.m3u8")));
+        assert_eq!(options[0].options().lyrics, Some(false));
+        assert!(profile.actions().actions().is_empty());
+
+        let e = DeviceProfile::parse("touch a\nplaylist *.m3u8 lyrics=maybe\n", Path::new(""))
+            .unwrap_err();
+        assert_eq!(
+            e,
+            "line 2: Invalid value of lyrics: maybe (expected true or false)"
+        );
+    }
+
+    #[test]
+    fn test_resolve() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dir = temp_dir.path();
+        fs::create_dir(dir.join("lists"))?;
+        for name in ["lists/b.m3u8", "lists/a.m3u8", "podcast.m3u8"] {
+            fs::write(dir.join(name), "")?;
+        }
+
+        let profile = DeviceProfile::parse(
+            "subscribe lists/*.m3u8\nsubscribe lists/a.m3u8\nsubscribe gone.m3u8\nsubscribe none/*.m3u8\n",
+            dir,
+        )
+        .unwrap();
+        let subscribed = profile.resolve()?;
+        assert_eq!(
+            subscribed.playlists,
+            [dir.join("lists/a.m3u8"), dir.join("lists/b.m3u8")]
+        );
+        assert_eq!(subscribed.unmatched, ["gone.m3u8", "none/*.m3u8"]);
+
+        assert!(read_synced(dir)?.is_empty());
+        write_synced(dir, &subscribed.playlists)?;
+        assert_eq!(read_synced(dir)?, subscribed.playlists);
+        Ok(())
+    }
+}
@@ -0,0 +1,451 @@
+//! Diagnostics of a destination and of the installation
+//!
+//! Syncs fail in ways that are hard to trace back to their cause: a card
+//! mounted read-only, a FAT filesystem refusing files over 4 GiB or
+//! folding the case of names, a device running out of space halfway, or
+//! an installation missing one of the commands.  This module checks a
+//! destination and the installation beforehand, and turns each problem
+//! found into a finding with advice on how to fix it.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Free space below which a destination is reported as running out of
+/// space
+pub const LOW_FREE_SPACE: u64 = 1024 * 1024 * 1024;
+
+/// Length of names most filesystems allow, in bytes
+const COMMON_NAME_MAX: u64 = 255;
+
+/// Severity of a finding
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Nothing to do
+    Ok,
+    /// Worth knowing, but syncs work
+    Info,
+    /// Syncs work, but some files may fail or be mishandled
+    Warning,
+    /// Syncs fail
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ok => "ok",
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// Result of a check
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub check: &'static str,    // Name of the check
+    pub severity: Severity,     // How serious the finding is
+    pub message: String,        // What was found
+    pub advice: Option<String>, // What to do about it, if anything
+}
+
+impl Finding {
+    /// Finding of `check` without advice
+    pub fn new(check: &'static str, severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            check,
+            severity,
+            message: message.into(),
+            advice: None,
+        }
+    }
+
+    /// The finding with advice on how to fix it
+    pub fn with_advice(mut self, advice: impl Into<String>) -> Self {
+        self.advice = Some(advice.into());
+        self
+    }
+}
+
+/// Check whether playlists can be synced to the destination directory
+/// `dest`: whether it is writable, its filesystem type, whether names
+/// are case-sensitive, its free space and the length of names allowed
+pub fn check_destination(dest: &Path) -> Vec<Finding> {
+    if !dest.is_dir() {
+        return vec![Finding::new(
+            "destination",
+            Severity::Error,
+            format!("{} is not a directory", dest.display()),
+        )
+        .with_advice("Mount the device, or create the directory")];
+    }
+
+    let mut findings = Vec::new();
+    match is_case_sensitive(dest) {
+        Ok(case_sensitive) => {
+            findings.push(Finding::new(
+                "destination",
+                Severity::Ok,
+                format!("{} is writable", dest.display()),
+            ));
+            findings.push(check_case(case_sensitive));
+        }
+        Err(e) => findings.push(
+            Finding::new(
+                "destination",
+                Severity::Error,
+                format!("{} is not writable: {}", dest.display(), e),
+            )
+            .with_advice(write_error_advice(&e)),
+        ),
+    }
+    findings.push(check_filesystem(filesystem_type(dest).as_deref()));
+    let stats = filesystem_stats(dest);
+    findings.push(check_free_space(stats.map(|stats| stats.free)));
+    findings.push(check_name_length(stats.map(|stats| stats.name_max)));
+    findings
+}
+
+/// Check that the commands of the installation are in `dir`, next to the
+/// command running the checks
+pub fn check_commands(dir: &Path, commands: &[&str]) -> Vec<Finding> {
+    commands
+        .iter()
+        .map(|command| {
+            let path = dir.join(format!("{}{}", command, std::env::consts::EXE_SUFFIX));
+            match path.is_file() {
+                true => Finding::new("commands", Severity::Ok, format!("{} found", command)),
+                false => Finding::new(
+                    "commands",
+                    Severity::Error,
+                    format!("{} not found in {}", command, dir.display()),
+                )
+                .with_advice("Reinstall Playlist Manager with make install"),
+            }
+        })
+        .collect()
+}
+
+/// Finding on whether names are case-sensitive
+fn check_case(case_sensitive: bool) -> Finding {
+    match case_sensitive {
+        true => Finding::new("case", Severity::Ok, "Names are case-sensitive"),
+        false => Finding::new("case", Severity::Info, "Names are case-insensitive").with_advice(
+            "Tracks whose paths differ only in case are copied to the same file; \
+             choose what happens to them with --on-conflict",
+        ),
+    }
+}
+
+/// Finding on the filesystem type of the destination
+fn check_filesystem(fs_type: Option<&str>) -> Finding {
+    match fs_type {
+        None => Finding::new("filesystem", Severity::Info, "Filesystem type unknown"),
+        Some(fs_type @ ("vfat" | "msdos" | "fat")) => Finding::new(
+            "filesystem",
+            Severity::Warning,
+            format!(
+                "FAT filesystem ({}): files of 4 GiB or more cannot be stored",
+                fs_type
+            ),
+        )
+        .with_advice(
+            "Keep media files under 4 GiB, or reformat the device with exFAT \
+             if the player supports it",
+        ),
+        Some(fs_type @ ("exfat" | "ntfs" | "ntfs3" | "fuseblk")) => Finding::new(
+            "filesystem",
+            Severity::Info,
+            format!(
+                "{} filesystem: names cannot contain \\ : * ? \" < > |",
+                fs_type
+            ),
+        )
+        .with_advice("Use --transliterate for names with characters the player cannot show"),
+        Some(fs_type) => Finding::new(
+            "filesystem",
+            Severity::Ok,
+            format!("{} filesystem", fs_type),
+        ),
+    }
+}
+
+/// Finding on the free space of the destination
+fn check_free_space(free: Option<u64>) -> Finding {
+    match free {
+        None => Finding::new("space", Severity::Info, "Free space unknown"),
+        Some(free) if free < LOW_FREE_SPACE => Finding::new(
+            "space",
+            Severity::Warning,
+            format!("Only {} free", format_size(free)),
+        )
+        .with_advice(
+            "Delete playlists no longer needed with plm delete-playlist --media, \
+             then plm prune",
+        ),
+        Some(free) => Finding::new("space", Severity::Ok, format!("{} free", format_size(free))),
+    }
+}
+
+/// Finding on the length of names allowed on the destination
+fn check_name_length(name_max: Option<u64>) -> Finding {
+    match name_max {
+        None => Finding::new("path length", Severity::Info, "Length of names unknown"),
+        Some(name_max) if name_max < COMMON_NAME_MAX => Finding::new(
+            "path length",
+            Severity::Warning,
+            format!("Names are limited to {} bytes", name_max),
+        )
+        .with_advice("Shorten the names of media files, or use --layout tags"),
+        Some(name_max) => Finding::new(
+            "path length",
+            Severity::Ok,
+            format!("Names up to {} bytes", name_max),
+        ),
+    }
+}
+
+/// Size in bytes, KiB, MiB or GiB for messages
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = "bytes";
+    for next in UNITS {
+        if size < 1024.0 {
+            break;
+        }
+        size /= 1024.0;
+        unit = next;
+    }
+    match unit {
+        "bytes" => format!("{} bytes", bytes),
+        _ => format!("{:.1} {}", size, unit),
+    }
+}
+
+/// Parse a size given in bytes, or in KiB, MiB or GiB with a `K`, `M`
+/// or `G` suffix
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let invalid = || format!("Invalid size: {} (expected bytes, or K, M or G)", s);
+    let size = s.trim().to_ascii_uppercase();
+    let (digits, unit) = match size.chars().last() {
+        Some('K') => (&size[..size.len() - 1], 1 << 10),
+        Some('M') => (&size[..size.len() - 1], 1 << 20),
+        Some('G') => (&size[..size.len() - 1], 1 << 30),
+        _ => (size.as_str(), 1),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .ok_or_else(invalid)
+}
+
+/// Check that files can be created in `dir`, by creating and removing a
+/// temporary file
+pub fn check_writable(dir: &Path) -> io::Result<()> {
+    let probe = dir.join(format!(".plm-write-test-{}", std::process::id()));
+    File::create(&probe)?;
+    fs::remove_file(&probe)
+}
+
+/// Advice on how to fix `error`, returned when writing to a destination
+pub fn write_error_advice(error: &io::Error) -> &'static str {
+    match error.kind() {
+        io::ErrorKind::ReadOnlyFilesystem => {
+            "The device is mounted read-only, as Linux does on finding errors on a FAT filesystem; \
+             unmount it, check it with fsck (such as fsck.vfat -a) and mount it read-write again"
+        }
+        io::ErrorKind::PermissionDenied => {
+            "Check the permissions of the destination; a FAT filesystem takes its owner from the \
+             uid and gid mount options"
+        }
+        _ => "Check that the device is mounted read-write and not write-protected",
+    }
+}
+
+/// Whether names differing only in case name different files in `dir`,
+/// found out by creating and removing a temporary file
+pub fn is_case_sensitive(dir: &Path) -> io::Result<bool> {
+    let name = format!(".plm-doctor-{}", std::process::id());
+    let probe = dir.join(&name);
+    File::create(&probe)?;
+    let case_sensitive = !dir.join(name.to_uppercase()).exists();
+    fs::remove_file(&probe)?;
+    Ok(case_sensitive)
+}
+
+/// Filesystem type of the mount holding `path`, as named in
+/// `/proc/self/mounts` on Linux
+pub fn filesystem_type(path: &Path) -> Option<String> {
+    let path = fs::canonicalize(path).ok()?;
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
+    parse_mounts(&mounts)
+        .into_iter()
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+        .map(|(_, fs_type)| fs_type)
+}
+
+/// Mount points and filesystem types listed in the `/proc/self/mounts`
+/// format, in which spaces and other special characters of mount points
+/// are escaped in octal
+fn parse_mounts(mounts: &str) -> Vec<(PathBuf, String)> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _device = fields.next()?;
+            let mount_point = unescape_octal(fields.next()?);
+            let fs_type = fields.next()?;
+            Some((PathBuf::from(mount_point), fs_type.to_string()))
+        })
+        .collect()
+}
+
+/// Field with `\NNN` octal escapes replaced by the characters escaped
+fn unescape_octal(field: &str) -> String {
+    let mut bytes = Vec::with_capacity(field.len());
+    let mut rest = field.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..3)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match (byte, escaped) {
+            (b'\\', Some(escaped)) => {
+                bytes.push(escaped);
+                rest = &tail[3..];
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Bytes available to unprivileged users on the filesystem holding
+/// `path`, if known
+pub fn free_space(path: &Path) -> Option<u64> {
+    filesystem_stats(path).map(|stats| stats.free)
+}
+
+/// Space available to the user and length of names allowed on a
+/// filesystem
+#[derive(Clone, Copy, Debug)]
+struct FilesystemStats {
+    free: u64,     // Bytes available to unprivileged users
+    name_max: u64, // Length of names allowed, in bytes
+}
+
+/// Statistics of the filesystem holding `path`
+#[cfg(unix)]
+fn filesystem_stats(path: &Path) -> Option<FilesystemStats> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `path` is NUL-terminated and `stats` is written by the
+    // call before being read when it succeeds
+    let stats = unsafe {
+        if libc::statvfs(path.as_ptr(), stats.as_mut_ptr()) != 0 {
+            return None;
+        }
+        stats.assume_init()
+    };
+    Some(FilesystemStats {
+        // The types of the fields differ between platforms
+        free: stats.f_bavail as u64 * stats.f_frsize as u64,
+        name_max: stats.f_namemax as u64,
+    })
+}
+
+/// Statistics of the filesystem holding `path`, unknown on this platform
+#[cfg(not(unix))]
+fn filesystem_stats(_path: &Path) -> Option<FilesystemStats> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check_destination() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let findings = check_destination(temp_dir.path());
+        assert!(findings
+            .iter()
+            .any(|finding| finding.message.ends_with("is writable")));
+        assert!(findings
+            .iter()
+            .all(|finding| finding.severity != Severity::Error));
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 0);
+
+        let findings = check_destination(&temp_dir.path().join("missing"));
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Error);
+        assert!(findings[0].advice.is_some());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_writable() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        check_writable(temp_dir.path())?;
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 0);
+        assert!(check_writable(&temp_dir.path().join("missing")).is_err());
+
+        let read_only = io::Error::from(io::ErrorKind::ReadOnlyFilesystem);
+        assert!(write_error_advice(&read_only).contains("fsck"));
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert!(write_error_advice(&denied).contains("uid"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1000"), Ok(1000));
+        assert_eq!(parse_size("64k"), Ok(64 * 1024));
+        assert_eq!(parse_size("64M"), Ok(64 * 1024 * 1024));
+        assert_eq!(parse_size("2G"), Ok(2 * 1024 * 1024 * 1024));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("1T").is_err());
+        assert!(parse_size("-1").is_err());
+    }
+
+    #[test]
+    fn test_parse_mounts() {
+        let mounts = "/dev/sda1 / ext4 rw,relatime 0 0\n\
+                      /dev/sdb1 /media/My\\040Card vfat rw 0 0\n";
+        assert_eq!(
+            parse_mounts(mounts),
+            vec![
+                (PathBuf::from("/"), "ext4".to_string()),
+                (PathBuf::from("/media/My Card"), "vfat".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_findings() {
+        assert_eq!(check_filesystem(Some("vfat")).severity, Severity::Warning);
+        assert_eq!(check_filesystem(Some("ext4")).severity, Severity::Ok);
+        assert_eq!(check_free_space(Some(1024)).severity, Severity::Warning);
+        assert_eq!(
+            check_free_space(Some(3 * LOW_FREE_SPACE / 2)).message,
+            "1.5 GiB free"
+        );
+        assert_eq!(check_name_length(Some(143)).severity, Severity::Warning);
+        assert_eq!(format_size(512), "512 bytes");
+    }
+}
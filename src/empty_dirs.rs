@@ -0,0 +1,202 @@
+//! Removal of empty directories
+//!
+//! Deleting media files from a device leaves the directories of their
+//! artists and albums behind.  This module removes the directories left
+//! empty under a root, deepest first, so that a directory whose
+//! subdirectories are all empty is removed with them.  Directories
+//! shallower than a minimum depth and directories matching exclusion
+//! patterns are kept, and a dry run lists what would be removed.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use glob::Pattern;
+
+use crate::logger::Logger;
+
+/// How empty directories are removed
+#[derive(Debug, Default)]
+pub struct PruneOptions {
+    pub dry_run: bool,    // List the empty directories without removing them
+    pub min_depth: usize, // Keep directories shallower than this, the root being 0
+    exclusions: Vec<Pattern>,
+}
+
+impl PruneOptions {
+    /// Remove every empty directory including the root
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep the directories matching a wildcard pattern, matched against
+    /// the path relative to the root or the directory name
+    ///
+    /// Excluded directories are kept with everything under them.
+    pub fn exclude(&mut self, pattern: &str) -> io::Result<()> {
+        let pattern = Pattern::new(&pattern.replace('\\', "/")).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid pattern \"{}\": {}", pattern, e),
+            )
+        })?;
+
+        self.exclusions.push(pattern);
+        Ok(())
+    }
+
+    /// Add the patterns listed in a file, one per line
+    ///
+    /// Blank lines and lines starting with `#` are ignored, and
+    /// surrounding white space is removed.
+    pub fn exclude_file(&mut self, path: &Path) -> io::Result<()> {
+        let content = fs::read_to_string(path)?;
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim_start_matches('\u{feff}').trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.exclude(line).map_err(|e| {
+                io::Error::new(e.kind(), format!("{}:{}: {}", path.display(), i + 1, e))
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether the directory at `relative` to the root, named `name`, is
+    /// excluded
+    fn is_excluded(&self, relative: &str, name: &str) -> bool {
+        self.exclusions
+            .iter()
+            .any(|pattern| pattern.matches(relative) || pattern.matches(name))
+    }
+}
+
+/// Remove the empty directories under `root`, and `root` itself if the
+/// options allow
+///
+/// Symbolic links are never followed.  Returns the directories removed,
+/// or with a dry run the directories that would be removed, deepest
+/// first.  A missing root has no empty directories.
+pub fn prune_empty_dirs(
+    root: &Path,
+    options: &PruneOptions,
+    logger: &dyn Logger,
+) -> io::Result<Vec<PathBuf>> {
+    let mut pruned = Vec::new();
+    if root.is_dir() {
+        prune_dir(root, "", 0, options, logger, &mut pruned)?;
+    }
+    Ok(pruned)
+}
+
+/// Remove the empty directories under `dir` at `depth`, and `dir` itself
+/// if it is left empty
+///
+/// Returns whether `dir` was removed, or would be with a dry run.
+fn prune_dir(
+    dir: &Path,
+    relative: &str,
+    depth: usize,
+    options: &PruneOptions,
+    logger: &dyn Logger,
+    pruned: &mut Vec<PathBuf>,
+) -> io::Result<bool> {
+    let mut is_empty = true;
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let entry_relative = match relative {
+            "" => name.clone(),
+            _ => format!("{}/{}", relative, name),
+        };
+
+        if !entry.file_type()?.is_dir()
+            || options.is_excluded(&entry_relative, &name)
+            || !prune_dir(
+                &entry.path(),
+                &entry_relative,
+                depth + 1,
+                options,
+                logger,
+                pruned,
+            )?
+        {
+            is_empty = false;
+        }
+    }
+
+    if !is_empty || depth < options.min_depth {
+        return Ok(false);
+    }
+
+    if !options.dry_run {
+        logger.log_formatted("Deleting empty directory \"{}\"", &[&dir.to_string_lossy()]);
+        fs::remove_dir(dir).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("Failed to delete directory {}: {}", dir.display(), e),
+            )
+        })?;
+    }
+    pruned.push(dir.to_path_buf());
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logger::NullLogger;
+    use tempfile::TempDir;
+
+    fn create_tree(root: &Path) -> io::Result<()> {
+        for dir in ["a/b/c", "a/d", "keep/e", ".Trashes/f"] {
+            fs::create_dir_all(root.join(dir))?;
+        }
+        fs::write(root.join("a/d/track.flac"), "content")
+    }
+
+    #[test]
+    fn test_prune_empty_dirs() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        create_tree(root)?;
+
+        let mut options = PruneOptions::new();
+        options.min_depth = 1;
+        options.exclude("keep")?;
+        options.exclude(".Trash*")?;
+        let pruned = prune_empty_dirs(root, &options, &NullLogger)?;
+
+        assert_eq!(pruned, vec![root.join("a/b/c"), root.join("a/b")]);
+        assert!(!root.join("a/b").exists());
+        assert!(root.join("a/d/track.flac").exists());
+        assert!(root.join("keep/e").exists());
+        assert!(root.join(".Trashes/f").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_empty_dirs_dry_run() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path().join("root");
+        create_tree(&root)?;
+        fs::remove_file(root.join("a/d/track.flac"))?;
+
+        let mut options = PruneOptions::new();
+        options.dry_run = true;
+        let pruned = prune_empty_dirs(&root, &options, &NullLogger)?;
+
+        assert_eq!(pruned.len(), 9);
+        assert_eq!(pruned.last(), Some(&root));
+        assert!(root.join("a/b/c").exists());
+        assert!(options.exclude("a[").is_err());
+
+        Ok(())
+    }
+}
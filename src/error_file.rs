@@ -0,0 +1,568 @@
+//! Reading and writing of error files listing failed operations
+//!
+//! An error file is a UTF-8 text file with one entry per line.  Version
+//! 2 of the format is laid out as follows:
+//!
+//! - The first line is the header `# plm-error-file 2`.  It is written
+//!   together with the first entry, so a run without failures leaves an
+//!   empty file.  Files without a header are read as version 1.
+//! - In version 2, the header may be followed by the totals of the
+//!   original run, such as `# plm-totals playlists=3 media=1500`, so
+//!   that a retry can report how much of the whole run is complete.
+//!   Totals only known after the first entries were written follow
+//!   those entries instead, and the last totals line is the one read.
+//!   Retries copy the totals into their own error files.  Unknown keys
+//!   are ignored.
+//! - The header may also be followed by the identifier of the run that
+//!   wrote the file, such as `# plm-run 20261016T105655Z-3039`, to tell
+//!   which run of several the file belongs to.
+//! - Each entry consists of a one-letter type, a space and a path:
+//!   `P` for a failed playlist, `M` for a failed media file and `L` for
+//!   a failed lyrics file.  Media and lyrics files are given by their
+//!   full source path.
+//! - `S` entries, added in version 2, record a sidecar file such as a
+//!   lyrics file that failed to copy along with a media file that was
+//!   copied.  The full source path of the media file is followed by a
+//!   tab and the full source path of the sidecar file, so that a retry
+//!   copies only the sidecar file, next to the copied media file.
+//! - Paths are escaped so that each entry stays on a single line: a
+//!   backslash, a line feed, a carriage return and a tab are written as
+//!   `\\`, `\n`, `\r` and `\t`, and a space at the start or the end of a
+//!   path as `\s`.  Unknown escape sequences are read back unchanged.
+//! - Blank lines, other lines starting with `#` and lines of unknown
+//!   type are ignored.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Version of the error file format written by this module
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Start of the header line, followed by the format version
+const HEADER_PREFIX: &str = "# plm-error-file ";
+
+/// Start of the line giving the totals of the original run
+const TOTALS_PREFIX: &str = "# plm-totals ";
+
+/// Start of the line giving the identifier of the run
+const RUN_PREFIX: &str = "# plm-run ";
+
+/// Numbers of playlists and media files the original run set out to
+/// copy
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RunTotals {
+    pub playlists: usize,
+    pub media_files: usize,
+}
+
+impl RunTotals {
+    /// Format the totals as a line of the error file, without line ending
+    fn to_line(self) -> String {
+        format!(
+            "{}playlists={} media={}",
+            TOTALS_PREFIX, self.playlists, self.media_files
+        )
+    }
+
+    /// Parse the totals from a line of the error file, if it gives them
+    fn parse_line(line: &str) -> Option<Self> {
+        let mut totals = Self::default();
+        for field in line.strip_prefix(TOTALS_PREFIX)?.split_whitespace() {
+            match field.split_once('=') {
+                Some(("playlists", n)) => totals.playlists = n.parse().ok()?,
+                Some(("media", n)) => totals.media_files = n.parse().ok()?,
+                _ => {}
+            }
+        }
+        Some(totals)
+    }
+}
+
+/// A failed operation recorded in an error file
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ErrorEntry {
+    /// Playlist that failed to copy
+    Playlist(PathBuf),
+    /// Full source path of a media file that failed to copy
+    Media(PathBuf),
+    /// Full source path of a lyrics file that failed to copy
+    Lyrics(PathBuf),
+    /// Full source paths of a copied media file and of its sidecar file
+    /// that failed to copy
+    Sidecar { media: PathBuf, sidecar: PathBuf },
+}
+
+impl ErrorEntry {
+    /// One-letter type of the entry
+    fn prefix(&self) -> char {
+        match self {
+            ErrorEntry::Playlist(_) => 'P',
+            ErrorEntry::Media(_) => 'M',
+            ErrorEntry::Lyrics(_) => 'L',
+            ErrorEntry::Sidecar { .. } => 'S',
+        }
+    }
+
+    /// Path of the failed file
+    pub fn path(&self) -> &Path {
+        match self {
+            ErrorEntry::Playlist(path) | ErrorEntry::Media(path) | ErrorEntry::Lyrics(path) => path,
+            ErrorEntry::Sidecar { sidecar, .. } => sidecar,
+        }
+    }
+
+    /// Format the entry as a line of the error file, without line ending
+    pub fn to_line(&self) -> String {
+        match self {
+            ErrorEntry::Sidecar { media, sidecar } => format!(
+                "{} {}\t{}",
+                self.prefix(),
+                escape_path(&media.to_string_lossy()),
+                escape_path(&sidecar.to_string_lossy())
+            ),
+            _ => format!(
+                "{} {}",
+                self.prefix(),
+                escape_path(&self.path().to_string_lossy())
+            ),
+        }
+    }
+
+    /// Parse a line of the error file
+    ///
+    /// Returns `None` for blank lines, comments and unknown entry types.
+    pub fn parse_line(line: &str) -> Option<Self> {
+        let (prefix, field) = line.split_at_checked(2)?;
+        if prefix == "S " {
+            // Tabs within paths are escaped, so the first one separates them
+            let (media, sidecar) = field.split_once('\t')?;
+            return Some(ErrorEntry::Sidecar {
+                media: PathBuf::from(unescape_path(trim_field(media))),
+                sidecar: PathBuf::from(unescape_path(trim_field(sidecar))),
+            });
+        }
+        let path = PathBuf::from(unescape_path(trim_field(field)));
+        match prefix {
+            "P " => Some(ErrorEntry::Playlist(path)),
+            "M " => Some(ErrorEntry::Media(path)),
+            "L " => Some(ErrorEntry::Lyrics(path)),
+            _ => None,
+        }
+    }
+}
+
+/// Escape a path for an entry of the error file
+///
+/// Backslashes, line breaks and tabs are written as `\\`, `\n`, `\r`
+/// and `\t`, and a space at either end of the path as `\s`, so that
+/// each entry stays on a single line and keeps its surrounding spaces.
+pub fn escape_path(path: &str) -> String {
+    let last = path.chars().count().saturating_sub(1);
+    let mut escaped = String::with_capacity(path.len());
+
+    for (i, c) in path.chars().enumerate() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            ' ' if i == 0 || i == last => escaped.push_str("\\s"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Restore a path escaped by [`escape_path`]
+///
+/// Unknown escape sequences are kept as they are, so error files written
+/// before escaping was introduced are still read unchanged.
+pub fn unescape_path(field: &str) -> String {
+    let mut path = String::with_capacity(field.len());
+    let mut chars = field.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            path.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => path.push('\\'),
+            Some('n') => path.push('\n'),
+            Some('r') => path.push('\r'),
+            Some('t') => path.push('\t'),
+            Some('s') => path.push(' '),
+            Some(other) => {
+                path.push('\\');
+                path.push(other);
+            }
+            None => path.push('\\'),
+        }
+    }
+
+    path
+}
+
+/// Strip the unescaped blanks around a field of the error file
+///
+/// Significant spaces are escaped by [`escape_path`], so only blanks
+/// added by hand or by CRLF line endings are removed here.
+fn trim_field(field: &str) -> &str {
+    field.trim_matches([' ', '\t', '\r'])
+}
+
+/// Parse the format version from a header line, if it is one
+fn parse_header(line: &str) -> Option<&str> {
+    line.strip_prefix(HEADER_PREFIX).map(trim_field)
+}
+
+/// Writer appending entries to an error file
+///
+/// Each entry is flushed as soon as it is written, so the file survives
+/// a crash part way through an operation.
+#[derive(Debug)]
+pub struct ErrorFileWriter<W: Write> {
+    writer: W,
+    header_written: bool,
+    run_id: Option<String>,
+    totals: Option<RunTotals>,
+}
+
+impl ErrorFileWriter<File> {
+    /// Create (or truncate) the error file at `path`
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> ErrorFileWriter<W> {
+    /// Write error file entries into `writer`
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            header_written: false,
+            run_id: None,
+            totals: None,
+        }
+    }
+
+    /// Write the identifier of the run after the header
+    ///
+    /// An identifier given after the first entry was written is written
+    /// at once, after the entries written so far.
+    pub fn set_run_id(&mut self, run_id: &str) -> io::Result<()> {
+        self.run_id = Some(run_id.to_string());
+        if self.header_written {
+            writeln!(self.writer, "{}{}", RUN_PREFIX, run_id)?;
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write the totals of the original run after the header
+    ///
+    /// Totals given after the first entry was written are written at
+    /// once, after the entries written so far.
+    pub fn set_totals(&mut self, totals: RunTotals) -> io::Result<()> {
+        self.totals = Some(totals);
+        if self.header_written {
+            writeln!(self.writer, "{}", totals.to_line())?;
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Append an entry, preceded by the header if it is the first one
+    pub fn write_entry(&mut self, entry: &ErrorEntry) -> io::Result<()> {
+        if !self.header_written {
+            writeln!(self.writer, "{}{}", HEADER_PREFIX, FORMAT_VERSION)?;
+            if let Some(run_id) = &self.run_id {
+                writeln!(self.writer, "{}{}", RUN_PREFIX, run_id)?;
+            }
+            if let Some(totals) = self.totals {
+                writeln!(self.writer, "{}", totals.to_line())?;
+            }
+            self.header_written = true;
+        }
+        writeln!(self.writer, "{}", entry.to_line())?;
+        self.writer.flush()
+    }
+
+    /// Unwrap the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Contents of an error file
+#[derive(Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ErrorFile {
+    pub run_id: Option<String>,    // Run that wrote the file, if given
+    pub totals: Option<RunTotals>, // Totals of the original run, if given
+    pub entries: Vec<ErrorEntry>,
+}
+
+impl ErrorFile {
+    /// Read the error file at `path`
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Self::parse(BufReader::new(File::open(path)?))
+    }
+
+    /// Read error file contents
+    ///
+    /// Fails if the header announces a format version newer than
+    /// [`FORMAT_VERSION`].
+    pub fn parse<R: BufRead>(reader: R) -> io::Result<Self> {
+        let mut error_file = Self::default();
+
+        for line in reader.lines() {
+            let line = line?;
+
+            if let Some(version) = parse_header(&line) {
+                match version.parse::<u32>() {
+                    Ok(version) if version <= FORMAT_VERSION => continue,
+                    _ => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Unsupported error file format version: {}", version),
+                        ))
+                    }
+                }
+            }
+
+            if let Some(totals) = RunTotals::parse_line(trim_field(&line)) {
+                error_file.totals = Some(totals);
+            } else if let Some(run_id) = trim_field(&line).strip_prefix(RUN_PREFIX) {
+                error_file.run_id = Some(trim_field(run_id).to_string());
+            } else if let Some(entry) = ErrorEntry::parse_line(&line) {
+                error_file.entries.push(entry);
+            }
+        }
+
+        Ok(error_file)
+    }
+}
+
+/// Read all entries from an error file
+pub fn read_error_file(path: &Path) -> io::Result<Vec<ErrorEntry>> {
+    Ok(ErrorFile::read(path)?.entries)
+}
+
+/// Read all entries from error file contents
+///
+/// Fails if the header announces a format version newer than
+/// [`FORMAT_VERSION`].
+pub fn parse_entries<R: BufRead>(reader: R) -> io::Result<Vec<ErrorEntry>> {
+    Ok(ErrorFile::parse(reader)?.entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_path_plain_path_unchanged() {
+        let path = "/home/user/MUSIC/artist1/album1/title 1.flac";
+        assert_eq!(escape_path(path), path);
+    }
+
+    #[test]
+    fn test_escape_path_round_trip() {
+        let paths = [
+            " leading space.flac",
+            "trailing space.flac ",
+            "   ",
+            " ",
+            "",
+            "line\nbreak.flac",
+            "carriage\r\nreturn.flac",
+            "tab\there.flac",
+            "back\\slash\\.flac",
+            "trailing backslash\\",
+            "\\s looks like an escape",
+            "/MUSIC/ odd\ndir / title.flac ",
+        ];
+
+        for path in paths {
+            let escaped = escape_path(path);
+            assert!(!escaped.contains('\n') && !escaped.contains('\r'));
+            assert_eq!(trim_field(&escaped), escaped);
+            assert_eq!(unescape_path(&escaped), path);
+        }
+    }
+
+    #[test]
+    fn test_unescape_path_keeps_unknown_escapes() {
+        assert_eq!(unescape_path("C:\\MUSIC\\x.flac"), "C:\\MUSIC\\x.flac");
+        assert_eq!(unescape_path("dir\\a\\b"), "dir\\a\\b");
+        assert_eq!(unescape_path("end\\"), "end\\");
+    }
+
+    #[test]
+    fn test_write_and_parse_entries_round_trip() -> io::Result<()> {
+        let entries = vec![
+            ErrorEntry::Playlist(PathBuf::from(" playlist.m3u8")),
+            ErrorEntry::Media(PathBuf::from("/music/artist1/line\nbreak.flac")),
+            ErrorEntry::Lyrics(PathBuf::from("/music/artist1/title1.lrc ")),
+            ErrorEntry::Sidecar {
+                media: PathBuf::from("/music/artist2/tab\there.flac"),
+                sidecar: PathBuf::from("/music/artist2/tab\there.lrc"),
+            },
+        ];
+
+        let mut writer = ErrorFileWriter::new(Vec::new());
+        for entry in &entries {
+            writer.write_entry(entry)?;
+        }
+        let content = String::from_utf8(writer.into_inner()).unwrap();
+
+        assert_eq!(
+            content,
+            "# plm-error-file 2\n\
+             P \\splaylist.m3u8\n\
+             M /music/artist1/line\\nbreak.flac\n\
+             L /music/artist1/title1.lrc\\s\n\
+             S /music/artist2/tab\\there.flac\t/music/artist2/tab\\there.lrc\n"
+        );
+        assert_eq!(parse_entries(content.as_bytes())?, entries);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_and_parse_totals() -> io::Result<()> {
+        let totals = RunTotals {
+            playlists: 3,
+            media_files: 1500,
+        };
+        let mut writer = ErrorFileWriter::new(Vec::new());
+        writer.set_totals(totals)?;
+        writer.write_entry(&ErrorEntry::Media(PathBuf::from("/music/a.flac")))?;
+        let content = String::from_utf8(writer.into_inner()).unwrap();
+
+        assert_eq!(
+            content,
+            "# plm-error-file 2\n# plm-totals playlists=3 media=1500\nM /music/a.flac\n"
+        );
+        let error_file = ErrorFile::parse(content.as_bytes())?;
+        assert_eq!(error_file.totals, Some(totals));
+        assert_eq!(error_file.entries.len(), 1);
+        assert_eq!(
+            ErrorFile::parse("# plm-totals media=7 lyrics=2\r\n".as_bytes())?.totals,
+            Some(RunTotals {
+                playlists: 0,
+                media_files: 7
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_totals_after_entries() -> io::Result<()> {
+        let totals = RunTotals {
+            playlists: 2,
+            media_files: 40,
+        };
+        let mut writer = ErrorFileWriter::new(Vec::new());
+        writer.write_entry(&ErrorEntry::Media(PathBuf::from("/music/a.flac")))?;
+        writer.set_totals(totals)?;
+        writer.write_entry(&ErrorEntry::Media(PathBuf::from("/music/b.flac")))?;
+        let content = String::from_utf8(writer.into_inner()).unwrap();
+
+        assert_eq!(
+            content,
+            "# plm-error-file 2\nM /music/a.flac\n# plm-totals playlists=2 media=40\n\
+             M /music/b.flac\n"
+        );
+        let error_file = ErrorFile::parse(content.as_bytes())?;
+        assert_eq!(error_file.totals, Some(totals));
+        assert_eq!(error_file.entries.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_and_parse_run_id() -> io::Result<()> {
+        let mut writer = ErrorFileWriter::new(Vec::new());
+        writer.set_run_id("20261016T105655Z-3039")?;
+        writer.set_totals(RunTotals {
+            playlists: 1,
+            media_files: 2,
+        })?;
+        writer.write_entry(&ErrorEntry::Media(PathBuf::from("/music/a.flac")))?;
+        let content = String::from_utf8(writer.into_inner()).unwrap();
+
+        assert_eq!(
+            content,
+            "# plm-error-file 2\n# plm-run 20261016T105655Z-3039\n\
+             # plm-totals playlists=1 media=2\nM /music/a.flac\n"
+        );
+        let error_file = ErrorFile::parse(content.as_bytes())?;
+        assert_eq!(error_file.run_id.as_deref(), Some("20261016T105655Z-3039"));
+        assert_eq!(error_file.entries.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_writer_without_entries_writes_nothing() {
+        let writer = ErrorFileWriter::new(Vec::new());
+        assert!(writer.into_inner().is_empty());
+    }
+
+    #[test]
+    fn test_parse_entries_without_header() -> io::Result<()> {
+        let content = "P /music/playlist.m3u8\r\n\n# comment\nX unknown\nS /music/a.flac\nM /music/a.flac  \n";
+
+        assert_eq!(
+            parse_entries(content.as_bytes())?,
+            vec![
+                ErrorEntry::Playlist(PathBuf::from("/music/playlist.m3u8")),
+                ErrorEntry::Media(PathBuf::from("/music/a.flac")),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_entries_rejects_newer_version() {
+        let content = "# plm-error-file 3\nP /music/playlist.m3u8\n";
+
+        let err = parse_entries(content.as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let error_file = ErrorFile {
+            run_id: None,
+            totals: Some(RunTotals {
+                playlists: 2,
+                media_files: 10,
+            }),
+            entries: vec![
+                ErrorEntry::Playlist(PathBuf::from("/music/list.m3u8")),
+                ErrorEntry::Sidecar {
+                    media: PathBuf::from("/music/a.flac"),
+                    sidecar: PathBuf::from("/music/a.lrc"),
+                },
+            ],
+        };
+
+        let json = serde_json::to_string(&error_file).unwrap();
+        assert!(json.contains(r#"{"playlist":"/music/list.m3u8"}"#));
+        assert_eq!(
+            serde_json::from_str::<ErrorFile>(&json).unwrap(),
+            error_file
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Lists of tracks never to be copied
+//!
+//! Tracks are excluded by wildcard patterns or by paths, given on the
+//! command line or read from exclusion files shared across runs.  Each
+//! is matched against playlist entries as a `TrackMatcher`, so a path
+//! may be written as a playlist entry or as the path to the media file.
+//! Sets of media files excluded at once, such as the tracks put on
+//! other destinations, are held by their absolute paths instead.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::track_matcher::{absolute_path, TrackMatcher};
+
+/// Characters making an exclusion a wildcard pattern rather than a path
+const WILDCARD_CHARS: &[char] = &['*', '?', '['];
+
+/// Tracks excluded from copying
+#[derive(Debug, Default)]
+pub struct ExclusionList {
+    matchers: Vec<TrackMatcher>,
+    paths: HashSet<PathBuf>, // Absolute paths of media files
+}
+
+impl ExclusionList {
+    /// Create an empty exclusion list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Exclude the tracks matching a wildcard pattern, or the track at a
+    /// path if `exclusion` contains no wildcard
+    pub fn add(&mut self, exclusion: &str) -> io::Result<()> {
+        let matcher = if exclusion.contains(WILDCARD_CHARS) {
+            TrackMatcher::pattern(exclusion).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Invalid pattern \"{}\": {}", exclusion, e),
+                )
+            })?
+        } else {
+            TrackMatcher::path(exclusion)?
+        };
+
+        self.matchers.push(matcher);
+        Ok(())
+    }
+
+    /// Add the exclusions listed in a file, one per line
+    ///
+    /// Blank lines and lines starting with `#` are ignored, and
+    /// surrounding white space is removed.
+    pub fn add_file(&mut self, path: &Path) -> io::Result<()> {
+        let content = fs::read_to_string(path)?;
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim_start_matches('\u{feff}').trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            self.add(line).map_err(|e| {
+                io::Error::new(e.kind(), format!("{}:{}: {}", path.display(), i + 1, e))
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Exclude the media files at the absolute paths `paths`, replacing
+    /// those excluded by a previous call
+    pub fn set_paths(&mut self, paths: HashSet<PathBuf>) {
+        self.paths = paths;
+    }
+
+    /// Whether the list excludes nothing
+    pub fn is_empty(&self) -> bool {
+        self.matchers.is_empty() && self.paths.is_empty()
+    }
+
+    /// Whether the entry of a playlist in `playlist_dir` is excluded
+    pub fn is_excluded(&self, playlist_dir: &Path, entry: &str) -> bool {
+        if !self.paths.is_empty()
+            && absolute_path(&playlist_dir.join(entry)).is_ok_and(|path| self.paths.contains(&path))
+        {
+            return true;
+        }
+        self.matchers
+            .iter()
+            .any(|matcher| matcher.matches(playlist_dir, entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_add_file() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("exclude.txt");
+        fs::write(
+            &path,
+            "# Never copy these\n\n*.wav\n  artist1/album1/title2.flac  \n/music/Live/*\n",
+        )?;
+
+        let mut exclusions = ExclusionList::new();
+        assert!(exclusions.is_empty());
+        exclusions.add_file(&path)?;
+
+        let dir = Path::new("/music");
+        assert!(exclusions.is_excluded(dir, "artist2/album1/title1.wav"));
+        assert!(exclusions.is_excluded(dir, "artist1/album1/title2.flac"));
+        assert!(exclusions.is_excluded(dir, "Live/title1.flac"));
+        assert!(!exclusions.is_excluded(dir, "artist1/album1/title1.flac"));
+        assert!(!exclusions.is_excluded(dir, "# Never copy these"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_paths() {
+        let mut exclusions = ExclusionList::new();
+        exclusions.set_paths(HashSet::from([PathBuf::from(
+            "/music/artist1/album1/title1.flac",
+        )]));
+        assert!(!exclusions.is_empty());
+
+        let dir = Path::new("/music/artist1");
+        assert!(exclusions.is_excluded(dir, "album1/title1.flac"));
+        assert!(exclusions.is_excluded(dir, "../artist1/album1/title1.flac"));
+        assert!(!exclusions.is_excluded(dir, "album1/title2.flac"));
+
+        exclusions.set_paths(HashSet::new());
+        assert!(exclusions.is_empty());
+    }
+
+    #[test]
+    fn test_add_file_invalid_pattern() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("exclude.txt");
+        fs::write(&path, "*.wav\nartist[\n")?;
+
+        let err = ExclusionList::new().add_file(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("exclude.txt:2: Invalid pattern"));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+//! Ordering of directory entries for players reading FAT directories
+//!
+//! Cheap players play the files of a directory in the order of its FAT
+//! directory entries rather than by name.  FAT fills the free entries of
+//! a directory from its start, so moving every file of a directory out
+//! and back in track order rewrites the entries in that order.  Files
+//! are only renamed, never copied, so the pass is cheap.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::album_playlist::compare_tracks;
+
+/// Name of the directory files are moved into while being reordered
+pub const SORT_DIR_NAME: &str = ".plm-sort";
+
+/// Names of the files in a directory, in the order the filesystem lists
+/// them
+fn listed_files(dir: &Path) -> io::Result<Vec<String>> {
+    let mut files = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            files.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+
+    Ok(files)
+}
+
+/// Reorder the directory entries of the files in `dir` into track order
+///
+/// Returns `false` without touching the directory if the filesystem
+/// already lists the files in track order.  Files left in the sort
+/// directory by an interrupted run are moved back as well.
+pub fn sort_directory(dir: &Path) -> io::Result<bool> {
+    let sort_dir = dir.join(SORT_DIR_NAME);
+    let mut files = listed_files(dir)?;
+
+    if !sort_dir.is_dir() {
+        let mut sorted = files.clone();
+        sorted.sort_by(|a, b| compare_tracks(a, b));
+        if sorted == files {
+            return Ok(false);
+        }
+        fs::create_dir(&sort_dir)?;
+    }
+
+    for file in &files {
+        fs::rename(dir.join(file), sort_dir.join(file))?;
+    }
+
+    files = listed_files(&sort_dir)?;
+    files.sort_by(|a, b| compare_tracks(a, b));
+    for file in &files {
+        fs::rename(sort_dir.join(file), dir.join(file))?;
+    }
+
+    fs::remove_dir(&sort_dir)?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sort_directory() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        for file in ["2 two.flac", "10 ten.flac", "1 one.flac", "cover.jpg"] {
+            fs::write(temp_dir.path().join(file), file)?;
+        }
+        fs::create_dir(temp_dir.path().join("sub"))?;
+
+        sort_directory(temp_dir.path())?;
+
+        let mut files = listed_files(temp_dir.path())?;
+        files.sort();
+        assert_eq!(
+            files,
+            vec!["1 one.flac", "10 ten.flac", "2 two.flac", "cover.jpg"]
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("2 two.flac"))?,
+            "2 two.flac"
+        );
+        assert!(temp_dir.path().join("sub").is_dir());
+        assert!(!temp_dir.path().join(SORT_DIR_NAME).exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_directory_recovers_interrupted_run() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let sort_dir = temp_dir.path().join(SORT_DIR_NAME);
+        fs::create_dir(&sort_dir)?;
+        fs::write(sort_dir.join("1 one.flac"), "one")?;
+        fs::write(temp_dir.path().join("2 two.flac"), "two")?;
+
+        assert!(sort_directory(temp_dir.path())?);
+
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("1 one.flac"))?,
+            "one"
+        );
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("2 two.flac"))?,
+            "two"
+        );
+        assert!(!sort_dir.exists());
+
+        Ok(())
+    }
+}
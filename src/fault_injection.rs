@@ -0,0 +1,172 @@
+//! Failures injected into the copy engine by tests
+//!
+//! Integration tests of `--keep-going`, error files, retries and files
+//! left half copied need operations to fail at given points, which is
+//! hard to contrive with missing files and permissions on a real
+//! filesystem, and impossible when running as root.  With the
+//! `fault-injection` cargo feature, the `PLM_INJECT_FAULTS` environment
+//! variable lists the operations to fail by their kind and number,
+//! counted from 1 in each process:
+//!
+//! ```text
+//! PLM_INJECT_FAULTS=copy:2,create-dir:1,playlist-write:3,partial-copy:4
+//! ```
+//!
+//! fails the second media file copy, the first directory creation, the
+//! third playlist write, and the fourth media file copy after writing
+//! the first half of the file.  Injected failures are interrupted I/O
+//! errors, which `--io-retries` retries like other transient errors,
+//! and each attempt of a retried copy is counted.
+//!
+//! Without the feature, the hooks do nothing and the variable is
+//! ignored, so that release builds cannot be made to fail.
+
+use std::io;
+use std::path::Path;
+
+/// Environment variable listing the failures to inject
+pub const FAULTS_VAR: &str = "PLM_INJECT_FAULTS";
+
+/// Operation of the copy engine a failure can be injected into
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultPoint {
+    /// Copy of a media file, failing before anything is written
+    Copy,
+    /// Copy of a media file, failing once half of the file is written
+    PartialCopy,
+    /// Creation of a destination directory
+    CreateDir,
+    /// Write of a destination playlist
+    PlaylistWrite,
+}
+
+impl FaultPoint {
+    /// Kinds of operations in the order of their counters
+    const ALL: [FaultPoint; 4] = [
+        FaultPoint::Copy,
+        FaultPoint::PartialCopy,
+        FaultPoint::CreateDir,
+        FaultPoint::PlaylistWrite,
+    ];
+
+    /// Name of the kind of operations in `PLM_INJECT_FAULTS`
+    pub fn name(self) -> &'static str {
+        match self {
+            FaultPoint::Copy => "copy",
+            FaultPoint::PartialCopy => "partial-copy",
+            FaultPoint::CreateDir => "create-dir",
+            FaultPoint::PlaylistWrite => "playlist-write",
+        }
+    }
+}
+
+/// Parse the failures listed by `PLM_INJECT_FAULTS`, as the kinds of
+/// operations with the numbers of those to fail
+pub fn parse_faults(spec: &str) -> Result<Vec<(FaultPoint, usize)>, String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|fault| !fault.is_empty())
+        .map(|fault| {
+            let (name, n) = fault
+                .split_once(':')
+                .ok_or_else(|| format!("Missing operation number: {}", fault))?;
+            let point = FaultPoint::ALL
+                .into_iter()
+                .find(|point| point.name() == name)
+                .ok_or_else(|| format!("Unknown operation: {}", name))?;
+            match n.parse::<usize>() {
+                Ok(n) if n > 0 => Ok((point, n)),
+                _ => Err(format!("Invalid operation number: {}", fault)),
+            }
+        })
+        .collect()
+}
+
+/// Count an operation of the kind `point`, returning its number if a
+/// failure is injected into it
+#[cfg(feature = "fault-injection")]
+fn injected(point: FaultPoint) -> Option<usize> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::OnceLock;
+
+    static FAULTS: OnceLock<Vec<(FaultPoint, usize)>> = OnceLock::new();
+    static COUNTERS: [AtomicUsize; 4] = [const { AtomicUsize::new(0) }; 4];
+
+    let faults = FAULTS.get_or_init(|| match std::env::var(FAULTS_VAR) {
+        Ok(spec) => parse_faults(&spec).unwrap_or_else(|e| panic!("Invalid {}: {}", FAULTS_VAR, e)),
+        Err(_) => Vec::new(),
+    });
+    let counter = FaultPoint::ALL.iter().position(|&p| p == point)?;
+    let n = COUNTERS[counter].fetch_add(1, Ordering::Relaxed) + 1;
+    faults.contains(&(point, n)).then_some(n)
+}
+
+#[cfg(not(feature = "fault-injection"))]
+fn injected(_point: FaultPoint) -> Option<usize> {
+    None
+}
+
+/// Error of an injected failure
+fn injected_error(point: FaultPoint, n: usize) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Interrupted,
+        format!("Injected failure of {} {}", point.name(), n),
+    )
+}
+
+/// Fail the operation of the kind `point` if a failure is injected into
+/// it
+pub fn inject(point: FaultPoint) -> io::Result<()> {
+    match injected(point) {
+        Some(n) => Err(injected_error(point, n)),
+        None => Ok(()),
+    }
+}
+
+/// Fail the copy of `src_path` to `dest_path` if a failure is injected
+/// into it, writing the first half of the file first for a partial copy
+pub fn inject_copy(src_path: &Path, dest_path: &Path) -> io::Result<()> {
+    // Both counters count every copy
+    let failed = injected(FaultPoint::Copy);
+    let partial = injected(FaultPoint::PartialCopy);
+    if let Some(n) = failed {
+        return Err(injected_error(FaultPoint::Copy, n));
+    }
+    if let Some(n) = partial {
+        let content = std::fs::read(src_path)?;
+        std::fs::write(dest_path, &content[..content.len() / 2])?;
+        return Err(injected_error(FaultPoint::PartialCopy, n));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_faults() {
+        assert_eq!(
+            parse_faults("copy:2, create-dir:1,playlist-write:3,partial-copy:4,"),
+            Ok(vec![
+                (FaultPoint::Copy, 2),
+                (FaultPoint::CreateDir, 1),
+                (FaultPoint::PlaylistWrite, 3),
+                (FaultPoint::PartialCopy, 4),
+            ])
+        );
+        assert_eq!(parse_faults(""), Ok(vec![]));
+        assert_eq!(
+            parse_faults("copy"),
+            Err("Missing operation number: copy".into())
+        );
+        assert_eq!(
+            parse_faults("move:1"),
+            Err("Unknown operation: move".into())
+        );
+        assert_eq!(
+            parse_faults("copy:0"),
+            Err("Invalid operation number: copy:0".into())
+        );
+    }
+}
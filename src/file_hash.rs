@@ -0,0 +1,220 @@
+//! Hashes of file contents by a selectable algorithm
+//!
+//! CRC-32 is fast and enough to tell a bad copy or a different file of
+//! the same size, so it is the default for verifying copies and finding
+//! identical media files.  BLAKE3, SHA-256 and XXH3 (64-bit) are offered
+//! for users who prefer a stronger hash or need digests matching those
+//! of external tools.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+use sha2::Digest;
+
+/// Size of the buffer used when hashing a file in chunks
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Algorithm hashing file contents
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum HashAlgorithm {
+    /// CRC-32 (IEEE), the fastest
+    #[default]
+    Crc32,
+    /// BLAKE3 with 256-bit digests
+    Blake3,
+    /// SHA-256
+    Sha256,
+    /// XXH3 with 64-bit digests
+    Xxh3,
+}
+
+impl FromStr for HashAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "crc32" => Ok(Self::Crc32),
+            "blake3" => Ok(Self::Blake3),
+            "sha256" => Ok(Self::Sha256),
+            "xxh3" => Ok(Self::Xxh3),
+            _ => Err(format!(
+                "Unknown hash algorithm: {} (expected crc32, blake3, sha256 or xxh3)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for HashAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Crc32 => "crc32",
+            Self::Blake3 => "blake3",
+            Self::Sha256 => "sha256",
+            Self::Xxh3 => "xxh3",
+        })
+    }
+}
+
+/// Digest of file contents, with the algorithm computing it
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FileHash {
+    algorithm: HashAlgorithm,
+    digest: Vec<u8>,
+}
+
+impl FileHash {
+    /// Digest of a CRC-32 checksum computed elsewhere, such as while
+    /// copying
+    pub fn from_crc32(checksum: u32) -> Self {
+        Self {
+            algorithm: HashAlgorithm::Crc32,
+            digest: checksum.to_be_bytes().to_vec(),
+        }
+    }
+
+    /// Algorithm the digest was computed with
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+
+    /// Bytes of the digest, most significant first for the algorithms
+    /// giving integers
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /// CRC-32 checksum of the contents if computed with CRC-32
+    pub fn crc32(&self) -> Option<u32> {
+        match self.algorithm {
+            HashAlgorithm::Crc32 => self
+                .digest
+                .as_slice()
+                .try_into()
+                .ok()
+                .map(u32::from_be_bytes),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FileHash {
+    /// Format the digest in lower-case hexadecimal as external tools do
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.digest
+            .iter()
+            .try_for_each(|byte| write!(f, "{:02x}", byte))
+    }
+}
+
+/// Hash of data fed in chunks
+pub enum Hasher {
+    Crc32(crc32fast::Hasher),
+    Blake3(Box<blake3::Hasher>),
+    Sha256(sha2::Sha256),
+    Xxh3(Box<xxhash_rust::xxh3::Xxh3>),
+}
+
+impl Hasher {
+    /// Start hashing with `algorithm`
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Crc32 => Self::Crc32(crc32fast::Hasher::new()),
+            HashAlgorithm::Blake3 => Self::Blake3(Box::default()),
+            HashAlgorithm::Sha256 => Self::Sha256(sha2::Sha256::new()),
+            HashAlgorithm::Xxh3 => Self::Xxh3(Box::default()),
+        }
+    }
+
+    /// Feed the next chunk of data
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Self::Crc32(hasher) => hasher.update(data),
+            Self::Blake3(hasher) => {
+                hasher.update(data);
+            }
+            Self::Sha256(hasher) => hasher.update(data),
+            Self::Xxh3(hasher) => hasher.update(data),
+        }
+    }
+
+    /// Digest of the data fed
+    pub fn finalize(self) -> FileHash {
+        let (algorithm, digest) = match self {
+            Self::Crc32(hasher) => (
+                HashAlgorithm::Crc32,
+                hasher.finalize().to_be_bytes().to_vec(),
+            ),
+            Self::Blake3(hasher) => (HashAlgorithm::Blake3, hasher.finalize().as_bytes().to_vec()),
+            Self::Sha256(hasher) => (HashAlgorithm::Sha256, hasher.finalize().to_vec()),
+            Self::Xxh3(hasher) => (HashAlgorithm::Xxh3, hasher.digest().to_be_bytes().to_vec()),
+        };
+        FileHash { algorithm, digest }
+    }
+}
+
+/// Hash the contents of a file with `algorithm`, reading it in chunks
+pub fn hash_file(path: &Path, algorithm: HashAlgorithm) -> io::Result<FileHash> {
+    let mut file = File::open(path)?;
+    let mut hasher = Hasher::new(algorithm);
+    let mut buf = vec![0; CHUNK_SIZE];
+
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    Ok(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_hash_algorithm_from_str() {
+        assert_eq!("blake3".parse(), Ok(HashAlgorithm::Blake3));
+        assert_eq!("SHA256".parse(), Ok(HashAlgorithm::Sha256));
+        assert_eq!("xxh3".parse(), Ok(HashAlgorithm::Xxh3));
+        assert_eq!("crc32".parse(), Ok(HashAlgorithm::Crc32));
+        assert!("md5".parse::<HashAlgorithm>().is_err());
+        assert_eq!(HashAlgorithm::Sha256.to_string(), "sha256");
+    }
+
+    #[test]
+    fn test_hash_file() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("a.flac");
+        fs::write(&path, "abc")?;
+
+        let sha256 = hash_file(&path, HashAlgorithm::Sha256)?;
+        assert_eq!(
+            sha256.to_string(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(sha256.crc32(), None);
+
+        let crc32 = hash_file(&path, HashAlgorithm::Crc32)?;
+        assert_eq!(crc32.crc32(), Some(crc32fast::hash(b"abc")));
+        assert_eq!(crc32.to_string(), "352441c2");
+
+        assert_eq!(
+            hash_file(&path, HashAlgorithm::Blake3)?.to_string(),
+            blake3::hash(b"abc").to_hex().as_str()
+        );
+        assert_eq!(
+            hash_file(&path, HashAlgorithm::Xxh3)?.as_bytes(),
+            xxhash_rust::xxh3::xxh3_64(b"abc").to_be_bytes()
+        );
+
+        Ok(())
+    }
+}
@@ -1,14 +1,20 @@
 //! File utilities for generic file operations
 
-use std::fs;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
 use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
 // Context trait is used via method calls (.context()), suppress unused warning
 #[allow(unused_imports)]
 use anyhow::{Context, Result};
 
+use crate::fault_injection::{inject, FaultPoint};
+
 /// Creates a directory if it doesn't exist.
 pub fn create_directory(path: &Path) -> Result<()> {
     if !path.exists() {
+        inject(FaultPoint::CreateDir)?;
         fs::create_dir_all(path)?;
     }
     Ok(())
@@ -29,12 +35,328 @@ pub fn copy_file(src_path: &Path, dest_path: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Characters not allowed in FAT file names
+pub const RESERVED_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Device names Windows reserves whatever the extension of a name
+const WINDOWS_DEVICE_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Whether the part of a name before its first dot, without trailing
+/// spaces, is a Windows device name
+fn is_device_stem(stem: &str) -> bool {
+    let stem = stem.trim_end_matches(' ');
+    WINDOWS_DEVICE_NAMES
+        .iter()
+        .any(|device| device.eq_ignore_ascii_case(stem))
+}
+
+/// Whether a file or directory name cannot be used on Windows: a
+/// device name such as `CON` or `aux.mp3`, or a name ending with a dot
+/// or a space
+pub fn is_windows_reserved_name(name: &str) -> bool {
+    name.ends_with(['.', ' ']) || is_device_stem(name.split('.').next().unwrap_or(name))
+}
+
+/// Rename a file or directory name that cannot be used on Windows,
+/// dropping trailing dots and spaces as Windows does and appending `_`
+/// to a device name before its extension as in `CON_.flac`
+///
+/// Other names, and the `.` and `..` components of paths, are returned
+/// unchanged.
+pub fn windows_safe_name(name: &str) -> String {
+    if !is_windows_reserved_name(name) || name == "." || name == ".." {
+        return name.to_string();
+    }
+
+    let trimmed = name.trim_end_matches(['.', ' ']);
+    let (stem, extension) = trimmed.split_at(trimmed.find('.').unwrap_or(trimmed.len()));
+    match is_device_stem(stem) || trimmed.is_empty() {
+        true => format!("{}_{}", stem.trim_end_matches(' '), extension),
+        false => trimmed.to_string(),
+    }
+}
+
+/// Size of the buffer used when hashing or comparing a file in chunks
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Default size of each of the two buffers used when copying a file in
+/// chunks, chosen with `cargo bench --bench copy_buffer`
+pub const DEFAULT_COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Copies a file in chunks, computing the CRC-32 checksum of the data
+/// as it is written.
+///
+/// The checksum is that of the source contents, so verifying the copy
+/// only requires reading the destination back once.
+pub fn copy_file_with_checksum(src_path: &Path, dest_path: &Path) -> Result<u32> {
+    copy_file_buffered(src_path, dest_path, DEFAULT_COPY_BUFFER_SIZE)
+}
+
+/// Copies a file in chunks of `buffer_size` bytes with read-ahead,
+/// computing the CRC-32 checksum of the data as it is written.
+///
+/// Two buffers take turns: while one is written to the destination, the
+/// next chunk is read into the other on a separate thread, so that a
+/// slow destination such as a USB device is kept busy.
+pub fn copy_file_buffered(src_path: &Path, dest_path: &Path, buffer_size: usize) -> Result<u32> {
+    let mut hasher = crc32fast::Hasher::new();
+    copy_file_inspected(src_path, dest_path, buffer_size, |data| hasher.update(data))?;
+
+    Ok(hasher.finalize())
+}
+
+/// Copies a file in chunks of `buffer_size` bytes with read-ahead like
+/// [`copy_file_buffered`], passing each chunk to `inspect` as it is
+/// written, such as to compute other hashes of the data.
+pub fn copy_file_inspected(
+    src_path: &Path,
+    dest_path: &Path,
+    buffer_size: usize,
+    mut inspect: impl FnMut(&[u8]),
+) -> Result<()> {
+    let src = File::open(src_path)?;
+    let mut dest = File::create(dest_path)?;
+
+    copy_double_buffered(src, &mut dest, buffer_size.max(1), &mut inspect)?;
+    dest.flush()?;
+
+    Ok(())
+}
+
+/// Copies `src` into `dest` through two buffers, reading on a separate
+/// thread while writing, and returns the number of bytes copied
+fn copy_double_buffered<R: Read + Send>(
+    mut src: R,
+    dest: &mut impl Write,
+    buffer_size: usize,
+    inspect: &mut impl FnMut(&[u8]),
+) -> io::Result<u64> {
+    // Filled buffers go to the writer, emptied buffers back to the reader
+    let (filled_tx, filled_rx) = mpsc::sync_channel::<io::Result<(Vec<u8>, usize)>>(1);
+    let (emptied_tx, emptied_rx) = mpsc::channel::<Vec<u8>>();
+    for _ in 0..2 {
+        emptied_tx
+            .send(vec![0; buffer_size])
+            .expect("reader not started yet");
+    }
+
+    thread::scope(move |scope| {
+        scope.spawn(move || {
+            for mut buf in emptied_rx {
+                let chunk = read_chunk(&mut src, &mut buf).map(|n| (buf, n));
+                let done = !matches!(chunk, Ok((_, n)) if n > 0);
+                // The writer hangs up after a write error
+                if filled_tx.send(chunk).is_err() || done {
+                    break;
+                }
+            }
+        });
+
+        // Returning drops the channels, which stops the reader
+        let mut n_bytes = 0;
+        for chunk in &filled_rx {
+            let (buf, n) = chunk?;
+            if n == 0 {
+                break;
+            }
+            inspect(&buf[..n]);
+            dest.write_all(&buf[..n])?;
+            n_bytes += n as u64;
+            let _ = emptied_tx.send(buf);
+        }
+        Ok(n_bytes)
+    })
+}
+
+/// Reads into `buf` until it is full or the end of `src` is reached,
+/// returning the number of bytes read
+fn read_chunk(src: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match src.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(read) => n += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
+/// Whether an error of a copy is likely to go away when the copy is
+/// retried: an I/O error of the device, such as EIO from a flaky USB
+/// connection, or a timeout.
+pub fn is_transient_error(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|e| e.downcast_ref::<io::Error>())
+        .any(is_transient_io_error)
+}
+
+fn is_transient_io_error(err: &io::Error) -> bool {
+    if matches!(
+        err.kind(),
+        io::ErrorKind::TimedOut | io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock
+    ) {
+        return true;
+    }
+    #[cfg(unix)]
+    if err.raw_os_error() == Some(libc::EIO) {
+        return true;
+    }
+    false
+}
+
+/// Whether one of two directories, given by canonical paths, is the
+/// other or lies inside it.
+pub fn paths_overlap(dir1: &Path, dir2: &Path) -> bool {
+    dir1.starts_with(dir2) || dir2.starts_with(dir1)
+}
+
+/// Computes the CRC-32 checksum of a file, reading it in chunks.
+pub fn file_checksum(path: &Path) -> Result<u32> {
+    let mut file = File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buf = vec![0; CHUNK_SIZE];
+
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    Ok(hasher.finalize())
+}
+
+/// Compares the contents of two files byte by byte, reading them in
+/// chunks.
+pub fn same_contents(path1: &Path, path2: &Path) -> Result<bool> {
+    let mut file1 = File::open(path1)?;
+    let mut file2 = File::open(path2)?;
+    if file1.metadata()?.len() != file2.metadata()?.len() {
+        return Ok(false);
+    }
+
+    let mut buf1 = vec![0; CHUNK_SIZE];
+    let mut buf2 = vec![0; CHUNK_SIZE];
+
+    loop {
+        let n = file1.read(&mut buf1)?;
+        if n == 0 {
+            return Ok(true);
+        }
+        file2.read_exact(&mut buf2[..n])?;
+        if buf1[..n] != buf2[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Copies the user extended attributes of a file, such as ratings
+/// written by some players and taggers, to another file.
+///
+/// Only attributes in the `user` namespace are copied, replacing those
+/// of the same names.  Returns `false` without copying if either
+/// filesystem does not support extended attributes, such as FAT.
+#[cfg(target_os = "linux")]
+pub fn copy_user_xattrs(src_path: &Path, dest_path: &Path) -> io::Result<bool> {
+    let src = path_cstring(src_path)?;
+    let dest = path_cstring(dest_path)?;
+
+    // SAFETY: `src` is NUL-terminated and no buffer is passed
+    let size = unsafe { libc::listxattr(src.as_ptr(), std::ptr::null_mut(), 0) };
+    let mut names = vec![0u8; xattr_result(size)?.unwrap_or(0)];
+    if names.is_empty() {
+        return Ok(size >= 0);
+    }
+    // SAFETY: `names` holds `names.len()` bytes
+    let size = unsafe { libc::listxattr(src.as_ptr(), names.as_mut_ptr().cast(), names.len()) };
+    let Some(size) = xattr_result(size)? else {
+        return Ok(false);
+    };
+    names.truncate(size);
+
+    for name in names.split(|&b| b == 0) {
+        if !name.starts_with(b"user.") {
+            continue;
+        }
+        let name = std::ffi::CString::new(name)?;
+        // SAFETY: `src` and `name` are NUL-terminated and no buffer is
+        // passed
+        let size = unsafe { libc::getxattr(src.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0) };
+        let mut value = vec![0u8; xattr_result(size)?.unwrap_or(0)];
+        // SAFETY: `value` holds `value.len()` bytes
+        let size = unsafe {
+            libc::getxattr(
+                src.as_ptr(),
+                name.as_ptr(),
+                value.as_mut_ptr().cast(),
+                value.len(),
+            )
+        };
+        let Some(size) = xattr_result(size)? else {
+            return Ok(false);
+        };
+        // SAFETY: `dest` and `name` are NUL-terminated and `value` holds
+        // at least `size` bytes
+        let result =
+            unsafe { libc::setxattr(dest.as_ptr(), name.as_ptr(), value.as_ptr().cast(), size, 0) };
+        if xattr_result(result as isize)?.is_none() {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Copies no extended attributes, as they are only supported on Linux.
+#[cfg(not(target_os = "linux"))]
+pub fn copy_user_xattrs(_src_path: &Path, _dest_path: &Path) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Turns a path into a NUL-terminated string for the C library.
+#[cfg(target_os = "linux")]
+fn path_cstring(path: &Path) -> io::Result<std::ffi::CString> {
+    use std::os::unix::ffi::OsStrExt;
+
+    Ok(std::ffi::CString::new(path.as_os_str().as_bytes())?)
+}
+
+/// Turns the result of an extended attribute call into a size, or into
+/// `None` if extended attributes are not supported.
+#[cfg(target_os = "linux")]
+fn xattr_result(result: isize) -> io::Result<Option<usize>> {
+    if result >= 0 {
+        return Ok(Some(result as usize));
+    }
+    let e = io::Error::last_os_error();
+    match e.raw_os_error() {
+        Some(libc::ENOTSUP) => Ok(None),
+        _ => Err(e),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
     use tempfile::TempDir;
 
+    #[test]
+    fn test_paths_overlap() {
+        let music = Path::new("/home/me/MUSIC");
+        assert!(paths_overlap(music, Path::new("/home/me/MUSIC")));
+        assert!(paths_overlap(music, Path::new("/home/me/MUSIC/DEST")));
+        assert!(paths_overlap(Path::new("/home/me"), music));
+        assert!(!paths_overlap(music, Path::new("/home/me/MUSIC2")));
+        assert!(!paths_overlap(music, Path::new("/mnt/sdcard/MUSIC")));
+    }
+
     #[test]
     fn test_create_directory_success() -> Result<()> {
         let temp_dir = TempDir::new()?;
@@ -83,4 +405,177 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_copy_file_with_checksum_matches_destination() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_file = temp_dir.path().join("source.bin");
+        let dest_file = temp_dir.path().join("dest.bin");
+
+        // Create a source file spanning several chunks
+        let content: Vec<u8> = (0..CHUNK_SIZE * 3 + 17).map(|i| i as u8).collect();
+        fs::write(&src_file, &content)?;
+
+        let checksum = copy_file_with_checksum(&src_file, &dest_file)?;
+
+        assert_eq!(fs::read(&dest_file)?, content);
+        assert_eq!(checksum, file_checksum(&src_file)?);
+        assert_eq!(checksum, file_checksum(&dest_file)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_file_buffered_small_buffers() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src_file = temp_dir.path().join("source.bin");
+        let dest_file = temp_dir.path().join("dest.bin");
+
+        let content: Vec<u8> = (0..10_007).map(|i| (i * 7) as u8).collect();
+        fs::write(&src_file, &content)?;
+
+        for buffer_size in [1, 100, 10_007, 1 << 20] {
+            let checksum = copy_file_buffered(&src_file, &dest_file, buffer_size)?;
+            assert_eq!(fs::read(&dest_file)?, content);
+            assert_eq!(checksum, file_checksum(&src_file)?);
+        }
+
+        fs::write(&src_file, "")?;
+        copy_file_buffered(&src_file, &dest_file, 16)?;
+        assert!(fs::read(&dest_file)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_double_buffered_write_error() {
+        struct Full;
+        impl Write for Full {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::StorageFull, "full"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let src = io::repeat(1).take(1 << 20);
+        let mut hasher = crc32fast::Hasher::new();
+        let mut inspect = |data: &[u8]| hasher.update(data);
+        let result = copy_double_buffered(src, &mut Full, 1024, &mut inspect);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::StorageFull);
+    }
+
+    #[test]
+    fn test_is_transient_error() {
+        let timeout = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+        assert!(is_transient_error(&anyhow::Error::new(timeout)));
+        let not_found = io::Error::new(io::ErrorKind::NotFound, "not found");
+        assert!(!is_transient_error(&anyhow::Error::new(not_found)));
+        assert!(!is_transient_error(&anyhow::anyhow!("Checksum mismatch")));
+        #[cfg(unix)]
+        {
+            let eio = anyhow::Error::new(io::Error::from_raw_os_error(libc::EIO));
+            assert!(is_transient_error(&eio.context("Failed to copy")));
+        }
+    }
+
+    #[test]
+    fn test_file_checksum_detects_difference() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let file1 = temp_dir.path().join("file1.txt");
+        let file2 = temp_dir.path().join("file2.txt");
+
+        fs::write(&file1, "test content")?;
+        fs::write(&file2, "test contenT")?;
+
+        assert_ne!(file_checksum(&file1)?, file_checksum(&file2)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_same_contents() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let file1 = temp_dir.path().join("file1.bin");
+        let file2 = temp_dir.path().join("file2.bin");
+        let file3 = temp_dir.path().join("file3.bin");
+
+        let content: Vec<u8> = (0..CHUNK_SIZE * 2 + 5).map(|i| i as u8).collect();
+        fs::write(&file1, &content)?;
+        fs::write(&file2, &content)?;
+        let mut different = content.clone();
+        *different.last_mut().unwrap() ^= 1;
+        fs::write(&file3, &different)?;
+
+        assert!(same_contents(&file1, &file2)?);
+        assert!(!same_contents(&file1, &file3)?);
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_windows_safe_name() {
+        assert!(is_windows_reserved_name("con"));
+        assert!(is_windows_reserved_name("Aux.mp3"));
+        assert!(is_windows_reserved_name("LPT1 .tar.gz"));
+        assert!(is_windows_reserved_name("Vol. 1."));
+        assert!(!is_windows_reserved_name("Console.flac"));
+        assert!(!is_windows_reserved_name("COM10.flac"));
+
+        assert_eq!(windows_safe_name("CON.flac"), "CON_.flac");
+        assert_eq!(windows_safe_name("nul"), "nul_");
+        assert_eq!(windows_safe_name("LPT1 .tar.gz"), "LPT1_.tar.gz");
+        assert_eq!(windows_safe_name("Vol. 1.."), "Vol. 1");
+        assert_eq!(windows_safe_name("Track.flac "), "Track.flac");
+        assert_eq!(windows_safe_name("AUX."), "AUX_");
+        assert_eq!(windows_safe_name("..."), "_");
+        assert_eq!(windows_safe_name("title1.flac"), "title1.flac");
+        assert_eq!(windows_safe_name(".."), "..");
+    }
+
+    #[test]
+    fn test_copy_user_xattrs() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let src = temp_dir.path().join("src.flac");
+        let dest = temp_dir.path().join("dest.flac");
+        fs::write(&src, "test content")?;
+        fs::write(&dest, "test content")?;
+
+        let src_path = path_cstring(&src)?;
+        let rating = b"5";
+        // SAFETY: the strings are NUL-terminated and `rating` holds its
+        // length in bytes
+        let result = unsafe {
+            libc::setxattr(
+                src_path.as_ptr(),
+                c"user.rating".as_ptr(),
+                rating.as_ptr().cast(),
+                rating.len(),
+                0,
+            )
+        };
+        if result != 0 {
+            // The temporary directory does not support user attributes
+            return Ok(());
+        }
+
+        assert!(copy_user_xattrs(&src, &dest)?);
+        let dest_path = path_cstring(&dest)?;
+        let mut value = [0u8; 8];
+        // SAFETY: `value` holds `value.len()` bytes
+        let size = unsafe {
+            libc::getxattr(
+                dest_path.as_ptr(),
+                c"user.rating".as_ptr(),
+                value.as_mut_ptr().cast(),
+                value.len(),
+            )
+        };
+        assert_eq!(size, 1);
+        assert_eq!(&value[..1], rating);
+
+        Ok(())
+    }
 }
@@ -1,5 +1,49 @@
 // Export the media_file_info module
+pub mod album_playlist;
+pub mod archive;
+pub mod archive_reader;
+pub mod copy_report;
+pub mod dest_index;
+pub mod device_lock;
+pub mod device_profile;
+pub mod diagnostics;
+pub mod empty_dirs;
+pub mod error_file;
+pub mod exclusion_list;
+pub mod fat_order;
+pub mod fault_injection;
+#[cfg(feature = "hashes")]
+pub mod file_hash;
 pub mod file_utils;
+#[cfg(feature = "tags")]
+pub mod library_playlists;
+pub mod media_extensions;
 pub mod media_file_info;
+pub mod media_set;
+#[cfg(feature = "tags")]
+pub mod media_tags;
+#[cfg(feature = "s3")]
+pub mod object_store;
+pub mod path_interner;
+#[cfg(feature = "encodings")]
+pub mod playlist_encoding;
+pub mod playlist_file;
+pub mod playlist_options;
 pub mod playlist_scanner;
+pub mod post_sync;
+pub mod progress_bar;
+pub mod run_id;
+pub mod source_guard;
+#[cfg(feature = "sync-state")]
+pub mod sync_state;
+pub mod track_matcher;
+pub mod track_path;
+#[cfg(feature = "transfer")]
+pub mod transfer;
+#[cfg(feature = "transliterate")]
+pub mod transliterate;
+pub mod trash;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod uring_copy;
+pub mod xspf;
 pub mod logger;
@@ -0,0 +1,370 @@
+//! Generation of playlists grouping the tracks of a library
+//!
+//! A freshly ripped library has no playlists, and players offering
+//! playlist navigation only cannot browse it by artist or genre.  This
+//! module scans a library and groups its media files by the artist or
+//! genre read from their tags, or by the top-level directories of the
+//! library, which name the artists or genres in layouts such as
+//! `Artist/Album/01 Title.flac`.  Each group is written as a playlist
+//! named after it, with its tracks ordered by directory and then by
+//! track number as in album playlists.
+//!
+//! The module also gathers the tracks modified recently into a single
+//! "Recently Added" playlist, newest first, so that a device can carry
+//! the latest rips without editing a playlist by hand.
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+use crate::album_playlist::compare_tracks;
+use crate::file_utils::RESERVED_CHARS;
+use crate::media_extensions::is_media_file;
+use crate::media_tags::{read_tags, MediaTags};
+use crate::track_matcher::absolute_path;
+
+/// Extension of the generated playlists
+const PLAYLIST_EXTENSION: &str = "m3u8";
+
+/// Name of the playlist of the recently added tracks
+pub const RECENTLY_ADDED_PLAYLIST: &str = "Recently Added.m3u8";
+
+/// What the tracks are grouped by
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GroupBy {
+    /// The album artist, or the artist if the album artist is not tagged
+    Artist,
+    /// The genre
+    Genre,
+}
+
+impl GroupBy {
+    /// Group of a track with the given tags
+    fn tag<'a>(&self, tags: &'a MediaTags) -> Option<&'a str> {
+        match self {
+            GroupBy::Artist => tags.album_artist_or_artist(),
+            GroupBy::Genre => tags.genre.as_deref(),
+        }
+    }
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "artist" => Ok(GroupBy::Artist),
+            "genre" => Ok(GroupBy::Genre),
+            _ => Err(format!("Unknown group: {} (expected artist or genre)", s)),
+        }
+    }
+}
+
+/// Where the group of a track is read from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GroupSource {
+    /// The tags of the media file
+    #[default]
+    Tags,
+    /// The top-level directory of the library containing the media file
+    Dirs,
+}
+
+impl FromStr for GroupSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tags" => Ok(GroupSource::Tags),
+            "dirs" => Ok(GroupSource::Dirs),
+            _ => Err(format!("Unknown source: {} (expected tags or dirs)", s)),
+        }
+    }
+}
+
+/// Tracks of a library grouped into playlists
+#[derive(Debug, Default)]
+pub struct LibraryPlaylists {
+    /// Tracks of each group by playlist file name, relative to the
+    /// library with `/` separators
+    pub groups: BTreeMap<String, Vec<String>>,
+    /// Tracks without a group, such as untagged tracks
+    pub ungrouped: Vec<String>,
+}
+
+/// Group the media files found recursively under `root`
+///
+/// Groups whose names only differ by characters not allowed in file
+/// names share a playlist.  Symbolic links to directories are not
+/// followed.
+pub fn group_library(
+    root: &Path,
+    group_by: GroupBy,
+    source: GroupSource,
+) -> io::Result<LibraryPlaylists> {
+    let mut tracks = Vec::new();
+    collect_tracks(root, "", &mut tracks)?;
+    tracks.sort_by(|a, b| compare_paths(a, b));
+
+    let mut playlists = LibraryPlaylists::default();
+    for track in tracks {
+        let group = match source {
+            GroupSource::Tags => read_tags(&root.join(&track))
+                .ok()
+                .and_then(|tags| group_by.tag(&tags).and_then(playlist_name)),
+            GroupSource::Dirs => track
+                .split_once('/')
+                .and_then(|(dir, _)| playlist_name(dir)),
+        };
+        match group {
+            Some(name) => playlists.groups.entry(name).or_default().push(track),
+            None => playlists.ungrouped.push(track),
+        }
+    }
+
+    Ok(playlists)
+}
+
+/// Time `days` days before now, to give `recently_added()` the tracks
+/// added within `days` days
+pub fn days_ago(days: u64) -> SystemTime {
+    let age = Duration::from_secs(days.saturating_mul(24 * 60 * 60));
+    SystemTime::now()
+        .checked_sub(age)
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// Gather the media files found recursively under `root` modified at
+/// `since` or later into the playlist of the recently added tracks,
+/// newest first
+///
+/// The playlist is kept even without tracks, so that writing it clears
+/// the tracks of an earlier run.  Tracks modified at the same time are
+/// ordered as in the playlists of groups.
+pub fn recently_added(root: &Path, since: SystemTime) -> io::Result<LibraryPlaylists> {
+    let mut tracks = Vec::new();
+    collect_tracks(root, "", &mut tracks)?;
+
+    let mut added = Vec::new();
+    for track in tracks {
+        let modified = fs::metadata(root.join(&track))?.modified()?;
+        if modified >= since {
+            added.push((modified, track));
+        }
+    }
+    added.sort_by(|(time_a, a), (time_b, b)| time_b.cmp(time_a).then_with(|| compare_paths(a, b)));
+
+    let mut playlists = LibraryPlaylists::default();
+    playlists.groups.insert(
+        RECENTLY_ADDED_PLAYLIST.to_string(),
+        added.into_iter().map(|(_, track)| track).collect(),
+    );
+    Ok(playlists)
+}
+
+impl LibraryPlaylists {
+    /// Write a playlist per group into `out_dir`, creating it if needed
+    ///
+    /// Entries are relative to `out_dir`.  Existing playlists of the same
+    /// names are replaced.  Returns the paths of the written playlists.
+    pub fn write(&self, root: &Path, out_dir: &Path) -> io::Result<Vec<PathBuf>> {
+        fs::create_dir_all(out_dir)?;
+        let root = absolute_path(root)?;
+        let out_dir_absolute = absolute_path(out_dir)?;
+
+        let mut written = Vec::new();
+        for (name, tracks) in &self.groups {
+            let mut content = String::new();
+            for track in tracks {
+                content.push_str(&relative_path(&out_dir_absolute, &root.join(track)));
+                content.push('\n');
+            }
+
+            let playlist = out_dir.join(name);
+            fs::write(&playlist, content)?;
+            written.push(playlist);
+        }
+
+        Ok(written)
+    }
+}
+
+/// Append the media files found recursively in `dir` to `tracks`,
+/// prefixed with `prefix`
+fn collect_tracks(dir: &Path, prefix: &str, tracks: &mut Vec<String>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        let track = match prefix {
+            "" => name,
+            _ => format!("{}/{}", prefix, name),
+        };
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_tracks(&path, &track, tracks)?;
+        } else if is_media_file(&path) && path.is_file() {
+            tracks.push(track);
+        }
+    }
+
+    Ok(())
+}
+
+/// Order of tracks in a playlist: by directory, then in track order
+fn compare_paths(a: &str, b: &str) -> Ordering {
+    let (dir_a, name_a) = a.rsplit_once('/').unwrap_or(("", a));
+    let (dir_b, name_b) = b.rsplit_once('/').unwrap_or(("", b));
+    dir_a
+        .cmp(dir_b)
+        .then_with(|| compare_tracks(name_a, name_b))
+}
+
+/// File name of the playlist of a group, replacing characters not
+/// allowed in FAT names by `_` and dropping trailing dots and spaces
+///
+/// Returns `None` for a group without a usable name.
+pub fn playlist_name(group: &str) -> Option<String> {
+    let name: String = group
+        .chars()
+        .map(|c| match RESERVED_CHARS.contains(&c) || c.is_control() {
+            true => '_',
+            false => c,
+        })
+        .collect();
+    let name = name.trim_end_matches(['.', ' ']).trim_start();
+    (!name.is_empty()).then(|| format!("{}.{}", name, PLAYLIST_EXTENSION))
+}
+
+/// Path of `path` relative to the directory `dir`, both absolute, with
+/// `/` separators and `..` to leave `dir`
+///
+/// Paths on different Windows drives cannot be made relative, so the
+/// absolute path is returned for them.
+fn relative_path(dir: &Path, path: &Path) -> String {
+    let dir_components: Vec<Component> = dir.components().collect();
+    let path_components: Vec<Component> = path.components().collect();
+    let common = dir_components
+        .iter()
+        .zip(&path_components)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return path.to_string_lossy().replace('\\', "/");
+    }
+
+    let parents = dir_components.len() - common;
+    let mut parts: Vec<String> = vec!["..".to_string(); parents];
+    parts.extend(
+        path_components[common..]
+            .iter()
+            .map(|component| component.as_os_str().to_string_lossy().to_string()),
+    );
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// Write a FLAC file holding only the given Vorbis comments
+    fn write_flac(path: &Path, comments: &[&str]) -> io::Result<()> {
+        let mut block = Vec::new();
+        block.extend_from_slice(&0u32.to_le_bytes());
+        block.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            block.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            block.extend_from_slice(comment.as_bytes());
+        }
+
+        let mut content = b"fLaC\x84".to_vec();
+        content.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+        content.extend_from_slice(&block);
+        fs::create_dir_all(path.parent().unwrap())?;
+        fs::write(path, content)
+    }
+
+    #[test]
+    fn test_group_library() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path().join("library");
+        write_flac(&root.join("band/album/10 Ten.flac"), &["ARTIST=AC/DC"])?;
+        write_flac(&root.join("band/album/2 Two.flac"), &["ARTIST=AC/DC"])?;
+        write_flac(&root.join("various/01.flac"), &["ALBUMARTIST=AC/DC"])?;
+        write_flac(&root.join("loose.flac"), &["GENRE=Rock"])?;
+        fs::write(root.join("band/album/cover.jpg"), "")?;
+
+        let playlists = group_library(&root, GroupBy::Artist, GroupSource::Tags)?;
+        assert_eq!(
+            playlists.groups["AC_DC.m3u8"],
+            vec![
+                "band/album/2 Two.flac",
+                "band/album/10 Ten.flac",
+                "various/01.flac"
+            ]
+        );
+        assert_eq!(playlists.ungrouped, vec!["loose.flac"]);
+
+        let playlists = group_library(&root, GroupBy::Genre, GroupSource::Dirs)?;
+        let names: Vec<&str> = playlists.groups.keys().map(|name| name.as_str()).collect();
+        assert_eq!(names, vec!["band.m3u8", "various.m3u8"]);
+
+        let out_dir = temp_dir.path().join("playlists");
+        let written = playlists.write(&root, &out_dir)?;
+        assert_eq!(written.len(), 2);
+        assert_eq!(
+            fs::read_to_string(out_dir.join("various.m3u8"))?,
+            "../library/various/01.flac\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recently_added() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 60 * 60);
+        for (track, age) in [
+            ("old.flac", 40),
+            ("b/2.flac", 1),
+            ("b/1.flac", 1),
+            ("a.mp3", 0),
+        ] {
+            write_flac(&root.join(track), &[])?;
+            fs::File::options()
+                .write(true)
+                .open(root.join(track))?
+                .set_modified(now - day * age)?;
+        }
+
+        let playlists = recently_added(root, now - day * 30)?;
+        assert_eq!(
+            playlists.groups[RECENTLY_ADDED_PLAYLIST],
+            vec!["a.mp3", "b/1.flac", "b/2.flac"]
+        );
+
+        let playlists = recently_added(root, now + day)?;
+        assert!(playlists.groups[RECENTLY_ADDED_PLAYLIST].is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_playlist_name() {
+        assert_eq!(playlist_name("Rock"), Some("Rock.m3u8".to_string()));
+        assert_eq!(
+            playlist_name("R&B / Soul"),
+            Some("R&B _ Soul.m3u8".to_string())
+        );
+        assert_eq!(playlist_name("Jr. "), Some("Jr.m3u8".to_string()));
+        assert_eq!(playlist_name(" ..."), None);
+        assert!("Jazz".parse::<GroupBy>().is_err());
+    }
+}
@@ -1,41 +1,60 @@
 //! Logging utilities for playlist manager operations.
+//!
+//! Messages are written to a [`Logger`] owned by the caller and handed to
+//! each operation, so library users can capture them into their own user
+//! interface and concurrent operations can each log to their own sink.
 
-use std::sync::OnceLock;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use std::sync::Mutex;
 
-/// A logger that handles verbose output with optional counters and formatting.
-#[derive(Debug)]
-pub struct Logger {
-    verbose: bool,
-}
+/// A sink for verbose output with optional counters and formatting.
+///
+/// Implementors only need to provide [`write`](Logger::write); the other
+/// methods format messages and pass them on.
+pub trait Logger: Send + Sync {
+    /// Whether messages are written at all.  Formatting is skipped when
+    /// this returns `false`.
+    fn enabled(&self) -> bool {
+        true
+    }
 
-impl Logger {
-    /// Create a new logger with the specified verbose flag.
-    pub fn new(verbose: bool) -> Self {
-        Logger { verbose }
+    /// Whether debug messages are written.  Debug messages are only
+    /// written by loggers opting in to them.
+    fn debug_enabled(&self) -> bool {
+        false
     }
 
-    /// Log a simple message if verbose mode is enabled.
-    pub fn log(&self, message: &str) {
-        if self.verbose {
-            eprintln!("{}", message);
+    /// Write a fully formatted message.
+    fn write(&self, message: &str);
+
+    /// Log a simple message if the logger is enabled.
+    fn log(&self, message: &str) {
+        if self.enabled() {
+            self.write(message);
         }
     }
 
-    /// Log a formatted message if verbose mode is enabled.
-    pub fn log_formatted(&self, message_template: &str, args: &[&str]) {
-        if !self.verbose {
+    /// Log a formatted message if the logger is enabled.
+    fn log_formatted(&self, message_template: &str, args: &[&str]) {
+        if !self.enabled() {
             return;
         }
 
-        let formatted_message = args.iter().fold(message_template.to_string(), |acc, arg| {
-            acc.replacen("{}", arg, 1)
-        });
+        self.write(&format_message(message_template, args));
+    }
+
+    /// Log a formatted debug message if debug messages are enabled.
+    fn debug_formatted(&self, message_template: &str, args: &[&str]) {
+        if !self.debug_enabled() {
+            return;
+        }
 
-        eprintln!("{}", formatted_message);
+        self.write(&format_message(message_template, args));
     }
 
     /// Log with counters and optional file type formatting.
-    pub fn log_with_counters(
+    fn log_with_counters(
         &self,
         message_template: &str,
         args: &[&str],
@@ -43,18 +62,17 @@ impl Logger {
         total_count: Option<usize>,
         file_type: Option<&str>,
     ) {
-        if !self.verbose {
+        if !self.enabled() {
             return;
         }
 
-        let formatted_message = args.iter().fold(message_template.to_string(), |acc, arg| {
-            acc.replacen("{}", arg, 1)
-        });
+        let formatted_message = format_message(message_template, args);
 
         let message = if let (Some(current), Some(total)) = (current_count, total_count) {
             let counter_prefix = match file_type {
                 Some("lyrics") => format!("({}-L/{})", current, total),
                 Some("media") => format!("({}-M/{})", current, total),
+                Some("sidecar") => format!("({}-S/{})", current, total),
                 _ => format!("({}/{})", current, total),
             };
             format!("{} {}", counter_prefix, formatted_message)
@@ -62,19 +80,240 @@ impl Logger {
             formatted_message
         };
 
+        self.write(&message);
+    }
+}
+
+/// Substitute each `{}` in the template with the next argument.
+fn format_message(message_template: &str, args: &[&str]) -> String {
+    args.iter().fold(message_template.to_string(), |acc, arg| {
+        acc.replacen("{}", arg, 1)
+    })
+}
+
+/// A logger writing to standard error when verbose mode is enabled.
+///
+/// Messages are written from verbosity level 1 and debug messages from
+/// level 2.
+#[derive(Debug)]
+pub struct StderrLogger {
+    verbosity: u8,
+}
+
+impl StderrLogger {
+    /// Create a new logger with the specified verbose flag.
+    pub fn new(verbose: bool) -> Self {
+        Self::with_verbosity(u8::from(verbose))
+    }
+
+    /// Create a new logger with the specified verbosity level.
+    pub fn with_verbosity(verbosity: u8) -> Self {
+        StderrLogger { verbosity }
+    }
+}
+
+impl Logger for StderrLogger {
+    fn enabled(&self) -> bool {
+        self.verbosity >= 1
+    }
+
+    fn debug_enabled(&self) -> bool {
+        self.verbosity >= 2
+    }
+
+    fn write(&self, message: &str) {
         eprintln!("{}", message);
     }
 }
 
-/// Static logger instance - will be initialized once in process_normal_operations
-static LOGGER: OnceLock<Logger> = OnceLock::new();
+/// A logger discarding all messages.
+#[derive(Debug, Default)]
+pub struct NullLogger;
 
-/// Initialize the static logger (called once from process_normal_operations)
-pub fn init_logger(verbose: bool) {
-    LOGGER.set(Logger::new(verbose)).ok(); // Ignore error if already set
+impl Logger for NullLogger {
+    fn enabled(&self) -> bool {
+        false
+    }
+
+    fn write(&self, _message: &str) {}
 }
 
-/// Get the static logger instance
-pub fn get_logger() -> &'static Logger {
-    LOGGER.get().expect("Logger not initialized - call init_logger first")
+/// A logger collecting messages in memory, for callers presenting them
+/// in their own user interface.
+#[derive(Debug, Default)]
+pub struct MemoryLogger {
+    messages: Mutex<Vec<String>>,
+}
+
+impl MemoryLogger {
+    /// Create an empty logger.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Take the messages logged so far.
+    pub fn take_messages(&self) -> Vec<String> {
+        std::mem::take(&mut *self.messages.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+impl Logger for MemoryLogger {
+    fn write(&self, message: &str) {
+        self.messages
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(message.to_string());
+    }
+}
+
+/// Files failing for the same reason, grouped by directory to be
+/// reported as one line per directory instead of one line per file.
+#[derive(Debug)]
+pub struct FailureGroups {
+    reason: String,
+    dirs: BTreeMap<PathBuf, Vec<String>>,
+}
+
+impl FailureGroups {
+    /// Create empty groups of files failing for `reason`, such as
+    /// `"missing"`.
+    pub fn new(reason: &str) -> Self {
+        Self {
+            reason: reason.to_string(),
+            dirs: BTreeMap::new(),
+        }
+    }
+
+    /// Add a failed file to the group of its directory.
+    pub fn add(&mut self, path: &Path) {
+        let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        self.dirs
+            .entry(dir)
+            .or_default()
+            .push(name.to_string_lossy().to_string());
+    }
+
+    /// Whether no file has been added.
+    pub fn is_empty(&self) -> bool {
+        self.dirs.is_empty()
+    }
+
+    /// One line per directory, in directory order, naming the number of
+    /// failed files and the first of them.
+    pub fn lines(&self) -> Vec<String> {
+        self.dirs
+            .iter()
+            .map(|(dir, names)| {
+                let dir = format!("{}{}", dir.display(), MAIN_SEPARATOR);
+                match names.len() {
+                    1 => format!("{}: 1 file {} ({})", dir, self.reason, names[0]),
+                    n => format!("{}: {} files {} (first: {})", dir, n, self.reason, names[0]),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_with_counters_formats_prefix() {
+        let logger = MemoryLogger::new();
+
+        logger.log_with_counters(
+            "Copy track \"{}\"",
+            &["a.flac"],
+            Some(1),
+            Some(3),
+            Some("media"),
+        );
+        logger.log_with_counters(
+            "Copy lyrics \"{}\"",
+            &["a.lrc"],
+            Some(1),
+            Some(3),
+            Some("lyrics"),
+        );
+        logger.log_with_counters(
+            "Copy sidecar \"{}\"",
+            &["a.cue"],
+            Some(1),
+            Some(3),
+            Some("sidecar"),
+        );
+        logger.log_with_counters("Copy playlist \"{}\"", &["p.m3u8"], Some(2), Some(2), None);
+        logger.log_with_counters("No counter \"{}\"", &["x"], None, Some(2), None);
+        logger.log_formatted("{} and {}", &["one", "two"]);
+
+        assert_eq!(
+            logger.take_messages(),
+            vec![
+                "(1-M/3) Copy track \"a.flac\"",
+                "(1-L/3) Copy lyrics \"a.lrc\"",
+                "(1-S/3) Copy sidecar \"a.cue\"",
+                "(2/2) Copy playlist \"p.m3u8\"",
+                "No counter \"x\"",
+                "one and two",
+            ]
+        );
+        assert!(logger.take_messages().is_empty());
+    }
+
+    #[test]
+    fn test_disabled_logger_writes_nothing() {
+        struct Disabled(MemoryLogger);
+
+        impl Logger for Disabled {
+            fn enabled(&self) -> bool {
+                false
+            }
+
+            fn write(&self, message: &str) {
+                self.0.write(message);
+            }
+        }
+
+        let logger = Disabled(MemoryLogger::new());
+        logger.log("message");
+        logger.log_formatted("{}", &["message"]);
+        logger.log_with_counters("{}", &["message"], Some(1), Some(1), None);
+        logger.debug_formatted("{}", &["message"]);
+
+        assert!(logger.0.take_messages().is_empty());
+    }
+
+    #[test]
+    fn test_stderr_logger_verbosity() {
+        assert!(!StderrLogger::new(false).enabled());
+        assert!(StderrLogger::new(true).enabled());
+        assert!(!StderrLogger::new(true).debug_enabled());
+        assert!(StderrLogger::with_verbosity(2).debug_enabled());
+    }
+
+    #[test]
+    fn test_failure_groups_lines() {
+        let mut groups = FailureGroups::new("missing");
+        assert!(groups.is_empty());
+
+        let music = Path::new("MUSIC");
+        groups.add(&music.join("artist3/album1/b.flac"));
+        groups.add(&music.join("top.flac"));
+        groups.add(&music.join("artist3/album1/a.flac"));
+
+        assert!(!groups.is_empty());
+        let sep = MAIN_SEPARATOR;
+        assert_eq!(
+            groups.lines(),
+            vec![
+                format!("MUSIC{}: 1 file missing (top.flac)", sep),
+                format!(
+                    "{}: 2 files missing (first: b.flac)",
+                    music.join("artist3/album1").join("").display()
+                ),
+            ]
+        );
+    }
 }
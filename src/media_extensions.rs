@@ -0,0 +1,123 @@
+//! Recognition of media files by their extensions
+//!
+//! Playlists exported carelessly may list cover images, booklets or
+//! `.nfo` files besides the tracks.  Media files are told apart from
+//! them by an allowlist of audio extensions, compared case
+//! insensitively.
+
+use std::path::Path;
+use std::str::FromStr;
+
+/// Extensions of media files recognised by default
+pub const MEDIA_EXTENSIONS: &[&str] = &[
+    "aac", "aif", "aiff", "alac", "ape", "dsf", "flac", "m4a", "mp3", "ogg", "opus", "wav", "wma",
+    "wv",
+];
+
+/// Whether the path has the extension of a media file
+pub fn is_media_file(path: &Path) -> bool {
+    has_extension(path, MEDIA_EXTENSIONS)
+}
+
+/// Parse a single extension, such as the extension of sidecar files
+/// kept next to media files, with or without a leading dot
+pub fn parse_extension(s: &str) -> Result<String, String> {
+    let extension = s.trim().trim_start_matches('.');
+    if extension.is_empty() || extension.contains(['/', '\\']) {
+        return Err(format!("Invalid extension: {}", s));
+    }
+    Ok(extension.to_string())
+}
+
+/// Whether the extension of the path is one of `extensions`
+fn has_extension<S: AsRef<str>>(path: &Path, extensions: &[S]) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| {
+            extensions
+                .iter()
+                .any(|media| ext.eq_ignore_ascii_case(media.as_ref()))
+        })
+}
+
+/// Allowlist of the extensions of media files to be copied
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtensionAllowlist {
+    extensions: Vec<String>,
+}
+
+impl ExtensionAllowlist {
+    /// Whether the file has an allowed extension
+    pub fn allows(&self, file: &str) -> bool {
+        has_extension(Path::new(file), &self.extensions)
+    }
+
+    /// Allowed extensions, in the order given
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+}
+
+impl Default for ExtensionAllowlist {
+    /// Allowlist of the extensions recognised by default
+    fn default() -> Self {
+        Self {
+            extensions: MEDIA_EXTENSIONS.iter().map(|ext| ext.to_string()).collect(),
+        }
+    }
+}
+
+impl FromStr for ExtensionAllowlist {
+    type Err = String;
+
+    /// Parse a comma-separated list of extensions such as `flac,mp3`,
+    /// with or without leading dots
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let extensions: Vec<String> = s
+            .split(',')
+            .map(|ext| ext.trim().trim_start_matches('.').to_ascii_lowercase())
+            .collect();
+
+        if extensions.iter().any(|ext| ext.is_empty()) {
+            return Err(format!("Invalid extension list: {}", s));
+        }
+
+        Ok(Self { extensions })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_media_file() {
+        assert!(is_media_file(Path::new("artist1/title1.flac")));
+        assert!(is_media_file(Path::new("title1.MP3")));
+        assert!(!is_media_file(Path::new("cover.jpg")));
+        assert!(!is_media_file(Path::new("README")));
+    }
+
+    #[test]
+    fn test_parse_extension() {
+        assert_eq!(parse_extension("cue"), Ok("cue".to_string()));
+        assert_eq!(parse_extension(" .pdf"), Ok("pdf".to_string()));
+        assert!(parse_extension("").is_err());
+        assert!(parse_extension(".").is_err());
+        assert!(parse_extension("a/b").is_err());
+    }
+
+    #[test]
+    fn test_extension_allowlist() {
+        let default = ExtensionAllowlist::default();
+        assert!(default.allows("artist1/title1.Flac"));
+        assert!(!default.allows("artist1/booklet.pdf"));
+
+        let allowlist: ExtensionAllowlist = "flac, .MP3".parse().unwrap();
+        assert!(allowlist.allows("title1.mp3"));
+        assert!(!allowlist.allows("title1.wav"));
+
+        assert!("flac,,mp3".parse::<ExtensionAllowlist>().is_err());
+        assert!("".parse::<ExtensionAllowlist>().is_err());
+    }
+}
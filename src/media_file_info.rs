@@ -1,14 +1,156 @@
+//! Information about a media file to be processed
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
 /// Struct to hold information about a media file to be processed
-/// This combines src_basedir and file parameters to reduce function argument count
-#[derive(Clone, Debug)]
+///
+/// This combines the source base directory and the file path relative to
+/// it, together with metadata of the source file gathered while planning
+/// a run, so that later stages need not stat the file again.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MediaFileInfo {
-    pub src_basedir: String,
-    pub file: String,
+    pub src_basedir: PathBuf,
+    pub file: PathBuf,               // Relative to src_basedir
+    pub dest_file: Option<PathBuf>,  // Relative to the destination root, if not file
+    pub size: Option<u64>,           // Size of the source file, if known
+    pub modified: Option<SystemTime>, // Modification time of the source file, if known
+    pub hash: Option<u32>,           // CRC-32 checksum of the source file, if known
 }
 
 impl MediaFileInfo {
-    /// Create a new MediaFileInfo instance
-    pub fn new(src_basedir: String, file: String) -> Self {
-        Self { src_basedir, file }
+    /// Create a new MediaFileInfo instance without metadata
+    pub fn new(src_basedir: impl Into<PathBuf>, file: impl Into<PathBuf>) -> Self {
+        Self {
+            src_basedir: src_basedir.into(),
+            file: file.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Fill in the size and modification time from the source file
+    ///
+    /// Metadata that cannot be read is left unknown; the error surfaces
+    /// when the file is copied.
+    pub fn with_metadata(mut self) -> Self {
+        if let Ok(metadata) = fs::metadata(self.src_path()) {
+            self.size = Some(metadata.len());
+            self.modified = metadata.modified().ok();
+        }
+        self
+    }
+
+    /// Full path of the source file
+    pub fn src_path(&self) -> PathBuf {
+        self.src_basedir.join(&self.file)
+    }
+
+    /// Path of the file relative to the destination root, which is the
+    /// path relative to src_basedir unless the file is renamed
+    pub fn dest_file(&self) -> &Path {
+        self.dest_file.as_deref().unwrap_or(&self.file)
+    }
+
+    /// Full path of the file under the destination root
+    pub fn dest_path(&self, dest_root: &Path) -> PathBuf {
+        dest_root.join(self.dest_file())
+    }
+
+    /// Path of the corresponding lyrics file, relative to src_basedir
+    pub fn lyrics_file(&self) -> Option<PathBuf> {
+        self.sidecar_file("lrc")
+    }
+
+    /// Path of the corresponding lyrics file, relative to the
+    /// destination root
+    pub fn dest_lyrics_file(&self) -> Option<PathBuf> {
+        self.dest_sidecar_file("lrc")
+    }
+
+    /// Path of the sidecar file with the extension `extension`, such as
+    /// a `.cue` sheet, relative to src_basedir
+    pub fn sidecar_file(&self, extension: &str) -> Option<PathBuf> {
+        sidecar_path(&self.file, extension)
+    }
+
+    /// Path of the sidecar file with the extension `extension`, relative
+    /// to the destination root
+    pub fn dest_sidecar_file(&self, extension: &str) -> Option<PathBuf> {
+        sidecar_path(self.dest_file(), extension)
+    }
+}
+
+/// Path of the sidecar file of a media file, sharing its stem
+fn sidecar_path(file: &Path, extension: &str) -> Option<PathBuf> {
+    file.file_stem().map(|stem| {
+        let mut sidecar_filename = stem.to_os_string();
+        sidecar_filename.push(".");
+        sidecar_filename.push(extension);
+        file.with_file_name(sidecar_filename)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_paths() {
+        let media_file = MediaFileInfo::new("/home/user/MUSIC", "artist1/album1/title1.flac");
+
+        assert_eq!(
+            media_file.src_path(),
+            Path::new("/home/user/MUSIC/artist1/album1/title1.flac")
+        );
+        assert_eq!(
+            media_file.dest_path(Path::new("/mnt/sdcard/MUSIC")),
+            Path::new("/mnt/sdcard/MUSIC/artist1/album1/title1.flac")
+        );
+        assert_eq!(
+            media_file.lyrics_file(),
+            Some(PathBuf::from("artist1/album1/title1.lrc"))
+        );
+
+        let renamed = MediaFileInfo {
+            dest_file: Some(PathBuf::from("artist1/album1/title1 (2).flac")),
+            ..media_file
+        };
+        assert_eq!(
+            renamed.dest_path(Path::new("/mnt/sdcard/MUSIC")),
+            Path::new("/mnt/sdcard/MUSIC/artist1/album1/title1 (2).flac")
+        );
+        assert_eq!(
+            renamed.dest_lyrics_file(),
+            Some(PathBuf::from("artist1/album1/title1 (2).lrc"))
+        );
+        assert_eq!(
+            renamed.sidecar_file("cue"),
+            Some(PathBuf::from("artist1/album1/title1.cue"))
+        );
+        assert_eq!(
+            renamed.dest_sidecar_file("cue"),
+            Some(PathBuf::from("artist1/album1/title1 (2).cue"))
+        );
+    }
+
+    #[test]
+    fn test_with_metadata() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        fs::write(temp_dir.path().join("title1.flac"), "test content")?;
+
+        let media_file = MediaFileInfo::new(temp_dir.path(), "title1.flac").with_metadata();
+        assert_eq!(media_file.size, Some(12));
+        assert!(media_file.modified.is_some());
+        assert_eq!(media_file.hash, None);
+
+        let missing = MediaFileInfo::new(temp_dir.path(), "missing.flac").with_metadata();
+        assert_eq!(missing.size, None);
+        assert_eq!(missing.modified, None);
+
+        Ok(())
     }
 }
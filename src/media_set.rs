@@ -0,0 +1,428 @@
+//! Deduplicated set of the media files referenced by playlists
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::media_extensions::is_media_file;
+use crate::path_interner::PathInterner;
+use crate::playlist_scanner;
+use crate::track_matcher::relative_entry;
+use crate::xspf::{is_xspf_file, XspfPlaylist};
+
+/// A media file given by its source root and its path relative to it
+pub type MediaKey = (Arc<str>, Arc<str>);
+
+/// Media files referenced by a single playlist
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaylistMedia {
+    pub src_root: Arc<str>,   // Directory the playlist entries are relative to
+    pub files: Vec<Arc<str>>, // Entries in playlist order
+}
+
+/// Read the media files of a playlist, interning their paths
+///
+/// Entries are relative to the directory containing the playlist.  XSPF
+/// playlists give the locations of their tracks.
+pub fn read_playlist_media(
+    playlist: &Path,
+    interner: &mut PathInterner,
+) -> io::Result<PlaylistMedia> {
+    let src_root = playlist
+        .parent()
+        .map(|p| interner.intern(&p.to_string_lossy()))
+        .unwrap_or_else(|| interner.intern("."));
+
+    if is_xspf_file(playlist) {
+        let xspf = XspfPlaylist::read(playlist)?;
+        let files = xspf.entries().map(|entry| interner.intern(entry)).collect();
+        return Ok(PlaylistMedia { src_root, files });
+    }
+
+    let file = File::open(playlist)?;
+    let files = playlist_scanner::read_playlist(file)
+        .map(|line| interner.intern(&line))
+        .collect();
+
+    Ok(PlaylistMedia { src_root, files })
+}
+
+/// Read the media files of a plain file list, one path per line,
+/// interning their paths
+///
+/// Relative paths are relative to `src_root`, and absolute paths must be
+/// under it.  Blank lines and lines starting with `#` are ignored, as in
+/// playlists.
+pub fn read_list_media(
+    list: &Path,
+    src_root: &Path,
+    interner: &mut PathInterner,
+) -> io::Result<PlaylistMedia> {
+    let file = File::open(list)?;
+    let mut files = Vec::new();
+
+    for line in playlist_scanner::read_playlist(file) {
+        files.push(interner.intern(&make_entry_relative(src_root, &line)?));
+    }
+
+    Ok(PlaylistMedia {
+        src_root: interner.intern(&src_root.to_string_lossy()),
+        files,
+    })
+}
+
+/// Turn an absolute entry into an entry relative to `src_root` with
+/// forward slashes, leaving relative entries as they are
+///
+/// Fails if an absolute entry is not under `src_root`, as it has no
+/// place on the destination.
+pub fn make_entry_relative(src_root: &Path, entry: &str) -> io::Result<String> {
+    if !Path::new(entry).is_absolute() {
+        return Ok(entry.to_string());
+    }
+    relative_entry(Path::new(entry), src_root)?.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is not under {}", entry, src_root.display()),
+        )
+    })
+}
+
+impl PlaylistMedia {
+    /// Replace the absolute entries by entries relative to the source
+    /// root
+    ///
+    /// See [`make_entry_relative`] for the entries that cannot be made
+    /// relative.
+    pub fn make_entries_relative(&mut self, interner: &mut PathInterner) -> io::Result<()> {
+        let src_root = Path::new(&*self.src_root);
+
+        for file in &mut self.files {
+            if Path::new(&**file).is_absolute() {
+                *file = interner.intern(&make_entry_relative(src_root, file)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replace the entries naming directories by the media files found
+    /// in them
+    ///
+    /// See [`expand_directory_entry`] for the order of the media files.
+    pub fn expand_directories(&mut self, interner: &mut PathInterner) -> io::Result<()> {
+        let src_root = Path::new(&*self.src_root);
+        let mut files = Vec::with_capacity(self.files.len());
+
+        for file in &self.files {
+            match expand_directory_entry(src_root, file)? {
+                Some(entries) => files.extend(entries.iter().map(|entry| interner.intern(entry))),
+                None => files.push(Arc::clone(file)),
+            }
+        }
+
+        self.files = files;
+        Ok(())
+    }
+}
+
+/// Expand a playlist entry naming a directory under `src_root` into
+/// entries for the media files found in it recursively
+///
+/// Entries are sorted by name within each directory, so the files of
+/// `disc1` come before those of `disc2`.  Returns `None` if the entry
+/// does not name a directory.
+pub fn expand_directory_entry(src_root: &Path, entry: &str) -> io::Result<Option<Vec<String>>> {
+    let dir = src_root.join(entry);
+    if entry.is_empty() || !dir.is_dir() {
+        return Ok(None);
+    }
+
+    let mut entries = Vec::new();
+    collect_media_files(&dir, entry.trim_end_matches('/'), &mut entries)?;
+    Ok(Some(entries))
+}
+
+/// Append the media files found recursively in `dir` to `entries`,
+/// prefixed with `prefix`
+fn collect_media_files(dir: &Path, prefix: &str, entries: &mut Vec<String>) -> io::Result<()> {
+    let mut children: Vec<_> = fs::read_dir(dir)?.collect::<io::Result<_>>()?;
+    children.sort_by_key(|child| child.file_name());
+
+    for child in children {
+        let path = child.path();
+        let entry = format!("{}/{}", prefix, child.file_name().to_string_lossy());
+        if path.is_dir() {
+            collect_media_files(&path, &entry, entries)?;
+        } else if is_media_file(&path) {
+            entries.push(entry);
+        }
+    }
+
+    Ok(())
+}
+
+/// Unique media files across playlists, with the membership of each
+/// playlist
+///
+/// Media files are kept in the order they are first referenced, so
+/// operations over the set are deterministic.
+#[derive(Debug, Default)]
+pub struct MediaSet {
+    entries: Vec<MediaKey>,
+    index: HashMap<MediaKey, usize>,
+    members: Vec<Vec<usize>>, // Per playlist, indices into entries
+}
+
+impl MediaSet {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the media files of a playlist, returning the playlist's index
+    pub fn add_playlist(&mut self, media: &PlaylistMedia) -> usize {
+        let mut members = Vec::new();
+        let mut seen = HashSet::new();
+
+        for file in &media.files {
+            let key = (Arc::clone(&media.src_root), Arc::clone(file));
+            let next = self.entries.len();
+            let position = *self.index.entry(key.clone()).or_insert(next);
+            if position == next {
+                self.entries.push(key);
+            }
+            if seen.insert(position) {
+                members.push(position);
+            }
+        }
+
+        self.members.push(members);
+        self.members.len() - 1
+    }
+
+    /// Number of unique media files
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no media file has been added
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Whether the media file is referenced by any playlist
+    pub fn contains(&self, src_root: &Arc<str>, file: &Arc<str>) -> bool {
+        self.index
+            .contains_key(&(Arc::clone(src_root), Arc::clone(file)))
+    }
+
+    /// Iterate over the unique media files
+    pub fn iter(&self) -> impl Iterator<Item = &MediaKey> {
+        self.entries.iter()
+    }
+
+    /// Source roots of the media files, in the order first referenced
+    pub fn roots(&self) -> Vec<&Arc<str>> {
+        let mut roots: Vec<&Arc<str>> = Vec::new();
+        for (src_root, _) in &self.entries {
+            if !roots.contains(&src_root) {
+                roots.push(src_root);
+            }
+        }
+        roots
+    }
+
+    /// Number of playlists added
+    pub fn playlist_count(&self) -> usize {
+        self.members.len()
+    }
+
+    /// Iterate over the unique media files of a playlist, in playlist order
+    ///
+    /// # Panics
+    ///
+    /// Panics if `playlist` is not an index returned by [`add_playlist`].
+    ///
+    /// [`add_playlist`]: MediaSet::add_playlist
+    pub fn playlist_members(&self, playlist: usize) -> impl Iterator<Item = &MediaKey> {
+        self.members[playlist]
+            .iter()
+            .map(|&position| &self.entries[position])
+    }
+
+    /// Indices of the playlists referencing the media file
+    pub fn playlists_of(&self, src_root: &Arc<str>, file: &Arc<str>) -> Vec<usize> {
+        match self.index.get(&(Arc::clone(src_root), Arc::clone(file))) {
+            Some(position) => self
+                .members
+                .iter()
+                .enumerate()
+                .filter(|(_, members)| members.contains(position))
+                .map(|(playlist, _)| playlist)
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+/// Compute the unique media set of the given playlists
+///
+/// Fails on the first playlist that cannot be read.  Callers needing to
+/// carry on past unreadable playlists use [`read_playlist_media`] and
+/// [`MediaSet::add_playlist`] directly.
+pub fn unique_media<P: AsRef<Path>>(
+    playlists: &[P],
+    interner: &mut PathInterner,
+) -> io::Result<MediaSet> {
+    let mut media_set = MediaSet::new();
+    for playlist in playlists {
+        media_set.add_playlist(&read_playlist_media(playlist.as_ref(), interner)?);
+    }
+    Ok(media_set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_unique_media_deduplicates_across_playlists() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let playlist1 = temp_dir.path().join("playlist1.m3u8");
+        let playlist2 = temp_dir.path().join("playlist2.m3u8");
+        fs::write(&playlist1, "#EXTM3U\nsong1.flac\nsong2.flac\nsong1.flac\n")?;
+        fs::write(&playlist2, "song2.flac\r\nsub\\song3.flac\r\n")?;
+
+        let media_set = unique_media(&[&playlist1, &playlist2], &mut PathInterner::new())?;
+
+        let files: Vec<&str> = media_set.iter().map(|(_, file)| &**file).collect();
+        assert_eq!(files, vec!["song1.flac", "song2.flac", "sub/song3.flac"]);
+        assert_eq!(media_set.len(), 3);
+        assert_eq!(media_set.playlist_count(), 2);
+        assert_eq!(media_set.roots().len(), 1);
+
+        let members: Vec<&str> = media_set
+            .playlist_members(1)
+            .map(|(_, file)| &**file)
+            .collect();
+        assert_eq!(members, vec!["song2.flac", "sub/song3.flac"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_expand_directories() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album = temp_dir.path().join("artist1/album1");
+        fs::create_dir_all(album.join("disc2"))?;
+        fs::create_dir_all(album.join("disc1"))?;
+        fs::write(album.join("disc2/01.flac"), "")?;
+        fs::write(album.join("disc1/02.flac"), "")?;
+        fs::write(album.join("disc1/01.flac"), "")?;
+        fs::write(album.join("disc1/cover.jpg"), "")?;
+        let playlist = temp_dir.path().join("playlist.m3u8");
+        fs::write(&playlist, "song1.flac\nartist1\\album1\\\nsong2.flac\n")?;
+
+        let mut interner = PathInterner::new();
+        let mut media = read_playlist_media(&playlist, &mut interner)?;
+        media.expand_directories(&mut interner)?;
+
+        let files: Vec<&str> = media.files.iter().map(|file| &**file).collect();
+        assert_eq!(
+            files,
+            vec![
+                "song1.flac",
+                "artist1/album1/disc1/01.flac",
+                "artist1/album1/disc1/02.flac",
+                "artist1/album1/disc2/01.flac",
+                "song2.flac",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_make_entries_relative() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let playlist = temp_dir.path().join("playlist.m3u8");
+        let absolute = temp_dir.path().join("artist2/./album1/title1.flac");
+        fs::write(
+            &playlist,
+            format!("artist1\\title1.flac\n{}\n", absolute.display()),
+        )?;
+
+        let mut interner = PathInterner::new();
+        let mut media = read_playlist_media(&playlist, &mut interner)?;
+        media.make_entries_relative(&mut interner)?;
+        let files: Vec<&str> = media.files.iter().map(|file| &**file).collect();
+        assert_eq!(
+            files,
+            vec!["artist1/title1.flac", "artist2/album1/title1.flac"]
+        );
+
+        fs::write(&playlist, "/elsewhere/title1.flac\n")?;
+        let mut media = read_playlist_media(&playlist, &mut interner)?;
+        assert!(media.make_entries_relative(&mut interner).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_list_media() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path().join("music");
+        let list = temp_dir.path().join("list.txt");
+        let absolute = root.join("artist2/title1.flac");
+        fs::write(
+            &list,
+            format!("# Tracks\nartist1\\title1.flac\n\n{}\n", absolute.display()),
+        )?;
+
+        let media = read_list_media(&list, &root, &mut PathInterner::new())?;
+        assert_eq!(&*media.src_root, root.to_string_lossy());
+        let files: Vec<&str> = media.files.iter().map(|file| &**file).collect();
+        assert_eq!(files, vec!["artist1/title1.flac", "artist2/title1.flac"]);
+
+        fs::write(&list, "/elsewhere/title1.flac\n")?;
+        assert!(read_list_media(&list, &root, &mut PathInterner::new()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_playlists_of() {
+        let mut interner = PathInterner::new();
+        let root = interner.intern("/music");
+        let song1 = interner.intern("song1.flac");
+        let song2 = interner.intern("song2.flac");
+
+        let mut media_set = MediaSet::new();
+        media_set.add_playlist(&PlaylistMedia {
+            src_root: Arc::clone(&root),
+            files: vec![Arc::clone(&song1), Arc::clone(&song2)],
+        });
+        media_set.add_playlist(&PlaylistMedia {
+            src_root: Arc::clone(&root),
+            files: vec![Arc::clone(&song2)],
+        });
+
+        assert!(media_set.contains(&root, &song1));
+        assert_eq!(media_set.playlists_of(&root, &song1), vec![0]);
+        assert_eq!(media_set.playlists_of(&root, &song2), vec![0, 1]);
+        assert!(media_set
+            .playlists_of(&root, &interner.intern("song3.flac"))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_unique_media_missing_playlist() {
+        let result = unique_media(&["/nonexistent/playlist.m3u8"], &mut PathInterner::new());
+        assert!(result.is_err());
+    }
+}
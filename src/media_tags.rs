@@ -0,0 +1,332 @@
+//! Reading of the tags of media files
+//!
+//! Only the tags needed to organise a library are read: the artists,
+//! album, title, track and disc numbers, and genre.  They are read from
+//! the Vorbis comments of FLAC files and from ID3v2.3 and ID3v2.4 tags,
+//! which cover most ripped libraries; other files have no tags.
+
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Block type of the Vorbis comments in a FLAC file
+const FLAC_VORBIS_COMMENT: u8 = 4;
+
+/// Tags of a media file
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MediaTags {
+    pub artist: Option<String>,
+    pub album_artist: Option<String>,
+    pub album: Option<String>,
+    pub title: Option<String>,
+    pub track_number: Option<u32>,
+    pub disc_number: Option<u32>,
+    pub genre: Option<String>,
+}
+
+impl MediaTags {
+    /// Album artist, or the artist if the album artist is not tagged
+    pub fn album_artist_or_artist(&self) -> Option<&str> {
+        self.album_artist.as_deref().or(self.artist.as_deref())
+    }
+
+    /// Set the tag named `key` as in Vorbis comments, ignoring unknown
+    /// keys and empty values
+    fn set(&mut self, key: &str, value: &str) {
+        let value = value.trim_end_matches('\0').trim();
+        if value.is_empty() {
+            return;
+        }
+
+        let tag = match key.to_ascii_uppercase().as_str() {
+            "ARTIST" => &mut self.artist,
+            "ALBUMARTIST" | "ALBUM ARTIST" => &mut self.album_artist,
+            "ALBUM" => &mut self.album,
+            "TITLE" => &mut self.title,
+            "GENRE" => &mut self.genre,
+            "TRACKNUMBER" => {
+                self.track_number = self.track_number.or_else(|| number(value));
+                return;
+            }
+            "DISCNUMBER" => {
+                self.disc_number = self.disc_number.or_else(|| number(value));
+                return;
+            }
+            _ => return,
+        };
+        tag.get_or_insert_with(|| value.to_string());
+    }
+}
+
+/// Number at the start of a tag such as `3/12`
+fn number(value: &str) -> Option<u32> {
+    value.split('/').next()?.trim().parse().ok()
+}
+
+/// Read the tags of a media file
+///
+/// Files in a format without supported tags have no tags.  Errors are
+/// returned for files that cannot be read or whose tags are truncated.
+pub fn read_tags(path: &Path) -> io::Result<MediaTags> {
+    let mut file = File::open(path)?;
+    let mut magic = [0; 4];
+    if file.read(&mut magic)? < magic.len() {
+        return Ok(MediaTags::default());
+    }
+
+    match &magic {
+        b"fLaC" => read_flac_tags(&mut file),
+        [b'I', b'D', b'3', _] => read_id3v2_tags(&mut file, magic[3]),
+        _ => Ok(MediaTags::default()),
+    }
+}
+
+/// Read the Vorbis comments of a FLAC file positioned after its marker
+fn read_flac_tags<R: Read + Seek>(reader: &mut R) -> io::Result<MediaTags> {
+    let mut tags = MediaTags::default();
+
+    loop {
+        let mut header = [0; 4];
+        reader.read_exact(&mut header)?;
+        let length = u32::from_be_bytes([0, header[1], header[2], header[3]]);
+
+        if header[0] & 0x7f == FLAC_VORBIS_COMMENT {
+            let mut block = vec![0; length as usize];
+            reader.read_exact(&mut block)?;
+            read_vorbis_comments(&block, &mut tags)?;
+            return Ok(tags);
+        }
+        if header[0] & 0x80 != 0 {
+            return Ok(tags);
+        }
+        reader.seek(SeekFrom::Current(i64::from(length)))?;
+    }
+}
+
+/// Read the `KEY=value` comments of a Vorbis comment block
+fn read_vorbis_comments(mut block: &[u8], tags: &mut MediaTags) -> io::Result<()> {
+    let vendor_length = take_length(&mut block)?;
+    take(&mut block, vendor_length)?;
+
+    for _ in 0..take_length(&mut block)? {
+        let comment_length = take_length(&mut block)?;
+        let comment = String::from_utf8_lossy(take(&mut block, comment_length)?);
+        if let Some((key, value)) = comment.split_once('=') {
+            tags.set(key, value);
+        }
+    }
+
+    Ok(())
+}
+
+/// Take `length` bytes from the start of `block`
+fn take<'a>(block: &mut &'a [u8], length: usize) -> io::Result<&'a [u8]> {
+    if block.len() < length {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Truncated Vorbis comments",
+        ));
+    }
+    let (taken, rest) = block.split_at(length);
+    *block = rest;
+    Ok(taken)
+}
+
+/// Take a little-endian 32-bit length from the start of `block`
+fn take_length(block: &mut &[u8]) -> io::Result<usize> {
+    let bytes = take(block, 4)?;
+    Ok(u32::from_le_bytes(bytes.try_into().expect("4 bytes")) as usize)
+}
+
+/// Read the text frames of an ID3v2 tag positioned after `ID3` and its
+/// major version
+fn read_id3v2_tags<R: Read>(reader: &mut R, version: u8) -> io::Result<MediaTags> {
+    let mut tags = MediaTags::default();
+    if version != 3 && version != 4 {
+        return Ok(tags);
+    }
+
+    let mut header = [0; 6];
+    reader.read_exact(&mut header)?;
+    let flags = header[1];
+    let mut body = vec![0; synchsafe(&header[2..6]) as usize];
+    reader.read_exact(&mut body)?;
+
+    let mut frames = body.as_slice();
+    if flags & 0x40 != 0 && frames.len() >= 4 {
+        // Skip the extended header, whose size excludes itself in v2.3
+        let size = match version {
+            3 => u32::from_be_bytes(frames[..4].try_into().expect("4 bytes")) as usize + 4,
+            _ => synchsafe(&frames[..4]) as usize,
+        };
+        frames = frames.get(size..).unwrap_or_default();
+    }
+
+    while frames.len() >= 10 && frames[0] != 0 {
+        let id = &frames[..4];
+        let size = match version {
+            3 => u32::from_be_bytes(frames[4..8].try_into().expect("4 bytes")),
+            _ => synchsafe(&frames[4..8]),
+        } as usize;
+        let Some(content) = frames.get(10..10 + size) else {
+            break;
+        };
+        frames = &frames[10 + size..];
+
+        let key = match id {
+            b"TPE1" => "ARTIST",
+            b"TPE2" => "ALBUMARTIST",
+            b"TALB" => "ALBUM",
+            b"TIT2" => "TITLE",
+            b"TRCK" => "TRACKNUMBER",
+            b"TPOS" => "DISCNUMBER",
+            b"TCON" => "GENRE",
+            _ => continue,
+        };
+        let text = decode_id3v2_text(content);
+        // Only the first of multiple values separated by NUL is used
+        tags.set(key, text.split('\0').next().unwrap_or_default());
+    }
+
+    Ok(tags)
+}
+
+/// Integer stored in 7-bit bytes, as the sizes of ID3v2 tags are
+fn synchsafe(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |size, byte| (size << 7) | u32::from(byte & 0x7f))
+}
+
+/// Decode the content of an ID3v2 text frame, starting with its
+/// encoding
+fn decode_id3v2_text(content: &[u8]) -> String {
+    let Some((&encoding, text)) = content.split_first() else {
+        return String::new();
+    };
+
+    let utf16 = |text: &[u8], big_endian: bool| {
+        let units: Vec<u16> = text
+            .chunks_exact(2)
+            .map(|pair| match big_endian {
+                true => u16::from_be_bytes([pair[0], pair[1]]),
+                false => u16::from_le_bytes([pair[0], pair[1]]),
+            })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+
+    match encoding {
+        0 => text.iter().map(|&byte| char::from(byte)).collect(),
+        1 => match text {
+            [0xfe, 0xff, rest @ ..] => utf16(rest, true),
+            [0xff, 0xfe, rest @ ..] => utf16(rest, false),
+            _ => utf16(text, false),
+        },
+        2 => utf16(text, true),
+        _ => String::from_utf8_lossy(text).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// FLAC file with the given Vorbis comments after a padding block
+    fn flac_file(comments: &[&str]) -> Vec<u8> {
+        let mut block = Vec::new();
+        block.extend_from_slice(&6u32.to_le_bytes());
+        block.extend_from_slice(b"vendor");
+        block.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            block.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            block.extend_from_slice(comment.as_bytes());
+        }
+
+        let mut file = b"fLaC".to_vec();
+        file.extend_from_slice(&[1, 0, 0, 2, 0, 0]);
+        file.push(0x80 | FLAC_VORBIS_COMMENT);
+        file.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+        file.extend_from_slice(&block);
+        file
+    }
+
+    /// ID3v2.3 tag with the given text frames
+    fn id3v2_file(frames: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (id, content) in frames {
+            body.extend_from_slice(*id);
+            body.extend_from_slice(&(content.len() as u32).to_be_bytes());
+            body.extend_from_slice(&[0, 0]);
+            body.extend_from_slice(content);
+        }
+
+        let mut file = b"ID3\x03\x00\x00".to_vec();
+        let size = body.len() as u32;
+        file.extend([21, 14, 7, 0].map(|shift| ((size >> shift) & 0x7f) as u8));
+        file.extend_from_slice(&body);
+        file.extend_from_slice(b"audio");
+        file
+    }
+
+    #[test]
+    fn test_read_flac_tags() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("track.flac");
+        fs::write(
+            &path,
+            flac_file(&[
+                "ARTIST=Björk",
+                "album=Début",
+                "TITLE=Human Behaviour",
+                "TRACKNUMBER=1/11",
+                "DISCNUMBER=1",
+                "GENRE=Pop",
+                "COMMENT=ignored",
+            ]),
+        )?;
+
+        let tags = read_tags(&path)?;
+        assert_eq!(tags.artist.as_deref(), Some("Björk"));
+        assert_eq!(tags.album_artist_or_artist(), Some("Björk"));
+        assert_eq!(tags.album.as_deref(), Some("Début"));
+        assert_eq!(tags.title.as_deref(), Some("Human Behaviour"));
+        assert_eq!(tags.track_number, Some(1));
+        assert_eq!(tags.disc_number, Some(1));
+        assert_eq!(tags.genre.as_deref(), Some("Pop"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_id3v2_tags() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let path = temp_dir.path().join("track.mp3");
+        fs::write(
+            &path,
+            id3v2_file(&[
+                (b"TPE1", b"\x00Artist"),
+                (b"TPE2", b"\x03Various Artists\x00"),
+                (b"TALB", b"\x01\xff\xfeA\x00l\x00b\x00"),
+                (b"TIT2", b"\x02\x00T\x00i"),
+                (b"TRCK", b"\x0003/12"),
+                (b"APIC", b"\x00image"),
+            ]),
+        )?;
+
+        let tags = read_tags(&path)?;
+        assert_eq!(tags.artist.as_deref(), Some("Artist"));
+        assert_eq!(tags.album_artist_or_artist(), Some("Various Artists"));
+        assert_eq!(tags.album.as_deref(), Some("Alb"));
+        assert_eq!(tags.title.as_deref(), Some("Ti"));
+        assert_eq!(tags.track_number, Some(3));
+        assert_eq!(tags.disc_number, None);
+
+        fs::write(&path, "not a media file")?;
+        assert_eq!(read_tags(&path)?, MediaTags::default());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+//! Transfer of files to and from S3-compatible object storage
+//!
+//! Objects are named by `s3://BUCKET/KEY` URLs and transferred with
+//! `curl`, which signs the requests with AWS Signature Version 4 and
+//! speaks HTTPS.  The credentials, the region and the endpoint are taken
+//! from the environment variables the AWS command line interface reads,
+//! and are passed to `curl` through its standard input rather than its
+//! command line, where other users could see them.
+
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Scheme of the URLs of objects
+const S3_SCHEME: &str = "s3://";
+
+/// Region used when none is set
+const DEFAULT_REGION: &str = "us-east-1";
+
+/// Command transferring objects
+const CURL_COMMAND: &str = "curl";
+
+/// Whether `url` names an object in object storage
+pub fn is_s3_url(url: &str) -> bool {
+    url.starts_with(S3_SCHEME)
+}
+
+/// Location of an object, parsed from an `s3://BUCKET/KEY` URL
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct S3Location {
+    pub bucket: String,
+    pub key: String,
+}
+
+impl S3Location {
+    /// Parse an `s3://BUCKET/KEY` URL, failing without a bucket or a key
+    pub fn parse(url: &str) -> io::Result<Self> {
+        let rest = url
+            .strip_prefix(S3_SCHEME)
+            .ok_or_else(|| invalid_input(format!("Not an S3 URL: {}", url)))?;
+        match rest.split_once('/') {
+            Some((bucket, key)) if !bucket.is_empty() && !key.is_empty() => Ok(Self {
+                bucket: bucket.to_string(),
+                key: key.to_string(),
+            }),
+            _ => Err(invalid_input(format!(
+                "S3 URL without a bucket or a key: {}",
+                url
+            ))),
+        }
+    }
+
+    /// HTTP URL of the object at the endpoint of `config`, in path style
+    /// so that bucket names need not be valid host names
+    pub fn http_url(&self, config: &S3Config) -> String {
+        format!(
+            "{}/{}/{}",
+            config.endpoint.trim_end_matches('/'),
+            encode_key(&self.bucket),
+            encode_key(&self.key)
+        )
+    }
+}
+
+/// Settings of the object storage, read from the environment
+#[derive(Clone, Debug)]
+pub struct S3Config {
+    pub endpoint: String, // Such as https://s3.us-east-1.amazonaws.com
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+impl S3Config {
+    /// Read the settings from `AWS_ACCESS_KEY_ID`,
+    /// `AWS_SECRET_ACCESS_KEY`, `AWS_SESSION_TOKEN`, `AWS_REGION` or
+    /// `AWS_DEFAULT_REGION`, and `AWS_ENDPOINT_URL_S3` or
+    /// `AWS_ENDPOINT_URL` for storage other than Amazon S3
+    pub fn from_env() -> io::Result<Self> {
+        let var = |name: &str| env::var(name).ok().filter(|value| !value.is_empty());
+        let required =
+            |name: &str| var(name).ok_or_else(|| invalid_input(format!("{} is not set", name)));
+
+        let region = var("AWS_REGION")
+            .or_else(|| var("AWS_DEFAULT_REGION"))
+            .unwrap_or_else(|| DEFAULT_REGION.to_string());
+        let endpoint = var("AWS_ENDPOINT_URL_S3")
+            .or_else(|| var("AWS_ENDPOINT_URL"))
+            .unwrap_or_else(|| format!("https://s3.{}.amazonaws.com", region));
+        Ok(Self {
+            endpoint,
+            region,
+            access_key_id: required("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: required("AWS_SECRET_ACCESS_KEY")?,
+            session_token: var("AWS_SESSION_TOKEN"),
+        })
+    }
+
+    /// Configuration of `curl` with the credentials, read from its
+    /// standard input
+    fn curl_config(&self) -> String {
+        let mut config = format!(
+            "user = {}\n",
+            quote(&format!(
+                "{}:{}",
+                self.access_key_id, self.secret_access_key
+            ))
+        );
+        // The payload is not hashed, so that it is read only once
+        config.push_str("header = \"x-amz-content-sha256: UNSIGNED-PAYLOAD\"\n");
+        if let Some(token) = &self.session_token {
+            config.push_str(&format!(
+                "header = {}\n",
+                quote(&format!("x-amz-security-token: {}", token))
+            ));
+        }
+        config
+    }
+}
+
+/// Upload the file at `path` as the object at `location`
+pub fn upload(path: &Path, location: &S3Location, config: &S3Config) -> io::Result<()> {
+    let url = location.http_url(config);
+    let args = [
+        OsStr::new("--upload-file"),
+        path.as_os_str(),
+        OsStr::new(&url),
+    ];
+    run_curl(config, &args)
+}
+
+/// Download the object at `location` into the file at `path`, removing
+/// the file if the download fails
+pub fn download(location: &S3Location, path: &Path, config: &S3Config) -> io::Result<()> {
+    let url = location.http_url(config);
+    let args = [OsStr::new("--output"), path.as_os_str(), OsStr::new(&url)];
+    run_curl(config, &args).inspect_err(|_| {
+        let _ = fs::remove_file(path);
+    })
+}
+
+/// Run `curl` with `args`, failing with its error message on HTTP errors
+fn run_curl(config: &S3Config, args: &[&OsStr]) -> io::Result<()> {
+    let mut child = Command::new(CURL_COMMAND)
+        .args(["--config", "-", "--silent", "--show-error", "--fail"])
+        .arg("--aws-sigv4")
+        .arg(format!("aws:amz:{}:s3", config.region))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::new(e.kind(), format!("Failed to run {}: {}", CURL_COMMAND, e)))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(config.curl_config().as_bytes())?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(io::Error::other(message.trim().to_string()));
+    }
+    Ok(())
+}
+
+/// Percent-encode a bucket name or a key as in the canonical requests of
+/// AWS Signature Version 4, keeping `/` between the segments of a key
+fn encode_key(key: &str) -> String {
+    let mut encoded = String::with_capacity(key.len());
+    for &b in key.as_bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(b as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+/// Quote a value of the configuration of `curl`
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> S3Config {
+        S3Config {
+            endpoint: "http://127.0.0.1:9000/".to_string(),
+            region: DEFAULT_REGION.to_string(),
+            access_key_id: "AK".to_string(),
+            secret_access_key: "S\"K".to_string(),
+            session_token: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_s3_location() {
+        assert!(is_s3_url("s3://bucket/backup.tar"));
+        assert!(!is_s3_url("backup.tar"));
+        assert_eq!(
+            S3Location::parse("s3://bucket/backups/backup.tar").unwrap(),
+            S3Location {
+                bucket: "bucket".to_string(),
+                key: "backups/backup.tar".to_string(),
+            }
+        );
+        assert!(S3Location::parse("s3://bucket").is_err());
+        assert!(S3Location::parse("s3://bucket/").is_err());
+        assert!(S3Location::parse("s3:///backup.tar").is_err());
+    }
+
+    #[test]
+    fn test_http_url() {
+        let location = S3Location::parse("s3://bucket/My Backups/bäckup+1.tar").unwrap();
+        assert_eq!(
+            location.http_url(&config()),
+            "http://127.0.0.1:9000/bucket/My%20Backups/b%C3%A4ckup%2B1.tar"
+        );
+    }
+
+    #[test]
+    fn test_curl_config() {
+        let mut config = config();
+        config.session_token = Some("TOKEN".to_string());
+        assert_eq!(
+            config.curl_config(),
+            "user = \"AK:S\\\"K\"\n\
+             header = \"x-amz-content-sha256: UNSIGNED-PAYLOAD\"\n\
+             header = \"x-amz-security-token: TOKEN\"\n"
+        );
+    }
+}
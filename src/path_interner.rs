@@ -0,0 +1,70 @@
+//! Interning of path strings shared across playlists
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+/// Pool of shared path strings
+///
+/// The same media file is often referenced by many playlists.  Interning
+/// stores each distinct path once and hands out cheap `Arc<str>` clones,
+/// keeping the bookkeeping of very large playlists lean.
+#[derive(Debug, Default)]
+pub struct PathInterner {
+    paths: HashSet<Arc<str>>,
+}
+
+impl PathInterner {
+    /// Create an empty interner
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the shared instance of `path`, storing it on first use
+    pub fn intern(&mut self, path: &str) -> Arc<str> {
+        if let Some(interned) = self.paths.get(path) {
+            return Arc::clone(interned);
+        }
+
+        let interned: Arc<str> = Arc::from(path);
+        self.paths.insert(Arc::clone(&interned));
+        interned
+    }
+
+    /// Number of distinct paths stored
+    pub fn len(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Whether no path has been stored yet
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intern_shares_identical_paths() {
+        let mut interner = PathInterner::new();
+
+        let first = interner.intern("artist1/album1/title1.flac");
+        let second = interner.intern("artist1/album1/title1.flac");
+
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(interner.len(), 1);
+    }
+
+    #[test]
+    fn test_intern_keeps_distinct_paths() {
+        let mut interner = PathInterner::new();
+
+        let first = interner.intern("artist1/album1/title1.flac");
+        let second = interner.intern("artist1/album1/title2.flac");
+
+        assert!(!Arc::ptr_eq(&first, &second));
+        assert_eq!(&*second, "artist1/album1/title2.flac");
+        assert_eq!(interner.len(), 2);
+    }
+}
@@ -0,0 +1,154 @@
+//! Character encodings of playlists written to a device
+//!
+//! Source playlists are read as UTF-8, but some legacy devices only
+//! parse playlists in UTF-16LE with a byte order mark or in CP932
+//! (Shift_JIS as extended by Microsoft), so the encoding of the written
+//! playlist is selected independently of the source.
+
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use encoding_rs::{SHIFT_JIS, UTF_16LE};
+
+/// Byte order mark written at the start of UTF-16LE playlists
+const UTF16LE_BOM: [u8; 2] = [0xff, 0xfe];
+
+/// Encoding of a playlist written to a device
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlaylistEncoding {
+    /// UTF-8, the encoding of the source playlists
+    #[default]
+    Utf8,
+    /// UTF-16 little endian with a byte order mark
+    Utf16Le,
+    /// Microsoft code page 932 (Shift_JIS)
+    Cp932,
+}
+
+impl PlaylistEncoding {
+    /// Encode the contents of a playlist
+    ///
+    /// A byte order mark at the start of `content` is dropped unless the
+    /// encoding is UTF-8, and UTF-16LE output starts with its own byte
+    /// order mark.  Returns an error of kind `InvalidData` if a
+    /// character cannot be represented in CP932, rather than writing an
+    /// entry that no longer names the file.
+    pub fn encode(self, content: &str) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Utf8 => Ok(content.as_bytes().to_vec()),
+            Self::Utf16Le => {
+                let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+                let mut bytes = UTF16LE_BOM.to_vec();
+                bytes.extend(content.encode_utf16().flat_map(u16::to_le_bytes));
+                Ok(bytes)
+            }
+            Self::Cp932 => {
+                let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+                let (bytes, _, unmappable) = SHIFT_JIS.encode(content);
+                if unmappable {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Playlist contains characters that cannot be encoded in cp932",
+                    ));
+                }
+                Ok(bytes.into_owned())
+            }
+        }
+    }
+
+    /// Decode the contents of a playlist written in this encoding
+    ///
+    /// Malformed sequences are replaced by U+FFFD, and a leading byte
+    /// order mark is removed.
+    pub fn decode(self, bytes: &[u8]) -> String {
+        let content = match self {
+            Self::Utf8 => String::from_utf8_lossy(bytes),
+            Self::Utf16Le => UTF_16LE.decode_with_bom_removal(bytes).0,
+            Self::Cp932 => SHIFT_JIS.decode_without_bom_handling(bytes).0,
+        };
+        content.trim_start_matches('\u{feff}').to_string()
+    }
+}
+
+impl FromStr for PlaylistEncoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utf8" | "utf-8" => Ok(Self::Utf8),
+            "utf16le" | "utf-16le" => Ok(Self::Utf16Le),
+            "cp932" | "shift_jis" | "sjis" => Ok(Self::Cp932),
+            _ => Err(format!(
+                "Unknown encoding: {} (expected utf8, utf16le or cp932)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PlaylistEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Utf8 => "utf8",
+            Self::Utf16Le => "utf16le",
+            Self::Cp932 => "cp932",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() -> io::Result<()> {
+        let content = "\u{feff}#EXTM3U\nアーティスト/title.flac";
+
+        assert_eq!(
+            PlaylistEncoding::Utf8.encode(content)?,
+            content.as_bytes().to_vec()
+        );
+
+        let utf16 = PlaylistEncoding::Utf16Le.encode("a/b.flac\n")?;
+        assert_eq!(&utf16[..4], &[0xff, 0xfe, b'a', 0]);
+        assert_eq!(utf16.len(), 2 + 2 * 9);
+
+        let cp932 = PlaylistEncoding::Cp932.encode(content)?;
+        assert_eq!(&cp932[..8], b"#EXTM3U\n");
+        assert_eq!(&cp932[8..10], &[0x83, 0x41]);
+
+        let err = PlaylistEncoding::Cp932.encode("café/ü.flac").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode() -> io::Result<()> {
+        let content = "#EXTM3U\nアーティスト/title.flac";
+
+        for encoding in [
+            PlaylistEncoding::Utf8,
+            PlaylistEncoding::Utf16Le,
+            PlaylistEncoding::Cp932,
+        ] {
+            assert_eq!(encoding.decode(&encoding.encode(content)?), content);
+        }
+        assert_eq!(
+            PlaylistEncoding::Utf8.decode("\u{feff}a.flac".as_bytes()),
+            "a.flac"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!("utf8".parse(), Ok(PlaylistEncoding::Utf8));
+        assert_eq!("UTF-16LE".parse(), Ok(PlaylistEncoding::Utf16Le));
+        assert_eq!("cp932".parse(), Ok(PlaylistEncoding::Cp932));
+        assert!("latin1".parse::<PlaylistEncoding>().is_err());
+        assert_eq!(PlaylistEncoding::Utf16Le.to_string(), "utf16le");
+    }
+}
@@ -0,0 +1,424 @@
+//! Editing of playlist files line by line
+//!
+//! A [`PlaylistFile`] keeps every line of a playlist as it was read, so
+//! that entries can be removed or inserted while comments, `#EXTINF`
+//! lines, a byte order mark and the line endings are written back
+//! unchanged.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::playlist_scanner::parse_extinf;
+use crate::track_path::TrackPath;
+
+/// Byte order mark some tools put at the start of UTF-8 playlists
+const BOM: char = '\u{feff}';
+
+/// Header line of extended M3U playlists
+const EXTM3U_HEADER: &str = "#EXTM3U";
+
+/// A playlist file held in memory for editing
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaylistFile {
+    lines: Vec<String>,        // Lines without line endings
+    bom: bool,                 // Whether the file starts with a BOM
+    line_ending: &'static str, // "\r\n" if the file uses CRLF, "\n" otherwise
+    final_newline: bool,       // Whether the last line is terminated
+}
+
+impl PlaylistFile {
+    /// Read a playlist file
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    /// Parse the contents of a playlist file
+    pub fn parse(content: &str) -> Self {
+        let (bom, content) = match content.strip_prefix(BOM) {
+            Some(stripped) => (true, stripped),
+            None => (false, content),
+        };
+        let line_ending = if content.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let final_newline = content.ends_with('\n');
+
+        let mut lines: Vec<String> = content
+            .split('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line).to_string())
+            .collect();
+        if final_newline || content.is_empty() {
+            lines.pop();
+        }
+
+        Self {
+            lines,
+            bom,
+            line_ending,
+            final_newline,
+        }
+    }
+
+    /// Write the playlist file
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.contents())
+    }
+
+    /// Contents of the playlist file, with its original BOM and line
+    /// endings
+    pub fn contents(&self) -> String {
+        let mut content = String::new();
+        if self.bom {
+            content.push(BOM);
+        }
+        content.push_str(&self.lines.join(self.line_ending));
+        if self.final_newline && !self.lines.is_empty() {
+            content.push_str(self.line_ending);
+        }
+        content
+    }
+
+    /// Iterate over the media file entries, with backslashes replaced by
+    /// forward slashes
+    pub fn entries(&self) -> impl Iterator<Item = String> + '_ {
+        self.lines.iter().filter_map(|line| normalize_entry(line))
+    }
+
+    /// Number of media file entries
+    pub fn entry_count(&self) -> usize {
+        self.entries().count()
+    }
+
+    /// Index of the first entry for which `matches` returns `true`
+    pub fn find_entry<F>(&self, mut matches: F) -> Option<usize>
+    where
+        F: FnMut(&str) -> bool,
+    {
+        self.entries().position(|entry| matches(&entry))
+    }
+
+    /// Whether the playlist is an extended M3U playlist, whose entries
+    /// are described by `#EXTINF` lines
+    pub fn is_extended(&self) -> bool {
+        self.lines
+            .first()
+            .is_some_and(|line| line.trim_end() == EXTM3U_HEADER)
+    }
+
+    /// Whether the entries are written with backslashes as separators
+    pub fn uses_backslashes(&self) -> bool {
+        self.lines
+            .iter()
+            .filter(|line| normalize_entry(line).is_some())
+            .find(|line| line.contains(['/', '\\']))
+            .is_some_and(|line| line.contains('\\'))
+    }
+
+    /// Insert an entry so that it becomes the entry at `index`, after
+    /// its `#EXTINF` line if one is given
+    ///
+    /// The entry is placed before the `#EXTINF` line of the entry
+    /// currently at `index`, so that line stays with its entry.  An
+    /// `index` equal to the number of entries appends the entry.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is greater than the number of entries.
+    pub fn insert_entry(&mut self, index: usize, extinf: Option<&str>, entry: &str) {
+        let position = self.entry_line(index).unwrap_or_else(|| {
+            assert_eq!(index, self.entry_count(), "entry index out of range");
+            self.lines.len()
+        });
+
+        if self.lines.is_empty() {
+            self.final_newline = true;
+        }
+
+        let new_lines = extinf.into_iter().chain([entry]).map(str::to_string);
+        self.lines.splice(position..position, new_lines);
+    }
+
+    /// Line at which the entry at `index` starts, including its
+    /// `#EXTINF` line
+    fn entry_line(&self, index: usize) -> Option<usize> {
+        let line = self
+            .lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| normalize_entry(line).is_some())
+            .nth(index)
+            .map(|(position, _)| position)?;
+
+        match line.checked_sub(1) {
+            Some(prev) if is_extinf(&self.lines[prev]) => Some(prev),
+            _ => Some(line),
+        }
+    }
+
+    /// Rearrange the entries so that the entry at `order[i]` becomes the
+    /// entry at `i`
+    ///
+    /// Each entry moves together with its `#EXTINF` line; comments and
+    /// blank lines between entries stay where they are.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `order` is not a permutation of the entry indices.
+    pub fn reorder_entries(&mut self, order: &[usize]) {
+        let n_entries = self.entry_count();
+        let mut seen = vec![false; n_entries];
+        for &index in order {
+            assert!(
+                index < n_entries && !std::mem::replace(&mut seen[index], true),
+                "entry order is not a permutation"
+            );
+        }
+        assert_eq!(order.len(), n_entries, "entry order is not a permutation");
+
+        let slots: Vec<(usize, usize)> = (0..n_entries)
+            .map(|index| {
+                let start = self.entry_line(index).expect("entry index in range");
+                let end = self.lines[start..]
+                    .iter()
+                    .position(|line| normalize_entry(line).is_some())
+                    .map_or(self.lines.len(), |offset| start + offset + 1);
+                (start, end)
+            })
+            .collect();
+
+        let mut lines = Vec::with_capacity(self.lines.len());
+        let mut position = 0;
+        for (&(start, end), &index) in slots.iter().zip(order) {
+            lines.extend_from_slice(&self.lines[position..start]);
+            let (from, to) = slots[index];
+            lines.extend_from_slice(&self.lines[from..to]);
+            position = end;
+        }
+        lines.extend_from_slice(&self.lines[position..]);
+
+        self.lines = lines;
+    }
+
+    /// Remove every entry for which `matches` returns `true`, together
+    /// with the `#EXTINF` line directly preceding it
+    ///
+    /// Returns the removed entries in playlist order.
+    pub fn remove_entries<F>(&mut self, mut matches: F) -> Vec<String>
+    where
+        F: FnMut(&str) -> bool,
+    {
+        let mut kept: Vec<String> = Vec::with_capacity(self.lines.len());
+        let mut removed = Vec::new();
+
+        for line in self.lines.drain(..) {
+            match normalize_entry(&line) {
+                Some(entry) if matches(&entry) => {
+                    if kept.last().is_some_and(|prev| is_extinf(prev)) {
+                        kept.pop();
+                    }
+                    removed.push(entry);
+                }
+                _ => kept.push(line),
+            }
+        }
+
+        self.lines = kept;
+        removed
+    }
+
+    /// Replace every entry by the entry `rewrite` returns for it, keeping
+    /// the other lines as they are
+    pub fn rewrite_entries<F>(&mut self, mut rewrite: F)
+    where
+        F: FnMut(&str) -> String,
+    {
+        for line in &mut self.lines {
+            if let Some(entry) = normalize_entry(line) {
+                *line = rewrite(&entry);
+            }
+        }
+    }
+
+    /// Replace the entries for which `replace` returns a new entry,
+    /// keeping the other lines, including the other entries, as they
+    /// are written
+    ///
+    /// Returns the number of replaced entries.
+    pub fn replace_entries<F>(&mut self, mut replace: F) -> usize
+    where
+        F: FnMut(&str) -> Option<String>,
+    {
+        let mut n_replaced = 0;
+        for line in &mut self.lines {
+            if let Some(new_entry) = normalize_entry(line).and_then(|entry| replace(&entry)) {
+                *line = new_entry;
+                n_replaced += 1;
+            }
+        }
+        n_replaced
+    }
+}
+
+/// Normalise a playlist line into a media file entry
+///
+/// Returns `None` for comments and blank lines.
+pub fn normalize_entry(line: &str) -> Option<String> {
+    TrackPath::from_line(line).map(String::from)
+}
+
+/// Whether the line describes the following entry
+fn is_extinf(line: &str) -> bool {
+    parse_extinf(line).is_some()
+}
+
+/// Whether the path has a playlist file extension (`.m3u` or `.m3u8`)
+pub fn is_playlist_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("m3u") || ext.eq_ignore_ascii_case("m3u8"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_contents_round_trip() {
+        let contents = [
+            "",
+            "a.flac",
+            "a.flac\n",
+            "#EXTM3U\r\n#EXTINF:10,A\r\na.flac\r\n",
+            "\u{feff}a.flac\nb.flac",
+            "a.flac\n\nb.flac\n",
+        ];
+
+        for content in contents {
+            assert_eq!(PlaylistFile::parse(content).contents(), content);
+        }
+    }
+
+    #[test]
+    fn test_entries() {
+        let playlist = PlaylistFile::parse("\u{feff}#EXTM3U\r\nsub\\a.flac\r\n\r\nb.flac\r\n");
+        let entries: Vec<String> = playlist.entries().collect();
+        assert_eq!(entries, vec!["sub/a.flac", "b.flac"]);
+    }
+
+    #[test]
+    fn test_remove_entries_with_extinf() {
+        let mut playlist = PlaylistFile::parse(
+            "#EXTM3U\r\n#EXTINF:10,A\r\nsub\\a.flac\r\n#EXTINF:20,B\r\nb.flac\r\n# note\r\na.flac\r\n",
+        );
+
+        let removed = playlist.remove_entries(|entry| entry == "sub/a.flac" || entry == "a.flac");
+
+        assert_eq!(removed, vec!["sub/a.flac", "a.flac"]);
+        assert_eq!(
+            playlist.contents(),
+            "#EXTM3U\r\n#EXTINF:20,B\r\nb.flac\r\n# note\r\n"
+        );
+    }
+
+    #[test]
+    fn test_remove_entries_without_match() {
+        let content = "a.flac\nb.flac";
+        let mut playlist = PlaylistFile::parse(content);
+
+        assert!(playlist
+            .remove_entries(|entry| entry == "c.flac")
+            .is_empty());
+        assert_eq!(playlist.contents(), content);
+    }
+
+    #[test]
+    fn test_rewrite_entries() {
+        let mut playlist = PlaylistFile::parse("\u{feff}#EXTM3U\r\n#EXTINF:10,A\r\nsub\\a.flac\r\n");
+
+        playlist.rewrite_entries(|entry| format!("music/{}", entry));
+
+        assert_eq!(
+            playlist.contents(),
+            "\u{feff}#EXTM3U\r\n#EXTINF:10,A\r\nmusic/sub/a.flac\r\n"
+        );
+    }
+
+    #[test]
+    fn test_replace_entries() {
+        let mut playlist =
+            PlaylistFile::parse("#EXTM3U\r\n#EXTINF:10,A\r\nsub\\a.flac\r\nsub\\b.flac\r\n");
+
+        let n_replaced = playlist
+            .replace_entries(|entry| (entry == "sub/a.flac").then(|| "new\\a.flac".to_string()));
+
+        assert_eq!(n_replaced, 1);
+        assert_eq!(
+            playlist.contents(),
+            "#EXTM3U\r\n#EXTINF:10,A\r\nnew\\a.flac\r\nsub\\b.flac\r\n"
+        );
+    }
+
+    #[test]
+    fn test_insert_entry() {
+        let mut playlist = PlaylistFile::parse("#EXTM3U\r\n#EXTINF:10,A\r\na.flac\r\nb.flac\r\n");
+        assert!(playlist.is_extended());
+        assert!(!playlist.uses_backslashes());
+
+        playlist.insert_entry(0, Some("#EXTINF:-1,C"), "c.flac");
+        playlist.insert_entry(2, None, "d.flac");
+        playlist.insert_entry(playlist.entry_count(), None, "e.flac");
+
+        assert_eq!(
+            playlist.contents(),
+            "#EXTM3U\r\n#EXTINF:-1,C\r\nc.flac\r\n#EXTINF:10,A\r\na.flac\r\nd.flac\r\nb.flac\r\ne.flac\r\n"
+        );
+        assert_eq!(playlist.find_entry(|entry| entry == "b.flac"), Some(3));
+    }
+
+    #[test]
+    fn test_insert_entry_into_empty_playlist() {
+        let mut playlist = PlaylistFile::parse("");
+        assert!(!playlist.is_extended());
+
+        playlist.insert_entry(0, None, "a.flac");
+        assert_eq!(playlist.contents(), "a.flac\n");
+    }
+
+    #[test]
+    fn test_reorder_entries() {
+        let mut playlist = PlaylistFile::parse(
+            "#EXTM3U\n#EXTINF:10,A\na.flac\n# note\nb.flac\n#EXTINF:30,C\nc.flac\n",
+        );
+
+        playlist.reorder_entries(&[2, 0, 1]);
+
+        assert_eq!(
+            playlist.contents(),
+            "#EXTM3U\n#EXTINF:30,C\nc.flac\n# note\n#EXTINF:10,A\na.flac\nb.flac\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "entry order is not a permutation")]
+    fn test_reorder_entries_rejects_non_permutation() {
+        let mut playlist = PlaylistFile::parse("a.flac\nb.flac\n");
+        playlist.reorder_entries(&[1, 1]);
+    }
+
+    #[test]
+    fn test_uses_backslashes() {
+        assert!(PlaylistFile::parse("a.flac\nartist\\a.flac").uses_backslashes());
+        assert!(!PlaylistFile::parse("artist/a.flac\nartist\\b.flac").uses_backslashes());
+    }
+
+    #[test]
+    fn test_is_playlist_file() {
+        assert!(is_playlist_file(Path::new("/music/playlist.m3u8")));
+        assert!(is_playlist_file(Path::new("PLAYLIST.M3U")));
+        assert!(!is_playlist_file(Path::new("title1.flac")));
+        assert!(!is_playlist_file(Path::new("m3u8")));
+    }
+}
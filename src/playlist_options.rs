@@ -0,0 +1,227 @@
+//! Options a device profile attaches to some of the playlists
+//!
+//! A `playlist` line of a device profile names the playlists it applies
+//! to by a glob pattern, and sets options for copying them in place of
+//! the options given on the command line:
+//!
+//! ```text
+//! playlist "Audiobooks*.m3u8" lyrics=false max-file-size=2G dest-subdir=AUDIOBOOKS
+//! ```
+//!
+//! A pattern without `/` is matched against the file name of a playlist,
+//! and any other against its path.  The pattern and the values may be
+//! quoted with double quotes, so that they can hold spaces.  A playlist
+//! matched by more than one line takes each option from the last line
+//! setting it.
+//!
+//! The options are:
+//!
+//! - `lyrics=true|false`: copy the lyrics files of the media files, as
+//!   with `--lyrics`, or not
+//! - `max-file-size=SIZE`: leave out the media files larger than SIZE,
+//!   in bytes or with a `K`, `M` or `G` suffix, along with their entries
+//! - `dest-subdir=DIR`: put the playlist and its media files into the
+//!   directory DIR of the destination
+
+use std::path::{Component, Path, PathBuf};
+
+use glob::Pattern;
+
+use crate::diagnostics::parse_size;
+
+/// Options of a playlist set by the `playlist` lines matching it, each
+/// left to the command line if unset
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct PlaylistOptions {
+    pub lyrics: Option<bool>,
+    pub max_file_size: Option<u64>,
+    pub dest_subdir: Option<PathBuf>, // Relative to the destination
+}
+
+/// `playlist` line of a device profile, with the pattern of the
+/// playlists it applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaylistMatch {
+    pattern: Pattern,
+    options: PlaylistOptions,
+}
+
+impl PlaylistMatch {
+    /// Parse the argument of a `playlist` line, the pattern followed by
+    /// the options as `KEY=VALUE` words
+    pub fn parse(argument: &str) -> Result<Self, String> {
+        let mut words = split_words(argument)?.into_iter();
+        let pattern = words.next().unwrap_or_default();
+        let pattern = Pattern::new(&pattern)
+            .map_err(|e| format!("Invalid playlist pattern {}: {}", pattern, e))?;
+        let mut options = PlaylistOptions::default();
+        let mut has_options = false;
+        for word in words {
+            let (key, value) = word
+                .split_once('=')
+                .ok_or_else(|| format!("Playlist option without a value: {}", word))?;
+            match key {
+                "lyrics" => options.lyrics = Some(parse_bool(key, value)?),
+                "max-file-size" => options.max_file_size = Some(parse_size(value)?),
+                "dest-subdir" => options.dest_subdir = Some(parse_subdir(value)?),
+                _ => return Err(format!("Unknown playlist option: {}", key)),
+            }
+            has_options = true;
+        }
+        if !has_options {
+            return Err(format!("No options for playlists {}", pattern));
+        }
+        Ok(Self { pattern, options })
+    }
+
+    /// Whether the line applies to the playlist at `playlist`
+    pub fn matches(&self, playlist: &Path) -> bool {
+        match self.pattern.as_str().contains('/') {
+            true => self.pattern.matches_path(playlist),
+            false => playlist
+                .file_name()
+                .is_some_and(|name| self.pattern.matches(&name.to_string_lossy())),
+        }
+    }
+
+    /// Options the line sets
+    pub fn options(&self) -> &PlaylistOptions {
+        &self.options
+    }
+}
+
+/// Options of the playlist at `playlist` set by the lines matching it,
+/// later lines overriding the options set by earlier ones
+pub fn options_for(matches: &[PlaylistMatch], playlist: &Path) -> PlaylistOptions {
+    let mut options = PlaylistOptions::default();
+    for line in matches.iter().filter(|line| line.matches(playlist)) {
+        let set = &line.options;
+        options.lyrics = set.lyrics.or(options.lyrics);
+        options.max_file_size = set.max_file_size.or(options.max_file_size);
+        options.dest_subdir = set.dest_subdir.clone().or(options.dest_subdir);
+    }
+    options
+}
+
+/// Split `s` into words at white space, keeping the white space within
+/// double quotes and removing the quotes
+fn split_words(s: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quoted = false;
+    for c in s.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quoted {
+        return Err(format!("Unterminated quote in {}", s));
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Parse the value of the boolean option `key`
+fn parse_bool(key: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!(
+            "Invalid value of {}: {} (expected true or false)",
+            key, value
+        )),
+    }
+}
+
+/// Parse a subdirectory of the destination, refusing paths that would
+/// name a directory outside of it
+fn parse_subdir(value: &str) -> Result<PathBuf, String> {
+    let path = PathBuf::from(value);
+    let under_dest = path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir));
+    match under_dest && !value.is_empty() {
+        true => Ok(path),
+        false => Err(format!("Subdirectory outside the destination: {}", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let line = PlaylistMatch::parse(
+            r#""Audio books*.m3u8" lyrics=false max-file-size="2G" dest-subdir=AUDIOBOOKS"#,
+        )
+        .unwrap();
+        assert_eq!(
+            line.options(),
+            &PlaylistOptions {
+                lyrics: Some(false),
+                max_file_size: Some(2 << 30),
+                dest_subdir: Some(PathBuf::from("AUDIOBOOKS")),
+            }
+        );
+        assert!(line.matches(Path::new("/music/Audio books 2024.m3u8")));
+        assert!(!line.matches(Path::new("/music/Podcasts.m3u8")));
+
+        for (argument, message) in [
+            ("*.m3u8", "No options for playlists *.m3u8"),
+            ("*.m3u8 lyrics", "Playlist option without a value: lyrics"),
+            (
+                "*.m3u8 lyrics=no",
+                "Invalid value of lyrics: no (expected true or false)",
+            ),
+            ("*.m3u8 speed=2", "Unknown playlist option: speed"),
+            (
+                "*.m3u8 dest-subdir=../up",
+                "Subdirectory outside the destination: ../up",
+            ),
+            (
+                "\"*.m3u8 lyrics=true",
+                "Unterminated quote in \"*.m3u8 lyrics=true",
+            ),
+        ] {
+            assert_eq!(PlaylistMatch::parse(argument).unwrap_err(), message);
+        }
+    }
+
+    #[test]
+    fn test_options_for() {
+        let matches: Vec<PlaylistMatch> = [
+            "*.m3u8 lyrics=true dest-subdir=MUSIC",
+            "Audiobooks*.m3u8 lyrics=false",
+            "/music/books/* max-file-size=1K",
+        ]
+        .into_iter()
+        .map(|argument| PlaylistMatch::parse(argument).unwrap())
+        .collect();
+
+        assert_eq!(
+            options_for(&matches, Path::new("/music/books/Audiobooks.m3u8")),
+            PlaylistOptions {
+                lyrics: Some(false),
+                max_file_size: Some(1024),
+                dest_subdir: Some(PathBuf::from("MUSIC")),
+            }
+        );
+        assert_eq!(
+            options_for(&matches, Path::new("/music/books/list.m3u")),
+            PlaylistOptions {
+                max_file_size: Some(1024),
+                ..PlaylistOptions::default()
+            }
+        );
+        assert_eq!(
+            options_for(&matches, Path::new("rock.m3u")),
+            PlaylistOptions::default()
+        );
+    }
+}
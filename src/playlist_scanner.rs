@@ -1,46 +1,82 @@
 use anyhow::Result;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader, Lines};
-use std::iter::{Filter, FilterMap, Map};
+use std::iter::FilterMap;
+
+use crate::track_path::TrackPath;
+
+/// Prefix of the extended M3U line describing the following entry
+const EXTINF_PREFIX: &str = "#EXTINF:";
 
 // Internal to this crate
-pub(crate) type PlaylistScanner = Map<
-    Filter<
-        Map<
-            FilterMap<Lines<BufReader<File>>, fn(Result<String, io::Error>) -> Option<String>>,
-            fn(String) -> String,
-        >,
-        fn(&String) -> bool,
-    >,
-    fn(String) -> String,
+pub(crate) type PlaylistScanner = FilterMap<
+    FilterMap<Lines<BufReader<File>>, fn(Result<String, io::Error>) -> Option<String>>,
+    fn(String) -> Option<String>,
 >;
 
-// Helper functions to replace closures with function pointers
-// Keep these helpers private to the module
-fn process_line(line: String) -> String {
-    // Remove BOM if present
-    let line = if line.starts_with('\u{feff}') {
-        line[3..].to_string()
-    } else {
-        line
-    };
+// Keep this helper private to the module
+/// Normalised entry of a line, or `None` for comments and blank lines
+fn track_entry(line: String) -> Option<String> {
+    TrackPath::from_line(&line).map(String::from)
+}
 
-    // Remove carriage return if present
-    if line.ends_with('\r') {
-        line[..line.len() - 1].to_string()
-    } else {
-        line
-    }
+/// Decode the value of a `%XX` escape
+fn hex_value(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
 }
 
-fn filter_line(line: &String) -> bool {
-    // Skip comments and empty lines
-    !(line.starts_with('#') || line.is_empty())
+/// Path of a `file:` URI entry, such as `file:///home/me/Music/a%20b.flac`
+/// exported by some desktop players, with its percent-encoding decoded
+///
+/// The URI may name the local host as `file://localhost/...` or omit the
+/// authority as `file:/...`, and a Windows path such as
+/// `file:///C:/Music/...` gives `C:/Music/...`.  Returns `None` if the
+/// entry is not a `file:` URI, names another host, or is not valid
+/// percent-encoded UTF-8.
+pub fn decode_file_uri(entry: &str) -> Option<String> {
+    let scheme = entry.get(..5)?;
+    if !scheme.eq_ignore_ascii_case("file:") {
+        return None;
+    }
+    let rest = &entry[5..];
+    let path = match rest.strip_prefix("//") {
+        Some(authority_path) => {
+            let slash = authority_path.find('/')?;
+            let host = &authority_path[..slash];
+            if !host.is_empty() && !host.eq_ignore_ascii_case("localhost") {
+                return None;
+            }
+            &authority_path[slash..]
+        }
+        None if rest.starts_with('/') => rest,
+        None => return None,
+    };
+    let path = decode_percent(path)?;
+
+    // `/C:/Music` is the Windows path `C:/Music`
+    let drive = path.as_bytes();
+    match drive.len() >= 3 && drive[1].is_ascii_alphabetic() && drive[2] == b':' {
+        true => Some(path[1..].to_string()),
+        false => Some(path),
+    }
 }
 
-fn replace_backslash(line: String) -> String {
-    // Replace backslashes with forward slashes
-    line.replace('\\', "/")
+/// Text with its `%XX` escapes decoded, such as the path of a URI, or
+/// `None` if it is not valid percent-encoded UTF-8
+pub fn decode_percent(text: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut iter = text.bytes();
+    while let Some(byte) = iter.next() {
+        match byte {
+            b'%' => {
+                let high = hex_value(iter.next()?)?;
+                let low = hex_value(iter.next()?)?;
+                bytes.push((high << 4) | low);
+            }
+            byte => bytes.push(byte),
+        }
+    }
+    String::from_utf8(bytes).ok()
 }
 
 // Only read_playlist should be public to external crates
@@ -48,61 +84,120 @@ pub fn read_playlist(file: File) -> PlaylistScanner {
     BufReader::new(file)
         .lines()
         .filter_map(Result::ok as fn(Result<String, io::Error>) -> Option<String>)
-        .map(process_line as fn(String) -> String)
-        .filter(filter_line as fn(&String) -> bool)
-        .map(replace_backslash as fn(String) -> String)
+        .filter_map(track_entry as fn(String) -> Option<String>)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Write;
-    use tempfile::NamedTempFile;
+/// Parse the entries of playlist contents already read and decoded, such
+/// as a playlist written in an encoding other than UTF-8
+pub fn parse_playlist(content: &str) -> impl Iterator<Item = String> + '_ {
+    content
+        .lines()
+        .filter_map(|line| TrackPath::from_line(line).map(String::from))
+}
 
-    #[test]
-    fn test_process_line_removes_bom() {
-        let input = "\u{feff}test line";
-        let result = process_line(input.to_string());
-        assert_eq!(result, "test line");
-    }
+/// Entry of an extended M3U playlist, with the duration and the title
+/// given by the `#EXTINF` line before it
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExtendedEntry {
+    pub path: String,          // Normalised entry
+    pub duration: Option<f64>, // Seconds, unless unknown or not given
+    pub title: Option<String>,
+}
 
-    #[test]
-    fn test_process_line_removes_carriage_return() {
-        let input = "test line\r";
-        let result = process_line(input.to_string());
-        assert_eq!(result, "test line");
-    }
+/// Duration and title of an `#EXTINF` line, or `None` if the line is not
+/// one
+///
+/// The line is `#EXTINF:duration[ attributes],title`, where a negative
+/// duration means that it is unknown and attributes such as
+/// `tvg-name="a, b"` may quote commas.
+pub fn parse_extinf(line: &str) -> Option<(Option<f64>, Option<String>)> {
+    let info = line
+        .trim_start_matches('\u{feff}')
+        .strip_prefix(EXTINF_PREFIX)?;
 
-    #[test]
-    fn test_process_line_handles_normal_text() {
-        let input = "test line";
-        let result = process_line(input.to_string());
-        assert_eq!(result, "test line");
-    }
+    let mut quoted = false;
+    let comma = info.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ',' if !quoted => Some(i),
+        _ => None,
+    });
+    let (head, title) = match comma {
+        Some(i) => (&info[..i], Some(info[i + 1..].trim())),
+        None => (info, None),
+    };
 
-    #[test]
-    fn test_filter_line_skips_comments() {
-        let input = "#This is a comment".to_string();
-        assert!(!filter_line(&input));
-    }
+    let duration = head
+        .split_whitespace()
+        .next()
+        .and_then(|duration| duration.parse::<f64>().ok())
+        .filter(|duration| *duration >= 0.0);
+    let title = title.filter(|title| !title.is_empty()).map(String::from);
+    Some((duration, title))
+}
 
-    #[test]
-    fn test_filter_line_skips_empty_lines() {
-        let input = "".to_string();
-        assert!(!filter_line(&input));
-    }
+/// Parse the entries of playlist contents with the durations and titles
+/// of their `#EXTINF` lines
+///
+/// An `#EXTINF` line describes the next entry, even with other directives
+/// such as `#EXTGRP` between them, and entries without one have neither
+/// duration nor title.
+pub fn parse_extended_playlist(content: &str) -> Vec<ExtendedEntry> {
+    let mut entries = Vec::new();
+    let mut extinf = None;
 
-    #[test]
-    fn test_filter_line_keeps_content_lines() {
-        let input = "artist/album/track.flac".to_string();
-        assert!(filter_line(&input));
+    for line in content.lines() {
+        if let Some(info) = parse_extinf(line) {
+            extinf = Some(info);
+        } else if let Some(path) = TrackPath::from_line(line) {
+            let (duration, title) = extinf.take().unwrap_or_default();
+            entries.push(ExtendedEntry {
+                path: path.into_string(),
+                duration,
+                title,
+            });
+        }
     }
+    entries
+}
+
+/// Read the entries of a playlist file with the durations and titles of
+/// their `#EXTINF` lines
+pub fn read_extended_playlist(file: File) -> io::Result<Vec<ExtendedEntry>> {
+    let content = io::read_to_string(file)?;
+    Ok(parse_extended_playlist(&content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
 
     #[test]
-    fn test_replace_backslash() {
-        let input = "artist\\album\\track.flac";
-        let result = replace_backslash(input.to_string());
-        assert_eq!(result, "artist/album/track.flac");
+    fn test_decode_file_uri() {
+        assert_eq!(
+            decode_file_uri("file:///home/me/Music/Bj%C3%B6rk/a%20b.flac"),
+            Some("/home/me/Music/Björk/a b.flac".to_string())
+        );
+        assert_eq!(
+            decode_file_uri("FILE://localhost/music/a.flac"),
+            Some("/music/a.flac".to_string())
+        );
+        assert_eq!(
+            decode_file_uri("file:/music/50%25.flac"),
+            Some("/music/50%.flac".to_string())
+        );
+        assert_eq!(
+            decode_file_uri("file:///C:/Music/a.flac"),
+            Some("C:/Music/a.flac".to_string())
+        );
+        assert_eq!(decode_file_uri("file://server/share/a.flac"), None);
+        assert_eq!(decode_file_uri("file:///music/a%2.flac"), None);
+        assert_eq!(decode_file_uri("file:///music/%FF.flac"), None);
+        assert_eq!(decode_file_uri("artist/file:.flac"), None);
     }
 
     #[test]
@@ -135,4 +230,58 @@ mod tests {
         assert_eq!(playlist_items[1], "artist2/album2/track2.flac");
         assert_eq!(playlist_items[2], "artist3/album3/track3.flac");
     }
+
+    #[test]
+    fn test_parse_playlist() {
+        let content = "\u{feff}#EXTM3U\r\nartist1\\album1\\track1.flac\r\n\r\nartist2/track2.flac";
+
+        let playlist_items: Vec<String> = parse_playlist(content).collect();
+
+        assert_eq!(
+            playlist_items,
+            vec!["artist1/album1/track1.flac", "artist2/track2.flac"]
+        );
+    }
+
+    #[test]
+    fn test_parse_extinf() {
+        assert_eq!(
+            parse_extinf("#EXTINF:123,Artist - Title"),
+            Some((Some(123.0), Some("Artist - Title".to_string())))
+        );
+        assert_eq!(
+            parse_extinf("#EXTINF:-1 tvg-name=\"a, b\",Title, with comma"),
+            Some((None, Some("Title, with comma".to_string())))
+        );
+        assert_eq!(parse_extinf("#EXTINF:61.5"), Some((Some(61.5), None)));
+        assert_eq!(parse_extinf("#EXTINF:,"), Some((None, None)));
+        assert_eq!(parse_extinf("#EXTM3U"), None);
+        assert_eq!(parse_extinf("artist/#EXTINF:1,a.flac"), None);
+    }
+
+    #[test]
+    fn test_parse_extended_playlist() {
+        let content = "\u{feff}#EXTM3U\r\n#EXTINF:61,A\r\n#EXTGRP:Rock\r\nsub\\a.flac\r\n\
+                       b.flac\r\n#EXTINF:-1,C\r\nc.flac";
+
+        assert_eq!(
+            parse_extended_playlist(content),
+            vec![
+                ExtendedEntry {
+                    path: "sub/a.flac".to_string(),
+                    duration: Some(61.0),
+                    title: Some("A".to_string()),
+                },
+                ExtendedEntry {
+                    path: "b.flac".to_string(),
+                    ..ExtendedEntry::default()
+                },
+                ExtendedEntry {
+                    path: "c.flac".to_string(),
+                    duration: None,
+                    title: Some("C".to_string()),
+                },
+            ]
+        );
+    }
 }
@@ -0,0 +1,250 @@
+//! Actions run on a device after its content has changed
+//!
+//! Some players only notice new content once a marker file on the
+//! device is touched or a tool has rebuilt their database.  The actions
+//! a device needs are kept in a profile file on the host, one per line:
+//!
+//! ```text
+//! # Rebuild the database of the player
+//! touch .rebuild_database
+//! run rebuild-db "$PLM_DEST"
+//! ```
+//!
+//! A profile can also list the playlists the device subscribes to, as
+//! described in the `device_profile` module, and set options for some
+//! of the playlists, as described in the `playlist_options` module.
+//!
+//! Profiles are never read from the device itself, so that plugging in
+//! a device cannot run commands on the host.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::time::SystemTime;
+
+use crate::logger::Logger;
+
+/// Environment variable giving commands the destination directory
+pub const DEST_VAR: &str = "PLM_DEST";
+
+/// Action run on a destination after its content has changed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PostSyncAction {
+    /// Create the file at a path relative to the destination, or update
+    /// its modification time
+    Touch(PathBuf),
+    /// Run a command with the shell in the destination directory
+    Run(String),
+}
+
+/// Actions of a device profile, run in order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PostSyncActions {
+    actions: Vec<PostSyncAction>,
+}
+
+impl PostSyncActions {
+    /// Parse the actions of a profile, one per line
+    ///
+    /// Blank lines and lines starting with `#` are ignored, and
+    /// surrounding white space is removed.  The paths of `touch` must be
+    /// relative and stay under the destination.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut actions = Vec::new();
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim_start_matches('\u{feff}').trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (keyword, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let argument = argument.trim();
+            let action = match (keyword, argument.is_empty()) {
+                ("touch", false) => {
+                    let path = PathBuf::from(argument);
+                    if !is_under_dest(&path) {
+                        return Err(format!(
+                            "line {}: Path outside the destination: {}",
+                            i + 1,
+                            argument
+                        ));
+                    }
+                    PostSyncAction::Touch(path)
+                }
+                ("run", false) => PostSyncAction::Run(argument.to_string()),
+                // Playlists subscribed to and options of playlists, read by
+                // the `device_profile` module
+                ("subscribe" | "playlist", false) => continue,
+                ("touch" | "run" | "subscribe" | "playlist", true) => {
+                    return Err(format!("line {}: Missing argument of {}", i + 1, keyword))
+                }
+                _ => return Err(format!("line {}: Unknown action: {}", i + 1, keyword)),
+            };
+            actions.push(action);
+        }
+
+        Ok(Self { actions })
+    }
+
+    /// Read the actions of the profile file at `path`
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Self::parse(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// The actions in the order they are run
+    pub fn actions(&self) -> &[PostSyncAction] {
+        &self.actions
+    }
+
+    /// Run the actions on the destination `dest` in order, stopping at
+    /// the first one failing
+    pub fn run(&self, dest: &Path, logger: &dyn Logger) -> io::Result<()> {
+        for action in &self.actions {
+            match action {
+                PostSyncAction::Touch(path) => {
+                    let path = dest.join(path);
+                    logger.log_formatted("Touching marker file \"{}\"", &[&path.to_string_lossy()]);
+                    touch(&path).map_err(|e| {
+                        io::Error::new(
+                            e.kind(),
+                            format!("Failed to touch {}: {}", path.display(), e),
+                        )
+                    })?;
+                }
+                PostSyncAction::Run(command) => {
+                    logger.log_formatted("Running \"{}\"", &[command]);
+                    run_command(command, dest)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether `path` is relative without `..` components, so that joined
+/// to a destination it names a file under it
+fn is_under_dest(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+
+/// Create the file at `path` along with its directory, or update its
+/// modification time if it exists
+fn touch(path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = File::options().create(true).append(true).open(path)?;
+    file.set_modified(SystemTime::now())
+}
+
+/// Run `command` with the shell in the directory `dest`, failing unless
+/// it exits successfully
+fn run_command(command: &str, dest: &Path) -> io::Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let status = shell
+        .arg(command)
+        .current_dir(dest)
+        .env(DEST_VAR, dest)
+        .status()
+        .map_err(|e| io::Error::new(e.kind(), format!("Failed to run {}: {}", command, e)))?;
+
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "Command failed with {}: {}",
+            status, command
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logger::NullLogger;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse() {
+        let actions = PostSyncActions::parse(
+            "# Walkman\n\ntouch  .rebuild_database \nrun echo \"$PLM_DEST\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            actions.actions(),
+            [
+                PostSyncAction::Touch(PathBuf::from(".rebuild_database")),
+                PostSyncAction::Run("echo \"$PLM_DEST\"".to_string()),
+            ]
+        );
+
+        assert!(PostSyncActions::parse("").unwrap().actions().is_empty());
+        let e = PostSyncActions::parse("touch a\ncopy a b\n").unwrap_err();
+        assert_eq!(e, "line 2: Unknown action: copy");
+        let e = PostSyncActions::parse("touch\n").unwrap_err();
+        assert_eq!(e, "line 1: Missing argument of touch");
+    }
+
+    #[test]
+    fn test_parse_touch_outside_dest() {
+        let e = PostSyncActions::parse("touch /etc/marker\n").unwrap_err();
+        assert_eq!(e, "line 1: Path outside the destination: /etc/marker");
+        let e = PostSyncActions::parse("touch ./DB/../../marker\n").unwrap_err();
+        assert_eq!(e, "line 1: Path outside the destination: ./DB/../../marker");
+        assert!(PostSyncActions::parse("touch ./DB/refresh\n").is_ok());
+    }
+
+    #[test]
+    fn test_run_touch() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dest = temp_dir.path();
+        let actions = PostSyncActions::parse("touch DB/refresh\n").unwrap();
+
+        actions.run(dest, &NullLogger)?;
+        let marker = dest.join("DB/refresh");
+        assert_eq!(fs::read_to_string(&marker)?, "");
+
+        // An existing marker file keeps its content
+        fs::write(&marker, "content")?;
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        File::options()
+            .append(true)
+            .open(&marker)?
+            .set_modified(old)?;
+        actions.run(dest, &NullLogger)?;
+        assert_eq!(fs::read_to_string(&marker)?, "content");
+        assert!(fs::metadata(&marker)?.modified()? > old);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dest = temp_dir.path();
+
+        let actions =
+            PostSyncActions::parse("run echo \"$PLM_DEST\" > dest.txt\nrun false\ntouch never\n")
+                .unwrap();
+        let e = actions.run(dest, &NullLogger).unwrap_err();
+        assert!(e.to_string().contains("Command failed"));
+        assert_eq!(
+            fs::read_to_string(dest.join("dest.txt"))?.trim_end(),
+            dest.to_str().unwrap()
+        );
+        assert!(!dest.join("never").exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+//! Progress of copying media files, drawn as a bar on a terminal
+//!
+//! On a terminal the bar is redrawn in place on a single line, with the
+//! bytes and files copied, an estimate of the time left and the file
+//! being copied:
+//!
+//! ```text
+//! [=========>          ]  12.3 MiB/45.6 MiB  3/120 files  ETA 0:42  title.flac
+//! ```
+//!
+//! Elsewhere, such as when the output is redirected to a log file, a
+//! plain line is written for each file copied instead.
+
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use crate::diagnostics::format_size;
+
+/// Width of the bar itself, between its brackets
+const BAR_WIDTH: usize = 20;
+
+/// Width of the line assumed when the terminal does not give it
+const DEFAULT_COLUMNS: usize = 80;
+
+/// Progress of copying media files, written to an output as they are
+/// copied
+pub struct ProgressBar {
+    out: Box<dyn Write + Send>,
+    terminal: bool, // Whether to redraw the bar in place
+    columns: usize,
+    started: Instant,
+    total_files: usize,
+    total_bytes: u64,
+    done_files: usize,
+    done_bytes: u64,
+    current: String, // Name of the file being copied
+    drawn: bool,     // Whether the bar is on the current line
+}
+
+impl ProgressBar {
+    /// Progress written to `out`, redrawn in place if it is a terminal
+    /// `columns` wide
+    pub fn new(out: Box<dyn Write + Send>, terminal: bool, columns: usize) -> Self {
+        Self {
+            out,
+            terminal,
+            columns,
+            started: Instant::now(),
+            total_files: 0,
+            total_bytes: 0,
+            done_files: 0,
+            done_bytes: 0,
+            current: String::new(),
+            drawn: false,
+        }
+    }
+
+    /// Progress written to standard error, redrawn in place if it is a
+    /// terminal as wide as `COLUMNS` says
+    pub fn stderr() -> Self {
+        let columns = std::env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.parse().ok())
+            .filter(|&columns| columns > 0)
+            .unwrap_or(DEFAULT_COLUMNS);
+        Self::new(Box::new(io::stderr()), io::stderr().is_terminal(), columns)
+    }
+
+    /// Set the numbers of files and bytes to copy, which may grow as
+    /// playlists are parsed
+    pub fn set_totals(&mut self, files: usize, bytes: u64) {
+        self.total_files = files;
+        self.total_bytes = bytes;
+    }
+
+    /// Show that the file named `name` is being copied
+    pub fn start_file(&mut self, name: &str) {
+        self.current = name.to_string();
+        if self.terminal {
+            self.draw();
+        }
+    }
+
+    /// Count a file of `bytes` bytes as done, copied or not
+    pub fn finish_file(&mut self, bytes: u64) {
+        self.done_files += 1;
+        self.done_bytes += bytes;
+        if self.terminal {
+            self.draw();
+        } else {
+            let line = self.status(self.started.elapsed());
+            let _ = writeln!(self.out, "{}  {}", line, self.current);
+        }
+    }
+
+    /// Remove the bar from its line, so that other messages can be
+    /// written there
+    pub fn clear(&mut self) {
+        if self.drawn {
+            let _ = write!(self.out, "\r\x1b[K");
+            let _ = self.out.flush();
+            self.drawn = false;
+        }
+    }
+
+    /// Remove the bar once every file is done
+    pub fn finish(&mut self) {
+        self.clear();
+    }
+
+    /// Redraw the bar in place
+    fn draw(&mut self) {
+        let line = format!(
+            "{}  {}  {}",
+            self.bar(),
+            self.status(self.started.elapsed()),
+            self.current
+        );
+        let line: String = line.chars().take(self.columns.saturating_sub(1)).collect();
+        let _ = write!(self.out, "\r\x1b[K{}", line);
+        let _ = self.out.flush();
+        self.drawn = true;
+    }
+
+    /// The bar filled in proportion to the bytes copied, or to the files
+    /// done when the bytes are not known
+    fn bar(&self) -> String {
+        let ratio = match (self.total_bytes, self.total_files) {
+            (0, 0) => 0.0,
+            (0, files) => self.done_files as f64 / files as f64,
+            (bytes, _) => self.done_bytes as f64 / bytes as f64,
+        };
+        let filled = ((ratio.min(1.0) * BAR_WIDTH as f64) as usize).min(BAR_WIDTH);
+        let head = if filled < BAR_WIDTH { ">" } else { "" };
+        format!(
+            "[{}{}{}]",
+            "=".repeat(filled),
+            head,
+            " ".repeat(BAR_WIDTH - filled - head.len())
+        )
+    }
+
+    /// Bytes and files done, and the time left estimated from the time
+    /// taken so far
+    fn status(&self, elapsed: Duration) -> String {
+        let bytes = match self.total_bytes {
+            0 => format_size(self.done_bytes),
+            total => format!("{}/{}", format_size(self.done_bytes), format_size(total)),
+        };
+        let eta = match (self.done_bytes, self.total_bytes) {
+            (0, _) | (_, 0) => "--:--".to_string(),
+            (done, _) => {
+                let left = self.total_bytes.saturating_sub(done);
+                let secs = (elapsed.as_secs_f64() * left as f64 / done as f64).round() as u64;
+                format_duration(secs)
+            }
+        };
+        format!(
+            "{}  {}/{} files  ETA {}",
+            bytes, self.done_files, self.total_files, eta
+        )
+    }
+}
+
+impl Drop for ProgressBar {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// Duration as minutes and seconds, with hours when needed
+fn format_duration(secs: u64) -> String {
+    match secs / 3600 {
+        0 => format!("{}:{:02}", secs / 60, secs % 60),
+        hours => format!("{}:{:02}:{:02}", hours, secs / 60 % 60, secs % 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Output shared with the test
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn take(&self) -> String {
+            String::from_utf8(std::mem::take(&mut *self.0.lock().unwrap())).unwrap()
+        }
+    }
+
+    #[test]
+    fn test_status() {
+        let mut progress = ProgressBar::new(Box::new(io::sink()), false, 80);
+        progress.set_totals(4, 4096);
+        assert_eq!(
+            progress.status(Duration::from_secs(0)),
+            "0 bytes/4.0 KiB  0/4 files  ETA --:--"
+        );
+        progress.done_files = 1;
+        progress.done_bytes = 1024;
+        assert_eq!(progress.bar(), format!("[=====>{}]", " ".repeat(14)));
+        assert_eq!(
+            progress.status(Duration::from_secs(20)),
+            "1.0 KiB/4.0 KiB  1/4 files  ETA 1:00"
+        );
+        progress.done_bytes = 4096;
+        assert_eq!(progress.bar(), format!("[{}]", "=".repeat(20)));
+        assert_eq!(format_duration(3725), "1:02:05");
+    }
+
+    #[test]
+    fn test_terminal_redraws_in_place() {
+        let output = Output::default();
+        let mut progress = ProgressBar::new(Box::new(output.clone()), true, 60);
+        progress.set_totals(2, 2048);
+
+        progress.start_file("title1.flac");
+        let drawn = output.take();
+        assert!(drawn.starts_with("\r\x1b[K[>"));
+        assert!(!drawn.contains('\n'));
+        assert!(drawn.chars().count() <= 4 + 59);
+
+        progress.finish_file(1024);
+        progress.finish();
+        assert!(output.take().ends_with("\r\x1b[K"));
+    }
+
+    #[test]
+    fn test_plain_lines() {
+        let output = Output::default();
+        let mut progress = ProgressBar::new(Box::new(output.clone()), false, 80);
+        progress.set_totals(2, 0);
+
+        progress.start_file("title1.flac");
+        progress.finish_file(1024);
+        progress.start_file("title2.flac");
+        progress.finish_file(2048);
+        progress.finish();
+        let lines = output.take();
+        let lines: Vec<&str> = lines.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("1.0 KiB  1/2 files  ETA "));
+        assert!(lines[0].ends_with("  title1.flac"));
+        assert!(lines[1].starts_with("3.0 KiB  2/2 files  ETA "));
+    }
+}
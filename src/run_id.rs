@@ -0,0 +1,44 @@
+//! Identifiers of runs, correlating the files written by a run
+
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::archive::civil_from_days;
+
+/// Identifier of a run started at `time` by the process `pid`, such as
+/// `20261016T105655Z-3039`: the start time in UTC, followed by the
+/// process ID in hexadecimal so that runs started within the same
+/// second differ
+pub fn run_id_at(time: SystemTime, pid: u32) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (year, month, day) = civil_from_days(secs / 86_400);
+    let secs_of_day = secs % 86_400;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z-{:x}",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60,
+        pid
+    )
+}
+
+/// Identifier of a run of this process starting now
+pub fn new_run_id() -> String {
+    run_id_at(SystemTime::now(), process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_run_id_at() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_792_148_215);
+        assert_eq!(run_id_at(time, 12345), "20261016T105655Z-3039");
+        assert_eq!(run_id_at(UNIX_EPOCH, 1), "19700101T000000Z-1");
+    }
+}
@@ -0,0 +1,135 @@
+//! Guard refusing writes under the source directories of a copy
+//!
+//! With `--source-read-only`, the directories the playlists and their
+//! media files are read from are protected along with everything below
+//! them, and a copy planning to write or delete anything there fails
+//! before touching the destination.  This is a safety net for libraries
+//! kept in a single copy, against a destination inside the library, a
+//! backup directory pointed at it or renames escaping the destination.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::track_matcher::absolute_path;
+
+/// Source directories protected from writes, each with everything below
+/// it
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceGuard {
+    roots: BTreeSet<PathBuf>,
+}
+
+impl SourceGuard {
+    /// Guard protecting nothing
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Protect the directory `dir` and everything below it
+    pub fn protect(&mut self, dir: &Path) -> io::Result<()> {
+        self.roots.insert(resolve_path(dir)?);
+        Ok(())
+    }
+
+    /// Protect the directory of the file at `path`
+    pub fn protect_file_dir(&mut self, path: &Path) -> io::Result<()> {
+        let path = resolve_path(path)?;
+        match path.parent() {
+            Some(dir) => self.protect(dir),
+            None => self.protect(&path),
+        }
+    }
+
+    /// Protected directories, as resolved paths
+    pub fn roots(&self) -> impl Iterator<Item = &Path> {
+        self.roots.iter().map(PathBuf::as_path)
+    }
+
+    /// Whether nothing is protected
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty()
+    }
+
+    /// Fail with a permission error if writing to or deleting `path`
+    /// would touch a protected directory
+    pub fn check(&self, path: &Path) -> io::Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let resolved = resolve_path(path)?;
+        match resolved.ancestors().find(|dir| self.roots.contains(*dir)) {
+            Some(root) => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "Refusing to write {} under the read-only source {}",
+                    path.display(),
+                    root.display()
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Make a path absolute, following the symbolic links of the part of it
+/// that exists and resolving the `.` and `..` components of the rest
+/// lexically
+///
+/// A path reaching a source directory through a symbolic link or `..`
+/// components resolves to the path of the source directory.
+pub fn resolve_path(path: &Path) -> io::Result<PathBuf> {
+    let path = absolute_path(path)?;
+    let mut missing = Vec::new();
+    for dir in path.ancestors() {
+        if let Ok(resolved) = fs::canonicalize(dir) {
+            let mut resolved = resolved;
+            resolved.extend(missing.iter().rev());
+            return Ok(resolved);
+        }
+        if let Some(name) = dir.file_name() {
+            missing.push(name.to_os_string());
+        }
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_check() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let music = temp_dir.path().join("music");
+        fs::create_dir_all(music.join("artist/album"))?;
+        fs::create_dir(temp_dir.path().join("dest"))?;
+
+        let mut guard = SourceGuard::new();
+        assert!(guard.check(&music).is_ok());
+        guard.protect(&music.join("playlists/.."))?;
+        guard.protect_file_dir(&music.join("artist/album/title.flac"))?;
+        assert_eq!(guard.roots().count(), 2);
+
+        assert!(guard.check(&temp_dir.path().join("dest/artist")).is_ok());
+        assert!(guard.check(temp_dir.path()).is_ok());
+        let err = guard
+            .check(&music.join("artist/album/title.flac"))
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+        assert!(guard.check(&music.join("new/dir/title.flac")).is_err());
+        assert!(guard
+            .check(&temp_dir.path().join("dest/../music/title.flac"))
+            .is_err());
+
+        #[cfg(unix)]
+        {
+            let link = temp_dir.path().join("dest/link");
+            std::os::unix::fs::symlink(&music, &link)?;
+            assert!(guard.check(&link.join("artist/title.flac")).is_err());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+//! State of the files synced to a destination, kept on the destination
+//!
+//! Whether a media file on a device is up to date cannot be told from
+//! the device alone: copies get new modification times, and the same
+//! card may be synced from several computers whose libraries have
+//! different paths and timestamps.  The state file `.plm/state.json` at
+//! the root of the destination records, for each file synced, where it
+//! came from, the size, modification time and CRC-32 checksum of the
+//! source, and when it was synced.  A later run from any computer can
+//! then skip files whose source is unchanged, and tell the files it
+//! synced from those it did not.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Directory of the state file under the destination root
+pub const STATE_DIR: &str = ".plm";
+
+/// Name of the state file in [`STATE_DIR`]
+pub const STATE_FILE: &str = "state.json";
+
+/// Version of the state file format written by this module
+pub const FORMAT_VERSION: u32 = 1;
+
+/// A file synced to the destination
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncedFile {
+    pub source: String,        // Absolute path of the source file
+    pub host: String,          // Computer the file was synced from
+    pub size: u64,             // Size of the source file
+    pub modified: Option<u64>, // Modification time of the source file, in seconds
+    pub crc32: Option<u32>,    // CRC-32 checksum of the source file
+    pub synced: u64,           // Time the file was synced, in seconds
+}
+
+/// Files synced to a destination, by path relative to the destination
+/// root with `/` separators
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncState {
+    version: u32,
+    files: BTreeMap<String, SyncedFile>,
+}
+
+impl Default for SyncState {
+    fn default() -> Self {
+        Self {
+            version: FORMAT_VERSION,
+            files: BTreeMap::new(),
+        }
+    }
+}
+
+impl SyncState {
+    /// Path of the state file of the destination at `dest_root`
+    pub fn path(dest_root: &Path) -> PathBuf {
+        dest_root.join(STATE_DIR).join(STATE_FILE)
+    }
+
+    /// Read the state file of a destination
+    ///
+    /// A destination without a state file has an empty state.  A state
+    /// file written by a newer version is an error rather than being
+    /// overwritten with less information.
+    pub fn load(dest_root: &Path) -> io::Result<Self> {
+        let content = match fs::read_to_string(Self::path(dest_root)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+
+        let state: Self = serde_json::from_str(&content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if state.version > FORMAT_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported state file version {}", state.version),
+            ));
+        }
+        Ok(state)
+    }
+
+    /// Write the state file of a destination, replacing the previous one
+    /// only once the new one is complete
+    pub fn save(&self, dest_root: &Path) -> io::Result<()> {
+        let path = Self::path(dest_root);
+        fs::create_dir_all(dest_root.join(STATE_DIR))?;
+
+        let content = serde_json::to_string_pretty(self)?;
+        let partial = path.with_extension("json.partial");
+        fs::write(&partial, content + "\n")?;
+        fs::rename(&partial, &path)
+    }
+
+    /// Synced file at `dest_file`, relative to the destination root
+    pub fn get(&self, dest_file: &str) -> Option<&SyncedFile> {
+        self.files.get(dest_file)
+    }
+
+    /// Record the file synced to `dest_file`, relative to the
+    /// destination root
+    pub fn record(&mut self, dest_file: &str, file: SyncedFile) {
+        self.files.insert(dest_file.to_string(), file);
+    }
+
+    /// Forget the file at `dest_file`, such as a file deleted from the
+    /// destination
+    pub fn forget(&mut self, dest_file: &str) -> Option<SyncedFile> {
+        self.files.remove(dest_file)
+    }
+
+    /// Synced files by path relative to the destination root, in order
+    pub fn files(&self) -> impl Iterator<Item = (&str, &SyncedFile)> {
+        self.files.iter().map(|(path, file)| (path.as_str(), file))
+    }
+}
+
+/// Seconds since the Unix epoch of a point in time, if after it
+pub fn unix_seconds(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+/// Name of the computer, recorded as the host of synced files
+///
+/// Read from the environment on Windows and from `/etc/hostname`
+/// elsewhere, falling back to `unknown`.
+pub fn host_name() -> String {
+    let name = std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .or_else(|_| fs::read_to_string("/etc/hostname"))
+        .unwrap_or_default();
+    match name.trim() {
+        "" => "unknown".to_string(),
+        name => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn synced_file(size: u64) -> SyncedFile {
+        SyncedFile {
+            source: "/music/artist1/title1.flac".to_string(),
+            host: "pc1".to_string(),
+            size,
+            modified: Some(1_700_000_000),
+            crc32: Some(0x1234_5678),
+            synced: 1_700_000_100,
+        }
+    }
+
+    #[test]
+    fn test_save_and_load() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        assert_eq!(SyncState::load(root)?, SyncState::default());
+
+        let mut state = SyncState::default();
+        state.record("artist1/title1.flac", synced_file(7));
+        state.record("artist1/title2.flac", synced_file(8));
+        assert_eq!(state.forget("artist1/title2.flac"), Some(synced_file(8)));
+        state.save(root)?;
+
+        let loaded = SyncState::load(root)?;
+        assert_eq!(loaded, state);
+        assert_eq!(loaded.get("artist1/title1.flac"), Some(&synced_file(7)));
+        assert_eq!(loaded.files().count(), 1);
+        assert!(!root.join(".plm/state.json.partial").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_newer_version() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        fs::create_dir(root.join(STATE_DIR))?;
+        fs::write(SyncState::path(root), r#"{"version": 2, "files": {}}"#)?;
+        assert!(SyncState::load(root).is_err());
+
+        fs::write(SyncState::path(root), "not json")?;
+        assert!(SyncState::load(root).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+//! Recognition of tracks in playlist entries
+//!
+//! Commands editing playlists take tracks on the command line either as
+//! entries written in the playlist or as paths to the media files.  This
+//! module matches both forms against playlist entries and turns paths
+//! into entries relative to a playlist.
+
+use std::io;
+use std::path::{self, Component, Path, PathBuf};
+
+use glob::Pattern;
+
+use crate::track_path::TrackPath;
+
+/// How tracks are recognised in playlist entries
+#[derive(Debug)]
+pub enum TrackMatcher {
+    /// An entry as written in the playlist, or a path to the media file
+    Path { entry: String, path: PathBuf },
+    /// A wildcard pattern matched against an entry or its full path
+    Pattern(Pattern),
+}
+
+impl TrackMatcher {
+    /// Match the entry or the media file given by `track`
+    ///
+    /// A relative path is resolved against the current directory.
+    pub fn path(track: &str) -> io::Result<Self> {
+        let entry = TrackPath::new(track).into_string();
+        let path = absolute_path(Path::new(&entry))?;
+        Ok(TrackMatcher::Path { entry, path })
+    }
+
+    /// Match entries with the wildcard pattern `track`
+    pub fn pattern(track: &str) -> Result<Self, glob::PatternError> {
+        Ok(TrackMatcher::Pattern(Pattern::new(
+            &track.replace('\\', "/"),
+        )?))
+    }
+
+    /// Whether the entry of a playlist in `playlist_dir` is the track
+    pub fn matches(&self, playlist_dir: &Path, entry: &str) -> bool {
+        let full_path = playlist_dir.join(entry);
+        match self {
+            TrackMatcher::Path { entry: track, path } => {
+                entry == track || absolute_path(&full_path).is_ok_and(|full| full == *path)
+            }
+            TrackMatcher::Pattern(pattern) => {
+                pattern.matches(entry) || pattern.matches(&full_path.to_string_lossy())
+            }
+        }
+    }
+}
+
+/// Directory containing a playlist, against which its entries resolve
+pub fn playlist_dir(playlist: &Path) -> PathBuf {
+    match playlist.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Make a path absolute and resolve its `.` and `..` components
+/// lexically, without following symbolic links
+pub fn absolute_path(path: &Path) -> io::Result<PathBuf> {
+    let mut resolved = PathBuf::new();
+
+    for component in path::absolute(path)?.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// Turn the path to a media file into an entry of a playlist in
+/// `playlist_dir`, using forward slashes
+///
+/// Returns `None` if the media file is not under the playlist directory.
+pub fn relative_entry(track: &Path, playlist_dir: &Path) -> io::Result<Option<String>> {
+    let track = absolute_path(track)?;
+    let playlist_dir = absolute_path(playlist_dir)?;
+
+    Ok(track.strip_prefix(&playlist_dir).ok().and_then(|relative| {
+        let components: Vec<_> = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        (!components.is_empty()).then(|| components.join("/"))
+    }))
+}
+
+/// Turn the path to a media file into an entry of a playlist in
+/// `playlist_dir`, using forward slashes and climbing out of the
+/// directory with `..` segments if the media file is not under it
+///
+/// The path is returned as an absolute path if it shares no root with
+/// the directory, as on another drive.
+pub fn climbing_entry(track: &Path, playlist_dir: &Path) -> io::Result<String> {
+    let track = absolute_path(track)?;
+    let playlist_dir = absolute_path(playlist_dir)?;
+    let common = track
+        .components()
+        .zip(playlist_dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return Ok(track.to_string_lossy().to_string());
+    }
+
+    let ups = playlist_dir.components().skip(common).map(|_| "..".into());
+    let downs = track
+        .components()
+        .skip(common)
+        .map(|component| component.as_os_str().to_string_lossy());
+    Ok(ups.chain(downs).collect::<Vec<_>>().join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_matcher() {
+        let matcher = TrackMatcher::path("/music/artist1/../artist1/title1.flac").unwrap();
+        assert!(matcher.matches(Path::new("/music"), "artist1/title1.flac"));
+        assert!(matcher.matches(Path::new("/music/sub"), "../artist1/title1.flac"));
+        assert!(!matcher.matches(Path::new("/music"), "artist1/title2.flac"));
+
+        let matcher = TrackMatcher::path("artist1\\title1.flac").unwrap();
+        assert!(matcher.matches(Path::new("/music"), "artist1/title1.flac"));
+    }
+
+    #[test]
+    fn test_pattern_matcher() {
+        let matcher = TrackMatcher::pattern("artist1/*").unwrap();
+        assert!(matcher.matches(Path::new("/music"), "artist1/album1/title1.flac"));
+        assert!(!matcher.matches(Path::new("/music"), "artist2/album1/title1.flac"));
+
+        let matcher = TrackMatcher::pattern("/music/*/title?.flac").unwrap();
+        assert!(matcher.matches(Path::new("/music"), "artist2/title1.flac"));
+
+        assert!(TrackMatcher::pattern("artist[").is_err());
+    }
+
+    #[test]
+    fn test_relative_entry() {
+        let dir = Path::new("/music");
+        assert_eq!(
+            relative_entry(Path::new("/music/./artist1/title1.flac"), dir).unwrap(),
+            Some("artist1/title1.flac".to_string())
+        );
+        assert_eq!(
+            relative_entry(Path::new("/other/title1.flac"), dir).unwrap(),
+            None
+        );
+        assert_eq!(relative_entry(Path::new("/music"), dir).unwrap(), None);
+    }
+
+    #[test]
+    fn test_climbing_entry() {
+        let entry =
+            |track: &str, dir: &str| climbing_entry(Path::new(track), Path::new(dir)).unwrap();
+        assert_eq!(entry("/music/a/title.flac", "/music"), "a/title.flac");
+        assert_eq!(entry("/music/a/title.flac", "/music/b"), "../a/title.flac");
+        assert_eq!(
+            entry("/music/a/../c/title.flac", "/music/b/d"),
+            "../../c/title.flac"
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! Paths of media files as written in playlist entries, normalised
+//!
+//! Playlists name their media files in many ways: with backslashes
+//! written on Windows, with a byte order mark or a carriage return left
+//! on the line, as `file:` URIs, or with `.` and `..` segments.  A
+//! [`TrackPath`] is the one normal form of an entry that every command
+//! reads playlists through, so that they agree on the media file an
+//! entry names.
+//!
+//! Unicode forms are kept as written: a name in NFC and the same name in
+//! NFD are different paths, as they are to the filesystems of Linux and
+//! Windows, and an entry has to name its media file byte for byte to be
+//! found there.
+
+use std::fmt;
+use std::path::Path;
+
+use crate::playlist_scanner::decode_file_uri;
+
+/// Byte order mark some tools put at the start of UTF-8 playlists
+const BOM: char = '\u{feff}';
+
+/// Path of a media file named by a playlist entry, with forward slashes
+/// and without empty, `.` or cancelled `..` segments
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TrackPath(String);
+
+impl TrackPath {
+    /// Normalise an entry
+    ///
+    /// A `file:` URI gives the path it encodes, and backslashes of other
+    /// entries are replaced by forward slashes.  Empty and `.` segments
+    /// are removed, and a `..` segment is removed with the segment
+    /// before it, so that `artist/./album/../album/title.flac` gives
+    /// `artist/album/title.flac`.  Leading `..` segments of a relative
+    /// entry are kept, while those above the root of an absolute entry
+    /// are dropped.  An entry left with no segment is `.`.
+    pub fn new(entry: &str) -> Self {
+        let path = decode_file_uri(entry).unwrap_or_else(|| entry.replace('\\', "/"));
+        Self(normalize_segments(&path))
+    }
+
+    /// Entry of a line of a playlist, with its byte order mark and
+    /// carriage return removed, or `None` for comments and blank lines
+    pub fn from_line(line: &str) -> Option<Self> {
+        let line = line.strip_prefix(BOM).unwrap_or(line);
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        Some(Self::new(line))
+    }
+
+    /// The path as a string
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The path as an owned string
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl fmt::Display for TrackPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for TrackPath {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TrackPath {
+    fn as_ref(&self) -> &Path {
+        Path::new(&self.0)
+    }
+}
+
+impl From<TrackPath> for String {
+    fn from(path: TrackPath) -> Self {
+        path.0
+    }
+}
+
+/// Whether a segment is the drive of a Windows path, such as `C:`
+fn is_drive(segment: &str) -> bool {
+    let bytes = segment.as_bytes();
+    bytes.len() == 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+/// Remove the empty and `.` segments of a path with forward slashes, and
+/// the `..` segments with the segments they cancel
+fn normalize_segments(path: &str) -> String {
+    // The root of an absolute path, with two slashes for a UNC path
+    let root = match path.starts_with("//") {
+        true => "//",
+        false if path.starts_with('/') => "/",
+        false => "",
+    };
+    let mut segments: Vec<&str> = Vec::new();
+
+    for segment in path[root.len()..].split('/') {
+        let at_root = match segments.as_slice() {
+            [] => !root.is_empty(),
+            [drive] => root.is_empty() && is_drive(drive),
+            _ => false,
+        };
+        match segment {
+            "" | "." => {}
+            ".." if at_root => {}
+            ".." if segments.last().is_some_and(|&last| last != "..") => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    match (root, segments.is_empty()) {
+        ("", true) => ".".to_string(),
+        _ => format!("{}{}", root, segments.join("/")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(entry: &str) -> String {
+        TrackPath::new(entry).into_string()
+    }
+
+    #[test]
+    fn test_separators() {
+        assert_eq!(
+            normalize("artist\\album\\title.flac"),
+            "artist/album/title.flac"
+        );
+        assert_eq!(
+            normalize("artist/album\\title.flac"),
+            "artist/album/title.flac"
+        );
+        assert_eq!(
+            normalize("artist//album/title.flac"),
+            "artist/album/title.flac"
+        );
+        assert_eq!(normalize("artist/album/"), "artist/album");
+        assert_eq!(normalize("C:\\Music\\title.flac"), "C:/Music/title.flac");
+        assert_eq!(
+            normalize("\\\\server\\share\\title.flac"),
+            "//server/share/title.flac"
+        );
+    }
+
+    #[test]
+    fn test_lines() {
+        let entry = |line| TrackPath::from_line(line).map(String::from);
+        assert_eq!(
+            entry("\u{feff}artist\\title.flac"),
+            Some("artist/title.flac".into())
+        );
+        assert_eq!(
+            entry("artist/title.flac\r"),
+            Some("artist/title.flac".into())
+        );
+        assert_eq!(entry("\u{feff}#EXTM3U\r"), None);
+        assert_eq!(entry("#EXTINF:10,Title"), None);
+        assert_eq!(entry("\r"), None);
+        assert_eq!(entry(""), None);
+        // A byte order mark is only removed from the start of a line
+        assert_eq!(entry("a\u{feff}.flac"), Some("a\u{feff}.flac".into()));
+    }
+
+    #[test]
+    fn test_dot_segments() {
+        assert_eq!(normalize("./artist/title.flac"), "artist/title.flac");
+        assert_eq!(
+            normalize("artist/./album/../album/title.flac"),
+            "artist/album/title.flac"
+        );
+        assert_eq!(normalize("../other/title.flac"), "../other/title.flac");
+        assert_eq!(normalize("a/../../title.flac"), "../title.flac");
+        assert_eq!(normalize("/music/../../title.flac"), "/title.flac");
+        assert_eq!(normalize("C:/../title.flac"), "C:/title.flac");
+        assert_eq!(normalize("artist/.."), ".");
+        assert_eq!(normalize("..."), "...");
+        assert_eq!(normalize(".hidden/title.flac"), ".hidden/title.flac");
+    }
+
+    #[test]
+    fn test_file_uris() {
+        assert_eq!(
+            normalize("file:///music/Bj%C3%B6rk/./a%20b.flac"),
+            "/music/Björk/a b.flac"
+        );
+        // A backslash encoded in a URI is part of a name
+        assert_eq!(normalize("file:///music/a%5Cb.flac"), "/music/a\\b.flac");
+    }
+
+    #[test]
+    fn test_unicode_forms() {
+        let nfc = "Bj\u{f6}rk/title.flac";
+        let nfd = "Bjo\u{308}rk/title.flac";
+        assert_eq!(normalize(nfc), nfc);
+        assert_eq!(normalize(nfd), nfd);
+        assert_ne!(TrackPath::new(nfc), TrackPath::new(nfd));
+        assert_eq!(
+            normalize("アーティスト\\タイトル.flac"),
+            "アーティスト/タイトル.flac"
+        );
+    }
+}
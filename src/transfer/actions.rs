@@ -0,0 +1,248 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::dest_index::DestIndex;
+use crate::media_file_info::MediaFileInfo;
+use crate::media_set::MediaKey;
+use crate::path_interner::PathInterner;
+use crate::sync_state::SyncState;
+
+use super::itemize::Change;
+use super::plan::{plan_playlists, CopyPlan};
+use super::read_only::check_plan;
+use super::state::{is_unchanged, load_sync_state};
+use super::{scan_destination, TransferOptions};
+
+/// What running a put plan does with a media file
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Action {
+    /// Copy the media file, making the change to its destination file
+    Copy(Change),
+    /// Skip the media file, unchanged since synced with `--sync-state`
+    /// or up to date with `--update`
+    Unchanged,
+    /// Skip the media file, identical to the media file copied instead
+    Duplicate(MediaKey),
+    /// Skip the media file, whose destination file is taken by the media
+    /// file copied instead
+    Conflict(MediaKey),
+    /// Fail to copy the media file, renamed to the destination path of
+    /// another file with the skip collision strategy
+    Collision,
+    /// Fail to copy the media file, missing from the source
+    Missing,
+}
+
+/// Numbers of the media files of a put plan by action
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ActionCounts {
+    pub new: usize,
+    pub updated: usize,
+    pub up_to_date: usize,
+    pub skipped: usize, // Unchanged, duplicate and conflicting media files
+    pub missing: usize,
+    pub collisions: usize,
+}
+
+/// Copy plan resolved against the destination
+///
+/// Each unique media file of the playlists is given the action taken
+/// for it, in the order the media files are copied.  Building the plan
+/// only lists the destination directories and reads the sync state, so
+/// the destination is left untouched until the plan is executed.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PutPlan {
+    pub plan: CopyPlan,
+    pub dest_index: DestIndex,
+    pub sync_state: Option<SyncState>,
+    pub actions: Vec<(MediaKey, Action)>,
+}
+
+impl PutPlan {
+    /// Count the media files of the plan by action
+    pub fn counts(&self) -> ActionCounts {
+        let mut counts = ActionCounts::default();
+        for (_, action) in &self.actions {
+            match action {
+                Action::Copy(Change::New) => counts.new += 1,
+                Action::Copy(Change::Update { .. }) => counts.updated += 1,
+                Action::Copy(Change::UpToDate) => counts.up_to_date += 1,
+                Action::Unchanged | Action::Duplicate(_) | Action::Conflict(_) => {
+                    counts.skipped += 1
+                }
+                Action::Collision => counts.collisions += 1,
+                Action::Missing => counts.missing += 1,
+            }
+        }
+        counts
+    }
+}
+
+/// Why a media file is not copied again: unchanged since synced with
+/// `--sync-state`, or with `--update`, its destination file of the same
+/// size and not older
+pub fn skip_reason(
+    media_file: &MediaFileInfo,
+    dest_index: &DestIndex,
+    options: &TransferOptions,
+    sync_state: Option<&SyncState>,
+) -> Option<&'static str> {
+    let dest_path = media_file.dest_path(dest_index.root());
+    if sync_state.is_some_and(|sync_state| {
+        is_unchanged(sync_state, &media_file.src_path(), &dest_path, dest_index)
+    }) {
+        return Some("unchanged");
+    }
+    let change = Change::of(media_file.size, media_file.modified, &dest_path, dest_index);
+    (options.update && change == Change::UpToDate).then_some("up to date")
+}
+
+/// Action taken for a media file of a plan
+fn resolve_action(
+    key: &MediaKey,
+    plan: &CopyPlan,
+    dest_index: &DestIndex,
+    options: &TransferOptions,
+    sync_state: Option<&SyncState>,
+) -> Action {
+    let (src_basedir, file) = key;
+    if let Some(original) = plan.duplicates.original(src_basedir, file) {
+        return Action::Duplicate(original.clone());
+    }
+    if let Some(winner) = plan.conflicts.winner(src_basedir, file) {
+        return Action::Conflict(winner.clone());
+    }
+    if plan.collisions.contains(key) {
+        return Action::Collision;
+    }
+
+    let media_file = &plan.media[key];
+    if media_file.size.is_none() {
+        return Action::Missing;
+    }
+    if skip_reason(media_file, dest_index, options, sync_state).is_some() {
+        return Action::Unchanged;
+    }
+    let dest_path = media_file.dest_path(dest_index.root());
+    Action::Copy(Change::of(
+        media_file.size,
+        media_file.modified,
+        &dest_path,
+        dest_index,
+    ))
+}
+
+/// Plan the copy of the playlists and the file lists of the options to
+/// `dest_dir`, resolving the action taken for each media file against
+/// the destination without writing to it
+pub fn plan_put(
+    playlists: &[String],
+    dest_dir: &str,
+    options: &TransferOptions,
+    interner: &mut PathInterner,
+) -> Result<PutPlan> {
+    let plan = plan_playlists(playlists, options, interner)?;
+    let dest_index = scan_destination(dest_dir, plan.dest_files())?;
+    check_plan(&plan, dest_index.root(), options)?;
+    let sync_state = load_sync_state(dest_index.root(), options)?;
+
+    let mut seen: HashSet<MediaKey> = HashSet::new();
+    let actions = plan
+        .playlists
+        .iter()
+        .filter_map(|planned| planned.media.as_ref().ok())
+        .flat_map(|(src_basedir, files)| {
+            files
+                .iter()
+                .map(|file| (Arc::clone(src_basedir), Arc::clone(file)))
+        })
+        .filter(|key| seen.insert(key.clone()))
+        .map(|key| {
+            let action = resolve_action(&key, &plan, &dest_index, options, sync_state.as_ref());
+            (key, action)
+        })
+        .collect();
+
+    Ok(PutPlan {
+        plan,
+        dest_index,
+        sync_state,
+        actions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfer::plan::PlannedPlaylist;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_counts() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let key = |file: &str| (Arc::from("MUSIC"), Arc::from(file));
+        let put_plan = PutPlan {
+            plan: CopyPlan::default(),
+            dest_index: DestIndex::scan(temp_dir.path(), [""])?,
+            sync_state: None,
+            actions: vec![
+                (key("new.flac"), Action::Copy(Change::New)),
+                (key("same.flac"), Action::Copy(Change::UpToDate)),
+                (key("copy.flac"), Action::Duplicate(key("same.flac"))),
+                (key("other.flac"), Action::Conflict(key("new.flac"))),
+                (key("missing.flac"), Action::Missing),
+                (key("Missing.flac"), Action::Collision),
+            ],
+        };
+
+        assert_eq!(
+            put_plan.counts(),
+            ActionCounts {
+                new: 1,
+                updated: 0,
+                up_to_date: 1,
+                skipped: 2,
+                missing: 1,
+                collisions: 1,
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_put_plan_json() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let key = |file: &str| (Arc::from("MUSIC"), Arc::from(file));
+        let mut plan = CopyPlan::default();
+        plan.playlists.push(PlannedPlaylist {
+            playlist: "missing.m3u8".to_string(),
+            media: Err(anyhow::anyhow!("Playlist not found")),
+            non_audio_entries: 0,
+            is_list: false,
+        });
+        plan.media
+            .insert(key("new.flac"), MediaFileInfo::new("MUSIC", "new.flac"));
+        plan.total_media_files = 1;
+        let put_plan = PutPlan {
+            plan,
+            dest_index: DestIndex::scan(temp_dir.path(), [""])?,
+            sync_state: None,
+            actions: vec![(key("new.flac"), Action::Copy(Change::New))],
+        };
+
+        // The plan is read back from JSON as it was written
+        let read: PutPlan = serde_json::from_str(&serde_json::to_string(&put_plan)?)?;
+        assert_eq!(read.actions, put_plan.actions);
+        assert_eq!(read.plan.total_media_files, 1);
+        assert!(read.plan.media.contains_key(&key("new.flac")));
+        let error = read.plan.playlists[0].media.as_ref().unwrap_err();
+        assert_eq!(error.to_string(), "Playlist not found");
+
+        Ok(())
+    }
+}
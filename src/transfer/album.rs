@@ -0,0 +1,93 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::album_playlist::write_album_playlist;
+use crate::fat_order::sort_directory;
+use crate::logger::Logger;
+
+use super::TransferOptions;
+
+/// Destination directories the given media files (relative to the
+/// destination root) are copied into, excluding the destination root
+/// itself
+///
+/// Directories that do not exist, such as when nothing could be copied
+/// into them, are left out.
+pub fn album_dirs<'a>(dest_dir: &str, files: impl Iterator<Item = &'a Path>) -> Vec<PathBuf> {
+    let rel_dirs: BTreeSet<&Path> = files
+        .filter_map(|file| file.parent())
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .collect();
+
+    rel_dirs
+        .into_iter()
+        .map(|dir| Path::new(dest_dir).join(dir))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+/// Create a playlist in each album directory, listing the media files
+/// in the directory by track number
+pub fn create_album_playlists(
+    album_dirs: &[PathBuf],
+    options: &TransferOptions,
+    logger: &dyn Logger,
+) -> Result<()> {
+    for album_dir in album_dirs {
+        match write_album_playlist(album_dir) {
+            Ok(Some(playlist)) => {
+                logger.log_formatted(
+                    "Create album playlist \"{}\"",
+                    &[&playlist.to_string_lossy()],
+                );
+            }
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!(
+                    "Error creating album playlist in {}: {}",
+                    album_dir.display(),
+                    e
+                );
+                if !options.keep_going {
+                    return Err(e.into());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reorder the directory entries of each album directory into track
+/// order for players playing files in FAT directory order
+pub fn sort_album_dirs(
+    album_dirs: &[PathBuf],
+    options: &TransferOptions,
+    logger: &dyn Logger,
+) -> Result<()> {
+    for album_dir in album_dirs {
+        match sort_directory(album_dir) {
+            Ok(true) => {
+                logger.log_formatted(
+                    "Sort directory entries of \"{}\"",
+                    &[&album_dir.to_string_lossy()],
+                );
+            }
+            Ok(false) => {}
+            Err(e) => {
+                eprintln!(
+                    "Error sorting directory entries of {}: {}",
+                    album_dir.display(),
+                    e
+                );
+                if !options.keep_going {
+                    return Err(e.into());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use glob::{MatchOptions, Pattern};
+
+use crate::dest_index::DestIndex;
+
+use super::copy::copy_indexed_file;
+use super::itemize::{print_change, Change};
+use super::plan::CopyPlan;
+use super::state::record_synced;
+use super::summary::CopySummary;
+use super::{ProgressContext, TransferOptions};
+
+/// Names of the cover images copied with `--artwork` unless
+/// `--artwork-pattern` gives others
+pub const ARTWORK_PATTERNS: &str = "cover.jpg,cover.png,folder.jpg,folder.png,album.jpg,album.png";
+
+/// Parse a pattern of the names of cover images
+pub fn parse_artwork_pattern(s: &str) -> Result<Pattern, String> {
+    Pattern::new(s.trim()).map_err(|e| format!("Invalid pattern {}: {}", s, e))
+}
+
+/// Album directories of the media files of a plan, as pairs of their
+/// paths relative to the destination root and the source directories
+/// their media files are copied from, excluding the destination root
+///
+/// A destination directory filled from several source directories gets
+/// the cover images of the first in the order of their paths.
+fn artwork_dirs(plan: &CopyPlan) -> BTreeMap<&Path, PathBuf> {
+    let mut dirs: BTreeMap<&Path, BTreeSet<PathBuf>> = BTreeMap::new();
+    for media_file in plan.media.values() {
+        let Some(dest_dir) = media_file.dest_file().parent() else {
+            continue;
+        };
+        if dest_dir.as_os_str().is_empty() {
+            continue;
+        }
+        if let Some(src_dir) = media_file.src_path().parent() {
+            dirs.entry(dest_dir)
+                .or_default()
+                .insert(src_dir.to_path_buf());
+        }
+    }
+    dirs.into_iter()
+        .filter_map(|(dest_dir, src_dirs)| Some((dest_dir, src_dirs.into_iter().next()?)))
+        .collect()
+}
+
+/// Cover images in a source directory whose names match one of the
+/// patterns of `--artwork`, compared case-insensitively, in the order of
+/// their names
+fn artwork_files(src_dir: &Path, options: &TransferOptions) -> Vec<PathBuf> {
+    let match_options = MatchOptions {
+        case_sensitive: false,
+        ..MatchOptions::new()
+    };
+    let Ok(entries) = fs::read_dir(src_dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            options
+                .artwork
+                .iter()
+                .any(|pattern| pattern.matches_with(&name, match_options))
+        })
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    files
+}
+
+/// Copy the cover images of the source album directories into the
+/// destination directories their media files were copied into, once per
+/// directory, recording the copies in the sync state
+///
+/// Directories that do not exist, such as when nothing could be copied
+/// into them, are left out.
+pub fn copy_artwork(
+    plan: &CopyPlan,
+    dest_index: &mut DestIndex,
+    options: &TransferOptions,
+    progress_context: &mut ProgressContext,
+) -> Result<()> {
+    let dest_root = dest_index.root().to_path_buf();
+    for (dest_dir, src_dir) in artwork_dirs(plan) {
+        if !dest_root.join(dest_dir).is_dir() {
+            continue;
+        }
+        for src_path in artwork_files(&src_dir, options) {
+            let dest_file = dest_dir.join(src_path.file_name().unwrap_or_default());
+            let dest_path = dest_root.join(&dest_file);
+            progress_context.total_artwork_files += 1;
+            progress_context.logger.log_formatted(
+                "Copy artwork \"{}\" to \"{}\"",
+                &[&src_path.to_string_lossy(), &dest_path.to_string_lossy()],
+            );
+            let change = options
+                .itemize
+                .then(|| Change::of_file(&src_path, &dest_path, dest_index));
+
+            let logger = &*progress_context.logger;
+            match copy_indexed_file(&src_path, &dest_path, dest_index, options, logger) {
+                Ok(checksum) => {
+                    record_synced(
+                        progress_context,
+                        &src_path,
+                        &dest_path,
+                        &dest_root,
+                        checksum,
+                    );
+                    progress_context.successful_artwork_files += 1;
+                    if let Some(change) = change {
+                        print_change(change, &dest_file);
+                    }
+                }
+                Err(err) => {
+                    eprintln!("Error: {}", err);
+                    if !options.keep_going {
+                        return Err(err);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print the changes copying the cover images would make and count them
+/// into the summary, for the dry run
+pub fn itemize_artwork(
+    plan: &CopyPlan,
+    dest_index: &DestIndex,
+    options: &TransferOptions,
+    summary: &mut CopySummary,
+) {
+    for (dest_dir, src_dir) in artwork_dirs(plan) {
+        for src_path in artwork_files(&src_dir, options) {
+            let dest_file = dest_dir.join(src_path.file_name().unwrap_or_default());
+            let dest_path = dest_index.root().join(&dest_file);
+            print_change(
+                Change::of_file(&src_path, &dest_path, dest_index),
+                &dest_file,
+            );
+            summary.successful_artwork_files += 1;
+            summary.total_artwork_files += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_artwork_files() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let album = temp_dir.path();
+        for name in ["Folder.JPG", "cover.jpg", "back.jpg", "title.flac"] {
+            fs::write(album.join(name), name)?;
+        }
+        fs::create_dir(album.join("album.png"))?;
+
+        let options = TransferOptions {
+            artwork: ARTWORK_PATTERNS
+                .split(',')
+                .map(|pattern| parse_artwork_pattern(pattern).unwrap())
+                .collect(),
+            ..TransferOptions::default()
+        };
+        assert_eq!(
+            artwork_files(album, &options),
+            [album.join("Folder.JPG"), album.join("cover.jpg")]
+        );
+
+        let options = TransferOptions {
+            artwork: vec![parse_artwork_pattern("*.jpg").unwrap()],
+            ..TransferOptions::default()
+        };
+        assert_eq!(artwork_files(album, &options).len(), 3);
+        Ok(())
+    }
+}
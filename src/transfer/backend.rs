@@ -0,0 +1,372 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::dest_index::DestIndex;
+use crate::logger::Logger;
+use crate::media_file_info::MediaFileInfo;
+
+use super::backup::back_up_changed_file;
+use super::copy::{copy_prepared_file, create_dest_dir};
+use super::itemize::Change;
+use super::TransferOptions;
+
+/// How media files are copied
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IoBackend {
+    /// Copy one file at a time with the standard library
+    #[default]
+    Portable,
+    /// Copy the media files of each playlist as a batch through io_uring,
+    /// falling back to the portable copier where io_uring is unavailable
+    Uring,
+}
+
+impl FromStr for IoBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "portable" => Ok(Self::Portable),
+            "uring" if cfg!(all(target_os = "linux", feature = "io-uring")) => Ok(Self::Uring),
+            "uring" => Err("The uring I/O backend requires Linux and the io-uring feature".into()),
+            _ => Err(format!(
+                "Unknown I/O backend: {} (expected portable or uring)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for IoBackend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Portable => "portable",
+            Self::Uring => "uring",
+        })
+    }
+}
+
+/// Media file copied ahead of being processed
+pub struct CopiedFile {
+    pub result: Result<Option<u32>>, // Checksum of the source if computed while copying
+    pub elapsed: Duration,
+    pub change: Option<Change>, // Change itemized before copying
+}
+
+/// Pairs of the source and destination paths of media files to copy as
+/// a batch, with the changes itemized before copying them
+type Batch = (Vec<(PathBuf, PathBuf)>, Vec<Option<Change>>);
+
+/// Number of media files copied ahead at most for each file copied at
+/// the same time, which bounds the files copied but not yet processed
+const FILES_AHEAD_PER_COPY: usize = 4;
+
+/// Prepare at most `window_size` of the media files to copy as a batch,
+/// creating their destination directories
+/// Returns the batch and the number of media files it went through
+///
+/// Directories that cannot be created and destinations taken twice are
+/// left to the portable copier.  A missing source file is left out with
+/// `--keep-going`, and ends the batch before it otherwise, as the copy
+/// stops at its error and no media file after it is to be copied.
+fn prepare_batch(
+    media_files: &[&MediaFileInfo],
+    window_size: usize,
+    dest_index: &mut DestIndex,
+    options: &TransferOptions,
+) -> (Batch, usize) {
+    let mut dest_paths = HashSet::new();
+    let mut batch = Vec::new();
+    let mut changes = Vec::new();
+    let mut prepared = 0;
+    for media_file in media_files {
+        if batch.len() == window_size || (media_file.size.is_none() && !options.keep_going) {
+            break;
+        }
+        prepared += 1;
+        let dest_path = media_file.dest_path(dest_index.root());
+        if media_file.size.is_none()
+            || dest_paths.contains(&dest_path)
+            || create_dest_dir(&dest_path, dest_index).is_err()
+        {
+            continue;
+        }
+        let change = options
+            .itemize
+            .then(|| Change::of(media_file.size, media_file.modified, &dest_path, dest_index));
+        changes.push(change);
+        dest_paths.insert(dest_path.clone());
+        batch.push((media_file.src_path(), dest_path));
+    }
+    ((batch, changes), prepared)
+}
+
+/// Media files of a playlist copied ahead of being processed, by
+/// destination path
+///
+/// The media files are copied ahead a window at a time, once the first
+/// media file of the window is to be processed, so that no more than a
+/// few files are copied for each one copied at the same time before
+/// they are processed.  Files the io_uring batch failed to copy are left
+/// out, so that the portable copier copies them again and reports its
+/// own error.  The media files are processed one by one in playlist
+/// order, so the counters, the error file and the messages are updated
+/// by a single thread whichever way the files were copied.
+pub struct CopiedAhead<'a> {
+    media_files: Vec<&'a MediaFileInfo>, // Media files to copy ahead, in playlist order
+    next: usize,                         // Index of the first media file not copied ahead yet
+    files: HashMap<PathBuf, CopiedFile>,
+}
+
+impl<'a> CopiedAhead<'a> {
+    /// Media files to copy ahead if the io_uring backend or more than one
+    /// job is selected, or nothing to copy ahead
+    pub fn new(media_files: Vec<&'a MediaFileInfo>, options: &TransferOptions) -> Self {
+        let copies_ahead = match options.io_backend {
+            IoBackend::Portable => options.jobs > 1,
+            IoBackend::Uring => true,
+        };
+        Self {
+            media_files: if copies_ahead {
+                media_files
+            } else {
+                Vec::new()
+            },
+            next: 0,
+            files: HashMap::new(),
+        }
+    }
+
+    /// Take the outcome of copying `media_file` ahead, copying the window
+    /// of media files starting with it first if it is the next one to
+    /// copy ahead
+    pub fn take(
+        &mut self,
+        media_file: &MediaFileInfo,
+        dest_index: &mut DestIndex,
+        options: &TransferOptions,
+        logger: &dyn Logger,
+    ) -> Option<CopiedFile> {
+        let dest_path = media_file.dest_path(dest_index.root());
+        let is_next = self
+            .media_files
+            .get(self.next)
+            .is_some_and(|next| std::ptr::eq(*next, media_file));
+        if is_next && !self.files.contains_key(&dest_path) {
+            self.copy_window(dest_index, options, logger);
+        }
+        self.files.remove(&dest_path)
+    }
+
+    /// Copy the window of media files starting with the next one to copy
+    /// ahead
+    fn copy_window(
+        &mut self,
+        dest_index: &mut DestIndex,
+        options: &TransferOptions,
+        logger: &dyn Logger,
+    ) {
+        let window_size = copies_at_once(options) * FILES_AHEAD_PER_COPY;
+        let (batch, prepared) = prepare_batch(
+            &self.media_files[self.next..],
+            window_size,
+            dest_index,
+            options,
+        );
+        self.next += prepared;
+        let copied = match options.io_backend {
+            IoBackend::Portable => copy_parallel(batch, dest_index, options, logger),
+            IoBackend::Uring => copy_batch(batch, dest_index, options, logger),
+        };
+        self.files.extend(copied);
+    }
+}
+
+/// Number of media files copied at the same time
+fn copies_at_once(options: &TransferOptions) -> usize {
+    match options.io_backend {
+        IoBackend::Portable => options.jobs,
+        #[cfg(all(target_os = "linux", feature = "io-uring"))]
+        IoBackend::Uring => crate::uring_copy::DEFAULT_FILES_IN_FLIGHT,
+        #[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+        IoBackend::Uring => 1,
+    }
+}
+
+/// Copy the media files with `--jobs` threads of the portable copier
+///
+/// Each thread backs up the destination file of the next media file of
+/// the batch left and copies it, until none is left, keeping the time
+/// taken by each of its copies.  Unless `--keep-going` is given, the
+/// threads stop taking media files after the first one failing, as the
+/// copy stops there once it is processed.
+fn copy_parallel(
+    (batch, changes): Batch,
+    dest_index: &DestIndex,
+    options: &TransferOptions,
+    logger: &dyn Logger,
+) -> Vec<(PathBuf, CopiedFile)> {
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let copy_next = || {
+        let mut copied = Vec::new();
+        while !failed.load(Ordering::Relaxed) {
+            let Some((src_path, dest_path)) = batch.get(next.fetch_add(1, Ordering::Relaxed))
+            else {
+                break;
+            };
+            let started = Instant::now();
+            let result = back_up_changed_file(src_path, dest_path, dest_index, options, logger)
+                .and_then(|_| copy_prepared_file(src_path, dest_path, options, logger));
+            if result.is_err() && !options.keep_going {
+                failed.store(true, Ordering::Relaxed);
+            }
+            copied.push((dest_path.clone(), result, started.elapsed()));
+        }
+        copied
+    };
+    let copied: Vec<_> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..options.jobs.min(batch.len()))
+            .map(|_| scope.spawn(copy_next))
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().expect("copy thread panicked"))
+            .collect()
+    });
+
+    let changes: HashMap<&PathBuf, Option<Change>> = batch
+        .iter()
+        .map(|(_, dest_path)| dest_path)
+        .zip(changes)
+        .collect();
+    copied
+        .into_iter()
+        .map(|(dest_path, result, elapsed)| {
+            let change = changes[&dest_path];
+            let copied = CopiedFile {
+                result,
+                elapsed,
+                change,
+            };
+            (dest_path, copied)
+        })
+        .collect()
+}
+
+/// Copy the media files through io_uring, backing up the destination
+/// file of each one just before it is copied
+///
+/// Destination files that cannot be backed up are left to the portable
+/// copier.  Unless `--keep-going` is given, no media file is started
+/// once one has failed, as the copy stops there once it is processed.
+/// The time taken by the batch is shared evenly between its files in
+/// the report, as they are copied at the same time.
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+fn copy_batch(
+    (batch, changes): Batch,
+    dest_index: &DestIndex,
+    options: &TransferOptions,
+    logger: &dyn Logger,
+) -> Vec<(PathBuf, CopiedFile)> {
+    use std::io;
+    use std::path::Path;
+
+    use crate::file_hash::{hash_file, FileHash, HashAlgorithm};
+    use crate::file_utils::DEFAULT_COPY_BUFFER_SIZE;
+    use crate::uring_copy::{copy_files, DEFAULT_FILES_IN_FLIGHT};
+
+    use super::copy::{copy_xattrs, verify_copy};
+
+    static FALLBACK_WARNED: AtomicBool = AtomicBool::new(false);
+
+    if batch.is_empty() {
+        return Vec::new();
+    }
+
+    let started = Instant::now();
+    let buffer_size = options.buffer_size.unwrap_or(DEFAULT_COPY_BUFFER_SIZE);
+    let back_up = |src_path: &Path, dest_path: &Path| {
+        back_up_changed_file(src_path, dest_path, dest_index, options, logger)
+            .map(|_| ())
+            .map_err(io::Error::other)
+    };
+    let stop_on_error = !options.keep_going;
+    let results = match copy_files(
+        &batch,
+        buffer_size,
+        DEFAULT_FILES_IN_FLIGHT,
+        stop_on_error,
+        back_up,
+    ) {
+        Ok(results) => results,
+        Err(e) => {
+            if !FALLBACK_WARNED.swap(true, Ordering::Relaxed) {
+                eprintln!(
+                    "Warning: Copying with the portable I/O backend as io_uring failed: {}",
+                    e
+                );
+            }
+            return Vec::new();
+        }
+    };
+    let elapsed = started.elapsed() / batch.len() as u32;
+
+    batch
+        .into_iter()
+        .zip(results)
+        .zip(changes)
+        .filter_map(|(((src_path, dest_path), result), change)| {
+            let src_checksum = result?.ok()?;
+            let result = match options.verify {
+                true => match options.hash {
+                    HashAlgorithm::Crc32 => Ok(FileHash::from_crc32(src_checksum)),
+                    algorithm => hash_file(&src_path, algorithm).map_err(Into::into),
+                }
+                .and_then(|src_hash| verify_copy(&src_path, &dest_path, &src_hash)),
+                false => Ok(()),
+            }
+            .and_then(|()| copy_xattrs(&src_path, &dest_path, options))
+            .map(|()| Some(src_checksum));
+            let copied = CopiedFile {
+                result,
+                elapsed,
+                change,
+            };
+            Some((dest_path, copied))
+        })
+        .collect()
+}
+
+/// The io_uring backend cannot be selected without io_uring support
+#[cfg(not(all(target_os = "linux", feature = "io-uring")))]
+fn copy_batch(
+    _: Batch,
+    _: &DestIndex,
+    _: &TransferOptions,
+    _: &dyn Logger,
+) -> Vec<(PathBuf, CopiedFile)> {
+    Vec::new()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_backend_from_str() {
+        assert_eq!("portable".parse(), Ok(IoBackend::Portable));
+        assert_eq!(
+            "URING".parse::<IoBackend>().is_ok(),
+            cfg!(all(target_os = "linux", feature = "io-uring"))
+        );
+        assert!("mmap".parse::<IoBackend>().is_err());
+    }
+}
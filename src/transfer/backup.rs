@@ -0,0 +1,152 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::dest_index::{DestIndex, FileStatus};
+use crate::file_utils::{create_directory, same_contents};
+use crate::logger::Logger;
+
+use super::TransferOptions;
+
+/// Suffix of the backups kept beside the destination files they replace
+pub const BACKUP_SUFFIX: &str = ".plm-bak";
+
+/// Where the old versions of destination files overwritten with
+/// different contents are kept
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Backup {
+    /// Overwrite destination files without keeping them
+    #[default]
+    Off,
+    /// Keep them beside the new versions with [`BACKUP_SUFFIX`]
+    Beside,
+    /// Keep them in a directory, under their paths relative to the
+    /// destination root
+    Dir(PathBuf),
+}
+
+impl Backup {
+    /// Backup selected by `--backup` and `--backup-dir`, which implies
+    /// `--backup`
+    pub fn from_args(backup: bool, backup_dir: Option<&str>) -> Self {
+        match (backup, backup_dir) {
+            (_, Some(dir)) => Self::Dir(PathBuf::from(dir)),
+            (true, None) => Self::Beside,
+            (false, None) => Self::Off,
+        }
+    }
+
+    /// Path of the backup of the destination file at `dest_path`
+    fn path(&self, dest_path: &Path, dest_root: &Path) -> Option<PathBuf> {
+        match self {
+            Self::Off => None,
+            Self::Beside => {
+                let mut name = dest_path.file_name()?.to_os_string();
+                name.push(BACKUP_SUFFIX);
+                Some(dest_path.with_file_name(name))
+            }
+            Self::Dir(dir) => {
+                let rel_path = dest_path.strip_prefix(dest_root).ok()?;
+                Some(dir.join(rel_path))
+            }
+        }
+    }
+}
+
+/// Move the destination file at `dest_path` to its backup before it is
+/// overwritten with the contents of `src_path`, unless it is absent or
+/// already holds the same contents
+/// Returns the path of the backup if one was made
+pub fn back_up_changed_file(
+    src_path: &Path,
+    dest_path: &Path,
+    dest_index: &DestIndex,
+    options: &TransferOptions,
+    logger: &dyn Logger,
+) -> Result<Option<PathBuf>> {
+    if options.backup == Backup::Off {
+        return Ok(None);
+    }
+    let changed = match dest_index.file_status(dest_path) {
+        FileStatus::Absent => false,
+        FileStatus::Present(_) | FileStatus::Unknown => {
+            dest_path.is_file() && !same_contents(src_path, dest_path)?
+        }
+    };
+    match changed {
+        true => back_up_file(dest_path, dest_index.root(), options, logger),
+        false => Ok(None),
+    }
+}
+
+/// Move the destination file at `dest_path` to its backup before it is
+/// overwritten, replacing an earlier backup, if the file exists
+/// Returns the path of the backup if one was made
+pub fn back_up_file(
+    dest_path: &Path,
+    dest_root: &Path,
+    options: &TransferOptions,
+    logger: &dyn Logger,
+) -> Result<Option<PathBuf>> {
+    let Some(backup_path) = options.backup.path(dest_path, dest_root) else {
+        return Ok(None);
+    };
+    if !dest_path.is_file() {
+        return Ok(None);
+    }
+
+    move_file(dest_path, &backup_path).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to back up {} to {}: {}",
+            dest_path.display(),
+            backup_path.display(),
+            e
+        )
+    })?;
+    logger.log_formatted(
+        "Back up \"{}\" to \"{}\"",
+        &[&dest_path.to_string_lossy(), &backup_path.to_string_lossy()],
+    );
+    Ok(Some(backup_path))
+}
+
+/// Move a file, copying it where it cannot be renamed, such as into a
+/// backup directory on another filesystem
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if let Some(dir) = to.parent() {
+        create_directory(dir)?;
+    }
+    match fs::remove_file(to) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_path() {
+        let dest_root = Path::new("/mnt/player");
+        let dest_path = dest_root.join("artist1/album1/title1.flac");
+
+        assert_eq!(Backup::Off.path(&dest_path, dest_root), None);
+        assert_eq!(
+            Backup::Beside.path(&dest_path, dest_root),
+            Some(dest_root.join("artist1/album1/title1.flac.plm-bak"))
+        );
+        assert_eq!(
+            Backup::from_args(false, Some("/backup")).path(&dest_path, dest_root),
+            Some(PathBuf::from("/backup/artist1/album1/title1.flac"))
+        );
+        assert_eq!(Backup::from_args(true, None), Backup::Beside);
+    }
+}
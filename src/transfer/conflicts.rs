@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::file_utils::same_contents;
+use crate::media_file_info::MediaFileInfo;
+use crate::media_set::MediaKey;
+
+use super::names::numbered_name;
+
+/// What to do with a media file whose destination path is taken by a
+/// different media file copied earlier
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Copy only the first media file, pointing the entries of the
+    /// others at it
+    #[default]
+    FirstWins,
+    /// Fail before copying anything
+    Error,
+    /// Copy the others under numbered names
+    Rename,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "first-wins" => Ok(Self::FirstWins),
+            "error" => Ok(Self::Error),
+            "rename" => Ok(Self::Rename),
+            _ => Err(format!(
+                "Unknown conflict policy: {} (expected first-wins, error or rename)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::FirstWins => "first-wins",
+            Self::Error => "error",
+            Self::Rename => "rename",
+        })
+    }
+}
+
+/// Media files mapping to the same destination path as a different
+/// media file referenced earlier, such as `artist/album/track.flac` of
+/// two playlists in different directories
+///
+/// Paths are compared case insensitively as FAT does.  Media files with
+/// identical contents are not in conflict, as copying either gives the
+/// same destination file.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Conflicts {
+    #[cfg_attr(feature = "serde", serde(with = "super::serialize::pairs"))]
+    winners: HashMap<MediaKey, MediaKey>, // Media file left uncopied -> media file copied
+    taken: HashMap<String, MediaKey>,     // Lower-case destination path -> media file added
+}
+
+impl Conflicts {
+    /// Find the conflicts among `keys`, given in the order they are
+    /// copied, and resolve them by `policy`
+    ///
+    /// Each conflict is warned about.  With the rename policy, the
+    /// conflicting media files are assigned numbered destination files
+    /// in `media`; with the error policy, an error is returned after
+    /// warning about every conflict.
+    pub fn find<'a>(
+        keys: impl Iterator<Item = &'a MediaKey>,
+        media: &mut HashMap<MediaKey, MediaFileInfo>,
+        policy: ConflictPolicy,
+    ) -> Result<Self> {
+        let keys: Vec<&MediaKey> = keys.collect();
+
+        // Destination path in lower case -> media file copied to it
+        let mut taken: HashMap<String, &MediaKey> = HashMap::new();
+        let mut conflicting = Vec::new();
+        for key in keys {
+            let dest = media[key].dest_file().to_string_lossy().to_lowercase();
+            match taken.get(&dest) {
+                Some(winner) if !same_file(&media[*winner], &media[key]) => {
+                    conflicting.push((key, *winner));
+                }
+                Some(_) => {}
+                None => {
+                    taken.insert(dest, key);
+                }
+            }
+        }
+
+        let mut winners = HashMap::new();
+        for (key, winner) in conflicting {
+            let src_path = media[key].src_path();
+            let winner_path = media[winner].src_path();
+            let dest = media[key].dest_file().to_string_lossy().to_string();
+
+            if policy != ConflictPolicy::Rename {
+                eprintln!(
+                    "Warning: \"{}\" and \"{}\" are both copied to \"{}\"",
+                    winner_path.display(),
+                    src_path.display(),
+                    dest
+                );
+                winners.insert(key.clone(), winner.clone());
+                continue;
+            }
+
+            let mut n = 2;
+            let mut candidate = numbered_name(&dest, n);
+            while taken.contains_key(&candidate.to_lowercase()) {
+                n += 1;
+                candidate = numbered_name(&dest, n);
+            }
+            eprintln!(
+                "Warning: Copying \"{}\" to \"{}\" as \"{}\" is copied to \"{}\"",
+                src_path.display(),
+                candidate,
+                winner_path.display(),
+                dest
+            );
+            taken.insert(candidate.to_lowercase(), key);
+            media.get_mut(key).expect("planned media file").dest_file =
+                Some(PathBuf::from(candidate));
+        }
+
+        if policy == ConflictPolicy::Error && !winners.is_empty() {
+            return Err(anyhow::anyhow!(
+                "{} media files conflict with others at the destination",
+                winners.len()
+            ));
+        }
+
+        Ok(Self {
+            winners,
+            taken: HashMap::new(),
+        })
+    }
+
+    /// Find the conflicts of `keys`, given in the order they are copied,
+    /// with the media files added earlier, and resolve them by the
+    /// first-wins policy
+    ///
+    /// Media files are added a playlist at a time as the playlists are
+    /// parsed, which gives the same conflicts as [`Conflicts::find`]
+    /// with the first-wins policy.  Returns the number of conflicting
+    /// media files among `keys`.
+    pub fn add<'a>(
+        &mut self,
+        keys: impl Iterator<Item = &'a MediaKey>,
+        media: &HashMap<MediaKey, MediaFileInfo>,
+    ) -> usize {
+        let mut n_conflicts = 0;
+        for key in keys {
+            let dest = media[key].dest_file().to_string_lossy().to_lowercase();
+            match self.taken.get(&dest) {
+                Some(winner) if !same_file(&media[winner], &media[key]) => {
+                    eprintln!(
+                        "Warning: \"{}\" and \"{}\" are both copied to \"{}\"",
+                        media[winner].src_path().display(),
+                        media[key].src_path().display(),
+                        media[key].dest_file().display()
+                    );
+                    self.winners.insert(key.clone(), winner.clone());
+                    n_conflicts += 1;
+                }
+                Some(_) => {}
+                None => {
+                    self.taken.insert(dest, key.clone());
+                }
+            }
+        }
+        n_conflicts
+    }
+
+    /// Number of conflicting media files, which are not copied
+    pub fn len(&self) -> usize {
+        self.winners.len()
+    }
+
+    /// Whether no media files conflict
+    pub fn is_empty(&self) -> bool {
+        self.winners.is_empty()
+    }
+
+    /// Media file copied to the destination path of the media file, if
+    /// the media file is left uncopied for it
+    pub fn winner(&self, src_basedir: &Arc<str>, file: &Arc<str>) -> Option<&MediaKey> {
+        self.winners
+            .get(&(Arc::clone(src_basedir), Arc::clone(file)))
+    }
+}
+
+/// Whether two media files have identical contents, so that copying
+/// either gives the same destination file
+fn same_file(info1: &MediaFileInfo, info2: &MediaFileInfo) -> bool {
+    info1.size.is_some()
+        && info1.size == info2.size
+        && same_contents(&info1.src_path(), &info2.src_path()).unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_conflicts() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        for (dir, content) in [("a", "first"), ("b", "second"), ("c", "first")] {
+            fs::create_dir_all(temp_dir.path().join(dir).join("album"))?;
+            fs::write(temp_dir.path().join(dir).join("album/track.flac"), content)?;
+        }
+
+        let keys: Vec<MediaKey> = ["a", "b", "c"]
+            .into_iter()
+            .map(|dir| {
+                let root = temp_dir.path().join(dir);
+                (
+                    Arc::from(root.to_string_lossy().as_ref()),
+                    Arc::from("album/track.flac"),
+                )
+            })
+            .collect();
+        let media = || -> HashMap<_, _> {
+            keys.iter()
+                .map(|key| {
+                    let info = MediaFileInfo::new(&*key.0, &*key.1).with_metadata();
+                    (key.clone(), info)
+                })
+                .collect()
+        };
+
+        let mut first_wins = media();
+        let conflicts = Conflicts::find(keys.iter(), &mut first_wins, ConflictPolicy::FirstWins)?;
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts.winner(&keys[1].0, &keys[1].1), Some(&keys[0]));
+        assert_eq!(conflicts.winner(&keys[2].0, &keys[2].1), None);
+
+        // Adding the media files a few at a time finds the same conflicts
+        let added = media();
+        let mut conflicts = Conflicts::default();
+        assert_eq!(conflicts.add(keys[..1].iter(), &added), 0);
+        assert_eq!(conflicts.add(keys[1..].iter(), &added), 1);
+        assert_eq!(conflicts.winner(&keys[1].0, &keys[1].1), Some(&keys[0]));
+        assert_eq!(conflicts.winner(&keys[2].0, &keys[2].1), None);
+
+        let mut renamed = media();
+        let conflicts = Conflicts::find(keys.iter(), &mut renamed, ConflictPolicy::Rename)?;
+        assert_eq!(conflicts.len(), 0);
+        assert_eq!(
+            renamed[&keys[1]].dest_file,
+            Some(PathBuf::from("album/track (2).flac"))
+        );
+        assert_eq!(renamed[&keys[2]].dest_file, None);
+
+        let mut failed = media();
+        assert!(Conflicts::find(keys.iter(), &mut failed, ConflictPolicy::Error).is_err());
+        assert_eq!("Rename".parse(), Ok(ConflictPolicy::Rename));
+        assert!("last-wins".parse::<ConflictPolicy>().is_err());
+
+        Ok(())
+    }
+}
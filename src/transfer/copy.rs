@@ -0,0 +1,474 @@
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+
+use crate::dest_index::DestIndex;
+use crate::diagnostics::parse_size;
+use crate::fault_injection::inject_copy;
+use crate::file_hash::{hash_file, FileHash, HashAlgorithm, Hasher};
+use crate::file_utils::{
+    copy_file_buffered, copy_file_inspected, copy_user_xattrs, create_directory,
+    is_transient_error, DEFAULT_COPY_BUFFER_SIZE,
+};
+use crate::logger::Logger;
+use crate::media_file_info::MediaFileInfo;
+
+use super::actions::skip_reason;
+use super::backend::{CopiedAhead, CopiedFile};
+use super::backup::back_up_changed_file;
+use super::itemize::{print_change, Change};
+use super::report::{record_copy, record_skip};
+use super::sidecar::{copy_sidecar_files, log_sidecar_files};
+use super::state::{forget_synced, record_synced};
+use super::{ErrorTracker, ProgressContext, TransferOptions};
+
+/// Smallest and largest copy buffer sizes accepted by `--buffer-size`
+const BUFFER_SIZE_RANGE: (usize, usize) = (4 * 1024, 256 * 1024 * 1024);
+
+/// Wait before the first retry of a copy with `--io-retries`, growing by
+/// as much before each further retry
+const IO_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Parse a copy buffer size given in bytes, or in KiB or MiB with a `K`
+/// or `M` suffix
+pub fn parse_buffer_size(s: &str) -> Result<usize, String> {
+    let size = Some(s)
+        .filter(|s| !s.trim().to_ascii_uppercase().ends_with('G'))
+        .and_then(|s| parse_size(s).ok())
+        .and_then(|size| usize::try_from(size).ok())
+        .ok_or_else(|| format!("Invalid buffer size: {} (expected bytes, or K or M)", s))?;
+
+    let (min, max) = BUFFER_SIZE_RANGE;
+    if !(min..=max).contains(&size) {
+        return Err(format!(
+            "Buffer size {} is out of range ({}K-{}M)",
+            s,
+            min / 1024,
+            max / 1024 / 1024
+        ));
+    }
+    Ok(size)
+}
+
+/// Copy a file into the destination, creating its directory only if the
+/// destination index does not know it yet so that each destination
+/// directory is created at most once per run
+///
+/// With `--buffer-size` or `--verify`, the file is copied in chunks with
+/// read-ahead, and the default buffer size is used for verification
+/// without `--buffer-size`.  With `--verify`, the source is hashed while
+/// it is being copied and only the destination is read back to check
+/// the copy, with the algorithm given by `--hash`.  With `--sync-state`,
+/// the file is copied in chunks as well for the CRC-32 checksum of the
+/// source to be recorded.  With `--backup`, a destination file with
+/// different contents is moved to its backup first.  With `--xattrs`,
+/// the user extended attributes of the source are copied as well.  With
+/// `--io-retries`, a copy failing with a transient error is retried.
+/// Returns the CRC-32 checksum of the source if computed while copying it
+pub fn copy_indexed_file(
+    src_path: &Path,
+    dest_path: &Path,
+    dest_index: &mut DestIndex,
+    options: &TransferOptions,
+    logger: &dyn Logger,
+) -> Result<Option<u32>> {
+    create_dest_dir(dest_path, dest_index)?;
+    back_up_changed_file(src_path, dest_path, dest_index, options, logger)?;
+    copy_prepared_file(src_path, dest_path, options, logger)
+}
+
+/// Copy a file into the destination once its directory is created and
+/// the destination file backed up, as by [`copy_indexed_file`]
+///
+/// The destination index is not needed, so that files can be copied by
+/// several threads at once.
+pub fn copy_prepared_file(
+    src_path: &Path,
+    dest_path: &Path,
+    options: &TransferOptions,
+    logger: &dyn Logger,
+) -> Result<Option<u32>> {
+    let mut retries = 0;
+    loop {
+        let result = inject_copy(src_path, dest_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| copy_contents(src_path, dest_path, options))
+            .and_then(|src_checksum| {
+                copy_xattrs(src_path, dest_path, options)?;
+                Ok(src_checksum)
+            });
+        match result {
+            Err(err) if retries < options.io_retries && is_transient_error(&err) => {
+                retries += 1;
+                logger.log(&format!(
+                    "Retry copying \"{}\" ({}/{}) after error: {}",
+                    src_path.display(),
+                    retries,
+                    options.io_retries,
+                    err
+                ));
+                thread::sleep(IO_RETRY_BACKOFF * retries);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Whether a copy failing with `err` is retried with `--io-retries`
+fn is_retried(err: &anyhow::Error, options: &TransferOptions) -> bool {
+    options.io_retries > 0 && is_transient_error(err)
+}
+
+/// Copy the contents of a file as selected by the options of
+/// [`copy_indexed_file`]
+fn copy_contents(
+    src_path: &Path,
+    dest_path: &Path,
+    options: &TransferOptions,
+) -> Result<Option<u32>> {
+    let buffer_size = match (options.buffer_size, options.verify || options.sync_state) {
+        (Some(buffer_size), _) => buffer_size,
+        (None, true) => DEFAULT_COPY_BUFFER_SIZE,
+        (None, false) => {
+            fs::copy(src_path, dest_path)?;
+            return Ok(None);
+        }
+    };
+
+    if options.hash == HashAlgorithm::Crc32 || !options.verify {
+        let src_checksum = copy_file_buffered(src_path, dest_path, buffer_size)?;
+        if options.verify {
+            verify_copy(src_path, dest_path, &FileHash::from_crc32(src_checksum))?;
+        }
+        return Ok(Some(src_checksum));
+    }
+
+    // Another hash verifies the copy, computed along with the CRC-32
+    // checksum recorded in the sync state
+    let mut crc32 = crc32fast::Hasher::new();
+    let mut hasher = Hasher::new(options.hash);
+    copy_file_inspected(src_path, dest_path, buffer_size, |data| {
+        crc32.update(data);
+        hasher.update(data);
+    })?;
+    verify_copy(src_path, dest_path, &hasher.finalize())?;
+    Ok(Some(crc32.finalize()))
+}
+
+/// Create the directory of a destination file unless the destination
+/// index knows it already
+pub fn create_dest_dir(dest_path: &Path, dest_index: &mut DestIndex) -> Result<()> {
+    if let Some(dest_dir) = dest_path.parent() {
+        if !dest_index.contains_dir(dest_dir) {
+            create_directory(dest_dir)?;
+            dest_index.add_dir(dest_dir);
+        }
+    }
+    Ok(())
+}
+
+/// Copy the user extended attributes of a copied file with `--xattrs`
+///
+/// Files are copied without them where the filesystem does not support
+/// them, such as FAT, with a single warning.
+pub fn copy_xattrs(src_path: &Path, dest_path: &Path, options: &TransferOptions) -> Result<()> {
+    static UNSUPPORTED_WARNED: AtomicBool = AtomicBool::new(false);
+
+    if !options.xattrs {
+        return Ok(());
+    }
+    let copied = copy_user_xattrs(src_path, dest_path).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to copy extended attributes of {} to {}: {}",
+            src_path.display(),
+            dest_path.display(),
+            e
+        )
+    })?;
+    if !copied && !UNSUPPORTED_WARNED.swap(true, Ordering::Relaxed) {
+        eprintln!(
+            "Warning: Copying without extended attributes as they are not supported for \"{}\"",
+            dest_path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Check a copied file against the hash of the source computed while
+/// copying it, hashing the destination with the same algorithm
+pub fn verify_copy(src_path: &Path, dest_path: &Path, src_hash: &FileHash) -> Result<()> {
+    if hash_file(dest_path, src_hash.algorithm())? != *src_hash {
+        anyhow::bail!(
+            "Checksum mismatch after copying \"{}\" to \"{}\"",
+            src_path.display(),
+            dest_path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Copy a single media file from source to destination, recording the
+/// attempt in the report for `playlist` and the copy in the sync state
+/// Returns a tuple of (number of files copied, whether the media file was successfully copied)
+fn copy_single_media_file(
+    playlist: &str,
+    media_file: &MediaFileInfo,
+    dest_index: &mut DestIndex,
+    options: &TransferOptions,
+    copied_ahead: &mut CopiedAhead<'_>,
+    error_tracker: &mut Option<&mut ErrorTracker>,
+    progress_context: &mut ProgressContext,
+) -> Result<(usize, bool)> {
+    let src_file = media_file.src_path();
+    let dest_file = media_file.dest_path(dest_index.root());
+    let dest_root = dest_index.root().to_path_buf();
+
+    // Copy the main media file unless it was copied ahead, itemizing the
+    // change it makes if requested.  A copy ahead failing with an error
+    // retried with `--io-retries` is made again here.
+    let CopiedFile {
+        result,
+        elapsed,
+        change,
+    } = copied_ahead
+        .take(media_file, dest_index, options, &*progress_context.logger)
+        .filter(|copied| {
+            !copied
+                .result
+                .as_ref()
+                .is_err_and(|err| is_retried(err, options))
+        })
+        .unwrap_or_else(|| {
+            let change = options
+                .itemize
+                .then(|| Change::of(media_file.size, media_file.modified, &dest_file, dest_index));
+            let started = Instant::now();
+            let logger = &*progress_context.logger;
+            let result = copy_indexed_file(&src_file, &dest_file, dest_index, options, logger);
+            CopiedFile {
+                result,
+                elapsed: started.elapsed(),
+                change,
+            }
+        });
+    record_copy(
+        progress_context,
+        playlist,
+        media_file,
+        &dest_file,
+        elapsed,
+        result.as_ref().err(),
+    );
+    let checksum = match result {
+        Ok(checksum) => checksum,
+        Err(err) => {
+            if let Some(progress) = &mut progress_context.progress {
+                progress.clear();
+            }
+            report_failed_copy(&err, media_file, options, progress_context);
+            forget_synced(progress_context, &dest_file, &dest_root);
+            progress_context.failed_media_files += 1;
+            if let Some(tracker) = error_tracker {
+                tracker.add_failed_media_file(
+                    media_file.src_basedir.to_string_lossy().to_string(),
+                    media_file.file.to_string_lossy().to_string(),
+                );
+            }
+            if options.keep_going {
+                return Ok((0, false));
+            } else {
+                return Err(err);
+            }
+        }
+    };
+    record_synced(
+        progress_context,
+        &src_file,
+        &dest_file,
+        &dest_root,
+        checksum,
+    );
+    if let Some(change) = change {
+        print_change(change, media_file.dest_file());
+    }
+
+    let n_sidecar_files = copy_sidecar_files(
+        media_file,
+        dest_index,
+        options,
+        error_tracker,
+        progress_context,
+    )?;
+    Ok((1 + n_sidecar_files, true))
+}
+
+/// Print the error of a media file failing to copy
+///
+/// With `--keep-going`, a media file missing from the source is only
+/// collected to be reported by `print_missing_files` along with the
+/// other missing media files of its directory, unless `--list-missing`
+/// is given.
+fn report_failed_copy(
+    err: &anyhow::Error,
+    media_file: &MediaFileInfo,
+    options: &TransferOptions,
+    progress_context: &mut ProgressContext,
+) {
+    let src_file = media_file.src_path();
+    if src_file.exists() {
+        eprintln!("Error: {}", err);
+    } else if options.keep_going && !options.list_missing {
+        progress_context.missing_files.add(&src_file);
+    } else {
+        eprintln!("Error: Media file not found: {}", src_file.display());
+    }
+}
+
+/// Print one error line per source directory with missing media files
+/// collected while copying
+pub fn print_missing_files(progress_context: &ProgressContext) {
+    for line in progress_context.missing_files.lines() {
+        eprintln!("Error: {}", line);
+    }
+}
+
+/// Copy media files from source to destination
+///
+/// Each media file is given with its interned path relative to the
+/// source base directory, which is returned for every successfully
+/// copied file.  Attempts are recorded in the report for `playlist`.
+/// Media files found unchanged in the sync state, or up to date with
+/// `--update`, are skipped and counted as unchanged rather than as
+/// copied, but are still returned.  With the io_uring backend, the other
+/// media files are copied ahead as a batch before being processed one
+/// by one.
+/// Returns a tuple of (number of files copied, list of successfully copied media files)
+pub fn copy_media_files<'a>(
+    playlist: &str,
+    dest_index: &mut DestIndex,
+    files: impl Iterator<Item = (Arc<str>, &'a MediaFileInfo)>,
+    options: &TransferOptions,
+    error_tracker: &mut Option<&mut ErrorTracker>,
+    progress_context: &mut ProgressContext,
+) -> Result<(usize, Vec<Arc<str>>)> {
+    let total_files = progress_context.total_media_files;
+    let mut n_files = 0;
+    let mut successful_files = Vec::new();
+    let files: Vec<_> = files
+        .map(|(file, media_file)| {
+            let sync_state = progress_context.sync_state.as_ref();
+            let skip = skip_reason(media_file, dest_index, options, sync_state);
+            (file, media_file, skip)
+        })
+        .collect();
+    let media_files: Vec<_> = files
+        .iter()
+        .filter(|(_, _, skip)| skip.is_none())
+        .map(|(_, media_file, _)| *media_file)
+        .collect();
+    let mut copied_ahead = CopiedAhead::new(media_files, options);
+
+    for (file, media_file, skip) in files {
+        if let Some(progress) = &mut progress_context.progress {
+            progress.start_file(&media_file.file.to_string_lossy());
+        }
+        let dest_path = media_file.dest_path(dest_index.root());
+        progress_context.dest_media_files.push(dest_path);
+
+        // A media file unchanged since synced is not copied again, but
+        // its sidecar files still are
+        let result = match skip {
+            Some(reason) => {
+                record_skip(
+                    progress_context,
+                    playlist,
+                    media_file,
+                    dest_index.root(),
+                    reason,
+                );
+                copy_sidecar_files(
+                    media_file,
+                    dest_index,
+                    options,
+                    error_tracker,
+                    progress_context,
+                )
+                .map(|copied| (copied, true))
+            }
+            None => copy_single_media_file(
+                playlist,
+                media_file,
+                dest_index,
+                options,
+                &mut copied_ahead,
+                error_tracker,
+                progress_context,
+            ),
+        };
+
+        if let Some(progress) = &mut progress_context.progress {
+            progress.finish_file(media_file.size.unwrap_or(0));
+        }
+
+        // We'll update current_file_num only if the copy is successful
+        match result {
+            Ok((copied, success)) => {
+                n_files += copied;
+                if success {
+                    // Increment the global success counter only for copied files
+                    match skip {
+                        Some(_) => progress_context.unchanged_media_files += 1,
+                        None => progress_context.successful_media_files += 1,
+                    }
+
+                    // Print message with updated counter after successful copy
+                    let src_file = media_file.src_path();
+                    let dest_file = media_file.dest_path(dest_index.root());
+
+                    progress_context.logger.log_with_counters(
+                        match skip {
+                            Some(_) => "Skip unchanged track \"{}\" at \"{}\"",
+                            None => "Copy track \"{}\" to \"{}\"",
+                        },
+                        &[&src_file.to_string_lossy(), &dest_file.to_string_lossy()],
+                        Some(progress_context.processed_media_files()),
+                        total_files,
+                        Some("media"),
+                    );
+
+                    // Print messages for the sidecar files too
+                    log_sidecar_files(media_file, dest_index.root(), options, progress_context);
+
+                    successful_files.push(file);
+                }
+                // Note: We don't increment the counter for failed files
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok((n_files, successful_files))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_buffer_size() {
+        assert_eq!(parse_buffer_size("65536"), Ok(64 * 1024));
+        assert_eq!(parse_buffer_size("256k"), Ok(256 * 1024));
+        assert_eq!(parse_buffer_size("4M"), Ok(4 * 1024 * 1024));
+        assert!(parse_buffer_size("1K").is_err());
+        assert!(parse_buffer_size("1G").is_err());
+        assert!(parse_buffer_size("512M").is_err());
+        assert!(parse_buffer_size("").is_err());
+        assert!(parse_buffer_size("4KK").is_err());
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::file_hash::{hash_file, FileHash, HashAlgorithm};
+use crate::file_utils::same_contents;
+use crate::media_file_info::MediaFileInfo;
+use crate::media_set::MediaKey;
+
+/// Media files whose contents are identical to a media file referenced
+/// earlier, such as the same rip in two album folders
+///
+/// Only the first of identical media files is copied; playlist entries
+/// referring to the others are pointed at its destination file instead.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Duplicates {
+    #[cfg_attr(feature = "serde", serde(with = "super::serialize::pairs"))]
+    originals: HashMap<MediaKey, MediaKey>, // Duplicate -> copied original
+}
+
+impl Duplicates {
+    /// Find the duplicates among `keys`, given in the order they are
+    /// copied
+    ///
+    /// Only files of equal size are hashed with `algorithm`, and files
+    /// with equal hashes are compared byte by byte before being treated
+    /// as duplicates.  CRC-32 checksums are recorded in `media`.  Files
+    /// that cannot be read are never treated as duplicates.
+    pub fn find<'a>(
+        keys: impl Iterator<Item = &'a MediaKey>,
+        media: &mut HashMap<MediaKey, MediaFileInfo>,
+        algorithm: HashAlgorithm,
+    ) -> Self {
+        let keys: Vec<&MediaKey> = keys.collect();
+
+        let mut size_count: HashMap<u64, usize> = HashMap::new();
+        for key in &keys {
+            if let Some(size) = media[*key].size {
+                *size_count.entry(size).or_default() += 1;
+            }
+        }
+
+        let mut originals = HashMap::new();
+        let mut candidates: HashMap<(u64, FileHash), Vec<&MediaKey>> = HashMap::new();
+
+        for key in keys {
+            let info = media.get_mut(key).expect("planned media file");
+            let Some(size) = info.size.filter(|size| size_count[size] > 1) else {
+                continue;
+            };
+            let Ok(hash) = hash_file(&info.src_path(), algorithm) else {
+                continue;
+            };
+            info.hash = hash.crc32();
+            let src_path = info.src_path();
+
+            let same = candidates.entry((size, hash)).or_default();
+            let original = same.iter().find(|original| {
+                same_contents(&media[**original].src_path(), &src_path).unwrap_or(false)
+            });
+
+            match original {
+                Some(original) => {
+                    originals.insert(key.clone(), (*original).clone());
+                }
+                None => same.push(key),
+            }
+        }
+
+        Self { originals }
+    }
+
+    /// Number of duplicates, which are not copied
+    pub fn len(&self) -> usize {
+        self.originals.len()
+    }
+
+    /// Whether no media files are duplicates
+    pub fn is_empty(&self) -> bool {
+        self.originals.is_empty()
+    }
+
+    /// Original the media file duplicates, if it does
+    pub fn original(&self, src_basedir: &Arc<str>, file: &Arc<str>) -> Option<&MediaKey> {
+        self.originals
+            .get(&(Arc::clone(src_basedir), Arc::clone(file)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_find_duplicates() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root: Arc<str> = Arc::from(temp_dir.path().to_string_lossy().as_ref());
+        fs::write(temp_dir.path().join("a.flac"), "same content")?;
+        fs::write(temp_dir.path().join("b.flac"), "same content")?;
+        fs::write(temp_dir.path().join("c.flac"), "other conten")?;
+        fs::write(temp_dir.path().join("d.flac"), "unique")?;
+
+        let keys: Vec<MediaKey> = ["a.flac", "b.flac", "c.flac", "d.flac", "e.flac"]
+            .into_iter()
+            .map(|file| (Arc::clone(&root), Arc::from(file)))
+            .collect();
+        let mut media: HashMap<_, _> = keys
+            .iter()
+            .map(|key| {
+                let info = MediaFileInfo::new(&*key.0, &*key.1).with_metadata();
+                (key.clone(), info)
+            })
+            .collect();
+
+        let duplicates = Duplicates::find(keys.iter(), &mut media, HashAlgorithm::Crc32);
+
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(
+            duplicates.original(&root, &Arc::from("b.flac")),
+            Some(&keys[0])
+        );
+        assert_eq!(duplicates.original(&root, &Arc::from("a.flac")), None);
+        assert!(media[&keys[2]].hash.is_some());
+        assert_eq!(media[&keys[3]].hash, None);
+
+        let mut media: HashMap<_, _> = keys
+            .iter()
+            .map(|key| {
+                (
+                    key.clone(),
+                    MediaFileInfo::new(&*key.0, &*key.1).with_metadata(),
+                )
+            })
+            .collect();
+        let duplicates = Duplicates::find(keys.iter(), &mut media, HashAlgorithm::Blake3);
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(media[&keys[2]].hash, None);
+
+        Ok(())
+    }
+}
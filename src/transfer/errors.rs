@@ -0,0 +1,92 @@
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error_file::{ErrorEntry, ErrorFileWriter, RunTotals};
+
+/// Struct to track failed files
+///
+/// Each failure is appended to the error file and flushed as soon as it
+/// is recorded, so the list survives a crash or power loss part way
+/// through the operation.  Entries appear in operation order.
+#[derive(Debug)]
+pub struct ErrorTracker {
+    writer: ErrorFileWriter<File>,  // Error file to stream failures into
+    write_error: Option<io::Error>, // First error encountered while streaming
+}
+
+impl ErrorTracker {
+    /// Create a tracker that streams failures into the file at `path`
+    pub fn create(path: &str) -> Result<Self, io::Error> {
+        Ok(Self {
+            writer: ErrorFileWriter::create(Path::new(path))?,
+            write_error: None,
+        })
+    }
+
+    /// Record the identifier of the run, written after the header if
+    /// given before any failure
+    pub fn set_run_id(&mut self, run_id: &str) {
+        if self.write_error.is_some() {
+            return;
+        }
+
+        if let Err(e) = self.writer.set_run_id(run_id) {
+            self.write_error = Some(e);
+        }
+    }
+
+    /// Record the totals of the original run, written after the header
+    /// if given before any failure
+    pub fn set_totals(&mut self, totals: RunTotals) {
+        if self.write_error.is_some() {
+            return;
+        }
+
+        if let Err(e) = self.writer.set_totals(totals) {
+            self.write_error = Some(e);
+        }
+    }
+
+    pub fn add_failed_playlist(&mut self, playlist: String) {
+        self.record(ErrorEntry::Playlist(PathBuf::from(playlist)));
+    }
+
+    pub fn add_failed_media_file(&mut self, src_basedir: String, file: String) {
+        self.record(ErrorEntry::Media(Path::new(&src_basedir).join(file)));
+    }
+
+    pub fn add_failed_lyrics_file(&mut self, src_basedir: String, file: String) {
+        self.record(ErrorEntry::Lyrics(Path::new(&src_basedir).join(file)));
+    }
+
+    /// Record a sidecar file, such as a lyrics file, that failed to copy
+    /// along with the copied media file `file`
+    pub fn add_failed_sidecar(&mut self, src_basedir: String, file: String, sidecar: String) {
+        let src_basedir = Path::new(&src_basedir);
+        self.record(ErrorEntry::Sidecar {
+            media: src_basedir.join(file),
+            sidecar: src_basedir.join(sidecar),
+        });
+    }
+
+    /// Append a failure to the error file immediately
+    fn record(&mut self, entry: ErrorEntry) {
+        // Keep the first error and stop streaming; it is reported by finish()
+        if self.write_error.is_some() {
+            return;
+        }
+
+        if let Err(e) = self.writer.write_entry(&entry) {
+            self.write_error = Some(e);
+        }
+    }
+
+    /// Complete the error file, reporting any error deferred while streaming
+    pub fn finish(mut self) -> Result<(), io::Error> {
+        match self.write_error.take() {
+            Some(e) => Err(e),
+            None => self.writer.into_inner().sync_all(),
+        }
+    }
+}
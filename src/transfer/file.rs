@@ -0,0 +1,322 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+use crate::fault_injection::{inject, FaultPoint};
+use crate::logger::Logger;
+use crate::media_set::{expand_directory_entry, make_entry_relative};
+use crate::playlist_encoding::PlaylistEncoding;
+use crate::playlist_scanner::{decode_file_uri, parse_extinf};
+use crate::track_path::TrackPath;
+use crate::xspf::{is_xspf_file, XspfPlaylist};
+
+use super::backup::back_up_file;
+use super::names::{check_windows_names, dest_name};
+use super::plan::{entries_dir, is_too_large};
+use super::TransferOptions;
+
+/// Copy a playlist file to the destination
+///
+/// Absolute entries are made relative to the directory of the playlist,
+/// or the directory given by `--relative-to`, as the playlist is on the
+/// destination.  Entries found in `renames`
+/// (with forward slashes) are replaced by the entries they map to.
+/// `#EXTINF` lines and other directives, the byte order mark and CRLF
+/// line endings are kept as they are, unless with `strip_extinf`
+/// comment and directive lines, blank lines and the byte order mark are
+/// removed so that only the entries remain.  The playlist is written in
+/// the encoding selected by `dest_encoding`, under a transliterated name
+/// with `transliterate`.  With `expand_dirs`, entries naming
+/// directories are replaced by the media files found in them.  The
+/// locations of the tracks of an XSPF playlist are rewritten the same
+/// way, and the playlist is written as M3U8 with `xspf_to_m3u8`.  A
+/// destination playlist already holding the same entries is left
+/// untouched, keeping its modification time, and one holding different
+/// entries is moved to its backup first with `--backup`.
+///
+/// Returns a tuple of (path of the destination playlist, whether it was
+/// up to date).
+fn copy_playlist_file(
+    playlist: &str,
+    dest_basedir: &str,
+    renames: &HashMap<String, String>,
+    options: &TransferOptions,
+    current_playlist_num: Option<usize>,
+    total_playlists: Option<usize>,
+    logger: &dyn Logger,
+) -> Result<(PathBuf, bool)> {
+    let playlist_path = Path::new(playlist);
+    let dest_dir = PathBuf::from(dest_basedir);
+
+    if !dest_dir.exists() {
+        fs::create_dir_all(&dest_dir)
+            .with_context(|| format!("Failed to create directory: {}", dest_dir.display()))?;
+    }
+
+    let playlist_filename = playlist_path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid playlist filename"))?;
+
+    let dest_filename = dest_name(&playlist_filename.to_string_lossy(), options);
+    let dest_playlist = if dest_filename != playlist_filename.to_string_lossy() {
+        dest_dir.join(dest_filename)
+    } else {
+        dest_dir.join(playlist_filename)
+    };
+    check_windows_names(Path::new(playlist_filename), options)?;
+
+    // Check if the playlist contains backslashes
+    let playlist_content = fs::read_to_string(playlist)
+        .with_context(|| format!("Failed to read playlist: {}", playlist))?;
+
+    let playlist_dir = entries_dir(playlist_path, options);
+    let is_excluded = |line: &str| {
+        let entry = TrackPath::new(line).into_string();
+        let non_audio = options.drop_non_audio
+            && !entry.is_empty()
+            && options
+                .audio_extensions
+                .as_ref()
+                .is_some_and(|allowlist| !allowlist.allows(&entry));
+        non_audio
+            || (!options.exclusions.is_empty()
+                && options.exclusions.is_excluded(&playlist_dir, &entry))
+            || is_too_large(&playlist_dir, &entry, options)
+    };
+    let has_backslashes = playlist_content
+        .lines()
+        .any(|line| !line.starts_with('#') && line.contains('\\'));
+    let has_absolute_entries = playlist_content
+        .lines()
+        .any(|line| {
+            !line.starts_with('#')
+                && (Path::new(line).is_absolute() || decode_file_uri(line).is_some())
+        });
+    let has_renames = playlist_content
+        .lines()
+        .any(|line| !line.starts_with('#') && renames.contains_key(TrackPath::new(line).as_str()));
+    let has_exclusions = playlist_content
+        .lines()
+        .any(|line| !line.starts_with('#') && is_excluded(line));
+    let expanded_entries = |entry: &str| match options.expand_dirs {
+        true => expand_directory_entry(&playlist_dir, entry).map_err(|e| {
+            anyhow::anyhow!("Failed to expand directory {} in playlist {}: {}", entry, playlist, e)
+        }),
+        false => Ok(None),
+    };
+    // Entries of the destination playlist replacing an entry, and whether
+    // it names a directory, which is expanded before its media files are
+    // excluded
+    let dest_entries = |line: &str| -> Result<(Vec<String>, bool)> {
+        let entry = TrackPath::new(line).into_string();
+        let entry = make_entry_relative(&playlist_dir, &entry)
+            .map_err(|e| anyhow::anyhow!("Invalid entry in playlist {}: {}", playlist, e))?;
+        let expanded = expanded_entries(&entry)?;
+        let is_directory = expanded.is_some();
+        let entries = expanded
+            .unwrap_or_else(|| vec![entry])
+            .into_iter()
+            .filter(|entry| !is_excluded(entry))
+            .map(|entry| renames.get(&entry).cloned().unwrap_or(entry))
+            .collect();
+        Ok((entries, is_directory))
+    };
+
+    if is_xspf_file(playlist_path) {
+        let resolve = |entry: &str| dest_entries(entry).map(|(entries, _)| entries);
+        let (dest_playlist, content, encoding) =
+            convert_xspf(&playlist_content, dest_playlist, options, resolve)?;
+        if is_up_to_date(&dest_playlist, &content, encoding) {
+            log_up_to_date(playlist, &dest_playlist, current_playlist_num, total_playlists, logger);
+            return Ok((dest_playlist, true));
+        }
+
+        let content = encoding
+            .encode(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to encode playlist {}: {}", playlist, e))?;
+        back_up_file(&dest_playlist, &dest_dir, options, logger)?;
+        inject(FaultPoint::PlaylistWrite)
+            .and_then(|_| fs::write(&dest_playlist, content))
+            .with_context(|| format!("Failed to write playlist: {}", dest_playlist.display()))?;
+        return Ok((dest_playlist, false));
+    }
+
+    let has_directories = options.expand_dirs
+        && playlist_content.lines().any(|line| {
+            !line.starts_with('#')
+                && !line.is_empty()
+                && playlist_dir.join(TrackPath::new(line)).is_dir()
+        });
+
+    let strip_directives = options.strip_extinf;
+    let reencode = options.dest_encoding != PlaylistEncoding::Utf8;
+
+    if has_backslashes
+        || has_absolute_entries
+        || has_renames
+        || has_exclusions
+        || has_directories
+        || strip_directives
+        || reencode
+    {
+        // Replace backslashes with forward slashes, absolute and file URI entries,
+        // renamed entries and directories, dropping excluded and non-audio entries with their
+        // #EXTINF lines, in the line endings of the source playlist
+        let line_ending = match playlist_content.contains("\r\n") {
+            true => "\r\n",
+            false => "\n",
+        };
+        let mut lines: Vec<String> = Vec::new();
+        for line in playlist_content.lines() {
+            let line = match strip_directives {
+                true => line.trim_start_matches('\u{feff}'),
+                false => line,
+            };
+            if strip_directives && (line.starts_with('#') || line.is_empty()) {
+                continue;
+            }
+            if line.starts_with('#') {
+                lines.push(line.to_string());
+                continue;
+            }
+            let (entries, is_directory) = dest_entries(line)?;
+            if (is_directory || entries.is_empty())
+                && lines.last().is_some_and(|last| parse_extinf(last).is_some())
+            {
+                lines.pop();
+            }
+            lines.extend(entries);
+        }
+        let modified_content = lines.join(line_ending);
+        if is_up_to_date(&dest_playlist, &modified_content, options.dest_encoding) {
+            log_up_to_date(playlist, &dest_playlist, current_playlist_num, total_playlists, logger);
+            return Ok((dest_playlist, true));
+        }
+
+        let modified_content = options
+            .dest_encoding
+            .encode(&modified_content)
+            .map_err(|e| anyhow::anyhow!("Failed to encode playlist {}: {}", playlist, e))?;
+
+        back_up_file(&dest_playlist, &dest_dir, options, logger)?;
+        inject(FaultPoint::PlaylistWrite)
+            .and_then(|_| fs::write(&dest_playlist, modified_content))
+            .with_context(|| format!("Failed to write playlist: {}", dest_playlist.display()))?;
+    } else if is_up_to_date(&dest_playlist, &playlist_content, PlaylistEncoding::Utf8) {
+        log_up_to_date(playlist, &dest_playlist, current_playlist_num, total_playlists, logger);
+        return Ok((dest_playlist, true));
+    } else {
+        logger.log_with_counters(
+            "Copy playlist \"{}\" to \"{}\"",
+            &[playlist, &format!("{}/", dest_basedir)],
+            current_playlist_num,
+            total_playlists,
+            None,
+        );
+
+        back_up_file(&dest_playlist, &dest_dir, options, logger)?;
+        inject(FaultPoint::PlaylistWrite)
+            .and_then(|_| fs::copy(playlist, &dest_playlist))
+            .with_context(|| {
+            format!("Failed to copy {} to {}", playlist, dest_playlist.display())
+        })?;
+    }
+
+    Ok((dest_playlist, false))
+}
+
+/// Convert the contents of an XSPF playlist for `dest_playlist`,
+/// rewriting the locations of its tracks to the entries `resolve` maps
+/// them to
+///
+/// With `xspf_to_m3u8`, the playlist is converted to M3U8, named with
+/// the `.m3u8` extension and encoded in `dest_encoding`, with only its
+/// entries with `strip_extinf`.  Otherwise it stays XSPF, in UTF-8.
+/// Returns the path of the destination playlist, its contents and their
+/// encoding.
+fn convert_xspf(
+    content: &str,
+    dest_playlist: PathBuf,
+    options: &TransferOptions,
+    resolve: impl FnMut(&str) -> Result<Vec<String>>,
+) -> Result<(PathBuf, String, PlaylistEncoding)> {
+    let mut xspf = XspfPlaylist::parse(content);
+    xspf.rewrite_locations(resolve)?;
+    if !options.xspf_to_m3u8 {
+        return Ok((dest_playlist, xspf.contents(), PlaylistEncoding::Utf8));
+    }
+
+    let content = match options.strip_extinf {
+        true => xspf.entries().collect::<Vec<_>>().join("\n"),
+        false => xspf.to_m3u8(),
+    };
+    Ok((
+        dest_playlist.with_extension("m3u8"),
+        content,
+        options.dest_encoding,
+    ))
+}
+
+/// Normalise the contents of a playlist for comparison, ignoring line
+/// endings and trailing newlines
+fn normalise(content: &str) -> String {
+    let content = content.lines().collect::<Vec<_>>().join("\n");
+    content.trim_end_matches('\n').to_string()
+}
+
+/// Whether the playlist at `dest_playlist` already holds `content` once
+/// decoded from `encoding`, apart from its byte order mark, line endings
+/// and trailing newlines
+fn is_up_to_date(dest_playlist: &Path, content: &str, encoding: PlaylistEncoding) -> bool {
+    match fs::read(dest_playlist) {
+        Ok(bytes) => normalise(&encoding.decode(&bytes)) == normalise(content),
+        Err(_) => false,
+    }
+}
+
+/// Log a playlist left untouched as its destination playlist is up to
+/// date
+fn log_up_to_date(
+    playlist: &str,
+    dest_playlist: &Path,
+    current_playlist_num: Option<usize>,
+    total_playlists: Option<usize>,
+    logger: &dyn Logger,
+) {
+    logger.log_with_counters(
+        "Playlist \"{}\" is up to date at \"{}\"",
+        &[playlist, &dest_playlist.to_string_lossy()],
+        current_playlist_num,
+        total_playlists,
+        None,
+    );
+}
+
+/// Process a playlist file by copying it to the destination
+/// Returns a tuple of (path of the destination playlist, whether it was
+/// up to date)
+pub fn process_playlist(
+    playlist: &str,
+    dest_basedir: &str,
+    renames: &HashMap<String, String>,
+    options: &TransferOptions,
+    current_playlist_num: Option<usize>,
+    total_playlists: Option<usize>,
+    logger: &dyn Logger,
+) -> Result<(PathBuf, bool)> {
+    logger.log_formatted("Processing playlist \"{}\"", &[playlist]);
+
+    // Copy the playlist file
+    copy_playlist_file(
+        playlist,
+        dest_basedir,
+        renames,
+        options,
+        current_playlist_num,
+        total_playlists,
+        logger,
+    )
+}
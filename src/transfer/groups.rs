@@ -0,0 +1,165 @@
+//! Playlists copied with the options the device profile sets for them
+//!
+//! The playlists are split into groups by the options the `playlist`
+//! lines of the device profile set for them, and each group is copied
+//! in turn with those options merged into the command-line options, so
+//! that one run can copy different playlists differently.  File lists
+//! are not playlists, so they are copied with the playlists no line
+//! applies to.
+
+use std::mem;
+use std::path::Path;
+
+use crate::playlist_options::{options_for, PlaylistOptions};
+
+use super::spillover::Spillover;
+use super::TransferOptions;
+
+/// Playlists the device profile sets the same options for
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PlaylistGroup {
+    pub options: PlaylistOptions,
+    pub playlists: Vec<String>,
+}
+
+/// Command-line options replaced by the options of a group, restored
+/// once the group is copied
+pub struct SavedOptions {
+    copy_lyrics: bool,
+    max_file_size: Option<u64>,
+    lists: Vec<String>,
+}
+
+impl PlaylistGroup {
+    /// Merge the options of the group into `options`, returning the
+    /// options replaced
+    ///
+    /// The file lists are left to the group of the playlists without
+    /// options of their own.
+    pub fn apply(&self, options: &mut TransferOptions) -> SavedOptions {
+        let lists = match self.options == PlaylistOptions::default() {
+            true => options.lists.clone(),
+            false => Vec::new(),
+        };
+        let copy_lyrics = self.options.lyrics.unwrap_or(options.copy_lyrics);
+        let max_file_size = self.options.max_file_size.or(options.max_file_size);
+        SavedOptions {
+            copy_lyrics: mem::replace(&mut options.copy_lyrics, copy_lyrics),
+            max_file_size: mem::replace(&mut options.max_file_size, max_file_size),
+            lists: mem::replace(&mut options.lists, lists),
+        }
+    }
+
+    /// Directory of the destination `dest_dir` the group is copied into
+    pub fn dest_dir(&self, dest_dir: &str) -> String {
+        match &self.options.dest_subdir {
+            Some(subdir) => Path::new(dest_dir)
+                .join(subdir)
+                .to_string_lossy()
+                .to_string(),
+            None => dest_dir.to_string(),
+        }
+    }
+
+    /// Further destinations of `spillover` the group is copied into
+    pub fn spillover(&self, spillover: &Spillover) -> Spillover {
+        Spillover {
+            dests: spillover
+                .dests
+                .iter()
+                .map(|dest| self.dest_dir(dest))
+                .collect(),
+            min_free: spillover.min_free,
+            max_fill: spillover.max_fill,
+        }
+    }
+}
+
+impl SavedOptions {
+    /// Put the replaced command-line options back into `options`
+    pub fn restore(self, options: &mut TransferOptions) {
+        options.copy_lyrics = self.copy_lyrics;
+        options.max_file_size = self.max_file_size;
+        options.lists = self.lists;
+    }
+}
+
+/// Group the playlists by the options the device profile sets for them,
+/// in the order of the first playlist of each group
+///
+/// A group without options of its own is added for the file lists if no
+/// playlist is in it, and for a run without playlists.
+pub fn group_playlists(playlists: &[String], options: &TransferOptions) -> Vec<PlaylistGroup> {
+    let mut groups: Vec<PlaylistGroup> = Vec::new();
+    for playlist in playlists {
+        let playlist_options = options_for(&options.playlist_options, Path::new(playlist));
+        match groups
+            .iter_mut()
+            .find(|group| group.options == playlist_options)
+        {
+            Some(group) => group.playlists.push(playlist.clone()),
+            None => groups.push(PlaylistGroup {
+                options: playlist_options,
+                playlists: vec![playlist.clone()],
+            }),
+        }
+    }
+
+    let has_default = groups
+        .iter()
+        .any(|group| group.options == PlaylistOptions::default());
+    if groups.is_empty() || (!options.lists.is_empty() && !has_default) {
+        groups.push(PlaylistGroup::default());
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::playlist_options::PlaylistMatch;
+
+    #[test]
+    fn test_group_playlists() {
+        let mut options = TransferOptions {
+            copy_lyrics: true,
+            max_file_size: Some(4096),
+            lists: vec!["files.txt".to_string()],
+            playlist_options: vec![PlaylistMatch::parse(
+                "Audiobooks*.m3u8 lyrics=false dest-subdir=BOOKS",
+            )
+            .unwrap()],
+            ..TransferOptions::default()
+        };
+        let playlists: Vec<String> = ["Audiobooks 1.m3u8", "rock.m3u8", "Audiobooks 2.m3u8"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+
+        let groups = group_playlists(&playlists, &options);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            groups[0].playlists,
+            ["Audiobooks 1.m3u8", "Audiobooks 2.m3u8"]
+        );
+        assert_eq!(groups[0].dest_dir("/dest"), "/dest/BOOKS");
+        assert_eq!(groups[1].playlists, ["rock.m3u8"]);
+        assert_eq!(groups[1].dest_dir("/dest"), "/dest");
+
+        // Options not set by the group are taken from the command line,
+        // and the file lists go with the playlists without options
+        let saved = groups[0].apply(&mut options);
+        assert!(!options.copy_lyrics);
+        assert_eq!(options.max_file_size, Some(4096));
+        assert!(options.lists.is_empty());
+        saved.restore(&mut options);
+        assert!(options.copy_lyrics);
+        assert_eq!(options.lists, ["files.txt"]);
+
+        // The file lists get a group of their own without such playlists
+        let groups = group_playlists(&playlists[..1], &options);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1], PlaylistGroup::default());
+        assert_eq!(group_playlists(&[], &options), [PlaylistGroup::default()]);
+    }
+}
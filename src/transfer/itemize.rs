@@ -0,0 +1,331 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::Result;
+
+use crate::dest_index::{DestIndex, FileStatus};
+use crate::error_file::RunTotals;
+use crate::logger::FailureGroups;
+use crate::media_file_info::MediaFileInfo;
+use crate::media_set::MediaKey;
+use crate::path_interner::PathInterner;
+
+use super::actions::{plan_put, Action, PutPlan};
+use super::artwork::itemize_artwork;
+use super::sidecar::{present_sidecar_files, SidecarKind};
+use super::summary::{CopySummary, PlaylistSummary};
+use super::{ErrorTracker, TransferOptions};
+
+/// Change made to a destination file by copying a source file over it,
+/// printed as the itemized changes of `rsync -i`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Change {
+    /// The destination file does not exist
+    New,
+    /// The destination file differs in size or is older than the source
+    Update { size: bool, time: bool },
+    /// The destination file has the size of the source and is not older
+    UpToDate,
+}
+
+impl Change {
+    /// Change made by copying a source file of the given size and
+    /// modification time to `dest_path`, looked up in the destination
+    /// index or on the destination if the index does not know it
+    pub fn of(
+        size: Option<u64>,
+        modified: Option<SystemTime>,
+        dest_path: &Path,
+        dest_index: &DestIndex,
+    ) -> Self {
+        let (dest_size, dest_modified) = match dest_index.file_status(dest_path) {
+            FileStatus::Present(entry) => (entry.len, entry.modified),
+            FileStatus::Absent => return Self::New,
+            FileStatus::Unknown => match fs::metadata(dest_path) {
+                Ok(metadata) => (metadata.len(), metadata.modified().ok()),
+                Err(_) => return Self::New,
+            },
+        };
+
+        let size = size.is_some_and(|size| size != dest_size);
+        let time = matches!((modified, dest_modified), (Some(src), Some(dest)) if src > dest);
+        match size || time {
+            true => Self::Update { size, time },
+            false => Self::UpToDate,
+        }
+    }
+
+    /// Change made by copying the source file at `src_path`
+    pub fn of_file(src_path: &Path, dest_path: &Path, dest_index: &DestIndex) -> Self {
+        let metadata = fs::metadata(src_path).ok();
+        let size = metadata.as_ref().map(|metadata| metadata.len());
+        let modified = metadata.and_then(|metadata| metadata.modified().ok());
+        Self::of(size, modified, dest_path, dest_index)
+    }
+
+    /// Indicator of the change, such as `>f+++++++++` for a new file
+    pub fn indicator(&self) -> String {
+        match self {
+            Self::New => ">f+++++++++".to_string(),
+            Self::Update { size, time } => format!(
+                ">f.{}{}......",
+                if *size { 's' } else { '.' },
+                if *time { 't' } else { '.' }
+            ),
+            Self::UpToDate => ".f         ".to_string(),
+        }
+    }
+}
+
+/// Print the change made to a destination file, given relative to the
+/// destination root
+pub fn print_change(change: Change, dest_file: &Path) {
+    println!("{} {}", change.indicator(), dest_file.display());
+}
+
+/// Print the change to be made to the destination files of a media file
+/// and its sidecar files
+fn itemize_media_file(
+    media_file: &MediaFileInfo,
+    change: Change,
+    dest_index: &DestIndex,
+    options: &TransferOptions,
+) {
+    print_change(change, media_file.dest_file());
+
+    for (sidecar_path, dest_sidecar_file) in present_sidecar_files(media_file, options) {
+        let dest_path = dest_index.root().join(&dest_sidecar_file);
+        let sidecar_change = Change::of_file(&sidecar_path, &dest_path, dest_index);
+        print_change(sidecar_change, &dest_sidecar_file);
+    }
+}
+
+/// Count the sidecar files of a media file the copy would copy into
+/// the summary
+fn count_sidecar_files(
+    media_file: &MediaFileInfo,
+    options: &TransferOptions,
+    summary: &mut CopySummary,
+) {
+    for (sidecar_path, _) in present_sidecar_files(media_file, options) {
+        let (successful, total) = match SidecarKind::of(&sidecar_path, options) {
+            SidecarKind::Lyrics => (
+                &mut summary.successful_lyrics_files,
+                &mut summary.total_lyrics_files,
+            ),
+            SidecarKind::Other => (
+                &mut summary.successful_sidecar_files,
+                &mut summary.total_sidecar_files,
+            ),
+        };
+        *successful += 1;
+        *total += 1;
+    }
+}
+
+/// Walk the playlists of a put plan in the order they would be copied,
+/// itemizing the changes to be made and counting the files the copy
+/// would copy into a summary
+///
+/// Media files missing from the source or renamed to the destination
+/// path of another file are reported and recorded in the error file as
+/// the copy would with `--keep-going`, as are the playlists that cannot
+/// be parsed.
+fn walk_put_plan(
+    put_plan: &PutPlan,
+    options: &TransferOptions,
+    error_tracker_ref: &mut Option<&mut ErrorTracker>,
+) -> CopySummary {
+    let plan = &put_plan.plan;
+    let actions: HashMap<&MediaKey, &Action> = put_plan
+        .actions
+        .iter()
+        .map(|(key, action)| (key, action))
+        .collect();
+    let mut summary = CopySummary {
+        total_playlists: plan
+            .playlists
+            .iter()
+            .filter(|planned| !planned.is_list)
+            .count(),
+        total_media_files: plan.total_media_files,
+        non_audio_entries: plan
+            .playlists
+            .iter()
+            .map(|planned| planned.non_audio_entries)
+            .sum(),
+        ..CopySummary::default()
+    };
+    if let Some(tracker) = error_tracker_ref {
+        tracker.set_totals(RunTotals {
+            playlists: summary.total_playlists,
+            media_files: summary.total_media_files,
+        });
+    }
+
+    let mut missing_files = FailureGroups::new("missing");
+    let mut seen: HashSet<MediaKey> = HashSet::new();
+    for planned in &plan.playlists {
+        let (src_basedir, files) = match &planned.media {
+            Ok(media) => media,
+            Err(e) => {
+                eprintln!("Error processing playlist {}: {}", planned.playlist, e);
+                match error_tracker_ref {
+                    Some(tracker) if !planned.is_list => {
+                        tracker.add_failed_playlist(planned.playlist.clone())
+                    }
+                    _ => {}
+                }
+                summary
+                    .playlists
+                    .push(PlaylistSummary::failed(&planned.playlist));
+                continue;
+            }
+        };
+        summary.successful_playlists += usize::from(!planned.is_list);
+
+        // Media files named by earlier entries, duplicates, conflicting
+        // and unchanged media files are skipped
+        let (mut attempted, mut copied, mut duplicates) = (0, 0, 0);
+        for file in files {
+            let key = (Arc::clone(src_basedir), Arc::clone(file));
+            if !seen.insert(key.clone()) {
+                duplicates += 1;
+                continue;
+            }
+            let media_file = &plan.media[&key];
+            if !matches!(
+                actions[&key],
+                Action::Duplicate(_) | Action::Conflict(_) | Action::Collision
+            ) {
+                let dest_path = media_file.dest_path(put_plan.dest_index.root());
+                summary.dest_media_files.push(dest_path);
+            }
+            let failed = match actions[&key] {
+                Action::Copy(change) => {
+                    itemize_media_file(media_file, *change, &put_plan.dest_index, options);
+                    false
+                }
+                Action::Unchanged => {
+                    summary.unchanged_media_files += 1;
+                    count_sidecar_files(media_file, options, &mut summary);
+                    continue;
+                }
+                Action::Duplicate(_) | Action::Conflict(_) => continue,
+                Action::Collision => {
+                    eprintln!(
+                        "Error: Skipping track \"{}\" renamed to the destination path of another file",
+                        media_file.src_path().display()
+                    );
+                    true
+                }
+                Action::Missing if options.list_missing => {
+                    let src_file = media_file.src_path();
+                    eprintln!("Error: Media file not found: {}", src_file.display());
+                    true
+                }
+                Action::Missing => {
+                    missing_files.add(&media_file.src_path());
+                    true
+                }
+            };
+            attempted += 1;
+            if failed {
+                if let Some(tracker) = error_tracker_ref {
+                    tracker.add_failed_media_file(src_basedir.to_string(), file.to_string());
+                }
+                continue;
+            }
+            copied += 1;
+            count_sidecar_files(media_file, options, &mut summary);
+        }
+        summary.successful_media_files += copied;
+        summary.playlists.push(
+            PlaylistSummary::copied(&planned.playlist, files.len(), attempted, copied)
+                .with_duplicates(duplicates),
+        );
+    }
+
+    for line in missing_files.lines() {
+        eprintln!("Error: {}", line);
+    }
+    if !options.artwork.is_empty() {
+        itemize_artwork(plan, &put_plan.dest_index, options, &mut summary);
+    }
+    summary.total_media_files -= summary.unchanged_media_files;
+    summary
+}
+
+/// Plan the copy of the playlists and print the changes it would make
+/// to the destination, in the order the media files would be copied,
+/// leaving the destination untouched
+///
+/// Returns the summary the copy would print, with the failures it would
+/// meet recorded in the error file, if any.
+pub fn process_dry_run(
+    playlists: &[String],
+    dest_dir: &str,
+    options: &TransferOptions,
+    error_tracker_ref: &mut Option<&mut ErrorTracker>,
+) -> Result<CopySummary> {
+    let put_plan = plan_put(playlists, dest_dir, options, &mut PathInterner::new())?;
+    let summary = walk_put_plan(&put_plan, options, error_tracker_ref);
+
+    let counts = put_plan.counts();
+    let skipped = match (counts.skipped + counts.missing, counts.collisions) {
+        (0, 0) => String::new(),
+        (_, 0) => format!("; {} skipped, {} missing", counts.skipped, counts.missing),
+        (_, collisions) => format!(
+            "; {} skipped, {} missing, {} colliding",
+            counts.skipped, counts.missing, collisions
+        ),
+    };
+    println!(
+        "Dry run: {} new, {} updated, {} up-to-date media files{}",
+        counts.new, counts.updated, counts.up_to_date, skipped
+    );
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_change() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+        fs::write(root.join("present.flac"), "content")?;
+        let modified = fs::metadata(root.join("present.flac"))?.modified()?;
+        let dest_index = DestIndex::scan(root, [""])?;
+
+        let present = root.join("present.flac");
+        let change = |size, modified| Change::of(Some(size), Some(modified), &present, &dest_index);
+        assert_eq!(change(7, modified), Change::UpToDate);
+        assert_eq!(
+            change(8, modified),
+            Change::Update {
+                size: true,
+                time: false
+            }
+        );
+        assert_eq!(
+            change(7, modified + Duration::from_secs(60)).indicator(),
+            ">f..t......"
+        );
+
+        let absent = Change::of(Some(7), None, &root.join("absent.flac"), &dest_index);
+        assert_eq!(absent, Change::New);
+        assert_eq!(absent.indicator(), ">f+++++++++");
+        assert_eq!(Change::UpToDate.indicator().len(), 11);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,581 @@
+//! Engine copying playlists and their media files to a destination
+//!
+//! This is the engine of `plm-put-playlist`, for other tools to copy
+//! playlists with.  A [`PlaylistTransfer`] is built for a destination
+//! with the [`TransferOptions`] to copy with, and optionally a logger,
+//! an [`ErrorTracker`] streaming failures into an error file and a
+//! [`CopyReport`] of the media files processed:
+//!
+//! ```no_run
+//! use playlist_manager::transfer::{PlaylistTransfer, TransferOptions};
+//!
+//! let options = TransferOptions {
+//!     copy_lyrics: true,
+//!     ..TransferOptions::default()
+//! };
+//! let mut transfer = PlaylistTransfer::new("/media/player").with_options(options);
+//! let summary = transfer.run(&["/home/me/Music/playlist.m3u8".to_string()])?;
+//! println!(
+//!     "({}/{}) media files copied",
+//!     summary.successful_media_files, summary.total_media_files
+//! );
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Copying is split into planning and execution: [`actions::plan_put`]
+//! reads the playlists into a [`actions::PutPlan`] resolving the action
+//! taken for each media file (copy, skip as unchanged, duplicate or
+//! conflicting, or fail as missing) without touching the destination,
+//! and [`normal::execute`] runs the plan.  Dry runs, estimates and
+//! itemized changes are built on the same plan as copies.
+//!
+//! The steps of a transfer are in the submodules: [`plan`] reads the
+//! playlists into a copy plan, [`file::process_playlist`] writes a
+//! playlist to the destination and [`copy::copy_media_files`] copies
+//! its media files.
+
+pub mod actions;
+pub mod album;
+pub mod artwork;
+pub mod backend;
+pub mod backup;
+pub mod conflicts;
+pub mod copy;
+pub mod dedup;
+pub mod errors;
+pub mod file;
+pub mod groups;
+pub mod itemize;
+pub mod name_policy;
+pub mod names;
+pub mod normal;
+pub mod pipeline;
+pub mod plan;
+pub mod read_only;
+pub mod report;
+pub mod retry;
+pub mod serialize;
+pub mod sidecar;
+pub mod spillover;
+pub mod state;
+pub mod summary;
+pub mod validate;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use glob::Pattern;
+
+use crate::dest_index::DestIndex;
+use crate::exclusion_list::ExclusionList;
+use crate::file_hash::HashAlgorithm;
+use crate::logger::{FailureGroups, Logger, NullLogger};
+use crate::media_extensions::ExtensionAllowlist;
+use crate::path_interner::PathInterner;
+use crate::playlist_encoding::PlaylistEncoding;
+use crate::playlist_options::PlaylistMatch;
+use crate::progress_bar::ProgressBar;
+use crate::sync_state::SyncState;
+
+pub use errors::ErrorTracker;
+
+use backend::IoBackend;
+use backup::Backup;
+use conflicts::ConflictPolicy;
+use name_policy::NamePolicy;
+use names::{CollisionStrategy, Layout, LetterCase};
+use report::CopyReport;
+use sidecar::LYRICS_EXTENSION;
+use spillover::Spillover;
+use summary::{CopySummary, PlaylistSummary};
+
+/// Options of a transfer, named after the options of `plm-put-playlist`
+/// setting them
+///
+/// The defaults copy the playlists and their media files as they are,
+/// stopping at the first failure, as `plm-put-playlist` does without
+/// options.
+#[derive(Debug)]
+pub struct TransferOptions {
+    pub copy_lyrics: bool,
+    pub lyrics_extensions: Vec<String>, // Extensions of lyrics files, `lrc` by default
+    pub sidecars: Vec<String>, // Extensions of other sidecar files to copy
+    pub artwork: Vec<Pattern>, // Names of the cover images to copy, none without --artwork
+    pub keep_going: bool,
+    pub verify: bool,
+    pub dedup: bool,
+    pub album_playlists: bool,
+    pub fat_order: bool,
+    pub strip_extinf: bool,
+    pub dest_encoding: PlaylistEncoding,
+    pub transliterate: bool,
+    pub max_depth: Option<usize>,
+    pub max_file_size: Option<u64>, // Media files larger than this are left out
+    pub exclusions: ExclusionList,
+    pub audio_extensions: Option<ExtensionAllowlist>,
+    pub drop_non_audio: bool,
+    pub expand_dirs: bool,
+    pub validate: bool,
+    pub on_conflict: ConflictPolicy,
+    pub on_collision: CollisionStrategy,
+    pub case: LetterCase,
+    pub layout: Layout,
+    pub itemize: bool,
+    pub lists: Vec<String>,
+    pub list_root: Option<String>,
+    pub relative_to: Option<String>, // Directory playlist entries are relative to with --relative-to
+    pub buffer_size: Option<usize>,
+    pub io_backend: IoBackend,
+    pub io_retries: u32, // Retries of a copy failing with a transient error
+    pub jobs: usize,     // Threads copying the media files of a playlist
+    pub sync_state: bool,
+    pub update: bool,   // Skip media files whose destination file is up to date
+    pub run_id: String, // Identifier of the run, for the error file and the summary
+    pub hash: HashAlgorithm,
+    pub backup: Backup,
+    pub xattrs: bool,
+    pub strict: bool,
+    pub list_missing: bool,
+    pub spillover: Option<Spillover>, // Further destinations with --spillover
+    pub rewrite_ext: Vec<(String, String)>, // (from, to) extensions of destination files
+    pub name_policy: NamePolicy,      // Renames of destination names with --name-policy
+    pub xspf_to_m3u8: bool,
+    pub progress: bool,         // Show the progress of copying media files
+    pub source_read_only: bool, // Fail before writing under the source directories
+    pub playlist_options: Vec<PlaylistMatch>, // Options the device profile sets for playlists
+}
+
+impl Default for TransferOptions {
+    fn default() -> Self {
+        Self {
+            copy_lyrics: false,
+            lyrics_extensions: vec![LYRICS_EXTENSION.to_string()],
+            sidecars: Vec::new(),
+            artwork: Vec::new(),
+            keep_going: false,
+            verify: false,
+            dedup: false,
+            album_playlists: false,
+            fat_order: false,
+            strip_extinf: false,
+            dest_encoding: Default::default(),
+            transliterate: false,
+            max_depth: None,
+            max_file_size: None,
+            exclusions: Default::default(),
+            audio_extensions: None,
+            drop_non_audio: false,
+            expand_dirs: false,
+            validate: false,
+            on_conflict: Default::default(),
+            on_collision: Default::default(),
+            case: Default::default(),
+            layout: Default::default(),
+            itemize: false,
+            lists: Vec::new(),
+            list_root: None,
+            relative_to: None,
+            buffer_size: None,
+            io_backend: Default::default(),
+            io_retries: 0,
+            jobs: 1,
+            sync_state: false,
+            update: false,
+            run_id: String::new(),
+            hash: Default::default(),
+            backup: Default::default(),
+            xattrs: false,
+            strict: false,
+            list_missing: false,
+            spillover: None,
+            rewrite_ext: Vec::new(),
+            name_policy: Default::default(),
+            xspf_to_m3u8: false,
+            progress: false,
+            source_read_only: false,
+            playlist_options: Vec::new(),
+        }
+    }
+}
+
+/// Struct to hold interned paths and copied files
+///
+/// Paths are interned so that a media file referenced by many playlists
+/// is stored once and the copied files set only holds shared references.
+pub struct MediaContext {
+    pub(crate) interner: PathInterner,
+    pub(crate) copied_files: HashSet<(Arc<str>, Arc<str>)>, // (src_basedir, file)
+}
+
+impl MediaContext {
+    pub fn new() -> Self {
+        Self {
+            interner: PathInterner::new(),
+            copied_files: HashSet::new(),
+        }
+    }
+}
+
+impl Default for MediaContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Struct to hold progress tracking information
+///
+/// Lyrics files are only found while copying, so they are counted as
+/// they are attempted rather than planned up front.  Playlist entries
+/// left out for their extensions, and media files skipped as unchanged,
+/// are counted separately from the media files copied.  The numbers of
+/// each playlist, the destination playlists written or found up to date
+/// and the destination paths of the media files processed are kept for
+/// the summary, for validation and for the files `plm-sync --delete`
+/// keeps.  Messages are written to the logger of the operation, and
+/// processed media files to the report if one is requested.  The sync
+/// state of the destination is kept with `--sync-state`.
+pub struct ProgressContext {
+    pub(crate) logger: Arc<dyn Logger>,
+    pub(crate) current_playlist_num: Option<usize>,
+    pub(crate) total_playlists: Option<usize>,
+    pub(crate) total_media_files: Option<usize>,
+    pub(crate) successful_media_files: usize,
+    pub(crate) failed_media_files: usize,
+    pub(crate) total_lyrics_files: usize,
+    pub(crate) successful_lyrics_files: usize,
+    pub(crate) total_sidecar_files: usize,
+    pub(crate) successful_sidecar_files: usize,
+    pub(crate) total_artwork_files: usize,
+    pub(crate) successful_artwork_files: usize,
+    pub(crate) non_audio_entries: usize,
+    pub(crate) unchanged_media_files: usize,
+    pub(crate) playlist_summaries: Vec<PlaylistSummary>,
+    pub(crate) written_playlists: Vec<PathBuf>,
+    pub(crate) up_to_date_playlists: usize,
+    pub(crate) dest_media_files: Vec<PathBuf>, // Destination paths of the media files processed
+    pub(crate) report: Option<CopyReport>,
+    pub(crate) sync_state: Option<SyncState>,
+    pub(crate) missing_files: FailureGroups, // Missing media files reported after copying
+    pub(crate) progress: Option<ProgressBar>, // Progress shown with --progress
+}
+
+impl ProgressContext {
+    pub fn new(logger: Arc<dyn Logger>, total_playlists: usize, total_media_files: usize) -> Self {
+        Self {
+            logger,
+            current_playlist_num: None,
+            total_playlists: Some(total_playlists),
+            total_media_files: Some(total_media_files),
+            successful_media_files: 0,
+            failed_media_files: 0,
+            total_lyrics_files: 0,
+            successful_lyrics_files: 0,
+            total_sidecar_files: 0,
+            successful_sidecar_files: 0,
+            total_artwork_files: 0,
+            successful_artwork_files: 0,
+            non_audio_entries: 0,
+            unchanged_media_files: 0,
+            playlist_summaries: Vec::new(),
+            written_playlists: Vec::new(),
+            up_to_date_playlists: 0,
+            dest_media_files: Vec::new(),
+            report: None,
+            sync_state: None,
+            missing_files: FailureGroups::new("missing"),
+            progress: None,
+        }
+    }
+
+    /// Show the progress of copying media files with `--progress`
+    pub(crate) fn show_progress(&mut self, options: &TransferOptions) {
+        if options.progress {
+            self.progress = Some(ProgressBar::stderr());
+        }
+    }
+
+    /// Number of media files processed so far, copied or skipped as
+    /// unchanged
+    pub(crate) fn processed_media_files(&self) -> usize {
+        self.successful_media_files + self.unchanged_media_files
+    }
+
+    /// Set the totals of the progress shown to the media files to copy,
+    /// of `total_bytes` bytes together
+    pub(crate) fn set_progress_totals(&mut self, total_bytes: u64) {
+        let total_files = self.total_media_files.unwrap_or(0);
+        if let Some(progress) = &mut self.progress {
+            progress.set_totals(total_files, total_bytes);
+        }
+    }
+}
+
+/// Filter out files that have already been copied and repeated entries
+/// of the same playlist, each of which is a duplicate reference
+pub(crate) fn filter_already_copied_files(
+    src_basedir: &Arc<str>,
+    files: &[Arc<str>],
+    copied_files: &HashSet<(Arc<str>, Arc<str>)>,
+) -> Vec<Arc<str>> {
+    let mut seen = HashSet::new();
+    files
+        .iter()
+        .filter(|file| !copied_files.contains(&(Arc::clone(src_basedir), Arc::clone(file))))
+        .filter(|file| seen.insert(Arc::clone(file)))
+        .cloned()
+        .collect()
+}
+
+/// Build an index of the destination directories the given media files
+/// (relative to the destination root) will be copied into
+pub(crate) fn scan_destination<'a>(
+    dest_dir: &str,
+    files: impl Iterator<Item = &'a Path>,
+) -> Result<DestIndex> {
+    let rel_dirs: HashSet<&Path> = files
+        .map(|file| file.parent().unwrap_or(Path::new("")))
+        .collect();
+
+    DestIndex::scan(Path::new(dest_dir), rel_dirs)
+        .with_context(|| format!("Failed to scan destination: {}", dest_dir))
+}
+
+/// List the destination directories of more media files into the index,
+/// such as those of a playlist parsed after the index was built
+pub(crate) fn scan_dest_dirs<'a>(
+    dest_index: &mut DestIndex,
+    files: impl Iterator<Item = &'a Path>,
+) -> Result<()> {
+    let rel_dirs: HashSet<&Path> = files
+        .map(|file| file.parent().unwrap_or(Path::new("")))
+        .collect();
+
+    dest_index.scan_dirs(rel_dirs).with_context(|| {
+        format!(
+            "Failed to scan destination: {}",
+            dest_index.root().display()
+        )
+    })
+}
+
+/// Transfer of playlists and their media files to a destination
+///
+/// Built with [`PlaylistTransfer::new`] and the `with_` methods, then
+/// run once with [`run`](Self::run), [`retry`](Self::retry) or
+/// [`dry_run`](Self::dry_run).  Messages go to a [`NullLogger`] unless
+/// a logger is given.
+pub struct PlaylistTransfer<'a> {
+    dest_dir: String,
+    options: TransferOptions,
+    logger: Arc<dyn Logger>,
+    error_tracker: Option<&'a mut ErrorTracker>,
+    report: Option<CopyReport>,
+}
+
+impl<'a> PlaylistTransfer<'a> {
+    /// Transfer into the absolute destination directory `dest_dir` with
+    /// the default options
+    pub fn new(dest_dir: impl Into<String>) -> Self {
+        Self {
+            dest_dir: dest_dir.into(),
+            options: TransferOptions::default(),
+            logger: Arc::new(NullLogger),
+            error_tracker: None,
+            report: None,
+        }
+    }
+
+    /// Copy with `options`
+    pub fn with_options(mut self, options: TransferOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Write messages to `logger`
+    pub fn with_logger(mut self, logger: Arc<dyn Logger>) -> Self {
+        self.logger = logger;
+        self
+    }
+
+    /// Record the files failing to copy into `error_tracker`
+    pub fn with_error_tracker(mut self, error_tracker: &'a mut ErrorTracker) -> Self {
+        self.error_tracker = Some(error_tracker);
+        self
+    }
+
+    /// Record the media files processed into `report`, which is handed
+    /// back in the summary of the run
+    pub fn with_report(mut self, report: CopyReport) -> Self {
+        self.report = Some(report);
+        self
+    }
+
+    /// Options the transfer copies with
+    pub fn options(&self) -> &TransferOptions {
+        &self.options
+    }
+
+    /// Copy the playlists and their media files, each playlist given
+    /// more than once being copied once, into the destination or, with
+    /// `spillover` options, into each destination in turn
+    ///
+    /// The playlists the device profile sets options for are copied in
+    /// groups, each with its options, after one another.
+    pub fn run(&mut self, playlists: &[String]) -> Result<CopySummary> {
+        let playlists = plan::unique_playlists(playlists);
+        let spillover = self.options.spillover.take();
+        let mut report = self.report.take();
+        let mut summary = CopySummary::default();
+        for group in groups::group_playlists(&playlists, &self.options) {
+            let dest_dir = group.dest_dir(&self.dest_dir);
+            let saved = group.apply(&mut self.options);
+            let logger = Arc::clone(&self.logger);
+            let group_summary = match &spillover {
+                Some(spillover) => spillover::process_spillover(
+                    &group.playlists,
+                    &dest_dir,
+                    &group.spillover(spillover),
+                    &mut self.options,
+                    &mut self.error_tracker,
+                    report.take(),
+                    logger,
+                ),
+                None => normal::process_normal_operations(
+                    &group.playlists,
+                    &dest_dir,
+                    &self.options,
+                    &mut self.error_tracker,
+                    report.take(),
+                    logger,
+                ),
+            };
+            saved.restore(&mut self.options);
+            let mut group_summary = group_summary?;
+            report = group_summary.report.take();
+            summary.add(group_summary);
+        }
+        summary.report = report;
+        Ok(summary)
+    }
+
+    /// Copy again the playlists and files listed in the error file
+    /// `retry_file` of an earlier run
+    pub fn retry(&mut self, retry_file: &str) -> Result<CopySummary> {
+        retry::retry_operations(
+            retry_file,
+            &self.dest_dir,
+            &self.options,
+            &mut self.error_tracker,
+            self.report.take(),
+            Arc::clone(&self.logger),
+        )
+    }
+
+    /// Print the changes copying the playlists would make, without
+    /// touching the destination
+    pub fn dry_run(&mut self, playlists: &[String]) -> Result<CopySummary> {
+        let playlists = plan::unique_playlists(playlists);
+        let mut summary = CopySummary::default();
+        for group in groups::group_playlists(&playlists, &self.options) {
+            let saved = group.apply(&mut self.options);
+            let group_summary = itemize::process_dry_run(
+                &group.playlists,
+                &group.dest_dir(&self.dest_dir),
+                &self.options,
+                &mut self.error_tracker,
+            );
+            saved.restore(&mut self.options);
+            summary.add(group_summary?);
+        }
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_filter_already_copied_files() {
+        let src_basedir: Arc<str> = Arc::from("/music");
+        let files: Vec<Arc<str>> = ["a.flac", "b.flac", "a.flac", "c.flac"]
+            .into_iter()
+            .map(Arc::from)
+            .collect();
+        let copied = HashSet::from([(Arc::clone(&src_basedir), Arc::from("b.flac"))]);
+
+        let files = filter_already_copied_files(&src_basedir, &files, &copied);
+        let files: Vec<&str> = files.iter().map(|file| &**file).collect();
+        assert_eq!(files, ["a.flac", "c.flac"]);
+    }
+
+    #[test]
+    fn test_playlist_transfer_run() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (music, dest) = (temp_dir.path().join("music"), temp_dir.path().join("dest"));
+        fs::create_dir_all(music.join("artist"))?;
+        fs::create_dir(&dest)?;
+        fs::write(music.join("artist/title.flac"), "flac")?;
+        fs::write(music.join("artist/title.lrc"), "lyrics")?;
+        fs::write(music.join("playlist.m3u8"), "artist/title.flac\n")?;
+        let playlist = music.join("playlist.m3u8").to_string_lossy().to_string();
+
+        let options = TransferOptions {
+            copy_lyrics: true,
+            ..TransferOptions::default()
+        };
+        let mut transfer = PlaylistTransfer::new(dest.to_string_lossy()).with_options(options);
+        assert!(transfer.options().copy_lyrics);
+
+        // Giving a playlist twice copies it once
+        let summary = transfer.run(&[playlist.clone(), playlist])?;
+        assert_eq!(summary.successful_playlists, 1);
+        assert_eq!(summary.total_playlists, 1);
+        assert_eq!(summary.successful_lyrics_files, 1);
+        assert_eq!(fs::read_to_string(dest.join("artist/title.flac"))?, "flac");
+        assert_eq!(fs::read_to_string(dest.join("artist/title.lrc"))?, "lyrics");
+        assert!(dest.join("playlist.m3u8").is_file());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_playlist_transfer_error_tracker() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dest = temp_dir.path().join("dest");
+        fs::create_dir(&dest)?;
+        let playlist = temp_dir.path().join("playlist.m3u8");
+        fs::write(&playlist, "missing.flac\n")?;
+        let error_file = temp_dir.path().join("errors.txt");
+
+        let mut error_tracker = ErrorTracker::create(&error_file.to_string_lossy())?;
+        let options = TransferOptions {
+            keep_going: true,
+            ..TransferOptions::default()
+        };
+        let summary = PlaylistTransfer::new(dest.to_string_lossy())
+            .with_options(options)
+            .with_error_tracker(&mut error_tracker)
+            .run(&[playlist.to_string_lossy().to_string()])?;
+        error_tracker.finish()?;
+
+        assert_eq!(summary.successful_media_files, 0);
+        assert_eq!(summary.total_media_files, 1);
+        let errors = fs::read_to_string(&error_file)?;
+        assert!(errors.contains(&format!(
+            "M {}",
+            temp_dir.path().join("missing.flac").display()
+        )));
+
+        // Without keep_going, the failure is returned to the caller
+        let result = PlaylistTransfer::new(dest.to_string_lossy())
+            .run(&[playlist.to_string_lossy().to_string()]);
+        assert!(result.is_err());
+
+        Ok(())
+    }
+}
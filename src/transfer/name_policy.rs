@@ -0,0 +1,266 @@
+//! Policy renaming destination names for the constraints of a device
+//!
+//! Some firmwares cannot cope with long names, certain characters or
+//! lower-case letters.  Rather than an option for every such quirk, a
+//! policy file on the host lists the renames a device needs, one rule
+//! per line, applied in order to each name of a destination path:
+//!
+//! ```text
+//! # Short ASCII names in upper case for an old car stereo
+//! transliterate
+//! replace "&" "and"
+//! replace " - " "-"
+//! truncate 32
+//! case upper
+//! ```
+//!
+//! The rules are:
+//!
+//! - `replace FROM TO`: Replace every `FROM` by `TO`
+//! - `truncate N`: Keep the first `N` characters, dropping the white
+//!   space left at the end
+//! - `transliterate`: Replace non-ASCII characters by their ASCII
+//!   approximations as `--transliterate` does
+//! - `case lower` or `case upper`: Convert the letter case
+//!
+//! Arguments holding white space are quoted with `"`, within which `\"`
+//! and `\\` stand for `"` and `\`.  Directory names are renamed as a
+//! whole, while file names keep their extensions, so that a media file
+//! and its lyrics file keep matching names.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::transliterate::transliterate_name;
+
+use super::names::LetterCase;
+
+/// Name of a file or directory renamed to nothing
+const EMPTY_NAME: &str = "_";
+
+/// Rule of a name policy
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NameRule {
+    /// Replace every occurrence of a string by another
+    Replace(String, String),
+    /// Keep the first characters of a name
+    Truncate(usize),
+    /// Replace non-ASCII characters by ASCII approximations
+    Transliterate,
+    /// Convert the letter case
+    Case(LetterCase),
+}
+
+impl NameRule {
+    /// Apply the rule to a name, or the stem of a file name
+    fn apply(&self, name: String) -> String {
+        match self {
+            Self::Replace(from, to) => name.replace(from.as_str(), to),
+            Self::Truncate(length) => match name.char_indices().nth(*length) {
+                Some((end, _)) => name[..end].trim_end().to_string(),
+                None => name,
+            },
+            Self::Transliterate => transliterate_name(&name),
+            Self::Case(case) => case.apply(name),
+        }
+    }
+}
+
+/// Rules renaming destination names, applied in order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NamePolicy {
+    rules: Vec<NameRule>,
+}
+
+impl NamePolicy {
+    /// Parse the rules of a policy, one per line
+    ///
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim_start_matches('\u{feff}').trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let words = split_words(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+            let words: Vec<&str> = words.iter().map(String::as_str).collect();
+            let rule = match words[..] {
+                ["replace", "", _] => return Err(format!("line {}: Nothing to replace", i + 1)),
+                ["replace", from, to] => NameRule::Replace(from.to_string(), to.to_string()),
+                ["truncate", length] => match length.parse::<usize>() {
+                    Ok(length) if length > 0 => NameRule::Truncate(length),
+                    _ => return Err(format!("line {}: Invalid length: {}", i + 1, length)),
+                },
+                ["transliterate"] => NameRule::Transliterate,
+                ["case", case] => {
+                    NameRule::Case(case.parse().map_err(|e| format!("line {}: {}", i + 1, e))?)
+                }
+                [keyword @ ("replace" | "truncate" | "transliterate" | "case"), ..] => {
+                    return Err(format!("line {}: Wrong arguments of {}", i + 1, keyword))
+                }
+                [keyword, ..] => return Err(format!("line {}: Unknown rule: {}", i + 1, keyword)),
+                [] => continue,
+            };
+            rules.push(rule);
+        }
+
+        Ok(Self { rules })
+    }
+
+    /// Read the rules of the policy file at `path`
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        Self::parse(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// The rules in the order they are applied
+    pub fn rules(&self) -> &[NameRule] {
+        &self.rules
+    }
+
+    /// Whether the policy renames nothing
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Rename a directory name, or a file name keeping its extension
+    pub fn apply_name(&self, name: &str, is_file: bool) -> String {
+        let (stem, extension) = match name.rsplit_once('.') {
+            Some((stem, extension)) if is_file && !stem.is_empty() => (stem, Some(extension)),
+            _ => (name, None),
+        };
+        let stem = self
+            .rules
+            .iter()
+            .fold(stem.to_string(), |stem, rule| rule.apply(stem));
+        let stem = match stem.is_empty() {
+            true => EMPTY_NAME.to_string(),
+            false => stem,
+        };
+
+        match extension {
+            Some(extension) => format!("{}.{}", stem, extension),
+            None => stem,
+        }
+    }
+
+    /// Rename each name of a path to a file, with forward slashes
+    pub fn apply_path(&self, path: &str) -> String {
+        if self.is_empty() {
+            return path.to_string();
+        }
+
+        let names: Vec<&str> = path.split('/').collect();
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| match *name {
+                "" | "." | ".." => name.to_string(),
+                _ => self.apply_name(name, i + 1 == names.len()),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+/// Split a line into words separated by white space, where words quoted
+/// with `"` may hold white space and `\"` and `\\` stand for `"` and `\`
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut chars = line.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+
+        let mut word = String::new();
+        if c == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(escaped @ ('"' | '\\')) => word.push(escaped),
+                        Some(other) => {
+                            word.push('\\');
+                            word.push(other);
+                        }
+                        None => return Err("Unterminated quote".to_string()),
+                    },
+                    Some(c) => word.push(c),
+                    None => return Err("Unterminated quote".to_string()),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                word.push(c);
+            }
+        }
+        words.push(word);
+    }
+
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let policy = NamePolicy::parse(
+            "\u{feff}# Comment\n\ntransliterate\nreplace \" - \" \"\"\ntruncate 8\ncase upper\n",
+        )
+        .unwrap();
+        assert_eq!(
+            policy.rules(),
+            [
+                NameRule::Transliterate,
+                NameRule::Replace(" - ".to_string(), String::new()),
+                NameRule::Truncate(8),
+                NameRule::Case(LetterCase::Upper),
+            ]
+        );
+
+        assert_eq!(
+            NamePolicy::parse("replace \"\\\"\" '").unwrap().rules(),
+            [NameRule::Replace("\"".to_string(), "'".to_string())]
+        );
+        assert!(NamePolicy::parse("truncate 0").is_err());
+        assert!(NamePolicy::parse("replace \"\" x").is_err());
+        assert!(NamePolicy::parse("replace \"a b").is_err());
+        assert!(NamePolicy::parse("case title").is_err());
+        assert_eq!(
+            NamePolicy::parse("case\nrot13"),
+            Err("line 1: Wrong arguments of case".to_string())
+        );
+        assert_eq!(
+            NamePolicy::parse("rot13"),
+            Err("line 1: Unknown rule: rot13".to_string())
+        );
+    }
+
+    #[test]
+    fn test_apply_path() {
+        let policy =
+            NamePolicy::parse("transliterate\nreplace & and\ntruncate 10\ncase lower").unwrap();
+        assert_eq!(
+            policy.apply_path("Björk & Friends/Homogenic/01 Hunter.flac"),
+            "bjork and/homogenic/01 hunter.flac"
+        );
+        assert_eq!(
+            policy.apply_path("Album/A Very Long Title.lrc"),
+            "album/a very lon.lrc"
+        );
+        assert_eq!(policy.apply_path("../.flac"), "../.flac");
+
+        let policy = NamePolicy::parse("replace Title \"\"").unwrap();
+        assert_eq!(policy.apply_path("Title.flac"), "_.flac");
+        assert_eq!(NamePolicy::default().apply_path("A/B.flac"), "A/B.flac");
+    }
+}
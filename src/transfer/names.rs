@@ -0,0 +1,568 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::Result;
+
+use crate::file_utils::{is_windows_reserved_name, windows_safe_name};
+use crate::media_extensions::{parse_extension, MEDIA_EXTENSIONS};
+use crate::media_file_info::MediaFileInfo;
+use crate::media_set::MediaKey;
+use crate::media_tags::read_tags;
+use crate::transliterate::{transliterate_path, RESERVED_CHARS};
+
+use super::TransferOptions;
+
+/// Separator of the directory names collapsed into one by `--max-depth`
+const COLLAPSED_DIR_SEPARATOR: &str = " - ";
+
+/// Layout of the destination directories
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Layout {
+    /// Mirror the source directories
+    #[default]
+    Source,
+    /// Derive the paths of media files from their tags
+    Tags,
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "source" => Ok(Self::Source),
+            "tags" => Ok(Self::Tags),
+            _ => Err(format!("Unknown layout: {} (expected source or tags)", s)),
+        }
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Source => "source",
+            Self::Tags => "tags",
+        })
+    }
+}
+
+/// Letter case of destination names
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LetterCase {
+    /// Keep the case of the source names
+    #[default]
+    Preserve,
+    Lower,
+    Upper,
+}
+
+impl LetterCase {
+    /// Convert a name to the letter case
+    pub(crate) fn apply(self, name: String) -> String {
+        match self {
+            Self::Preserve => name,
+            Self::Lower => name.to_lowercase(),
+            Self::Upper => name.to_uppercase(),
+        }
+    }
+}
+
+impl FromStr for LetterCase {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "preserve" => Ok(Self::Preserve),
+            "lower" => Ok(Self::Lower),
+            "upper" => Ok(Self::Upper),
+            _ => Err(format!(
+                "Unknown case: {} (expected lower, upper or preserve)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LetterCase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Preserve => "preserve",
+            Self::Lower => "lower",
+            Self::Upper => "upper",
+        })
+    }
+}
+
+/// What to do with a media file renamed to a destination path taken by
+/// another file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CollisionStrategy {
+    /// Insert a number such as ` (2)` before the extension
+    #[default]
+    Number,
+    /// Insert the CRC-32 of the source path such as ` [1a2b3c4d]`
+    /// before the extension
+    Hash,
+    /// Leave the media file uncopied, failing it
+    Skip,
+    /// Fail before copying anything
+    Fail,
+}
+
+impl FromStr for CollisionStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "number" => Ok(Self::Number),
+            "hash" => Ok(Self::Hash),
+            "skip" => Ok(Self::Skip),
+            "fail" => Ok(Self::Fail),
+            _ => Err(format!(
+                "Unknown collision strategy: {} (expected number, hash, skip or fail)",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CollisionStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Number => "number",
+            Self::Hash => "hash",
+            Self::Skip => "skip",
+            Self::Fail => "fail",
+        })
+    }
+}
+
+/// Whether the options change the names of files on the destination
+pub fn renames_files(options: &TransferOptions) -> bool {
+    options.transliterate
+        || options.max_depth.is_some()
+        || options.case != LetterCase::Preserve
+        || options.layout == Layout::Tags
+        || !options.rewrite_ext.is_empty()
+        || !options.name_policy.is_empty()
+}
+
+/// Parse a rewrite of the extension of destination files, such as
+/// `flac=opus`, with or without leading dots
+pub fn parse_extension_rewrite(s: &str) -> Result<(String, String), String> {
+    let (from, to) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid extension rewrite: {} (expected FROM=TO)", s))?;
+    Ok((parse_extension(from)?, parse_extension(to)?))
+}
+
+/// Path, with forward slashes, with its extension rewritten by the first
+/// of `rewrites` whose source extension matches it case insensitively
+fn rewrite_extension(file: &str, rewrites: &[(String, String)]) -> String {
+    let name = file.rsplit('/').next().unwrap_or(file);
+    let Some((stem, extension)) = name.rsplit_once('.') else {
+        return file.to_string();
+    };
+    match rewrites
+        .iter()
+        .find(|(from, _)| !stem.is_empty() && from.eq_ignore_ascii_case(extension))
+    {
+        Some((_, to)) => format!("{}.{}", &file[..file.len() - extension.len() - 1], to),
+        None => file.to_string(),
+    }
+}
+
+/// Make a tag usable as a file or directory name, replacing characters
+/// not allowed in FAT names by `_` and dropping trailing dots and spaces
+fn tag_name(tag: &str) -> Option<String> {
+    let name: String = tag
+        .chars()
+        .map(|c| match RESERVED_CHARS.contains(&c) || c.is_control() {
+            true => '_',
+            false => c,
+        })
+        .collect();
+    let name = name.trim_end_matches(['.', ' ']).trim_start();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Path of a media file in the tag layout, such as
+/// `Artist/Album/01 Title.flac`, derived from the tags of the source
+/// file at `src_path`
+///
+/// The album artist is preferred over the artist, so compilations stay
+/// in one directory, and the disc number is prefixed to the track
+/// number as in `1-01 Title.flac` if tagged.  A lyrics file follows the
+/// media file of the same name.  Returns `None` if the artist, album or
+/// title cannot be read.
+fn tag_path(src_path: &Path) -> Option<String> {
+    let extension = src_path.extension()?.to_string_lossy();
+    if extension.eq_ignore_ascii_case("lrc") {
+        return MEDIA_EXTENSIONS
+            .iter()
+            .map(|media| src_path.with_extension(media))
+            .find(|media_path| media_path.is_file())
+            .and_then(|media_path| tag_path(&media_path))
+            .map(|path| Path::new(&path).with_extension(&*extension))
+            .map(|path| path.to_string_lossy().to_string());
+    }
+
+    let tags = read_tags(src_path).ok()?;
+    let artist = tag_name(tags.album_artist_or_artist()?)?;
+    let album = tag_name(tags.album.as_deref()?)?;
+    let title = tag_name(tags.title.as_deref()?)?;
+    let number = match (tags.disc_number, tags.track_number) {
+        (Some(disc), Some(track)) => format!("{}-{:02} ", disc, track),
+        (None, Some(track)) => format!("{:02} ", track),
+        (_, None) => String::new(),
+    };
+
+    Some(format!(
+        "{}/{}/{}{}.{}",
+        artist, album, number, title, extension
+    ))
+}
+
+/// Collapse the directories of a path below `max_depth` levels into the
+/// directory at the deepest level, so that `artist/album/disc1/track.flac`
+/// becomes `artist/album - disc1/track.flac` with a depth of 2
+fn flatten_path(file: &str, max_depth: usize) -> String {
+    let mut components: Vec<&str> = file.split('/').collect();
+    let Some(name) = components.pop() else {
+        return file.to_string();
+    };
+    if components.len() <= max_depth {
+        return file.to_string();
+    }
+
+    let collapsed = components.split_off(max_depth.saturating_sub(1));
+    let collapsed = collapsed.join(COLLAPSED_DIR_SEPARATOR);
+    components.push(&collapsed);
+    components.push(name);
+    components.join("/")
+}
+
+/// Rename the names of a path, with forward slashes, that cannot be used
+/// on Windows
+fn windows_safe_path(file: &str) -> String {
+    file.split('/')
+        .map(windows_safe_name)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Fail with `--strict` if a destination path, relative to the
+/// destination root, has a name that cannot be used on Windows
+///
+/// Without `--strict`, such names are renamed by `dest_name` instead.
+pub fn check_windows_names(dest_file: &Path, options: &TransferOptions) -> Result<()> {
+    if !options.strict {
+        return Ok(());
+    }
+
+    match dest_file
+        .iter()
+        .map(|name| name.to_string_lossy())
+        .find(|name| is_windows_reserved_name(name))
+    {
+        Some(name) => Err(anyhow::anyhow!(
+            "Destination name \"{}\" of {} cannot be used on Windows",
+            name,
+            dest_file.display()
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Destination path of a file given by its path relative to the source
+/// base directory, with forward slashes
+///
+/// The names are renamed by the rules of `--name-policy` after the other
+/// renames.  Names that cannot be used on Windows, such as `CON.flac` or
+/// names ending with a dot, are renamed unless `--strict` is given.
+pub fn dest_name(file: &str, options: &TransferOptions) -> String {
+    let file = match options.max_depth {
+        Some(max_depth) => flatten_path(file, max_depth),
+        None => file.to_string(),
+    };
+
+    let file = if options.transliterate {
+        transliterate_path(&file)
+    } else {
+        file
+    };
+
+    let file = options.case.apply(file);
+    let file = options.name_policy.apply_path(&file);
+    match options.strict {
+        true => file,
+        false => windows_safe_path(&file),
+    }
+}
+
+/// Destination path of a media file or lyrics file given by its source
+/// base directory and its path relative to it, with forward slashes
+///
+/// With the tag layout, the path is derived from the tags of the file,
+/// or from its path if they cannot be read.  Its extension is rewritten
+/// by `--rewrite-ext` before the other renames.
+pub fn media_dest_name(src_basedir: &str, file: &str, options: &TransferOptions) -> String {
+    let tagged = match options.layout {
+        Layout::Tags => tag_path(&Path::new(src_basedir).join(file)),
+        Layout::Source => None,
+    };
+
+    let file = rewrite_extension(tagged.as_deref().unwrap_or(file), &options.rewrite_ext);
+    dest_name(&file, options)
+}
+
+/// Set the destination file of a media file renamed by the options
+///
+/// Unlike `assign_dest_files`, the destination path is not made unique,
+/// as single files are retried without the plan of a whole run.
+pub fn with_dest_name(mut media_file: MediaFileInfo, options: &TransferOptions) -> MediaFileInfo {
+    let file = media_file.file.to_string_lossy();
+    let src_basedir = media_file.src_basedir.to_string_lossy();
+    let dest = media_dest_name(&src_basedir, &file, options);
+    if dest != file {
+        media_file.dest_file = Some(PathBuf::from(dest));
+    }
+    media_file
+}
+
+/// Path with ` (n)` inserted before the extension of its file name
+pub fn numbered_name(path: &str, n: usize) -> String {
+    suffixed_name(path, &format!("({})", n))
+}
+
+/// Path with the CRC-32 of `source` such as ` [1a2b3c4d]` inserted
+/// before the extension of its file name, the same whatever other files
+/// are copied
+pub fn hashed_name(path: &str, source: &str) -> String {
+    suffixed_name(
+        path,
+        &format!("[{:08x}]", crc32fast::hash(source.as_bytes())),
+    )
+}
+
+/// Path with a space and `suffix` inserted before the extension of its
+/// file name
+fn suffixed_name(path: &str, suffix: &str) -> String {
+    let (dir, name) = path
+        .rsplit_once('/')
+        .map_or(("", path), |(dir, name)| (dir, name));
+    let name = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => {
+            format!("{} {}.{}", stem, suffix, extension)
+        }
+        _ => format!("{} {}", name, suffix),
+    };
+
+    if dir.is_empty() {
+        name
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Assign destination files to the media files of a plan whose names
+/// are changed on the destination
+///
+/// Media files keeping their names are placed first.  A renamed media
+/// file whose destination path is taken by another file, compared case
+/// insensitively as FAT does, is resolved by the collision strategy in
+/// the order the files are given: by default, it gets a number such as
+/// ` (2)` before the extension.  Returns the media files left uncopied
+/// with the skip strategy, keeping their names; with the fail strategy,
+/// an error is returned after warning about every collision.
+pub fn assign_dest_files<'a>(
+    keys: impl Iterator<Item = &'a MediaKey>,
+    media: &mut HashMap<MediaKey, MediaFileInfo>,
+    options: &TransferOptions,
+) -> Result<HashSet<MediaKey>> {
+    let renamed: Vec<(&MediaKey, String)> = keys
+        .map(|key| (key, media_dest_name(&key.0, &key.1, options)))
+        .collect();
+
+    // Destination path in lower case -> source file occupying it
+    let mut taken: HashMap<String, &str> = renamed
+        .iter()
+        .filter(|(key, dest)| *key.1 == *dest)
+        .map(|(key, dest)| (dest.to_lowercase(), &*key.1))
+        .collect();
+
+    let mut skipped = HashSet::new();
+    for (key, dest) in &renamed {
+        if *key.1 == *dest {
+            continue;
+        }
+
+        let taken_by = |candidate: &str| {
+            taken
+                .get(&candidate.to_lowercase())
+                .copied()
+                .filter(|file| *file != &*key.1)
+        };
+        let base = match (taken_by(dest), options.on_collision) {
+            (None, _) | (Some(_), CollisionStrategy::Number) => dest.clone(),
+            (Some(_), CollisionStrategy::Hash) => hashed_name(dest, &key.1),
+            (Some(file), CollisionStrategy::Skip | CollisionStrategy::Fail) => {
+                eprintln!(
+                    "Warning: \"{}\" is renamed to \"{}\", taken by \"{}\"",
+                    key.1, dest, file
+                );
+                skipped.insert((*key).clone());
+                continue;
+            }
+        };
+        let mut candidate = base.clone();
+        let mut n = 1;
+        while taken_by(&candidate).is_some() {
+            n += 1;
+            candidate = numbered_name(&base, n);
+        }
+
+        taken.insert(candidate.to_lowercase(), &key.1);
+        media.get_mut(*key).expect("planned media file").dest_file = Some(PathBuf::from(candidate));
+    }
+
+    if options.on_collision == CollisionStrategy::Fail && !skipped.is_empty() {
+        return Err(anyhow::anyhow!(
+            "{} renamed media files collide with other files at the destination",
+            skipped.len()
+        ));
+    }
+
+    Ok(skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_assign_dest_files() {
+        let options = TransferOptions {
+            transliterate: true,
+            ..TransferOptions::default()
+        };
+        let root: Arc<str> = Arc::from("/music");
+        let keys: Vec<MediaKey> = [
+            "Café/1.flac",
+            "cafe/1.flac",
+            "Cafe/1.flac",
+            "a/b.flac",
+            "a/CON.flac",
+        ]
+        .into_iter()
+        .map(|file| (Arc::clone(&root), Arc::from(file)))
+        .collect();
+        let mut media: HashMap<_, _> = keys
+            .iter()
+            .map(|key| (key.clone(), MediaFileInfo::new(&*key.0, &*key.1)))
+            .collect();
+
+        assert!(assign_dest_files(keys.iter(), &mut media, &options)
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(
+            media[&keys[0]].dest_file,
+            Some(PathBuf::from("Cafe/1 (2).flac"))
+        );
+        assert_eq!(media[&keys[1]].dest_file, None);
+        assert_eq!(media[&keys[2]].dest_file, None);
+        assert_eq!(media[&keys[3]].dest_file, None);
+        assert_eq!(
+            media[&keys[4]].dest_file,
+            Some(PathBuf::from("a/CON_.flac"))
+        );
+        assert_eq!(numbered_name("README", 3), "README (3)");
+
+        // The other strategies resolve the collision of "Café/1.flac"
+        let options = TransferOptions {
+            on_collision: CollisionStrategy::Hash,
+            ..options
+        };
+        let mut hashed = media.clone();
+        assign_dest_files(keys.iter(), &mut hashed, &options).unwrap();
+        assert_eq!(
+            hashed[&keys[0]].dest_file,
+            Some(PathBuf::from(hashed_name("Cafe/1.flac", "Café/1.flac")))
+        );
+        let options = TransferOptions {
+            on_collision: CollisionStrategy::Skip,
+            ..options
+        };
+        let mut skipped = media.clone();
+        let skipped_keys = assign_dest_files(keys.iter(), &mut skipped, &options).unwrap();
+        assert_eq!(skipped_keys, HashSet::from([keys[0].clone()]));
+        let options = TransferOptions {
+            on_collision: CollisionStrategy::Fail,
+            ..options
+        };
+        assert!(assign_dest_files(keys.iter(), &mut media.clone(), &options).is_err());
+        assert_eq!(hashed_name("README", "x"), "README [8cdc1683]");
+        assert_eq!("Hash".parse(), Ok(CollisionStrategy::Hash));
+    }
+
+    #[test]
+    fn test_rewrite_extension() {
+        let rewrites = vec![
+            parse_extension_rewrite("flac=opus").unwrap(),
+            parse_extension_rewrite(".wav=.mp3").unwrap(),
+        ];
+        assert_eq!(
+            rewrite_extension("a.b/title.FLAC", &rewrites),
+            "a.b/title.opus"
+        );
+        assert_eq!(rewrite_extension("title.wav", &rewrites), "title.mp3");
+        assert_eq!(
+            rewrite_extension("a.flac/title.mp3", &rewrites),
+            "a.flac/title.mp3"
+        );
+        assert_eq!(rewrite_extension("a/.flac", &rewrites), "a/.flac");
+        assert_eq!(rewrite_extension("title", &rewrites), "title");
+        assert!(parse_extension_rewrite("flac").is_err());
+        assert!(parse_extension_rewrite("flac=").is_err());
+    }
+
+    #[test]
+    fn test_flatten_path() {
+        assert_eq!(
+            flatten_path("artist/album/disc1/track.flac", 2),
+            "artist/album - disc1/track.flac"
+        );
+        assert_eq!(
+            flatten_path("a/b/c/d/track.flac", 1),
+            "a - b - c - d/track.flac"
+        );
+        assert_eq!(
+            flatten_path("artist/album/track.flac", 2),
+            "artist/album/track.flac"
+        );
+        assert_eq!(flatten_path("track.flac", 1), "track.flac");
+    }
+
+    #[test]
+    fn test_tag_name() {
+        assert_eq!(tag_name("AC/DC"), Some("AC_DC".to_string()));
+        assert_eq!(tag_name("What?"), Some("What_".to_string()));
+        assert_eq!(tag_name(" Vol. 1... "), Some("Vol. 1".to_string()));
+        assert_eq!(tag_name(" .. "), None);
+        assert_eq!(tag_path(Path::new("/nonexistent/title1.flac")), None);
+    }
+
+    #[test]
+    fn test_letter_case() {
+        let name = || "Artist/Álbum/Track.FLAC".to_string();
+        assert_eq!(LetterCase::Lower.apply(name()), "artist/álbum/track.flac");
+        assert_eq!(LetterCase::Upper.apply(name()), "ARTIST/ÁLBUM/TRACK.FLAC");
+        assert_eq!(LetterCase::Preserve.apply(name()), name());
+        assert_eq!("UPPER".parse(), Ok(LetterCase::Upper));
+        assert!("title".parse::<LetterCase>().is_err());
+    }
+}
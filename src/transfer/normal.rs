@@ -0,0 +1,396 @@
+use std::iter;
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+
+use anyhow::Result;
+
+use crate::dest_index::DestIndex;
+use crate::error_file::RunTotals;
+use crate::logger::Logger;
+
+use super::actions::{plan_put, PutPlan};
+use super::album::{album_dirs, create_album_playlists, sort_album_dirs};
+use super::artwork::copy_artwork;
+use super::copy::{copy_media_files, print_missing_files};
+use super::file::process_playlist;
+use super::pipeline::{parse_ahead, uses_pipeline};
+use super::plan::{CopyPlan, PlannedPlaylist};
+use super::report::{record_skip, CopyReport};
+use super::state::{load_sync_state, save_sync_state};
+use super::summary::{CopySummary, PlaylistSummary};
+use super::{
+    filter_already_copied_files, scan_dest_dirs, scan_destination, ErrorTracker, MediaContext,
+    ProgressContext, TransferOptions,
+};
+
+/// Process a single planned playlist and its associated media files,
+/// looking up their planned information in `plan`
+fn process_single_playlist(
+    planned: &PlannedPlaylist,
+    plan: &CopyPlan,
+    dest_index: &mut DestIndex,
+    options: &TransferOptions,
+    error_tracker_ref: &mut Option<&mut ErrorTracker>,
+    media_context: &mut MediaContext,
+    progress_context: &mut ProgressContext,
+) -> Result<bool> {
+    let playlist = planned.playlist.as_str();
+    let dest_dir = dest_index.root().to_string_lossy().to_string();
+
+    progress_context.logger.log_formatted(
+        match planned.is_list {
+            true => "Put media files listed in \"{}\" into \"{}\"",
+            false => "Put playlist \"{}\" into \"{}\"",
+        },
+        &[playlist, &dest_dir],
+    );
+
+    let result = planned.media.as_ref().map_err(|e| anyhow::anyhow!("{}", e)).and_then(
+        |(src_basedir, files)| {
+            if planned.is_list {
+                return Ok((src_basedir, files));
+            }
+            process_playlist(
+                playlist,
+                &dest_dir,
+                &plan.renames(src_basedir),
+                options,
+                progress_context.current_playlist_num,
+                progress_context.total_playlists,
+                &*progress_context.logger,
+            )
+            .map(|(dest_playlist, up_to_date)| {
+                progress_context.written_playlists.push(dest_playlist);
+                progress_context.up_to_date_playlists += usize::from(up_to_date);
+                (src_basedir, files)
+            })
+        },
+    );
+
+    match result {
+        Ok((src_basedir, files)) => {
+            // Filter out already copied files, duplicates of other files and
+            // files conflicting with them
+            let media_file =
+                |file: &Arc<str>| &plan.media[&(Arc::clone(src_basedir), Arc::clone(file))];
+            let mut files_to_copy =
+                filter_already_copied_files(src_basedir, files, &media_context.copied_files);
+            let duplicates = files.len() - files_to_copy.len();
+            if progress_context.report.is_some() {
+                let dest_root = dest_index.root();
+                for file in files.iter().filter(|file| !files_to_copy.contains(file)) {
+                    let reason = "already copied";
+                    record_skip(progress_context, playlist, media_file(file), dest_root, reason);
+                }
+            }
+            files_to_copy.retain(|file| match plan.duplicates.original(src_basedir, file) {
+                Some(original) => {
+                    progress_context.logger.log_formatted(
+                        "Skip track \"{}\" identical to \"{}\"",
+                        &[file, &original.1],
+                    );
+                    let reason = format!("identical to {}", original.1);
+                    let dest_root = dest_index.root();
+                    record_skip(progress_context, playlist, media_file(file), dest_root, &reason);
+                    false
+                }
+                None => true,
+            });
+            files_to_copy.retain(|file| match plan.conflicts.winner(src_basedir, file) {
+                Some((winner_basedir, winner_file)) => {
+                    let winner = Path::new(&**winner_basedir).join(&**winner_file);
+                    let winner = winner.to_string_lossy();
+                    progress_context.logger.log_formatted(
+                        "Skip track \"{}\" conflicting with \"{}\"",
+                        &[file, &winner],
+                    );
+                    let reason = format!("conflicts with {}", winner);
+                    let dest_root = dest_index.root();
+                    record_skip(progress_context, playlist, media_file(file), dest_root, &reason);
+                    false
+                }
+                None => true,
+            });
+            // Files renamed to the destination path of another file with
+            // the skip collision strategy fail without being copied
+            files_to_copy.retain(|file| {
+                let key = (Arc::clone(src_basedir), Arc::clone(file));
+                if !plan.collisions.contains(&key) {
+                    return true;
+                }
+                eprintln!(
+                    "Error: Skipping track \"{}\" renamed to the destination path of another file",
+                    media_file(file).src_path().display()
+                );
+                if let Some(tracker) = error_tracker_ref {
+                    tracker.add_failed_media_file(src_basedir.to_string(), file.to_string());
+                }
+                let dest_root = dest_index.root();
+                let reason = "name collides with another file";
+                record_skip(progress_context, playlist, media_file(file), dest_root, reason);
+                false
+            });
+
+            progress_context.logger.log_formatted(
+                "Copying {} media files for playlist \"{}\"",
+                &[&files_to_copy.len().to_string(), playlist],
+            );
+
+            // Copy files for this playlist
+            let attempted = files_to_copy.len();
+            let unchanged = progress_context.unchanged_media_files;
+            let files_to_copy = files_to_copy.into_iter().map(|file| {
+                let media_file = media_file(&file);
+                (file, media_file)
+            });
+            match copy_media_files(
+                playlist,
+                dest_index,
+                files_to_copy,
+                options,
+                error_tracker_ref,
+                progress_context,
+            ) {
+                Ok((_copied, successful_files)) => {
+                    // Media files skipped as unchanged are counted as
+                    // skipped
+                    let unchanged = progress_context.unchanged_media_files - unchanged;
+                    progress_context.playlist_summaries.push(
+                        PlaylistSummary::copied(
+                            playlist,
+                            files.len(),
+                            attempted - unchanged,
+                            successful_files.len() - unchanged,
+                        )
+                        .with_duplicates(duplicates),
+                    );
+
+                    // Update copied_files set with only the successfully copied files
+                    for file in successful_files {
+                        media_context
+                            .copied_files
+                            .insert((Arc::clone(src_basedir), file));
+                    }
+                    Ok(true) // Playlist processed successfully
+                }
+                Err(e) => {
+                    eprintln!("Error copying media files for playlist {}: {}", playlist, e);
+                    if !options.keep_going {
+                        return Err(e);
+                    }
+                    progress_context.playlist_summaries.push(
+                        PlaylistSummary::copied(playlist, files.len(), attempted, 0)
+                            .with_duplicates(duplicates),
+                    );
+                    Ok(false) // Playlist processing failed
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Error processing playlist {}: {}", playlist, e);
+            match error_tracker_ref {
+                Some(tracker) if !planned.is_list => {
+                    tracker.add_failed_playlist(playlist.to_string())
+                }
+                _ => {}
+            }
+            if !options.keep_going {
+                return Err(e);
+            }
+            progress_context
+                .playlist_summaries
+                .push(PlaylistSummary::failed(playlist));
+            Ok(false) // Playlist processing failed
+        }
+    }
+}
+
+/// Execute the `i`th playlist of the plan
+/// Returns whether it is a playlist, rather than a file list, processed
+/// successfully
+fn execute_playlist(
+    i: usize,
+    plan: &CopyPlan,
+    dest_index: &mut DestIndex,
+    options: &TransferOptions,
+    error_tracker_ref: &mut Option<&mut ErrorTracker>,
+    media_context: &mut MediaContext,
+    progress_context: &mut ProgressContext,
+) -> Result<bool> {
+    let planned = &plan.playlists[i];
+    progress_context.current_playlist_num = Some(i + 1);
+
+    let success = process_single_playlist(
+        planned,
+        plan,
+        dest_index,
+        options,
+        error_tracker_ref,
+        media_context,
+        progress_context,
+    )?;
+    Ok(success && !planned.is_list)
+}
+
+/// Record the totals of the run in the error file, if any
+fn set_totals(
+    error_tracker_ref: &mut Option<&mut ErrorTracker>,
+    total_playlists: usize,
+    total_media_files: usize,
+) {
+    if let Some(tracker) = error_tracker_ref {
+        tracker.set_totals(RunTotals {
+            playlists: total_playlists,
+            media_files: total_media_files,
+        });
+    }
+}
+
+/// Execute a put plan, copying the media files of each playlist
+/// one-by-one
+///
+/// The plan also gives the total number of unique media files across
+/// all playlists.  Returns the number of playlists processed
+/// successfully.
+pub fn execute(
+    put_plan: &mut PutPlan,
+    options: &TransferOptions,
+    error_tracker_ref: &mut Option<&mut ErrorTracker>,
+    media_context: &mut MediaContext,
+    progress_context: &mut ProgressContext,
+) -> Result<usize> {
+    let plan = &put_plan.plan;
+    let total_playlists = plan.playlists.iter().filter(|planned| !planned.is_list).count();
+    set_totals(error_tracker_ref, total_playlists, plan.total_media_files);
+    progress_context.total_media_files = Some(plan.total_media_files);
+    progress_context.set_progress_totals(plan.total_bytes());
+    progress_context.sync_state = put_plan.sync_state.take();
+    progress_context.non_audio_entries = plan
+        .playlists
+        .iter()
+        .map(|planned| planned.non_audio_entries)
+        .sum();
+
+    let mut successful_playlists = 0;
+    for i in 0..plan.playlists.len() {
+        successful_playlists += usize::from(execute_playlist(
+            i,
+            plan,
+            &mut put_plan.dest_index,
+            options,
+            error_tracker_ref,
+            media_context,
+            progress_context,
+        )?);
+    }
+    Ok(successful_playlists)
+}
+
+/// Process normal operations (non-retry mode)
+///
+/// The playlists are copied as they are parsed by a parser thread when
+/// the options allow it, so that copying starts at once; the total
+/// number of media files then grows as playlists are parsed.  Otherwise
+/// every playlist is parsed before copying starts.
+pub fn process_normal_operations(
+    playlists: &[String],
+    dest_dir: &str,
+    options: &TransferOptions,
+    error_tracker_ref: &mut Option<&mut ErrorTracker>,
+    report: Option<CopyReport>,
+    logger: Arc<dyn Logger>,
+) -> Result<CopySummary> {
+    let mut media_context = MediaContext::new();
+    let total_playlists = playlists.len();
+    let mut progress_context = ProgressContext::new(logger, total_playlists, 0);
+    progress_context.report = report;
+    progress_context.show_progress(options);
+    let mut successful_playlists = 0;
+
+    let (plan, mut dest_index) = if uses_pipeline(options) {
+        thread::scope(|scope| -> Result<_> {
+            let receiver = parse_ahead(scope, playlists, options);
+            let mut plan = CopyPlan::default();
+            let mut dest_index = scan_destination(dest_dir, iter::empty())?;
+            progress_context.sync_state = load_sync_state(dest_index.root(), options)?;
+
+            for parsed in receiver {
+                let parsed = parsed?;
+                let is_last = parsed.is_last;
+                let dest_files = parsed.new_media.iter().map(|(_, info)| info.dest_file());
+                scan_dest_dirs(&mut dest_index, dest_files)?;
+                progress_context.non_audio_entries += parsed.planned.non_audio_entries;
+                plan.add_playlist(parsed);
+                progress_context.total_media_files = Some(plan.total_media_files);
+                progress_context.set_progress_totals(plan.total_bytes());
+                if is_last {
+                    set_totals(error_tracker_ref, total_playlists, plan.total_media_files);
+                }
+
+                let i = plan.playlists.len() - 1;
+                successful_playlists += usize::from(execute_playlist(
+                    i,
+                    &plan,
+                    &mut dest_index,
+                    options,
+                    error_tracker_ref,
+                    &mut media_context,
+                    &mut progress_context,
+                )?);
+            }
+            Ok((plan, dest_index))
+        })?
+    } else {
+        // Parse every playlist once and list the destination directories
+        // to be written into up front
+        let mut put_plan = plan_put(playlists, dest_dir, options, &mut media_context.interner)?;
+        successful_playlists = execute(
+            &mut put_plan,
+            options,
+            error_tracker_ref,
+            &mut media_context,
+            &mut progress_context,
+        )?;
+        (put_plan.plan, put_plan.dest_index)
+    };
+    let total_media_files = plan.total_media_files;
+
+    print_missing_files(&progress_context);
+    if !options.artwork.is_empty() {
+        copy_artwork(&plan, &mut dest_index, options, &mut progress_context)?;
+    }
+    save_sync_state(&progress_context, dest_index.root())?;
+
+    // Post-process the album directories written into
+    if options.album_playlists || options.fat_order {
+        let album_dirs = album_dirs(dest_dir, plan.dest_files());
+        if options.album_playlists {
+            create_album_playlists(&album_dirs, options, &*progress_context.logger)?;
+        }
+        if options.fat_order {
+            sort_album_dirs(&album_dirs, options, &*progress_context.logger)?;
+        }
+    }
+
+    Ok(CopySummary {
+        successful_playlists,
+        total_playlists,
+        successful_media_files: progress_context.successful_media_files,
+        total_media_files: total_media_files - progress_context.unchanged_media_files,
+        successful_lyrics_files: progress_context.successful_lyrics_files,
+        total_lyrics_files: progress_context.total_lyrics_files,
+        successful_sidecar_files: progress_context.successful_sidecar_files,
+        total_sidecar_files: progress_context.total_sidecar_files,
+        successful_artwork_files: progress_context.successful_artwork_files,
+        total_artwork_files: progress_context.total_artwork_files,
+        non_audio_entries: progress_context.non_audio_entries,
+        unchanged_media_files: progress_context.unchanged_media_files,
+        playlists: progress_context.playlist_summaries,
+        written_playlists: progress_context.written_playlists,
+        up_to_date_playlists: progress_context.up_to_date_playlists,
+        dest_media_files: progress_context.dest_media_files,
+        report: progress_context.report,
+        completion: None,
+    })
+}
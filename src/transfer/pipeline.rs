@@ -0,0 +1,85 @@
+use std::collections::HashSet;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::Scope;
+
+use anyhow::Result;
+
+use crate::media_file_info::MediaFileInfo;
+use crate::media_set::MediaKey;
+use crate::path_interner::PathInterner;
+
+use super::conflicts::ConflictPolicy;
+use super::names::{check_windows_names, renames_files, with_dest_name};
+use super::plan::{plan_source, plan_sources, ParsedPlaylist};
+use super::TransferOptions;
+
+/// Number of playlists the parser thread may parse ahead of the
+/// playlist being copied
+const PLAYLISTS_AHEAD: usize = 16;
+
+/// Whether the playlists can be copied as they are parsed
+///
+/// Deduplication, renaming media files and conflict policies other than
+/// first-wins need every media file to be known before the first one is
+/// copied, so the whole plan is built first with them, as it is with
+/// `--source-read-only` to check every write before the first.
+pub fn uses_pipeline(options: &TransferOptions) -> bool {
+    !options.dedup
+        && !renames_files(options)
+        && options.on_conflict == ConflictPolicy::FirstWins
+        && !options.source_read_only
+}
+
+/// Parse the playlists and the file lists of the options on a parser
+/// thread, stat'ing the media files each references for the first time
+/// and renaming those that cannot be used on Windows, and receive them
+/// in order as they are parsed
+///
+/// The parser stops after a playlist failing to parse without
+/// `--keep-going`, which is received as an error, or once the receiver
+/// is dropped.
+pub fn parse_ahead<'scope, 'env>(
+    scope: &'scope Scope<'scope, 'env>,
+    playlists: &'env [String],
+    options: &'env TransferOptions,
+) -> Receiver<Result<ParsedPlaylist>> {
+    let (sender, receiver) = mpsc::sync_channel(PLAYLISTS_AHEAD);
+
+    scope.spawn(move || {
+        let mut interner = PathInterner::new();
+        let mut seen: HashSet<MediaKey> = HashSet::new();
+        let total_sources = playlists.len() + options.lists.len();
+
+        for (i, (playlist, is_list)) in plan_sources(playlists, options).enumerate() {
+            let parsed =
+                plan_source(playlist, is_list, options, &mut interner).and_then(|planned| {
+                    let new_media = match &planned.media {
+                        Ok((src_basedir, files)) => files
+                            .iter()
+                            .map(|file| (Arc::clone(src_basedir), Arc::clone(file)))
+                            .filter(|key| seen.insert(key.clone()))
+                            .map(|key| {
+                                let info = MediaFileInfo::new(&*key.0, &*key.1).with_metadata();
+                                let info = with_dest_name(info, options);
+                                check_windows_names(info.dest_file(), options)?;
+                                Ok((key, info))
+                            })
+                            .collect::<Result<_>>()?,
+                        Err(_) => Vec::new(),
+                    };
+                    Ok(ParsedPlaylist {
+                        planned,
+                        new_media,
+                        is_last: i + 1 == total_sources,
+                    })
+                });
+            let failed = parsed.is_err();
+            if sender.send(parsed).is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
@@ -0,0 +1,478 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+
+use crate::diagnostics::format_size;
+use crate::media_file_info::MediaFileInfo;
+use crate::media_set::{read_list_media, read_playlist_media, MediaKey, MediaSet, PlaylistMedia};
+use crate::path_interner::PathInterner;
+use crate::track_matcher::playlist_dir;
+
+use super::conflicts::Conflicts;
+use super::dedup::Duplicates;
+use super::names::{assign_dest_files, check_windows_names};
+use super::TransferOptions;
+
+/// A playlist, or a file list given by `--from-list`, and the media
+/// files extracted from it during planning
+///
+/// File lists are not copied to the destination, only their media files.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlannedPlaylist {
+    pub playlist: String,
+    #[cfg_attr(feature = "serde", serde(with = "super::serialize::parse_result"))]
+    pub media: Result<(Arc<str>, Vec<Arc<str>>)>, // (src_basedir, files) or the parse error
+    pub non_audio_entries: usize,                 // Entries left out for their extensions
+    pub is_list: bool,
+}
+
+/// A playlist parsed by the parser thread of the pipeline, with the
+/// media files it references for the first time
+///
+/// The media files are stat'ed by the parser thread, so that copying is
+/// left to the copying thread.
+#[derive(Debug)]
+pub struct ParsedPlaylist {
+    pub planned: PlannedPlaylist,
+    pub new_media: Vec<(MediaKey, MediaFileInfo)>,
+    pub is_last: bool, // Whether no playlist follows
+}
+
+/// Copy plan built from a single parse pass over all playlists
+///
+/// Each unique media file is stat'ed once while planning, and its
+/// `MediaFileInfo` is shared by every playlist referring to it.  With
+/// deduplication, media files identical to an earlier one are not
+/// counted, as they are not copied.  Media files whose names change on
+/// the destination, including those that cannot be used on Windows,
+/// are assigned their destination files, with the collisions of their
+/// new names resolved by the collision strategy, and media files mapping
+/// to the destination file of another are resolved by the conflict
+/// policy.
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CopyPlan {
+    pub playlists: Vec<PlannedPlaylist>,
+    #[cfg_attr(feature = "serde", serde(with = "super::serialize::pairs"))]
+    pub media: HashMap<MediaKey, MediaFileInfo>, // (src_basedir, file) -> info
+    pub duplicates: Duplicates,
+    pub conflicts: Conflicts,
+    pub collisions: HashSet<MediaKey>, // Media files skipped for colliding new names
+    pub total_media_files: usize,
+}
+
+impl CopyPlan {
+    fn new(playlists: Vec<PlannedPlaylist>, options: &TransferOptions) -> Result<Self> {
+        // Collect unique media files across all playlists
+        let mut media_set = MediaSet::new();
+        for (src_root, files) in playlists
+            .iter()
+            .filter_map(|planned| planned.media.as_ref().ok())
+        {
+            media_set.add_playlist(&PlaylistMedia {
+                src_root: Arc::clone(src_root),
+                files: files.clone(),
+            });
+        }
+
+        let mut media: HashMap<_, _> = media_set
+            .iter()
+            .map(|(src_basedir, file)| {
+                let info = MediaFileInfo::new(&**src_basedir, &**file).with_metadata();
+                ((Arc::clone(src_basedir), Arc::clone(file)), info)
+            })
+            .collect();
+        let duplicates = if options.dedup {
+            Duplicates::find(media_set.iter(), &mut media, options.hash)
+        } else {
+            Duplicates::default()
+        };
+        // Names that cannot be used on Windows are renamed whatever the
+        // options
+        let copied = media_set
+            .iter()
+            .filter(|(src_basedir, file)| duplicates.original(src_basedir, file).is_none());
+        let collisions = assign_dest_files(copied, &mut media, options)?;
+        for key in media_set.iter() {
+            check_windows_names(media[key].dest_file(), options)?;
+        }
+        let copied = media_set.iter().filter(|key @ (src_basedir, file)| {
+            duplicates.original(src_basedir, file).is_none() && !collisions.contains(*key)
+        });
+        let conflicts = Conflicts::find(copied, &mut media, options.on_conflict)?;
+        let total_media_files = media_set.len() - duplicates.len() - conflicts.len();
+
+        Ok(Self {
+            playlists,
+            media,
+            duplicates,
+            conflicts,
+            collisions,
+            total_media_files,
+        })
+    }
+
+    /// Add a playlist parsed by the pipeline, with the media files it
+    /// references for the first time, finding the conflicts of those
+    /// media files with the media files added earlier
+    ///
+    /// Only the first-wins conflict policy is resolved this way, and
+    /// media files are neither deduplicated nor renamed.
+    pub fn add_playlist(&mut self, parsed: ParsedPlaylist) {
+        let keys: Vec<MediaKey> = parsed.new_media.iter().map(|(key, _)| key.clone()).collect();
+        self.media.extend(parsed.new_media);
+        let n_conflicts = self.conflicts.add(keys.iter(), &self.media);
+        self.total_media_files += keys.len() - n_conflicts;
+        self.playlists.push(parsed.planned);
+    }
+
+    /// Total size of the source media files of all successfully parsed
+    /// playlists, as far as known
+    pub fn total_bytes(&self) -> u64 {
+        self.media.values().filter_map(|info| info.size).sum()
+    }
+
+    /// Iterate over the destination files (relative to the destination
+    /// root) of all successfully parsed playlists
+    pub fn dest_files(&self) -> impl Iterator<Item = &Path> {
+        self.media.values().map(|info| info.dest_file())
+    }
+
+    /// Entries of playlists in `src_basedir` naming a different file on
+    /// the destination: duplicates, pointed at the files of their
+    /// originals, conflicting media files left uncopied, pointed at the
+    /// files copied instead, and renamed media files
+    pub fn renames(&self, src_basedir: &Arc<str>) -> HashMap<String, String> {
+        self.media
+            .keys()
+            .filter(|(root, _)| root == src_basedir)
+            .filter_map(|key @ (root, file)| {
+                let target = self
+                    .duplicates
+                    .original(root, file)
+                    .or_else(|| self.conflicts.winner(root, file))
+                    .unwrap_or(key);
+                let dest = self.media[target].dest_file().to_string_lossy();
+                (dest != **file).then(|| (file.to_string(), dest.to_string()))
+            })
+            .collect()
+    }
+}
+
+/// Directory the entries of a playlist are relative to, the directory
+/// given by `--relative-to` or the directory of the playlist
+pub fn entries_dir(playlist: &Path, options: &TransferOptions) -> PathBuf {
+    match &options.relative_to {
+        Some(dir) => PathBuf::from(dir),
+        None => playlist_dir(playlist),
+    }
+}
+
+/// Extract media files from a playlist, interning the paths, expanding
+/// directory entries and leaving out the media files excluded by the
+/// options
+pub fn extract_media_files(
+    playlist: &str,
+    options: &TransferOptions,
+    interner: &mut PathInterner,
+) -> Result<(Arc<str>, Vec<Arc<str>>)> {
+    let mut media = read_playlist_media(Path::new(playlist), interner)
+        .with_context(|| format!("Failed to open playlist: {}", playlist))?;
+    if let Some(dir) = &options.relative_to {
+        media.src_root = interner.intern(dir);
+    }
+    filter_media_files(playlist, media, options, interner)
+}
+
+/// Extract media files from a file list, relative to the list root of
+/// the options or to the directory of the list, in the same way as
+/// from a playlist
+fn extract_listed_files(
+    list: &str,
+    options: &TransferOptions,
+    interner: &mut PathInterner,
+) -> Result<(Arc<str>, Vec<Arc<str>>)> {
+    let src_root = match &options.list_root {
+        Some(list_root) => PathBuf::from(list_root),
+        None => playlist_dir(Path::new(list)),
+    };
+    let media = read_list_media(Path::new(list), &src_root, interner)
+        .with_context(|| format!("Failed to read file list: {}", list))?;
+    filter_media_files(list, media, options, interner)
+}
+
+/// Make the absolute entries of the media files read from a playlist
+/// or a file list relative, expand their directory entries, and leave
+/// out the excluded ones
+fn filter_media_files(
+    playlist: &str,
+    mut media: PlaylistMedia,
+    options: &TransferOptions,
+    interner: &mut PathInterner,
+) -> Result<(Arc<str>, Vec<Arc<str>>)> {
+    media
+        .make_entries_relative(interner)
+        .map_err(|e| anyhow::anyhow!("Invalid entry in playlist {}: {}", playlist, e))?;
+    if options.expand_dirs {
+        media.expand_directories(interner).map_err(|e| {
+            anyhow::anyhow!("Failed to expand directories in playlist {}: {}", playlist, e)
+        })?;
+    }
+
+    if !options.exclusions.is_empty() {
+        let src_root = Path::new(&*media.src_root);
+        media
+            .files
+            .retain(|file| !options.exclusions.is_excluded(src_root, file));
+    }
+    if let Some(max_file_size) = options.max_file_size {
+        let src_root = Path::new(&*media.src_root);
+        media.files.retain(|file| {
+            let too_large = is_too_large(src_root, file, options);
+            if too_large {
+                eprintln!(
+                    "Warning: Skipping media file \"{}\" larger than {} in playlist {}",
+                    file,
+                    format_size(max_file_size),
+                    playlist
+                );
+            }
+            !too_large
+        });
+    }
+
+    Ok((media.src_root, media.files))
+}
+
+/// Whether the media file `file` of the directory `src_root` is larger
+/// than the maximum file size of the options
+///
+/// Media files that cannot be stat'ed are left to fail when copied.
+pub fn is_too_large(src_root: &Path, file: &str, options: &TransferOptions) -> bool {
+    options.max_file_size.is_some_and(|max_file_size| {
+        fs::metadata(src_root.join(file)).is_ok_and(|metadata| metadata.len() > max_file_size)
+    })
+}
+
+/// Leave out the media files without an allowed audio extension,
+/// warning about each of them
+///
+/// Returns the number of entries left out.
+pub fn skip_non_audio_files(
+    playlist: &str,
+    files: &mut Vec<Arc<str>>,
+    options: &TransferOptions,
+) -> usize {
+    let Some(allowlist) = &options.audio_extensions else {
+        return 0;
+    };
+
+    let n_files = files.len();
+    files.retain(|file| {
+        let allowed = allowlist.allows(file);
+        if !allowed {
+            eprintln!(
+                "Warning: Skipping non-audio entry \"{}\" in playlist {}",
+                file, playlist
+            );
+        }
+        allowed
+    });
+
+    n_files - files.len()
+}
+
+/// Playlists given on the command line, each once, in the order first
+/// given
+///
+/// Playlists are compared by their canonical paths, so the same playlist
+/// given through different paths, such as by overlapping globs, is
+/// processed once with a warning.  Playlists that cannot be resolved
+/// are compared as given and fail when processed.
+pub fn unique_playlists(playlists: &[String]) -> Vec<String> {
+    let mut seen: HashSet<PathBuf> = HashSet::new();
+    playlists
+        .iter()
+        .filter(|playlist| {
+            let path = fs::canonicalize(playlist).unwrap_or_else(|_| PathBuf::from(playlist));
+            let first = seen.insert(path);
+            if !first {
+                eprintln!("Warning: Skipping playlist {} given more than once", playlist);
+            }
+            first
+        })
+        .cloned()
+        .collect()
+}
+
+/// Playlists followed by the file lists of the options, each with
+/// whether it is a file list
+pub fn plan_sources<'a>(
+    playlists: &'a [String],
+    options: &'a TransferOptions,
+) -> impl Iterator<Item = (&'a String, bool)> {
+    playlists
+        .iter()
+        .map(|playlist| (playlist, false))
+        .chain(options.lists.iter().map(|list| (list, true)))
+}
+
+/// Parse a playlist, or a file list, into a planned playlist
+///
+/// A playlist failing to parse is an error unless `--keep-going` is
+/// given, in which case the error is kept in the planned playlist.
+pub fn plan_source(
+    playlist: &str,
+    is_list: bool,
+    options: &TransferOptions,
+    interner: &mut PathInterner,
+) -> Result<PlannedPlaylist> {
+    let media = match is_list {
+        true => extract_listed_files(playlist, options, interner),
+        false => extract_media_files(playlist, options, interner),
+    };
+    match media {
+        Err(e) if !options.keep_going => {
+            eprintln!(
+                "Error extracting media files from {} {}: {}",
+                if is_list { "file list" } else { "playlist" },
+                playlist,
+                e
+            );
+            Err(e)
+        }
+        mut media => {
+            let non_audio_entries = media.as_mut().map_or(0, |(_, files)| {
+                skip_non_audio_files(playlist, files, options)
+            });
+            Ok(PlannedPlaylist {
+                playlist: playlist.to_string(),
+                media,
+                non_audio_entries,
+                is_list,
+            })
+        }
+    }
+}
+
+/// Parse every playlist and every file list of the options once and
+/// build the copy plan
+pub fn plan_playlists(
+    playlists: &[String],
+    options: &TransferOptions,
+    interner: &mut PathInterner,
+) -> Result<CopyPlan> {
+    let mut planned_playlists = Vec::with_capacity(playlists.len() + options.lists.len());
+    for (playlist, is_list) in plan_sources(playlists, options) {
+        planned_playlists.push(plan_source(playlist, is_list, options, interner)?);
+    }
+
+    CopyPlan::new(planned_playlists, options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_plan_playlists_empty_playlists() -> Result<()> {
+        let options = TransferOptions::default();
+
+        let plan = plan_playlists(&[], &options, &mut PathInterner::new())?;
+        assert!(plan.playlists.is_empty());
+        assert_eq!(plan.total_media_files, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_playlists_with_keep_going() -> Result<()> {
+        let options = TransferOptions {
+            keep_going: true,
+            ..TransferOptions::default()
+        };
+
+        // Test with non-existent playlist files - should not fail with keep_going
+        let playlists = vec!["nonexistent1.m3u".to_string(), "nonexistent2.m3u".to_string()];
+        let plan = plan_playlists(&playlists, &options, &mut PathInterner::new())?;
+        assert_eq!(plan.playlists.len(), 2);
+        assert!(plan.playlists.iter().all(|planned| planned.media.is_err()));
+        assert_eq!(plan.total_media_files, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_playlists_without_keep_going() {
+        let options = TransferOptions::default();
+
+        // Test with non-existent playlist files - should fail without keep_going
+        let playlists = vec!["nonexistent.m3u".to_string()];
+        let result = plan_playlists(&playlists, &options, &mut PathInterner::new());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_plan_playlists_deduplication() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let playlist1_path = temp_dir.path().join("playlist1.m3u");
+        let playlist2_path = temp_dir.path().join("playlist2.m3u");
+
+        // Create two playlists with overlapping media files
+        fs::write(&playlist1_path, "song1.mp3\nsong2.mp3\n")?;
+        fs::write(&playlist2_path, "song2.mp3\nsong3.mp3\n")?;
+
+        let options = TransferOptions::default();
+
+        let playlists = vec![
+            playlist1_path.to_string_lossy().to_string(),
+            playlist2_path.to_string_lossy().to_string(),
+        ];
+
+        let plan = plan_playlists(&playlists, &options, &mut PathInterner::new())?;
+
+        // Should have 3 unique files (song1.mp3, song2.mp3, song3.mp3)
+        assert_eq!(plan.total_media_files, 3);
+
+        // Each playlist keeps its own entries in order
+        let temp_dir_str = temp_dir.path().to_string_lossy().to_string();
+        let (src_basedir, files) = plan.playlists[1].media.as_ref().unwrap();
+        assert_eq!(&**src_basedir, temp_dir_str);
+        let files: Vec<&str> = files.iter().map(|file| &**file).collect();
+        assert_eq!(files, vec!["song2.mp3", "song3.mp3"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_playlists_reads_metadata() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let playlist_path = temp_dir.path().join("playlist.m3u");
+
+        fs::write(&playlist_path, "song1.mp3
+missing.mp3
+")?;
+        fs::write(temp_dir.path().join("song1.mp3"), "test content")?;
+
+        let options = TransferOptions::default();
+
+        let playlists = vec![playlist_path.to_string_lossy().to_string()];
+        let plan = plan_playlists(&playlists, &options, &mut PathInterner::new())?;
+
+        let (src_basedir, files) = plan.playlists[0].media.as_ref().unwrap();
+        let song1 = &plan.media[&(Arc::clone(src_basedir), Arc::clone(&files[0]))];
+        assert_eq!(song1.src_path(), temp_dir.path().join("song1.mp3"));
+        assert_eq!(song1.size, Some(12));
+        assert!(song1.modified.is_some());
+
+        let missing = &plan.media[&(Arc::clone(src_basedir), Arc::clone(&files[1]))];
+        assert_eq!(missing.size, None);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+//! Checks of `--source-read-only`, failing a copy that would write under
+//! its source directories before anything is written
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::source_guard::SourceGuard;
+use crate::track_matcher::playlist_dir;
+
+use super::backup::Backup;
+use super::plan::CopyPlan;
+use super::TransferOptions;
+
+/// Guard protecting the directories of the playlists and file lists of
+/// a plan, the directories their entries are relative to and the
+/// directories of their media files
+pub fn plan_guard(plan: &CopyPlan) -> Result<SourceGuard> {
+    let mut guard = SourceGuard::new();
+    for planned in &plan.playlists {
+        guard.protect(&playlist_dir(Path::new(&planned.playlist)))?;
+        if let Ok((src_basedir, _)) = &planned.media {
+            guard.protect(Path::new(&**src_basedir))?;
+        }
+    }
+    for media_file in plan.media.values() {
+        guard.protect_file_dir(&media_file.src_path())?;
+    }
+    Ok(guard)
+}
+
+/// Guard protecting the directories of the playlists and file lists
+/// given, known before any of them is read
+///
+/// The destinations are checked against it before they are test-written.
+pub fn playlist_guard(playlists: &[String], lists: &[String]) -> Result<SourceGuard> {
+    let mut guard = SourceGuard::new();
+    for playlist in playlists.iter().chain(lists) {
+        guard.protect(&playlist_dir(Path::new(playlist)))?;
+    }
+    Ok(guard)
+}
+
+/// Guard protecting the directories of the playlists and media files of
+/// an error file retried, and the directories its media files are
+/// relative to
+pub fn retry_guard(
+    playlists: &[String],
+    media_files: &[(Arc<str>, Arc<str>)],
+) -> Result<SourceGuard> {
+    let mut guard = playlist_guard(playlists, &[])?;
+    for (src_basedir, file) in media_files {
+        guard.protect(Path::new(&**src_basedir))?;
+        guard.protect_file_dir(&Path::new(&**src_basedir).join(&**file))?;
+    }
+    Ok(guard)
+}
+
+/// Fail with `--source-read-only` if the destination root, the backup
+/// directory or one of the destination files, relative to the root,
+/// lies under a directory protected by `guard`
+///
+/// Lyrics, sidecar and artwork files and album playlists are written
+/// beside the media files and the playlists into the root, so checking
+/// these covers every file a copy writes or deletes.
+pub fn check_writes<'a>(
+    guard: &SourceGuard,
+    dest_root: &Path,
+    dest_files: impl Iterator<Item = &'a Path>,
+    options: &TransferOptions,
+) -> Result<()> {
+    if !options.source_read_only {
+        return Ok(());
+    }
+    guard.check(dest_root)?;
+    if let Backup::Dir(dir) = &options.backup {
+        guard.check(dir)?;
+    }
+    for dest_file in dest_files {
+        guard.check(&dest_root.join(dest_file))?;
+    }
+    Ok(())
+}
+
+/// Fail with `--source-read-only` if copying the plan into `dest_root`
+/// would write under its source directories
+pub fn check_plan(plan: &CopyPlan, dest_root: &Path, options: &TransferOptions) -> Result<()> {
+    if !options.source_read_only {
+        return Ok(());
+    }
+    check_writes(&plan_guard(plan)?, dest_root, plan.dest_files(), options)
+}
@@ -0,0 +1,96 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::copy_report::{ReportAction, ReportRow, ReportWriter};
+use crate::media_file_info::MediaFileInfo;
+
+use super::ProgressContext;
+
+/// Report of the media files processed by a run
+///
+/// Each row is appended to the report file and flushed as soon as it is
+/// recorded.  Like the error file, a write error does not stop the run;
+/// it is kept and reported by `finish()`.
+#[derive(Debug)]
+pub struct CopyReport {
+    writer: ReportWriter<File>,     // Report file to stream rows into
+    write_error: Option<io::Error>, // First error encountered while streaming
+}
+
+impl CopyReport {
+    /// Create a report streaming rows into the file at `path`
+    pub fn create(path: &str) -> Result<Self, io::Error> {
+        Ok(Self {
+            writer: ReportWriter::create(Path::new(path))?,
+            write_error: None,
+        })
+    }
+
+    /// Append a row to the report file immediately
+    fn record(&mut self, row: &ReportRow) {
+        if self.write_error.is_some() {
+            return;
+        }
+
+        if let Err(e) = self.writer.write_row(row) {
+            self.write_error = Some(e);
+        }
+    }
+
+    /// Complete the report file, reporting any error deferred while
+    /// streaming
+    pub fn finish(mut self) -> Result<(), io::Error> {
+        match self.write_error.take() {
+            Some(e) => Err(e),
+            None => self.writer.into_inner().sync_all(),
+        }
+    }
+}
+
+/// Record an attempt to copy a media file, with the error if it failed
+pub fn record_copy(
+    progress_context: &mut ProgressContext,
+    playlist: &str,
+    media_file: &MediaFileInfo,
+    dest_file: &Path,
+    duration: Duration,
+    error: Option<&anyhow::Error>,
+) {
+    if let Some(report) = &mut progress_context.report {
+        let reason = error.map(|e| e.to_string()).unwrap_or_default();
+        report.record(&ReportRow {
+            playlist,
+            source: &media_file.src_path(),
+            destination: dest_file,
+            action: ReportAction::Copy,
+            bytes: media_file.size,
+            duration: Some(duration),
+            succeeded: error.is_none(),
+            reason: &reason,
+        });
+    }
+}
+
+/// Record a media file left uncopied for `reason`
+pub fn record_skip(
+    progress_context: &mut ProgressContext,
+    playlist: &str,
+    media_file: &MediaFileInfo,
+    dest_root: &Path,
+    reason: &str,
+) {
+    if let Some(report) = &mut progress_context.report {
+        report.record(&ReportRow {
+            playlist,
+            source: &media_file.src_path(),
+            destination: &media_file.dest_path(dest_root),
+            action: ReportAction::Skip,
+            bytes: media_file.size,
+            duration: None,
+            succeeded: true,
+            reason,
+        });
+    }
+}
@@ -0,0 +1,622 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context as AnyhowContext, Result};
+
+use crate::dest_index::DestIndex;
+use crate::error_file::{ErrorEntry, ErrorFile, RunTotals};
+use crate::logger::Logger;
+use crate::media_file_info::MediaFileInfo;
+
+use super::copy::{copy_indexed_file, copy_media_files, print_missing_files};
+use super::names::{media_dest_name, with_dest_name};
+use super::read_only::{check_writes, retry_guard};
+use super::report::{record_skip, CopyReport};
+use super::sidecar::SidecarKind;
+use super::state::{load_sync_state, record_synced, save_sync_state};
+use super::summary::{Completion, CopySummary, PlaylistSummary};
+use super::{MediaContext, ProgressContext};
+
+/// Struct to hold destination directory information
+pub struct RetryContext {
+    pub dest_dir: String,
+    pub dest_index: DestIndex,
+}
+
+/// Failed playlists, (src_basedir, file) media files and lyrics files,
+/// and (src_basedir, file, sidecar) sidecar files of copied media files
+/// read from an error file
+#[derive(Debug, Default)]
+pub struct ErrorFileEntries {
+    pub playlists: Vec<String>,
+    pub media_files: Vec<(String, String)>,
+    pub lyrics_files: Vec<(String, String)>,
+    pub sidecars: Vec<(String, String, String)>,
+    pub totals: Option<RunTotals>, // Totals of the original run, if recorded
+}
+
+/// Parse an error file and extract failed playlists and media files
+///
+/// The parsed entries are reported as debug messages of `logger`.
+pub fn parse_error_file(path: &str, logger: &dyn Logger) -> Result<ErrorFileEntries> {
+    let ErrorFile { totals, entries, .. } = ErrorFile::read(Path::new(path))
+        .with_context(|| format!("Failed to read error file: {}", path))?;
+
+    let mut playlists = Vec::new();
+    let mut media_files = Vec::new();
+    let mut lyrics_files = Vec::new();
+    let mut sidecars = Vec::new();
+
+    logger.debug_formatted("Parsing error file \"{}\"", &[path]);
+
+    for entry in entries {
+        let file_path = entry.path().to_string_lossy().to_string();
+
+        match entry {
+            ErrorEntry::Playlist(_) => {
+                logger.debug_formatted("Found playlist \"{}\"", &[&file_path]);
+                playlists.push(file_path);
+            }
+            ErrorEntry::Media(_) => {
+                logger.debug_formatted("Found media file \"{}\"", &[&file_path]);
+                if let Some(media_file) = split_error_path(&file_path) {
+                    log_split_path(logger, &media_file);
+                    media_files.push(media_file);
+                }
+            }
+            ErrorEntry::Lyrics(_) => {
+                logger.debug_formatted("Found lyrics file \"{}\"", &[&file_path]);
+                if let Some(lyrics_file) = split_error_path(&file_path) {
+                    log_split_path(logger, &lyrics_file);
+                    lyrics_files.push(lyrics_file);
+                }
+            }
+            ErrorEntry::Sidecar { media, sidecar } => {
+                let media_path = media.to_string_lossy().to_string();
+                logger.debug_formatted(
+                    "Found sidecar file \"{}\" of media file \"{}\"",
+                    &[&file_path, &media_path],
+                );
+                if let Some((src_basedir, file)) = split_error_path(&media_path) {
+                    log_split_path(logger, &(src_basedir.clone(), file.clone()));
+                    let sidecar = sidecar
+                        .strip_prefix(&src_basedir)
+                        .unwrap_or(&sidecar)
+                        .to_string_lossy()
+                        .to_string();
+                    sidecars.push((src_basedir, file, sidecar));
+                }
+            }
+        }
+    }
+
+    logger.debug_formatted(
+        "Parsed {} playlists, {} media files, {} lyrics files and {} sidecar files",
+        &[
+            &playlists.len().to_string(),
+            &media_files.len().to_string(),
+            &lyrics_files.len().to_string(),
+            &sidecars.len().to_string(),
+        ],
+    );
+
+    Ok(ErrorFileEntries {
+        playlists,
+        media_files,
+        lyrics_files,
+        sidecars,
+        totals,
+    })
+}
+
+/// Split the full path of an error file entry into the base directory
+/// and the path relative to it
+fn split_error_path(file_path: &str) -> Option<(String, String)> {
+    let path = Path::new(file_path);
+
+    // Extract the base directory (up to the MUSIC directory) and the relative path
+    let path_str = path.to_string_lossy();
+    if let Some(music_idx) = path_str.find("/MUSIC/") {
+        // Extract the base directory (up to and including MUSIC)
+        let src_basedir = &path_str[..music_idx + 7]; // +7 to include "/MUSIC/"
+
+        // Extract the relative path (after MUSIC/)
+        let rel_path = &path_str[music_idx + 7..];
+
+        if !rel_path.is_empty() {
+            return Some((src_basedir.to_string(), rel_path.to_string()));
+        }
+    } else {
+        // Fallback to the old method if MUSIC directory is not found
+        let src_basedir = path
+            .parent()
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|| ".".to_string());
+
+        let file_name = path
+            .file_name()
+            .map(|f| f.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        if !file_name.is_empty() {
+            return Some((src_basedir, file_name));
+        }
+    }
+
+    None
+}
+
+/// Report how the path of an error file entry was split
+fn log_split_path(logger: &dyn Logger, (src_basedir, file): &(String, String)) {
+    logger.debug_formatted(
+        "Base directory \"{}\", relative path \"{}\"",
+        &[src_basedir, file],
+    );
+}
+
+/// Retry processing a single playlist from the error file
+pub fn retry_playlist(
+    playlist: &str,
+    retry_context: &mut RetryContext,
+    options: &super::TransferOptions,
+    error_tracker: &mut Option<&mut super::ErrorTracker>,
+    media_context: &mut MediaContext,
+    progress_context: &mut ProgressContext,
+) -> Result<(bool, usize)> {
+    progress_context.logger.log_formatted("Retrying playlist \"{}\"", &[playlist]);
+
+    let result = super::plan::extract_media_files(playlist, options, &mut media_context.interner).and_then(
+        |(src_basedir, mut files)| {
+            progress_context.non_audio_entries +=
+                super::plan::skip_non_audio_files(playlist, &mut files, options);
+            let renames: HashMap<String, String> = files
+                .iter()
+                .filter_map(|file| {
+                    let dest = media_dest_name(&src_basedir, file, options);
+                    (dest != **file).then(|| (file.to_string(), dest))
+                })
+                .collect();
+            let (dest_playlist, up_to_date) = super::file::process_playlist(
+                playlist,
+                &retry_context.dest_dir,
+                &renames,
+                options,
+                progress_context.current_playlist_num,
+                progress_context.total_playlists,
+                &*progress_context.logger,
+            )?;
+            progress_context.written_playlists.push(dest_playlist);
+            progress_context.up_to_date_playlists += usize::from(up_to_date);
+            Ok((src_basedir, files))
+        },
+    );
+
+    match result {
+        Ok((src_basedir, files)) => {
+            // Copy media files for this playlist
+            let files_to_copy = super::filter_already_copied_files(
+                &src_basedir,
+                &files,
+                &media_context.copied_files,
+            );
+            let duplicates = files.len() - files_to_copy.len();
+
+            progress_context.logger.log_formatted(
+                "Copying {} media files for playlist \"{}\"",
+                &[&files_to_copy.len().to_string(), playlist],
+            );
+            let media_files: Vec<MediaFileInfo> = files_to_copy
+                .iter()
+                .map(|file| {
+                    let media_file = MediaFileInfo::new(&*src_basedir, &**file).with_metadata();
+                    with_dest_name(media_file, options)
+                })
+                .collect();
+            let attempted = media_files.len();
+            let unchanged = progress_context.unchanged_media_files;
+            // The media files of the playlist count with those of the
+            // error file
+            if let Some(total) = &mut progress_context.total_media_files {
+                *total += attempted;
+            }
+            match copy_media_files(
+                playlist,
+                &mut retry_context.dest_index,
+                files_to_copy.into_iter().zip(media_files.iter()),
+                options,
+                error_tracker,
+                progress_context,
+            ) {
+                Ok((_, successful_files)) => {
+                    let successful_count = successful_files.len();
+                    let unchanged = progress_context.unchanged_media_files - unchanged;
+                    progress_context.playlist_summaries.push(
+                        PlaylistSummary::copied(
+                            playlist,
+                            files.len(),
+                            attempted - unchanged,
+                            successful_count - unchanged,
+                        )
+                        .with_duplicates(duplicates),
+                    );
+
+                    // Update copied_files set
+                    for file in successful_files {
+                        media_context
+                            .copied_files
+                            .insert((Arc::clone(&src_basedir), file));
+                    }
+
+                    Ok((true, successful_count))
+                }
+                Err(e) => {
+                    eprintln!("Error copying media files for playlist {}: {}", playlist, e);
+                    if !options.keep_going {
+                        return Err(e);
+                    }
+                    progress_context.playlist_summaries.push(
+                        PlaylistSummary::copied(playlist, files.len(), attempted, 0)
+                            .with_duplicates(duplicates),
+                    );
+                    Ok((true, 0))
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Error processing playlist {}: {}", playlist, e);
+            if let Some(tracker) = error_tracker {
+                tracker.add_failed_playlist(playlist.to_string());
+            }
+            if !options.keep_going {
+                return Err(e);
+            }
+            progress_context
+                .playlist_summaries
+                .push(PlaylistSummary::failed(playlist));
+            Ok((false, 0))
+        }
+    }
+}
+
+/// Retry copying a single media file from the error file
+///
+/// This function has been refactored to use:
+/// 1. A MediaFileInfo struct instead of separate src_basedir and file parameters
+/// 2. Grouped parameters for better organization using context structs
+///
+/// This reduces the number of arguments from the original 9 to 6.
+pub fn retry_media_file(
+    media_file: &MediaFileInfo,
+    retry_context: &mut RetryContext,
+    options: &super::TransferOptions,
+    error_tracker: &mut Option<&mut super::ErrorTracker>,
+    media_context: &mut MediaContext,
+    progress_context: &mut ProgressContext,
+) -> Result<usize> {
+    let file_full_path = media_file.src_path();
+    let src_basedir = media_context
+        .interner
+        .intern(&media_file.src_basedir.to_string_lossy());
+    let file = media_context.interner.intern(&media_file.file.to_string_lossy());
+
+    progress_context.logger.log_formatted(
+        "Retrying media file \"{}\"",
+        &[&file_full_path.to_string_lossy()],
+    );
+
+    // Check if this file has already been copied
+    if media_context
+        .copied_files
+        .contains(&(Arc::clone(&src_basedir), Arc::clone(&file)))
+    {
+        progress_context.logger.log_formatted(
+            "Skipping already copied file \"{}\"",
+            &[&file_full_path.to_string_lossy()],
+        );
+        let dest_root = retry_context.dest_index.root().to_path_buf();
+        record_skip(progress_context, "", media_file, &dest_root, "already copied");
+        return Ok(1);
+    }
+
+    // Copy the file
+    match copy_media_files(
+        "",
+        &mut retry_context.dest_index,
+        std::iter::once((file, media_file)),
+        options,
+        error_tracker,
+        progress_context,
+    ) {
+        Ok((_, successful_files)) => {
+            let successful_count = successful_files.len();
+
+            // Update copied_files set
+            for file in successful_files {
+                media_context
+                    .copied_files
+                    .insert((Arc::clone(&src_basedir), file));
+            }
+
+            Ok(successful_count)
+        }
+        Err(e) => {
+            eprintln!(
+                "Error copying media file {}: {}",
+                file_full_path.display(),
+                e
+            );
+            if !options.keep_going {
+                return Err(e);
+            }
+            Ok(0)
+        }
+    }
+}
+
+/// Retry copying a single lyrics file or other sidecar file from the
+/// error file
+///
+/// A file recorded as the sidecar file of `media_file` is recorded as
+/// such again if it fails.  Files other than lyrics files are counted
+/// as sidecar files.
+pub fn retry_sidecar_file(
+    sidecar_file: &MediaFileInfo,
+    media_file: Option<&MediaFileInfo>,
+    retry_context: &mut RetryContext,
+    options: &super::TransferOptions,
+    error_tracker: &mut Option<&mut super::ErrorTracker>,
+    progress_context: &mut ProgressContext,
+) -> Result<usize> {
+    let src_file = sidecar_file.src_path();
+    let dest_file = sidecar_file.dest_path(Path::new(&retry_context.dest_dir));
+    let kind = SidecarKind::of(&sidecar_file.file, options);
+
+    progress_context.logger.log_formatted(
+        "Retrying {} file \"{}\"",
+        &[kind.file_type(), &src_file.to_string_lossy()],
+    );
+
+    kind.count_attempt(progress_context);
+    match copy_indexed_file(
+        &src_file,
+        &dest_file,
+        &mut retry_context.dest_index,
+        options,
+        &*progress_context.logger,
+    ) {
+        Ok(checksum) => {
+            let dest_root = retry_context.dest_index.root().to_path_buf();
+            record_synced(progress_context, &src_file, &dest_file, &dest_root, checksum);
+            kind.count_success(progress_context);
+            progress_context.logger.log_with_counters(
+                "Copy {} \"{}\" to \"{}\"",
+                &[
+                    kind.file_type(),
+                    &src_file.to_string_lossy(),
+                    &dest_file.to_string_lossy(),
+                ],
+                None, // Don't increment counter for sidecar files
+                progress_context.total_media_files,
+                Some(kind.file_type()),
+            );
+            Ok(1)
+        }
+        Err(e) => {
+            eprintln!(
+                "Error copying {} file {}: {}",
+                kind.file_type(),
+                src_file.display(),
+                e
+            );
+            if let Some(tracker) = error_tracker {
+                let src_basedir = sidecar_file.src_basedir.to_string_lossy().to_string();
+                let file = sidecar_file.file.to_string_lossy().to_string();
+                match media_file {
+                    Some(media_file) => tracker.add_failed_sidecar(
+                        src_basedir,
+                        media_file.file.to_string_lossy().to_string(),
+                        file,
+                    ),
+                    None => tracker.add_failed_lyrics_file(src_basedir, file),
+                }
+            }
+            if !options.keep_going {
+                return Err(e);
+            }
+            Ok(0)
+        }
+    }
+}
+
+/// Sidecar file `sidecar` of a copied media file, to be copied next to
+/// the media file under its destination name
+fn sidecar_file(media_file: &MediaFileInfo, sidecar: &str) -> MediaFileInfo {
+    let mut sidecar_file = MediaFileInfo::new(&media_file.src_basedir, sidecar);
+    if let Some(extension) = sidecar_file.file.extension() {
+        sidecar_file.dest_file = Some(media_file.dest_file().with_extension(extension));
+    }
+    sidecar_file
+}
+
+/// Process retry operations from an error file
+pub fn retry_operations(
+    retry_file: &str,
+    dest_dir: &str,
+    options: &super::TransferOptions,
+    error_tracker: &mut Option<&mut super::ErrorTracker>,
+    report: Option<CopyReport>,
+    logger: Arc<dyn Logger>,
+) -> Result<CopySummary> {
+    logger.log_formatted(
+        "Retrying operations from error file \"{}\"",
+        &[retry_file],
+    );
+
+    let ErrorFileEntries {
+        playlists,
+        media_files,
+        lyrics_files,
+        sidecars,
+        totals,
+    } = parse_error_file(retry_file, &*logger)?;
+
+    // Carry the totals of the original run over to the new error file
+    if let (Some(tracker), Some(totals)) = (error_tracker.as_mut(), totals) {
+        tracker.set_totals(totals);
+    }
+
+    let total_playlists = playlists.len();
+    let total_media_files = media_files.len();
+    let mut successful_playlists = 0;
+    let mut successful_media_files = 0;
+
+    // Create context structs
+    let mut media_context = MediaContext::new();
+
+    let media_files: Vec<(Arc<str>, Arc<str>)> = media_files
+        .iter()
+        .map(|(src_basedir, file)| {
+            (
+                media_context.interner.intern(src_basedir),
+                media_context.interner.intern(file),
+            )
+        })
+        .collect();
+
+    let dest_files: Vec<PathBuf> = media_files
+        .iter()
+        .map(|(src_basedir, file)| PathBuf::from(media_dest_name(src_basedir, file, options)))
+        .collect();
+    if options.source_read_only {
+        let guard = retry_guard(&playlists, &media_files)?;
+        let dest_files = dest_files.iter().map(PathBuf::as_path);
+        check_writes(&guard, Path::new(dest_dir), dest_files, options)?;
+    }
+    let mut retry_context = RetryContext {
+        dest_dir: dest_dir.to_string(),
+        dest_index: super::scan_destination(dest_dir, dest_files.iter().map(PathBuf::as_path))?,
+    };
+
+    let mut progress_context = ProgressContext::new(logger, total_playlists, total_media_files);
+    progress_context.report = report;
+    progress_context.show_progress(options);
+    let total_bytes = media_files
+        .iter()
+        .filter_map(|(src_basedir, file)| {
+            fs::metadata(Path::new(&**src_basedir).join(&**file)).ok()
+        })
+        .map(|metadata| metadata.len())
+        .sum();
+    progress_context.set_progress_totals(total_bytes);
+    progress_context.sync_state = load_sync_state(Path::new(dest_dir), options)?;
+
+    // Process playlists first
+    for (i, playlist) in playlists.iter().enumerate() {
+        progress_context.current_playlist_num = Some(i + 1);
+
+        match retry_playlist(
+            playlist,
+            &mut retry_context,
+            options,
+            error_tracker,
+            &mut media_context,
+            &mut progress_context,
+        ) {
+            Ok((success, count)) => {
+                if success {
+                    successful_playlists += 1;
+                }
+                successful_media_files += count;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    // Process media files
+    for (src_basedir, file) in media_files.iter() {
+        let media_file = MediaFileInfo::new(&**src_basedir, &**file).with_metadata();
+        let media_file = with_dest_name(media_file, options);
+
+        match retry_media_file(
+            &media_file,
+            &mut retry_context,
+            options,
+            error_tracker,
+            &mut media_context,
+            &mut progress_context,
+        ) {
+            Ok(count) => {
+                successful_media_files += count;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    // Process lyrics files
+    for (src_basedir, file) in lyrics_files {
+        let lyrics_file = with_dest_name(MediaFileInfo::new(src_basedir, file), options);
+
+        retry_sidecar_file(
+            &lyrics_file,
+            None,
+            &mut retry_context,
+            options,
+            error_tracker,
+            &mut progress_context,
+        )?;
+    }
+
+    // Process the sidecar files of copied media files, without copying
+    // the media files again
+    for (src_basedir, file, sidecar) in sidecars {
+        let media_file = with_dest_name(MediaFileInfo::new(src_basedir, file), options);
+
+        retry_sidecar_file(
+            &sidecar_file(&media_file, &sidecar),
+            Some(&media_file),
+            &mut retry_context,
+            options,
+            error_tracker,
+            &mut progress_context,
+        )?;
+    }
+
+    print_missing_files(&progress_context);
+    save_sync_state(&progress_context, Path::new(dest_dir))?;
+
+    let completion = totals.map(|totals| {
+        Completion::after_retry(
+            totals,
+            total_playlists - successful_playlists,
+            progress_context.failed_media_files,
+        )
+    });
+
+    // Media files skipped as unchanged are counted as skipped
+    let total_media_files = progress_context
+        .total_media_files
+        .unwrap_or(total_media_files);
+    let unchanged_media_files = progress_context.unchanged_media_files;
+    Ok(CopySummary {
+        successful_playlists,
+        total_playlists,
+        successful_media_files: successful_media_files - unchanged_media_files,
+        total_media_files: total_media_files - unchanged_media_files,
+        successful_lyrics_files: progress_context.successful_lyrics_files,
+        total_lyrics_files: progress_context.total_lyrics_files,
+        successful_sidecar_files: progress_context.successful_sidecar_files,
+        total_sidecar_files: progress_context.total_sidecar_files,
+        successful_artwork_files: progress_context.successful_artwork_files,
+        total_artwork_files: progress_context.total_artwork_files,
+        non_audio_entries: progress_context.non_audio_entries,
+        unchanged_media_files,
+        playlists: progress_context.playlist_summaries,
+        written_playlists: progress_context.written_playlists,
+        up_to_date_playlists: progress_context.up_to_date_playlists,
+        dest_media_files: progress_context.dest_media_files,
+        report: progress_context.report,
+        completion,
+    })
+}
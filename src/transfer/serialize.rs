@@ -0,0 +1,61 @@
+//! Serialization of the parts of the copy plan that serde cannot derive
+//! as they are
+//!
+//! Maps keyed by media files are serialized as sequences of pairs, as
+//! formats such as JSON only take strings as keys, and the errors of
+//! playlists failing to parse as their messages.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Map serialized as a sequence of (key, value) pairs
+pub mod pairs {
+    use super::*;
+
+    pub fn serialize<S, K, V>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        K: Serialize,
+        V: Serialize,
+    {
+        serializer.collect_seq(map)
+    }
+
+    pub fn deserialize<'de, D, K, V>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+    where
+        D: Deserializer<'de>,
+        K: Deserialize<'de> + Eq + Hash,
+        V: Deserialize<'de>,
+    {
+        Vec::<(K, V)>::deserialize(deserializer).map(|pairs| pairs.into_iter().collect())
+    }
+}
+
+/// Media files of a planned playlist, or the message of the error
+/// parsing it
+pub mod parse_result {
+    use super::*;
+
+    type Media = (Arc<str>, Vec<Arc<str>>);
+
+    pub fn serialize<S>(media: &anyhow::Result<Media>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        media
+            .as_ref()
+            .map_err(|e| e.to_string())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<anyhow::Result<Media>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Result::<Media, String>::deserialize(deserializer)
+            .map(|media| media.map_err(anyhow::Error::msg))
+    }
+}
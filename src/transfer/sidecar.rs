@@ -0,0 +1,272 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::dest_index::DestIndex;
+use crate::media_file_info::MediaFileInfo;
+
+use super::copy::copy_indexed_file;
+use super::itemize::{print_change, Change};
+use super::state::{forget_synced, record_synced};
+use super::{ErrorTracker, ProgressContext, TransferOptions};
+
+/// Extension of lyrics files unless `--lyrics-ext` gives others
+pub const LYRICS_EXTENSION: &str = "lrc";
+
+/// Kind of a file copied alongside a media file and sharing its stem,
+/// counted separately in the summary
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SidecarKind {
+    /// Lyrics file, with one of the extensions given to `--lyrics-ext`
+    Lyrics,
+    /// Any other sidecar file, such as a `.cue` sheet
+    Other,
+}
+
+impl SidecarKind {
+    /// Kind of the sidecar files with the extension `extension`, given
+    /// the extensions of lyrics files
+    pub fn of_extension(extension: &str, lyrics_extensions: &[String]) -> Self {
+        match lyrics_extensions
+            .iter()
+            .any(|lyrics| lyrics.eq_ignore_ascii_case(extension))
+        {
+            true => SidecarKind::Lyrics,
+            false => SidecarKind::Other,
+        }
+    }
+
+    /// Kind of a sidecar file by its extension
+    pub fn of(file: &Path, options: &TransferOptions) -> Self {
+        let extension = file.extension().unwrap_or_default();
+        Self::of_extension(&extension.to_string_lossy(), &options.lyrics_extensions)
+    }
+
+    /// File type naming the kind in messages and counters
+    pub fn file_type(self) -> &'static str {
+        match self {
+            SidecarKind::Lyrics => "lyrics",
+            SidecarKind::Other => "sidecar",
+        }
+    }
+
+    /// Count an attempt to copy a sidecar file of the kind
+    pub fn count_attempt(self, progress_context: &mut ProgressContext) {
+        match self {
+            SidecarKind::Lyrics => progress_context.total_lyrics_files += 1,
+            SidecarKind::Other => progress_context.total_sidecar_files += 1,
+        }
+    }
+
+    /// Count a sidecar file of the kind successfully copied
+    pub fn count_success(self, progress_context: &mut ProgressContext) {
+        match self {
+            SidecarKind::Lyrics => progress_context.successful_lyrics_files += 1,
+            SidecarKind::Other => progress_context.successful_sidecar_files += 1,
+        }
+    }
+}
+
+/// Extensions of the sidecar files copied alongside each media file:
+/// those of lyrics files with `--lyrics`, followed by those given to
+/// `--sidecar`, each listed once
+pub fn sidecar_extensions(options: &TransferOptions) -> Vec<&str> {
+    let lyrics: &[String] = match options.copy_lyrics {
+        true => &options.lyrics_extensions,
+        false => &[],
+    };
+    let mut extensions: Vec<&str> = Vec::new();
+    for extension in lyrics
+        .iter()
+        .map(String::as_str)
+        .chain(options.sidecars.iter().map(String::as_str))
+    {
+        if !extensions
+            .iter()
+            .any(|listed| listed.eq_ignore_ascii_case(extension))
+        {
+            extensions.push(extension);
+        }
+    }
+    extensions
+}
+
+/// Sidecar files of a media file present in the source, as pairs of
+/// their full source paths and their paths relative to the destination
+/// root
+pub fn present_sidecar_files(
+    media_file: &MediaFileInfo,
+    options: &TransferOptions,
+) -> Vec<(PathBuf, PathBuf)> {
+    sidecar_extensions(options)
+        .into_iter()
+        .filter_map(|extension| {
+            let sidecar_file = media_file.sidecar_file(extension)?;
+            let dest_sidecar_file = media_file.dest_sidecar_file(extension)?;
+            let sidecar_path = media_file.src_basedir.join(sidecar_file);
+            sidecar_path
+                .exists()
+                .then_some((sidecar_path, dest_sidecar_file))
+        })
+        .collect()
+}
+
+/// Copy the sidecar files of a media file present in the source, the
+/// lyrics file with `--lyrics` and those with the extensions given to
+/// `--sidecar`, recording the copies in the sync state
+///
+/// Returns the number of files copied
+pub fn copy_sidecar_files(
+    media_file: &MediaFileInfo,
+    dest_index: &mut DestIndex,
+    options: &TransferOptions,
+    error_tracker: &mut Option<&mut ErrorTracker>,
+    progress_context: &mut ProgressContext,
+) -> Result<usize> {
+    let mut n_files = 0;
+    for (sidecar_path, dest_sidecar_file) in present_sidecar_files(media_file, options) {
+        n_files += copy_sidecar_file(
+            media_file,
+            &sidecar_path,
+            &dest_sidecar_file,
+            dest_index,
+            options,
+            error_tracker,
+            progress_context,
+        )?;
+    }
+    Ok(n_files)
+}
+
+/// Copy a sidecar file of a media file
+///
+/// Returns the number of files copied
+fn copy_sidecar_file(
+    media_file: &MediaFileInfo,
+    sidecar_path: &Path,
+    dest_sidecar_file: &Path,
+    dest_index: &mut DestIndex,
+    options: &TransferOptions,
+    error_tracker: &mut Option<&mut ErrorTracker>,
+    progress_context: &mut ProgressContext,
+) -> Result<usize> {
+    let kind = SidecarKind::of(sidecar_path, options);
+    let dest_root = dest_index.root().to_path_buf();
+    let dest_sidecar_path = dest_root.join(dest_sidecar_file);
+    kind.count_attempt(progress_context);
+    let change = options
+        .itemize
+        .then(|| Change::of_file(sidecar_path, &dest_sidecar_path, dest_index));
+
+    // Copy the sidecar file, tracking a failure as an "S" entry of the
+    // media file so that a retry copies only the sidecar file
+    let logger = &*progress_context.logger;
+    match copy_indexed_file(
+        sidecar_path,
+        &dest_sidecar_path,
+        dest_index,
+        options,
+        logger,
+    ) {
+        Ok(checksum) => {
+            record_synced(
+                progress_context,
+                sidecar_path,
+                &dest_sidecar_path,
+                &dest_root,
+                checksum,
+            );
+            kind.count_success(progress_context);
+            if let Some(change) = change {
+                print_change(change, dest_sidecar_file);
+            }
+            Ok(1)
+        }
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            forget_synced(progress_context, &dest_sidecar_path, &dest_root);
+            if let Some(tracker) = error_tracker {
+                let sidecar_file = sidecar_path
+                    .strip_prefix(&media_file.src_basedir)
+                    .unwrap_or(sidecar_path);
+                tracker.add_failed_sidecar(
+                    media_file.src_basedir.to_string_lossy().to_string(),
+                    media_file.file.to_string_lossy().to_string(),
+                    sidecar_file.to_string_lossy().to_string(),
+                );
+            }
+            match options.keep_going {
+                true => Ok(0),
+                false => Err(err),
+            }
+        }
+    }
+}
+
+/// Log the copy of the sidecar files of a media file present in the
+/// source under the counter of the media file, suffixed with the kind
+/// of each sidecar file
+pub fn log_sidecar_files(
+    media_file: &MediaFileInfo,
+    dest_root: &Path,
+    options: &TransferOptions,
+    progress_context: &ProgressContext,
+) {
+    for (sidecar_path, dest_sidecar_file) in present_sidecar_files(media_file, options) {
+        let kind = SidecarKind::of(&sidecar_path, options);
+        let dest_sidecar_path = dest_root.join(dest_sidecar_file);
+        progress_context.logger.log_with_counters(
+            match kind {
+                SidecarKind::Lyrics => "Copy lyrics \"{}\" to \"{}\"",
+                SidecarKind::Other => "Copy sidecar \"{}\" to \"{}\"",
+            },
+            &[
+                &sidecar_path.to_string_lossy(),
+                &dest_sidecar_path.to_string_lossy(),
+            ],
+            Some(progress_context.processed_media_files()),
+            progress_context.total_media_files,
+            Some(kind.file_type()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sidecar_kind() {
+        let options = TransferOptions::default();
+        let kind = |file: &str| SidecarKind::of(Path::new(file), &options);
+        assert_eq!(kind("a/title.lrc"), SidecarKind::Lyrics);
+        assert_eq!(kind("a/title.LRC"), SidecarKind::Lyrics);
+        assert_eq!(kind("a/title.cue"), SidecarKind::Other);
+        assert_eq!(kind("a/title"), SidecarKind::Other);
+
+        let options = TransferOptions {
+            lyrics_extensions: vec!["lrc".to_string(), "txt".to_string()],
+            ..TransferOptions::default()
+        };
+        let kind = |file: &str| SidecarKind::of(Path::new(file), &options);
+        assert_eq!(kind("a/title.txt"), SidecarKind::Lyrics);
+        assert_eq!(kind("a/title.cue"), SidecarKind::Other);
+    }
+
+    #[test]
+    fn test_sidecar_extensions() {
+        let options = TransferOptions {
+            copy_lyrics: true,
+            lyrics_extensions: vec!["lrc".to_string(), "txt".to_string()],
+            sidecars: vec!["cue".to_string(), "TXT".to_string()],
+            ..TransferOptions::default()
+        };
+        assert_eq!(sidecar_extensions(&options), ["lrc", "txt", "cue"]);
+
+        let options = TransferOptions {
+            copy_lyrics: false,
+            ..options
+        };
+        assert_eq!(sidecar_extensions(&options), ["cue", "TXT"]);
+    }
+}
@@ -0,0 +1,274 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+
+use crate::diagnostics::{format_size, free_space};
+use crate::logger::Logger;
+use crate::media_file_info::MediaFileInfo;
+use crate::media_set::MediaKey;
+use crate::path_interner::PathInterner;
+use crate::track_matcher::absolute_path;
+
+use super::normal::process_normal_operations;
+use super::plan::{plan_playlists, CopyPlan};
+use super::read_only::{check_writes, plan_guard};
+use super::report::CopyReport;
+use super::sidecar::present_sidecar_files;
+use super::summary::CopySummary;
+use super::{ErrorTracker, TransferOptions};
+
+/// Further destinations the tracks not fitting into the destination are
+/// put into with `--spillover`, and how full each destination may get
+#[derive(Debug)]
+pub struct Spillover {
+    pub dests: Vec<String>,    // Absolute paths of the further destinations
+    pub min_free: u64,         // Bytes left free on each destination
+    pub max_fill: Option<u64>, // Bytes of files put into each destination at most
+}
+
+/// Assign each track, in order, to the destination filled last or to
+/// one after it with room for the track, given the size the track takes
+/// on each destination and the room left on each
+///
+/// Returns the destination of each track, or `None` for the tracks
+/// fitting into none.
+fn fill(sizes: &[Vec<u64>], room: &mut [u64]) -> Vec<Option<usize>> {
+    let mut current = 0;
+    sizes
+        .iter()
+        .map(|track_sizes| {
+            let dest = (current..room.len()).find(|&i| track_sizes[i] <= room[i])?;
+            room[dest] -= track_sizes[dest];
+            current = dest;
+            Some(dest)
+        })
+        .collect()
+}
+
+/// Bytes a track takes on the destination at `dest_root`: its media
+/// file and its sidecar files copied with it, except those already
+/// there with the same size
+fn track_size(media_file: &MediaFileInfo, dest_root: &Path, options: &TransferOptions) -> u64 {
+    let size_to_copy = |size: Option<u64>, dest_path: &Path| match size {
+        Some(size) if fs::metadata(dest_path).is_ok_and(|dest| dest.len() == size) => 0,
+        size => size.unwrap_or(0),
+    };
+    let sidecars: u64 = present_sidecar_files(media_file, options)
+        .iter()
+        .map(|(src_path, dest_file)| {
+            let size = fs::metadata(src_path).ok().map(|src| src.len());
+            size_to_copy(size, &dest_root.join(dest_file))
+        })
+        .sum();
+    size_to_copy(media_file.size, &media_file.dest_path(dest_root)) + sidecars
+}
+
+/// Room for files on each destination: its free space less the space to
+/// leave free, limited to the most to put into it
+fn destination_room(dests: &[&str], spillover: &Spillover) -> Vec<u64> {
+    dests
+        .iter()
+        .map(|dest| {
+            let free = free_space(Path::new(dest))
+                .map_or(u64::MAX, |free| free.saturating_sub(spillover.min_free));
+            spillover
+                .max_fill
+                .map_or(free, |max_fill| free.min(max_fill))
+        })
+        .collect()
+}
+
+/// Unique media files of the plan in the order they are copied
+fn unique_media(plan: &CopyPlan) -> Vec<MediaKey> {
+    let mut seen: HashSet<MediaKey> = HashSet::new();
+    plan.playlists
+        .iter()
+        .filter_map(|planned| planned.media.as_ref().ok())
+        .flat_map(|(src_basedir, files)| {
+            files
+                .iter()
+                .map(|file| (Arc::clone(src_basedir), Arc::clone(file)))
+        })
+        .filter(|key| seen.insert(key.clone()))
+        .collect()
+}
+
+/// Report the tracks fitting into no destination, failing unless
+/// `--keep-going` is given
+fn check_unfit(unfit: &[(&MediaKey, u64)], options: &TransferOptions) -> Result<()> {
+    if unfit.is_empty() {
+        return Ok(());
+    }
+    let kind = match options.keep_going {
+        true => "Warning: Skipping media file fitting into no destination",
+        false => "Error: Media file fits into no destination",
+    };
+    for ((src_basedir, file), _) in unfit {
+        eprintln!(
+            "{}: {}",
+            kind,
+            Path::new(&**src_basedir).join(&**file).display()
+        );
+    }
+    if !options.keep_going {
+        let short: u64 = unfit.iter().map(|(_, size)| size).sum();
+        anyhow::bail!(
+            "{} media files fit into no destination ({})",
+            unfit.len(),
+            format_size(short)
+        );
+    }
+    Ok(())
+}
+
+/// Put the playlists into the destination and the further destinations
+/// of `--spillover`, filling each in turn up to the space left free
+///
+/// The tracks are assigned to the destinations in the order they are
+/// copied, before anything is copied.  Each destination then gets the
+/// playlists with tracks on it, referencing only those tracks, as the
+/// tracks on the other destinations are excluded.  Playlists failing
+/// to parse are put into the first destination only.
+pub fn process_spillover(
+    playlists: &[String],
+    dest_dir: &str,
+    spillover: &Spillover,
+    options: &mut TransferOptions,
+    error_tracker_ref: &mut Option<&mut ErrorTracker>,
+    report: Option<CopyReport>,
+    logger: Arc<dyn Logger>,
+) -> Result<CopySummary> {
+    // Identical media files are sized separately, as they may end up on
+    // different destinations
+    let dedup = mem::replace(&mut options.dedup, false);
+    let plan = plan_playlists(playlists, options, &mut PathInterner::new());
+    options.dedup = dedup;
+    let plan = plan?;
+
+    let dests: Vec<&str> = std::iter::once(dest_dir)
+        .chain(spillover.dests.iter().map(String::as_str))
+        .collect();
+    // Each destination is checked again with the media files put into
+    // it, but none is filled before all are known to be safe
+    if options.source_read_only {
+        let guard = plan_guard(&plan)?;
+        for dest in &dests {
+            check_writes(&guard, Path::new(dest), std::iter::empty(), options)?;
+        }
+    }
+    let keys = unique_media(&plan);
+    let sizes: Vec<Vec<u64>> = keys
+        .iter()
+        .map(|key| {
+            dests
+                .iter()
+                .map(|dest| track_size(&plan.media[key], Path::new(dest), options))
+                .collect()
+        })
+        .collect();
+    let mut room = destination_room(&dests, spillover);
+    let assigned = fill(&sizes, &mut room);
+
+    let unfit: Vec<(&MediaKey, u64)> = keys
+        .iter()
+        .zip(&assigned)
+        .zip(&sizes)
+        .filter(|((_, dest), _)| dest.is_none())
+        .map(|((key, _), sizes)| (key, sizes[0]))
+        .collect();
+    check_unfit(&unfit, options)?;
+
+    let paths: HashMap<&MediaKey, PathBuf> = keys
+        .iter()
+        .filter_map(|key| {
+            let path = absolute_path(&plan.media[key].src_path()).ok()?;
+            Some((key, path))
+        })
+        .collect();
+    let dest_of: HashMap<&MediaKey, Option<usize>> = keys.iter().zip(assigned).collect();
+
+    let mut summary: Option<CopySummary> = None;
+    let mut report = report;
+    for (i, dest) in dests.iter().enumerate() {
+        let on_dest = |key: &MediaKey| dest_of.get(key).is_some_and(|&dest| dest == Some(i));
+        let dest_playlists: Vec<String> = plan
+            .playlists
+            .iter()
+            .filter(|planned| !planned.is_list)
+            .filter(|planned| match &planned.media {
+                Ok((src_basedir, files)) if !files.is_empty() => files
+                    .iter()
+                    .any(|file| on_dest(&(Arc::clone(src_basedir), Arc::clone(file)))),
+                _ => i == 0,
+            })
+            .map(|planned| planned.playlist.clone())
+            .collect();
+        let n_files = keys.iter().filter(|key| on_dest(key)).count();
+        if dest_playlists.is_empty() && n_files == 0 {
+            continue;
+        }
+
+        let size: u64 = keys
+            .iter()
+            .zip(&sizes)
+            .filter(|(key, _)| on_dest(key))
+            .map(|(_, sizes)| sizes[i])
+            .sum();
+        println!(
+            "Destination {}/{}: {} ({} media files, {})",
+            i + 1,
+            dests.len(),
+            dest,
+            n_files,
+            format_size(size)
+        );
+        options.exclusions.set_paths(
+            paths
+                .iter()
+                .filter(|(key, _)| !on_dest(key))
+                .map(|(_, path)| path.clone())
+                .collect(),
+        );
+        let mut dest_summary = process_normal_operations(
+            &dest_playlists,
+            dest,
+            options,
+            error_tracker_ref,
+            report,
+            Arc::clone(&logger),
+        )?;
+        report = dest_summary.report.take();
+        summary = Some(match summary {
+            Some(mut summary) => {
+                summary.add(dest_summary);
+                summary
+            }
+            None => dest_summary,
+        });
+    }
+    options.exclusions.set_paths(HashSet::new());
+
+    let mut summary = summary.unwrap_or_default();
+    summary.report = report;
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill() {
+        let sizes = vec![vec![4, 4], vec![4, 4], vec![1, 1], vec![8, 0], vec![9, 9]];
+        let mut room = vec![6, 8];
+        assert_eq!(
+            fill(&sizes, &mut room),
+            vec![Some(0), Some(1), Some(1), Some(1), None]
+        );
+        assert_eq!(room, vec![2, 3]);
+    }
+}
@@ -0,0 +1,165 @@
+use std::fs;
+use std::path::{Component, Path};
+use std::sync::OnceLock;
+use std::time::SystemTime;
+
+use anyhow::Result;
+
+use crate::dest_index::{DestIndex, FileStatus};
+use crate::file_utils::file_checksum;
+use crate::sync_state::{host_name, unix_seconds, SyncState, SyncedFile};
+
+use super::{ProgressContext, TransferOptions};
+
+/// Read the sync state of the destination if `--sync-state` is given
+pub fn load_sync_state(dest_root: &Path, options: &TransferOptions) -> Result<Option<SyncState>> {
+    if !options.sync_state {
+        return Ok(None);
+    }
+
+    SyncState::load(dest_root).map(Some).map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to read sync state of {}: {}",
+            dest_root.display(),
+            e
+        )
+    })
+}
+
+/// Write the sync state kept by the run back to the destination
+pub fn save_sync_state(progress_context: &ProgressContext, dest_root: &Path) -> Result<()> {
+    match &progress_context.sync_state {
+        Some(sync_state) => sync_state.save(dest_root).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to write sync state of {}: {}",
+                dest_root.display(),
+                e
+            )
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Path of a destination file in the sync state, relative to the
+/// destination root with `/` separators whatever the platform
+fn state_path(dest_path: &Path, dest_root: &Path) -> String {
+    let rel_path = dest_path.strip_prefix(dest_root).unwrap_or(dest_path);
+    rel_path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Size and modification time of a file, in seconds
+fn size_and_mtime(path: &Path) -> Option<(u64, Option<u64>)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok().and_then(unix_seconds);
+    Some((metadata.len(), modified))
+}
+
+/// Whether the file at `dest_path` was synced from a source identical to
+/// `src_path` as it is now, so that copying it again can be skipped
+///
+/// The destination file must still have the size recorded, and the
+/// source the same size.  The source must then be the one recorded on
+/// this computer with the same modification time, or, as when the
+/// destination was synced from another computer, have the checksum
+/// recorded.
+pub fn is_unchanged(
+    sync_state: &SyncState,
+    src_path: &Path,
+    dest_path: &Path,
+    dest_index: &DestIndex,
+) -> bool {
+    let Some(synced) = sync_state.get(&state_path(dest_path, dest_index.root())) else {
+        return false;
+    };
+    let dest_size = match dest_index.file_status(dest_path) {
+        FileStatus::Present(entry) => Some(entry.len),
+        FileStatus::Absent => None,
+        FileStatus::Unknown => size_and_mtime(dest_path).map(|(size, _)| size),
+    };
+    let Some((src_size, src_modified)) = size_and_mtime(src_path) else {
+        return false;
+    };
+    if dest_size != Some(synced.size) || src_size != synced.size {
+        return false;
+    }
+
+    let same_source = synced.source == src_path.to_string_lossy() && synced.host == *host();
+    if same_source && src_modified.is_some() && src_modified == synced.modified {
+        return true;
+    }
+    synced.crc32.is_some() && file_checksum(src_path).ok() == synced.crc32
+}
+
+/// Record a file copied from `src_path` to `dest_path` in the sync state
+/// kept by the run, with the checksum of the source if computed while
+/// copying it
+pub fn record_synced(
+    progress_context: &mut ProgressContext,
+    src_path: &Path,
+    dest_path: &Path,
+    dest_root: &Path,
+    checksum: Option<u32>,
+) {
+    let Some(sync_state) = &mut progress_context.sync_state else {
+        return;
+    };
+    let path = state_path(dest_path, dest_root);
+    match size_and_mtime(src_path) {
+        Some((size, modified)) => {
+            let synced = SyncedFile {
+                source: src_path.to_string_lossy().to_string(),
+                host: host().clone(),
+                size,
+                modified,
+                crc32: checksum,
+                synced: unix_seconds(SystemTime::now()).unwrap_or_default(),
+            };
+            sync_state.record(&path, synced);
+        }
+        None => {
+            sync_state.forget(&path);
+        }
+    }
+}
+
+/// Forget a file that failed to be copied to `dest_path`, which may have
+/// been left incomplete
+pub fn forget_synced(progress_context: &mut ProgressContext, dest_path: &Path, dest_root: &Path) {
+    if let Some(sync_state) = &mut progress_context.sync_state {
+        sync_state.forget(&state_path(dest_path, dest_root));
+    }
+}
+
+/// Name of this computer, looked up once per run
+fn host() -> &'static String {
+    static HOST: OnceLock<String> = OnceLock::new();
+    HOST.get_or_init(host_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_state_path() {
+        let dest_root = Path::new("/mnt/player");
+        assert_eq!(
+            state_path(
+                Path::new("/mnt/player/artist1/album1/title1.flac"),
+                dest_root
+            ),
+            "artist1/album1/title1.flac"
+        );
+        assert_eq!(
+            state_path(Path::new("artist1/title1.flac"), dest_root),
+            "artist1/title1.flac"
+        );
+    }
+}
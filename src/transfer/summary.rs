@@ -0,0 +1,306 @@
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+use crate::error_file::RunTotals;
+
+use super::report::CopyReport;
+
+/// Numbers of files copied by a run, printed as the summary
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CopySummary {
+    pub successful_playlists: usize,
+    pub total_playlists: usize,
+    pub successful_media_files: usize,
+    pub total_media_files: usize,
+    pub successful_lyrics_files: usize,
+    pub total_lyrics_files: usize,
+    pub successful_sidecar_files: usize,
+    pub total_sidecar_files: usize,
+    pub successful_artwork_files: usize,
+    pub total_artwork_files: usize,
+    pub non_audio_entries: usize,
+    pub unchanged_media_files: usize, // Media files skipped, not counted in total_media_files
+    #[cfg_attr(feature = "serde", serde(rename = "per_playlist"))]
+    pub playlists: Vec<PlaylistSummary>,
+    pub written_playlists: Vec<PathBuf>,
+    pub up_to_date_playlists: usize, // Playlists left untouched as up to date
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub dest_media_files: Vec<PathBuf>, // Destination paths of the media files processed
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub report: Option<CopyReport>,
+    pub completion: Option<Completion>, // Completion of the original run after a retry
+}
+
+impl CopySummary {
+    /// Add the numbers of a run into another destination, keeping the
+    /// report of the later run
+    pub fn add(&mut self, other: CopySummary) {
+        self.successful_playlists += other.successful_playlists;
+        self.total_playlists += other.total_playlists;
+        self.successful_media_files += other.successful_media_files;
+        self.total_media_files += other.total_media_files;
+        self.successful_lyrics_files += other.successful_lyrics_files;
+        self.total_lyrics_files += other.total_lyrics_files;
+        self.successful_sidecar_files += other.successful_sidecar_files;
+        self.total_sidecar_files += other.total_sidecar_files;
+        self.successful_artwork_files += other.successful_artwork_files;
+        self.total_artwork_files += other.total_artwork_files;
+        self.non_audio_entries += other.non_audio_entries;
+        self.unchanged_media_files += other.unchanged_media_files;
+        self.playlists.extend(other.playlists);
+        self.written_playlists.extend(other.written_playlists);
+        self.up_to_date_playlists += other.up_to_date_playlists;
+        self.dest_media_files.extend(other.dest_media_files);
+        self.report = other.report.or(self.report.take());
+    }
+
+    /// Number of entries of all playlists skipped for naming a media
+    /// file named earlier
+    pub fn duplicate_references(&self) -> usize {
+        self.playlists
+            .iter()
+            .map(|playlist| playlist.duplicates)
+            .sum()
+    }
+}
+
+/// Numbers of playlists and media files present on the destination
+/// after a retry, out of the totals of the original run
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Completion {
+    pub present_playlists: usize,
+    pub total_playlists: usize,
+    pub present_media_files: usize,
+    pub total_media_files: usize,
+}
+
+impl Completion {
+    /// Completion of an original run with `totals` after a retry that
+    /// left `failed_playlists` and `failed_media_files` failed
+    ///
+    /// Every failure of the original run is retried, so the files
+    /// missing are those that failed again.
+    pub fn after_retry(
+        totals: RunTotals,
+        failed_playlists: usize,
+        failed_media_files: usize,
+    ) -> Self {
+        Self {
+            present_playlists: totals.playlists.saturating_sub(failed_playlists),
+            total_playlists: totals.playlists,
+            present_media_files: totals.media_files.saturating_sub(failed_media_files),
+            total_media_files: totals.media_files,
+        }
+    }
+}
+
+/// Numbers of the media entries of a single playlist, by what was done
+/// with them
+///
+/// Entries are the media files left after exclusions.  Skipped entries
+/// were already copied for an earlier playlist, repeat an earlier entry
+/// of the same playlist or are identical to another media file, and
+/// failed entries could not be copied.  Duplicates are the skipped
+/// entries naming a media file named earlier, in this playlist or an
+/// earlier one.
+#[derive(Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlaylistSummary {
+    pub playlist: String,
+    pub playlist_copied: bool,
+    pub entries: usize,
+    pub copied: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    #[cfg_attr(feature = "serde", serde(rename = "duplicate_references"))]
+    pub duplicates: usize,
+}
+
+impl PlaylistSummary {
+    /// Summary of a playlist that could not be copied
+    pub fn failed(playlist: &str) -> Self {
+        Self {
+            playlist: playlist.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Summary of a copied playlist with `entries` media entries, of
+    /// which `attempted` were copied and `copied` succeeded
+    pub fn copied(playlist: &str, entries: usize, attempted: usize, copied: usize) -> Self {
+        Self {
+            playlist: playlist.to_string(),
+            playlist_copied: true,
+            entries,
+            copied,
+            skipped: entries - attempted,
+            failed: attempted - copied,
+            duplicates: 0,
+        }
+    }
+
+    /// The summary with `duplicates` of its skipped entries counted as
+    /// duplicate references
+    pub fn with_duplicates(self, duplicates: usize) -> Self {
+        Self { duplicates, ..self }
+    }
+
+    /// Line printed for the playlist in the summary
+    fn line(&self) -> String {
+        let mut line = format!(
+            "{}: {} entries, {} copied, {} skipped, {} failed",
+            self.playlist, self.entries, self.copied, self.skipped, self.failed
+        );
+        if self.duplicates > 0 {
+            line += &format!(", {} duplicate references skipped", self.duplicates);
+        }
+        match self.playlist_copied {
+            true => line,
+            false => format!("{} (playlist not copied)", line),
+        }
+    }
+}
+
+/// Print the summary of a run, followed by a line for each playlist with
+/// `per_playlist`
+pub fn print_summary(summary: &CopySummary, per_playlist: bool) {
+    println!(
+        "({}/{}) playlist copied",
+        summary.successful_playlists, summary.total_playlists
+    );
+    println!(
+        "({}/{}) media files copied",
+        summary.successful_media_files, summary.total_media_files
+    );
+    if summary.total_lyrics_files > 0 {
+        println!(
+            "({}/{}) lyrics files copied",
+            summary.successful_lyrics_files, summary.total_lyrics_files
+        );
+    }
+    if summary.total_sidecar_files > 0 {
+        println!(
+            "({}/{}) sidecar files copied",
+            summary.successful_sidecar_files, summary.total_sidecar_files
+        );
+    }
+    if summary.total_artwork_files > 0 {
+        println!(
+            "({}/{}) artwork files copied",
+            summary.successful_artwork_files, summary.total_artwork_files
+        );
+    }
+    if summary.up_to_date_playlists > 0 {
+        println!("({}) playlists up to date", summary.up_to_date_playlists);
+    }
+    if summary.non_audio_entries > 0 {
+        println!("({}) non-audio entries skipped", summary.non_audio_entries);
+    }
+    if summary.unchanged_media_files > 0 {
+        println!(
+            "({}) unchanged media files skipped",
+            summary.unchanged_media_files
+        );
+    }
+    let duplicate_references = summary.duplicate_references();
+    if duplicate_references > 0 {
+        println!("({}) duplicate references skipped", duplicate_references);
+    }
+    if let Some(completion) = &summary.completion {
+        println!(
+            "({}/{}) playlists present after retry",
+            completion.present_playlists, completion.total_playlists
+        );
+        println!(
+            "({}/{}) media files present after retry",
+            completion.present_media_files, completion.total_media_files
+        );
+    }
+    if per_playlist {
+        for playlist in &summary.playlists {
+            println!("{}", playlist.line());
+        }
+    }
+}
+
+/// Summary of a run as JSON, with every playlist, the number of
+/// duplicate references and the run ID
+///
+/// A written playlist whose path is not valid UTF-8 cannot be given.
+pub fn json_summary(summary: &CopySummary, run_id: &str) -> serde_json::Result<Value> {
+    let mut json = serde_json::to_value(summary)?;
+    json["duplicate_references"] = Value::from(summary.duplicate_references());
+    json["run_id"] = Value::from(run_id);
+    Ok(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playlist_summary_line() {
+        let summary = PlaylistSummary::copied("/music/playlist.m3u8", 5, 3, 2);
+        assert_eq!(summary.skipped, 2);
+        assert_eq!(summary.failed, 1);
+        assert_eq!(
+            summary.line(),
+            "/music/playlist.m3u8: 5 entries, 2 copied, 2 skipped, 1 failed"
+        );
+
+        assert_eq!(
+            PlaylistSummary::failed("missing.m3u8").line(),
+            "missing.m3u8: 0 entries, 0 copied, 0 skipped, 0 failed (playlist not copied)"
+        );
+
+        let summary = PlaylistSummary::copied("p.m3u8", 4, 2, 2).with_duplicates(2);
+        assert_eq!(
+            summary.line(),
+            "p.m3u8: 4 entries, 2 copied, 2 skipped, 0 failed, 2 duplicate references skipped"
+        );
+    }
+
+    #[test]
+    fn test_json_summary() {
+        let summary = CopySummary {
+            successful_playlists: 2,
+            total_playlists: 2,
+            successful_media_files: 3,
+            total_media_files: 3,
+            playlists: vec![
+                PlaylistSummary::copied("p1.m3u8", 4, 3, 3).with_duplicates(1),
+                PlaylistSummary::copied("p2.m3u8", 2, 0, 0).with_duplicates(2),
+            ],
+            ..CopySummary::default()
+        };
+        let json = json_summary(&summary, "run").unwrap();
+        assert_eq!(json["run_id"], "run");
+        assert_eq!(json["successful_media_files"], 3);
+        assert_eq!(json["total_media_files"], 3);
+        assert_eq!(json["duplicate_references"], 3);
+        assert_eq!(json["per_playlist"][1]["duplicate_references"], 2);
+        assert_eq!(json["completion"], Value::Null);
+        assert!(json.get("report").is_none());
+
+        // The summary is read back as it was written
+        let read: CopySummary = serde_json::from_value(json).unwrap();
+        assert_eq!(read.playlists, summary.playlists);
+        assert_eq!(read.total_media_files, 3);
+    }
+
+    #[test]
+    fn test_completion_after_retry() {
+        let totals = RunTotals {
+            playlists: 3,
+            media_files: 1500,
+        };
+        let completion = Completion::after_retry(totals, 0, 20);
+        assert_eq!(completion.present_playlists, 3);
+        assert_eq!(completion.present_media_files, 1480);
+        assert_eq!(completion.total_media_files, 1500);
+        assert_eq!(Completion::after_retry(totals, 5, 0).present_playlists, 0);
+    }
+}
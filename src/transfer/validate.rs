@@ -0,0 +1,69 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::playlist_scanner::parse_playlist;
+use crate::track_matcher::playlist_dir;
+use crate::xspf::{is_xspf_file, XspfPlaylist};
+
+use super::TransferOptions;
+
+/// Check that every entry of the destination playlists resolves to a
+/// file on the destination
+///
+/// Each playlist is read back in the encoding it was written in and
+/// parsed with the playlist scanner, so entries the device would fail to
+/// open are reported with a warning.  Returns the numbers of resolved
+/// entries and of all entries.
+pub fn validate_playlists(playlists: &[PathBuf], options: &TransferOptions) -> (usize, usize) {
+    let mut resolved_entries = 0;
+    let mut total_entries = 0;
+
+    for playlist in playlists {
+        match validate_playlist(playlist, options) {
+            Ok((resolved, total)) => {
+                resolved_entries += resolved;
+                total_entries += total;
+            }
+            Err(e) => {
+                eprintln!(
+                    "Warning: Failed to read playlist {} back: {}",
+                    playlist.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    (resolved_entries, total_entries)
+}
+
+/// Check the entries of a single destination playlist, in UTF-8 if it
+/// is an XSPF playlist
+fn validate_playlist(playlist: &Path, options: &TransferOptions) -> io::Result<(usize, usize)> {
+    let entries: Vec<String> = if is_xspf_file(playlist) {
+        let xspf = XspfPlaylist::read(playlist)?;
+        xspf.entries().map(str::to_string).collect()
+    } else {
+        let content = options.dest_encoding.decode(&fs::read(playlist)?);
+        parse_playlist(&content).collect()
+    };
+    let dest_dir = playlist_dir(playlist);
+    let mut resolved = 0;
+    let mut total = 0;
+
+    for entry in entries {
+        total += 1;
+        if dest_dir.join(&entry).is_file() {
+            resolved += 1;
+        } else {
+            eprintln!(
+                "Warning: Entry \"{}\" of playlist {} does not resolve to a file",
+                entry,
+                playlist.display()
+            );
+        }
+    }
+
+    Ok((resolved, total))
+}
@@ -0,0 +1,80 @@
+//! ASCII transliteration of names written to a device
+//!
+//! Some players render non-Latin names as boxes or fail to open the
+//! files altogether.  Non-ASCII characters are replaced by the ASCII
+//! approximations of the transliteration table of the `deunicode`
+//! crate, so that `Björk` becomes `Bjork` and `アーティスト` becomes
+//! `ateisuto`.
+
+/// Characters not allowed in FAT file names, replaced by `_` when an
+/// approximation contains them
+pub use crate::file_utils::RESERVED_CHARS;
+
+/// Replacement of characters without an approximation
+const REPLACEMENT: &str = "_";
+
+/// Transliterate a part of a name, dropping the space the table leaves
+/// after the syllables of ideographs
+fn transliterate_part(part: &str) -> String {
+    deunicode::deunicode_with_tofu(part, REPLACEMENT)
+        .trim_end()
+        .replace(RESERVED_CHARS, REPLACEMENT)
+}
+
+/// Transliterate a file or directory name into ASCII
+///
+/// The stem and the extension are transliterated separately, so that
+/// `曲.flac` becomes `Qu.flac`.  ASCII names are returned unchanged.
+pub fn transliterate_name(name: &str) -> String {
+    if name.is_ascii() {
+        return name.to_string();
+    }
+
+    let transliterated = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => {
+            format!(
+                "{}.{}",
+                transliterate_part(stem),
+                transliterate_part(extension)
+            )
+        }
+        _ => transliterate_part(name),
+    };
+
+    if transliterated.is_empty() || transliterated.starts_with('.') {
+        format!("{}{}", REPLACEMENT, transliterated)
+    } else {
+        transliterated
+    }
+}
+
+/// Transliterate each component of a relative path separated by `/`
+pub fn transliterate_path(path: &str) -> String {
+    path.split('/')
+        .map(transliterate_name)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transliterate_name() {
+        assert_eq!(transliterate_name("title1.flac"), "title1.flac");
+        assert_eq!(transliterate_name("Björk - Jóga.flac"), "Bjork - Joga.flac");
+        assert_eq!(transliterate_name("アーティスト"), "ateisuto");
+        assert_eq!(transliterate_name("曲.flac"), "Qu.flac");
+        assert_eq!(transliterate_name("½.mp3"), "1_2.mp3");
+        assert_eq!(transliterate_name("\u{e000}.mp3"), "_.mp3");
+    }
+
+    #[test]
+    fn test_transliterate_path() {
+        assert_eq!(
+            transliterate_path("東京事変/教育/01 Café.flac"),
+            "Dong Jing Shi Bian/Jiao Yu/01 Cafe.flac"
+        );
+    }
+}
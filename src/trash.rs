@@ -0,0 +1,187 @@
+//! Trash of a destination, keeping the files deleted from it for a
+//! while before they are deleted for good
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::archive::{civil_from_days, days_from_civil};
+
+/// Directory of a destination holding the trash folders, one per day
+pub const TRASH_DIR: &str = ".plm-trash";
+
+/// Days after 1970-01-01 of today in UTC
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() / 86_400)
+}
+
+/// Name of the trash folder of the files deleted `days` days after
+/// 1970-01-01, such as `2026-10-16`
+pub fn trash_folder_name(days: u64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Days after 1970-01-01 of the trash folder named `name`, or `None`
+/// if the name is not a date
+pub fn trash_folder_days(name: &str) -> Option<u64> {
+    let mut fields = name.split('-');
+    let mut field = |len: usize| {
+        fields
+            .next()
+            .filter(|f| f.len() == len && f.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|f| f.parse::<u64>().ok())
+    };
+    let (year, month, day) = (field(4)?, field(2)?, field(2)?);
+    match fields.next() {
+        Some(_) => None,
+        None => days_from_civil(year, month, day),
+    }
+}
+
+/// Parse an age such as `30d` or `2w` into a number of days
+pub fn parse_age(age: &str) -> Result<u64, String> {
+    let (number, days_per_unit) = match age.strip_suffix('w') {
+        Some(weeks) => (weeks, 7),
+        None => (age.strip_suffix('d').unwrap_or(age), 1),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(days_per_unit))
+        .ok_or_else(|| format!("Invalid age: {} (expected days such as 30d)", age))
+}
+
+/// Move the file at `path` under `dest` into the trash folder `folder`
+/// of `dest`, keeping its path relative to `dest`, and return its path
+/// in the trash
+///
+/// A file already in the trash folder under the same path is kept, and
+/// a number is appended to the name of the one moved, such as
+/// `title1.flac.1`.
+pub fn move_to_trash(dest: &Path, path: &Path, folder: &str) -> io::Result<PathBuf> {
+    let relative = path.strip_prefix(dest).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Not under {}: {}", dest.display(), path.display()),
+        )
+    })?;
+    let trash_path = dest.join(TRASH_DIR).join(folder).join(relative);
+    let mut to = trash_path.clone();
+    let mut n = 0;
+    while to.symlink_metadata().is_ok() {
+        n += 1;
+        let mut name = trash_path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}", n));
+        to = trash_path.with_file_name(name);
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(path, &to)?;
+    Ok(to)
+}
+
+/// Trash folders of `dest` more than `older_than` days old on the day
+/// `today`, or all of them without `older_than`, in order of date
+///
+/// Entries of the trash whose names are not dates are left out.
+pub fn expired_trash_folders(
+    dest: &Path,
+    older_than: Option<u64>,
+    today: u64,
+) -> io::Result<Vec<PathBuf>> {
+    let trash = dest.join(TRASH_DIR);
+    if !trash.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut folders = Vec::new();
+    for entry in fs::read_dir(&trash)? {
+        let entry = entry?;
+        let Some(days) = entry.file_name().to_str().and_then(trash_folder_days) else {
+            continue;
+        };
+        let expired = older_than.is_none_or(|age| today.saturating_sub(days) > age);
+        if expired && entry.file_type()?.is_dir() {
+            folders.push((days, entry.path()));
+        }
+    }
+    folders.sort();
+    Ok(folders.into_iter().map(|(_, path)| path).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_trash_folder_name() {
+        assert_eq!(trash_folder_name(20_742), "2026-10-16");
+        assert_eq!(trash_folder_days("2026-10-16"), Some(20_742));
+        assert_eq!(trash_folder_days("2026-02-30"), None);
+        assert_eq!(trash_folder_days("2026-10-16-1"), None);
+        assert_eq!(trash_folder_days("26-10-16"), None);
+        assert_eq!(trash_folder_days("notes"), None);
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("30d"), Ok(30));
+        assert_eq!(parse_age("2w"), Ok(14));
+        assert_eq!(parse_age("7"), Ok(7));
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("1m").is_err());
+        assert!(parse_age("").is_err());
+        assert_eq!(
+            parse_age("9999999999999999999w"),
+            Err("Invalid age: 9999999999999999999w (expected days such as 30d)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_move_to_trash() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dest = temp_dir.path();
+        fs::create_dir_all(dest.join("artist/album"))?;
+        for content in ["first", "second"] {
+            fs::write(dest.join("artist/album/title.flac"), content)?;
+            move_to_trash(dest, &dest.join("artist/album/title.flac"), "2026-10-16")?;
+        }
+
+        let folder = dest.join(TRASH_DIR).join("2026-10-16/artist/album");
+        assert_eq!(fs::read_to_string(folder.join("title.flac"))?, "first");
+        assert_eq!(fs::read_to_string(folder.join("title.flac.1"))?, "second");
+        assert!(!dest.join("artist/album/title.flac").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_expired_trash_folders() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dest = temp_dir.path();
+        assert!(expired_trash_folders(dest, None, 20_742)?.is_empty());
+        for name in ["2026-10-16", "2026-09-16", "2026-09-15", "notes"] {
+            fs::create_dir_all(dest.join(TRASH_DIR).join(name))?;
+        }
+
+        let trash = dest.join(TRASH_DIR);
+        assert_eq!(
+            expired_trash_folders(dest, Some(30), 20_742)?,
+            vec![trash.join("2026-09-15")]
+        );
+        assert_eq!(
+            expired_trash_folders(dest, None, 20_742)?,
+            vec![
+                trash.join("2026-09-15"),
+                trash.join("2026-09-16"),
+                trash.join("2026-10-16")
+            ]
+        );
+        Ok(())
+    }
+}
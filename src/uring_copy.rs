@@ -0,0 +1,319 @@
+//! Copying batches of files through io_uring on Linux
+//!
+//! The portable copier reads and writes one file at a time, which
+//! leaves fast NVMe staging areas and network filesystems waiting on
+//! each request in turn.  This module keeps several files in flight in
+//! a single io_uring instance: each file being copied has a read or a
+//! write of its next chunk queued, and the completions are handled in
+//! whatever order the kernel finishes them.
+//!
+//! Setting up the ring fails on kernels without io_uring or where it is
+//! disabled, such as in many containers, in which case the caller is
+//! expected to fall back to [`crate::file_utils::copy_file_buffered`].
+
+use std::fs::File;
+use std::io;
+use std::os::fd::AsRawFd;
+use std::path::Path;
+
+use io_uring::{opcode, squeue, types, IoUring};
+
+/// Number of files copied at the same time by default
+pub const DEFAULT_FILES_IN_FLIGHT: usize = 16;
+
+/// A file being copied, with the chunk it is reading or writing
+struct Transfer {
+    index: usize, // Index of the file in the batch
+    src: File,
+    dest: File,
+    buffer: Vec<u8>,
+    offset: u64,    // Offset of the chunk in the files
+    filled: usize,  // Bytes of the chunk read from the source
+    written: usize, // Bytes of the chunk written to the destination
+    writing: bool,
+    hasher: crc32fast::Hasher,
+}
+
+impl Transfer {
+    /// Queue entry reading the next chunk from the source
+    fn read_entry(&mut self, slot: usize) -> squeue::Entry {
+        let fd = types::Fd(self.src.as_raw_fd());
+        let len = self.buffer.len() as u32;
+        self.writing = false;
+        opcode::Read::new(fd, self.buffer.as_mut_ptr(), len)
+            .offset(self.offset)
+            .build()
+            .user_data(slot as u64)
+    }
+
+    /// Queue entry writing what is left of the chunk to the destination
+    fn write_entry(&mut self, slot: usize) -> squeue::Entry {
+        let fd = types::Fd(self.dest.as_raw_fd());
+        let remaining = &self.buffer[self.written..self.filled];
+        self.writing = true;
+        opcode::Write::new(fd, remaining.as_ptr(), remaining.len() as u32)
+            .offset(self.offset + self.written as u64)
+            .build()
+            .user_data(slot as u64)
+    }
+
+    /// Queue entry continuing the copy after an operation of `len` bytes
+    /// completed, or `None` once the source is exhausted
+    fn next_entry(&mut self, slot: usize, len: usize) -> io::Result<Option<squeue::Entry>> {
+        if !self.writing {
+            if len == 0 {
+                return Ok(None);
+            }
+            self.hasher.update(&self.buffer[..len]);
+            self.filled = len;
+            self.written = 0;
+            return Ok(Some(self.write_entry(slot)));
+        }
+
+        if len == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        self.written += len;
+        if self.written < self.filled {
+            return Ok(Some(self.write_entry(slot)));
+        }
+        self.offset += self.filled as u64;
+        Ok(Some(self.read_entry(slot)))
+    }
+}
+
+/// Copies each `(source, destination)` pair of `files` in chunks of
+/// `buffer_size` bytes, with up to `files_in_flight` files at a time,
+/// computing the CRC-32 checksum of each file as it is written.
+///
+/// `prepare` is called with the paths of each file just before it is
+/// opened, such as to back up the destination file it overwrites, and
+/// the file fails with its error.  With `stop_on_error`, no file is
+/// started once one has failed.
+///
+/// Returns the checksum of each file or the error copying it, in the
+/// order of `files`, or `None` for the files left uncopied after an
+/// error.  Returns an error without copying anything if the ring cannot
+/// be set up, and an error after copying some files if the ring stops
+/// accepting requests, so that the files can be copied again with the
+/// portable copier either way.
+pub fn copy_files<P: AsRef<Path>>(
+    files: &[(P, P)],
+    buffer_size: usize,
+    files_in_flight: usize,
+    stop_on_error: bool,
+    mut prepare: impl FnMut(&Path, &Path) -> io::Result<()>,
+) -> io::Result<Vec<Option<io::Result<u32>>>> {
+    let files_in_flight = files_in_flight.clamp(1, files.len().max(1));
+    let mut ring = IoUring::new(files_in_flight.next_power_of_two() as u32)?;
+
+    let mut results: Vec<Option<io::Result<u32>>> = files.iter().map(|_| None).collect();
+    let mut slots: Vec<Option<Transfer>> = (0..files_in_flight).map(|_| None).collect();
+    let mut pending = files.iter().enumerate();
+    let mut in_flight = 0;
+
+    // Start a file in each slot, then keep each slot busy until the
+    // files run out
+    let mut entries = Vec::new();
+    for slot in 0..files_in_flight {
+        entries.extend(start_next(
+            &mut pending,
+            &mut slots,
+            slot,
+            buffer_size,
+            &mut results,
+            &mut prepare,
+            stop_on_error,
+        ));
+    }
+
+    loop {
+        for entry in entries.drain(..) {
+            // SAFETY: the buffer and the file descriptors of the entry
+            // belong to a transfer kept in its slot until the entry
+            // completes
+            if unsafe { ring.submission().push(&entry) }.is_err() {
+                std::mem::forget(slots);
+                return Err(io::Error::other("io_uring submission queue is full"));
+            }
+            in_flight += 1;
+        }
+        if in_flight == 0 {
+            break;
+        }
+
+        if let Err(e) = ring.submit_and_wait(1) {
+            if e.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            // Operations may still be using the buffers
+            std::mem::forget(slots);
+            return Err(e);
+        }
+
+        let completions: Vec<(usize, i32)> = ring
+            .completion()
+            .map(|cqe| (cqe.user_data() as usize, cqe.result()))
+            .collect();
+        for (slot, result) in completions {
+            in_flight -= 1;
+            let Some(transfer) = slots[slot].as_mut() else {
+                continue;
+            };
+            let next = match result {
+                len if len >= 0 => transfer.next_entry(slot, len as usize),
+                errno => Err(io::Error::from_raw_os_error(-errno)),
+            };
+            match next {
+                Ok(Some(entry)) => entries.push(entry),
+                Ok(None) | Err(_) => {
+                    let transfer = slots[slot].take().expect("slot of a completed transfer");
+                    if next.is_err() && stop_on_error {
+                        pending.by_ref().for_each(drop);
+                    }
+                    results[transfer.index] = Some(next.map(|_| transfer.hasher.finalize()));
+                    entries.extend(start_next(
+                        &mut pending,
+                        &mut slots,
+                        slot,
+                        buffer_size,
+                        &mut results,
+                        &mut prepare,
+                        stop_on_error,
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(results)
+}
+
+/// Opens the next file that can be prepared and opened into `slot`,
+/// recording the files that cannot, and returns the entry reading its
+/// first chunk
+///
+/// The files left are dropped from `pending` once one fails with
+/// `stop_on_error`.
+fn start_next<'a, P: AsRef<Path> + 'a>(
+    pending: &mut impl Iterator<Item = (usize, &'a (P, P))>,
+    slots: &mut [Option<Transfer>],
+    slot: usize,
+    buffer_size: usize,
+    results: &mut [Option<io::Result<u32>>],
+    prepare: &mut impl FnMut(&Path, &Path) -> io::Result<()>,
+    stop_on_error: bool,
+) -> Option<squeue::Entry> {
+    while let Some((index, (src_path, dest_path))) = pending.next() {
+        let (src_path, dest_path) = (src_path.as_ref(), dest_path.as_ref());
+        let files = prepare(src_path, dest_path)
+            .and_then(|()| File::open(src_path))
+            .and_then(|src| Ok((src, File::create(dest_path)?)));
+        match files {
+            Ok((src, dest)) => {
+                let transfer = slots[slot].insert(Transfer {
+                    index,
+                    src,
+                    dest,
+                    buffer: vec![0; buffer_size.max(1)],
+                    offset: 0,
+                    filled: 0,
+                    written: 0,
+                    writing: false,
+                    hasher: crc32fast::Hasher::new(),
+                });
+                return Some(transfer.read_entry(slot));
+            }
+            Err(e) => {
+                results[index] = Some(Err(e));
+                if stop_on_error {
+                    pending.by_ref().for_each(drop);
+                }
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_copy_files() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+
+        // Five files through two slots, chunked unevenly, one missing
+        let mut files = Vec::new();
+        for i in 0..5 {
+            let content: Vec<u8> = (0..i * 3000).map(|n| (n * 7 + i) as u8).collect();
+            let src = root.join(format!("src{}.flac", i));
+            if i != 3 {
+                fs::write(&src, &content)?;
+            }
+            files.push((src, root.join(format!("dest{}.flac", i))));
+        }
+
+        let mut prepared = Vec::new();
+        let prepare = |src: &Path, _: &Path| {
+            prepared.push(src.to_path_buf());
+            Ok(())
+        };
+        let results = match copy_files(&files, 4096, 2, false, prepare) {
+            Ok(results) => results,
+            // io_uring is unavailable where the tests run
+            Err(_) => return Ok(()),
+        };
+
+        assert_eq!(results.len(), 5);
+        assert_eq!(prepared.len(), 5);
+        assert!(results[3].as_ref().is_some_and(|result| result.is_err()));
+        for (i, (src, dest)) in files.iter().enumerate().filter(|(i, _)| *i != 3) {
+            let content = fs::read(src)?;
+            assert_eq!(fs::read(dest)?, content);
+            let checksum = results[i].as_ref().and_then(|result| result.as_ref().ok());
+            assert_eq!(checksum, Some(&crc32fast::hash(&content)));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_files_stop_on_error() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let root = temp_dir.path();
+
+        // The second file fails to be prepared, so the files after it
+        // are neither prepared nor copied through a single slot
+        let mut files = Vec::new();
+        for i in 0..4 {
+            let src = root.join(format!("src{}.flac", i));
+            fs::write(&src, format!("content {}", i))?;
+            files.push((src, root.join(format!("dest{}.flac", i))));
+        }
+        let mut prepared = 0;
+        let prepare = |_: &Path, _: &Path| {
+            prepared += 1;
+            match prepared {
+                2 => Err(io::Error::other("cannot back up")),
+                _ => Ok(()),
+            }
+        };
+        let results = match copy_files(&files, 4096, 1, true, prepare) {
+            Ok(results) => results,
+            // io_uring is unavailable where the tests run
+            Err(_) => return Ok(()),
+        };
+
+        assert!(results[0].as_ref().is_some_and(|result| result.is_ok()));
+        assert!(results[1].as_ref().is_some_and(|result| result.is_err()));
+        assert!(results[2].is_none() && results[3].is_none());
+        assert_eq!(prepared, 2);
+        assert!(!files[2].1.exists() && !files[3].1.exists());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,370 @@
+//! Reading and writing of XSPF (XML Shareable Playlist Format) playlists
+//!
+//! XSPF playlists, exported by VLC and other players, list their tracks
+//! as `<track>` elements whose `<location>` is a URI, usually a `file:`
+//! URI of a local file.  Only the parts of XML used by XSPF playlists are
+//! read: the document is not validated, and a playlist rewritten keeps
+//! everything but the locations of its tracks as it is.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::playlist_scanner::{decode_file_uri, decode_percent};
+
+/// Extension of XSPF playlists
+pub const XSPF_EXTENSION: &str = "xspf";
+
+/// Whether the path has the extension of XSPF playlists (`.xspf`)
+pub fn is_xspf_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case(XSPF_EXTENSION))
+}
+
+/// Track of an XSPF playlist
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XspfTrack {
+    indent: String,               // Whitespace before the element
+    element: String,              // Text of the element
+    pub location: Option<String>, // Path of its first location
+    pub title: Option<String>,
+    pub creator: Option<String>,
+    pub duration: Option<u64>, // Milliseconds
+}
+
+impl XspfTrack {
+    /// Parse the text of a track element, indented by `indent`
+    fn parse(indent: &str, element: &str) -> Self {
+        Self {
+            indent: indent.to_string(),
+            element: element.to_string(),
+            location: element_text(element, "location").map(|uri| location_path(&uri)),
+            title: element_text(element, "title"),
+            creator: element_text(element, "creator"),
+            duration: element_text(element, "duration").and_then(|ms| ms.trim().parse().ok()),
+        }
+    }
+
+    /// The track with its location replaced by the path `entry`
+    fn with_location(&self, entry: &str) -> Self {
+        let mut track = self.clone();
+        let uri = escape_text(&path_uri(entry));
+        track.element = match element_range(&self.element, "location") {
+            Some((start, end)) => {
+                format!("{}{}{}", &self.element[..start], uri, &self.element[end..])
+            }
+            None => self.element.clone(),
+        };
+        track.location = Some(entry.to_string());
+        track
+    }
+
+    /// Track of its own at the path `entry`, without any other data,
+    /// indented as `self`
+    fn new_at(&self, entry: &str) -> Self {
+        let element = format!(
+            "<track><location>{}</location></track>",
+            escape_text(&path_uri(entry))
+        );
+        Self::parse(&self.indent, &element)
+    }
+}
+
+/// XSPF playlist, kept as text around its tracks
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XspfPlaylist {
+    head: String, // Text before the first track
+    pub tracks: Vec<XspfTrack>,
+    tail: String, // Text after the last track
+}
+
+impl XspfPlaylist {
+    /// Read and parse the XSPF playlist at `path`
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    /// Parse the contents of an XSPF playlist
+    pub fn parse(content: &str) -> Self {
+        let mut tracks = Vec::new();
+        let mut head_end = None;
+        let mut end = 0;
+        while let Some(start) = find_element(content, "track", end) {
+            let Some(close) = content[start..].find("</track>") else {
+                break;
+            };
+            // The whitespace before a track is its indentation
+            let lead = match head_end {
+                Some(_) => end,
+                None => content[..start].trim_end().len(),
+            };
+            head_end.get_or_insert(lead);
+            end = start + close + "</track>".len();
+            tracks.push(XspfTrack::parse(
+                &content[lead..start],
+                &content[start..end],
+            ));
+        }
+
+        let head_end = head_end.unwrap_or(content.len());
+        Self {
+            head: content[..head_end].to_string(),
+            tracks,
+            tail: content[end.max(head_end)..].to_string(),
+        }
+    }
+
+    /// Paths of the locations of the tracks, in playlist order
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.tracks
+            .iter()
+            .filter_map(|track| track.location.as_deref())
+    }
+
+    /// Replace the location of each track by the paths `rewrite` maps it
+    /// to, leaving tracks without a location as they are
+    ///
+    /// A track mapped to no path is removed, and one mapped to several
+    /// paths, such as an expanded directory, is replaced by a track of
+    /// its own for each of them.
+    pub fn rewrite_locations<E>(
+        &mut self,
+        mut rewrite: impl FnMut(&str) -> Result<Vec<String>, E>,
+    ) -> Result<(), E> {
+        let mut tracks = Vec::with_capacity(self.tracks.len());
+        for track in &self.tracks {
+            let Some(location) = &track.location else {
+                tracks.push(track.clone());
+                continue;
+            };
+            match rewrite(location)?.as_slice() {
+                [] => {}
+                [entry] => tracks.push(track.with_location(entry)),
+                entries => tracks.extend(entries.iter().map(|entry| track.new_at(entry))),
+            }
+        }
+        self.tracks = tracks;
+        Ok(())
+    }
+
+    /// Contents of the playlist as XSPF
+    pub fn contents(&self) -> String {
+        let mut content = self.head.clone();
+        for track in &self.tracks {
+            content.push_str(&track.indent);
+            content.push_str(&track.element);
+        }
+        content.push_str(&self.tail);
+        content
+    }
+
+    /// Contents of the playlist as an extended M3U playlist, with an
+    /// `#EXTINF` line for each track with a title
+    pub fn to_m3u8(&self) -> String {
+        let mut content = String::from("#EXTM3U\n");
+        for track in &self.tracks {
+            let Some(location) = &track.location else {
+                continue;
+            };
+            if let Some(title) = &track.title {
+                let seconds = track.duration.map_or(-1, |ms| (ms / 1000) as i64);
+                let name = match &track.creator {
+                    Some(creator) => format!("{} - {}", creator, title),
+                    None => title.clone(),
+                };
+                content.push_str(&format!("#EXTINF:{},{}\n", seconds, name));
+            }
+            content.push_str(location);
+            content.push('\n');
+        }
+        content
+    }
+}
+
+/// Byte position of the next element named `name` in `content`, starting
+/// at `from`, skipping empty elements such as `<track/>`
+fn find_element(content: &str, name: &str, from: usize) -> Option<usize> {
+    let open = format!("<{}", name);
+    let mut pos = from;
+    while let Some(found) = content[pos..].find(&open) {
+        let start = pos + found;
+        let after = start + open.len();
+        match content[after..].chars().next() {
+            Some(c) if c == '>' || c.is_whitespace() => return Some(start),
+            _ => pos = after,
+        }
+    }
+    None
+}
+
+/// Byte range of the text of the first element named `name` in `element`
+fn element_range(element: &str, name: &str) -> Option<(usize, usize)> {
+    let start = find_element(element, name, 0)?;
+    let text_start = start + element[start..].find('>')? + 1;
+    let text_end = text_start + element[text_start..].find(&format!("</{}>", name))?;
+    Some((text_start, text_end))
+}
+
+/// Unescaped text of the first element named `name` in `element`
+fn element_text(element: &str, name: &str) -> Option<String> {
+    let (start, end) = element_range(element, name)?;
+    Some(unescape_text(&element[start..end]))
+}
+
+/// Path of a location URI: the decoded path of a `file:` URI or of a
+/// relative URI, or the URI itself for other schemes such as `http:`
+fn location_path(uri: &str) -> String {
+    let uri = uri.trim();
+    let has_scheme = uri
+        .split_once(':')
+        .is_some_and(|(scheme, _)| scheme.len() > 1 && !scheme.contains('/'));
+    decode_file_uri(uri)
+        .or_else(|| (!has_scheme).then(|| decode_percent(uri)).flatten())
+        .unwrap_or_else(|| uri.to_string())
+}
+
+/// URI of the path of an entry: relative for relative paths, and a
+/// `file:` URI for absolute ones
+fn path_uri(entry: &str) -> String {
+    let (mut uri, path) = match entry.as_bytes() {
+        [b'/', ..] => (String::from("file://"), entry),
+        [drive, b':', ..] if drive.is_ascii_alphabetic() => {
+            (format!("file:///{}", &entry[..2]), &entry[2..])
+        }
+        _ => (String::new(), entry),
+    };
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Text with the characters XML reserves escaped
+fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Text with its XML character and entity references resolved
+fn unescape_text(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        result.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semicolon) = rest.find(';') else {
+            break;
+        };
+        let resolved = match &rest[1..semicolon] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            reference => reference
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| reference.strip_prefix('#').map(str::parse))
+                .and_then(Result::ok)
+                .and_then(char::from_u32),
+        };
+        match resolved {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[semicolon + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYLIST: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<playlist version="1" xmlns="http://xspf.org/ns/0/">
+  <trackList>
+    <track>
+      <location>file:///music/artist/a%20b.flac</location>
+      <title>A &amp; B</title>
+      <creator>Artist</creator>
+      <duration>61500</duration>
+    </track>
+    <track>
+      <location>album/c.flac</location>
+    </track>
+    <track>
+      <location>http://example.com/stream</location>
+    </track>
+  </trackList>
+</playlist>
+"#;
+
+    #[test]
+    fn test_parse_entries() {
+        let playlist = XspfPlaylist::parse(PLAYLIST);
+        assert_eq!(
+            playlist.entries().collect::<Vec<_>>(),
+            vec![
+                "/music/artist/a b.flac",
+                "album/c.flac",
+                "http://example.com/stream"
+            ]
+        );
+        assert_eq!(playlist.tracks[0].title.as_deref(), Some("A & B"));
+        assert_eq!(playlist.tracks[0].duration, Some(61500));
+        assert_eq!(playlist.contents(), PLAYLIST);
+        assert!(XspfPlaylist::parse("<playlist/>").tracks.is_empty());
+        assert!(is_xspf_file(Path::new("Exported.XSPF")));
+    }
+
+    #[test]
+    fn test_rewrite_locations() {
+        let mut playlist = XspfPlaylist::parse(PLAYLIST);
+        playlist
+            .rewrite_locations(|entry| -> Result<_, ()> {
+                Ok(match entry {
+                    "/music/artist/a b.flac" => vec!["artist/a b & c.flac".to_string()],
+                    "album/c.flac" => vec!["album/1.flac".to_string(), "album/2.flac".to_string()],
+                    _ => vec![],
+                })
+            })
+            .unwrap();
+
+        let content = playlist.contents();
+        assert!(content.contains("<location>artist/a%20b%20%26%20c.flac</location>"));
+        assert!(content.contains("<title>A &amp; B</title>"));
+        assert!(content.contains("    <track><location>album/2.flac</location></track>\n"));
+        assert!(!content.contains("example.com"));
+        assert_eq!(
+            XspfPlaylist::parse(&content).entries().collect::<Vec<_>>(),
+            vec!["artist/a b & c.flac", "album/1.flac", "album/2.flac"]
+        );
+        assert_eq!(
+            playlist.to_m3u8(),
+            "#EXTM3U\n#EXTINF:61,Artist - A & B\nartist/a b & c.flac\nalbum/1.flac\nalbum/2.flac\n"
+        );
+    }
+
+    #[test]
+    fn test_uris() {
+        assert_eq!(path_uri("/music/a b.flac"), "file:///music/a%20b.flac");
+        assert_eq!(path_uri("C:/Music/a.flac"), "file:///C:/Music/a.flac");
+        assert_eq!(location_path("file:///C:/Music/a.flac"), "C:/Music/a.flac");
+        assert_eq!(location_path("caf%C3%A9.flac"), "café.flac");
+        assert_eq!(unescape_text("&#233;&#xE9;&bogus; &"), "éé&bogus; &");
+    }
+}
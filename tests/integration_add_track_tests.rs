@@ -0,0 +1,215 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_add_track_appends_relative_entry() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("short.m3u8");
+        create_test_file(&playlist_path, "artist1/album1/title1.flac\n");
+
+        let mut cmd = plm_command("plm-add-track");
+        cmd.arg(playlist_path.to_str().unwrap())
+            .arg(
+                music_dir
+                    .join("artist2/album1/title1.flac")
+                    .to_str()
+                    .unwrap(),
+            )
+            .arg(
+                music_dir
+                    .join("artist2/album2/title1.flac")
+                    .to_str()
+                    .unwrap(),
+            )
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty());
+
+        assert_eq!(
+            fs::read_to_string(&playlist_path).unwrap(),
+            "artist1/album1/title1.flac\nartist2/album1/title1.flac\nartist2/album2/title1.flac\n"
+        );
+    }
+
+    #[test]
+    fn test_add_track_relative_to_current_directory() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("short.m3u8");
+        create_test_file(&playlist_path, "artist1/album1/title1.flac\n");
+
+        let mut cmd = plm_command("plm-add-track");
+        cmd.current_dir(music_dir.join("artist2"))
+            .arg("--position")
+            .arg("1")
+            .arg("../short.m3u8")
+            .arg("./album1/title1.flac")
+            .assert()
+            .success();
+
+        assert_eq!(
+            fs::read_to_string(&playlist_path).unwrap(),
+            "artist2/album1/title1.flac\nartist1/album1/title1.flac\n"
+        );
+    }
+
+    #[test]
+    fn test_add_track_before_entry_generates_extinf() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("extended.m3u8");
+        create_test_file(
+            &playlist_path,
+            "#EXTM3U\r\n#EXTINF:10,Title 1\r\nartist1\\album1\\title1.flac\r\n#EXTINF:20,Title 2\r\nartist1\\album1\\title2.flac\r\n",
+        );
+
+        let mut cmd = plm_command("plm-add-track");
+        cmd.arg("--before")
+            .arg("artist1/album1/title2.flac")
+            .arg(playlist_path.to_str().unwrap())
+            .arg(
+                music_dir
+                    .join("artist2/album1/title1.flac")
+                    .to_str()
+                    .unwrap(),
+            )
+            .assert()
+            .success();
+
+        assert_eq!(
+            fs::read_to_string(&playlist_path).unwrap(),
+            "#EXTM3U\r\n#EXTINF:10,Title 1\r\nartist1\\album1\\title1.flac\r\n#EXTINF:-1,title1\r\nartist2\\album1\\title1.flac\r\n#EXTINF:20,Title 2\r\nartist1\\album1\\title2.flac\r\n"
+        );
+    }
+
+    #[test]
+    fn test_add_track_after_entry() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+
+        let mut cmd = plm_command("plm-add-track");
+        cmd.arg("--after")
+            .arg(
+                music_dir
+                    .join("artist1/album1/title1.flac")
+                    .to_str()
+                    .unwrap(),
+            )
+            .arg("--verbose")
+            .arg(playlist_path.to_str().unwrap())
+            .arg(
+                music_dir
+                    .join("artist2/album2/title1.flac")
+                    .to_str()
+                    .unwrap(),
+            )
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Number of added tracks: 1"));
+
+        let content = fs::read_to_string(&playlist_path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines[0], "artist1/album1/title1.flac");
+        assert_eq!(lines[1], "artist2/album2/title1.flac");
+        assert_eq!(lines[2], "artist1/album1/title2.flac");
+        assert_eq!(lines.len(), 5);
+    }
+
+    #[test]
+    fn test_add_track_position_out_of_range() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        let content = fs::read_to_string(&playlist_path).unwrap();
+
+        let mut cmd = plm_command("plm-add-track");
+        cmd.arg("--position")
+            .arg("6")
+            .arg(playlist_path.to_str().unwrap())
+            .arg(
+                music_dir
+                    .join("artist1/album1/title1.flac")
+                    .to_str()
+                    .unwrap(),
+            )
+            .assert()
+            .failure()
+            .code(1)
+            .stderr(predicate::str::contains("Position 6 is out of range (1-5)"));
+
+        assert_eq!(fs::read_to_string(&playlist_path).unwrap(), content);
+    }
+
+    #[test]
+    fn test_add_track_entry_not_found() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+
+        let mut cmd = plm_command("plm-add-track");
+        cmd.arg("--after")
+            .arg("artist3/album1/title1.flac")
+            .arg(playlist_path.to_str().unwrap())
+            .arg(
+                music_dir
+                    .join("artist1/album1/title1.flac")
+                    .to_str()
+                    .unwrap(),
+            )
+            .assert()
+            .failure()
+            .code(1)
+            .stderr(predicate::str::contains("Entry not found in playlist"));
+    }
+
+    #[test]
+    fn test_add_track_outside_playlist_directory() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let outside = temp_dir.path().join("outside.flac");
+        create_test_file(&outside, "test content");
+
+        let mut cmd = plm_command("plm-add-track");
+        cmd.arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg(outside.to_str().unwrap())
+            .assert()
+            .failure()
+            .code(255)
+            .stderr(predicate::str::contains(
+                "Track is not under the directory of the playlist",
+            ));
+    }
+
+    #[test]
+    fn test_add_track_warns_about_missing_media_file() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+
+        let mut cmd = plm_command("plm-add-track");
+        cmd.arg(playlist_path.to_str().unwrap())
+            .arg(
+                music_dir
+                    .join("artist3/album1/title1.flac")
+                    .to_str()
+                    .unwrap(),
+            )
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("Warning: Media file not found"));
+
+        assert!(fs::read_to_string(&playlist_path)
+            .unwrap()
+            .ends_with("artist2/album2/title1.flac\nartist3/album1/title1.flac"));
+    }
+}
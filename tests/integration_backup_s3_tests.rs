@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(all(test, feature = "s3"))]
+mod tests {
+    use super::*;
+    use integration_test_common::{plm_command, setup_test_directory};
+
+    /// Objects stored by the fake object storage, and the Authorization
+    /// headers of the requests
+    #[derive(Default)]
+    struct Storage {
+        objects: HashMap<String, Vec<u8>>,
+        authorizations: Vec<String>,
+    }
+
+    /// Start a fake object storage serving PUT and GET requests of
+    /// objects, returning its endpoint URL
+    fn start_storage(storage: Arc<Mutex<Storage>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut headers = HashMap::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    match line.trim_end().split_once(": ") {
+                        Some((name, value)) => {
+                            headers.insert(name.to_ascii_lowercase(), value.to_string())
+                        }
+                        None => break,
+                    };
+                }
+                if headers.contains_key("expect") {
+                    stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").unwrap();
+                }
+
+                let mut parts = request_line.split(' ');
+                let (method, path) = (parts.next().unwrap(), parts.next().unwrap());
+                let mut storage = storage.lock().unwrap();
+                storage
+                    .authorizations
+                    .push(headers.get("authorization").cloned().unwrap_or_default());
+                let (status, body) = match method {
+                    "PUT" => {
+                        let len = headers["content-length"].parse().unwrap();
+                        let mut body = vec![0; len];
+                        reader.read_exact(&mut body).unwrap();
+                        storage.objects.insert(path.to_string(), body);
+                        ("200 OK", Vec::new())
+                    }
+                    _ => match storage.objects.get(path) {
+                        Some(body) => ("200 OK", body.clone()),
+                        None => ("404 Not Found", Vec::new()),
+                    },
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+        endpoint
+    }
+
+    fn has_curl() -> bool {
+        std::process::Command::new("curl")
+            .arg("--version")
+            .output()
+            .is_ok()
+    }
+
+    /// Command with the settings of the fake object storage
+    fn command(name: &str, endpoint: &str) -> Command {
+        let mut cmd = plm_command(name);
+        cmd.env("AWS_ENDPOINT_URL", endpoint)
+            .env("AWS_ACCESS_KEY_ID", "AKID")
+            .env("AWS_SECRET_ACCESS_KEY", "SECRET")
+            .env("AWS_REGION", "eu-west-1")
+            .env_remove("AWS_ENDPOINT_URL_S3")
+            .env_remove("AWS_SESSION_TOKEN");
+        cmd
+    }
+
+    #[test]
+    fn test_backup_and_restore_s3() {
+        if !has_curl() {
+            return;
+        }
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        std::fs::create_dir_all(&dest_dir).unwrap();
+        let storage = Arc::new(Mutex::new(Storage::default()));
+        let endpoint = start_storage(storage.clone());
+
+        command("plm-backup", &endpoint)
+            .arg("--verbose")
+            .arg("s3://music/backups/my playlist.tar")
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(
+                "Upload archive to s3://music/backups/my playlist.tar",
+            ));
+        assert!(storage
+            .lock()
+            .unwrap()
+            .objects
+            .contains_key("/music/backups/my%20playlist.tar"));
+
+        command("plm-restore", &endpoint)
+            .arg("s3://music/backups/my playlist.tar")
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success();
+        assert_eq!(
+            std::fs::read_to_string(dest_dir.join("artist2/album2/title1.flac")).unwrap(),
+            "test content 4"
+        );
+
+        let storage = storage.lock().unwrap();
+        assert_eq!(storage.authorizations.len(), 2);
+        for authorization in &storage.authorizations {
+            assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKID/"));
+            assert!(authorization.contains("/eu-west-1/s3/aws4_request"));
+        }
+    }
+
+    #[test]
+    fn test_restore_s3_missing_object() {
+        if !has_curl() {
+            return;
+        }
+        let temp_dir = setup_test_directory();
+        let dest_dir = temp_dir.path().join("DEST");
+        std::fs::create_dir_all(&dest_dir).unwrap();
+        let endpoint = start_storage(Arc::new(Mutex::new(Storage::default())));
+
+        command("plm-restore", &endpoint)
+            .arg("s3://music/missing.tar")
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "Failed to download archive from s3://music/missing.tar",
+            ));
+    }
+
+    #[test]
+    fn test_backup_s3_without_credentials() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+
+        let mut cmd = plm_command("plm-backup");
+        cmd.env_remove("AWS_ACCESS_KEY_ID")
+            .arg("s3://music/backup.tar")
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("AWS_ACCESS_KEY_ID is not set"));
+    }
+}
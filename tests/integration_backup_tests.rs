@@ -0,0 +1,153 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Read the entries of a tar archive without long names as pairs of
+    /// names and contents
+    fn read_tar(archive: &[u8]) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        while archive[offset] != 0 {
+            let header = &archive[offset..offset + 512];
+            let field = |start: usize, len: usize| {
+                let field = &header[start..start + len];
+                let end = field.iter().position(|&b| b == 0).unwrap_or(len);
+                String::from_utf8(field[..end].to_vec()).unwrap()
+            };
+            let size = usize::from_str_radix(&field(124, 12), 8).unwrap();
+            let contents = &archive[offset + 512..offset + 512 + size];
+            entries.push((
+                field(0, 100),
+                String::from_utf8(contents.to_vec()).unwrap(),
+            ));
+            offset += 512 + size.div_ceil(512) * 512;
+        }
+        entries
+    }
+
+    #[test]
+    fn test_backup_tar_with_lyrics_and_manifest() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let archive_path = temp_dir.path().join("backup.tar");
+
+        let mut cmd = plm_command("plm-backup");
+        cmd.arg("--lyrics")
+            .arg("--manifest")
+            .arg("--verbose")
+            .arg(archive_path.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(
+                "Archived 1 playlists, 4 media files and 2 lyrics files",
+            ));
+
+        let entries = read_tar(&fs::read(&archive_path).unwrap());
+        let names: Vec<&str> = entries.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "playlist.m3u8",
+                "artist1/album1/title1.flac",
+                "artist1/album1/title1.lrc",
+                "artist1/album1/title2.flac",
+                "artist2/album1/title1.flac",
+                "artist2/album2/title1.flac",
+                "artist2/album2/title1.lrc",
+                "plm-manifest.tsv",
+            ]
+        );
+        assert_eq!(
+            entries[0].1,
+            fs::read_to_string(music_dir.join("playlist.m3u8")).unwrap()
+        );
+        let manifest = &entries[7].1;
+        assert!(manifest.starts_with("# plm-manifest 1\n"));
+        assert!(manifest.contains(&format!(
+            "artist1/album1/title1.flac\t{}\t{:08x}\n",
+            entries[1].1.len(),
+            crc32fast::hash(entries[1].1.as_bytes())
+        )));
+    }
+
+    #[test]
+    fn test_backup_zip_rewrite_paths() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let archive_path = temp_dir.path().join("backup.zip");
+        let playlist_path = music_dir.join("absolute.m3u");
+        create_test_file(
+            &playlist_path,
+            &format!(
+                "#EXTM3U\r\n{}\r\nartist2\\album1\\title1.flac\r\n",
+                music_dir.join("artist1/album1/title2.flac").display()
+            ),
+        );
+
+        let mut cmd = plm_command("plm-backup");
+        cmd.arg("--rewrite-paths")
+            .arg(archive_path.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        let archive = fs::read(&archive_path).unwrap();
+        assert_eq!(archive[..4], *b"PK\x03\x04");
+        let end = &archive[archive.len() - 22..];
+        assert_eq!(end[..4], *b"PK\x05\x06");
+        assert_eq!(u16::from_le_bytes([end[10], end[11]]), 3);
+        let contents = String::from_utf8_lossy(&archive);
+        assert!(contents
+            .contains("#EXTM3U\r\nartist1/album1/title2.flac\r\nartist2/album1/title1.flac\r\n"));
+    }
+
+    #[test]
+    fn test_backup_missing_media_file() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let archive_path = temp_dir.path().join("backup.tar");
+        fs::remove_file(music_dir.join("artist2/album1/title1.flac")).unwrap();
+
+        // The incomplete archive is deleted
+        let mut cmd = plm_command("plm-backup");
+        cmd.arg(archive_path.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("File not found"));
+        assert!(!archive_path.exists());
+
+        let mut cmd = plm_command("plm-backup");
+        cmd.arg("--keep-going")
+            .arg(archive_path.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("File not found"));
+        assert_eq!(read_tar(&fs::read(&archive_path).unwrap()).len(), 4);
+    }
+
+    #[test]
+    fn test_backup_entry_outside_playlist_dir() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let archive_path = temp_dir.path().join("backup.tar");
+        let playlist_path = music_dir.join("artist1/outside.m3u8");
+        create_test_file(&playlist_path, "../artist2/album1/title1.flac\n");
+
+        let mut cmd = plm_command("plm-backup");
+        cmd.arg(archive_path.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("is not under"));
+    }
+}
@@ -0,0 +1,83 @@
+mod integration_test_common;
+use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delete_playlist_with_sidecar_files() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+
+        // Sidecar files of media files and of whole albums, in an album
+        // fully deleted and in one keeping a track outside the playlist
+        create_test_file(&music_dir.join("artist1/album1/title1.cue"), "FILE");
+        create_test_file(&music_dir.join("artist1/album1/cover.jpg"), "JPEG");
+        create_test_file(&music_dir.join("artist1/album1/title3.flac"), "kept");
+        create_test_file(&music_dir.join("artist2/album1/title1.CUE"), "FILE");
+        create_test_file(&music_dir.join("artist2/album1/cover.jpg"), "JPEG");
+        create_test_file(&music_dir.join("artist2/album1/notes.txt"), "kept");
+        create_test_file(&music_dir.join("artist2/album2/booklet.pdf"), "PDF");
+
+        let mut cmd = plm_command("plm-delete-playlist");
+        cmd.arg("--media")
+            .arg("--sidecar")
+            .arg("cue,.JPG")
+            .arg("--sidecar")
+            .arg("pdf")
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        // Sidecar files sharing the stem of a deleted media file are
+        // deleted, album files only once no media file is left
+        assert!(!music_dir.join("artist1/album1/title1.cue").exists());
+        assert!(music_dir.join("artist1/album1/cover.jpg").exists());
+        assert!(music_dir.join("artist1/album1/title3.flac").exists());
+        assert!(!music_dir.join("artist2/album1/cover.jpg").exists());
+        assert!(music_dir.join("artist2/album1/notes.txt").exists());
+
+        // An album left with no other file is pruned
+        assert!(!music_dir.join("artist2/album2").exists());
+    }
+
+    #[test]
+    fn test_delete_playlist_with_lyrics_extensions() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+
+        create_test_file(&music_dir.join("artist1/album1/title2.txt"), "lyrics");
+        create_test_file(&music_dir.join("artist1/album1/title3.flac"), "kept");
+
+        let mut cmd = plm_command("plm-delete-playlist");
+        cmd.arg("--media")
+            .arg("--lyrics-ext")
+            .arg("txt")
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        // Only the lyrics files with the given extensions are deleted
+        assert!(!music_dir.join("artist1/album1/title2.txt").exists());
+        assert!(music_dir.join("artist1/album1/title1.lrc").exists());
+    }
+
+    #[test]
+    fn test_delete_playlist_sidecar_requires_media() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+
+        let mut cmd = plm_command("plm-delete-playlist");
+        cmd.arg("--sidecar")
+            .arg("cue")
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .failure();
+
+        assert!(playlist_path.exists());
+    }
+}
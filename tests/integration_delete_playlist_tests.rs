@@ -1,8 +1,7 @@
-use assert_cmd::Command;
 use predicates::prelude::*;
 
 mod integration_test_common;
-use integration_test_common::{create_test_file, setup_test_directory};
+use integration_test_common::{create_test_file, plm_command, setup_test_directory};
 
 #[cfg(test)]
 mod tests {
@@ -17,7 +16,7 @@ mod tests {
         // Verify playlist exists before deletion
         assert!(playlist_path.exists());
 
-        let mut cmd = Command::cargo_bin("plm-delete-playlist").unwrap();
+        let mut cmd = plm_command("plm-delete-playlist");
         let assert = cmd
             .arg(playlist_path.to_str().unwrap())
             .assert();
@@ -49,7 +48,7 @@ mod tests {
         assert!(music_dir.join("artist2/album2/title1.flac").exists());
         assert!(music_dir.join("artist2/album2/title1.lrc").exists());
 
-        let mut cmd = Command::cargo_bin("plm-delete-playlist").unwrap();
+        let mut cmd = plm_command("plm-delete-playlist");
         let assert = cmd
             .arg("--media")
             .arg(playlist_path.to_str().unwrap())
@@ -82,7 +81,7 @@ mod tests {
         let music_dir = temp_dir.path().join("MUSIC");
         let playlist_path = music_dir.join("playlist.m3u8");
 
-        let mut cmd = Command::cargo_bin("plm-delete-playlist").unwrap();
+        let mut cmd = plm_command("plm-delete-playlist");
         let assert = cmd
             .arg("-v")
             .arg(playlist_path.to_str().unwrap())
@@ -109,7 +108,7 @@ mod tests {
         assert!(playlist1_path.exists());
         assert!(playlist2_path.exists());
 
-        let mut cmd = Command::cargo_bin("plm-delete-playlist").unwrap();
+        let mut cmd = plm_command("plm-delete-playlist");
         let assert = cmd
             .arg(playlist1_path.to_str().unwrap())
             .arg(playlist2_path.to_str().unwrap())
@@ -140,7 +139,7 @@ mod tests {
         assert!(music_dir.join("artist1/album1/title1.flac").exists());
         assert!(music_dir.join("artist2/album2/title1.flac").exists());
 
-        let mut cmd = Command::cargo_bin("plm-delete-playlist").unwrap();
+        let mut cmd = plm_command("plm-delete-playlist");
         let assert = cmd
             .arg("--media")
             .arg(playlist1_path.to_str().unwrap())
@@ -167,9 +166,139 @@ mod tests {
         assert!(!music_dir.join("artist2").exists());
     }
 
+    #[test]
+    fn test_delete_playlist_expand_dirs() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+
+        let playlist_path = music_dir.join("folders.m3u8");
+        create_test_file(&playlist_path, "artist2/\nartist1/album1/title1.flac");
+
+        let mut cmd = plm_command("plm-delete-playlist");
+        let assert = cmd
+            .arg("--media")
+            .arg("--expand-dirs")
+            .arg(playlist_path.to_str().unwrap())
+            .assert();
+
+        assert.success();
+
+        // Verify the media and lyrics files in the directory were deleted
+        assert!(!playlist_path.exists());
+        assert!(!music_dir.join("artist2").exists());
+        assert!(!music_dir.join("artist1/album1/title1.flac").exists());
+        assert!(music_dir.join("artist1/album1/title2.flac").exists());
+    }
+
+    #[test]
+    fn test_delete_playlist_from_stdin() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        let other_path = music_dir.join("other.m3u8");
+        create_test_file(&other_path, "artist2/album2/title1.flac");
+
+        let mut cmd = plm_command("plm-delete-playlist");
+        cmd.arg("--media")
+            .arg("-")
+            .write_stdin(format!(
+                "# Device playlists\r\n{}\r\n\n",
+                other_path.display()
+            ))
+            .assert()
+            .success();
+
+        assert!(!other_path.exists());
+        assert!(!music_dir.join("artist2/album2").exists());
+        assert!(playlist_path.exists());
+    }
+
+    #[test]
+    fn test_delete_playlist_playlists_from_file() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        let other_path = music_dir.join("other.m3u8");
+        let list_path = temp_dir.path().join("playlists.txt");
+        create_test_file(&other_path, "artist2/album2/title1.flac");
+        create_test_file(&list_path, &format!("{}\n", other_path.display()));
+
+        let mut cmd = plm_command("plm-delete-playlist");
+        cmd.arg("--playlists-from")
+            .arg(list_path.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert!(!playlist_path.exists());
+        assert!(!other_path.exists());
+
+        let mut cmd = plm_command("plm-delete-playlist");
+        cmd.arg("--playlists-from")
+            .arg(temp_dir.path().join("missing.txt").to_str().unwrap())
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("Failed to read playlist list"));
+    }
+
+    #[test]
+    fn test_delete_playlist_pattern() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        for name in ["2023-01.m3u8", "2023-02.m3u8"] {
+            create_test_file(&music_dir.join(name), "artist2/album2/title1.flac");
+        }
+
+        let mut cmd = plm_command("plm-delete-playlist");
+        cmd.arg(music_dir.join("2023-*.m3u8").to_str().unwrap())
+            .assert()
+            .success();
+
+        assert!(!music_dir.join("2023-01.m3u8").exists());
+        assert!(!music_dir.join("2023-02.m3u8").exists());
+        assert!(playlist_path.exists());
+
+        let mut cmd = plm_command("plm-delete-playlist");
+        cmd.arg(music_dir.join("2023-*.m3u8").to_str().unwrap())
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("No playlist matches"));
+    }
+
+    #[test]
+    fn test_delete_playlist_match_regex() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        for name in ["2023-01.m3u8", "2024-01.m3u8", "2023-notes.txt"] {
+            create_test_file(&music_dir.join(name), "artist2/album2/title1.flac");
+        }
+
+        let mut cmd = plm_command("plm-delete-playlist");
+        cmd.arg("--match-regex")
+            .arg("^2023-")
+            .arg(music_dir.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert!(!music_dir.join("2023-01.m3u8").exists());
+        assert!(music_dir.join("2024-01.m3u8").exists());
+        assert!(music_dir.join("2023-notes.txt").exists());
+        assert!(playlist_path.exists());
+
+        let mut cmd = plm_command("plm-delete-playlist");
+        cmd.arg("--match-regex")
+            .arg("(")
+            .arg(music_dir.to_str().unwrap())
+            .assert()
+            .failure();
+        assert!(music_dir.join("2024-01.m3u8").exists());
+    }
+
     #[test]
     fn test_delete_playlist_missing_args() {
-        let mut cmd = Command::cargo_bin("plm-delete-playlist").unwrap();
+        let mut cmd = plm_command("plm-delete-playlist");
         let assert = cmd.assert();
 
         assert.failure();
@@ -180,7 +309,7 @@ mod tests {
         let temp_dir = setup_test_directory();
         let nonexistent_path = temp_dir.path().join("nonexistent.m3u8");
 
-        let mut cmd = Command::cargo_bin("plm-delete-playlist").unwrap();
+        let mut cmd = plm_command("plm-delete-playlist");
         let assert = cmd
             .arg(nonexistent_path.to_str().unwrap())
             .assert();
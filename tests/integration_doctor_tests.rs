@@ -0,0 +1,62 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{plm_command, setup_test_directory};
+
+    #[test]
+    fn test_doctor_checks_destination() {
+        let temp_dir = setup_test_directory();
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-doctor");
+        cmd.arg("--verbose")
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "[ok] commands: plm-put-playlist found",
+            ))
+            .stdout(predicate::str::contains("is writable"))
+            .stdout(
+                predicate::str::contains("[ok] case: Names are case-sensitive").or(
+                    predicate::str::contains("[info] case: Names are case-insensitive"),
+                ),
+            );
+
+        // The probe files are removed
+        assert_eq!(fs::read_dir(&dest_dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_doctor_reports_problems() {
+        let temp_dir = setup_test_directory();
+        let dest_dir = temp_dir.path().join("DEST");
+
+        let mut cmd = plm_command("plm-doctor");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .assert()
+            .failure()
+            .stdout(predicate::str::contains("[error] destination:"))
+            .stdout(predicate::str::contains("Mount the device"))
+            .stdout(predicate::str::contains("[ok]").not());
+
+        // An unreadable sync state is reported with what to do about it
+        fs::create_dir_all(dest_dir.join(".plm")).unwrap();
+        fs::write(dest_dir.join(".plm/state.json"), "not json").unwrap();
+        let mut cmd = plm_command("plm-doctor");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .assert()
+            .failure()
+            .stdout(predicate::str::contains(
+                "[error] sync state: Invalid sync state",
+            ))
+            .stdout(predicate::str::contains("Number of problems found: 1"));
+    }
+}
@@ -0,0 +1,157 @@
+//! Tests of the copy engine with failures injected at given points,
+//! built with `cargo test --features fault-injection`
+#![cfg(feature = "fault-injection")]
+
+use std::fs;
+use std::path::Path;
+
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{plm_command, setup_test_directory};
+
+    /// Command copying the test playlist into `dest_dir` with the
+    /// failures of `faults` injected
+    fn put_playlist(music_dir: &Path, dest_dir: &Path, faults: &str) -> Command {
+        fs::create_dir_all(dest_dir).unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.env("PLM_INJECT_FAULTS", faults)
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap());
+        cmd
+    }
+
+    /// Media file entries of an error file
+    fn failed_media_files(error_file: &Path) -> Vec<String> {
+        fs::read_to_string(error_file)
+            .unwrap()
+            .lines()
+            .filter_map(|line| line.strip_prefix("M ").map(String::from))
+            .collect()
+    }
+
+    #[test]
+    fn test_keep_going_records_failed_copy() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.log");
+
+        put_playlist(&music_dir, &dest_dir, "copy:2")
+            .arg("--keep-going")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(3/4) media files copied"));
+
+        let failed = failed_media_files(&error_file);
+        assert_eq!(failed.len(), 1);
+        let failed = Path::new(&failed[0]).strip_prefix(&music_dir).unwrap();
+        assert!(!dest_dir.join(failed).exists());
+        assert!(dest_dir.join("playlist.m3u8").exists());
+
+        // Retrying from the error file copies the failed file
+        plm_command("plm-put-playlist")
+            .arg("--retry")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1/1) media files copied"));
+        assert!(dest_dir.join(failed).exists());
+    }
+
+    #[test]
+    fn test_failed_copy_stops_without_keep_going() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        put_playlist(&music_dir, &dest_dir, "copy:1")
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Injected failure of copy 1"));
+        assert!(!dest_dir.join("artist2/album2/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_io_retries_retry_injected_failure() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        put_playlist(&music_dir, &dest_dir, "copy:1,copy:3")
+            .arg("--io-retries")
+            .arg("1")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"));
+    }
+
+    #[test]
+    fn test_failed_directory_creation() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.log");
+
+        put_playlist(&music_dir, &dest_dir, "create-dir:1")
+            .arg("--keep-going")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(3/4) media files copied"));
+        assert_eq!(failed_media_files(&error_file).len(), 1);
+    }
+
+    #[test]
+    fn test_failed_playlist_write() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        put_playlist(&music_dir, &dest_dir, "playlist-write:1")
+            .assert()
+            .code(1);
+        assert!(!dest_dir.join("playlist.m3u8").exists());
+    }
+
+    #[test]
+    fn test_partial_copy_is_copied_again() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.log");
+
+        put_playlist(&music_dir, &dest_dir, "partial-copy:1")
+            .arg("--keep-going")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .assert()
+            .success();
+        let failed = failed_media_files(&error_file);
+        assert_eq!(failed.len(), 1);
+        let src = Path::new(&failed[0]);
+        let dest = dest_dir.join(src.strip_prefix(&music_dir).unwrap());
+        let content = fs::read(src).unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), content[..content.len() / 2]);
+
+        // The half-copied file differs in size, so --skip-existing copies
+        // it again
+        put_playlist(&music_dir, &dest_dir, "")
+            .arg("--skip-existing")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "(3) unchanged media files skipped",
+            ));
+        assert_eq!(fs::read(&dest).unwrap(), content);
+    }
+}
@@ -0,0 +1,152 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    fn put_playlist(dest_dir: &Path, playlist_path: &Path) {
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--sync-state")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+    }
+
+    fn fsck(dest_dir: &Path, args: &[&str]) -> assert_cmd::assert::Assert {
+        let mut cmd = plm_command("plm-fsck");
+        cmd.args(args).arg(dest_dir.to_str().unwrap()).assert()
+    }
+
+    #[test]
+    fn test_fsck_clean_destination() {
+        let temp_dir = setup_test_directory();
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        put_playlist(&dest_dir, &temp_dir.path().join("MUSIC/playlist.m3u8"));
+
+        fsck(&dest_dir, &[])
+            .success()
+            .stdout("(0) problems found\n");
+    }
+
+    #[test]
+    fn test_fsck_reports_problems() {
+        let temp_dir = setup_test_directory();
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        put_playlist(&dest_dir, &temp_dir.path().join("MUSIC/playlist.m3u8"));
+
+        // A track deleted behind the back of the state file, a backup
+        // whose original is gone and a playlist that is not UTF-8
+        fs::remove_file(dest_dir.join("artist1/album1/title2.flac")).unwrap();
+        create_test_file(&dest_dir.join("artist2/album1/other.mp3.plm-bak"), "x");
+        fs::write(dest_dir.join("broken.m3u8"), b"\xc3\x28\n").unwrap();
+
+        let dest = |path: &str| dest_dir.join(path).display().to_string();
+        let expected = [
+            format!(
+                "orphaned backup: {}",
+                dest("artist2/album1/other.mp3.plm-bak")
+            ),
+            format!(
+                "unreadable playlist: {}: Not valid UTF-8 (try --encoding)",
+                dest("broken.m3u8")
+            ),
+            format!(
+                "missing entry: {}: artist1/album1/title2.flac",
+                dest("playlist.m3u8")
+            ),
+            "stale state entry: artist1/album1/title2.flac".to_string(),
+            "(4) problems found".to_string(),
+        ];
+        let output = fsck(&dest_dir, &[]).code(1).get_output().stdout.clone();
+        let stdout = String::from_utf8(output).unwrap();
+        assert_eq!(stdout.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_fsck_fixes_interrupted_runs() {
+        let temp_dir = setup_test_directory();
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        put_playlist(&dest_dir, &temp_dir.path().join("MUSIC/playlist.m3u8"));
+
+        // A sort, a state update and a copy over a backed up file, each
+        // interrupted half way
+        let album_dir = dest_dir.join("artist1/album1");
+        fs::create_dir(album_dir.join(".plm-sort")).unwrap();
+        fs::rename(
+            album_dir.join("title1.flac"),
+            album_dir.join(".plm-sort/title1.flac"),
+        )
+        .unwrap();
+        create_test_file(&dest_dir.join(".plm/state.json.partial"), "{");
+        fs::rename(
+            dest_dir.join("artist2/album2/title1.flac"),
+            dest_dir.join("artist2/album2/title1.flac.plm-bak"),
+        )
+        .unwrap();
+        fs::remove_file(dest_dir.join("artist2/album1/title1.flac")).unwrap();
+
+        fsck(&dest_dir, &["--fix", "--restore-backups"])
+            .code(1)
+            .stdout(
+                predicate::str::contains("interrupted sort: ")
+                    .and(predicate::str::contains("artist2/album1/title1.flac").count(2)),
+            )
+            .stdout(predicate::str::contains(
+                "(5) problems found\n(4) problems fixed\n",
+            ));
+
+        assert!(album_dir.join("title1.flac").is_file());
+        assert!(!album_dir.join(".plm-sort").exists());
+        assert!(!dest_dir.join(".plm/state.json.partial").exists());
+        assert!(dest_dir.join("artist2/album2/title1.flac").is_file());
+
+        // Only the track missing from the playlist is left
+        fsck(&dest_dir, &[])
+            .code(1)
+            .stdout(predicate::str::contains("(1) problems found"));
+    }
+
+    #[test]
+    fn test_fsck_deletes_orphaned_backups() {
+        let temp_dir = setup_test_directory();
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        put_playlist(&dest_dir, &temp_dir.path().join("MUSIC/playlist.m3u8"));
+
+        let track = dest_dir.join("artist2/album2/title1.flac");
+        let backup = dest_dir.join("artist2/album2/title1.flac.plm-bak");
+        fs::rename(&track, &backup).unwrap();
+
+        fsck(&dest_dir, &["--fix"])
+            .success()
+            .stdout(predicate::str::contains(
+                "(1) problems found\n(1) problems fixed\n",
+            ));
+        assert!(!backup.exists());
+        assert!(!track.exists());
+
+        // The track is now missing from the playlist and the state file
+        fsck(&dest_dir, &[])
+            .code(1)
+            .stdout(predicate::str::contains("(2) problems found"));
+    }
+
+    #[test]
+    fn test_fsck_restore_backups_requires_fix() {
+        let temp_dir = setup_test_directory();
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        fsck(&dest_dir, &["--restore-backups"]).code(2);
+    }
+}
@@ -0,0 +1,107 @@
+use std::fs;
+
+use predicates::prelude::*;
+use tempfile::TempDir;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Create a destination with trash folders of the given dates
+    /// besides the music library
+    fn setup_trash(dates: &[&str]) -> TempDir {
+        let temp_dir = setup_test_directory();
+        for date in dates {
+            let path = temp_dir
+                .path()
+                .join(".plm-trash")
+                .join(date)
+                .join("artist1/album1/title1.flac");
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            create_test_file(&path, "test content 1");
+        }
+        fs::create_dir_all(temp_dir.path().join(".plm-trash/notes")).unwrap();
+        temp_dir
+    }
+
+    #[test]
+    fn test_gc_empty_trash_older_than() {
+        let temp_dir = setup_trash(&["2000-01-01", "2000-02-01", "2999-01-01"]);
+        let trash_dir = temp_dir.path().join(".plm-trash");
+
+        let mut cmd = plm_command("plm-gc");
+        cmd.arg("--empty-trash")
+            .arg("--older-than")
+            .arg("30d")
+            .arg("-n")
+            .arg(temp_dir.path().to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("2000-01-01\n"))
+            .stdout(predicate::str::contains("2000-02-01\n"))
+            .stdout(predicate::str::contains("2999-01-01").not());
+        assert!(trash_dir.join("2000-01-01").exists());
+
+        let mut cmd = plm_command("plm-gc");
+        cmd.arg("--empty-trash")
+            .arg("--older-than")
+            .arg("30d")
+            .arg("-v")
+            .arg(temp_dir.path().to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(2) trash folders deleted"))
+            .stderr(predicate::str::contains("Deleting trash folder"));
+        assert!(!trash_dir.join("2000-01-01").exists());
+        assert!(!trash_dir.join("2000-02-01").exists());
+        assert!(trash_dir
+            .join("2999-01-01/artist1/album1/title1.flac")
+            .exists());
+        assert!(trash_dir.join("notes").exists());
+    }
+
+    #[test]
+    fn test_gc_empty_trash() {
+        let temp_dir = setup_trash(&["2000-01-01", "2999-01-01"]);
+
+        let mut cmd = plm_command("plm-gc");
+        cmd.arg("--empty-trash")
+            .arg(temp_dir.path().to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(2) trash folders deleted"));
+        assert!(!temp_dir.path().join(".plm-trash/2999-01-01").exists());
+        assert!(temp_dir.path().join(".plm-trash/notes").exists());
+    }
+
+    #[test]
+    fn test_gc_invalid_arguments() {
+        let temp_dir = setup_trash(&["2000-01-01"]);
+
+        let mut cmd = plm_command("plm-gc");
+        cmd.arg(temp_dir.path().to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains("give --empty-trash"));
+
+        let mut cmd = plm_command("plm-gc");
+        cmd.arg("--empty-trash")
+            .arg("--older-than")
+            .arg("1m")
+            .arg(temp_dir.path().to_str().unwrap())
+            .assert()
+            .code(2)
+            .stderr(predicate::str::contains("Invalid age"));
+
+        let mut cmd = plm_command("plm-gc");
+        cmd.arg("--empty-trash")
+            .arg(temp_dir.path().join("NONEXISTENT").to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains("Not a directory"));
+        assert!(temp_dir.path().join(".plm-trash/2000-01-01").exists());
+    }
+}
@@ -0,0 +1,171 @@
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{plm_command, setup_test_directory};
+
+    /// Write a FLAC file holding only the given Vorbis comments
+    fn create_flac_file(path: &Path, comments: &[&str]) {
+        let mut block = Vec::new();
+        block.extend_from_slice(&0u32.to_le_bytes());
+        block.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            block.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            block.extend_from_slice(comment.as_bytes());
+        }
+
+        let mut content = b"fLaC\x84".to_vec();
+        content.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+        content.extend_from_slice(&block);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// Set the modification time of a file to `days` days ago
+    fn set_age(path: &Path, days: u64) {
+        let modified = SystemTime::now() - Duration::from_secs(days * 24 * 60 * 60);
+        fs::File::options()
+            .write(true)
+            .open(path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_generate_by_genre_tags() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let out_dir = music_dir.join("Playlists");
+        create_flac_file(
+            &music_dir.join("artist3/album1/02 Second.flac"),
+            &["ARTIST=Artist 3", "GENRE=Jazz"],
+        );
+        create_flac_file(
+            &music_dir.join("artist3/album1/01 First.flac"),
+            &["ARTIST=Artist 3", "GENRE=Jazz"],
+        );
+        create_flac_file(
+            &music_dir.join("artist4/album1/01 Only.flac"),
+            &["ARTIST=Artist 4", "GENRE=Rock/Pop"],
+        );
+
+        let mut cmd = plm_command("plm-generate");
+        cmd.arg("--verbose")
+            .arg("--group-by")
+            .arg("genre")
+            .arg(music_dir.to_str().unwrap())
+            .arg("-o")
+            .arg(out_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Number of generated playlists: 2"))
+            .stderr(predicate::str::contains(
+                "No group for track \"artist1/album1/title1.flac\"",
+            ));
+
+        assert_eq!(
+            fs::read_to_string(out_dir.join("Jazz.m3u8")).unwrap(),
+            "../artist3/album1/01 First.flac\n../artist3/album1/02 Second.flac\n"
+        );
+        assert!(out_dir.join("Rock_Pop.m3u8").exists());
+    }
+
+    #[test]
+    fn test_generate_by_artist_dirs() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+
+        let mut cmd = plm_command("plm-generate");
+        cmd.arg("--group-by")
+            .arg("artist")
+            .arg("--from")
+            .arg("dirs")
+            .arg("--output")
+            .arg(music_dir.to_str().unwrap())
+            .arg(music_dir.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert_eq!(
+            fs::read_to_string(music_dir.join("artist1.m3u8")).unwrap(),
+            "artist1/album1/title1.flac\nartist1/album1/title2.flac\n"
+        );
+        assert_eq!(
+            fs::read_to_string(music_dir.join("artist2.m3u8")).unwrap(),
+            "artist2/album1/title1.flac\nartist2/album2/title1.flac\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_recently_added() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        for (track, days) in [
+            ("artist1/album1/title1.flac", 40),
+            ("artist1/album1/title2.flac", 2),
+            ("artist2/album1/title1.flac", 3),
+            ("artist2/album2/title1.flac", 1),
+        ] {
+            set_age(&music_dir.join(track), days);
+        }
+
+        let mut cmd = plm_command("plm-generate");
+        cmd.arg("--added-since")
+            .arg("30d")
+            .arg("-o")
+            .arg(music_dir.to_str().unwrap())
+            .arg(music_dir.to_str().unwrap())
+            .assert()
+            .success();
+
+        // Newest first, leaving out the track older than 30 days
+        assert_eq!(
+            fs::read_to_string(music_dir.join("Recently Added.m3u8")).unwrap(),
+            "artist2/album2/title1.flac\nartist1/album1/title2.flac\nartist2/album1/title1.flac\n"
+        );
+
+        let mut cmd = plm_command("plm-generate");
+        cmd.arg("--added-since")
+            .arg("30d")
+            .arg("--group-by")
+            .arg("artist")
+            .arg("-o")
+            .arg(music_dir.to_str().unwrap())
+            .arg(music_dir.to_str().unwrap())
+            .assert()
+            .failure();
+    }
+
+    #[test]
+    fn test_generate_invalid_args() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+
+        let mut cmd = plm_command("plm-generate");
+        cmd.arg("--group-by")
+            .arg("album")
+            .arg("-o")
+            .arg(temp_dir.path().to_str().unwrap())
+            .arg(music_dir.to_str().unwrap())
+            .assert()
+            .failure();
+
+        let mut cmd = plm_command("plm-generate");
+        cmd.arg("--group-by")
+            .arg("artist")
+            .arg("-o")
+            .arg(temp_dir.path().to_str().unwrap())
+            .arg(music_dir.join("missing").to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains("Not a directory"));
+    }
+}
@@ -0,0 +1,132 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_get_playlist_with_lyrics() {
+        let temp_dir = setup_test_directory();
+        let device_dir = temp_dir.path().join("MUSIC");
+        let library_dir = temp_dir.path().join("LIBRARY");
+        fs::create_dir_all(&library_dir).unwrap();
+
+        let mut cmd = plm_command("plm-get-playlist");
+        cmd.arg("--lyrics")
+            .arg(library_dir.to_str().unwrap())
+            .arg(device_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1/1) playlist copied"))
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stdout(predicate::str::contains("(2/2) lyrics files copied"));
+
+        assert_eq!(
+            fs::read_to_string(library_dir.join("playlist.m3u8")).unwrap(),
+            fs::read_to_string(device_dir.join("playlist.m3u8")).unwrap()
+        );
+        assert_eq!(
+            fs::read_to_string(library_dir.join("artist2/album1/title1.flac")).unwrap(),
+            "test content 3"
+        );
+        assert!(library_dir.join("artist1/album1/title1.lrc").exists());
+        assert!(library_dir.join("artist2/album2/title1.lrc").exists());
+
+        // Files already in the library are not copied again
+        let mut cmd = plm_command("plm-get-playlist");
+        cmd.arg("--verbose")
+            .arg(library_dir.to_str().unwrap())
+            .arg(device_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("Skip up-to-date"))
+            .stderr(predicate::str::contains("Copy \"").not())
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stdout(predicate::str::contains("lyrics files copied").not());
+    }
+
+    #[test]
+    fn test_get_playlist_keep_going_with_error_files() {
+        let temp_dir = setup_test_directory();
+        let device_dir = temp_dir.path().join("MUSIC");
+        let library_dir = temp_dir.path().join("LIBRARY");
+        let error_file = temp_dir.path().join("errors.txt");
+        fs::create_dir_all(&library_dir).unwrap();
+        let playlist_path = device_dir.join("missing.m3u8");
+        create_test_file(
+            &playlist_path,
+            "artist1/album1/title1.flac\nartist1/album1/missing.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-get-playlist");
+        cmd.arg(library_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("missing.flac"));
+
+        let mut cmd = plm_command("plm-get-playlist");
+        let output = cmd
+            .arg("--keep-going")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .arg(library_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .arg(device_dir.join("nonexistent.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1/2) playlist copied"))
+            .stdout(predicate::str::contains("(1/2) media files copied"))
+            .get_output()
+            .stdout
+            .clone();
+
+        // The error file records the run ID printed with the summary
+        let stdout = String::from_utf8(output).unwrap();
+        let run_id = stdout
+            .lines()
+            .find_map(|line| line.strip_prefix("Run ID: "))
+            .unwrap();
+        assert!(library_dir.join("artist1/album1/title1.flac").exists());
+        assert_eq!(
+            fs::read_to_string(&error_file).unwrap(),
+            format!(
+                "# plm-error-file 2\n# plm-run {}\n# plm-totals playlists=2 media=2\nM {}\nP {}\n",
+                run_id,
+                device_dir.join("artist1/album1/missing.flac").display(),
+                device_dir.join("nonexistent.m3u8").display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_get_playlist_invalid_arguments() {
+        let temp_dir = setup_test_directory();
+        let device_dir = temp_dir.path().join("MUSIC");
+        let library_dir = temp_dir.path().join("LIBRARY");
+
+        let mut cmd = plm_command("plm-get-playlist");
+        cmd.arg(library_dir.to_str().unwrap())
+            .arg(device_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains("Destination directory not found"));
+
+        fs::create_dir_all(&library_dir).unwrap();
+        let mut cmd = plm_command("plm-get-playlist");
+        cmd.arg("--error-files")
+            .arg(temp_dir.path().join("errors.txt").to_str().unwrap())
+            .arg(library_dir.to_str().unwrap())
+            .arg(device_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains(
+                "--error-files can only be used with --keep-going",
+            ));
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs;
+
+use predicates::prelude::*;
+use serde_json::Value;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_list_playlist_prints_tracks_and_totals() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        fs::remove_file(music_dir.join("artist1/album1/title2.flac")).unwrap();
+        let other_path = music_dir.join("artist2/other.m3u8");
+        create_test_file(
+            &other_path,
+            "#EXTM3U\r\n..\\artist1\\album1\\title1.flac\r\n",
+        );
+
+        let mut cmd = plm_command("plm-list-playlist");
+        let output = cmd
+            .current_dir(&music_dir)
+            .arg("playlist.m3u8")
+            .arg(other_path.to_str().unwrap())
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+
+        let track = |file: &str| music_dir.join(file).display().to_string();
+        let expected = [
+            music_dir.join("playlist.m3u8").display().to_string(),
+            format!("  found    lrc  {}", track("artist1/album1/title1.flac")),
+            format!("  missing  -    {}", track("artist1/album1/title2.flac")),
+            format!("  found    -    {}", track("artist2/album1/title1.flac")),
+            format!("  found    lrc  {}", track("artist2/album2/title1.flac")),
+            "  4 tracks, 3 found, 1 missing, 2 with lyrics".to_string(),
+            other_path.display().to_string(),
+            format!("  found    lrc  {}", track("artist1/album1/title1.flac")),
+            "  1 tracks, 1 found, 0 missing, 1 with lyrics".to_string(),
+            "Total: 2 playlists, 5 tracks, 4 found, 1 missing, 3 with lyrics".to_string(),
+        ];
+        let stdout = String::from_utf8(output).unwrap();
+        assert_eq!(stdout.lines().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn test_list_playlist_json() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        fs::remove_file(music_dir.join("artist2/album1/title1.flac")).unwrap();
+
+        let mut cmd = plm_command("plm-list-playlist");
+        let output = cmd
+            .arg("--json")
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+
+        let document: Value = serde_json::from_slice(&output).unwrap();
+        let playlist = &document["playlists"][0];
+        assert_eq!(
+            playlist["playlist"],
+            music_dir.join("playlist.m3u8").to_str().unwrap()
+        );
+        let tracks = playlist["tracks"].as_array().unwrap();
+        assert_eq!(tracks.len(), 4);
+        assert_eq!(
+            tracks[2]["path"],
+            music_dir
+                .join("artist2/album1/title1.flac")
+                .to_str()
+                .unwrap()
+        );
+        assert_eq!(tracks[2]["exists"], false);
+        assert_eq!(tracks[0]["lyrics"], true);
+        assert_eq!(tracks[1]["lyrics"], false);
+        assert_eq!(
+            document["totals"],
+            serde_json::json!({
+                "playlists": 1, "tracks": 4, "found": 3, "missing": 1, "lyrics": 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_list_playlist_missing_playlist() {
+        let temp_dir = setup_test_directory();
+
+        let mut cmd = plm_command("plm-list-playlist");
+        cmd.arg(temp_dir.path().join("missing.m3u8").to_str().unwrap())
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("Failed to open playlist"));
+    }
+}
@@ -0,0 +1,117 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_mv_rewrites_playlists() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+
+        // Playlists in the library and in a subdirectory, one extended
+        // with backslashes and CRLF line endings
+        let extended = music_dir.join("lists/extended.m3u8");
+        fs::create_dir_all(music_dir.join("lists")).unwrap();
+        create_test_file(
+            &extended,
+            "#EXTM3U\r\n#EXTINF:10,Title 1\r\n..\\artist1\\album1\\title1.flac\r\n..\\artist2\\album1\\title1.flac\r\n",
+        );
+
+        let mut cmd = plm_command("plm-mv");
+        cmd.arg("-v")
+            .arg("--playlists")
+            .arg(music_dir.to_str().unwrap())
+            .arg(
+                music_dir
+                    .join("artist1/album1/title1.flac")
+                    .to_str()
+                    .unwrap(),
+            )
+            .arg(
+                music_dir
+                    .join("artist3/album1/01 title1.flac")
+                    .to_str()
+                    .unwrap(),
+            )
+            .assert()
+            .success()
+            .stdout("Number of moved files: 2\nNumber of rewritten playlists: 2\n");
+
+        // The media file moves along with its lyrics file
+        assert!(!music_dir.join("artist1/album1/title1.flac").exists());
+        assert!(music_dir.join("artist3/album1/01 title1.flac").is_file());
+        assert!(music_dir.join("artist3/album1/01 title1.lrc").is_file());
+
+        assert_eq!(
+            fs::read_to_string(music_dir.join("playlist.m3u8")).unwrap(),
+            "artist3/album1/01 title1.flac\nartist1/album1/title2.flac\nartist2/album1/title1.flac\nartist2/album2/title1.flac"
+        );
+        assert_eq!(
+            fs::read_to_string(&extended).unwrap(),
+            "#EXTM3U\r\n#EXTINF:10,Title 1\r\n..\\artist3\\album1\\01 title1.flac\r\n..\\artist2\\album1\\title1.flac\r\n"
+        );
+    }
+
+    #[test]
+    fn test_mv_into_directory() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        fs::create_dir_all(music_dir.join("artist1/album2")).unwrap();
+
+        let mut cmd = plm_command("plm-mv");
+        cmd.current_dir(&music_dir)
+            .arg("--playlists")
+            .arg(".")
+            .arg("artist1/album1/title2.flac")
+            .arg("artist1/album2")
+            .assert()
+            .success();
+
+        assert!(music_dir.join("artist1/album2/title2.flac").is_file());
+        assert!(fs::read_to_string(music_dir.join("playlist.m3u8"))
+            .unwrap()
+            .contains("\nartist1/album2/title2.flac\n"));
+    }
+
+    #[test]
+    fn test_mv_refuses_to_overwrite() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist = fs::read_to_string(music_dir.join("playlist.m3u8")).unwrap();
+
+        // The lyrics file would land on an existing one
+        create_test_file(&music_dir.join("artist1/album1/title3.lrc"), "lyrics");
+
+        let mut cmd = plm_command("plm-mv");
+        cmd.arg("--playlists")
+            .arg(music_dir.to_str().unwrap())
+            .arg(
+                music_dir
+                    .join("artist1/album1/title1.flac")
+                    .to_str()
+                    .unwrap(),
+            )
+            .arg(
+                music_dir
+                    .join("artist1/album1/title3.flac")
+                    .to_str()
+                    .unwrap(),
+            )
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains("File already exists"));
+
+        assert!(music_dir.join("artist1/album1/title1.flac").is_file());
+        assert!(!music_dir.join("artist1/album1/title3.flac").exists());
+        assert_eq!(
+            fs::read_to_string(music_dir.join("playlist.m3u8")).unwrap(),
+            playlist
+        );
+    }
+}
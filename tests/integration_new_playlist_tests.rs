@@ -0,0 +1,93 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_new_playlist_from_directory() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist = music_dir.join("lists/artist1.m3u8");
+        create_test_file(&music_dir.join("artist1/album1/10 title10.flac"), "ten");
+        create_test_file(&music_dir.join("artist1/album1/cover.jpg"), "JPEG");
+
+        let mut cmd = plm_command("plm-new-playlist");
+        cmd.arg("-v")
+            .arg("--extinf")
+            .arg(playlist.to_str().unwrap())
+            .arg(music_dir.join("artist1").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout("Number of added tracks: 3\n");
+
+        // Numbered tracks come first, and the cover image is left out
+        assert_eq!(
+            fs::read_to_string(&playlist).unwrap(),
+            "#EXTM3U\n#EXTINF:-1,10 title10\n../artist1/album1/10 title10.flac\n#EXTINF:-1,title1\n../artist1/album1/title1.flac\n#EXTINF:-1,title2\n../artist1/album1/title2.flac\n"
+        );
+    }
+
+    #[test]
+    fn test_new_playlist_from_stdin_and_patterns() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+
+        let mut cmd = plm_command("plm-new-playlist");
+        cmd.current_dir(&music_dir)
+            .arg("new.m3u8")
+            .arg("-")
+            .arg("artist2/*/*.flac")
+            .write_stdin("# Favourites\nartist1/album1/title2.flac\n\nartist2/album2/title1.flac\n")
+            .assert()
+            .success();
+
+        // A track given twice is listed once, where it was first given
+        assert_eq!(
+            fs::read_to_string(music_dir.join("new.m3u8")).unwrap(),
+            "artist1/album1/title2.flac\nartist2/album2/title1.flac\nartist2/album1/title1.flac\n"
+        );
+    }
+
+    #[test]
+    fn test_new_playlist_refuses_to_overwrite() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist = music_dir.join("playlist.m3u8");
+        let content = fs::read_to_string(&playlist).unwrap();
+
+        let mut cmd = plm_command("plm-new-playlist");
+        cmd.arg(playlist.to_str().unwrap())
+            .arg(music_dir.join("artist2").to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains("use --force"));
+        assert_eq!(fs::read_to_string(&playlist).unwrap(), content);
+
+        let mut cmd = plm_command("plm-new-playlist");
+        cmd.arg("--force")
+            .arg(playlist.to_str().unwrap())
+            .arg(music_dir.join("artist3").to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains("Source not found"));
+
+        let mut cmd = plm_command("plm-new-playlist");
+        cmd.arg("--force")
+            .arg("--ext")
+            .arg("lrc")
+            .arg(playlist.to_str().unwrap())
+            .arg(music_dir.join("artist2").to_str().unwrap())
+            .assert()
+            .success();
+        assert_eq!(
+            fs::read_to_string(&playlist).unwrap(),
+            "artist2/album2/title1.lrc\n"
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Audiobook playlist with a media file of 2 KiB, and the profile
+    /// putting audiobooks into a directory of their own without lyrics
+    /// or media files over 1 KiB
+    fn setup_audiobooks(music_dir: &std::path::Path, profile: &std::path::Path, head: &str) {
+        create_test_file(&music_dir.join("big.flac"), &"x".repeat(2048));
+        create_test_file(
+            &music_dir.join("Audiobooks.m3u8"),
+            "artist2/album2/title1.flac\nbig.flac\n",
+        );
+        create_test_file(
+            &music_dir.join("rock.m3u8"),
+            "artist2/album2/title1.flac\nbig.flac\n",
+        );
+        create_test_file(
+            profile,
+            &format!(
+                "{}playlist \"Audio*.m3u8\" lyrics=false max-file-size=1K dest-subdir=AUDIOBOOKS\n",
+                head
+            ),
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_playlist_options() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let profile = temp_dir.path().join("player.profile");
+        fs::create_dir_all(&dest_dir).unwrap();
+        setup_audiobooks(&music_dir, &profile, "");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--profile")
+            .arg(profile.to_str().unwrap())
+            .arg("-l")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("rock.m3u8").to_str().unwrap())
+            .arg(music_dir.join("Audiobooks.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(2/2) playlist copied"))
+            .stdout(predicate::str::contains("(3/3) media files copied"))
+            .stderr(predicate::str::contains(
+                "Warning: Skipping media file \"big.flac\" larger than 1.0 KiB",
+            ));
+
+        // Playlists without options are put with the command-line options
+        assert!(dest_dir.join("rock.m3u8").is_file());
+        assert!(dest_dir.join("big.flac").is_file());
+        assert!(dest_dir.join("artist2/album2/title1.lrc").is_file());
+
+        // The audiobooks are put into their directory, without lyrics and
+        // without the media file over the maximum size
+        let books_dir = dest_dir.join("AUDIOBOOKS");
+        assert_eq!(
+            fs::read_to_string(books_dir.join("Audiobooks.m3u8")).unwrap(),
+            "artist2/album2/title1.flac"
+        );
+        assert!(books_dir.join("artist2/album2/title1.flac").is_file());
+        assert!(!books_dir.join("artist2/album2/title1.lrc").exists());
+        assert!(!books_dir.join("big.flac").exists());
+
+        // A malformed playlist line is refused before anything is copied
+        create_test_file(&profile, "playlist *.m3u8 lyrics=maybe\n");
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--profile")
+            .arg(profile.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("rock.m3u8").to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains(
+                "line 1: Invalid value of lyrics: maybe (expected true or false)",
+            ));
+    }
+
+    #[test]
+    fn test_sync_device_playlist_options() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let profile_dir = temp_dir.path().join("devices");
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::create_dir_all(&profile_dir).unwrap();
+        let subscribe = format!("subscribe {}/*.m3u8\n", music_dir.display());
+        setup_audiobooks(&music_dir, &profile_dir.join("walkman"), &subscribe);
+
+        let sync = |dry_run: bool| {
+            let mut cmd = plm_command("plm-sync");
+            cmd.env("PLM_PROFILE_DIR", &profile_dir)
+                .args(["--device", "walkman", "--delete"])
+                .args(dry_run.then_some("--dry-run"))
+                .arg(dest_dir.to_str().unwrap())
+                .args(["--", "-l"])
+                .assert()
+                .success()
+        };
+        sync(false);
+        let books_dir = dest_dir.join("AUDIOBOOKS");
+        assert!(books_dir.join("Audiobooks.m3u8").is_file());
+        assert!(books_dir.join("artist2/album2/title1.flac").is_file());
+        assert!(!books_dir.join("artist2/album2/title1.lrc").exists());
+        assert!(!books_dir.join("big.flac").exists());
+        assert!(dest_dir.join("big.flac").is_file());
+        assert!(dest_dir.join("artist2/album2/title1.lrc").is_file());
+
+        // The media files put into the directory of the audiobooks are
+        // referenced, and kept by --delete
+        sync(true).stdout(predicate::str::contains(
+            "(0) files would be moved to trash",
+        ));
+        sync(false).stdout(predicate::str::contains("(0) files moved to trash"));
+        assert!(books_dir.join("artist2/album2/title1.flac").is_file());
+    }
+
+    #[test]
+    fn test_sync_profile_playlist_options() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let profile = temp_dir.path().join("player.profile");
+        fs::create_dir_all(&dest_dir).unwrap();
+        setup_audiobooks(&music_dir, &profile, "");
+        let playlist = music_dir.join("Audiobooks.m3u8");
+
+        // The playlist options of a profile given by --post-sync are not
+        // applied, only its actions
+        plm_command("plm-sync")
+            .arg("--post-sync")
+            .arg(profile.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist.to_str().unwrap())
+            .assert()
+            .success();
+        assert!(dest_dir.join("big.flac").is_file());
+        assert!(!dest_dir.join("AUDIOBOOKS").exists());
+
+        plm_command("plm-sync")
+            .arg("--profile")
+            .arg(profile.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist.to_str().unwrap())
+            .assert()
+            .success();
+        let books_dir = dest_dir.join("AUDIOBOOKS");
+        assert!(books_dir.join("Audiobooks.m3u8").is_file());
+        assert!(!books_dir.join("big.flac").exists());
+    }
+}
@@ -0,0 +1,136 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_post_sync() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let profile = temp_dir.path().join("player.profile");
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_test_file(
+            &profile,
+            "# Refresh the database\ntouch DB/refresh\nrun ls artist1/album1 > files.txt\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--post-sync")
+            .arg(profile.to_str().unwrap())
+            .arg("-v")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("Touching marker file"));
+
+        // The actions run after every file is copied
+        assert!(dest_dir.join("DB/refresh").exists());
+        let files = fs::read_to_string(dest_dir.join("files.txt")).unwrap();
+        assert!(files.contains("title2.flac"));
+
+        // Nothing runs in a dry run
+        fs::remove_file(dest_dir.join("DB/refresh")).unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--post-sync")
+            .arg(profile.to_str().unwrap())
+            .arg("--dry-run")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success();
+        assert!(!dest_dir.join("DB/refresh").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_post_sync_errors() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let profile = temp_dir.path().join("player.profile");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        // A malformed profile is refused before anything is copied
+        create_test_file(&profile, "touch DB/refresh\nreboot\n");
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--post-sync")
+            .arg(profile.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains("line 2: Unknown action: reboot"));
+        assert!(!dest_dir.join("playlist.m3u8").exists());
+
+        // A failing command fails the run after copying
+        create_test_file(&profile, "run exit 3\ntouch DB/refresh\n");
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--post-sync")
+            .arg(profile.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("Command failed"));
+        assert!(dest_dir.join("playlist.m3u8").exists());
+        assert!(!dest_dir.join("DB/refresh").exists());
+    }
+
+    #[test]
+    fn test_sync_post_sync_after_delete() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let profile = temp_dir.path().join("player.profile");
+        fs::create_dir_all(dest_dir.join("artist9")).unwrap();
+        create_test_file(&dest_dir.join("artist9/title9.flac"), "dropped");
+        create_test_file(
+            &profile,
+            "run test ! -e artist9/title9.flac && touch synced\n",
+        );
+
+        let mut cmd = plm_command("plm-sync");
+        cmd.arg("--delete")
+            .arg("--post-sync")
+            .arg(profile.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1) files moved to trash"));
+        assert!(dest_dir.join("synced").exists());
+    }
+
+    #[test]
+    fn test_delete_playlist_post_sync() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let profile = temp_dir.path().join("player.profile");
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_test_file(&profile, "touch DB/refresh\n");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success();
+
+        let mut cmd = plm_command("plm-delete-playlist");
+        cmd.arg("-m")
+            .arg("--post-sync")
+            .arg(profile.to_str().unwrap())
+            .arg(dest_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success();
+        assert!(!dest_dir.join("playlist.m3u8").exists());
+        assert!(dest_dir.join("DB/refresh").exists());
+    }
+}
@@ -0,0 +1,120 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Delete the media files of `artist2`, leaving its album directories
+    /// and an empty trash directory behind
+    fn delete_artist2(music_dir: &Path) {
+        fs::remove_dir_all(music_dir.join("artist2")).unwrap();
+        fs::create_dir_all(music_dir.join("artist2/album1")).unwrap();
+        fs::create_dir_all(music_dir.join("artist2/album2")).unwrap();
+        fs::create_dir_all(music_dir.join(".Trashes/501")).unwrap();
+    }
+
+    #[test]
+    fn test_prune_deletes_empty_directories() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        delete_artist2(&music_dir);
+
+        let mut cmd = plm_command("plm-prune");
+        cmd.arg("--verbose")
+            .arg("--exclude")
+            .arg(".Trash*")
+            .arg(music_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Number of deleted directories: 3"))
+            .stderr(predicate::str::contains("Deleting empty directory"));
+
+        assert!(!music_dir.join("artist2").exists());
+        assert!(music_dir.join(".Trashes/501").exists());
+        assert!(music_dir.join("artist1/album1/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_prune_dry_run() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        delete_artist2(&music_dir);
+
+        let mut cmd = plm_command("plm-prune");
+        cmd.arg("--dry-run")
+            .arg("--min-depth")
+            .arg("2")
+            .arg(music_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("artist2/album1"))
+            .stdout(predicate::str::contains(".Trashes/501"))
+            .stdout(predicate::str::contains("artist2\n").not());
+
+        assert!(music_dir.join("artist2/album1").exists());
+    }
+
+    #[test]
+    fn test_prune_min_depth_zero_deletes_dir() {
+        let temp_dir = setup_test_directory();
+        let empty_dir = temp_dir.path().join("EMPTY");
+        fs::create_dir_all(empty_dir.join("a/b")).unwrap();
+
+        let mut cmd = plm_command("plm-prune");
+        cmd.arg(empty_dir.to_str().unwrap()).assert().success();
+        assert!(empty_dir.exists() && !empty_dir.join("a").exists());
+
+        let mut cmd = plm_command("plm-prune");
+        cmd.arg("--min-depth")
+            .arg("0")
+            .arg(empty_dir.to_str().unwrap())
+            .assert()
+            .success();
+        assert!(!empty_dir.exists());
+    }
+
+    #[test]
+    fn test_prune_exclude_from_file() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        delete_artist2(&music_dir);
+        let exclusion_file = temp_dir.path().join("keep.txt");
+        create_test_file(&exclusion_file, "# Keep the albums\nartist2/album*\n");
+
+        let mut cmd = plm_command("plm-prune");
+        cmd.arg("--exclude-from")
+            .arg(exclusion_file.to_str().unwrap())
+            .arg(music_dir.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert!(music_dir.join("artist2/album1").exists());
+        assert!(!music_dir.join(".Trashes").exists());
+    }
+
+    #[test]
+    fn test_prune_invalid_arguments() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+
+        let mut cmd = plm_command("plm-prune");
+        cmd.arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains("Not a directory"));
+
+        let mut cmd = plm_command("plm-prune");
+        cmd.arg("--exclude")
+            .arg("artist[")
+            .arg(music_dir.to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains("Invalid exclusion"));
+    }
+}
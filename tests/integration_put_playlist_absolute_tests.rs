@@ -0,0 +1,117 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_mixed_absolute_entries() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let playlist_path = music_dir.join("mixed.m3u8");
+        create_test_file(
+            &playlist_path,
+            &format!(
+                "#EXTM3U\n#EXTINF:180,Artist 1 - Title 1\n{}\nartist1\\album1\\title2.flac\n{}\n",
+                music_dir.join("artist1/album1/title1.flac").display(),
+                music_dir.join("artist2/./album2/title1.flac").display()
+            ),
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--lyrics")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(3/3) media files copied"));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("mixed.m3u8")).unwrap(),
+            "#EXTM3U\n#EXTINF:180,Artist 1 - Title 1\nartist1/album1/title1.flac\nartist1/album1/title2.flac\nartist2/album2/title1.flac"
+        );
+        assert!(dest_dir.join("artist1/album1/title1.flac").exists());
+        assert!(dest_dir.join("artist1/album1/title1.lrc").exists());
+        assert!(dest_dir.join("artist2/album2/title1.flac").exists());
+        assert!(dest_dir.join("artist2/album2/title1.lrc").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_absolute_entry_outside_playlist_dir() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let outside = temp_dir.path().join("OTHER/title1.flac");
+        fs::create_dir_all(outside.parent().unwrap()).unwrap();
+        create_test_file(&outside, "other content");
+        let playlist_path = music_dir.join("outside.m3u8");
+        create_test_file(
+            &playlist_path,
+            &format!("artist1/album1/title1.flac\n{}\n", outside.display()),
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("is not under"));
+
+        assert!(!dest_dir.join("outside.m3u8").exists());
+        assert_eq!(fs::read_to_string(&outside).unwrap(), "other content");
+    }
+
+    #[test]
+    fn test_put_playlist_file_uri_entries() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::create_dir_all(music_dir.join("artist3/Café")).unwrap();
+        create_test_file(&music_dir.join("artist3/Café/title 1.flac"), "content 5");
+
+        let playlist_path = music_dir.join("uri.m3u8");
+        let uri = |path: &str| {
+            format!("file://{}/{}", music_dir.display(), path)
+                .replace(' ', "%20")
+                .replace('é', "%C3%A9")
+        };
+        create_test_file(
+            &playlist_path,
+            &format!(
+                "#EXTM3U\n{}\n{}\n",
+                uri("artist1/album1/title1.flac"),
+                uri("artist3/Café/title 1.flac")
+            ),
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(2/2) media files copied"));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("uri.m3u8")).unwrap(),
+            "#EXTM3U\nartist1/album1/title1.flac\nartist3/Café/title 1.flac"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist3/Café/title 1.flac")).unwrap(),
+            "content 5"
+        );
+    }
+}
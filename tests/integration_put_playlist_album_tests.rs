@@ -0,0 +1,133 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_album_playlists() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        // A numbered track already on the device belongs to the album too
+        fs::create_dir_all(dest_dir.join("artist1/album1")).unwrap();
+        create_test_file(&dest_dir.join("artist1/album1/01 intro.flac"), "intro");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--album-playlists")
+            .arg("--lyrics")
+            .arg("--verbose")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stderr(predicate::str::contains("Create album playlist"));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist1/album1/album1.m3u8")).unwrap(),
+            "01 intro.flac\ntitle1.flac\ntitle2.flac\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist2/album1/album1.m3u8")).unwrap(),
+            "title1.flac\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist2/album2/album2.m3u8")).unwrap(),
+            "title1.flac\n"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_album_playlists_sorted_by_track_number() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::create_dir_all(music_dir.join("artist3/album1")).unwrap();
+        for file in ["10 ten.mp3", "2 two.mp3", "1 one.mp3"] {
+            create_test_file(&music_dir.join("artist3/album1").join(file), file);
+        }
+        let playlist_path = music_dir.join("album.m3u8");
+        create_test_file(
+            &playlist_path,
+            "artist3/album1/10 ten.mp3\nartist3/album1/2 two.mp3\nartist3/album1/1 one.mp3\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--album-playlists")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist3/album1/album1.m3u8")).unwrap(),
+            "1 one.mp3\n2 two.mp3\n10 ten.mp3\n"
+        );
+        assert!(!dest_dir.join("artist3/artist3.m3u8").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_without_album_playlists() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success();
+
+        assert!(!dest_dir.join("artist1/album1/album1.m3u8").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_fat_order() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--fat-order")
+            .arg("--album-playlists")
+            .arg("--lyrics")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"));
+
+        let album_dir = dest_dir.join("artist1/album1");
+        let mut files: Vec<String> = fs::read_dir(&album_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            vec!["album1.m3u8", "title1.flac", "title1.lrc", "title2.flac"]
+        );
+        assert_eq!(
+            fs::read_to_string(album_dir.join("title2.flac")).unwrap(),
+            "test content 2"
+        );
+    }
+}
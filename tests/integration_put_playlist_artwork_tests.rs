@@ -0,0 +1,90 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Create cover images and other images in album directories of the
+    /// test directory
+    fn create_artwork(music_dir: &Path) {
+        create_test_file(&music_dir.join("artist1/album1/cover.jpg"), "JPEG");
+        create_test_file(&music_dir.join("artist1/album1/back.jpg"), "JPEG");
+        create_test_file(&music_dir.join("artist2/album1/Folder.JPG"), "JPEG");
+        create_test_file(&music_dir.join("artist2/album2/front.png"), "PNG");
+    }
+
+    #[test]
+    fn test_put_playlist_copies_artwork() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_artwork(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--artwork")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(2/2) artwork files copied"));
+
+        // The album holding two tracks of the playlist gets its cover once
+        assert!(dest_dir.join("artist1/album1/cover.jpg").is_file());
+        assert!(!dest_dir.join("artist1/album1/back.jpg").exists());
+        assert!(dest_dir.join("artist2/album1/Folder.JPG").is_file());
+        assert!(!dest_dir.join("artist2/album2/front.png").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_copies_artwork_with_patterns() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_artwork(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--dry-run")
+            .arg("--artwork")
+            .arg("--artwork-pattern")
+            .arg("front.*,back.jpg")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                ">f+++++++++ artist1/album1/back.jpg",
+            ))
+            .stdout(predicate::str::contains(
+                ">f+++++++++ artist2/album2/front.png",
+            ))
+            .stdout(predicate::str::contains("cover.jpg").not());
+
+        assert!(!dest_dir.join("artist1").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_artwork_pattern_requires_artwork() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--artwork-pattern")
+            .arg("cover.jpg")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure();
+
+        assert!(!dest_dir.join("playlist.m3u8").exists());
+    }
+}
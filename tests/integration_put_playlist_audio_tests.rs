@@ -0,0 +1,127 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Create a playlist listing a cover image and a booklet among tracks
+    fn create_sloppy_playlist(music_dir: &Path) -> PathBuf {
+        create_test_file(&music_dir.join("artist1/album1/cover.jpg"), "image");
+        create_test_file(&music_dir.join("artist1/album1/booklet.pdf"), "booklet");
+
+        let playlist_path = music_dir.join("sloppy.m3u8");
+        create_test_file(
+            &playlist_path,
+            "#EXTM3U\n#EXTINF:1,Title 1\nartist1/album1/title1.flac\nartist1/album1/cover.jpg\n#EXTINF:-1,Booklet\nartist1/album1/booklet.pdf\nartist2/album1/title1.flac\n",
+        );
+        playlist_path
+    }
+
+    #[test]
+    fn test_put_playlist_audio_only() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_sloppy_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--audio-only")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(2/2) media files copied"))
+            .stdout(predicate::str::contains("(2) non-audio entries skipped"))
+            .stderr(predicate::str::contains(
+                "Warning: Skipping non-audio entry \"artist1/album1/cover.jpg\"",
+            ))
+            .stderr(predicate::str::contains(
+                "Warning: Skipping non-audio entry \"artist1/album1/booklet.pdf\"",
+            ));
+
+        assert!(!dest_dir.join("artist1/album1/cover.jpg").exists());
+        assert!(!dest_dir.join("artist1/album1/booklet.pdf").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("sloppy.m3u8")).unwrap(),
+            fs::read_to_string(&playlist_path).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_drop_non_audio() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_sloppy_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--drop-non-audio")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(2) non-audio entries skipped"));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("sloppy.m3u8")).unwrap(),
+            "#EXTM3U\n#EXTINF:1,Title 1\nartist1/album1/title1.flac\nartist2/album1/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_audio_extensions() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_sloppy_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--audio-extensions")
+            .arg("flac,.JPG")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(3/3) media files copied"))
+            .stdout(predicate::str::contains("(1) non-audio entries skipped"));
+
+        assert!(dest_dir.join("artist1/album1/cover.jpg").exists());
+        assert!(!dest_dir.join("artist1/album1/booklet.pdf").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_without_audio_only() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_sloppy_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stdout(predicate::str::contains("non-audio").not())
+            .stderr(predicate::str::contains("Warning").not());
+
+        assert!(dest_dir.join("artist1/album1/cover.jpg").exists());
+    }
+}
@@ -0,0 +1,102 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    fn put_playlist(
+        dest_dir: &Path,
+        playlist_path: &Path,
+        options: &[&str],
+    ) -> assert_cmd::assert::Assert {
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("-v")
+            .args(options)
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+    }
+
+    #[test]
+    fn test_put_playlist_backup_beside_changed_files() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        put_playlist(&dest_dir, &playlist_path, &[]).success();
+        let dest_file = dest_dir.join("artist1/album1/title1.flac");
+        create_test_file(&dest_file, "old content 1");
+
+        put_playlist(&dest_dir, &playlist_path, &["--backup"])
+            .success()
+            .stderr(predicate::str::contains("Back up \""));
+
+        let backup = dest_dir.join("artist1/album1/title1.flac.plm-bak");
+        assert_eq!(fs::read_to_string(&backup).unwrap(), "old content 1");
+        assert_eq!(
+            fs::read_to_string(&dest_file).unwrap(),
+            fs::read_to_string(music_dir.join("artist1/album1/title1.flac")).unwrap()
+        );
+
+        // Files with the same contents are overwritten without a backup
+        assert!(!dest_dir.join("artist1/album1/title2.flac.plm-bak").exists());
+        assert!(!dest_dir.join("playlist.m3u8.plm-bak").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_backup_dir() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let backup_dir = temp_dir.path().join("BACKUP");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        put_playlist(&dest_dir, &playlist_path, &[]).success();
+        create_test_file(&dest_dir.join("artist2/album1/title1.flac"), "old content");
+        create_test_file(&dest_dir.join("playlist.m3u8"), "old entry.flac\n");
+
+        put_playlist(
+            &dest_dir,
+            &playlist_path,
+            &["--backup-dir", backup_dir.to_str().unwrap()],
+        )
+        .success();
+
+        assert_eq!(
+            fs::read_to_string(backup_dir.join("artist2/album1/title1.flac")).unwrap(),
+            "old content"
+        );
+        assert_eq!(
+            fs::read_to_string(backup_dir.join("playlist.m3u8")).unwrap(),
+            "old entry.flac\n"
+        );
+        assert!(!dest_dir.join("artist2/album1/title1.flac.plm-bak").exists());
+        assert!(!backup_dir.join("artist1").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_without_backup() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        put_playlist(&dest_dir, &playlist_path, &[]).success();
+        create_test_file(&dest_dir.join("artist1/album1/title1.flac"), "old content");
+        put_playlist(&dest_dir, &playlist_path, &[])
+            .success()
+            .stderr(predicate::str::contains("Back up").not());
+
+        assert!(!dest_dir.join("artist1/album1/title1.flac.plm-bak").exists());
+    }
+}
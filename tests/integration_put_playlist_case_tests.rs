@@ -0,0 +1,110 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Create a playlist referring to tracks in directories of mixed case
+    fn create_mixed_case_playlist(music_dir: &Path) -> PathBuf {
+        fs::create_dir_all(music_dir.join("The Band/First Album")).unwrap();
+        create_test_file(
+            &music_dir.join("The Band/First Album/01 Intro.flac"),
+            "test content 5",
+        );
+        create_test_file(
+            &music_dir.join("The Band/First Album/01 Intro.lrc"),
+            "lyrics 5",
+        );
+
+        let playlist_path = music_dir.join("Mixed.m3u8");
+        create_test_file(
+            &playlist_path,
+            "#EXTM3U\n#EXTINF:1,The Band - Intro\nThe Band\\First Album\\01 Intro.flac\nartist1/album1/title1.flac\n",
+        );
+        playlist_path
+    }
+
+    #[test]
+    fn test_put_playlist_case_lower() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_mixed_case_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--case")
+            .arg("lower")
+            .arg("--lyrics")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(2/2) media files copied"))
+            .stdout(predicate::str::contains("(2/2) lyrics files copied"));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("the band/first album/01 intro.flac")).unwrap(),
+            "test content 5"
+        );
+        assert!(dest_dir.join("the band/first album/01 intro.lrc").exists());
+        assert!(!dest_dir.join("The Band").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("mixed.m3u8")).unwrap(),
+            "#EXTM3U\n#EXTINF:1,The Band - Intro\nthe band/first album/01 intro.flac\nartist1/album1/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_case_upper() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_mixed_case_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--case")
+            .arg("upper")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert!(dest_dir.join("THE BAND/FIRST ALBUM/01 INTRO.FLAC").exists());
+        assert!(dest_dir.join("ARTIST1/ALBUM1/TITLE1.FLAC").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("MIXED.M3U8")).unwrap(),
+            "#EXTM3U\n#EXTINF:1,The Band - Intro\nTHE BAND/FIRST ALBUM/01 INTRO.FLAC\nARTIST1/ALBUM1/TITLE1.FLAC"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_case_invalid() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--case")
+            .arg("title")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Unknown case: title"));
+    }
+}
@@ -0,0 +1,82 @@
+use std::fs;
+
+use serde_json::Value;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_print_config() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.txt");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist = music_dir.join("playlist.m3u8");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let output = cmd
+            .arg("--print-config")
+            .arg("--dry-run")
+            .arg("--case")
+            .arg("lower")
+            .arg("--backup-dir")
+            .arg("backups")
+            .arg("--audio-extensions")
+            .arg(".FLAC,mp3")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist.to_str().unwrap())
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+
+        // Defaults are filled in and implied options turned on
+        let config: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(config["dest"], dest_dir.to_str().unwrap());
+        assert_eq!(config["playlists"][0], playlist.to_str().unwrap());
+        assert_eq!(config["case"], "lower");
+        assert_eq!(config["layout"], "source");
+        assert_eq!(config["on-conflict"], "first-wins");
+        assert_eq!(config["dest-encoding"], "utf8");
+        assert_eq!(config["min-free"], 64 << 20);
+        assert_eq!(config["max-depth"], Value::Null);
+        assert_eq!(config["keep-going"], true);
+        assert_eq!(config["itemize"], true);
+        assert_eq!(config["backup"], true);
+        assert_eq!(config["audio-only"], true);
+        assert_eq!(
+            config["audio-extensions"],
+            serde_json::json!(["flac", "mp3"])
+        );
+        assert_eq!(config["lyrics"], false);
+
+        // Nothing is read or written
+        assert!(!error_file.exists());
+        assert!(fs::read_dir(&dest_dir).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_put_playlist_print_config_invalid_arguments() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--print-config")
+            .arg("--error-files")
+            .arg(temp_dir.path().join("errors.txt").to_str().unwrap())
+            .arg(temp_dir.path().join("DEST").to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .code(255)
+            .stdout("");
+    }
+}
@@ -0,0 +1,222 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Create a playlist in another source directory referencing a
+    /// different file at the path of `artist1/album1/title1.flac`
+    fn create_conflicting_playlist(root: &Path) -> PathBuf {
+        let other_dir = root.join("OTHER");
+        fs::create_dir_all(other_dir.join("artist1/album1")).unwrap();
+        create_test_file(
+            &other_dir.join("artist1/album1/title1.flac"),
+            "other content",
+        );
+
+        let playlist_path = other_dir.join("other.m3u8");
+        create_test_file(&playlist_path, "artist1/album1/title1.flac\n");
+        playlist_path
+    }
+
+    #[test]
+    fn test_put_playlist_conflict_first_wins() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let other_playlist = create_conflicting_playlist(temp_dir.path());
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg(other_playlist.to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(2/2) playlist copied"))
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stderr(predicate::str::contains(
+                "are both copied to \"artist1/album1/title1.flac\"",
+            ));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist1/album1/title1.flac")).unwrap(),
+            "test content 1"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("other.m3u8")).unwrap(),
+            "artist1/album1/title1.flac\n"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_conflict_rename() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let other_playlist = create_conflicting_playlist(temp_dir.path());
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--on-conflict")
+            .arg("rename")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg(other_playlist.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(5/5) media files copied"))
+            .stderr(predicate::str::contains(
+                "to \"artist1/album1/title1 (2).flac\"",
+            ));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist1/album1/title1.flac")).unwrap(),
+            "test content 1"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist1/album1/title1 (2).flac")).unwrap(),
+            "other content"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("other.m3u8")).unwrap(),
+            "artist1/album1/title1 (2).flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_conflict_error() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let other_playlist = create_conflicting_playlist(temp_dir.path());
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--on-conflict")
+            .arg("error")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg(other_playlist.to_str().unwrap())
+            .assert()
+            .failure()
+            .code(1)
+            .stderr(predicate::str::contains(
+                "1 media files conflict with others at the destination",
+            ));
+
+        assert!(!dest_dir.join("playlist.m3u8").exists());
+        assert!(!dest_dir.join("artist1").exists());
+    }
+
+    /// Create a playlist referencing `artist1/album1/title1.flac` and a
+    /// different file whose name differs from it only in case
+    fn create_colliding_playlist(music_dir: &Path) -> PathBuf {
+        create_test_file(
+            &music_dir.join("artist1/album1/Title1.flac"),
+            "other content",
+        );
+
+        let playlist_path = music_dir.join("colliding.m3u8");
+        create_test_file(
+            &playlist_path,
+            "artist1/album1/title1.flac\nartist1/album1/Title1.flac\n",
+        );
+        playlist_path
+    }
+
+    #[test]
+    fn test_put_playlist_collision_hash() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_colliding_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--case")
+            .arg("lower")
+            .arg("--on-collision")
+            .arg("hash")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(2/2) media files copied"));
+
+        // The renamed file gets the CRC-32 of its source path
+        let renamed = "artist1/album1/title1 [3e0b38e2].flac";
+        assert_eq!(
+            fs::read_to_string(dest_dir.join(renamed)).unwrap(),
+            "other content"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("colliding.m3u8")).unwrap(),
+            format!("artist1/album1/title1.flac\n{}", renamed)
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_collision_skip_and_fail() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.txt");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_colliding_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--case")
+            .arg("lower")
+            .arg("--on-collision")
+            .arg("fail")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains(
+                "1 renamed media files collide with other files at the destination",
+            ));
+        assert!(!dest_dir.join("artist1").exists());
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--case")
+            .arg("lower")
+            .arg("--on-collision")
+            .arg("skip")
+            .arg("--keep-going")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(
+                "renamed to the destination path of another file",
+            ))
+            .stdout(predicate::str::contains("(1/2) media files copied"));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist1/album1/title1.flac")).unwrap(),
+            "test content 1"
+        );
+        assert!(fs::read_to_string(&error_file).unwrap().ends_with(&format!(
+            "M {}\n",
+            music_dir.join("artist1/album1/Title1.flac").display()
+        )));
+    }
+}
@@ -0,0 +1,141 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_dedup_copies_identical_tracks_once() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        // The same rip in a second album folder
+        create_test_file(
+            &music_dir.join("artist2/album2/title1.flac"),
+            "test content 1",
+        );
+
+        let playlist_path = music_dir.join("playlist.m3u8");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--dedup")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(1/1) playlist copied"))
+            .stdout(predicate::str::contains("(3/3) media files copied"));
+
+        assert!(dest_dir.join("artist1/album1/title1.flac").exists());
+        assert!(!dest_dir.join("artist2/album2/title1.flac").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("playlist.m3u8")).unwrap(),
+            "artist1/album1/title1.flac\nartist1/album1/title2.flac\nartist2/album1/title1.flac\nartist1/album1/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_dedup_across_playlists() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        fs::create_dir_all(music_dir.join("artist3/album1")).unwrap();
+        create_test_file(
+            &music_dir.join("artist3/album1/title1.flac"),
+            "test content 2",
+        );
+        let playlist2_path = music_dir.join("playlist2.m3u8");
+        create_test_file(
+            &playlist2_path,
+            "#EXTM3U\n#EXTINF:1,Title\nartist3\\album1\\title1.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--dedup")
+            .arg("--verbose")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg(playlist2_path.to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stderr(predicate::str::contains(
+                "Skip track \"artist3/album1/title1.flac\" identical to \"artist1/album1/title2.flac\"",
+            ));
+
+        assert!(!dest_dir.join("artist3").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("playlist2.m3u8")).unwrap(),
+            "#EXTM3U\n#EXTINF:1,Title\nartist1/album1/title2.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_dedup_with_hash() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        create_test_file(
+            &music_dir.join("artist2/album2/title1.flac"),
+            "test content 1",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--dedup")
+            .arg("--hash")
+            .arg("xxh3")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(3/3) media files copied"));
+
+        assert!(!dest_dir.join("artist2/album2/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_without_dedup_copies_identical_tracks() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        create_test_file(
+            &music_dir.join("artist2/album2/title1.flac"),
+            "test content 1",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"));
+
+        assert!(dest_dir.join("artist2/album2/title1.flac").exists());
+    }
+}
@@ -0,0 +1,77 @@
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+
+use assert_cmd::cargo::cargo_bin;
+use playlist_manager::device_lock::{lock_devices, physical_device, LOCK_DIR_VAR};
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_waits_for_same_device() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let lock_dir = temp_dir.path().join("locks");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::create_dir_all(&lock_dir).unwrap();
+
+        // Another run holds the lock of the device of the destination
+        let locks = lock_devices(&[&dest_dir], &lock_dir, |_| {}).unwrap();
+
+        let mut child = Command::new(cargo_bin("plm-put-playlist"))
+            .env(LOCK_DIR_VAR, &lock_dir)
+            .arg("--verbose")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut stderr = BufReader::new(child.stderr.take().unwrap());
+        let mut line = String::new();
+        stderr.read_line(&mut line).unwrap();
+
+        assert_eq!(
+            line.trim_end(),
+            format!(
+                "Waiting for another run into device {} to finish",
+                physical_device(&dest_dir).unwrap()
+            )
+        );
+        assert!(!dest_dir.join("playlist.m3u8").exists());
+
+        // The run copies once the lock is released
+        drop(locks);
+        assert!(child.wait().unwrap().success());
+        assert!(dest_dir.join("playlist.m3u8").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_dry_run_does_not_wait() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let lock_dir = temp_dir.path().join("locks");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::create_dir_all(&lock_dir).unwrap();
+        let _locks = lock_devices(&[&dest_dir], &lock_dir, |_| {}).unwrap();
+
+        plm_command("plm-put-playlist")
+            .env(LOCK_DIR_VAR, &lock_dir)
+            .args(["--dry-run", "--verbose"])
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("Waiting").not());
+    }
+}
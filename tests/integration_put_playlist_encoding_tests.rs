@@ -0,0 +1,121 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_dest_encoding_utf16le() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let playlist_path = music_dir.join("extended.m3u8");
+        create_test_file(
+            &playlist_path,
+            "\u{feff}#EXTM3U\n#EXTINF:180,Title 1\nartist1\\album1\\title1.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--dest-encoding")
+            .arg("utf16le")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(1/1) playlist copied"))
+            .stdout(predicate::str::contains("(1/1) media files copied"));
+
+        let mut expected = vec![0xff, 0xfe];
+        expected.extend(
+            "#EXTM3U\n#EXTINF:180,Title 1\nartist1/album1/title1.flac"
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes),
+        );
+        assert_eq!(fs::read(dest_dir.join("extended.m3u8")).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_put_playlist_dest_encoding_cp932() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        fs::create_dir_all(music_dir.join("アーティスト")).unwrap();
+        create_test_file(&music_dir.join("アーティスト/曲.flac"), "test content 5");
+        let playlist_path = music_dir.join("japanese.m3u8");
+        create_test_file(&playlist_path, "アーティスト/曲.flac\n");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--dest-encoding")
+            .arg("cp932")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert!(dest_dir.join("アーティスト/曲.flac").exists());
+        assert_eq!(
+            fs::read(dest_dir.join("japanese.m3u8")).unwrap(),
+            b"\x83\x41\x81\x5b\x83\x65\x83\x42\x83\x58\x83\x67/\x8b\xc8.flac".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_dest_encoding_cp932_unmappable() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        fs::create_dir_all(music_dir.join("café")).unwrap();
+        create_test_file(&music_dir.join("café/title1.flac"), "test content 5");
+        let playlist_path = music_dir.join("french.m3u8");
+        create_test_file(&playlist_path, "café/title1.flac\n");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--dest-encoding")
+            .arg("cp932")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .failure()
+            .code(1)
+            .stderr(predicate::str::contains("cannot be encoded in cp932"));
+
+        assert!(!dest_dir.join("french.m3u8").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_dest_encoding_invalid() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--dest-encoding")
+            .arg("latin1")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Unknown encoding: latin1"));
+
+        assert!(!dest_dir.join("playlist.m3u8").exists());
+    }
+}
@@ -0,0 +1,232 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_error_files_escape_surrounding_spaces() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.log");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        // Create a playlist referring to a missing file with surrounding spaces
+        let playlist_content = "artist1/album1/title1.flac\nartist1/album1/ spaced.flac ";
+        let playlist_path = music_dir.join("playlist_with_spaces.m3u8");
+        create_test_file(&playlist_path, playlist_content);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--keep-going")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        let error_content = fs::read_to_string(&error_file).unwrap();
+        let lines: Vec<&str> = error_content.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "# plm-error-file 2");
+        assert!(lines[1].starts_with("# plm-run "));
+        assert_eq!(lines[2], "# plm-totals playlists=1 media=2");
+        assert!(lines[3].starts_with("M "));
+        assert!(lines[3].ends_with("artist1/album1/ spaced.flac\\s"));
+
+        // Create the missing file and retry from the error file
+        create_test_file(
+            &music_dir.join("artist1/album1/ spaced.flac "),
+            "test content for spaced file",
+        );
+
+        let mut retry_cmd = plm_command("plm-put-playlist");
+        retry_cmd
+            .arg("--retry")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1/1) media files copied"))
+            .stdout(predicate::str::contains(
+                "(2/2) media files present after retry",
+            ));
+
+        // The trailing space Windows cannot use is dropped on the destination
+        let content = fs::read_to_string(dest_dir.join("artist1/album1/ spaced.flac")).unwrap();
+        assert_eq!(content, "test content for spaced file");
+    }
+
+    #[test]
+    fn test_error_files_escape_line_breaks() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.log");
+        let new_error_file = temp_dir.path().join("new_errors.log");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        // Create a playlist in a directory whose name contains a line break
+        let odd_dir = music_dir.join("odd\ndir");
+        fs::create_dir_all(&odd_dir).unwrap();
+        let playlist_path = odd_dir.join("playlist.m3u8");
+        create_test_file(&playlist_path, "missing.flac");
+        let missing_playlist_path = odd_dir.join("missing\tplaylist.m3u8");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--keep-going")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(missing_playlist_path.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        // Each failure must stay on a single line; the totals follow the
+        // failure of the first playlist, recorded before the second one
+        // was parsed
+        let error_content = fs::read_to_string(&error_file).unwrap();
+        let lines: Vec<&str> = error_content.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "# plm-error-file 2");
+        assert!(lines[1].starts_with("# plm-run "));
+        assert!(lines[2].starts_with("P "));
+        assert!(lines[2].ends_with("/MUSIC/odd\\ndir/missing\\tplaylist.m3u8"));
+        assert_eq!(lines[3], "# plm-totals playlists=2 media=1");
+        assert!(lines[4].starts_with("M "));
+        assert!(lines[4].ends_with("/MUSIC/odd\\ndir/missing.flac"));
+
+        // Retrying the still missing entries writes them back unchanged
+        let mut retry_cmd = plm_command("plm-put-playlist");
+        retry_cmd
+            .arg("--retry")
+            .arg(error_file.to_str().unwrap())
+            .arg("--keep-going")
+            .arg("--error-files")
+            .arg(new_error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                "(1/2) playlists present after retry",
+            ))
+            .stdout(predicate::str::contains(
+                "(0/1) media files present after retry",
+            ));
+
+        // The totals of the original run are carried over, known up front
+        // this time, under the ID of the retry run
+        let new_error_content = fs::read_to_string(&new_error_file).unwrap();
+        let new_lines: Vec<&str> = new_error_content.lines().collect();
+        assert_eq!(new_lines.len(), 5);
+        assert!(new_lines[1].starts_with("# plm-run "));
+        assert_eq!(
+            [new_lines[0], new_lines[2], new_lines[3], new_lines[4]],
+            [lines[0], lines[3], lines[2], lines[4]]
+        );
+    }
+
+    #[test]
+    fn test_error_files_lyrics_entry_and_retry() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.log");
+
+        // Block one lyrics file on the destination with a directory
+        fs::create_dir_all(dest_dir.join("artist1/album1/title1.lrc")).unwrap();
+
+        let playlist_path = music_dir.join("playlist.m3u8");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--keep-going")
+            .arg("--lyrics")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stdout(predicate::str::contains("(1/2) lyrics files copied"));
+
+        let error_content = fs::read_to_string(&error_file).unwrap();
+        let lines: Vec<&str> = error_content.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "# plm-error-file 2");
+        assert!(lines[1].starts_with("# plm-run "));
+        assert!(lines[3].starts_with("S "));
+        assert!(lines[3].contains("/MUSIC/artist1/album1/title1.flac\t"));
+        assert!(lines[3].ends_with("/MUSIC/artist1/album1/title1.lrc"));
+
+        // Unblock the lyrics file and retry from the error file
+        fs::remove_dir(dest_dir.join("artist1/album1/title1.lrc")).unwrap();
+
+        let mut retry_cmd = plm_command("plm-put-playlist");
+        retry_cmd
+            .arg("--retry")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(0/0) media files copied"))
+            .stdout(predicate::str::contains("(1/1) lyrics files copied"));
+
+        let content = fs::read_to_string(dest_dir.join("artist1/album1/title1.lrc")).unwrap();
+        assert_eq!(content, "[00:00.00] Lyrics for title1");
+    }
+
+    #[test]
+    fn test_error_files_sidecar_follows_renamed_media_file() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.log");
+
+        // Block the renamed lyrics file on the destination with a directory
+        fs::create_dir_all(dest_dir.join("ARTIST1/ALBUM1/TITLE1.lrc")).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--keep-going")
+            .arg("--lyrics")
+            .arg("--case")
+            .arg("upper")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1/2) lyrics files copied"));
+
+        // Change the copied media file to check it is not copied again
+        fs::remove_dir(dest_dir.join("ARTIST1/ALBUM1/TITLE1.lrc")).unwrap();
+        fs::write(dest_dir.join("ARTIST1/ALBUM1/TITLE1.FLAC"), "kept").unwrap();
+
+        let mut retry_cmd = plm_command("plm-put-playlist");
+        retry_cmd
+            .arg("--retry")
+            .arg(error_file.to_str().unwrap())
+            .arg("--case")
+            .arg("upper")
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1/1) lyrics files copied"));
+
+        assert!(dest_dir.join("ARTIST1/ALBUM1/TITLE1.lrc").is_file());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("ARTIST1/ALBUM1/TITLE1.FLAC")).unwrap(),
+            "kept"
+        );
+    }
+}
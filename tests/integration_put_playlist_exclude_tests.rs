@@ -0,0 +1,122 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_exclude_from() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let exclude_file = temp_dir.path().join("exclude.txt");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_test_file(
+            &exclude_file,
+            &format!(
+                "# Never copy these\n\nartist2/album2/*\n{}\n",
+                music_dir.join("artist1/album1/title2.flac").display()
+            ),
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--exclude-from")
+            .arg(exclude_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(1/1) playlist copied"))
+            .stdout(predicate::str::contains("(2/2) media files copied"));
+
+        assert!(dest_dir.join("artist1/album1/title1.flac").exists());
+        assert!(dest_dir.join("artist2/album1/title1.flac").exists());
+        assert!(!dest_dir.join("artist1/album1/title2.flac").exists());
+        assert!(!dest_dir.join("artist2/album2").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("playlist.m3u8")).unwrap(),
+            "artist1/album1/title1.flac\nartist2/album1/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_exclude_drops_extinf() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = music_dir.join("extended.m3u8");
+        create_test_file(
+            &playlist_path,
+            "#EXTM3U\n#EXTINF:1,Title 1\nartist1\\album1\\title1.flac\n#EXTINF:2,Title 2\nartist1\\album1\\title2.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--exclude")
+            .arg("*/title2.*")
+            .arg("-x")
+            .arg("artist9/missing.flac")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1/1) media files copied"));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("extended.m3u8")).unwrap(),
+            "#EXTM3U\n#EXTINF:1,Title 1\nartist1/album1/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_exclude_from_invalid_pattern() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let exclude_file = temp_dir.path().join("exclude.txt");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_test_file(&exclude_file, "*.wav\nartist[\n");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--exclude-from")
+            .arg(exclude_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .code(255)
+            .stderr(predicate::str::contains("exclude.txt:2: Invalid pattern"));
+
+        assert!(!dest_dir.join("playlist.m3u8").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_exclude_from_missing_file() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--exclude-from")
+            .arg(temp_dir.path().join("missing.txt").to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .code(255)
+            .stderr(predicate::str::contains("Failed to read exclusion file"));
+    }
+}
@@ -0,0 +1,97 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_expand_dirs() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_test_file(&music_dir.join("artist1/album1/cover.jpg"), "image");
+        fs::create_dir_all(music_dir.join("artist1/album1/bonus")).unwrap();
+        create_test_file(&music_dir.join("artist1/album1/bonus/title3.flac"), "bonus");
+
+        let playlist_path = music_dir.join("folders.m3u8");
+        create_test_file(
+            &playlist_path,
+            "#EXTM3U\n#EXTINF:-1,Album 1\nartist1\\album1\\\n#EXTINF:1,Title 1\nartist2/album2/title1.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--expand-dirs")
+            .arg("--lyrics")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"));
+
+        assert!(dest_dir.join("artist1/album1/bonus/title3.flac").exists());
+        assert!(dest_dir.join("artist1/album1/title1.lrc").exists());
+        assert!(!dest_dir.join("artist1/album1/cover.jpg").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("folders.m3u8")).unwrap(),
+            "#EXTM3U\nartist1/album1/bonus/title3.flac\nartist1/album1/title1.flac\nartist1/album1/title2.flac\n#EXTINF:1,Title 1\nartist2/album2/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_expand_dirs_with_exclusions() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = music_dir.join("folders.m3u8");
+        create_test_file(&playlist_path, "artist2\nartist1/album1/title1.flac\n");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--expand-dirs")
+            .arg("--drop-non-audio")
+            .arg("--exclude")
+            .arg("*/album2/*")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(2/2) media files copied"))
+            .stdout(predicate::str::contains("non-audio").not());
+
+        assert!(!dest_dir.join("artist2/album2").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("folders.m3u8")).unwrap(),
+            "artist2/album1/title1.flac\nartist1/album1/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_without_expand_dirs() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = music_dir.join("folders.m3u8");
+        create_test_file(&playlist_path, "artist2\n");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .failure();
+
+        assert!(!dest_dir.join("artist2/album1/title1.flac").exists());
+    }
+}
@@ -0,0 +1,204 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_dry_run() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        // One file as copied earlier, one changed since
+        fs::create_dir_all(dest_dir.join("artist1/album1")).unwrap();
+        create_test_file(
+            &dest_dir.join("artist1/album1/title1.flac"),
+            "test content 1",
+        );
+        create_test_file(&dest_dir.join("artist1/album1/title2.flac"), "old");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--dry-run")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains(
+                ".f          artist1/album1/title1.flac\n",
+            ))
+            .stdout(predicate::str::contains(
+                ">f.s....... artist1/album1/title2.flac\n",
+            ))
+            .stdout(predicate::str::contains(
+                ">f+++++++++ artist2/album1/title1.flac\n",
+            ))
+            .stdout(predicate::str::contains(
+                "Dry run: 2 new, 1 updated, 1 up-to-date media files",
+            ))
+            .stdout(predicate::str::contains("(1/1) playlist copied"))
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stdout(predicate::str::contains("Run ID: "));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist1/album1/title2.flac")).unwrap(),
+            "old"
+        );
+        assert!(!dest_dir.join("artist2").exists());
+        assert!(!dest_dir.join("playlist.m3u8").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_dry_run_skipped_and_missing() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        // The same rip in a second album folder, and a track not ripped
+        create_test_file(
+            &music_dir.join("artist2/album2/title1.flac"),
+            "test content 1",
+        );
+        let playlist_path = music_dir.join("dry.m3u8");
+        create_test_file(
+            &playlist_path,
+            "artist1/album1/title1.flac\nartist2/album2/title1.flac\nartist3/title1.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--dry-run")
+            .arg("--dedup")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                ">f+++++++++ artist1/album1/title1.flac\n",
+            ))
+            .stdout(predicate::str::contains("artist2/album2/title1.flac").not())
+            .stdout(predicate::str::contains("artist3/title1.flac").not())
+            .stdout(predicate::str::contains(
+                "Dry run: 1 new, 0 updated, 0 up-to-date media files; 1 skipped, 1 missing",
+            ));
+
+        assert!(!dest_dir.join("artist1").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_dry_run_error_files() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.txt");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = music_dir.join("dry.m3u8");
+        create_test_file(
+            &playlist_path,
+            "artist1/album1/title1.flac\nartist3/title1.flac\nartist3/title2.flac\n",
+        );
+
+        // The missing media files and playlist are reported and recorded
+        // as a copy with --keep-going would
+        let mut cmd = plm_command("plm-put-playlist");
+        let output = cmd
+            .arg("--dry-run")
+            .arg("--lyrics")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .arg(music_dir.join("nonexistent.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1/2) playlist copied"))
+            .stdout(predicate::str::contains("(1/3) media files copied"))
+            .stdout(predicate::str::contains("(1/1) lyrics files copied"))
+            .stderr(predicate::str::contains(
+                "2 files missing (first: title1.flac)",
+            ))
+            .stderr(predicate::str::contains("nonexistent.m3u8"))
+            .get_output()
+            .stdout
+            .clone();
+
+        let stdout = String::from_utf8(output).unwrap();
+        let run_id = stdout
+            .lines()
+            .find_map(|line| line.strip_prefix("Run ID: "))
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(&error_file).unwrap(),
+            format!(
+                "# plm-error-file 2\n# plm-run {}\n# plm-totals playlists=2 media=3\n\
+                 M {}\nM {}\nP {}\n",
+                run_id,
+                music_dir.join("artist3/title1.flac").display(),
+                music_dir.join("artist3/title2.flac").display(),
+                music_dir.join("nonexistent.m3u8").display()
+            )
+        );
+        assert!(fs::read_dir(&dest_dir).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_put_playlist_itemize() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("-i")
+            .arg("--lyrics")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains(
+                ">f+++++++++ artist1/album1/title1.flac\n>f+++++++++ artist1/album1/title1.lrc\n",
+            ))
+            .stdout(predicate::str::contains(
+                ">f+++++++++ artist2/album2/title1.flac\n",
+            ))
+            .stdout(predicate::str::contains("(4/4) media files copied"));
+
+        assert!(dest_dir.join("artist2/album2/title1.lrc").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_dry_run_with_retry() {
+        let temp_dir = setup_test_directory();
+        let dest_dir = temp_dir.path().join("DEST");
+        let retry_file = temp_dir.path().join("retry.txt");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_test_file(&retry_file, "# plm-error-file 1\n");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--dry-run")
+            .arg("--retry")
+            .arg(retry_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .failure()
+            .code(255)
+            .stderr(predicate::str::contains(
+                "--dry-run cannot be used with --retry",
+            ));
+    }
+}
@@ -0,0 +1,176 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Paths of the files under `dir`, relative to it, in sorted order
+    fn files_under(dir: &Path) -> Vec<String> {
+        let mut files = Vec::new();
+        let mut dirs = vec![dir.to_path_buf()];
+        while let Some(current) = dirs.pop() {
+            for entry in fs::read_dir(&current).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else {
+                    let rel_path = path.strip_prefix(dir).unwrap();
+                    files.push(rel_path.to_string_lossy().replace('\\', "/"));
+                }
+            }
+        }
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn test_put_playlist_jobs() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        // The changes are itemized in playlist order whichever thread
+        // copied each media file
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--jobs")
+            .arg("3")
+            .arg("--verify")
+            .arg("--lyrics")
+            .arg("--itemize")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(
+                ">f+++++++++ artist1/album1/title1.flac\n\
+                 >f+++++++++ artist1/album1/title1.lrc\n\
+                 >f+++++++++ artist1/album1/title2.flac\n\
+                 >f+++++++++ artist2/album1/title1.flac\n\
+                 >f+++++++++ artist2/album2/title1.flac\n\
+                 >f+++++++++ artist2/album2/title1.lrc\n",
+            ))
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stdout(predicate::str::contains("(2/2) lyrics files copied"));
+
+        for (file, content) in [
+            ("artist1/album1/title1.flac", "test content 1"),
+            ("artist1/album1/title2.flac", "test content 2"),
+            ("artist2/album1/title1.flac", "test content 3"),
+            ("artist2/album2/title1.flac", "test content 4"),
+        ] {
+            assert_eq!(fs::read_to_string(dest_dir.join(file)).unwrap(), content);
+        }
+    }
+
+    #[test]
+    fn test_put_playlist_jobs_keep_going_with_error_files() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.txt");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let playlist_path = music_dir.join("missing.m3u8");
+        create_test_file(
+            &playlist_path,
+            "artist1/album1/title1.flac\nartist1/album1/missing.flac\n\
+             artist1/album1/title2.flac\nartist2/album1/title1.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("-j")
+            .arg("2")
+            .arg("--keep-going")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(3/4) media files copied"))
+            .stderr(predicate::str::contains("1 file missing (missing.flac)"));
+
+        let error_file = fs::read_to_string(&error_file).unwrap();
+        let failures: Vec<&str> = error_file
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .collect();
+        assert_eq!(
+            failures,
+            [format!(
+                "M {}",
+                music_dir.join("artist1/album1/missing.flac").display()
+            )]
+        );
+        assert!(dest_dir.join("artist2/album1/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_jobs_stops_at_first_error() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let album_dir = music_dir.join("artist1/album1");
+        create_test_file(&album_dir.join("title4.flac"), "test content 4");
+        create_test_file(&album_dir.join("title5.flac"), "test content 5");
+        let playlist_path = music_dir.join("missing.m3u8");
+        create_test_file(
+            &playlist_path,
+            "artist1/album1/title1.flac\nartist1/album1/title2.flac\n\
+             artist1/album1/missing.flac\nartist1/album1/title4.flac\n\
+             artist1/album1/title5.flac\n",
+        );
+
+        // Copying with several jobs or through io_uring stops at the
+        // same media file as with one job, and copies nothing after it
+        let mut runs = vec![["-j", "1"], ["-j", "4"]];
+        if cfg!(all(target_os = "linux", feature = "io-uring")) {
+            runs.push(["--io-backend", "uring"]);
+        }
+        let mut dest_files = Vec::new();
+        for (i, [option, value]) in runs.into_iter().enumerate() {
+            let dest_dir = temp_dir.path().join(format!("DEST{}", i));
+            fs::create_dir_all(&dest_dir).unwrap();
+            let mut cmd = plm_command("plm-put-playlist");
+            cmd.arg(option)
+                .arg(value)
+                .arg(dest_dir.to_str().unwrap())
+                .arg(playlist_path.to_str().unwrap())
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("missing.flac"));
+            let album_dir = dest_dir.join("artist1/album1");
+            assert!(album_dir.join("title2.flac").exists());
+            assert!(!album_dir.join("title4.flac").exists());
+            assert!(!album_dir.join("title5.flac").exists());
+            dest_files.push(files_under(&dest_dir));
+        }
+        assert!(dest_files.iter().all(|files| *files == dest_files[0]));
+    }
+
+    #[test]
+    fn test_put_playlist_jobs_out_of_range() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        for jobs in ["0", "65"] {
+            let mut cmd = plm_command("plm-put-playlist");
+            cmd.arg("--jobs")
+                .arg(jobs)
+                .arg(dest_dir.to_str().unwrap())
+                .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+                .assert()
+                .failure()
+                .stderr(predicate::str::contains("--jobs"));
+        }
+        assert!(fs::read_dir(&dest_dir).unwrap().next().is_none());
+    }
+}
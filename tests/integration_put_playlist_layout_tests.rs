@@ -0,0 +1,116 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Write a FLAC file holding only the given Vorbis comments
+    fn create_flac_file(path: &Path, comments: &[&str]) {
+        let mut block = Vec::new();
+        block.extend_from_slice(&0u32.to_le_bytes());
+        block.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+        for comment in comments {
+            block.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            block.extend_from_slice(comment.as_bytes());
+        }
+
+        let mut content = b"fLaC\x84".to_vec();
+        content.extend_from_slice(&(block.len() as u32).to_be_bytes()[1..]);
+        content.extend_from_slice(&block);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    /// Create a playlist referring to tagged tracks in a messy tree
+    fn create_tagged_playlist(music_dir: &Path) -> PathBuf {
+        create_flac_file(
+            &music_dir.join("incoming/rip 1/track_a.flac"),
+            &[
+                "ARTIST=The Band",
+                "ALBUM=First Album",
+                "TITLE=Intro",
+                "TRACKNUMBER=1",
+            ],
+        );
+        create_test_file(&music_dir.join("incoming/rip 1/track_a.lrc"), "lyrics");
+        create_flac_file(
+            &music_dir.join("misc/b.flac"),
+            &[
+                "ARTIST=Guest",
+                "ALBUMARTIST=The Band",
+                "ALBUM=First Album",
+                "TITLE=What?",
+                "TRACKNUMBER=2/9",
+            ],
+        );
+
+        let playlist_path = music_dir.join("tagged.m3u8");
+        create_test_file(
+            &playlist_path,
+            "#EXTM3U\nincoming\\rip 1\\track_a.flac\nmisc/b.flac\nartist1/album1/title1.flac\n",
+        );
+        playlist_path
+    }
+
+    #[test]
+    fn test_put_playlist_layout_tags() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_tagged_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--layout")
+            .arg("tags")
+            .arg("--lyrics")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(3/3) media files copied"));
+
+        assert!(dest_dir.join("The Band/First Album/01 Intro.flac").exists());
+        assert!(dest_dir.join("The Band/First Album/01 Intro.lrc").exists());
+        assert!(dest_dir.join("The Band/First Album/02 What_.flac").exists());
+        assert!(dest_dir.join("artist1/album1/title1.flac").exists());
+        assert!(!dest_dir.join("incoming").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("tagged.m3u8")).unwrap(),
+            "#EXTM3U\nThe Band/First Album/01 Intro.flac\nThe Band/First Album/02 What_.flac\nartist1/album1/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_layout_tags_with_case() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_tagged_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--layout")
+            .arg("tags")
+            .arg("--case")
+            .arg("lower")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert!(dest_dir.join("the band/first album/01 intro.flac").exists());
+        assert!(dest_dir.join("the band/first album/02 what_.flac").exists());
+    }
+}
@@ -0,0 +1,106 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_from_list() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let list_path = music_dir.join("tracks.txt");
+        create_test_file(
+            &list_path,
+            &format!(
+                "# Tracks to copy\nartist1/album1/title1.flac\n\n{}\n",
+                music_dir.join("artist2/album2/title1.flac").display()
+            ),
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--lyrics")
+            .arg("--from-list")
+            .arg(list_path.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(0/0) playlist copied"))
+            .stdout(predicate::str::contains("(2/2) media files copied"))
+            .stdout(predicate::str::contains("(2/2) lyrics files copied"));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist1/album1/title1.flac")).unwrap(),
+            "test content 1"
+        );
+        assert!(dest_dir.join("artist2/album2/title1.lrc").exists());
+        assert!(!dest_dir.join("tracks.txt").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_from_list_with_root_and_playlist() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let list_path = temp_dir.path().join("tracks.txt");
+        create_test_file(
+            &list_path,
+            "artist1/album1/title1.flac\nartist2\\album1\\title1.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--from-list")
+            .arg(list_path.to_str().unwrap())
+            .arg("--list-root")
+            .arg(music_dir.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1/1) playlist copied"))
+            .stdout(predicate::str::contains("(4/4) media files copied"));
+
+        assert!(dest_dir.join("playlist.m3u8").exists());
+        assert!(dest_dir.join("artist2/album1/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_from_list_outside_root() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let list_path = music_dir.join("tracks.txt");
+        create_test_file(&list_path, "/elsewhere/title1.flac\n");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--from-list")
+            .arg(list_path.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("Failed to read file list"));
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--from-list")
+            .arg(list_path.to_str().unwrap())
+            .arg("--retry")
+            .arg(temp_dir.path().join("errors.txt").to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains(
+                "--from-list cannot be used with --retry",
+            ));
+    }
+}
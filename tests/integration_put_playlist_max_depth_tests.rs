@@ -0,0 +1,117 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_max_depth() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        for disc in ["disc1", "disc2"] {
+            let disc_dir = music_dir.join("artist3/album1").join(disc);
+            fs::create_dir_all(&disc_dir).unwrap();
+            create_test_file(&disc_dir.join("01 track.flac"), disc);
+            create_test_file(&disc_dir.join("01 track.lrc"), disc);
+        }
+
+        let playlist_path = music_dir.join("discs.m3u8");
+        create_test_file(
+            &playlist_path,
+            "#EXTM3U\nartist3\\album1\\disc1\\01 track.flac\nartist3/album1/disc2/01 track.flac\nartist1/album1/title1.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--max-depth")
+            .arg("2")
+            .arg("--lyrics")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(3/3) media files copied"));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist3/album1 - disc2/01 track.flac")).unwrap(),
+            "disc2"
+        );
+        assert!(dest_dir
+            .join("artist3/album1 - disc1/01 track.lrc")
+            .exists());
+        assert!(!dest_dir.join("artist3/album1/disc1").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("discs.m3u8")).unwrap(),
+            "#EXTM3U\nartist3/album1 - disc1/01 track.flac\nartist3/album1 - disc2/01 track.flac\nartist1/album1/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_max_depth_collision() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::create_dir_all(music_dir.join("artist1/album1/disc1")).unwrap();
+        fs::create_dir_all(music_dir.join("artist1 - album1/disc1")).unwrap();
+        create_test_file(&music_dir.join("artist1/album1/disc1/track.flac"), "nested");
+        create_test_file(&music_dir.join("artist1 - album1/disc1/track.flac"), "flat");
+
+        let playlist_path = music_dir.join("collision.m3u8");
+        create_test_file(
+            &playlist_path,
+            "artist1/album1/disc1/track.flac\nartist1 - album1/disc1/track.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--max-depth")
+            .arg("1")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist1 - album1 - disc1/track.flac")).unwrap(),
+            "nested"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist1 - album1 - disc1/track (2).flac")).unwrap(),
+            "flat"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("collision.m3u8")).unwrap(),
+            "artist1 - album1 - disc1/track.flac\nartist1 - album1 - disc1/track (2).flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_max_depth_zero() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--max-depth")
+            .arg("0")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure();
+
+        assert!(!dest_dir.join("playlist.m3u8").exists());
+    }
+}
@@ -0,0 +1,80 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Create a playlist of one present and three missing media files,
+    /// returning the path of the playlist
+    fn create_missing_playlist(music_dir: &Path) -> PathBuf {
+        let playlist_path = music_dir.join("missing.m3u8");
+        create_test_file(
+            &playlist_path,
+            "artist3/gone1.flac\nartist1/album1/title1.flac\nartist3/gone2.flac\nartist3/gone3.flac\n",
+        );
+        playlist_path
+    }
+
+    #[test]
+    fn test_put_playlist_groups_missing_files_by_directory() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_missing_playlist(&music_dir);
+        let error_file = temp_dir.path().join("errors.txt");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--keep-going")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1/4) media files copied"))
+            .stderr(predicate::str::contains(
+                "/MUSIC/artist3/: 3 files missing (first: gone1.flac)",
+            ))
+            .stderr(predicate::str::contains("Media file not found").not());
+
+        // The error file still lists every missing media file
+        let errors = fs::read_to_string(&error_file).unwrap();
+        for name in ["gone1.flac", "gone2.flac", "gone3.flac"] {
+            assert!(errors.contains(&format!("/MUSIC/artist3/{}", name)));
+        }
+    }
+
+    #[test]
+    fn test_put_playlist_list_missing_prints_each_file() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_missing_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--keep-going")
+            .arg("--list-missing")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("files missing").not());
+
+        let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+        for name in ["gone1.flac", "gone2.flac", "gone3.flac"] {
+            assert!(stderr.contains(&format!(
+                "Error: Media file not found: {}",
+                music_dir.join("artist3").join(name).display()
+            )));
+        }
+    }
+}
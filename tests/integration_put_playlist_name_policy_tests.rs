@@ -0,0 +1,90 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_name_policy() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        let policy = temp_dir.path().join("policy.txt");
+        create_test_file(
+            &policy,
+            "# Upper-case names without \"artist\"\nreplace artist \"Band \"\ncase upper\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--name-policy")
+            .arg(policy.to_str().unwrap())
+            .arg("--lyrics")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stdout(predicate::str::contains("(2/2) lyrics files copied"));
+
+        // Extensions are kept, so the lyrics files follow the media files
+        assert!(dest_dir.join("BAND 1/ALBUM1/TITLE1.flac").is_file());
+        assert!(dest_dir.join("BAND 1/ALBUM1/TITLE1.lrc").is_file());
+        assert!(dest_dir.join("BAND 2/ALBUM2/TITLE1.lrc").is_file());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("PLAYLIST.m3u8")).unwrap(),
+            "BAND 1/ALBUM1/TITLE1.flac\nBAND 1/ALBUM1/TITLE2.flac\nBAND 2/ALBUM1/TITLE1.flac\nBAND 2/ALBUM2/TITLE1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_name_policy_truncate_collision() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        let policy = temp_dir.path().join("policy.txt");
+        create_test_file(&policy, "truncate 6");
+
+        // "artist1" and "artist2" are both cut to "artist"
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--name-policy")
+            .arg(policy.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("playli.m3u8")).unwrap(),
+            "artist/album1/title1.flac\nartist/album1/title2.flac\nartist/album1/title1 (2).flac\nartist/album2/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_name_policy_invalid() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        let policy = temp_dir.path().join("policy.txt");
+        create_test_file(&policy, "case lower\nrot13\n");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--name-policy")
+            .arg(policy.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains("line 2: Unknown rule: rot13"));
+
+        assert!(!dest_dir.join("playlist.m3u8").exists());
+    }
+}
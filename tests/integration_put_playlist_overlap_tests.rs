@@ -0,0 +1,61 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_dest_inside_source() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = music_dir.join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains("overlaps the directory"))
+            .stderr(predicate::str::contains("--allow-overlap"));
+        assert!(fs::read_dir(&dest_dir).unwrap().next().is_none());
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--allow-overlap")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"));
+        assert!(dest_dir.join("artist1/album1/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_source_inside_dest() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let list_path = temp_dir.path().join("LISTS/tracks.txt");
+        fs::create_dir_all(temp_dir.path().join("LISTS")).unwrap();
+        create_test_file(&list_path, "artist1/album1/title1.flac\n");
+
+        // The list root, rather than the directory of the list, is the source
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--from-list")
+            .arg(list_path.to_str().unwrap())
+            .arg("--list-root")
+            .arg(music_dir.to_str().unwrap())
+            .arg(temp_dir.path().to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains(format!(
+                "overlaps the directory {} of {}",
+                fs::canonicalize(&music_dir).unwrap().display(),
+                list_path.display()
+            )));
+    }
+}
@@ -0,0 +1,93 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_total_grows_as_playlists_are_parsed() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let playlist1_path = music_dir.join("playlist1.m3u8");
+        create_test_file(
+            &playlist1_path,
+            "artist1/album1/title1.flac\nartist1/album1/title2.flac",
+        );
+        let playlist2_path = music_dir.join("playlist2.m3u8");
+        create_test_file(
+            &playlist2_path,
+            "artist1/album1/title2.flac\nartist2/album1/title1.flac\nartist2/album2/title1.flac",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("-v")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist1_path.to_str().unwrap())
+            .arg(playlist2_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("(2-M/2) Copy track"))
+            .stderr(predicate::str::contains("(4-M/4) Copy track"))
+            .stdout(predicate::str::contains("(2/2) playlist copied"))
+            .stdout(predicate::str::contains("(4/4) media files copied"));
+
+        assert!(dest_dir.join("artist2/album2/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_copies_before_failing_playlist() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let missing_playlist_path = music_dir.join("missing.m3u8");
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg(missing_playlist_path.to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "Error extracting media files from playlist",
+            ));
+
+        // The playlist parsed before the failing one is copied already
+        assert!(dest_dir.join("playlist.m3u8").exists());
+        assert!(dest_dir.join("artist2/album2/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_dedup_plans_before_copying() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let playlist2_path = music_dir.join("playlist2.m3u8");
+        create_test_file(&playlist2_path, "artist2/album1/title1.flac");
+
+        // With deduplication, every playlist is parsed before copying, so
+        // the total is known from the first track
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("-v")
+            .arg("--dedup")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg(playlist2_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("(1-M/4) Copy track"));
+    }
+}
@@ -0,0 +1,55 @@
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{plm_command, setup_test_directory};
+
+    #[test]
+    fn test_progress_prints_lines_when_not_a_terminal() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        std::fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let output = cmd
+            .arg("--progress")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .get_output()
+            .stderr
+            .clone();
+
+        // Standard error is a pipe, so a plain line is written for each
+        // media file without redrawing a bar
+        let stderr = String::from_utf8(output).unwrap();
+        assert!(!stderr.contains('\r'));
+        let lines: Vec<&str> = stderr.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("14 bytes/56 bytes  1/4 files  ETA "));
+        assert!(lines[0].ends_with("  artist1/album1/title1.flac"));
+        assert!(lines[3].starts_with("56 bytes/56 bytes  4/4 files  ETA "));
+        assert!(lines[3].ends_with("  artist2/album2/title1.flac"));
+    }
+
+    #[test]
+    fn test_progress_conflicts_with_verbose() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--progress")
+            .arg("--verbose")
+            .arg(temp_dir.path().to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("cannot be used with"));
+    }
+}
@@ -0,0 +1,120 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_report_csv() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let report_path = temp_dir.path().join("report.csv");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist2_path = music_dir.join("playlist2.m3u8");
+        create_test_file(&playlist2_path, "artist1/album1/title1.flac\n");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--report")
+            .arg(report_path.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg(playlist2_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        let report = fs::read_to_string(&report_path).unwrap();
+        let rows: Vec<Vec<&str>> = report
+            .lines()
+            .map(|line| line.split(',').collect())
+            .collect();
+        assert_eq!(
+            rows[0],
+            vec![
+                "playlist",
+                "source",
+                "destination",
+                "action",
+                "bytes",
+                "duration",
+                "result",
+                "reason"
+            ]
+        );
+        assert_eq!(rows.len(), 6);
+
+        let first = &rows[1];
+        assert!(first[0].ends_with("playlist.m3u8"));
+        assert!(first[1].ends_with("MUSIC/artist1/album1/title1.flac"));
+        assert!(first[2].ends_with("DEST/artist1/album1/title1.flac"));
+        assert_eq!(&first[3..5], ["copy", "14"]);
+        assert_eq!(&first[6..], ["ok", ""]);
+
+        let last = &rows[5];
+        assert!(last[0].ends_with("playlist2.m3u8"));
+        assert_eq!(&last[3..], ["skip", "14", "", "ok", "already copied"]);
+    }
+
+    #[test]
+    fn test_put_playlist_report_tsv_with_failure() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let report_path = temp_dir.path().join("report.tsv");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = music_dir.join("missing.m3u8");
+        create_test_file(
+            &playlist_path,
+            "artist1/album1/title1.flac\nartist9/album1/missing.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--keep-going")
+            .arg("--report")
+            .arg(report_path.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        let report = fs::read_to_string(&report_path).unwrap();
+        let rows: Vec<Vec<&str>> = report
+            .lines()
+            .map(|line| line.split('\t').collect())
+            .collect();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0][0], "playlist");
+        assert_eq!(rows[1][6], "ok");
+        assert_eq!(&rows[2][3..5], ["copy", ""]);
+        assert_eq!(rows[2][6], "failed");
+        assert!(!rows[2][7].is_empty());
+    }
+
+    #[test]
+    fn test_put_playlist_report_cannot_be_created() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--report")
+            .arg(temp_dir.path().join("missing/report.csv").to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .code(255)
+            .stderr(predicate::str::contains("Failed to create report file"));
+
+        assert!(!dest_dir.join("playlist.m3u8").exists());
+    }
+}
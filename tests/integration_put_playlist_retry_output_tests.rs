@@ -0,0 +1,119 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+use regex::Regex;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Write an error file listing the test playlist and one media file
+    fn create_error_file(music_dir: &Path, error_file: &Path) {
+        let content = format!(
+            "# plm-error-file 1\nP {}\nM {}\nL {}\n",
+            music_dir.join("playlist.m3u8").display(),
+            music_dir.join("artist2/album1/title1.flac").display(),
+            music_dir.join("artist2/album2/title1.lrc").display()
+        );
+        create_test_file(error_file, &content);
+    }
+
+    /// Assert that standard output consists of summary lines and the run
+    /// ID only
+    fn assert_summary_only(stdout: &[u8]) {
+        let stdout = String::from_utf8_lossy(stdout);
+        let summary =
+            Regex::new(r"^(\(\d+/\d+\) (playlist|media files|lyrics files) copied|Run ID: \S+)$")
+                .unwrap();
+
+        assert!(!stdout.is_empty());
+        for line in stdout.lines() {
+            assert!(summary.is_match(line), "unexpected output: {:?}", line);
+        }
+    }
+
+    #[test]
+    fn test_retry_prints_only_summary() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.log");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_error_file(&music_dir, &error_file);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let output = cmd
+            .arg("--retry")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::is_empty())
+            .get_output()
+            .clone();
+
+        assert_summary_only(&output.stdout);
+        assert!(dest_dir.join("artist2/album1/title1.flac").exists());
+        assert!(dest_dir.join("artist2/album2/title1.lrc").exists());
+    }
+
+    #[test]
+    fn test_retry_verbose_keeps_stdout_clean() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.log");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_error_file(&music_dir, &error_file);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let output = cmd
+            .arg("--verbose")
+            .arg("--retry")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("Retrying playlist"))
+            .stderr(predicate::str::contains("Found playlist").not())
+            .get_output()
+            .clone();
+
+        assert_summary_only(&output.stdout);
+    }
+
+    #[test]
+    fn test_retry_debug_messages_go_to_stderr() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.log");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_error_file(&music_dir, &error_file);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let output = cmd
+            .arg("-vv")
+            .arg("--retry")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("Parsing error file"))
+            .stderr(predicate::str::contains("Found media file"))
+            .stderr(predicate::str::contains(
+                "Parsed 1 playlists, 1 media files, 1 lyrics files and 0 sidecar files",
+            ))
+            .get_output()
+            .clone();
+
+        assert_summary_only(&output.stdout);
+    }
+}
@@ -0,0 +1,114 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_rewrite_ext() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_test_file(&music_dir.join("artist1/album1/title3.mp3"), "converted");
+        create_test_file(&music_dir.join("artist1/album1/title4.WAV"), "wave");
+        let playlist_path = music_dir.join("mixed.m3u8");
+        create_test_file(
+            &playlist_path,
+            "#EXTM3U\n#EXTINF:1,Title 1\nartist1\\album1\\title1.flac\nartist1/album1/title3.mp3\nartist1/album1/title4.WAV\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--rewrite-ext")
+            .arg("flac=mp3,wav=mp3")
+            .arg("--lyrics")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(3/3) media files copied"))
+            .stdout(predicate::str::contains("(1/1) lyrics files copied"));
+
+        // The contents are copied unchanged
+        let album_dir = dest_dir.join("artist1/album1");
+        assert_eq!(
+            fs::read_to_string(album_dir.join("title1.mp3")).unwrap(),
+            "test content 1"
+        );
+        assert!(album_dir.join("title1.lrc").exists());
+        assert!(!album_dir.join("title1.flac").exists());
+        assert_eq!(
+            fs::read_to_string(album_dir.join("title3.mp3")).unwrap(),
+            "converted"
+        );
+        assert_eq!(
+            fs::read_to_string(album_dir.join("title4.mp3")).unwrap(),
+            "wave"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("mixed.m3u8")).unwrap(),
+            "#EXTM3U\n#EXTINF:1,Title 1\nartist1/album1/title1.mp3\nartist1/album1/title3.mp3\nartist1/album1/title4.mp3"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_rewrite_ext_conflict() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_test_file(&music_dir.join("artist1/album1/title1.mp3"), "converted");
+        let playlist_path = music_dir.join("both.m3u8");
+        create_test_file(
+            &playlist_path,
+            "artist1/album1/title1.flac\nartist1/album1/title1.mp3\n",
+        );
+
+        // The file keeping its name keeps the destination path
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--rewrite-ext")
+            .arg(".flac=.mp3")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        let album_dir = dest_dir.join("artist1/album1");
+        assert_eq!(
+            fs::read_to_string(album_dir.join("title1.mp3")).unwrap(),
+            "converted"
+        );
+        assert_eq!(
+            fs::read_to_string(album_dir.join("title1 (2).mp3")).unwrap(),
+            "test content 1"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("both.m3u8")).unwrap(),
+            "artist1/album1/title1 (2).mp3\nartist1/album1/title1.mp3"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_rewrite_ext_invalid() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--rewrite-ext")
+            .arg("flac")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("expected FROM=TO"));
+    }
+}
@@ -0,0 +1,139 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Create a cue sheet and a booklet next to media files of the test
+    /// directory
+    fn create_sidecar_files(music_dir: &Path) {
+        create_test_file(&music_dir.join("artist1/album1/title1.cue"), "FILE");
+        create_test_file(&music_dir.join("artist1/album1/title1.pdf"), "PDF");
+        create_test_file(&music_dir.join("artist2/album1/title1.cue"), "FILE");
+    }
+
+    #[test]
+    fn test_put_playlist_copies_sidecar_files() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_sidecar_files(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("-v")
+            .arg("--lyrics")
+            .arg("--sidecar")
+            .arg("cue,.pdf")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(2/2) lyrics files copied"))
+            .stdout(predicate::str::contains("(3/3) sidecar files copied"))
+            .stderr(predicate::str::contains("(1-S/4) Copy sidecar"));
+
+        assert!(dest_dir.join("artist1/album1/title1.lrc").exists());
+        assert!(dest_dir.join("artist1/album1/title1.cue").exists());
+        assert!(dest_dir.join("artist1/album1/title1.pdf").exists());
+        assert!(dest_dir.join("artist2/album1/title1.cue").exists());
+        assert!(!dest_dir.join("artist2/album2/title1.cue").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_copies_lyrics_with_given_extensions() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_sidecar_files(&music_dir);
+        create_test_file(&music_dir.join("artist1/album1/title2.txt"), "lyrics");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--lyrics")
+            .arg("--lyrics-ext")
+            .arg(".lrc,txt")
+            .arg("--sidecar")
+            .arg("cue")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(3/3) lyrics files copied"))
+            .stdout(predicate::str::contains("(2/2) sidecar files copied"));
+
+        assert!(dest_dir.join("artist1/album1/title1.lrc").exists());
+        assert!(dest_dir.join("artist1/album1/title2.txt").exists());
+        assert!(dest_dir.join("artist2/album2/title1.lrc").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_dry_run_itemizes_sidecar_files() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_sidecar_files(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--dry-run")
+            .arg("--sidecar")
+            .arg("cue")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("artist1/album1/title1.cue"))
+            .stdout(predicate::str::contains("title1.lrc").not());
+
+        assert!(!dest_dir.join("artist1").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_retries_failed_sidecar_files() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        create_sidecar_files(&music_dir);
+        let error_file = temp_dir.path().join("errors.txt");
+
+        // A directory in the way of a sidecar file makes it fail to copy
+        let blocker = dest_dir.join("artist1/album1/title1.cue");
+        fs::create_dir_all(blocker.join("blocker")).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--keep-going")
+            .arg("--sidecar")
+            .arg("cue")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stdout(predicate::str::contains("(1/2) sidecar files copied"));
+
+        let errors = fs::read_to_string(&error_file).unwrap();
+        assert!(errors.contains("S "));
+        assert!(errors.contains("/MUSIC/artist1/album1/title1.cue"));
+
+        // Retry once the way is clear
+        fs::remove_dir_all(&blocker).unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--retry")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1/1) sidecar files copied"));
+
+        assert!(dest_dir.join("artist1/album1/title1.cue").is_file());
+    }
+}
@@ -0,0 +1,134 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_spillover() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest1 = temp_dir.path().join("DEST1");
+        let dest2 = temp_dir.path().join("DEST2");
+        let dest3 = temp_dir.path().join("DEST3");
+        for dest in [&dest1, &dest2, &dest3] {
+            fs::create_dir_all(dest).unwrap();
+        }
+        let first_path = music_dir.join("first.m3u8");
+        create_test_file(&first_path, "artist1/album1/title2.flac\n");
+
+        // Each media file takes 14 bytes, so two fit into each destination
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--spillover")
+            .arg(format!("{},{}", dest2.display(), dest3.display()))
+            .arg("--min-free")
+            .arg("0")
+            .arg("--max-fill")
+            .arg("30")
+            .arg(dest1.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg(first_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(format!(
+                "Destination 1/3: {} (2 media files, 28 bytes)",
+                fs::canonicalize(&dest1).unwrap().display()
+            )))
+            .stdout(predicate::str::contains("Destination 2/3:"))
+            .stdout(predicate::str::contains("Destination 3/3").not())
+            .stdout(predicate::str::contains("(3/3) playlist copied"))
+            .stdout(predicate::str::contains("(4/4) media files copied"));
+
+        assert_eq!(
+            fs::read_to_string(dest1.join("playlist.m3u8")).unwrap(),
+            "artist1/album1/title1.flac\nartist1/album1/title2.flac"
+        );
+        assert!(dest1.join("first.m3u8").exists());
+        assert!(!dest1.join("artist2").exists());
+        assert_eq!(
+            fs::read_to_string(dest2.join("playlist.m3u8")).unwrap(),
+            "artist2/album1/title1.flac\nartist2/album2/title1.flac"
+        );
+        assert!(!dest2.join("first.m3u8").exists());
+        assert!(!dest2.join("artist1").exists());
+        assert!(fs::read_dir(&dest3).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_put_playlist_spillover_not_fitting() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest1 = temp_dir.path().join("DEST1");
+        let dest2 = temp_dir.path().join("DEST2");
+        fs::create_dir_all(&dest1).unwrap();
+        fs::create_dir_all(&dest2).unwrap();
+
+        // Nothing is copied when a track fits into no destination
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--spillover")
+            .arg(dest2.to_str().unwrap())
+            .arg("--max-fill")
+            .arg("20")
+            .arg(dest1.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "2 media files fit into no destination (28 bytes)",
+            ));
+        assert!(fs::read_dir(&dest1).unwrap().next().is_none());
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--spillover")
+            .arg(dest2.to_str().unwrap())
+            .arg("--max-fill")
+            .arg("20")
+            .arg("--keep-going")
+            .arg(dest1.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(
+                "Warning: Skipping media file fitting into no destination",
+            ))
+            .stdout(predicate::str::contains("(2/2) media files copied"));
+        assert_eq!(
+            fs::read_to_string(dest2.join("playlist.m3u8")).unwrap(),
+            "artist1/album1/title2.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_spillover_invalid_options() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--spillover")
+            .arg(dest_dir.to_str().unwrap())
+            .arg("--dry-run")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "--spillover cannot be used with --dry-run",
+            ));
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--spillover")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Destination given more than once"));
+    }
+}
@@ -0,0 +1,115 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_strip_extinf() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let playlist_path = music_dir.join("extended.m3u8");
+        create_test_file(
+            &playlist_path,
+            "\u{feff}#EXTM3U\n#EXTINF:180,Artist 1 - Title 1\nartist1\\album1\\title1.flac\n\n#EXTINF:200,Artist 2 - Title 1\nartist2/album1/title1.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--strip-extinf")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(1/1) playlist copied"))
+            .stdout(predicate::str::contains("(2/2) media files copied"));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("extended.m3u8")).unwrap(),
+            "artist1/album1/title1.flac\nartist2/album1/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_strip_extinf_without_directives() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--strip-extinf")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success();
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("playlist.m3u8")).unwrap(),
+            fs::read_to_string(music_dir.join("playlist.m3u8")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_keeps_extinf_by_default() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let content = "#EXTM3U\n#EXTINF:180,Artist 1 - Title 1\nartist1/album1/title1.flac\n";
+        let playlist_path = music_dir.join("extended.m3u8");
+        create_test_file(&playlist_path, content);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("extended.m3u8")).unwrap(),
+            content
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_keeps_extinf_with_backslashes() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let playlist_path = music_dir.join("extended.m3u8");
+        create_test_file(
+            &playlist_path,
+            "\u{feff}#EXTM3U\r\n#EXTINF:180,Artist 1 - Title 1\r\n#EXTGRP:Rock\r\nartist1\\album1\\title1.flac\r\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        // The directives and the line endings survive the backslash conversion
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("extended.m3u8")).unwrap(),
+            "\u{feff}#EXTM3U\r\n#EXTINF:180,Artist 1 - Title 1\r\n#EXTGRP:Rock\r\nartist1/album1/title1.flac"
+        );
+    }
+}
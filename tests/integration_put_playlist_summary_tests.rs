@@ -0,0 +1,201 @@
+use std::fs;
+
+use predicates::prelude::*;
+use serde_json::Value;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_playlist_summary() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist1_path = music_dir.join("playlist.m3u8");
+        let playlist2_path = music_dir.join("playlist2.m3u8");
+        create_test_file(
+            &playlist2_path,
+            "artist1/album1/title1.flac\nartist9/album1/missing.flac\n",
+        );
+        let missing_path = music_dir.join("missing.m3u8");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--keep-going")
+            .arg("--playlist-summary")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist1_path.to_str().unwrap())
+            .arg(playlist2_path.to_str().unwrap())
+            .arg(missing_path.to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(2/3) playlist copied"))
+            .stdout(predicate::str::contains(format!(
+                "{}: 4 entries, 4 copied, 0 skipped, 0 failed\n",
+                playlist1_path.display()
+            )))
+            .stdout(predicate::str::contains(format!(
+                "{}: 2 entries, 0 copied, 1 skipped, 1 failed, 1 duplicate references skipped\n",
+                playlist2_path.display()
+            )))
+            .stdout(predicate::str::contains(format!(
+                "{}: 0 entries, 0 copied, 0 skipped, 0 failed (playlist not copied)\n",
+                missing_path.display()
+            )));
+    }
+
+    #[test]
+    fn test_put_playlist_without_playlist_summary() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("entries").not());
+    }
+    #[test]
+    fn test_put_playlist_duplicate_arguments() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = music_dir.join("playlist.m3u8");
+        let other_path = music_dir.join("artist1/../playlist.m3u8");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--playlist-summary")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .arg(other_path.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains(format!(
+                "Warning: Skipping playlist {} given more than once",
+                other_path.display()
+            )))
+            .stdout(predicate::str::contains("(1/1) playlist copied"))
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stdout(predicate::str::contains(": 4 entries").count(1));
+    }
+
+    #[test]
+    fn test_put_playlist_duplicate_references() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist1_path = music_dir.join("playlist.m3u8");
+        let playlist2_path = music_dir.join("playlist2.m3u8");
+        create_test_file(
+            &playlist2_path,
+            "artist2/album2/title1.flac\nartist2/album2/title1.flac\nartist1/album1/title1.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--playlist-summary")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist2_path.to_str().unwrap())
+            .arg(playlist1_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stdout(predicate::str::contains("(3) duplicate references skipped"))
+            .stdout(predicate::str::contains(format!(
+                "{}: 3 entries, 2 copied, 1 skipped, 0 failed, 1 duplicate references skipped\n",
+                playlist2_path.display()
+            )))
+            .stdout(predicate::str::contains(format!(
+                "{}: 4 entries, 2 copied, 2 skipped, 0 failed, 2 duplicate references skipped\n",
+                playlist1_path.display()
+            )));
+    }
+
+    #[test]
+    fn test_put_playlist_json_summary() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist1_path = music_dir.join("playlist.m3u8");
+        let playlist2_path = music_dir.join("playlist2.m3u8");
+        create_test_file(&playlist2_path, "artist1/album1/title1.flac\n");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let output = cmd
+            .arg("--json")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist1_path.to_str().unwrap())
+            .arg(playlist2_path.to_str().unwrap())
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+
+        let summary: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(summary["successful_media_files"], 4);
+        assert_eq!(summary["total_media_files"], 4);
+        assert_eq!(summary["duplicate_references"], 1);
+        let playlists = summary["per_playlist"].as_array().unwrap();
+        assert_eq!(playlists.len(), 2);
+        assert_eq!(playlists[0]["duplicate_references"], 0);
+        assert_eq!(playlists[1]["playlist"], playlist2_path.to_str().unwrap());
+        assert_eq!(playlists[1]["skipped"], 1);
+        assert_eq!(playlists[1]["duplicate_references"], 1);
+        assert!(summary["run_id"].as_str().is_some());
+    }
+
+    #[test]
+    fn test_put_playlist_run_id() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.txt");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let missing_path = music_dir.join("missing.m3u8");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let output = cmd
+            .arg("--keep-going")
+            .arg("--error-files")
+            .arg(error_file.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg(missing_path.to_str().unwrap())
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+
+        // The run ID printed after the summary is written into the error file
+        let stdout = String::from_utf8(output).unwrap();
+        let run_id = stdout
+            .lines()
+            .find_map(|line| line.strip_prefix("Run ID: "))
+            .unwrap();
+        assert!(stdout.ends_with(&format!("Run ID: {}\n", run_id)));
+        assert!(fs::read_to_string(&error_file)
+            .unwrap()
+            .starts_with(&format!("# plm-error-file 2\n# plm-run {}\n", run_id)));
+    }
+}
@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    fn put_playlist(dest_dir: &Path, playlist_path: &Path) -> assert_cmd::assert::Assert {
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("-v")
+            .arg("--sync-state")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+    }
+
+    #[test]
+    fn test_put_playlist_sync_state_skips_unchanged_tracks() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        put_playlist(&dest_dir, &playlist_path).success();
+        let state = fs::read_to_string(dest_dir.join(".plm/state.json")).unwrap();
+        assert!(state.contains("\"artist1/album1/title1.flac\""));
+        assert!(state.contains("\"crc32\""));
+
+        // A second run leaves the synced tracks alone, even ones whose
+        // contents were changed on the destination behind its back
+        let dest_file = dest_dir.join("artist1/album1/title1.flac");
+        create_test_file(&dest_file, "test content X");
+        put_playlist(&dest_dir, &playlist_path)
+            .success()
+            .stdout(predicate::str::contains("(0/0) media files copied"))
+            .stdout(predicate::str::contains("(4) unchanged media files skipped"))
+            .stderr(predicate::str::contains("Skip unchanged track"));
+        assert_eq!(fs::read_to_string(&dest_file).unwrap(), "test content X");
+
+        // A track whose source changed is copied again
+        create_test_file(
+            &music_dir.join("artist1/album1/title1.flac"),
+            "new content 1",
+        );
+        put_playlist(&dest_dir, &playlist_path).success();
+        assert_eq!(fs::read_to_string(&dest_file).unwrap(), "new content 1");
+    }
+
+    #[test]
+    fn test_put_playlist_sync_state_from_another_computer() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        put_playlist(&dest_dir, &playlist_path).success();
+
+        // Tracks synced from another library are recognised by checksum
+        let state_path = dest_dir.join(".plm/state.json");
+        let state = fs::read_to_string(&state_path).unwrap();
+        let music_dir = music_dir.to_str().unwrap();
+        fs::write(&state_path, state.replace(music_dir, "/home/other/Music")).unwrap();
+        let dest_file = dest_dir.join("artist1/album1/title1.flac");
+        create_test_file(&dest_file, "test content X");
+
+        put_playlist(&dest_dir, &playlist_path)
+            .success()
+            .stderr(predicate::str::contains("Skip unchanged track"));
+        assert_eq!(fs::read_to_string(&dest_file).unwrap(), "test content X");
+    }
+
+    #[test]
+    fn test_put_playlist_without_sync_state() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success();
+
+        assert!(!dest_dir.join(".plm").exists());
+    }
+}
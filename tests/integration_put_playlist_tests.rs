@@ -1,7 +1,6 @@
 use std::fs;
 use std::path::Path;
 
-use assert_cmd::Command;
 use predicates::prelude::*;
 
 mod integration_test_common;
@@ -9,7 +8,7 @@ mod integration_test_common;
 #[cfg(test)]
 mod tests {
     use super::*;
-    use integration_test_common::{create_test_file, setup_test_directory};
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
 
     // Helper function to verify file exists and has expected content
     fn verify_file(path: &Path, expected_content: &str) -> bool {
@@ -31,7 +30,7 @@ mod tests {
 
         let playlist_path = music_dir.join("playlist.m3u8");
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg(dest_dir.to_str().unwrap())
             .arg(playlist_path.to_str().unwrap())
@@ -84,7 +83,7 @@ mod tests {
         let playlist_path = music_dir.join("playlist_backslash.m3u8");
         create_test_file(&playlist_path, playlist_content);
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg(dest_dir.to_str().unwrap())
             .arg(playlist_path.to_str().unwrap())
@@ -120,7 +119,7 @@ mod tests {
 
         let playlist_path = music_dir.join("playlist.m3u8");
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("-v")
             .arg(dest_dir.to_str().unwrap())
@@ -152,7 +151,7 @@ mod tests {
 
         let playlist1_path = music_dir.join("playlist.m3u8");
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg(dest_dir.to_str().unwrap())
             .arg(playlist1_path.to_str().unwrap())
@@ -178,7 +177,7 @@ mod tests {
         let invalid_dest = music_dir.join("artist1/album1/title1.flac");
         let playlist_path = music_dir.join("playlist.m3u8");
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg(invalid_dest.to_str().unwrap())
             .arg(playlist_path.to_str().unwrap())
@@ -195,7 +194,7 @@ mod tests {
         fs::create_dir_all(&dest_dir).unwrap();
 
         // Missing playlist argument
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd.arg(dest_dir.to_str().unwrap()).assert();
 
         assert.failure();
@@ -211,7 +210,7 @@ mod tests {
 
         let playlist_path = music_dir.join("playlist.m3u8");
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("--lyrics")
             .arg(dest_dir.to_str().unwrap())
@@ -262,7 +261,7 @@ mod tests {
         let playlist_path = music_dir.join("playlist_no_lyrics.m3u8");
         create_test_file(&playlist_path, playlist_content);
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("--lyrics")
             .arg("-v") // Use verbose mode to ensure we would see any error messages
@@ -295,7 +294,7 @@ mod tests {
 
         let playlist_path = music_dir.join("playlist.m3u8");
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("--keep-going")
             .arg(dest_dir.to_str().unwrap())
@@ -320,7 +319,7 @@ mod tests {
         let existing_playlist = music_dir.join("playlist.m3u8");
         let missing_playlist = music_dir.join("missing.m3u8");
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("--keep-going")
             .arg(dest_dir.to_str().unwrap())
@@ -357,7 +356,7 @@ mod tests {
         let playlist2_path = music_dir.join("playlist2.m3u8");
         create_test_file(&playlist2_path, playlist2_content);
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("--keep-going")
             .arg(dest_dir.to_str().unwrap())
@@ -390,7 +389,7 @@ mod tests {
         let existing_playlist = music_dir.join("playlist.m3u8");
         let missing_playlist = music_dir.join("missing.m3u8");
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg(dest_dir.to_str().unwrap())
             .arg(existing_playlist.to_str().unwrap())
@@ -412,7 +411,7 @@ mod tests {
 
         let playlist_path = music_dir.join("playlist.m3u8");
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("--error-files")
             .arg(error_file.to_str().unwrap())
@@ -441,7 +440,7 @@ mod tests {
         let playlist_path = music_dir.join("playlist_with_missing.m3u8");
         create_test_file(&playlist_path, playlist_content);
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("--keep-going")
             .arg("--error-files")
@@ -483,7 +482,7 @@ mod tests {
         // Create a third playlist that doesn't exist
         let missing_playlist_path = music_dir.join("missing_playlist.m3u8");
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("--keep-going")
             .arg("--error-files")
@@ -529,7 +528,7 @@ mod tests {
         let playlist_path = music_dir.join("playlist_with_missing.m3u8");
         create_test_file(&playlist_path, playlist_content);
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("--keep-going")
             .arg("--error-files")
@@ -546,11 +545,16 @@ mod tests {
         assert!(error_file.exists());
         let error_content = fs::read_to_string(&error_file).unwrap();
 
-        // The first line should be the failed playlist with P prefix
+        // The first line should be the format header and the run ID,
+        // followed by the failed playlist with P prefix, recorded before
+        // the totals are known once the last playlist is parsed
         let lines: Vec<&str> = error_content.lines().collect();
         assert!(!lines.is_empty());
-        assert!(lines[0].starts_with("P "));
-        assert!(lines[0].contains(missing_playlist_path.to_str().unwrap()));
+        assert_eq!(lines[0], "# plm-error-file 2");
+        assert!(lines[1].starts_with("# plm-run "));
+        assert!(lines[2].starts_with("P "));
+        assert!(lines[2].contains(missing_playlist_path.to_str().unwrap()));
+        assert!(lines[3].starts_with("# plm-totals "));
 
         // The subsequent lines should be the failed media files with M prefix
         let media_lines: Vec<&str> = lines
@@ -585,7 +589,7 @@ mod tests {
         create_test_file(&playlist_path, playlist_content);
 
         // First run: create error file
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("--keep-going")
             .arg("--error-files")
@@ -612,7 +616,7 @@ mod tests {
         fs::create_dir_all(&dest_dir).unwrap();
 
         // Second run: retry with error file
-        let mut retry_cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut retry_cmd = plm_command("plm-put-playlist");
         let retry_assert = retry_cmd
             .arg("--retry")
             .arg(error_file.to_str().unwrap())
@@ -644,7 +648,7 @@ mod tests {
         create_test_file(&playlist_path, playlist_content);
 
         // First run: create error file
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("--keep-going")
             .arg("--error-files")
@@ -667,7 +671,7 @@ mod tests {
         fs::create_dir_all(&dest_dir).unwrap();
 
         // Second run: retry with error file and create new error file
-        let mut retry_cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut retry_cmd = plm_command("plm-put-playlist");
         let retry_assert = retry_cmd
             .arg("--retry")
             .arg(error_file.to_str().unwrap())
@@ -708,7 +712,7 @@ mod tests {
         create_test_file(&error_file, &error_content);
 
         // Run retry with lyrics option
-        let mut retry_cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut retry_cmd = plm_command("plm-put-playlist");
         let retry_assert = retry_cmd
             .arg("--retry")
             .arg(error_file.to_str().unwrap())
@@ -745,7 +749,7 @@ mod tests {
         create_test_file(&playlist_path, playlist_content);
 
         // First run: create error file
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         cmd.arg("--keep-going")
             .arg("--error-files")
             .arg(error_file.to_str().unwrap())
@@ -755,7 +759,7 @@ mod tests {
             .success();
 
         // Second run: try to use same file for retry and error-files
-        let mut retry_cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut retry_cmd = plm_command("plm-put-playlist");
         let retry_assert = retry_cmd
             .arg("--retry")
             .arg(error_file.to_str().unwrap())
@@ -796,7 +800,7 @@ mod tests {
         );
 
         // Run retry
-        let mut retry_cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut retry_cmd = plm_command("plm-put-playlist");
         let retry_assert = retry_cmd
             .arg("--retry")
             .arg(error_file.to_str().unwrap())
@@ -833,7 +837,7 @@ mod tests {
         create_test_file(&error_file, &error_content);
 
         // Run retry
-        let mut retry_cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut retry_cmd = plm_command("plm-put-playlist");
         let retry_assert = retry_cmd
             .arg("--retry")
             .arg(error_file.to_str().unwrap())
@@ -893,7 +897,7 @@ mod tests {
         create_test_file(&playlist2_path, playlist2_content);
 
         // Run with verbose mode to capture progress messages
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("-v")
             .arg(dest_dir.to_str().unwrap())
@@ -933,7 +937,7 @@ mod tests {
         create_test_file(&playlist_path, playlist_content);
 
         // Run with verbose and keep-going mode
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("-v")
             .arg("--keep-going")
@@ -979,7 +983,7 @@ mod tests {
         create_test_file(&playlist2_path, playlist2_content);
 
         // Run with verbose mode
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("-v")
             .arg(dest_dir.to_str().unwrap())
@@ -1014,7 +1018,7 @@ mod tests {
 
         let playlist_path = music_dir.join("playlist.m3u8");
 
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let output = cmd
             .arg("-v")
             .arg(dest_dir.to_str().unwrap())
@@ -1068,7 +1072,7 @@ mod tests {
         create_test_file(&playlist2_path, playlist2_content);
 
         // Run with verbose mode to capture progress messages
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("-v")
             .arg(dest_dir.to_str().unwrap())
@@ -1098,20 +1102,15 @@ mod tests {
         // Verify we have at least one count from each playlist
         assert!(!total_counts.is_empty(), "No total counts found in output");
 
-        // Get the expected total count (4 unique files across both playlists)
-        let expected_total = 4;
-
-        // Verify all total counts are equal to the expected total
-        for (i, &count) in total_counts.iter().enumerate() {
-            assert_eq!(
-                count,
-                expected_total,
-                "Total count in message {} is {}, expected {}",
-                i + 1,
-                count,
-                expected_total
-            );
-        }
+        // The total grows as the playlists are parsed while copying: it
+        // counts the 2 files of the first playlist while copying them,
+        // then the 4 unique files across both playlists
+        let expected_totals = vec![2, 2, 4, 4];
+        assert_eq!(
+            total_counts, expected_totals,
+            "Total counts are {:?}, expected {:?}",
+            total_counts, expected_totals
+        );
 
         // Verify all files were copied
         assert!(dest_dir.join("artist1/album1/title1.flac").exists());
@@ -1140,7 +1139,7 @@ mod tests {
         create_test_file(&playlist2_path, playlist2_content);
 
         // Run with verbose and keep-going mode
-        let mut cmd = Command::cargo_bin("plm-put-playlist").unwrap();
+        let mut cmd = plm_command("plm-put-playlist");
         let assert = cmd
             .arg("-v")
             .arg("--keep-going")
@@ -0,0 +1,148 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Create a playlist referring to tracks with non-ASCII names
+    fn create_non_ascii_playlist(music_dir: &Path) -> std::path::PathBuf {
+        fs::create_dir_all(music_dir.join("Björk/Début")).unwrap();
+        create_test_file(
+            &music_dir.join("Björk/Début/01 Café.flac"),
+            "test content 5",
+        );
+        create_test_file(&music_dir.join("Björk/Début/01 Café.lrc"), "lyrics 5");
+
+        let playlist_path = music_dir.join("Björk.m3u8");
+        create_test_file(
+            &playlist_path,
+            "#EXTM3U\n#EXTINF:1,Björk - Café\nBjörk\\Début\\01 Café.flac\nartist1/album1/title1.flac\n",
+        );
+        playlist_path
+    }
+
+    #[test]
+    fn test_put_playlist_transliterate() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_non_ascii_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--transliterate")
+            .arg("--lyrics")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(1/1) playlist copied"))
+            .stdout(predicate::str::contains("(2/2) media files copied"))
+            .stdout(predicate::str::contains("(2/2) lyrics files copied"));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("Bjork/Debut/01 Cafe.flac")).unwrap(),
+            "test content 5"
+        );
+        assert!(dest_dir.join("Bjork/Debut/01 Cafe.lrc").exists());
+        assert!(!dest_dir.join("Björk").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("Bjork.m3u8")).unwrap(),
+            "#EXTM3U\n#EXTINF:1,Björk - Café\nBjork/Debut/01 Cafe.flac\nartist1/album1/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_transliterate_collision() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::create_dir_all(music_dir.join("Café")).unwrap();
+        fs::create_dir_all(music_dir.join("Cafe")).unwrap();
+        create_test_file(&music_dir.join("Café/title1.flac"), "accented");
+        create_test_file(&music_dir.join("Cafe/title1.flac"), "plain");
+
+        let playlist_path = music_dir.join("cafe.m3u8");
+        create_test_file(&playlist_path, "Café/title1.flac\nCafe/title1.flac\n");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--transliterate")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(2/2) media files copied"));
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("Cafe/title1.flac")).unwrap(),
+            "plain"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("Cafe/title1 (2).flac")).unwrap(),
+            "accented"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("cafe.m3u8")).unwrap(),
+            "Cafe/title1 (2).flac\nCafe/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_retry_transliterate() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let error_file = temp_dir.path().join("errors.log");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_non_ascii_playlist(&music_dir);
+        create_test_file(
+            &error_file,
+            &format!("# plm-error-file 1\nP {}\n", playlist_path.display()),
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--retry")
+            .arg(error_file.to_str().unwrap())
+            .arg("--transliterate")
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert!(dest_dir.join("Bjork/Debut/01 Cafe.flac").exists());
+        assert!(fs::read_to_string(dest_dir.join("Bjork.m3u8"))
+            .unwrap()
+            .contains("\nBjork/Debut/01 Cafe.flac\n"));
+    }
+
+    #[test]
+    fn test_put_playlist_without_transliterate() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_non_ascii_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert!(dest_dir.join("Björk/Début/01 Café.flac").exists());
+        assert!(dest_dir.join("Björk.m3u8").exists());
+    }
+}
@@ -0,0 +1,169 @@
+use std::fs;
+use std::path::Path;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    fn put_playlist(
+        dest_dir: &Path,
+        playlist_path: &Path,
+        options: &[&str],
+    ) -> assert_cmd::assert::Assert {
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("-v")
+            .args(options)
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+    }
+
+    #[test]
+    fn test_put_playlist_leaves_up_to_date_playlist() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        let dest_playlist = dest_dir.join("playlist.m3u8");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        put_playlist(&dest_dir, &playlist_path, &[])
+            .success()
+            .stdout(predicate::str::contains("playlists up to date").not());
+
+        // Line endings and trailing newlines do not make a difference
+        let content = fs::read_to_string(&dest_playlist).unwrap();
+        let crlf_content = content.replace('\n', "\r\n") + "\r\n";
+        fs::write(&dest_playlist, &crlf_content).unwrap();
+        put_playlist(&dest_dir, &playlist_path, &[])
+            .success()
+            .stdout(predicate::str::contains("(1/1) playlist copied"))
+            .stdout(predicate::str::contains("(1) playlists up to date"))
+            .stderr(predicate::str::contains("is up to date at"));
+        assert_eq!(fs::read_to_string(&dest_playlist).unwrap(), crlf_content);
+
+        // A changed playlist is written again
+        create_test_file(&playlist_path, "artist1/album1/title1.flac\n");
+        put_playlist(&dest_dir, &playlist_path, &[])
+            .success()
+            .stdout(predicate::str::contains("playlists up to date").not());
+        assert_eq!(
+            fs::read_to_string(&dest_playlist).unwrap(),
+            "artist1/album1/title1.flac\n"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_leaves_up_to_date_rewritten_playlist() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let options = ["--dest-encoding", "utf16le", "--case", "upper"];
+        put_playlist(&dest_dir, &playlist_path, &options).success();
+        let written = fs::read(dest_dir.join("PLAYLIST.M3U8")).unwrap();
+
+        put_playlist(&dest_dir, &playlist_path, &options)
+            .success()
+            .stdout(predicate::str::contains("(1) playlists up to date"));
+        assert_eq!(fs::read(dest_dir.join("PLAYLIST.M3U8")).unwrap(), written);
+
+        // A playlist written in another encoding is written again
+        put_playlist(&dest_dir, &playlist_path, &["--case", "upper"])
+            .success()
+            .stdout(predicate::str::contains("playlists up to date").not());
+    }
+
+    #[test]
+    fn test_put_playlist_update_skips_up_to_date_media_files() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        let dest_file = dest_dir.join("artist1/album1/title1.flac");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        put_playlist(&dest_dir, &playlist_path, &["--update"]).success();
+
+        // A destination file of the same size and not older is kept
+        create_test_file(&dest_file, "test content X");
+        put_playlist(&dest_dir, &playlist_path, &["--skip-existing"])
+            .success()
+            .stdout(predicate::str::contains("(0/0) media files copied"))
+            .stdout(predicate::str::contains("(4) unchanged media files skipped"))
+            .stderr(predicate::str::contains("Skip unchanged track"));
+        assert_eq!(fs::read_to_string(&dest_file).unwrap(), "test content X");
+
+        // One of another size is copied again
+        create_test_file(&music_dir.join("artist1/album1/title1.flac"), "new");
+        put_playlist(&dest_dir, &playlist_path, &["--update", "--dry-run"])
+            .success()
+            .stdout(predicate::str::contains(
+                "Dry run: 0 new, 1 updated, 0 up-to-date media files; 3 skipped",
+            ))
+            .stdout(predicate::str::contains("(3) unchanged media files skipped"));
+        put_playlist(&dest_dir, &playlist_path, &["--update"]).success();
+        assert_eq!(fs::read_to_string(&dest_file).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_put_playlist_counts_unchanged_media_files_as_skipped() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        put_playlist(&dest_dir, &playlist_path, &["--update"]).success();
+
+        // Only the media file of another size is copied and counted
+        create_test_file(&music_dir.join("artist1/album1/title1.flac"), "new");
+        for options in [&["--update", "--dry-run"][..], &["--update"]] {
+            let mut options = options.to_vec();
+            options.push("--playlist-summary");
+            put_playlist(&dest_dir, &playlist_path, &options)
+                .success()
+                .stdout(predicate::str::contains("(1/1) media files copied"))
+                .stdout(predicate::str::contains("(3) unchanged media files skipped"))
+                .stdout(predicate::str::contains(
+                    "4 entries, 1 copied, 3 skipped, 0 failed",
+                ));
+        }
+    }
+
+    #[test]
+    fn test_retry_playlist_counts_its_media_files() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        let error_file = temp_dir.path().join("errors.log");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        put_playlist(&dest_dir, &playlist_path, &["--update"]).success();
+        create_test_file(&error_file, &format!("P {}\n", playlist_path.display()));
+
+        // The media files of a retried playlist count with the media files
+        // of the error file, whether copied or skipped as unchanged
+        let retry = |options: &[&str]| {
+            let mut cmd = plm_command("plm-put-playlist");
+            cmd.args(options)
+                .arg("--retry")
+                .arg(error_file.to_str().unwrap())
+                .arg(dest_dir.to_str().unwrap())
+                .assert()
+                .success()
+        };
+        retry(&["--update"])
+            .stdout(predicate::str::contains("(0/0) media files copied"))
+            .stdout(predicate::str::contains("(4) unchanged media files skipped"));
+        retry(&[]).stdout(predicate::str::contains("(4/4) media files copied"));
+    }
+}
@@ -0,0 +1,247 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_verify() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let playlist_path = music_dir.join("playlist.m3u8");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--verify")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert();
+
+        assert
+            .success()
+            .stdout(predicate::str::contains("(1/1) playlist copied"))
+            .stdout(predicate::str::contains("(4/4) media files copied"));
+
+        for file in [
+            "artist1/album1/title1.flac",
+            "artist1/album1/title2.flac",
+            "artist2/album1/title1.flac",
+            "artist2/album2/title1.flac",
+        ] {
+            assert_eq!(
+                fs::read(dest_dir.join(file)).unwrap(),
+                fs::read(music_dir.join(file)).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_put_playlist_verify_with_lyrics() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let playlist_path = music_dir.join("playlist.m3u8");
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--verify")
+            .arg("--lyrics")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert();
+
+        assert.success();
+
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist1/album1/title1.lrc")).unwrap(),
+            "[00:00.00] Lyrics for title1"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist2/album2/title1.lrc")).unwrap(),
+            "[00:00.00] Lyrics for another title1"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_buffer_size() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        // Span several buffers with a larger media file
+        let content: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+        fs::write(music_dir.join("artist1/album1/title2.flac"), &content).unwrap();
+        let playlist_path = music_dir.join("playlist.m3u8");
+
+        for args in [
+            &["--buffer-size", "4K"][..],
+            &["--buffer-size", "8k", "--verify"],
+        ] {
+            let mut cmd = plm_command("plm-put-playlist");
+            cmd.args(args)
+                .arg(dest_dir.to_str().unwrap())
+                .arg(playlist_path.to_str().unwrap())
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("(4/4) media files copied"));
+
+            assert_eq!(
+                fs::read(dest_dir.join("artist1/album1/title2.flac")).unwrap(),
+                content
+            );
+        }
+    }
+
+    #[test]
+    fn test_put_playlist_invalid_buffer_size() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--buffer-size")
+            .arg("1G")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Invalid buffer size"));
+    }
+
+    #[test]
+    fn test_put_playlist_verify_hash() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let content: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+        fs::write(music_dir.join("artist1/album1/title2.flac"), &content).unwrap();
+
+        for hash in ["blake3", "sha256", "xxh3"] {
+            let mut cmd = plm_command("plm-put-playlist");
+            cmd.arg("--verify")
+                .arg("--hash")
+                .arg(hash)
+                .arg("--buffer-size")
+                .arg("4K")
+                .arg(dest_dir.to_str().unwrap())
+                .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+                .assert()
+                .success()
+                .stdout(predicate::str::contains("(4/4) media files copied"));
+            assert_eq!(
+                fs::read(dest_dir.join("artist1/album1/title2.flac")).unwrap(),
+                content
+            );
+        }
+    }
+
+    #[test]
+    fn test_put_playlist_invalid_hash() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--verify")
+            .arg("--hash")
+            .arg("md5")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("Unknown hash algorithm"));
+    }
+
+    #[test]
+    fn test_put_playlist_io_backend_uring() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let content: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+        fs::write(music_dir.join("artist1/album1/title2.flac"), &content).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        let assert = cmd
+            .arg("--io-backend")
+            .arg("uring")
+            .arg("--buffer-size")
+            .arg("4K")
+            .arg("--verify")
+            .arg("--itemize")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert();
+
+        // Without io_uring support, the backend is rejected as an argument
+        if !cfg!(all(target_os = "linux", feature = "io-uring")) {
+            assert
+                .failure()
+                .stderr(predicate::str::contains("io-uring feature"));
+            return;
+        }
+        assert
+            .success()
+            .stdout(predicate::str::contains(
+                ">f+++++++++ artist1/album1/title2.flac",
+            ))
+            .stdout(predicate::str::contains("(4/4) media files copied"));
+        assert_eq!(
+            fs::read(dest_dir.join("artist1/album1/title2.flac")).unwrap(),
+            content
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_io_retries() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::remove_file(music_dir.join("artist2/album1/title1.flac")).unwrap();
+
+        // A missing media file is not a transient error and fails at once
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--io-retries")
+            .arg("3")
+            .arg("--keep-going")
+            .arg("--verbose")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("Retry copying").not())
+            .stdout(predicate::str::contains("(3/4) media files copied"));
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--io-retries")
+            .arg("11")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("11 is not in 0..=10"));
+    }
+}
@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Create media files named as Windows cannot use and a playlist of
+    /// them, returning the path of the playlist
+    fn create_reserved_playlist(music_dir: &Path) -> PathBuf {
+        fs::create_dir_all(music_dir.join("artist3/Live.")).unwrap();
+        create_test_file(&music_dir.join("artist3/CON.flac"), "test content 5");
+        create_test_file(
+            &music_dir.join("artist3/Live./title1.flac"),
+            "test content 6",
+        );
+        let playlist_path = music_dir.join("reserved.m3u8");
+        create_test_file(
+            &playlist_path,
+            "artist1/album1/title1.flac\nartist3/CON.flac\nartist3/Live./title1.flac\n",
+        );
+        playlist_path
+    }
+
+    #[test]
+    fn test_put_playlist_renames_windows_reserved_names() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_reserved_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(3/3) media files copied"));
+
+        assert!(dest_dir.join("artist3/CON_.flac").exists());
+        assert!(dest_dir.join("artist3/Live/title1.flac").exists());
+        assert!(!dest_dir.join("artist3/CON.flac").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("reserved.m3u8")).unwrap(),
+            "artist1/album1/title1.flac\nartist3/CON_.flac\nartist3/Live/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_renames_windows_reserved_names_in_plan() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_reserved_playlist(&music_dir);
+
+        // With deduplication, the whole plan is built before copying
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--dedup")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert!(dest_dir.join("artist3/CON_.flac").exists());
+        assert!(dest_dir.join("artist3/Live/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_strict_fails_on_windows_reserved_names() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_reserved_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--strict")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "Destination name \"CON.flac\" of artist3/CON.flac cannot be used on Windows",
+            ));
+
+        assert!(!dest_dir.join("artist3").exists());
+        assert!(!dest_dir.join("reserved.m3u8").exists());
+    }
+}
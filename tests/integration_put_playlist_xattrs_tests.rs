@@ -0,0 +1,116 @@
+#![cfg(target_os = "linux")]
+
+use std::ffi::CString;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+mod integration_test_common;
+
+/// Set a user extended attribute, returning whether the filesystem
+/// supports it
+fn set_xattr(path: &Path, name: &str, value: &[u8]) -> bool {
+    let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    let name = CString::new(name).unwrap();
+    // SAFETY: the strings are NUL-terminated and `value` holds its length
+    // in bytes
+    unsafe {
+        libc::setxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            0,
+        ) == 0
+    }
+}
+
+/// Get a user extended attribute, or `None` if the file has none of the
+/// name
+fn get_xattr(path: &Path, name: &str) -> Option<Vec<u8>> {
+    let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    let name = CString::new(name).unwrap();
+    let mut value = vec![0u8; 256];
+    // SAFETY: `value` holds `value.len()` bytes
+    let size = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.as_mut_ptr().cast(),
+            value.len(),
+        )
+    };
+    (size >= 0).then(|| value[..size as usize].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_xattrs() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let src_file = music_dir.join("artist1/album1/title1.flac");
+        if !set_xattr(&src_file, "user.rating", b"80") {
+            // The temporary directory does not support user attributes
+            return;
+        }
+        set_xattr(
+            &music_dir.join("artist1/album1/title1.lrc"),
+            "user.synced",
+            b"yes",
+        );
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--xattrs")
+            .arg("--lyrics")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert_eq!(
+            get_xattr(&dest_dir.join("artist1/album1/title1.flac"), "user.rating"),
+            Some(b"80".to_vec())
+        );
+        assert_eq!(
+            get_xattr(&dest_dir.join("artist1/album1/title1.lrc"), "user.synced"),
+            Some(b"yes".to_vec())
+        );
+        assert_eq!(
+            get_xattr(&dest_dir.join("artist1/album1/title2.flac"), "user.rating"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_put_playlist_without_xattrs() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let src_file = music_dir.join("artist1/album1/title1.flac");
+        if !set_xattr(&src_file, "user.rating", b"80") {
+            return;
+        }
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert_eq!(
+            get_xattr(&dest_dir.join("artist1/album1/title1.flac"), "user.rating"),
+            None
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Create an XSPF playlist referring to a track by a `file:` URI and
+    /// to another by a relative URI
+    fn create_xspf_playlist(music_dir: &Path) -> PathBuf {
+        let uri = format!(
+            "file://{}",
+            music_dir
+                .join("artist1/album1/title1.flac")
+                .to_str()
+                .unwrap()
+                .replace(' ', "%20")
+        );
+        let playlist_path = music_dir.join("exported.xspf");
+        create_test_file(
+            &playlist_path,
+            &format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n  <trackList>\n    \
+                 <track>\n      <location>{}</location>\n      <title>Title &amp; One</title>\n      \
+                 <creator>Artist</creator>\n      <duration>90000</duration>\n    </track>\n    \
+                 <track>\n      <location>artist2/album1/title1.flac</location>\n    </track>\n  \
+                 </trackList>\n</playlist>\n",
+                uri
+            ),
+        );
+        playlist_path
+    }
+
+    #[test]
+    fn test_put_playlist_xspf() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_xspf_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--lyrics")
+            .arg("--validate")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(2/2) media files copied"))
+            .stdout(predicate::str::contains("(1/1) lyrics files copied"))
+            .stdout(predicate::str::contains("(2/2) playlist entries validated"));
+
+        // The file URI is made relative to the destination playlist
+        let content = fs::read_to_string(dest_dir.join("exported.xspf")).unwrap();
+        assert!(content.contains("<location>artist1/album1/title1.flac</location>"));
+        assert!(content.contains("<location>artist2/album1/title1.flac</location>"));
+        assert!(content.contains("<title>Title &amp; One</title>"));
+        assert!(dest_dir.join("artist1/album1/title1.lrc").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_xspf_to_m3u8() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+
+        fs::create_dir_all(&dest_dir).unwrap();
+        let playlist_path = create_xspf_playlist(&music_dir);
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--xspf-to-m3u8")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(2/2) media files copied"));
+
+        assert!(!dest_dir.join("exported.xspf").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("exported.m3u8")).unwrap(),
+            "#EXTM3U\n#EXTINF:90,Artist - Title & One\nartist1/album1/title1.flac\n\
+             artist2/album1/title1.flac\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist2/album1/title1.flac")).unwrap(),
+            "test content 3"
+        );
+    }
+}
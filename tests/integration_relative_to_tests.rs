@@ -0,0 +1,73 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_put_playlist_relative_to() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let playlist = temp_dir.path().join("Playlists/favourites.m3u8");
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::create_dir_all(playlist.parent().unwrap()).unwrap();
+        create_test_file(
+            &playlist,
+            &format!(
+                "artist1/album1/title1.flac\n{}\n",
+                music_dir.join("artist2/album2/title1.flac").display()
+            ),
+        );
+
+        // The entries are not relative to the Playlists folder
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(playlist.to_str().unwrap())
+            .assert()
+            .failure();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--relative-to")
+            .arg(music_dir.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(2/2) media files copied"));
+
+        assert!(dest_dir.join("artist1/album1/title1.flac").exists());
+        assert!(dest_dir.join("artist2/album2/title1.flac").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("favourites.m3u8")).unwrap(),
+            "artist1/album1/title1.flac\nartist2/album2/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_delete_playlist_relative_to() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist = music_dir.join("Playlists/artist1.m3u8");
+        fs::create_dir_all(playlist.parent().unwrap()).unwrap();
+        create_test_file(&playlist, "artist1/album1/title1.flac\n");
+
+        let mut cmd = plm_command("plm-delete-playlist");
+        cmd.arg("--media")
+            .arg("--relative-to")
+            .arg(music_dir.to_str().unwrap())
+            .arg(playlist.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert!(!playlist.exists());
+        assert!(!music_dir.join("artist1/album1/title1.flac").exists());
+        assert!(!music_dir.join("artist1/album1/title1.lrc").exists());
+        assert!(music_dir.join("artist1/album1/title2.flac").exists());
+    }
+}
@@ -0,0 +1,179 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_remove_track_by_entry() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+
+        let mut cmd = plm_command("plm-remove-track");
+        cmd.arg("artist1/album1/title2.flac")
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert_eq!(
+            fs::read_to_string(&playlist_path).unwrap(),
+            "artist1/album1/title1.flac\nartist2/album1/title1.flac\nartist2/album2/title1.flac"
+        );
+
+        // The media file is kept without --media
+        assert!(music_dir.join("artist1/album1/title2.flac").exists());
+    }
+
+    #[test]
+    fn test_remove_track_by_path_keeps_extinf_and_line_endings() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("extended.m3u8");
+        create_test_file(
+            &playlist_path,
+            "#EXTM3U\r\n#EXTINF:10,Title 1\r\nartist1\\album1\\title1.flac\r\n#EXTINF:20,Title 2\r\nartist1\\album1\\title2.flac\r\n",
+        );
+
+        let mut cmd = plm_command("plm-remove-track");
+        cmd.arg(
+            music_dir
+                .join("artist1/album1/title1.flac")
+                .to_str()
+                .unwrap(),
+        )
+        .arg(playlist_path.to_str().unwrap())
+        .assert()
+        .success();
+
+        assert_eq!(
+            fs::read_to_string(&playlist_path).unwrap(),
+            "#EXTM3U\r\n#EXTINF:20,Title 2\r\nartist1\\album1\\title2.flac\r\n"
+        );
+    }
+
+    #[test]
+    fn test_remove_track_by_pattern_from_multiple_playlists() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+        let other_playlist_path = music_dir.join("other.m3u8");
+        create_test_file(
+            &other_playlist_path,
+            "artist2/album2/title1.flac\nartist1/album1/title1.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-remove-track");
+        cmd.arg("--pattern")
+            .arg("artist2/*")
+            .arg(playlist_path.to_str().unwrap())
+            .arg(other_playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert_eq!(
+            fs::read_to_string(&playlist_path).unwrap(),
+            "artist1/album1/title1.flac\nartist1/album1/title2.flac"
+        );
+        assert_eq!(
+            fs::read_to_string(&other_playlist_path).unwrap(),
+            "artist1/album1/title1.flac\n"
+        );
+    }
+
+    #[test]
+    fn test_remove_track_with_media_deletes_unreferenced_files() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+
+        let mut cmd = plm_command("plm-remove-track");
+        cmd.arg("--media")
+            .arg("--verbose")
+            .arg("artist2/album2/title1.flac")
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Number of removed tracks: 1"))
+            .stdout(predicate::str::contains("Number of deleted files: 2"));
+
+        // The media file, its lyrics file and the empty album directory are gone
+        assert!(!music_dir.join("artist2/album2/title1.flac").exists());
+        assert!(!music_dir.join("artist2/album2/title1.lrc").exists());
+        assert!(!music_dir.join("artist2/album2").exists());
+        assert!(music_dir.join("artist2/album1/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_remove_track_with_media_keeps_referenced_files() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_path = music_dir.join("playlist.m3u8");
+
+        // Another playlist in the same directory still refers to the track
+        create_test_file(
+            &music_dir.join("favourites.m3u"),
+            "artist1/album1/title1.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-remove-track");
+        cmd.arg("--media")
+            .arg("artist1/album1/title1.flac")
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert!(!fs::read_to_string(&playlist_path)
+            .unwrap()
+            .contains("artist1/album1/title1.flac"));
+        assert!(music_dir.join("artist1/album1/title1.flac").exists());
+        assert!(music_dir.join("artist1/album1/title1.lrc").exists());
+    }
+
+    #[test]
+    fn test_remove_track_not_found() {
+        let temp_dir = setup_test_directory();
+        let playlist_path = temp_dir.path().join("MUSIC/playlist.m3u8");
+        let content = fs::read_to_string(&playlist_path).unwrap();
+
+        let mut cmd = plm_command("plm-remove-track");
+        cmd.arg("artist3/album1/title1.flac")
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .failure()
+            .code(1)
+            .stderr(predicate::str::contains("Track not found in any playlist"));
+
+        assert_eq!(fs::read_to_string(&playlist_path).unwrap(), content);
+    }
+
+    #[test]
+    fn test_remove_track_invalid_pattern() {
+        let temp_dir = setup_test_directory();
+        let playlist_path = temp_dir.path().join("MUSIC/playlist.m3u8");
+
+        let mut cmd = plm_command("plm-remove-track");
+        cmd.arg("--pattern")
+            .arg("artist[")
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .failure()
+            .code(255);
+    }
+
+    #[test]
+    fn test_remove_track_missing_playlist() {
+        let mut cmd = plm_command("plm-remove-track");
+        cmd.arg("artist1/album1/title1.flac")
+            .arg("/nonexistent/playlist.m3u8")
+            .assert()
+            .failure()
+            .code(1)
+            .stderr(predicate::str::contains("Error processing playlist"));
+    }
+}
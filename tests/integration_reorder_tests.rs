@@ -0,0 +1,137 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_reorder_move_entry() {
+        let temp_dir = setup_test_directory();
+        let playlist_path = temp_dir.path().join("MUSIC/playlist.m3u8");
+
+        let mut cmd = plm_command("plm-reorder");
+        cmd.arg("--move")
+            .arg("4")
+            .arg("--to")
+            .arg("1")
+            .arg("--verbose")
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("Number of moved entries: 4"));
+
+        assert_eq!(
+            fs::read_to_string(&playlist_path).unwrap(),
+            "artist2/album2/title1.flac\nartist1/album1/title1.flac\nartist1/album1/title2.flac\nartist2/album1/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_reorder_move_range_keeps_extinf() {
+        let temp_dir = setup_test_directory();
+        let playlist_path = temp_dir.path().join("MUSIC/extended.m3u8");
+        create_test_file(
+            &playlist_path,
+            "#EXTM3U\r\n#EXTINF:10,A\r\na.flac\r\n#EXTINF:20,B\r\nb.flac\r\n#EXTINF:30,C\r\nc.flac\r\n",
+        );
+
+        let mut cmd = plm_command("plm-reorder");
+        cmd.arg("-m")
+            .arg("1-2")
+            .arg("-t")
+            .arg("2")
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert_eq!(
+            fs::read_to_string(&playlist_path).unwrap(),
+            "#EXTM3U\r\n#EXTINF:30,C\r\nc.flac\r\n#EXTINF:10,A\r\na.flac\r\n#EXTINF:20,B\r\nb.flac\r\n"
+        );
+    }
+
+    #[test]
+    fn test_reorder_reverse() {
+        let temp_dir = setup_test_directory();
+        let playlist_path = temp_dir.path().join("MUSIC/playlist.m3u8");
+
+        let mut cmd = plm_command("plm-reorder");
+        cmd.arg("--reverse")
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert_eq!(
+            fs::read_to_string(&playlist_path).unwrap(),
+            "artist2/album2/title1.flac\nartist2/album1/title1.flac\nartist1/album1/title2.flac\nartist1/album1/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_reorder_interleave_by_artist() {
+        let temp_dir = setup_test_directory();
+        let playlist_path = temp_dir.path().join("MUSIC/playlist.m3u8");
+
+        let mut cmd = plm_command("plm-reorder");
+        cmd.arg("--interleave")
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success();
+
+        assert_eq!(
+            fs::read_to_string(&playlist_path).unwrap(),
+            "artist1/album1/title1.flac\nartist2/album1/title1.flac\nartist1/album1/title2.flac\nartist2/album2/title1.flac"
+        );
+    }
+
+    #[test]
+    fn test_reorder_position_out_of_range() {
+        let temp_dir = setup_test_directory();
+        let playlist_path = temp_dir.path().join("MUSIC/playlist.m3u8");
+        let content = fs::read_to_string(&playlist_path).unwrap();
+
+        let mut cmd = plm_command("plm-reorder");
+        cmd.arg("--move")
+            .arg("2-3")
+            .arg("--to")
+            .arg("4")
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .failure()
+            .code(1)
+            .stderr(predicate::str::contains("Position 4 is out of range (1-3)"));
+
+        assert_eq!(fs::read_to_string(&playlist_path).unwrap(), content);
+    }
+
+    #[test]
+    fn test_reorder_invalid_range() {
+        let temp_dir = setup_test_directory();
+        let playlist_path = temp_dir.path().join("MUSIC/playlist.m3u8");
+
+        let mut cmd = plm_command("plm-reorder");
+        cmd.arg("--move")
+            .arg("3-1")
+            .arg("--to")
+            .arg("1")
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .failure()
+            .code(255)
+            .stderr(predicate::str::contains("Invalid range: 3-1"));
+    }
+
+    #[test]
+    fn test_reorder_requires_operation() {
+        let temp_dir = setup_test_directory();
+        let playlist_path = temp_dir.path().join("MUSIC/playlist.m3u8");
+
+        let mut cmd = plm_command("plm-reorder");
+        cmd.arg(playlist_path.to_str().unwrap()).assert().failure();
+    }
+}
@@ -0,0 +1,116 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{plm_command, setup_test_directory};
+
+    /// Archive the test playlist with `plm-backup` and the given options
+    fn backup(temp_dir: &tempfile::TempDir, archive: &str, options: &[&str]) -> String {
+        let music_dir = temp_dir.path().join("MUSIC");
+        let archive_path = temp_dir.path().join(archive);
+        let mut cmd = plm_command("plm-backup");
+        cmd.args(options)
+            .arg(archive_path.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success();
+        archive_path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_restore_tar_with_put_options() {
+        let temp_dir = setup_test_directory();
+        let archive = backup(&temp_dir, "backup.tar", &["--lyrics"]);
+        // The archive is restored even once the library is gone
+        fs::remove_dir_all(temp_dir.path().join("MUSIC")).unwrap();
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-restore");
+        cmd.arg(&archive)
+            .arg(dest_dir.to_str().unwrap())
+            .arg("--")
+            .arg("--lyrics")
+            .arg("--verify")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1/1) playlist copied"));
+
+        assert!(dest_dir.join("playlist.m3u8").exists());
+        assert_eq!(
+            fs::read_to_string(dest_dir.join("artist2/album1/title1.flac")).unwrap(),
+            "test content 3"
+        );
+        assert!(dest_dir.join("artist1/album1/title1.lrc").exists());
+    }
+
+    #[test]
+    fn test_restore_zip_with_manifest_into_staging_dir() {
+        let temp_dir = setup_test_directory();
+        let archive = backup(&temp_dir, "backup.zip", &["--manifest"]);
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        let staging_dir = temp_dir.path().join("STAGING");
+
+        let mut cmd = plm_command("plm-restore");
+        cmd.arg("--verbose")
+            .arg("--staging-dir")
+            .arg(staging_dir.to_str().unwrap())
+            .arg(&archive)
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("Extracted 6 files into"));
+
+        assert!(dest_dir.join("artist2/album2/title1.flac").exists());
+        assert!(!dest_dir.join("plm-manifest.tsv").exists());
+        assert!(staging_dir.join("artist2/album2/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_restore_manifest_mismatch() {
+        let temp_dir = setup_test_directory();
+        let archive = backup(&temp_dir, "backup.tar", &["--manifest"]);
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut contents = fs::read(&archive).unwrap();
+        let pos = contents
+            .windows(14)
+            .position(|window| window == b"test content 2")
+            .unwrap();
+        contents[pos] = b'T';
+        fs::write(&archive, contents).unwrap();
+
+        let mut cmd = plm_command("plm-restore");
+        cmd.arg(&archive)
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains(
+                "File does not match the manifest: artist1/album1/title2.flac",
+            ));
+        assert!(!dest_dir.join("playlist.m3u8").exists());
+    }
+
+    #[test]
+    fn test_restore_without_playlists() {
+        let temp_dir = setup_test_directory();
+        let archive = temp_dir.path().join("empty.tar");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::write(&archive, [0; 1024]).unwrap();
+
+        let mut cmd = plm_command("plm-restore");
+        cmd.arg(archive.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .failure()
+            .stderr(predicate::str::contains("No playlists at the root"));
+    }
+}
@@ -0,0 +1,157 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    /// Paths under `dir` with the contents of the files and the
+    /// modification times of the directories, which creating and
+    /// removing a file changes
+    fn snapshot(dir: &Path) -> Vec<(PathBuf, Vec<u8>, Option<SystemTime>)> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                entries.extend(snapshot(&path));
+            } else {
+                entries.push((path.clone(), fs::read(&path).unwrap(), None));
+            }
+        }
+        let modified = fs::metadata(dir).unwrap().modified().unwrap();
+        entries.push((dir.to_path_buf(), Vec::new(), Some(modified)));
+        entries.sort();
+        entries
+    }
+
+    #[test]
+    fn test_put_playlist_source_read_only_dest_inside_source() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = music_dir.join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        for dry_run in [true, false] {
+            let mut cmd = plm_command("plm-put-playlist");
+            cmd.arg("--allow-overlap")
+                .arg("--source-read-only")
+                .args(dry_run.then_some("--dry-run"))
+                .arg(dest_dir.to_str().unwrap())
+                .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+                .assert()
+                .code(1)
+                .stderr(predicate::str::contains("under the read-only source"));
+        }
+        assert!(fs::read_dir(&dest_dir).unwrap().next().is_none());
+    }
+
+    #[test]
+    fn test_put_playlist_source_read_only_leaves_source_untouched() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = music_dir.join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        let before = snapshot(&music_dir);
+
+        // The destination is not even test-written before the refusal
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--allow-overlap")
+            .arg("--source-read-only")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("under the read-only source"));
+        assert_eq!(snapshot(&music_dir), before);
+    }
+
+    #[test]
+    fn test_put_playlist_source_read_only_climbing_entries() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::create_dir_all(temp_dir.path().join("lists")).unwrap();
+        let playlist = temp_dir.path().join("lists/playlist.m3u8");
+        create_test_file(&playlist, "../MUSIC/artist1/album1/title1.flac\n");
+
+        // The track would be copied onto itself through DEST/..
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--source-read-only")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(playlist.to_str().unwrap())
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains(format!(
+                "under the read-only source {}",
+                music_dir
+                    .join("artist1/album1")
+                    .canonicalize()
+                    .unwrap()
+                    .display()
+            )));
+        assert!(!dest_dir.join("playlist.m3u8").exists());
+    }
+
+    #[test]
+    fn test_put_playlist_source_read_only_backup_dir() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--source-read-only")
+            .arg("--backup-dir")
+            .arg(music_dir.join("backup").to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("under the read-only source"));
+        assert!(fs::read_dir(&dest_dir).unwrap().next().is_none());
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--source-read-only")
+            .arg("--backup-dir")
+            .arg(temp_dir.path().join("backup").to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"));
+        assert!(dest_dir.join("artist1/album1/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_sync_source_read_only_delete() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path();
+
+        // The library inside the destination is not referenced by the
+        // destination playlist, so --delete would move it to the trash
+        let mut cmd = plm_command("plm-sync");
+        cmd.arg("--delete")
+            .arg("--source-read-only")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg("--")
+            .arg("--allow-overlap")
+            .assert()
+            .code(1)
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stderr(predicate::str::contains("under the read-only source"));
+
+        assert!(dest_dir.join("artist1/album1/title1.flac").exists());
+        assert!(music_dir.join("artist1/album1/title1.flac").exists());
+        assert!(music_dir.join("artist2/album2/title1.lrc").exists());
+        assert!(!dest_dir.join(".plm-trash").exists());
+    }
+}
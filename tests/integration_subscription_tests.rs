@@ -0,0 +1,115 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_sync_device_subscriptions() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let profile_dir = temp_dir.path().join("devices");
+        fs::create_dir_all(&dest_dir).unwrap();
+        fs::create_dir_all(&profile_dir).unwrap();
+        create_test_file(
+            &music_dir.join("artist1.m3u8"),
+            "artist1/album1/title1.flac\n",
+        );
+        create_test_file(
+            &profile_dir.join("walkman"),
+            &format!(
+                "subscribe {}/artist*.m3u8\nsubscribe {}/gone.m3u8\ntouch DB/refresh\n",
+                music_dir.display(),
+                music_dir.display()
+            ),
+        );
+
+        let mut cmd = plm_command("plm-sync");
+        cmd.env("PLM_PROFILE_DIR", &profile_dir)
+            .arg("--device")
+            .arg("walkman")
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1/1) media files copied"))
+            .stdout(predicate::str::contains("New subscribed playlist").not())
+            .stderr(predicate::str::contains(
+                "Warning: Subscription matches no playlist",
+            ));
+        assert!(dest_dir.join("artist1.m3u8").exists());
+        assert!(dest_dir.join("DB/refresh").exists());
+
+        // A newly matching playlist is added, and a playlist gone from
+        // the library is flagged while its tracks are moved to the trash
+        create_test_file(
+            &music_dir.join("artist2.m3u8"),
+            "artist2/album2/title1.flac\n",
+        );
+        fs::remove_file(music_dir.join("artist1.m3u8")).unwrap();
+        let mut cmd = plm_command("plm-sync");
+        cmd.env("PLM_PROFILE_DIR", &profile_dir)
+            .arg("--device")
+            .arg("walkman")
+            .arg("--delete")
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(format!(
+                "New subscribed playlist: {}",
+                music_dir.join("artist2.m3u8").display()
+            )))
+            .stdout(predicate::str::contains("(1) files moved to trash"))
+            .stderr(predicate::str::contains(format!(
+                "Warning: Subscribed playlist no longer exists: {}",
+                music_dir.join("artist1.m3u8").display()
+            )));
+        assert!(dest_dir.join("artist2/album2/title1.flac").exists());
+        assert!(!dest_dir.join("artist1/album1/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_sync_device_errors() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let profile = temp_dir.path().join("empty.profile");
+        fs::create_dir_all(&dest_dir).unwrap();
+        create_test_file(&profile, "subscribe lists/*.m3u8\n");
+
+        let mut cmd = plm_command("plm-sync");
+        cmd.arg("--device")
+            .arg(profile.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("No playlists subscribed to"));
+
+        // Playlists given on the command line are synced instead
+        let mut cmd = plm_command("plm-sync");
+        cmd.arg("--device")
+            .arg(profile.to_str().unwrap())
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"));
+        assert!(!dest_dir.join(".plm/subscriptions").exists());
+
+        let mut cmd = plm_command("plm-sync");
+        cmd.env("PLM_PROFILE_DIR", temp_dir.path())
+            .arg("--device")
+            .arg("walkman")
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains(
+                "Failed to read device profile walkman",
+            ));
+    }
+}
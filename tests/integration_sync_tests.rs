@@ -0,0 +1,279 @@
+use std::fs;
+use std::time::{Duration, SystemTime};
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_sync_copies_only_new_or_changed_files() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        let dest_file = dest_dir.join("artist2/album1/title1.flac");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-sync");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg("--")
+            .arg("--lyrics")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stdout(predicate::str::contains("(2/2) lyrics files copied"));
+
+        // Files up to date are not copied again
+        create_test_file(&dest_file, "test content X");
+        let mut cmd = plm_command("plm-sync");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg("--")
+            .arg("-v")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(0/0) media files copied"))
+            .stdout(predicate::str::contains("(4) unchanged media files skipped"))
+            .stderr(predicate::str::contains("Copy track").not());
+        assert_eq!(fs::read_to_string(&dest_file).unwrap(), "test content X");
+    }
+
+    #[test]
+    fn test_sync_recently_added() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+        let old = SystemTime::now() - Duration::from_secs(60 * 24 * 60 * 60);
+        for track in ["artist1/album1/title1.flac", "artist2/album1/title1.flac"] {
+            fs::File::options()
+                .write(true)
+                .open(music_dir.join(track))
+                .unwrap()
+                .set_modified(old)
+                .unwrap();
+        }
+
+        let mut cmd = plm_command("plm-sync");
+        cmd.arg("--recently-added")
+            .arg(music_dir.to_str().unwrap())
+            .arg("--added-since")
+            .arg("2w")
+            .arg(dest_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(2/2) media files copied"));
+
+        assert!(dest_dir.join("Recently Added.m3u8").is_file());
+        assert!(dest_dir.join("artist1/album1/title2.flac").is_file());
+        assert!(!dest_dir.join("artist1/album1/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_sync_delete_unreferenced_files() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--lyrics")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success();
+        fs::create_dir_all(dest_dir.join(".backup")).unwrap();
+        create_test_file(&dest_dir.join(".backup/title9.flac"), "kept");
+
+        let first_path = music_dir.join("first.m3u8");
+        create_test_file(&first_path, "artist2/album2/title1.flac\n");
+        let mut cmd = plm_command("plm-sync");
+        cmd.arg("--delete")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(first_path.to_str().unwrap())
+            .arg("--")
+            .arg("--lyrics")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(1) unchanged media files skipped"))
+            .stdout(predicate::str::contains("(4) files moved to trash"));
+
+        assert!(dest_dir.join("artist2/album2/title1.flac").exists());
+        assert!(dest_dir.join("artist2/album2/title1.lrc").exists());
+        assert!(!dest_dir.join("artist1/album1/title1.flac").exists());
+        assert!(!dest_dir.join("artist1/album1/title1.lrc").exists());
+        assert!(!dest_dir.join("artist1/album1/title2.flac").exists());
+        assert!(!dest_dir.join("artist2/album1/title1.flac").exists());
+        assert!(dest_dir.join(".backup/title9.flac").exists());
+
+        // The files are kept in the trash folder of today
+        let folders: Vec<_> = fs::read_dir(dest_dir.join(".plm-trash"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(folders.len(), 1);
+        assert_eq!(
+            fs::read_to_string(folders[0].join("artist1/album1/title2.flac")).unwrap(),
+            "test content 2"
+        );
+        assert!(folders[0].join("artist1/album1/title1.lrc").exists());
+        assert!(folders[0].join("artist2/album1/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_sync_delete_lyrics_extensions() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success();
+        create_test_file(&dest_dir.join("artist1/album1/title2.txt"), "lyrics");
+        create_test_file(&dest_dir.join("artist2/album2/title1.txt"), "lyrics");
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&music_dir, dest_dir.join("LINK")).unwrap();
+
+        // The lyrics files of --lyrics-ext go with their media files, and
+        // the directories linked to are left alone
+        let first_path = music_dir.join("first.m3u8");
+        create_test_file(&first_path, "artist2/album2/title1.flac\n");
+        let mut cmd = plm_command("plm-sync");
+        cmd.arg("--delete")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(first_path.to_str().unwrap())
+            .arg("--")
+            .arg("--lyrics-ext")
+            .arg("txt")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4) files moved to trash"));
+        assert!(!dest_dir.join("artist1/album1/title2.txt").exists());
+        assert!(dest_dir.join("artist2/album2/title1.txt").exists());
+        assert!(music_dir.join("artist1/album1/title1.flac").exists());
+    }
+
+    #[test]
+    fn test_sync_delete_after_failed_playlist() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success();
+
+        // The media files of a playlist failing with --keep-going are not
+        // known, so nothing is moved to the trash
+        let first_path = music_dir.join("first.m3u8");
+        create_test_file(&first_path, "artist2/album2/title1.flac\n");
+        for dry_run in [true, false] {
+            let mut cmd = plm_command("plm-sync");
+            cmd.arg("--delete")
+                .args(dry_run.then_some("--dry-run"))
+                .arg(dest_dir.to_str().unwrap())
+                .arg(first_path.to_str().unwrap())
+                .arg(music_dir.join("missing.m3u8").to_str().unwrap())
+                .arg("--")
+                .arg("--keep-going")
+                .assert()
+                .stderr(predicate::str::contains(
+                    "Warning: Not moving unreferenced files to trash, as 1 playlists failed",
+                ))
+                .stdout(predicate::str::contains("to trash").not());
+        }
+        assert!(dest_dir.join("artist1/album1/title2.flac").exists());
+        assert!(!dest_dir.join(".plm-trash").exists());
+    }
+
+    #[test]
+    fn test_sync_delete_renamed_files() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success();
+
+        // The media files renamed by --case are kept, and the files of
+        // the earlier names are moved to the trash
+        let mut cmd = plm_command("plm-sync");
+        cmd.arg("--delete")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg("--")
+            .arg("--case")
+            .arg("upper")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains("(4/4) media files copied"))
+            .stdout(predicate::str::contains("(4) files moved to trash"));
+        assert!(dest_dir.join("ARTIST1/ALBUM1/TITLE1.FLAC").exists());
+        assert!(dest_dir.join("ARTIST2/ALBUM2/TITLE1.FLAC").exists());
+        assert!(!dest_dir.join("artist1/album1/title1.flac").exists());
+
+        let mut cmd = plm_command("plm-sync");
+        cmd.arg(temp_dir.path().join("NONEXISTENT").to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("Destination directory not found"));
+    }
+
+    #[test]
+    fn test_sync_dry_run() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let dest_dir = temp_dir.path().join("DEST");
+        fs::create_dir_all(&dest_dir).unwrap();
+
+        let mut cmd = plm_command("plm-put-playlist");
+        cmd.arg("--lyrics")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success();
+
+        // The files --delete would move are listed, and nothing is
+        // copied or moved
+        let first_path = music_dir.join("first.m3u8");
+        create_test_file(&first_path, "artist2/album2/title1.flac\nartist1/album1/new.flac\n");
+        create_test_file(&music_dir.join("artist1/album1/new.flac"), "new");
+        let title2 = dest_dir.join("artist1/album1/title2.flac");
+        let mut cmd = plm_command("plm-sync");
+        cmd.arg("--dry-run")
+            .arg("--delete")
+            .arg(dest_dir.to_str().unwrap())
+            .arg(first_path.to_str().unwrap())
+            .arg("--")
+            .arg("--lyrics")
+            .assert()
+            .success()
+            .stdout(predicate::str::contains(">f+++++++++ artist1/album1/new.flac"))
+            .stdout(predicate::str::contains(format!(
+                "Would move media file \"{}\" to trash",
+                title2.display()
+            )))
+            .stdout(predicate::str::contains("title1.lrc\" to trash"))
+            .stdout(predicate::str::contains("(4) files would be moved to trash"));
+
+        assert!(title2.exists());
+        assert!(!dest_dir.join("artist1/album1/new.flac").exists());
+        assert!(!dest_dir.join(".plm-trash").exists());
+    }
+}
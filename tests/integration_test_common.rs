@@ -2,8 +2,13 @@ use std::fs::{self, File};
 use std::io::Write;
 use std::path::Path;
 
+use assert_cmd::Command;
 use tempfile::TempDir;
 
+// Re-export the test utilities for easier imports
+#[cfg(test)]
+pub use test_utils::*;
+
 #[cfg(test)]
 pub mod test_utils {
     use super::*;
@@ -53,13 +58,24 @@ pub mod test_utils {
         temp_dir
     }
 
+    thread_local! {
+        // Directory of the device locks taken by the commands of the test
+        // running on this thread
+        static LOCK_DIR: TempDir = TempDir::new().unwrap();
+    }
+
+    // Helper function to create a command running the binary `name` with
+    // a lock directory of the test's own, so that tests copying to the
+    // same device in parallel do not wait for each other
+    pub fn plm_command(name: &str) -> Command {
+        let mut cmd = Command::cargo_bin(name).unwrap();
+        LOCK_DIR.with(|lock_dir| cmd.env("PLM_LOCK_DIR", lock_dir.path()));
+        cmd
+    }
+
     // Helper function to create a test file with content
     pub fn create_test_file(path: &Path, content: &str) {
         let mut file = File::create(path).unwrap();
         file.write_all(content.as_bytes()).unwrap();
     }
 }
-
-// Re-export the test utilities for easier imports
-#[cfg(test)]
-pub use test_utils::*;
@@ -0,0 +1,90 @@
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_tracks_prints_absolute_paths() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+
+        let mut cmd = plm_command("plm-tracks");
+        let output = cmd
+            .current_dir(&music_dir)
+            .arg("playlist.m3u8")
+            .assert()
+            .success()
+            .get_output()
+            .stdout
+            .clone();
+
+        let expected: String = [
+            "artist1/album1/title1.flac",
+            "artist1/album1/title2.flac",
+            "artist2/album1/title1.flac",
+            "artist2/album2/title1.flac",
+        ]
+        .iter()
+        .map(|file| format!("{}\n", music_dir.join(file).display()))
+        .collect();
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_tracks_null_unique() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let other_path = music_dir.join("artist2/other.m3u8");
+        create_test_file(
+            &other_path,
+            "#EXTM3U\r\n..\\artist1\\album1\\title1.flac\r\n./album3/title1.flac\r\n",
+        );
+
+        let mut cmd = plm_command("plm-tracks");
+        let output = cmd
+            .arg("-0")
+            .arg("--unique")
+            .arg("--verbose")
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .arg(other_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("Media file not found"))
+            .stderr(predicate::str::contains("Number of tracks: 5"))
+            .get_output()
+            .stdout
+            .clone();
+
+        let tracks: Vec<String> = String::from_utf8(output)
+            .unwrap()
+            .split_terminator('\0')
+            .map(str::to_string)
+            .collect();
+        assert_eq!(tracks.len(), 5);
+        assert_eq!(
+            tracks[4],
+            music_dir
+                .join("artist2/album3/title1.flac")
+                .display()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_tracks_missing_playlist() {
+        let temp_dir = setup_test_directory();
+
+        let mut cmd = plm_command("plm-tracks");
+        cmd.arg(temp_dir.path().join("missing.m3u8").to_str().unwrap())
+            .assert()
+            .code(1)
+            .stderr(predicate::str::contains("Failed to open playlist"));
+
+        let mut cmd = plm_command("plm-tracks");
+        cmd.assert().failure();
+    }
+}
@@ -0,0 +1,126 @@
+use std::fs;
+
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_verify_playlist_no_problems() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+
+        let mut cmd = plm_command("plm-verify-playlist");
+        cmd.arg("-v")
+            .arg(music_dir.join("playlist.m3u8").to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(format!(
+                "{}: ok\nNo problems found\n",
+                music_dir.join("playlist.m3u8").display()
+            ));
+    }
+
+    #[test]
+    fn test_verify_playlist_problems() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist = music_dir.join("problems.m3u8");
+        create_test_file(&music_dir.join("artist1/album1/cover.jpg"), "JPEG");
+        fs::write(
+            &playlist,
+            b"#EXTM3U\nartist1/album1/title1.flac\nartist1/album1/missing.flac\n\
+              ./artist1/album1/title1.flac\nartist1/album1/cover.jpg\n\
+              /nowhere/title.flac\nartist2/\xe9.flac\n",
+        )
+        .unwrap();
+        let name = playlist.to_str().unwrap();
+
+        let mut cmd = plm_command("plm-verify-playlist");
+        cmd.arg(name)
+            .assert()
+            .code(1)
+            .stdout(predicate::str::contains(format!(
+                "{}:3: error: missing: Media file not found: artist1/album1/missing.flac\n\
+                 {}:4: warning: duplicate: Duplicate of the entry at 2: artist1/album1/title1.flac\n\
+                 {}:5: warning: non-audio: Entry without an audio extension: artist1/album1/cover.jpg\n",
+                name, name, name
+            )))
+            .stdout(predicate::str::contains(format!(
+                "{}:6: warning: mixed-paths: Absolute entry among relative entries: /nowhere/title.flac\n",
+                name
+            )))
+            .stdout(predicate::str::contains(format!(
+                "{}:7: error: encoding: Line is not valid UTF-8\n",
+                name
+            )))
+            .stdout(predicate::str::ends_with(
+                "Number of problems found: 4 errors, 3 warnings\n",
+            ));
+
+        // Warnings alone pass unless --strict is given
+        let mut cmd = plm_command("plm-verify-playlist");
+        cmd.arg("--ignore")
+            .arg("missing,encoding")
+            .arg(name)
+            .assert()
+            .success();
+        let mut cmd = plm_command("plm-verify-playlist");
+        cmd.arg("--strict")
+            .arg("--ignore")
+            .arg("missing,encoding")
+            .arg(name)
+            .assert()
+            .code(1);
+        let mut cmd = plm_command("plm-verify-playlist");
+        cmd.arg("--strict")
+            .arg("--ignore")
+            .arg("missing,encoding,duplicate,mixed-paths")
+            .arg("--audio-extensions")
+            .arg("flac,jpg")
+            .arg(name)
+            .assert()
+            .success()
+            .stdout("No problems found\n");
+    }
+
+    #[test]
+    fn test_verify_playlist_encodings() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let utf16 = music_dir.join("utf16.m3u8");
+        fs::write(&utf16, b"\xff\xfea\x00/\x00").unwrap();
+        let latin1 = music_dir.join("latin1.m3u");
+        create_test_file(
+            &latin1,
+            "artist1/album1/title1.flac\nartist1/caf\u{e9}.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-verify-playlist");
+        cmd.arg(utf16.to_str().unwrap())
+            .arg(latin1.to_str().unwrap())
+            .assert()
+            .code(1)
+            .stdout(predicate::str::contains(format!(
+                "{}:1: error: encoding: Playlist is encoded in UTF-16",
+                utf16.display()
+            )))
+            .stdout(predicate::str::contains(format!(
+                "{}:2: warning: encoding: Non-ASCII characters in an .m3u playlist",
+                latin1.display()
+            )))
+            .stdout(predicate::str::contains(
+                "Number of problems found: 2 errors, 1 warnings",
+            ));
+
+        let mut cmd = plm_command("plm-verify-playlist");
+        cmd.arg(music_dir.join("none.m3u8").to_str().unwrap())
+            .assert()
+            .code(255)
+            .stderr(predicate::str::contains("Failed to read playlist"));
+    }
+}
@@ -0,0 +1,68 @@
+use predicates::prelude::*;
+
+mod integration_test_common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use integration_test_common::{create_test_file, plm_command, setup_test_directory};
+
+    #[test]
+    fn test_which_prints_referencing_playlists() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        create_test_file(
+            &music_dir.join("artist1/other.m3u"),
+            "#EXTM3U\r\nalbum1\\title2.flac\r\n../artist2/album1/title1.flac\r\n",
+        );
+        create_test_file(
+            &music_dir.join("artist2/unrelated.m3u8"),
+            "album1/title1.flac\n",
+        );
+
+        let mut cmd = plm_command("plm-which");
+        let output = cmd
+            .current_dir(&music_dir)
+            .arg("--verbose")
+            .arg("./artist1/album1/../album1/title2.flac")
+            .assert()
+            .success()
+            .stderr(predicate::str::contains("Found track at entry 1 of"))
+            .stderr(predicate::str::contains("Number of playlists: 2"))
+            .get_output()
+            .stdout
+            .clone();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "./artist1/other.m3u\n./playlist.m3u8\n"
+        );
+    }
+
+    #[test]
+    fn test_which_expand_dirs() {
+        let temp_dir = setup_test_directory();
+        let music_dir = temp_dir.path().join("MUSIC");
+        let playlist_dir = temp_dir.path().join("PLAYLISTS");
+        let playlist_path = playlist_dir.join("albums.m3u8");
+        std::fs::create_dir_all(&playlist_dir).unwrap();
+        create_test_file(&playlist_path, "../MUSIC/artist2/album2\n");
+        let track = music_dir.join("artist2/album2/title1.flac");
+
+        let mut cmd = plm_command("plm-which");
+        cmd.arg(track.to_str().unwrap())
+            .arg(playlist_dir.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(predicate::str::is_empty());
+
+        let mut cmd = plm_command("plm-which");
+        cmd.arg("--expand-dirs")
+            .arg("-0")
+            .arg(track.to_str().unwrap())
+            .arg(playlist_path.to_str().unwrap())
+            .assert()
+            .success()
+            .stdout(format!("{}\0", playlist_path.display()));
+    }
+}